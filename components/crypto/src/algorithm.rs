@@ -0,0 +1,250 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection of the hash function used by [`hash`] and [`HashStream`].
+//!
+//! The hash function is a property of the whole network: it is chosen once in the genesis
+//! configuration of the blockchain and must not change afterwards, since every hash stored in
+//! the blockchain depends on it. The selected algorithm is process-wide and should be set
+//! with [`set_hash_algorithm`] before any hashes are computed.
+//!
+//! All supported algorithms produce digests of [`HASH_SIZE`] bytes.
+//!
+//! [`hash`]: ../fn.hash.html
+//! [`HashStream`]: ../struct.HashStream.html
+//! [`set_hash_algorithm`]: fn.set_hash_algorithm.html
+//! [`HASH_SIZE`]: ../constant.HASH_SIZE.html
+
+#[cfg(feature = "blake2b-hashing")]
+use blake2::{
+    digest::{Input, VariableOutput},
+    VarBlake2b,
+};
+
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use super::crypto_impl;
+#[cfg(feature = "blake2b-hashing")]
+use super::HASH_SIZE;
+
+/// Identifier of the currently selected algorithm.
+static SELECTED_ALGORITHM: AtomicU8 = AtomicU8::new(HashAlgorithm::Sha256 as u8);
+
+/// Hash function used to compute [`Hash`] digests.
+///
+/// [`Hash`]: ../struct.Hash.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum HashAlgorithm {
+    /// SHA-256; the default algorithm.
+    Sha256 = 0,
+    /// BLAKE2b with a 256-bit output.
+    ///
+    /// Available with the `blake2b-hashing` feature.
+    Blake2b = 1,
+}
+
+impl HashAlgorithm {
+    /// Returns `true` if the algorithm is supported by this build of the library.
+    pub fn is_supported(self) -> bool {
+        match self {
+            HashAlgorithm::Sha256 => true,
+            HashAlgorithm::Blake2b => cfg!(feature = "blake2b-hashing"),
+        }
+    }
+
+    /// Returns the name of the algorithm, as used in the configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake2b => "blake2b",
+        }
+    }
+
+    /// Calculates a hash of a bytes slice with this algorithm, regardless of the
+    /// process-wide selection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the algorithm is not supported by this build of the library.
+    pub fn hash(self, data: &[u8]) -> super::Hash {
+        HashState::new(self).update(data).finalize()
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => HashAlgorithm::Sha256,
+            1 => HashAlgorithm::Blake2b,
+            _ => unreachable!("Invalid hash algorithm identifier: {}", value),
+        }
+    }
+}
+
+impl Default for HashAlgorithm {
+    fn default() -> Self {
+        HashAlgorithm::Sha256
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "blake2b" => Ok(HashAlgorithm::Blake2b),
+            _ => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Unknown hash algorithm: {}", s),
+            )),
+        }
+    }
+}
+
+/// Selects the hash function used by `hash` and `HashStream` in this process.
+///
+/// Hashes computed before the call are not affected, so the algorithm should be selected
+/// before any data is hashed; normally this is done by the blockchain on startup, based on
+/// its genesis configuration.
+///
+/// # Panics
+///
+/// Panics if the algorithm is not supported by this build of the library.
+pub fn set_hash_algorithm(algorithm: HashAlgorithm) {
+    if !algorithm.is_supported() {
+        panic!(
+            "Hash algorithm {} is not supported, enable the corresponding feature of exonum-crypto",
+            algorithm
+        );
+    }
+    SELECTED_ALGORITHM.store(algorithm as u8, Ordering::SeqCst);
+}
+
+/// Returns the hash function currently used by `hash` and `HashStream`.
+pub fn hash_algorithm() -> HashAlgorithm {
+    HashAlgorithm::from_u8(SELECTED_ALGORITHM.load(Ordering::SeqCst))
+}
+
+/// State of a streaming hash computation for one of the supported algorithms.
+pub(crate) enum HashState {
+    Sha256(crypto_impl::HashState),
+    #[cfg(feature = "blake2b-hashing")]
+    Blake2b(VarBlake2b),
+}
+
+impl HashState {
+    pub(crate) fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => HashState::Sha256(crypto_impl::HashState::init()),
+            #[cfg(feature = "blake2b-hashing")]
+            HashAlgorithm::Blake2b => HashState::Blake2b(
+                VarBlake2b::new(HASH_SIZE).expect("HASH_SIZE is a valid BLAKE2b output size"),
+            ),
+            #[cfg(not(feature = "blake2b-hashing"))]
+            HashAlgorithm::Blake2b => panic!("Hash algorithm blake2b is not supported"),
+        }
+    }
+
+    pub(crate) fn update(mut self, chunk: &[u8]) -> Self {
+        match self {
+            HashState::Sha256(ref mut state) => state.update(chunk),
+            #[cfg(feature = "blake2b-hashing")]
+            HashState::Blake2b(ref mut state) => state.input(chunk),
+        }
+        self
+    }
+
+    pub(crate) fn finalize(self) -> super::Hash {
+        match self {
+            HashState::Sha256(state) => super::Hash(state.finalize()),
+            #[cfg(feature = "blake2b-hashing")]
+            HashState::Blake2b(state) => {
+                let mut digest = [0; HASH_SIZE];
+                state.variable_result(|bytes| digest.copy_from_slice(bytes));
+                super::Hash::new(digest)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for HashState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let algorithm = match self {
+            HashState::Sha256(..) => HashAlgorithm::Sha256,
+            #[cfg(feature = "blake2b-hashing")]
+            HashState::Blake2b(..) => HashAlgorithm::Blake2b,
+        };
+        f.debug_tuple("HashState").field(&algorithm).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn algorithm_names_roundtrip() {
+        for &algorithm in &[HashAlgorithm::Sha256, HashAlgorithm::Blake2b] {
+            assert_eq!(
+                algorithm.name().parse::<HashAlgorithm>().unwrap(),
+                algorithm
+            );
+            let json = serde_json::to_string(&algorithm).unwrap();
+            assert_eq!(json, format!("\"{}\"", algorithm.name()));
+        }
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn sha256_matches_default_backend() {
+        let data = [1, 2, 3, 4, 5];
+        assert_eq!(
+            HashAlgorithm::Sha256.hash(&data),
+            super::super::Hash(crypto_impl::hash(&data))
+        );
+    }
+
+    #[cfg(feature = "blake2b-hashing")]
+    #[test]
+    fn blake2b_known_digest() {
+        // BLAKE2b-256 of the empty input.
+        let expected = "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8";
+        assert_eq!(HashAlgorithm::Blake2b.hash(&[]).to_hex(), expected);
+
+        let chunked = HashState::new(HashAlgorithm::Blake2b)
+            .update(b"hello ")
+            .update(b"world")
+            .finalize();
+        assert_eq!(chunked, HashAlgorithm::Blake2b.hash(b"hello world"));
+    }
+
+    #[cfg(not(feature = "blake2b-hashing"))]
+    #[test]
+    #[should_panic(expected = "Hash algorithm blake2b is not supported")]
+    fn unsupported_algorithm_is_rejected() {
+        set_hash_algorithm(HashAlgorithm::Blake2b);
+    }
+}
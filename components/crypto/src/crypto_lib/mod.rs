@@ -15,5 +15,9 @@
 //! This module contains implementations of cryptographic
 //! primitives for different cryptographic backends.
 
+#[cfg(feature = "secp256k1-signatures")]
+pub mod secp256k1;
 #[cfg(feature = "sodiumoxide-crypto")]
 pub mod sodiumoxide;
+#[cfg(feature = "threshold-signatures")]
+pub mod threshold;
@@ -0,0 +1,154 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signatures made with secp256k1 keys, such as the keys of Bitcoin and Ethereum accounts.
+//!
+//! Since the data in Exonum is authored by `PublicKey`s, the data signed with a secp256k1
+//! key is authored by the hash of the compressed secp256k1 public key, see [`author_key`].
+//! The signature is 64 bytes long like an ed25519 one: it consists of the `r` and `s`
+//! values of the ECDSA signature of the hash of the data, and the recovery identifier,
+//! which is the parity of the signature point, is stored in the highest bit of `s`.
+//! The bit is otherwise zero, since only the signatures with the lower `s` values are
+//! accepted. The public key is recovered from the signature during the verification.
+//!
+//! [`author_key`]: fn.author_key.html
+
+use rand::RngCore;
+use secp256k1::{
+    recovery::{RecoverableSignature, RecoveryId},
+    All, Message, Secp256k1,
+};
+
+pub use secp256k1::{PublicKey, SecretKey};
+
+use crate::{hash, PublicKey as AuthorKey, Signature, SIGNATURE_LENGTH};
+
+/// Mask of the bit of the signature storing the recovery identifier.
+const PARITY_BIT: u8 = 0x80;
+/// Index of the byte of the signature containing the recovery identifier.
+const PARITY_BYTE: usize = SIGNATURE_LENGTH / 2;
+
+thread_local! {
+    static CONTEXT: Secp256k1<All> = Secp256k1::new();
+}
+
+/// Generates a secp256k1 secret key and the corresponding public key.
+pub fn gen_keypair() -> (PublicKey, SecretKey) {
+    let mut rng = rand::thread_rng();
+    let mut bytes = [0; 32];
+    let secret_key = loop {
+        rng.fill_bytes(&mut bytes);
+        // Almost all 32-byte values are valid secret keys.
+        if let Ok(secret_key) = SecretKey::from_slice(&bytes) {
+            break secret_key;
+        }
+    };
+    (public_key(&secret_key), secret_key)
+}
+
+/// Computes the secp256k1 public key corresponding to the secret key.
+pub fn public_key(secret_key: &SecretKey) -> PublicKey {
+    CONTEXT.with(|context| PublicKey::from_secret_key(context, secret_key))
+}
+
+/// Returns the key authoring the data signed with the secp256k1 key, that is,
+/// the hash of the compressed public key.
+pub fn author_key(public_key: &PublicKey) -> AuthorKey {
+    let digest = hash(&public_key.serialize());
+    AuthorKey::from_slice(digest.as_ref()).expect("Hash has the size of a public key")
+}
+
+/// Signs the data with the secp256k1 secret key.
+pub fn sign(data: &[u8], secret_key: &SecretKey) -> Signature {
+    let message = digest(data);
+    let (recovery_id, mut bytes) = CONTEXT.with(|context| {
+        context
+            .sign_recoverable(&message, secret_key)
+            .serialize_compact()
+    });
+    // The signatures are normalized to the lower `s` values, so the highest bit is free.
+    debug_assert_eq!(bytes[PARITY_BYTE] & PARITY_BIT, 0);
+    if recovery_id.to_i32() & 1 == 1 {
+        bytes[PARITY_BYTE] |= PARITY_BIT;
+    }
+    Signature::new(bytes)
+}
+
+/// Verifies that the data is signed with the secp256k1 key corresponding to the author key.
+pub fn verify(signature: &Signature, data: &[u8], author: &AuthorKey) -> bool {
+    let mut bytes = [0; SIGNATURE_LENGTH];
+    bytes.copy_from_slice(signature.as_ref());
+    let parity = bytes[PARITY_BYTE] & PARITY_BIT != 0;
+    bytes[PARITY_BYTE] &= !PARITY_BIT;
+
+    let recovery_id = RecoveryId::from_i32(i32::from(parity)).expect("Valid recovery id");
+    let signature = match RecoverableSignature::from_compact(&bytes, recovery_id) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    // Signatures with the higher `s` values are rejected, so that the signatures
+    // are not malleable.
+    let standard = signature.to_standard();
+    let mut normalized = standard;
+    normalized.normalize_s();
+    if normalized != standard {
+        return false;
+    }
+
+    let message = digest(data);
+    match CONTEXT.with(|context| context.recover(&message, &signature)) {
+        Ok(public_key) => author_key(&public_key) == *author,
+        Err(_) => false,
+    }
+}
+
+fn digest(data: &[u8]) -> Message {
+    Message::from_slice(hash(data).as_ref()).expect("Hash has the size of a message")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_verify() {
+        let (public_key, secret_key) = gen_keypair();
+        let author = author_key(&public_key);
+        let data = [1, 2, 3];
+
+        // The recovery identifier is random, so several signatures are checked.
+        for i in 0..8 {
+            let data = [&data[..], &[i]].concat();
+            let signature = sign(&data, &secret_key);
+            assert!(verify(&signature, &data, &author));
+            assert!(!verify(&signature, &[4, 5, 6], &author));
+
+            let (other_key, _) = gen_keypair();
+            assert!(!verify(&signature, &data, &author_key(&other_key)));
+        }
+    }
+
+    #[test]
+    fn test_reject_tampered_signature() {
+        let (public_key, secret_key) = gen_keypair();
+        let author = author_key(&public_key);
+        let data = [1, 2, 3];
+        let signature = sign(&data, &secret_key);
+
+        let mut bytes = [0; SIGNATURE_LENGTH];
+        bytes.copy_from_slice(signature.as_ref());
+        bytes[PARITY_BYTE] ^= PARITY_BIT;
+        assert!(!verify(&Signature::new(bytes), &data, &author));
+    }
+}
@@ -54,8 +54,12 @@ pub use self::ed25519::State as SignState;
 /// for sodiumoxide-based implementation.
 pub use self::sha256::State as HashState;
 
-use self::sodiumoxide::crypto::{hash::sha256, sign::ed25519};
+use self::sodiumoxide::{
+    crypto::{hash::sha256, sign::ed25519},
+    utils,
+};
 
+pub mod secretbox;
 pub mod x25519;
 
 /// Number of bytes in a `Hash`.
@@ -91,6 +95,38 @@ pub fn sign(data: &[u8], secret_key: &SecretKey) -> Signature {
     ed25519::sign_detached(data, secret_key)
 }
 
+/// Signs several slices of bytes with the same secret key.
+#[cfg(not(feature = "batch-verification"))]
+pub fn sign_batch(messages: &[&[u8]], secret_key: &SecretKey) -> Vec<Signature> {
+    messages.iter().map(|data| sign(data, secret_key)).collect()
+}
+
+/// Signs several slices of bytes with the same secret key.
+///
+/// The secret key is expanded by `ed25519-dalek` only once for all the slices.
+/// The signatures are the same as the ones produced by `sign`, since ed25519 signatures
+/// are deterministic.
+#[cfg(feature = "batch-verification")]
+pub fn sign_batch(messages: &[&[u8]], secret_key: &SecretKey) -> Vec<Signature> {
+    let seed = &secret_key.0[..SEED_LENGTH];
+    let public_key = &secret_key.0[SEED_LENGTH..];
+    let keys = ed25519_dalek::SecretKey::from_bytes(seed).and_then(|secret| {
+        ed25519_dalek::PublicKey::from_bytes(public_key)
+            .map(|public| (ed25519_dalek::ExpandedSecretKey::from(&secret), public))
+    });
+    let (expanded_key, public_key) = match keys {
+        Ok(keys) => keys,
+        Err(_) => return messages.iter().map(|data| sign(data, secret_key)).collect(),
+    };
+    messages
+        .iter()
+        .map(|data| {
+            let signature = expanded_key.sign(data, &public_key).to_bytes();
+            Signature::from_slice(&signature).expect("Signature has the correct length")
+        })
+        .collect()
+}
+
 /// Computes a secret key and a corresponding public key from a `Seed`.
 pub fn gen_keypair_from_seed(seed: &Seed) -> (PublicKey, SecretKey) {
     ed25519::keypair_from_seed(seed)
@@ -108,7 +144,58 @@ pub fn verify(sig: &Signature, data: &[u8], pub_key: &PublicKey) -> bool {
     ed25519::verify_detached(sig, data, pub_key)
 }
 
+/// Verifies several signatures at once. Returns `true` only if all the signatures are valid.
+///
+/// Sodium does not support batch verification, so the signatures are verified one by one
+/// unless the `batch-verification` feature is enabled.
+#[cfg(not(feature = "batch-verification"))]
+pub fn verify_batch(signatures: &[Signature], messages: &[&[u8]], pub_keys: &[PublicKey]) -> bool {
+    signatures.len() == messages.len()
+        && signatures.len() == pub_keys.len()
+        && signatures
+            .iter()
+            .zip(messages)
+            .zip(pub_keys)
+            .all(|((sig, data), pub_key)| verify(sig, data, pub_key))
+}
+
+/// Verifies several signatures at once. Returns `true` only if all the signatures are valid.
+///
+/// The signatures are verified with the batch verification of `ed25519-dalek`, which is
+/// several times faster than verifying them one by one.
+#[cfg(feature = "batch-verification")]
+pub fn verify_batch(signatures: &[Signature], messages: &[&[u8]], pub_keys: &[PublicKey]) -> bool {
+    if signatures.len() != messages.len() || signatures.len() != pub_keys.len() {
+        return false;
+    }
+    let signatures = signatures
+        .iter()
+        .map(|sig| ed25519_dalek::Signature::from_bytes(&sig.0))
+        .collect::<Result<Vec<_>, _>>();
+    let pub_keys = pub_keys
+        .iter()
+        .map(|pub_key| ed25519_dalek::PublicKey::from_bytes(&pub_key.0))
+        .collect::<Result<Vec<_>, _>>();
+    match (signatures, pub_keys) {
+        (Ok(signatures), Ok(pub_keys)) => {
+            ed25519_dalek::verify_batch(messages, &signatures, &pub_keys).is_ok()
+        }
+        _ => false,
+    }
+}
+
 /// Calculates hash of a bytes slice.
 pub fn hash(data: &[u8]) -> Hash {
     sha256::hash(data)
 }
+
+/// Compares two slices of bytes in the time which does not depend on their content.
+/// Slices of different lengths are never equal.
+pub fn constant_time_eq(x: &[u8], y: &[u8]) -> bool {
+    utils::memcmp(x, y)
+}
+
+/// Fills the slice of bytes with zeros, so that the write is not optimized away.
+pub fn memzero(x: &mut [u8]) {
+    utils::memzero(x)
+}
@@ -0,0 +1,109 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Symmetric authenticated encryption used to protect data at rest.
+//!
+//! Data is encrypted with XSalsa20 and authenticated with Poly1305. Encryption keys
+//! are derived from passphrases with the scrypt key derivation function.
+
+use std::fmt;
+
+use super::sodiumoxide::crypto::{pwhash, secretbox};
+
+/// Length of the encryption key.
+pub const KEY_LENGTH: usize = secretbox::KEYBYTES;
+/// Length of the salt used for the key derivation.
+pub const SALT_LENGTH: usize = pwhash::SALTBYTES;
+/// Number of bytes added to the data by the encryption.
+pub const OVERHEAD_LENGTH: usize = secretbox::NONCEBYTES + secretbox::MACBYTES;
+
+/// Symmetric encryption key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key(secretbox::Key);
+
+impl Key {
+    /// Generates a new random key.
+    pub fn generate() -> Self {
+        Key(secretbox::gen_key())
+    }
+
+    /// Creates a key from its bytes, for example, derived with another key derivation
+    /// function. Returns `None` if the slice has a wrong length.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        secretbox::Key::from_slice(bytes).map(Key)
+    }
+
+    /// Derives a key from the passphrase and the salt.
+    ///
+    /// The same passphrase and salt always produce the same key.
+    pub fn derive(passphrase: &[u8], salt: &[u8; SALT_LENGTH]) -> Self {
+        let mut key = secretbox::Key([0; KEY_LENGTH]);
+        pwhash::derive_key(
+            &mut key.0,
+            passphrase,
+            &pwhash::Salt(*salt),
+            pwhash::OPSLIMIT_INTERACTIVE,
+            pwhash::MEMLIMIT_INTERACTIVE,
+        )
+        .expect("Failed to derive encryption key");
+        Key(key)
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The key itself is not printed.
+        f.write_str("Key(..)")
+    }
+}
+
+/// Generates a random salt for the key derivation.
+pub fn gen_salt() -> [u8; SALT_LENGTH] {
+    pwhash::gen_salt().0
+}
+
+/// Encrypts the data with the key.
+///
+/// A random nonce is prepended to the returned ciphertext, so encrypting the same
+/// data twice produces different results.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate exonum_crypto;
+/// # exonum_crypto::init();
+/// use exonum_crypto::secretbox::{self, Key};
+///
+/// let key = Key::generate();
+/// let ciphertext = secretbox::seal(b"data", &key);
+/// assert_eq!(secretbox::open(&ciphertext, &key).unwrap(), b"data");
+/// ```
+pub fn seal(data: &[u8], key: &Key) -> Vec<u8> {
+    let nonce = secretbox::gen_nonce();
+    let mut sealed = nonce.0.to_vec();
+    sealed.extend_from_slice(&secretbox::seal(data, &nonce, &key.0));
+    sealed
+}
+
+/// Decrypts the data encrypted by [`seal`](fn.seal.html).
+///
+/// Returns `None` if the key is wrong or the ciphertext has been tampered with.
+pub fn open(sealed: &[u8], key: &Key) -> Option<Vec<u8>> {
+    if sealed.len() < OVERHEAD_LENGTH {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(secretbox::NONCEBYTES);
+    let nonce = secretbox::Nonce::from_slice(nonce)?;
+    secretbox::open(ciphertext, &nonce, &key.0).ok()
+}
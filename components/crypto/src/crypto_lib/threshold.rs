@@ -0,0 +1,146 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Threshold BLS signatures, which allow the validators to certify a block with a single
+//! signature instead of a set of ed25519 signatures.
+//!
+//! Each of the `n` validators holds a share of the secret key generated by a trusted dealer
+//! with [`gen_key_set`]. Any `threshold + 1` signature shares of the same data can be
+//! combined into a signature verifiable with the public key of the whole set, while
+//! `threshold` shares reveal nothing about it.
+//!
+//! [`gen_key_set`]: fn.gen_key_set.html
+
+use threshold_crypto::{serde_impl::SerdeSecret, SecretKeySet};
+
+pub use threshold_crypto::{
+    PublicKey, PublicKeySet, PublicKeyShare, SecretKeyShare, Signature, SignatureShare, PK_SIZE,
+    SIG_SIZE,
+};
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind, Write},
+    path::Path,
+};
+
+/// Generates a key set of `validators_count` shares, any `threshold + 1` of which
+/// are needed to produce a signature.
+///
+/// # Panics
+///
+/// Panics if `threshold` is not less than `validators_count`.
+pub fn gen_key_set(
+    validators_count: usize,
+    threshold: usize,
+) -> (PublicKeySet, Vec<SecretKeyShare>) {
+    assert!(
+        threshold < validators_count,
+        "Threshold must be less than the number of validators"
+    );
+    let secret_keys = SecretKeySet::random(threshold, &mut rand::thread_rng());
+    let shares = (0..validators_count)
+        .map(|i| secret_keys.secret_key_share(i))
+        .collect();
+    (secret_keys.public_keys(), shares)
+}
+
+/// Parses the signature share from bytes.
+pub fn signature_share_from_slice(bytes: &[u8]) -> Option<SignatureShare> {
+    if bytes.len() != SIG_SIZE {
+        return None;
+    }
+    let mut array = [0; SIG_SIZE];
+    array.copy_from_slice(bytes);
+    SignatureShare::from_bytes(array).ok()
+}
+
+/// Parses the signature from bytes.
+pub fn signature_from_slice(bytes: &[u8]) -> Option<Signature> {
+    signature_share_from_slice(bytes).map(|share| share.0)
+}
+
+/// Combines the signature shares of the validators with the given indexes into
+/// the signature of the key set. The shares are not verified, so the resulting
+/// signature should be verified by the caller.
+pub fn combine<'a, I>(key_set: &PublicKeySet, shares: I) -> Option<Signature>
+where
+    I: IntoIterator<Item = (usize, &'a SignatureShare)>,
+{
+    key_set.combine_signatures(shares).ok()
+}
+
+/// Writes the secret key share to a JSON file readable by the owner only.
+pub fn write_key_share_file<P: AsRef<Path>>(path: P, share: &SecretKeyShare) -> Result<(), Error> {
+    let content = serde_json::to_vec(&SerdeSecret(share.clone()))
+        .map_err(|e| Error::new(ErrorKind::Other, e))?;
+    let mut open_options = OpenOptions::new();
+    open_options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open_options.mode(0o_600);
+    }
+    open_options.open(path.as_ref())?.write_all(&content)
+}
+
+/// Reads the secret key share from the file written by `write_key_share_file`.
+pub fn read_key_share_file<P: AsRef<Path>>(path: P) -> Result<SecretKeyShare, Error> {
+    let file = File::open(path)?;
+    let share: SerdeSecret<SecretKeyShare> =
+        serde_json::from_reader(file).map_err(|e| Error::new(ErrorKind::Other, e))?;
+    Ok(share.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_combine_shares() {
+        let (key_set, shares) = gen_key_set(4, 2);
+        let data = b"block hash";
+        let signature_shares = shares
+            .iter()
+            .map(|share| share.sign(data))
+            .collect::<Vec<_>>();
+        for (i, share) in signature_shares.iter().enumerate() {
+            assert!(key_set.public_key_share(i).verify(share, data));
+        }
+
+        let signature = combine(&key_set, signature_shares.iter().enumerate().skip(1)).unwrap();
+        assert!(key_set.public_key().verify(&signature, data));
+        assert_eq!(
+            signature_from_slice(&signature.to_bytes()[..]),
+            Some(signature.clone())
+        );
+
+        // The threshold number of shares is not enough.
+        assert!(combine(&key_set, signature_shares.iter().enumerate().take(2)).is_none());
+        assert!(signature_share_from_slice(&[0; 3]).is_none());
+    }
+
+    #[test]
+    fn test_key_share_file() {
+        let dir = TempDir::new("exonum_threshold").unwrap();
+        let path = dir.path().join("share.json");
+        let (key_set, shares) = gen_key_set(1, 0);
+        write_key_share_file(&path, &shares[0]).unwrap();
+
+        let share = read_key_share_file(&path).unwrap();
+        assert_eq!(share.public_key_share(), key_set.public_key_share(0));
+    }
+}
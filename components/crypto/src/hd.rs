@@ -0,0 +1,308 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hierarchical deterministic derivation of ed25519 keys according to [SLIP-10].
+//!
+//! A single master seed backs an arbitrary number of keypairs, each identified by
+//! a derivation path such as `m/0'/3'`. Only hardened derivation is defined for
+//! ed25519, so all indexes of a path are hardened.
+//!
+//! The paths used by Exonum nodes have the following layout:
+//!
+//! - `m/0'/<rotation>'` - consensus keys;
+//! - `m/1'/<rotation>'` - service keys;
+//! - `m/2'/<service_id>'/<rotation>'` - keys of individual services.
+//!
+//! Increasing the rotation index yields a fresh key for the same role, so the keys
+//! can be rotated on schedule without escrowing a new seed.
+//!
+//! [SLIP-10]: https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+
+use hmac::{Hmac, Mac};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::Sha512;
+
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
+
+use super::{gen_keypair_from_seed, mnemonic::KeyPurpose, PublicKey, SecretKey, Seed, SEED_LENGTH};
+
+/// The bit set in the hardened indexes.
+const HARDENED_BIT: u32 = 1 << 31;
+/// The HMAC key used to derive the master key from the seed.
+const MASTER_KEY: &[u8] = b"ed25519 seed";
+/// The first index of the paths of individual services.
+const SERVICE_KEYS_INDEX: u32 = 2;
+
+/// Path from the master key to a derived key. All indexes are hardened.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// Creates a path from the indexes without the hardened bit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the indexes has the hardened bit set.
+    pub fn new(indexes: Vec<u32>) -> Self {
+        assert!(
+            indexes.iter().all(|&index| index < HARDENED_BIT),
+            "Derivation index must be less than 2^31"
+        );
+        DerivationPath(indexes)
+    }
+
+    /// Returns the path of the consensus or service key with the given rotation index.
+    pub fn for_purpose(purpose: KeyPurpose, rotation: u32) -> Self {
+        let role = match purpose {
+            KeyPurpose::Consensus => 0,
+            KeyPurpose::Service => 1,
+        };
+        Self::new(vec![role, rotation])
+    }
+
+    /// Returns the path of the key of an individual service with the given rotation index.
+    pub fn for_service(service_id: u16, rotation: u32) -> Self {
+        Self::new(vec![SERVICE_KEYS_INDEX, u32::from(service_id), rotation])
+    }
+
+    /// Returns the indexes of the path without the hardened bit.
+    pub fn indexes(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: &str| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid derivation path `{}`: {}", s, message),
+            )
+        };
+
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(invalid("path must start with `m`"));
+        }
+        let indexes = parts
+            .map(|part| {
+                if !part.ends_with('\'') && !part.ends_with('h') {
+                    return Err(invalid("only hardened indexes are supported"));
+                }
+                let index = part[..part.len() - 1]
+                    .parse::<u32>()
+                    .map_err(|_| invalid("index is not a number"))?;
+                if index >= HARDENED_BIT {
+                    Err(invalid("index must be less than 2^31"))
+                } else {
+                    Ok(index)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(DerivationPath(indexes))
+    }
+}
+
+impl Serialize for DerivationPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DerivationPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Secret key with the chain code, from which the child keys are derived.
+#[derive(Clone)]
+pub struct ExtendedKey {
+    key: [u8; SEED_LENGTH],
+    chain_code: [u8; SEED_LENGTH],
+}
+
+impl ExtendedKey {
+    /// Derives the master key from the seed, for example, from the seed of
+    /// a mnemonic phrase.
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(MASTER_KEY, &[seed])
+    }
+
+    /// Derives the hardened child key with the given index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index has the hardened bit set.
+    pub fn derive_child(&self, index: u32) -> Self {
+        assert!(
+            index < HARDENED_BIT,
+            "Derivation index must be less than 2^31"
+        );
+        let index_bytes = (index | HARDENED_BIT).to_be_bytes();
+        Self::from_hmac(
+            &self.chain_code,
+            &[&[0][..], &self.key[..], &index_bytes[..]],
+        )
+    }
+
+    /// Derives the key at the given path from this key.
+    pub fn derive_path(&self, path: &DerivationPath) -> Self {
+        path.indexes()
+            .iter()
+            .fold(self.clone(), |key, &index| key.derive_child(index))
+    }
+
+    /// Returns the ed25519 keypair of this key.
+    pub fn keypair(&self) -> (PublicKey, SecretKey) {
+        gen_keypair_from_seed(&Seed::new(self.key))
+    }
+
+    /// Returns the chain code of this key.
+    pub fn chain_code(&self) -> &[u8] {
+        &self.chain_code
+    }
+
+    fn from_hmac(key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC accepts keys of any length");
+        for chunk in data {
+            mac.input(chunk);
+        }
+        let output = mac.result().code();
+
+        let mut extended_key = ExtendedKey {
+            key: [0; SEED_LENGTH],
+            chain_code: [0; SEED_LENGTH],
+        };
+        extended_key.key.copy_from_slice(&output[..SEED_LENGTH]);
+        extended_key
+            .chain_code
+            .copy_from_slice(&output[SEED_LENGTH..]);
+        extended_key
+    }
+}
+
+impl fmt::Debug for ExtendedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ExtendedKey")
+            .field("public_key", &self.keypair().0)
+            .finish()
+    }
+}
+
+impl Drop for ExtendedKey {
+    fn drop(&mut self) {
+        self.key = [0; SEED_LENGTH];
+        self.chain_code = [0; SEED_LENGTH];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::FromHex;
+
+    use super::*;
+
+    #[test]
+    fn test_slip10_vectors() {
+        let seed = Vec::<u8>::from_hex("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedKey::master(&seed);
+        assert_eq!(
+            hex::encode(&master.key),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(master.chain_code()),
+            "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+        );
+
+        let child = master.derive_path(&"m/0'".parse().unwrap());
+        assert_eq!(
+            hex::encode(&child.key),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert_eq!(
+            hex::encode(child.chain_code()),
+            "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69"
+        );
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/2'/128h/3'".parse().unwrap();
+        assert_eq!(path, DerivationPath::for_service(128, 3));
+        assert_eq!(path.to_string(), "m/2'/128'/3'");
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap(),
+            DerivationPath::default()
+        );
+        assert_eq!(
+            DerivationPath::for_purpose(KeyPurpose::Service, 1).to_string(),
+            "m/1'/1'"
+        );
+
+        let invalid_paths = [
+            "",
+            "0'/1'",
+            "m/1",
+            "m/x'",
+            "m/1''",
+            "m/2147483648'",
+            "m/1'/",
+        ];
+        for path in &invalid_paths {
+            assert!(path.parse::<DerivationPath>().is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_derived_keys_differ() {
+        let master = ExtendedKey::master(&[1; 64]);
+        let consensus_keys = master
+            .derive_path(&DerivationPath::for_purpose(KeyPurpose::Consensus, 0))
+            .keypair();
+        let rotated_keys = master
+            .derive_path(&DerivationPath::for_purpose(KeyPurpose::Consensus, 1))
+            .keypair();
+        let service_keys = master
+            .derive_path(&DerivationPath::for_purpose(KeyPurpose::Service, 0))
+            .keypair();
+        assert_ne!(consensus_keys.0, rotated_keys.0);
+        assert_ne!(consensus_keys.0, service_keys.0);
+        assert_eq!(
+            consensus_keys,
+            ExtendedKey::master(&[1; 64])
+                .derive_child(0)
+                .derive_child(0)
+                .keypair()
+        );
+    }
+}
@@ -23,13 +23,22 @@
 #[macro_use]
 extern crate serde_derive;
 
+pub use self::algorithm::{hash_algorithm, set_hash_algorithm, HashAlgorithm};
 #[doc(inline)]
 pub use self::crypto_impl::{
     HASH_SIZE, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH, SEED_LENGTH, SIGNATURE_LENGTH,
 };
+#[cfg(feature = "secp256k1-signatures")]
+pub use self::crypto_lib::secp256k1;
 #[cfg(feature = "sodiumoxide-crypto")]
-pub use self::crypto_lib::sodiumoxide::x25519;
-pub use self::utils::{generate_keys_file, read_keys_from_file};
+pub use self::crypto_lib::sodiumoxide::{secretbox, x25519};
+#[cfg(feature = "threshold-signatures")]
+pub use self::crypto_lib::threshold;
+pub use self::utils::{
+    create_keys_file, create_keys_file_with_options, create_seed_file,
+    create_seed_file_with_options, generate_keys_file, read_derived_keys_from_file,
+    read_keys_from_file, read_secret_file_metadata, KdfParams, KeyFileOptions, SecretFileMetadata,
+};
 
 use byteorder::{ByteOrder, LittleEndian};
 use chrono::{DateTime, Duration, Utc};
@@ -56,7 +65,10 @@ use self::crypto_lib::sodiumoxide as crypto_impl;
 #[macro_use]
 mod macros;
 
+pub mod algorithm;
 pub mod crypto_lib;
+pub mod hd;
+pub mod mnemonic;
 pub mod utils;
 
 /// The size to crop the string in debug messages.
@@ -155,9 +167,123 @@ pub fn verify(sig: &Signature, data: &[u8], pubkey: &PublicKey) -> bool {
     crypto_impl::verify(&sig.0, data, &pubkey.0)
 }
 
+/// Scheme of the signature made by the author of some data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignatureScheme {
+    /// The data is signed with the ed25519 secret key of the author.
+    Ed25519,
+    /// The data is signed with a secp256k1 secret key, see the [`secp256k1`] module.
+    /// Available with the `secp256k1-signatures` feature.
+    ///
+    /// [`secp256k1`]: secp256k1/index.html
+    Secp256k1,
+    /// The data is signed with the ed25519 secret key of the author in the prehashed
+    /// mode (ed25519ph), see [`SignStream`]. The data is hashed incrementally, so large
+    /// data can be signed without keeping it in memory at once.
+    ///
+    /// [`SignStream`]: struct.SignStream.html
+    Ed25519ph,
+}
+
+impl SignatureScheme {
+    /// Verifies that `data` is signed by the author according to the scheme.
+    /// Secp256k1 signatures are never valid if the `secp256k1-signatures` feature
+    /// is disabled.
+    pub fn verify(self, signature: &Signature, data: &[u8], author: &PublicKey) -> bool {
+        match self {
+            SignatureScheme::Ed25519 => verify(signature, data, author),
+            #[cfg(feature = "secp256k1-signatures")]
+            SignatureScheme::Secp256k1 => secp256k1::verify(signature, data, author),
+            #[cfg(not(feature = "secp256k1-signatures"))]
+            SignatureScheme::Secp256k1 => false,
+            SignatureScheme::Ed25519ph => SignStream::new().update(data).verify(signature, author),
+        }
+    }
+}
+
+/// Signs several slices of bytes with the same secret key and returns the signatures
+/// in the same order. This may be faster than signing the slices one by one, since
+/// the secret key is expanded only once if the `batch-verification` feature is enabled.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate exonum_crypto;
+///
+/// # exonum_crypto::init();
+/// let (public_key, secret_key) = exonum_crypto::gen_keypair();
+/// let messages: [&[u8]; 2] = [&[1, 2, 3], &[4, 5]];
+/// let signatures = exonum_crypto::sign_batch(&messages, &secret_key);
+/// assert!(exonum_crypto::verify(&signatures[1], messages[1], &public_key));
+/// ```
+pub fn sign_batch(messages: &[&[u8]], secret_key: &SecretKey) -> Vec<Signature> {
+    crypto_impl::sign_batch(messages, &secret_key.0)
+        .into_iter()
+        .map(Signature)
+        .collect()
+}
+
+/// Verifies several signatures at once, which may be considerably faster than verifying
+/// them one by one if the crypto backend supports batch verification. Each item
+/// consists of the signed data, the signature and the public key of the signer.
+/// Returns `true` only if all the signatures are valid.
+///
+/// With the `batch-verification` feature the signatures are checked with the cofactored
+/// equation of `ed25519-dalek`, which accepts some signatures rejected by `verify`.
+/// Do not use this function where several parties must agree on the validity of
+/// the signatures, such as in consensus.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate exonum_crypto;
+///
+/// # exonum_crypto::init();
+/// let (first_key, first_secret) = exonum_crypto::gen_keypair();
+/// let (second_key, second_secret) = exonum_crypto::gen_keypair();
+/// let messages: [&[u8]; 2] = [&[1, 2, 3], &[4, 5]];
+/// let signatures = [
+///     exonum_crypto::sign(messages[0], &first_secret),
+///     exonum_crypto::sign(messages[1], &second_secret),
+/// ];
+/// assert!(exonum_crypto::verify_batch(&[
+///     (messages[0], &signatures[0], &first_key),
+///     (messages[1], &signatures[1], &second_key),
+/// ]));
+/// ```
+pub fn verify_batch(items: &[(&[u8], &Signature, &PublicKey)]) -> bool {
+    let messages = items.iter().map(|&(data, _, _)| data).collect::<Vec<_>>();
+    let signatures = items.iter().map(|&(_, sig, _)| sig.0).collect::<Vec<_>>();
+    let pub_keys = items.iter().map(|&(_, _, key)| key.0).collect::<Vec<_>>();
+    crypto_impl::verify_batch(&signatures, &messages, &pub_keys)
+}
+
+/// Compares two slices of bytes in the time which does not depend on their content,
+/// so that the comparison does not leak secret data through timing. Slices of
+/// different lengths are never equal.
+///
+/// Secret keys and seeds are compared in constant time by their `PartialEq`
+/// implementations; this function can be used to compare other secret data,
+/// such as passphrases.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate exonum_crypto;
+///
+/// # exonum_crypto::init();
+/// assert!(exonum_crypto::constant_time_eq(b"passphrase", b"passphrase"));
+/// assert!(!exonum_crypto::constant_time_eq(b"passphrase", b"Passphrase"));
+/// assert!(!exonum_crypto::constant_time_eq(b"passphrase", b"pass"));
+/// ```
+pub fn constant_time_eq(x: &[u8], y: &[u8]) -> bool {
+    crypto_impl::constant_time_eq(x, y)
+}
+
 /// Calculates a hash of a bytes slice.
 ///
-/// Type of a hash depends on a chosen crypto backend (via `...-crypto` cargo feature).
+/// The hash function is selected with [`set_hash_algorithm`](fn.set_hash_algorithm.html)
+/// and is SHA-256 by default.
 ///
 /// # Examples
 ///
@@ -171,8 +297,10 @@ pub fn verify(sig: &Signature, data: &[u8], pubkey: &PublicKey) -> bool {
 /// let hash = exonum_crypto::hash(&data);
 /// ```
 pub fn hash(data: &[u8]) -> Hash {
-    let dig = crypto_impl::hash(data);
-    Hash(dig)
+    match hash_algorithm() {
+        HashAlgorithm::Sha256 => Hash(crypto_impl::hash(data)),
+        algorithm => algorithm.hash(data),
+    }
 }
 
 /// A common trait for the ability to compute a cryptographic hash.
@@ -227,26 +355,39 @@ pub fn init() {
 /// }
 /// let _ = hash_stream.hash();
 /// ```
-#[derive(Debug, Default)]
-pub struct HashStream(crypto_impl::HashState);
+#[derive(Debug)]
+pub struct HashStream(algorithm::HashState);
 
 impl HashStream {
-    /// Creates a new instance of `HashStream`.
+    /// Creates a new instance of `HashStream` using the currently selected hash function.
     pub fn new() -> Self {
-        HashStream(crypto_impl::HashState::init())
+        Self::with_algorithm(hash_algorithm())
+    }
+
+    /// Creates a new instance of `HashStream` using the specified hash function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the algorithm is not supported by this build of the library.
+    pub fn with_algorithm(algorithm: HashAlgorithm) -> Self {
+        HashStream(algorithm::HashState::new(algorithm))
     }
 
     /// Processes a chunk of stream and returns a `HashStream` with the updated internal state.
-    pub fn update(mut self, chunk: &[u8]) -> Self {
-        self.0.update(chunk);
-        self
+    pub fn update(self, chunk: &[u8]) -> Self {
+        HashStream(self.0.update(chunk))
     }
 
     /// Returns the resulting hash of the system calculated upon the commit
     /// of currently supplied data.
     pub fn hash(self) -> Hash {
-        let dig = self.0.finalize();
-        Hash(dig)
+        self.0.finalize()
+    }
+}
+
+impl Default for HashStream {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -596,7 +737,10 @@ impl CryptoHash for i128 {
 
 impl CryptoHash for () {
     fn hash(&self) -> Hash {
-        Hash(crypto_impl::EMPTY_SLICE_HASH)
+        match hash_algorithm() {
+            HashAlgorithm::Sha256 => Hash(crypto_impl::EMPTY_SLICE_HASH),
+            algorithm => algorithm.hash(&[]),
+        }
     }
 }
 
@@ -703,6 +847,58 @@ mod tests {
         assert_eq!(s, sh);
     }
 
+    #[test]
+    fn verify_signatures_batch() {
+        let keys = (0..4).map(|_| gen_keypair()).collect::<Vec<_>>();
+        let messages = (0..4_u8).map(|i| vec![i; 10]).collect::<Vec<_>>();
+        let messages = messages.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let mut signatures = keys
+            .iter()
+            .zip(&messages)
+            .map(|((_, secret_key), message)| sign(message, secret_key))
+            .collect::<Vec<_>>();
+        let batch = |signatures: &[Signature]| {
+            messages
+                .iter()
+                .zip(signatures)
+                .zip(&keys)
+                .map(|((&message, signature), (key, _))| (message, signature, key))
+                .collect::<Vec<_>>()
+        };
+
+        assert!(verify_batch(&batch(&signatures)));
+        assert!(verify_batch(&[]));
+
+        signatures.swap(0, 1);
+        assert!(!verify_batch(&batch(&signatures)));
+    }
+
+    #[test]
+    fn sign_batch_matches_sign() {
+        let (pub_key, secret_key) = gen_keypair();
+        let messages = (0..4_u8).map(|i| vec![i; 10]).collect::<Vec<_>>();
+        let messages = messages.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        let signatures = sign_batch(&messages, &secret_key);
+        assert_eq!(signatures.len(), messages.len());
+        for (message, signature) in messages.iter().zip(&signatures) {
+            assert_eq!(*signature, sign(message, &secret_key));
+            assert!(verify(signature, message, &pub_key));
+        }
+        assert!(sign_batch(&[], &secret_key).is_empty());
+    }
+
+    #[test]
+    fn secret_keys_equality() {
+        let (_, secret_key) = gen_keypair_from_seed(&Seed::new([1; SEED_LENGTH]));
+        let (_, same_secret_key) = gen_keypair_from_seed(&Seed::new([1; SEED_LENGTH]));
+        let (_, other_secret_key) = gen_keypair_from_seed(&Seed::new([2; SEED_LENGTH]));
+        assert_eq!(secret_key, same_secret_key);
+        assert_ne!(secret_key, other_secret_key);
+        assert_eq!(secret_key.clone(), secret_key);
+        assert!(constant_time_eq(&[], &[]));
+        assert!(!constant_time_eq(&secret_key[..], &secret_key[1..]));
+    }
+
     #[test]
     fn serialize_deserialize_hash() {
         assert_serialize_deserialize(&Hash::new([207; HASH_SIZE]));
@@ -810,11 +1006,40 @@ mod tests {
         assert!(verified_stream.verify(&sig, &pk));
     }
 
+    #[test]
+    fn verify_prehashed_scheme() {
+        let data: [u8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 0];
+        let (pk, sk) = gen_keypair();
+        let sig = SignStream::new()
+            .update(&data[..5])
+            .update(&data[5..])
+            .sign(&sk);
+        assert!(SignatureScheme::Ed25519ph.verify(&sig, &data, &pk));
+        // Prehashed signatures are not valid plain ed25519 signatures and vice versa.
+        assert!(!SignatureScheme::Ed25519.verify(&sig, &data, &pk));
+        assert!(!SignatureScheme::Ed25519ph.verify(&sign(&data, &sk), &data, &pk));
+    }
+
     #[test]
     fn empty_slice_hash() {
         assert_eq!(Hash(super::crypto_impl::EMPTY_SLICE_HASH), hash(&[]));
     }
 
+    #[test]
+    fn secretbox_seal_open() {
+        let salt = secretbox::gen_salt();
+        let key = secretbox::Key::derive(b"passphrase", &salt);
+        assert_eq!(key, secretbox::Key::derive(b"passphrase", &salt));
+
+        let sealed = secretbox::seal(b"data", &key);
+        assert_eq!(sealed.len(), 4 + secretbox::OVERHEAD_LENGTH);
+        assert_eq!(secretbox::open(&sealed, &key).unwrap(), b"data");
+
+        let wrong_key = secretbox::Key::derive(b"wrong passphrase", &salt);
+        assert!(secretbox::open(&sealed, &wrong_key).is_none());
+        assert!(secretbox::open(&sealed[..secretbox::OVERHEAD_LENGTH - 1], &key).is_none());
+    }
+
     fn assert_serialize_deserialize<T>(original_value: &T)
     where
         T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
@@ -70,8 +70,11 @@ macro_rules! implement_public_crypto_wrapper {
 
 macro_rules! implement_private_crypto_wrapper {
     ($(#[$attr:meta])* struct $name:ident, $size:expr) => (
-    #[derive(Clone, PartialEq, Eq)]
+    #[derive(Clone)]
     $(#[$attr])*
+    ///
+    /// The memory of the instance is filled with zeros on drop, and the instances
+    /// are compared in constant time.
     pub struct $name($crate::crypto_impl::$name);
 
     impl $name {
@@ -117,6 +120,20 @@ macro_rules! implement_private_crypto_wrapper {
             (self.0).0.as_ref().write_hex_upper(w)
         }
     }
+
+    impl PartialEq for $name {
+        fn eq(&self, other: &Self) -> bool {
+            $crate::constant_time_eq(&self[..], &other[..])
+        }
+    }
+
+    impl Eq for $name {}
+
+    impl Drop for $name {
+        fn drop(&mut self) {
+            $crate::crypto_impl::memzero(&mut (self.0).0[..]);
+        }
+    }
     )
 }
 
@@ -0,0 +1,146 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generation and recovery of the node keys from [BIP-39] mnemonic phrases.
+//!
+//! A single phrase of 24 English words together with an optional passphrase
+//! determines both the consensus and the service keypairs of a node, so the phrase
+//! can be escrowed instead of the key files.
+//!
+//! [BIP-39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+
+use bip39::{Language, Mnemonic, MnemonicType, Seed as MnemonicSeed};
+
+use std::io::{Error, ErrorKind};
+
+use super::{gen_keypair_from_seed, hash, PublicKey, SecretKey, Seed};
+
+/// The number of words in the mnemonic phrases.
+pub const MNEMONIC_WORDS: usize = 24;
+
+/// The purpose of the keypair derived from a mnemonic phrase.
+///
+/// The purpose is also recorded in the encrypted key files, see [`KeyFileOptions`].
+///
+/// [`KeyFileOptions`]: ../utils/struct.KeyFileOptions.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyPurpose {
+    /// Consensus keypair of the node.
+    Consensus,
+    /// Service keypair of the node.
+    Service,
+}
+
+impl KeyPurpose {
+    pub(crate) fn label(self) -> &'static [u8] {
+        match self {
+            KeyPurpose::Consensus => b"exonum consensus key",
+            KeyPurpose::Service => b"exonum service key",
+        }
+    }
+}
+
+/// Generates a random mnemonic phrase of 24 words.
+pub fn generate_mnemonic() -> String {
+    Mnemonic::new(MnemonicType::Words24, Language::English).into_phrase()
+}
+
+/// Checks that the mnemonic phrase consists of 24 known words and has a valid checksum.
+pub fn validate_mnemonic(phrase: &str) -> Result<(), Error> {
+    parse_mnemonic(phrase).map(drop)
+}
+
+/// Derives the keypair with the given purpose from the mnemonic phrase and the passphrase.
+///
+/// The passphrase may be empty. A different passphrase yields different keys, so
+/// it should be escrowed along with the phrase.
+pub fn keypair_from_mnemonic(
+    phrase: &str,
+    passphrase: &str,
+    purpose: KeyPurpose,
+) -> Result<(PublicKey, SecretKey), Error> {
+    let mut data = seed_from_mnemonic(phrase, passphrase)?;
+    data.extend_from_slice(purpose.label());
+    let seed = Seed::from_slice(hash(&data).as_ref()).expect("Hash has the length of a seed");
+    for byte in &mut data {
+        *byte = 0;
+    }
+    Ok(gen_keypair_from_seed(&seed))
+}
+
+/// Returns the 64-byte BIP-39 seed of the mnemonic phrase and the passphrase.
+///
+/// The seed can serve as the master seed of the [`hd`] module.
+///
+/// [`hd`]: ../hd/index.html
+pub fn seed_from_mnemonic(phrase: &str, passphrase: &str) -> Result<Vec<u8>, Error> {
+    let mnemonic = parse_mnemonic(phrase)?;
+    Ok(MnemonicSeed::new(&mnemonic, passphrase).as_bytes().to_vec())
+}
+
+fn parse_mnemonic(phrase: &str) -> Result<Mnemonic, Error> {
+    let words = phrase.split_whitespace().collect::<Vec<_>>();
+    if words.len() != MNEMONIC_WORDS {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Mnemonic phrase must have {} words", MNEMONIC_WORDS),
+        ));
+    }
+    Mnemonic::from_phrase(words.join(" "), Language::English)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recover_keys() {
+        let phrase = generate_mnemonic();
+        assert_eq!(phrase.split(' ').count(), MNEMONIC_WORDS);
+        assert!(validate_mnemonic(&phrase).is_ok());
+
+        let consensus_keys = keypair_from_mnemonic(&phrase, "", KeyPurpose::Consensus).unwrap();
+        let service_keys = keypair_from_mnemonic(&phrase, "", KeyPurpose::Service).unwrap();
+        assert_ne!(consensus_keys.0, service_keys.0);
+
+        // Extra whitespace does not change the keys.
+        let spaced_phrase = format!(" {} ", phrase.replace(' ', "\n  "));
+        assert_eq!(
+            keypair_from_mnemonic(&spaced_phrase, "", KeyPurpose::Consensus).unwrap(),
+            consensus_keys
+        );
+        // The passphrase does.
+        let protected_keys =
+            keypair_from_mnemonic(&phrase, "passphrase", KeyPurpose::Consensus).unwrap();
+        assert_ne!(protected_keys.0, consensus_keys.0);
+        assert_eq!(seed_from_mnemonic(&phrase, "").unwrap().len(), 64);
+    }
+
+    #[test]
+    fn test_invalid_mnemonic() {
+        let words = vec!["abandon"; MNEMONIC_WORDS - 1];
+        let valid_phrase = format!("{} art", words.join(" "));
+        assert!(validate_mnemonic(&valid_phrase).is_ok());
+
+        let short_phrase = words.join(" ");
+        assert!(validate_mnemonic(&short_phrase).is_err());
+        let wrong_checksum = format!("{} abandon", short_phrase);
+        assert!(validate_mnemonic(&wrong_checksum).is_err());
+        assert!(keypair_from_mnemonic(&wrong_checksum, "", KeyPurpose::Service).is_err());
+        let unknown_word = format!("{} exonum", short_phrase);
+        assert!(validate_mnemonic(&unknown_word).is_err());
+    }
+}
@@ -12,12 +12,30 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// spell-checker:ignore cipherparams ciphertext
+// spell-checker:ignore cipherparams ciphertext kdfparams
 
-use super::{gen_keypair, gen_keypair_from_seed, PublicKey, SecretKey, Seed, SEED_LENGTH};
+//! Files with the secret keys and seeds encrypted with passphrases.
+//!
+//! The files are written in the format of version [`SECRET_FILE_VERSION`]: the encryption key
+//! is derived from the passphrase with the memory-hard Argon2id function, the parameters of
+//! which are stored in the file along with the optional purpose of the key. Files written
+//! in the previous format (without the `version` field, with the keys derived by scrypt)
+//! are still read by the same functions.
+//!
+//! [`SECRET_FILE_VERSION`]: constant.SECRET_FILE_VERSION.html
+
+use super::{
+    gen_keypair, gen_keypair_from_seed,
+    hd::{DerivationPath, ExtendedKey},
+    mnemonic::KeyPurpose,
+    secretbox::{self, Key},
+    PublicKey, SecretKey, Seed, SEED_LENGTH,
+};
+use argon2::{Config, ThreadMode, Variant, Version};
 use hex_buffer_serde::Hex;
-use pwbox::{sodium::Sodium, ErasedPwBox, Eraser, Suite};
-use rand::thread_rng;
+use pwbox::{sodium::Sodium, ErasedPwBox, Eraser};
+use rand::{thread_rng, RngCore};
+use serde::Serialize;
 use std::borrow::Cow;
 #[cfg(unix)]
 use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
@@ -27,23 +45,225 @@ use std::{
     path::Path,
 };
 
+/// Version of the format of the encrypted files written by this module.
+pub const SECRET_FILE_VERSION: u32 = 2;
+
+/// Version of the files written before the format was versioned.
+const LEGACY_FILE_VERSION: u32 = 1;
+/// Name of the key derivation function of the current format.
+const KDF_NAME: &str = "argon2id";
+/// Name of the cipher of the current format.
+const CIPHER_NAME: &str = "xsalsa20-poly1305";
+/// Length of the salt of the key derivation function.
+const KDF_SALT_LENGTH: usize = 16;
+
+/// Parameters of the Argon2id key derivation function used to encrypt the files.
+///
+/// The default parameters are the second recommended option of RFC 9106: 64 MiB
+/// of memory, 3 passes and 4 lanes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Amount of memory in KiB.
+    pub mem_cost: u32,
+    /// Number of passes over the memory.
+    pub time_cost: u32,
+    /// Degree of parallelism.
+    pub lanes: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            mem_cost: 64 * 1024,
+            time_cost: 3,
+            lanes: 4,
+        }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(&self, pass_phrase: &[u8], salt: &[u8], ad: &[u8]) -> Result<Key, Error> {
+        let config = Config {
+            variant: Variant::Argon2id,
+            version: Version::Version13,
+            mem_cost: self.mem_cost,
+            time_cost: self.time_cost,
+            lanes: self.lanes,
+            thread_mode: ThreadMode::Sequential,
+            secret: &[],
+            ad,
+            hash_length: secretbox::KEY_LENGTH as u32,
+        };
+        let mut key_bytes = argon2::hash_raw(pass_phrase, salt, &config)
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        let key = Key::from_slice(&key_bytes).expect("Derived key has the key length");
+        for byte in &mut key_bytes {
+            *byte = 0;
+        }
+        Ok(key)
+    }
+}
+
+/// Options of the encrypted files.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyFileOptions {
+    kdf_params: KdfParams,
+    purpose: Option<KeyPurpose>,
+}
+
+impl KeyFileOptions {
+    /// Creates the default options: the default parameters of the key derivation function
+    /// and no purpose of the key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the parameters of the key derivation function.
+    pub fn with_kdf_params(mut self, kdf_params: KdfParams) -> Self {
+        self.kdf_params = kdf_params;
+        self
+    }
+
+    /// Records the purpose of the key in the file. The purpose is authenticated
+    /// by the encryption, so it cannot be changed without the passphrase.
+    pub fn with_purpose(mut self, purpose: KeyPurpose) -> Self {
+        self.purpose = Some(purpose);
+        self
+    }
+}
+
+/// Metadata of an encrypted file, which can be read without the passphrase.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecretFileMetadata {
+    /// Version of the file format.
+    pub version: u32,
+    /// Purpose of the key, if recorded in the file.
+    pub purpose: Option<KeyPurpose>,
+    /// Parameters of the key derivation function. `None` for the files
+    /// of the legacy format.
+    pub kdf_params: Option<KdfParams>,
+}
+
 /// Creates a TOML file that contains encrypted `SecretKey` and returns `PublicKey` for the secret key.
 pub fn generate_keys_file<P: AsRef<Path>, W: AsRef<[u8]>>(
     path: P,
     pass_phrase: W,
 ) -> Result<PublicKey, Error> {
     let (pk, sk) = gen_keypair();
-    let keys = EncryptedKeys::encrypt(pk, &sk, pass_phrase)?;
+    create_keys_file(path, pass_phrase, pk, &sk)?;
+    Ok(pk)
+}
+
+/// Creates a TOML file that contains the given `SecretKey` encrypted with the passphrase.
+pub fn create_keys_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+    public_key: PublicKey,
+    secret_key: &SecretKey,
+) -> Result<(), Error> {
+    create_keys_file_with_options(
+        path,
+        pass_phrase,
+        public_key,
+        secret_key,
+        &KeyFileOptions::default(),
+    )
+}
+
+/// Creates a TOML file that contains the given `SecretKey` encrypted with the passphrase,
+/// with the specified parameters of the key derivation function and the purpose of the key.
+pub fn create_keys_file_with_options<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+    public_key: PublicKey,
+    secret_key: &SecretKey,
+    options: &KeyFileOptions,
+) -> Result<(), Error> {
+    let keys = EncryptedKeys::encrypt_with_options(public_key, secret_key, pass_phrase, options)?;
+    write_secret_file(path, &keys)
+}
+
+/// Creates a TOML file that contains the master seed of the derived keys encrypted with
+/// the passphrase. See the [`hd`] module for the derivation of the keys.
+///
+/// [`hd`]: ../hd/index.html
+pub fn create_seed_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+    seed: &[u8],
+) -> Result<(), Error> {
+    create_seed_file_with_options(path, pass_phrase, seed, &KeyFileOptions::default())
+}
+
+/// Creates a TOML file that contains the master seed encrypted with the passphrase,
+/// with the specified parameters of the key derivation function.
+pub fn create_seed_file_with_options<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+    seed: &[u8],
+    options: &KeyFileOptions,
+) -> Result<(), Error> {
+    let seed = EncryptedSeed::encrypt(seed, pass_phrase, options)?;
+    write_secret_file(path, &seed)
+}
+
+/// Reads the master seed from the encrypted file and returns the keypair derived
+/// from it with the given path.
+pub fn read_derived_keys_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+    derivation_path: &DerivationPath,
+) -> Result<(PublicKey, SecretKey), Error> {
+    let file_content = read_secret_file(path)?;
+    let seed: EncryptedSeed =
+        toml::from_slice(file_content.as_slice()).map_err(|e| Error::new(ErrorKind::Other, e))?;
+    let mut seed = seed.decrypt(pass_phrase)?;
+    let keys = ExtendedKey::master(&seed)
+        .derive_path(derivation_path)
+        .keypair();
+    for byte in &mut seed {
+        *byte = 0;
+    }
+    Ok(keys)
+}
+
+/// Reads the metadata of an encrypted key or seed file without decrypting it.
+pub fn read_secret_file_metadata<P: AsRef<Path>>(path: P) -> Result<SecretFileMetadata, Error> {
+    let file_content = read_secret_file(path)?;
+    let header: SecretFileHeader =
+        toml::from_slice(file_content.as_slice()).map_err(|e| Error::new(ErrorKind::Other, e))?;
+    let kdf_params = match header.secret_key.or(header.seed) {
+        Some(EncryptedSecret::Sealed(sealed)) => Some(sealed.kdfparams.params()),
+        Some(EncryptedSecret::Legacy(_)) => None,
+        None => return Err(Error::new(ErrorKind::Other, "No encrypted secret in file")),
+    };
+    Ok(SecretFileMetadata {
+        version: header.version,
+        purpose: header.purpose,
+        kdf_params,
+    })
+}
+
+fn write_secret_file<P: AsRef<Path>, T: Serialize>(path: P, content: &T) -> Result<(), Error> {
     let file_content =
-        toml::to_string_pretty(&keys).map_err(|e| Error::new(ErrorKind::Other, e))?;
+        toml::to_string_pretty(content).map_err(|e| Error::new(ErrorKind::Other, e))?;
     let mut open_options = OpenOptions::new();
     open_options.create(true).write(true);
     #[cfg(unix)]
     open_options.mode(0o_600);
     let mut file = open_options.open(path.as_ref())?;
-    file.write_all(file_content.as_bytes())?;
+    file.write_all(file_content.as_bytes())
+}
 
-    Ok(pk)
+fn read_secret_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, Error> {
+    let mut file = File::open(path)?;
+
+    #[cfg(unix)]
+    validate_file_mode(file.metadata()?.mode())?;
+
+    let mut file_content = vec![];
+    file.read_to_end(&mut file_content)?;
+    Ok(file_content)
 }
 
 /// Reads and returns `PublicKey` and `SecretKey` from encrypted file located by path and returns its.
@@ -51,13 +271,7 @@ pub fn read_keys_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
     path: P,
     pass_phrase: W,
 ) -> Result<(PublicKey, SecretKey), Error> {
-    let mut key_file = File::open(path)?;
-
-    #[cfg(unix)]
-    validate_file_mode(key_file.metadata()?.mode())?;
-
-    let mut file_content = vec![];
-    key_file.read_to_end(&mut file_content)?;
+    let file_content = read_secret_file(path)?;
     let keys: EncryptedKeys =
         toml::from_slice(file_content.as_slice()).map_err(|e| Error::new(ErrorKind::Other, e))?;
     keys.decrypt(pass_phrase)
@@ -86,11 +300,178 @@ impl Hex<PublicKey> for PublicKeyHex {
     }
 }
 
+fn legacy_version() -> u32 {
+    LEGACY_FILE_VERSION
+}
+
+fn check_version(version: u32) -> Result<(), Error> {
+    if version > SECRET_FILE_VERSION {
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported version of the secret file: {}", version),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+struct BytesHex;
+
+impl Hex<Vec<u8>> for BytesHex {
+    fn create_bytes(value: &Vec<u8>) -> Cow<[u8]> {
+        Cow::Borrowed(value.as_slice())
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Associated data of the key derivation, which binds the metadata of the file
+/// to the encrypted secret.
+fn associated_data(purpose: Option<KeyPurpose>, public_key: Option<&PublicKey>) -> Vec<u8> {
+    let mut ad = SECRET_FILE_VERSION.to_le_bytes().to_vec();
+    if let Some(purpose) = purpose {
+        ad.extend_from_slice(purpose.label());
+    }
+    if let Some(public_key) = public_key {
+        ad.extend_from_slice(public_key.as_ref());
+    }
+    ad
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedKdfParams {
+    #[serde(with = "BytesHex")]
+    salt: Vec<u8>,
+    mem_cost: u32,
+    time_cost: u32,
+    lanes: u32,
+}
+
+impl SealedKdfParams {
+    fn params(&self) -> KdfParams {
+        KdfParams {
+            mem_cost: self.mem_cost,
+            time_cost: self.time_cost,
+            lanes: self.lanes,
+        }
+    }
+}
+
+/// Secret encrypted with the key derived by Argon2id.
+#[derive(Serialize, Deserialize)]
+struct SealedSecret {
+    kdf: String,
+    cipher: String,
+    /// The nonce, the encrypted secret and the authentication tag.
+    #[serde(with = "BytesHex")]
+    ciphertext: Vec<u8>,
+    kdfparams: SealedKdfParams,
+}
+
+impl SealedSecret {
+    fn seal(
+        secret: &[u8],
+        pass_phrase: impl AsRef<[u8]>,
+        kdf_params: KdfParams,
+        ad: &[u8],
+    ) -> Result<Self, Error> {
+        let mut salt = vec![0; KDF_SALT_LENGTH];
+        thread_rng().fill_bytes(&mut salt);
+        let key = kdf_params.derive_key(pass_phrase.as_ref(), &salt, ad)?;
+        Ok(SealedSecret {
+            kdf: KDF_NAME.to_owned(),
+            cipher: CIPHER_NAME.to_owned(),
+            ciphertext: secretbox::seal(secret, &key),
+            kdfparams: SealedKdfParams {
+                salt,
+                mem_cost: kdf_params.mem_cost,
+                time_cost: kdf_params.time_cost,
+                lanes: kdf_params.lanes,
+            },
+        })
+    }
+
+    fn open(&self, pass_phrase: impl AsRef<[u8]>, ad: &[u8]) -> Result<Vec<u8>, Error> {
+        if self.kdf != KDF_NAME || self.cipher != CIPHER_NAME {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported encryption: {} with {}", self.kdf, self.cipher),
+            ));
+        }
+        let key =
+            self.kdfparams
+                .params()
+                .derive_key(pass_phrase.as_ref(), &self.kdfparams.salt, ad)?;
+        secretbox::open(&self.ciphertext, &key)
+            .ok_or_else(|| Error::new(ErrorKind::Other, "Couldn't open an encrypted secret"))
+    }
+}
+
+/// Encrypted secret in the current or the legacy format.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum EncryptedSecret {
+    Sealed(SealedSecret),
+    Legacy(ErasedPwBox),
+}
+
+impl EncryptedSecret {
+    fn open_legacy(pwbox: &ErasedPwBox, pass_phrase: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let mut eraser = Eraser::new();
+        eraser.add_suite::<Sodium>();
+        let restored = eraser
+            .restore(pwbox)
+            .map_err(|_| Error::new(ErrorKind::Other, "Couldn't restore a secret"))?;
+        let secret = restored
+            .open(pass_phrase)
+            .map_err(|_| Error::new(ErrorKind::Other, "Couldn't open an encrypted secret"))?;
+        Ok(secret[..].to_vec())
+    }
+
+    fn open(
+        &self,
+        version: u32,
+        pass_phrase: impl AsRef<[u8]>,
+        ad: &[u8],
+    ) -> Result<Vec<u8>, Error> {
+        check_version(version)?;
+        match (version, self) {
+            (LEGACY_FILE_VERSION, EncryptedSecret::Legacy(pwbox)) => {
+                Self::open_legacy(pwbox, pass_phrase)
+            }
+            (SECRET_FILE_VERSION, EncryptedSecret::Sealed(sealed)) => sealed.open(pass_phrase, ad),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                "Encrypted secret does not match the version of the file",
+            )),
+        }
+    }
+}
+
+/// Fields of the encrypted files which can be read without the passphrase.
+#[derive(Deserialize)]
+struct SecretFileHeader {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(default)]
+    purpose: Option<KeyPurpose>,
+    #[serde(default)]
+    secret_key: Option<EncryptedSecret>,
+    #[serde(default)]
+    seed: Option<EncryptedSecret>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct EncryptedKeys {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    purpose: Option<KeyPurpose>,
     #[serde(with = "PublicKeyHex")]
     public_key: PublicKey,
-    secret_key: ErasedPwBox,
+    secret_key: EncryptedSecret,
 }
 
 impl EncryptedKeys {
@@ -99,35 +480,41 @@ impl EncryptedKeys {
         secret_key: &SecretKey,
         pass_phrase: impl AsRef<[u8]>,
     ) -> Result<EncryptedKeys, Error> {
-        let mut rng = thread_rng();
-        let mut eraser = Eraser::new();
+        Self::encrypt_with_options(
+            public_key,
+            secret_key,
+            pass_phrase,
+            &KeyFileOptions::default(),
+        )
+    }
+
+    pub fn encrypt_with_options(
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+        pass_phrase: impl AsRef<[u8]>,
+        options: &KeyFileOptions,
+    ) -> Result<EncryptedKeys, Error> {
         let seed = &secret_key[..SEED_LENGTH];
-        eraser.add_suite::<Sodium>();
-        let pwbox = Sodium::build_box(&mut rng)
-            .seal(pass_phrase, &seed)
-            .map_err(|_| Error::new(ErrorKind::Other, "Couldn't create a pw box"))?;
-        let encrypted_key = eraser
-            .erase(&pwbox)
-            .map_err(|_| Error::new(ErrorKind::Other, "Couldn't convert a pw box"))?;
+        let ad = associated_data(options.purpose, Some(&public_key));
+        let secret_key = SealedSecret::seal(seed, pass_phrase, options.kdf_params, &ad)?;
 
         Ok(EncryptedKeys {
+            version: SECRET_FILE_VERSION,
+            purpose: options.purpose,
             public_key,
-            secret_key: encrypted_key,
+            secret_key: EncryptedSecret::Sealed(secret_key),
         })
     }
 
     pub fn decrypt(self, pass_phrase: impl AsRef<[u8]>) -> Result<(PublicKey, SecretKey), Error> {
-        let mut eraser = Eraser::new();
-        eraser.add_suite::<Sodium>();
-        let restored = eraser
-            .restore(&self.secret_key)
-            .map_err(|_| Error::new(ErrorKind::Other, "Couldn't restore a secret key"))?;
-        assert_eq!(restored.len(), SEED_LENGTH);
-        let seed_bytes = restored
-            .open(pass_phrase)
-            .map_err(|_| Error::new(ErrorKind::Other, "Couldn't open an encrypted key"))?;
-        let seed = Seed::from_slice(&seed_bytes[..])
-            .ok_or_else(|| Error::new(ErrorKind::Other, "Couldn't create seed from slice"))?;
+        let ad = associated_data(self.purpose, Some(&self.public_key));
+        let mut seed_bytes = self.secret_key.open(self.version, pass_phrase, &ad)?;
+        let seed = Seed::from_slice(&seed_bytes[..]);
+        for byte in &mut seed_bytes {
+            *byte = 0;
+        }
+        let seed =
+            seed.ok_or_else(|| Error::new(ErrorKind::Other, "Couldn't create seed from slice"))?;
         let (public_key, secret_key) = gen_keypair_from_seed(&seed);
 
         if self.public_key == public_key {
@@ -138,6 +525,36 @@ impl EncryptedKeys {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct EncryptedSeed {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    purpose: Option<KeyPurpose>,
+    seed: EncryptedSecret,
+}
+
+impl EncryptedSeed {
+    fn encrypt(
+        seed: &[u8],
+        pass_phrase: impl AsRef<[u8]>,
+        options: &KeyFileOptions,
+    ) -> Result<Self, Error> {
+        let ad = associated_data(options.purpose, None);
+        let seed = SealedSecret::seal(seed, pass_phrase, options.kdf_params, &ad)?;
+        Ok(EncryptedSeed {
+            version: SECRET_FILE_VERSION,
+            purpose: options.purpose,
+            seed: EncryptedSecret::Sealed(seed),
+        })
+    }
+
+    fn decrypt(self, pass_phrase: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let ad = associated_data(self.purpose, None);
+        self.seed.open(self.version, pass_phrase, &ad)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,6 +570,23 @@ mod tests {
         assert_eq!(pk1, pk2);
     }
 
+    #[test]
+    fn test_create_and_read_seed_file() {
+        let dir = TempDir::new("test_utils").expect("Couldn't create TempDir");
+        let file_path = dir.path().join("master_seed.toml");
+        let pass_phrase = b"passphrase";
+        let seed = [7; 64];
+        create_seed_file(file_path.as_path(), pass_phrase, &seed).unwrap();
+
+        let path: DerivationPath = "m/0'/1'".parse().unwrap();
+        let keys = read_derived_keys_from_file(file_path.as_path(), pass_phrase, &path).unwrap();
+        assert_eq!(
+            keys,
+            ExtendedKey::master(&seed).derive_path(&path).keypair()
+        );
+        assert!(read_derived_keys_from_file(file_path.as_path(), b"wrong", &path).is_err());
+    }
+
     #[test]
     fn test_encrypt_decrypt() {
         let pass_phrase = b"passphrase";
@@ -197,6 +631,95 @@ mod tests {
         )
     }
 
+    fn light_options() -> KeyFileOptions {
+        KeyFileOptions::new().with_kdf_params(KdfParams {
+            mem_cost: 1024,
+            time_cost: 1,
+            lanes: 1,
+        })
+    }
+
+    #[test]
+    fn test_keys_file_metadata() {
+        let dir = TempDir::new("test_utils").expect("Couldn't create TempDir");
+        let file_path = dir.path().join("consensus.toml");
+        let pass_phrase = b"passphrase";
+        let (pk, sk) = gen_keypair();
+        let options = light_options().with_purpose(KeyPurpose::Consensus);
+        create_keys_file_with_options(&file_path, pass_phrase, pk, &sk, &options).unwrap();
+
+        let metadata = read_secret_file_metadata(&file_path).unwrap();
+        assert_eq!(
+            metadata,
+            SecretFileMetadata {
+                version: SECRET_FILE_VERSION,
+                purpose: Some(KeyPurpose::Consensus),
+                kdf_params: Some(options.kdf_params),
+            }
+        );
+        assert_eq!(
+            read_keys_from_file(&file_path, pass_phrase).unwrap(),
+            (pk, sk)
+        );
+    }
+
+    #[test]
+    fn test_changed_purpose() {
+        let pass_phrase = b"passphrase";
+        let (pk, sk) = gen_keypair();
+        let options = light_options().with_purpose(KeyPurpose::Consensus);
+        let keys = EncryptedKeys::encrypt_with_options(pk, &sk, pass_phrase, &options).unwrap();
+        let content = toml::to_string(&keys).unwrap();
+        assert!(content.contains("purpose = \"consensus\""));
+
+        let content = content.replace("purpose = \"consensus\"", "purpose = \"service\"");
+        let keys: EncryptedKeys = toml::from_str(&content).unwrap();
+        assert!(keys.decrypt(pass_phrase).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let pass_phrase = b"passphrase";
+        let (pk, sk) = gen_keypair();
+        let keys =
+            EncryptedKeys::encrypt_with_options(pk, &sk, pass_phrase, &light_options()).unwrap();
+        let content = toml::to_string(&keys)
+            .unwrap()
+            .replace("version = 2", "version = 3");
+        let keys: EncryptedKeys = toml::from_str(&content).unwrap();
+        assert_eq!(
+            keys.decrypt(pass_phrase).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_legacy_file_header() {
+        let file_content = r#"
+            [seed]
+            ciphertext = '7fbb51090742482da42816b2c908ff61c470a19ca1b984014c7ac37dd46ef1ef'
+            mac = '862f27c67b07f9665628b6f9a72a1c20'
+            kdf = 'scrypt-nacl'
+            cipher = 'xsalsa20-poly1305'
+
+            [seed.kdfparams]
+            salt = '2ee70102a15aff032523a5df91e435172ef003ad9898a3a5eb2f5af447d28b63'
+            memlimit = 16777216
+            opslimit = 524288
+
+            [seed.cipherparams]
+            iv = '374c8dc0ab8d753ae0515f485e24f6c76b469cde3dee285c'
+        "#;
+
+        let header: SecretFileHeader = toml::from_str(file_content).unwrap();
+        assert_eq!(header.version, LEGACY_FILE_VERSION);
+        assert!(header.purpose.is_none());
+        match header.seed {
+            Some(EncryptedSecret::Legacy(_)) => {}
+            _ => panic!("Legacy seed expected"),
+        }
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_validate_file_mode() {
@@ -199,7 +199,7 @@ fn implement_transaction_set_trait(
         let id = variant.id;
         let source_type = variant.source_type();
         quote! {
-            #id => #source_type::from_bytes(std::borrow::Cow::from(&vec)).map(#name::from),
+            #id => #cr::proto::from_payload_bytes::<#source_type>(&vec).map(#name::from),
         }
     });
 
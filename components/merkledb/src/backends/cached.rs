@@ -0,0 +1,324 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of `CachedDB` database.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, mem,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    db::Change,
+    views::{index_data_prefix, index_identifier, INDEXES_POOL_NAME},
+    CacheOptions, ColumnFamilySize, Database, Iter, Patch, Result, Snapshot,
+};
+
+const LOCK_ERROR: &str = "Database cache lock is poisoned";
+/// Name of the column family storing the data of the indexes.
+const INDEXES_DATA_NAME: &str = "";
+/// Length of the prefix of the index data keys.
+const DATA_PREFIX_LEN: usize = mem::size_of::<u64>();
+
+/// Wrapper over a database backend which keeps recently read entries of the specified
+/// indexes in memory.
+///
+/// Each `CacheOptions` describes a bounded cache shared by the indexes with the given
+/// name prefixes. The cache keeps both present and absent entries, evicting the least
+/// recently read ones, and is shared by all the snapshots of the database. Entries
+/// changed by a merged patch are invalidated; snapshots created before the latest merge
+/// read from the wrapped database directly, so the caches never break their isolation.
+/// Iteration over the indexes is not cached.
+///
+/// If values should also be encrypted, `CachedDB` should wrap an [`EncryptedDB`],
+/// since the caches cannot be assigned to the indexes with encrypted metadata.
+///
+/// [`EncryptedDB`]: struct.EncryptedDB.html
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{CacheOptions, CachedDB, Database, MapIndex, TemporaryDB};
+///
+/// let options = vec![CacheOptions::new(vec!["wallets"], 1_000)];
+/// let db = CachedDB::new(Box::new(TemporaryDB::new()), options);
+/// let fork = db.fork();
+/// MapIndex::new("wallets", &fork).put(&1_u8, 10_u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let wallets: MapIndex<_, u8, u64> = MapIndex::new("wallets", &snapshot);
+/// assert_eq!(wallets.get(&1), Some(10));
+/// // The second read is served from the cache.
+/// assert_eq!(wallets.get(&1), Some(10));
+/// ```
+pub struct CachedDB {
+    inner: Box<dyn Database>,
+    options: Vec<CacheOptions>,
+    state: Arc<Mutex<CacheState>>,
+}
+
+/// State of the caches shared by a `CachedDB` and its snapshots.
+struct CacheState {
+    /// Number of the merged patches. Snapshots created before the latest merge
+    /// bypass the caches.
+    generation: u64,
+    /// Caches assigned to the indexes, keyed by the prefix of the index data.
+    routes: HashMap<Vec<u8>, usize>,
+    caches: Vec<Cache>,
+}
+
+/// A bounded cache evicting the least recently read entries.
+struct Cache {
+    capacity: usize,
+    entries: HashMap<Vec<u8>, CacheEntry>,
+    /// Keys of the entries ordered by the time of the latest read.
+    recent: BTreeMap<u64, Vec<u8>>,
+    clock: u64,
+}
+
+struct CacheEntry {
+    value: Option<Vec<u8>>,
+    read_at: u64,
+}
+
+/// A snapshot of a `CachedDB`.
+struct CachedSnapshot {
+    inner: Box<dyn Snapshot>,
+    state: Arc<Mutex<CacheState>>,
+    generation: u64,
+}
+
+impl CachedDB {
+    /// Wraps the database, caching the entries of the indexes specified by `options`.
+    pub fn new(inner: Box<dyn Database>, options: Vec<CacheOptions>) -> Self {
+        let mut state = CacheState {
+            generation: 0,
+            routes: HashMap::new(),
+            caches: options
+                .iter()
+                .map(|options| Cache::new(options.capacity))
+                .collect(),
+        };
+
+        let snapshot = inner.snapshot();
+        let mut indexes = snapshot.iter(INDEXES_POOL_NAME, &[]);
+        while let Some((index_name, metadata)) = indexes.next() {
+            state.assign_cache(&options, index_name, metadata);
+        }
+
+        Self {
+            inner,
+            options,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+}
+
+impl Database for CachedDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        let state = self.state.lock().expect(LOCK_ERROR);
+        Box::new(CachedSnapshot {
+            inner: self.inner.snapshot(),
+            state: Arc::clone(&self.state),
+            generation: state.generation,
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        let mut state = self.state.lock().expect(LOCK_ERROR);
+        state.invalidate(&self.options, &patch);
+        self.inner.merge(patch)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        let mut state = self.state.lock().expect(LOCK_ERROR);
+        state.invalidate(&self.options, &patch);
+        self.inner.merge_sync(patch)
+    }
+
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        self.inner.create_checkpoint(path)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        self.inner.checkpoint_snapshot(path)
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner.approximate_sizes()
+    }
+}
+
+impl fmt::Debug for CachedDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CachedDB")
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl CacheState {
+    /// Assigns the index to the first cache matching its name, if any.
+    fn assign_cache(&mut self, options: &[CacheOptions], index_name: &[u8], metadata: &[u8]) {
+        // The empty key stores the length of the pool.
+        if index_name.is_empty() {
+            return;
+        }
+        let cache = options.iter().position(|options| {
+            options
+                .index_prefixes
+                .iter()
+                .any(|prefix| index_name.starts_with(prefix.as_bytes()))
+        });
+        if let (Some(cache), Some(identifier)) = (cache, index_identifier(metadata)) {
+            self.routes.insert(index_data_prefix(identifier), cache);
+        }
+    }
+
+    /// Removes the entries changed by the patch from the caches and starts a new generation.
+    fn invalidate(&mut self, options: &[CacheOptions], patch: &Patch) {
+        for (name, changes) in patch.iter() {
+            if name == INDEXES_POOL_NAME {
+                for (index_name, change) in changes.iter() {
+                    if let Change::Put(metadata) = change {
+                        self.assign_cache(options, index_name, metadata);
+                    }
+                }
+            } else if name == INDEXES_DATA_NAME {
+                for prefix in changes.prefixes_to_remove() {
+                    for cache in &mut self.caches {
+                        cache.remove_prefix(prefix);
+                    }
+                }
+                for (key, _) in changes.iter() {
+                    if let Some(cache) = self.cache_for(key) {
+                        self.caches[cache].remove(key);
+                    }
+                }
+            }
+        }
+        self.generation += 1;
+    }
+
+    fn cache_for(&self, key: &[u8]) -> Option<usize> {
+        if key.len() < DATA_PREFIX_LEN {
+            return None;
+        }
+        self.routes.get(&key[..DATA_PREFIX_LEN]).cloned()
+    }
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recent: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        let now = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        let key = self
+            .recent
+            .remove(&entry.read_at)
+            .expect("Cache entry is not tracked");
+        entry.read_at = now;
+        self.recent.insert(now, key);
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: Vec<u8>, value: Option<Vec<u8>>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let now = self.tick();
+        let entry = CacheEntry {
+            value,
+            read_at: now,
+        };
+        if let Some(old_entry) = self.entries.insert(key.clone(), entry) {
+            self.recent.remove(&old_entry.read_at);
+        } else if self.entries.len() > self.capacity {
+            let oldest = *self.recent.keys().next().expect("Cache is empty");
+            let oldest_key = self.recent.remove(&oldest).expect("Cache is empty");
+            self.entries.remove(&oldest_key);
+        }
+        self.recent.insert(now, key);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recent.remove(&entry.read_at);
+        }
+    }
+
+    fn remove_prefix(&mut self, prefix: &[u8]) {
+        let keys = self
+            .entries
+            .keys()
+            .filter(|key| key.starts_with(prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in keys {
+            self.remove(&key);
+        }
+    }
+}
+
+impl Snapshot for CachedSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        if name != INDEXES_DATA_NAME {
+            return self.inner.get(name, key);
+        }
+
+        let cache = {
+            let mut state = self.state.lock().expect(LOCK_ERROR);
+            if state.generation != self.generation {
+                None
+            } else if let Some(cache) = state.cache_for(key) {
+                if let Some(value) = state.caches[cache].get(key) {
+                    return value;
+                }
+                Some(cache)
+            } else {
+                None
+            }
+        };
+
+        let value = self.inner.get(name, key);
+        if let Some(cache) = cache {
+            let mut state = self.state.lock().expect(LOCK_ERROR);
+            // Entries read after a merge could be outdated.
+            if state.generation == self.generation {
+                state.caches[cache].insert(key.to_vec(), value.clone());
+            }
+        }
+        value
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        self.inner.iter(name, from)
+    }
+}
@@ -0,0 +1,246 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of `CompressedDB` database.
+
+use std::{fmt, io, path::Path};
+
+use crate::{
+    db::DB_METADATA,
+    views::{View, INDEXES_POOL_NAME},
+    ColumnFamilySize, CompressionAlgorithm, CompressionOptions, Database, Error, Iter, Iterator,
+    Patch, Result, Snapshot,
+};
+
+/// Name of the column family with the compression parameters.
+const COMPRESSION_METADATA: &str = "__COMPRESSION__";
+/// Marker of the database with compressed values.
+const COMPRESSED_NAME: &str = "compressed";
+
+/// Tag of the values stored as is.
+const RAW_TAG: u8 = 0;
+/// Tag of the values compressed with LZ4.
+const LZ4_TAG: u8 = 1;
+/// Tag of the values compressed with Zstandard.
+const ZSTD_TAG: u8 = 2;
+/// Zstandard compression level; zero means the default level.
+const ZSTD_LEVEL: i32 = 0;
+
+/// Wrapper over a database backend which transparently compresses stored values.
+///
+/// Values not shorter than the threshold specified in `CompressionOptions` are compressed;
+/// each stored value is prefixed with a tag of the compression algorithm, so the algorithm
+/// may be changed when the database is reopened. The metadata of the indexes is stored
+/// as is.
+///
+/// If values should also be encrypted, `CompressedDB` should wrap an [`EncryptedDB`],
+/// since encrypted values cannot be compressed.
+///
+/// [`EncryptedDB`]: struct.EncryptedDB.html
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{
+///     CompressedDB, CompressionAlgorithm, CompressionOptions, Database, ListIndex, TemporaryDB,
+/// };
+///
+/// let options = CompressionOptions::new(CompressionAlgorithm::Zstd);
+/// let db = CompressedDB::new(Box::new(TemporaryDB::new()), options).unwrap();
+/// let fork = db.fork();
+/// ListIndex::new("index", &fork).push(vec![0_u8; 1024]);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let index: ListIndex<_, Vec<u8>> = ListIndex::new("index", &snapshot);
+/// assert_eq!(index.get(0), Some(vec![0_u8; 1024]));
+/// ```
+pub struct CompressedDB {
+    inner: Box<dyn Database>,
+    options: CompressionOptions,
+}
+
+/// A snapshot of a `CompressedDB`.
+struct CompressedSnapshot {
+    inner: Box<dyn Snapshot>,
+}
+
+/// An iterator over the entries of a `CompressedDB`.
+struct CompressedIterator<'a> {
+    inner: Iter<'a>,
+    compressed: bool,
+    value: Vec<u8>,
+}
+
+impl CompressedDB {
+    /// Wraps the database, compressing its values with the given options.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the database already contains uncompressed data.
+    pub fn new(inner: Box<dyn Database>, options: CompressionOptions) -> Result<Self> {
+        let snapshot = inner.snapshot();
+        if snapshot
+            .get(COMPRESSION_METADATA, COMPRESSED_NAME.as_bytes())
+            .is_none()
+        {
+            if snapshot.iter(INDEXES_POOL_NAME, &[]).next().is_some() {
+                return Err(Error::new("Database already contains uncompressed data"));
+            }
+            let fork = inner.fork();
+            View::new(&fork, COMPRESSION_METADATA).put(COMPRESSED_NAME, 1_u8);
+            inner.merge_sync(fork.into_patch())?;
+        }
+        Ok(Self { inner, options })
+    }
+
+    fn compress(&self, patch: Patch) -> Patch {
+        patch.map_values(|name, _, value| {
+            if is_compressed(name) {
+                compress(value, self.options)
+            } else {
+                value
+            }
+        })
+    }
+}
+
+impl Database for CompressedDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        Box::new(CompressedSnapshot {
+            inner: self.inner.snapshot(),
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.inner.merge(self.compress(patch))
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.inner.merge_sync(self.compress(patch))
+    }
+
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        self.inner.create_checkpoint(path)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        Ok(Box::new(CompressedSnapshot {
+            inner: self.inner.checkpoint_snapshot(path)?,
+        }))
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner.approximate_sizes()
+    }
+}
+
+impl fmt::Debug for CompressedDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CompressedDB")
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl Snapshot for CompressedSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.inner.get(name, key)?;
+        if is_compressed(name) {
+            Some(decompress(&value))
+        } else {
+            Some(value)
+        }
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        Box::new(CompressedIterator {
+            inner: self.inner.iter(name, from),
+            compressed: is_compressed(name),
+            value: Vec::new(),
+        })
+    }
+}
+
+impl CompressedIterator<'_> {
+    fn decompress<'a>(
+        entry: Option<(&'a [u8], &'a [u8])>,
+        compressed: bool,
+        buffer: &'a mut Vec<u8>,
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        let (k, v) = entry?;
+        if compressed {
+            *buffer = decompress(v);
+            Some((k, buffer))
+        } else {
+            Some((k, v))
+        }
+    }
+}
+
+impl Iterator for CompressedIterator<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        Self::decompress(self.inner.next(), self.compressed, &mut self.value)
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        Self::decompress(self.inner.peek(), self.compressed, &mut self.value)
+    }
+}
+
+/// Returns `true` if values of the column family are compressed.
+fn is_compressed(name: &str) -> bool {
+    name != COMPRESSION_METADATA && name != DB_METADATA && name != INDEXES_POOL_NAME
+}
+
+fn compress(value: Vec<u8>, options: CompressionOptions) -> Vec<u8> {
+    if value.len() >= options.threshold {
+        let compressed = match options.algorithm {
+            CompressionAlgorithm::Lz4 => {
+                lz4::block::compress(&value, None, true).map(|bytes| (LZ4_TAG, bytes))
+            }
+            CompressionAlgorithm::Zstd => {
+                zstd::encode_all(value.as_slice(), ZSTD_LEVEL).map(|bytes| (ZSTD_TAG, bytes))
+            }
+        };
+        // Values which do not benefit from the compression are stored as is.
+        if let Ok((tag, bytes)) = compressed {
+            if bytes.len() < value.len() {
+                return tagged(tag, bytes);
+            }
+        }
+    }
+    tagged(RAW_TAG, value)
+}
+
+fn tagged(tag: u8, mut bytes: Vec<u8>) -> Vec<u8> {
+    bytes.insert(0, tag);
+    bytes
+}
+
+fn decompress(value: &[u8]) -> Vec<u8> {
+    let (tag, bytes) = value
+        .split_first()
+        .expect("Compressed value is empty, the database is corrupted");
+    let value = match *tag {
+        RAW_TAG => Ok(bytes.to_vec()),
+        LZ4_TAG => lz4::block::decompress(bytes, None),
+        ZSTD_TAG => zstd::decode_all(bytes),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unknown compression tag: {}", tag),
+        )),
+    };
+    value.expect("Unable to decompress the value, the database is corrupted")
+}
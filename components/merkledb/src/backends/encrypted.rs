@@ -0,0 +1,263 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of `EncryptedDB` database.
+
+use exonum_crypto::{
+    secretbox::{self, Key, SALT_LENGTH},
+    Hash, HashAlgorithm, HashStream, HASH_SIZE,
+};
+
+use std::{fmt, path::Path, sync::Arc};
+
+use crate::{
+    db::DB_METADATA, views::View, ColumnFamilySize, Database, Error, Iter, Iterator, Patch, Result,
+    Snapshot,
+};
+
+/// Name of the column family with the encryption parameters.
+const ENCRYPTION_METADATA: &str = "__ENCRYPTION__";
+/// Name of the column family with the index metadata.
+const INDEXES_POOL: &str = "__INDEXES_POOL__";
+/// Salt used to derive the encryption key from the passphrase.
+const SALT_NAME: &str = "salt";
+/// Known value encrypted with the key, used to check the passphrase on opening.
+const KEY_CHECK_NAME: &str = "key_check";
+const KEY_CHECK_VALUE: &[u8] = b"exonum-merkledb";
+
+/// Wrapper over a database backend which transparently encrypts stored values.
+///
+/// Values are encrypted with a key derived from the passphrase; keys, index names
+/// and column family names are stored as is. Each value is sealed together with
+/// the hash of its column family name and key, so a value moved to another key
+/// cannot be decrypted. Thus, the structure of the database
+/// and the keys of the indexes, such as the public keys of accounts, are not protected
+/// by the encryption. The salt for the key derivation and a value used to check the
+/// passphrase are kept in the database itself, so the same passphrase must be provided
+/// each time the database is opened.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{Database, EncryptedDB, ListIndex, TemporaryDB};
+///
+/// let db = EncryptedDB::new(Box::new(TemporaryDB::new()), b"passphrase").unwrap();
+/// let fork = db.fork();
+/// ListIndex::new("index", &fork).push(1_u8);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let index: ListIndex<_, u8> = ListIndex::new("index", &snapshot);
+/// assert_eq!(index.get(0), Some(1));
+/// ```
+pub struct EncryptedDB {
+    inner: Box<dyn Database>,
+    key: Arc<Key>,
+}
+
+/// A snapshot of an `EncryptedDB`.
+struct EncryptedSnapshot {
+    inner: Box<dyn Snapshot>,
+    key: Arc<Key>,
+}
+
+/// An iterator over the entries of an `EncryptedDB`.
+struct EncryptedIterator<'a> {
+    inner: Iter<'a>,
+    name: String,
+    key: Option<&'a Key>,
+    value: Vec<u8>,
+}
+
+impl EncryptedDB {
+    /// Wraps the database, encrypting its values with a key derived from the passphrase.
+    ///
+    /// If the database is opened with encryption for the first time, a new salt
+    /// is generated and saved to it.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the passphrase does not match the one the database
+    /// was encrypted with, or if the database already contains unencrypted data.
+    pub fn new(inner: Box<dyn Database>, passphrase: &[u8]) -> Result<Self> {
+        let snapshot = inner.snapshot();
+        let key = match snapshot.get(ENCRYPTION_METADATA, SALT_NAME.as_bytes()) {
+            Some(salt) => {
+                let key = Key::derive(passphrase, &Self::salt(&salt)?);
+                let key_check = snapshot
+                    .get(ENCRYPTION_METADATA, KEY_CHECK_NAME.as_bytes())
+                    .and_then(|value| secretbox::open(&value, &key));
+                if key_check.as_ref().map(Vec::as_slice) != Some(KEY_CHECK_VALUE) {
+                    return Err(Error::new("Wrong database encryption passphrase"));
+                }
+                key
+            }
+            None => {
+                if snapshot.iter(INDEXES_POOL, &[]).next().is_some() {
+                    return Err(Error::new("Database already contains unencrypted data"));
+                }
+                let salt = secretbox::gen_salt();
+                let key = Key::derive(passphrase, &salt);
+
+                let fork = inner.fork();
+                {
+                    let mut view = View::new(&fork, ENCRYPTION_METADATA);
+                    view.put(SALT_NAME, salt.to_vec());
+                    view.put(KEY_CHECK_NAME, secretbox::seal(KEY_CHECK_VALUE, &key));
+                }
+                inner.merge_sync(fork.into_patch())?;
+                key
+            }
+        };
+
+        Ok(Self {
+            inner,
+            key: Arc::new(key),
+        })
+    }
+
+    fn salt(value: &[u8]) -> Result<[u8; SALT_LENGTH]> {
+        if value.len() != SALT_LENGTH {
+            return Err(Error::new("Invalid database encryption salt"));
+        }
+        let mut salt = [0; SALT_LENGTH];
+        salt.copy_from_slice(value);
+        Ok(salt)
+    }
+
+    fn encrypt(&self, patch: Patch) -> Patch {
+        let key = &self.key;
+        patch.map_values(|name, entry_key, value| {
+            if is_encrypted(name) {
+                let mut data = entry_binding(name, entry_key).as_ref().to_vec();
+                data.extend_from_slice(&value);
+                secretbox::seal(&data, key)
+            } else {
+                value
+            }
+        })
+    }
+}
+
+impl Database for EncryptedDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        Box::new(EncryptedSnapshot {
+            inner: self.inner.snapshot(),
+            key: Arc::clone(&self.key),
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.inner.merge(self.encrypt(patch))
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.inner.merge_sync(self.encrypt(patch))
+    }
+
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        self.inner.create_checkpoint(path)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        Ok(Box::new(EncryptedSnapshot {
+            inner: self.inner.checkpoint_snapshot(path)?,
+            key: Arc::clone(&self.key),
+        }))
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner.approximate_sizes()
+    }
+}
+
+impl fmt::Debug for EncryptedDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EncryptedDB").finish()
+    }
+}
+
+impl Snapshot for EncryptedSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.inner.get(name, key)?;
+        if is_encrypted(name) {
+            Some(decrypt(name, key, &value, &self.key))
+        } else {
+            Some(value)
+        }
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        Box::new(EncryptedIterator {
+            inner: self.inner.iter(name, from),
+            name: name.to_owned(),
+            key: if is_encrypted(name) {
+                Some(&self.key)
+            } else {
+                None
+            },
+            value: Vec::new(),
+        })
+    }
+}
+
+impl EncryptedIterator<'_> {
+    fn decrypt<'a>(
+        entry: Option<(&'a [u8], &'a [u8])>,
+        name: &str,
+        key: Option<&Key>,
+        buffer: &'a mut Vec<u8>,
+    ) -> Option<(&'a [u8], &'a [u8])> {
+        let (k, v) = entry?;
+        match key {
+            Some(key) => {
+                *buffer = decrypt(name, k, v, key);
+                Some((k, buffer))
+            }
+            None => Some((k, v)),
+        }
+    }
+}
+
+impl Iterator for EncryptedIterator<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        Self::decrypt(self.inner.next(), &self.name, self.key, &mut self.value)
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        Self::decrypt(self.inner.peek(), &self.name, self.key, &mut self.value)
+    }
+}
+
+/// Returns `true` if values of the column family are encrypted.
+fn is_encrypted(name: &str) -> bool {
+    name != ENCRYPTION_METADATA && name != DB_METADATA
+}
+
+/// Returns the hash of the column family name and the key of the entry. The hash
+/// does not depend on the hash function selected for the blockchain.
+fn entry_binding(name: &str, key: &[u8]) -> Hash {
+    HashStream::with_algorithm(HashAlgorithm::Sha256)
+        .update(&(name.len() as u64).to_le_bytes())
+        .update(name.as_bytes())
+        .update(key)
+        .hash()
+}
+
+fn decrypt(name: &str, key: &[u8], value: &[u8], encryption_key: &Key) -> Vec<u8> {
+    secretbox::open(value, encryption_key)
+        .filter(|data| data.starts_with(entry_binding(name, key).as_ref()))
+        .map(|mut data| data.split_off(HASH_SIZE))
+        .expect("Unable to decrypt the value, the database is corrupted or tampered with")
+}
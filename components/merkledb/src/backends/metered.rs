@@ -0,0 +1,264 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of `MeteredDB` database.
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::{
+    fmt,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::{Change, ColumnFamilySize, Database, Iter, Iterator, Patch, Result, Snapshot};
+
+/// Counters of the storage operations.
+#[derive(Debug, Default)]
+struct Counters {
+    reads: AtomicU64,
+    read_bytes: AtomicU64,
+    iterated_entries: AtomicU64,
+    merges: AtomicU64,
+    failed_merges: AtomicU64,
+    last_merge_failed: AtomicBool,
+    written_entries: AtomicU64,
+    written_bytes: AtomicU64,
+    max_merge_bytes: AtomicU64,
+}
+
+/// Values of the storage counters since the database was opened.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct StorageMetricsInfo {
+    /// Number of values read by the key.
+    pub reads: u64,
+    /// Total size of the values read by the key in bytes.
+    pub read_bytes: u64,
+    /// Number of entries read by iterators.
+    pub iterated_entries: u64,
+    /// Number of merged patches.
+    pub merges: u64,
+    /// Number of patches which the database has failed to merge.
+    pub failed_merges: u64,
+    /// Number of entries put or deleted by the merged patches.
+    pub written_entries: u64,
+    /// Total size of the keys and values put by the merged patches in bytes.
+    pub written_bytes: u64,
+    /// Size of the largest merged patch in bytes.
+    pub max_merge_bytes: u64,
+}
+
+/// Shared counters of the storage operations performed with a `MeteredDB`.
+///
+/// Clones of the metrics share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct StorageMetrics(Arc<Counters>);
+
+impl StorageMetrics {
+    /// Returns the current values of the counters.
+    pub fn info(&self) -> StorageMetricsInfo {
+        let counters = &self.0;
+        StorageMetricsInfo {
+            reads: counters.reads.load(Ordering::Relaxed),
+            read_bytes: counters.read_bytes.load(Ordering::Relaxed),
+            iterated_entries: counters.iterated_entries.load(Ordering::Relaxed),
+            merges: counters.merges.load(Ordering::Relaxed),
+            failed_merges: counters.failed_merges.load(Ordering::Relaxed),
+            written_entries: counters.written_entries.load(Ordering::Relaxed),
+            written_bytes: counters.written_bytes.load(Ordering::Relaxed),
+            max_merge_bytes: counters.max_merge_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns `true` if the latest merge has failed, i.e., the database may not accept
+    /// writes. Unlike a test merge, the check does not touch the database.
+    pub fn last_merge_failed(&self) -> bool {
+        self.0.last_merge_failed.load(Ordering::Relaxed)
+    }
+
+    fn record_read(&self, value: Option<&Vec<u8>>) {
+        self.0.reads.fetch_add(1, Ordering::Relaxed);
+        if let Some(value) = value {
+            self.0
+                .read_bytes
+                .fetch_add(value.len() as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn record_merge(&self, patch: &Patch) {
+        let mut entries = 0;
+        let mut bytes = 0;
+        for (_, changes) in patch.iter() {
+            for (key, change) in changes.iter() {
+                entries += 1;
+                if let Change::Put(ref value) = *change {
+                    bytes += (key.len() + value.len()) as u64;
+                }
+            }
+        }
+
+        let counters = &self.0;
+        counters.merges.fetch_add(1, Ordering::Relaxed);
+        counters
+            .written_entries
+            .fetch_add(entries, Ordering::Relaxed);
+        counters.written_bytes.fetch_add(bytes, Ordering::Relaxed);
+        let mut max_bytes = counters.max_merge_bytes.load(Ordering::Relaxed);
+        while bytes > max_bytes {
+            match counters.max_merge_bytes.compare_exchange_weak(
+                max_bytes,
+                bytes,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => max_bytes = actual,
+            }
+        }
+    }
+
+    fn record_merge_result(&self, result: &Result<()>) {
+        if result.is_err() {
+            self.0.failed_merges.fetch_add(1, Ordering::Relaxed);
+        }
+        self.0
+            .last_merge_failed
+            .store(result.is_err(), Ordering::Relaxed);
+    }
+}
+
+/// Wrapper over a database backend which counts storage operations.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{Database, Entry, MeteredDB, TemporaryDB};
+///
+/// let db = MeteredDB::new(TemporaryDB::new());
+/// let fork = db.fork();
+/// Entry::new("entry", &fork).set(1_u8);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let info = db.metrics().info();
+/// assert_eq!(info.merges, 1);
+/// ```
+pub struct MeteredDB {
+    inner: Arc<dyn Database>,
+    metrics: StorageMetrics,
+}
+
+/// A snapshot of a `MeteredDB`.
+struct MeteredSnapshot {
+    inner: Box<dyn Snapshot>,
+    metrics: StorageMetrics,
+}
+
+/// An iterator over the entries of a `MeteredDB`.
+struct MeteredIterator<'a> {
+    inner: Iter<'a>,
+    metrics: &'a StorageMetrics,
+}
+
+impl MeteredDB {
+    /// Wraps the database, counting operations with it.
+    pub fn new<D: Into<Arc<dyn Database>>>(inner: D) -> Self {
+        Self {
+            inner: inner.into(),
+            metrics: StorageMetrics::default(),
+        }
+    }
+
+    /// Returns the counters of the storage operations.
+    pub fn metrics(&self) -> &StorageMetrics {
+        &self.metrics
+    }
+}
+
+impl Database for MeteredDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        Box::new(MeteredSnapshot {
+            inner: self.inner.snapshot(),
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.metrics.record_merge(&patch);
+        let result = self.inner.merge(patch);
+        self.metrics.record_merge_result(&result);
+        result
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.metrics.record_merge(&patch);
+        let result = self.inner.merge_sync(patch);
+        self.metrics.record_merge_result(&result);
+        result
+    }
+
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        self.inner.create_checkpoint(path)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        self.inner.checkpoint_snapshot(path)
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner.approximate_sizes()
+    }
+}
+
+impl fmt::Debug for MeteredDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MeteredDB")
+            .field("metrics", &self.metrics.info())
+            .finish()
+    }
+}
+
+impl Snapshot for MeteredSnapshot {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        let value = self.inner.get(name, key);
+        self.metrics.record_read(value.as_ref());
+        value
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        Box::new(MeteredIterator {
+            inner: self.inner.iter(name, from),
+            metrics: &self.metrics,
+        })
+    }
+}
+
+impl Iterator for MeteredIterator<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        let entry = self.inner.next();
+        if entry.is_some() {
+            self.metrics
+                .0
+                .iterated_entries
+                .fetch_add(1, Ordering::Relaxed);
+        }
+        entry
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.inner.peek()
+    }
+}
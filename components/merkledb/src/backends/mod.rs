@@ -12,5 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod cached;
+pub mod compressed;
+pub mod encrypted;
+pub mod metered;
+pub mod pipelined;
 pub mod rocksdb;
 pub mod temporarydb;
@@ -0,0 +1,258 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of `PipelinedDB` database.
+
+use std::{
+    collections::HashSet,
+    fmt, mem,
+    path::Path,
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread,
+};
+
+use crate::{
+    db::Change,
+    views::{index_data_prefix, index_identifier, INDEXES_POOL_NAME},
+    ColumnFamilySize, Database, Error, Patch, Result, Snapshot,
+};
+
+const LOCK_ERROR: &str = "Pipelined database lock is poisoned";
+const WRITER_ERROR: &str = "Secondary index writer has stopped";
+/// Name of the column family storing the data of the indexes.
+const INDEXES_DATA_NAME: &str = "";
+/// Length of the prefix of the index data keys.
+const DATA_PREFIX_LEN: usize = mem::size_of::<u64>();
+
+/// Wrapper over a database backend which writes the changes of the secondary indexes
+/// in a background thread.
+///
+/// Secondary indexes are specified by the prefixes of their names. `merge` hands
+/// the changes of the secondary indexes over to the writer thread and writes the rest
+/// of the patch itself, so that both parts are written in parallel. `merge` returns
+/// once both parts are written and fails if writing any of them fails, so the caller
+/// of the failed merge always receives the error.
+///
+/// The parts of the patch are not written atomically: a snapshot taken during
+/// the merge, or the database after a crash, may contain only one of them. Thus,
+/// only the indexes which are not read together with the other ones, such as
+/// the lookup indexes of the services, should be secondary. Patches creating
+/// secondary indexes are written as a whole.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{Database, ListIndex, PipelinedDB, TemporaryDB};
+///
+/// let db = PipelinedDB::new(Box::new(TemporaryDB::new()), vec!["lookup".to_owned()]);
+/// let fork = db.fork();
+/// ListIndex::new("lookup", &fork).push(1_u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let fork = db.fork();
+/// ListIndex::new("lookup", &fork).push(2_u64);
+/// ListIndex::new("index", &fork).push(3_u64);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let index: ListIndex<_, u64> = ListIndex::new("lookup", &snapshot);
+/// assert_eq!(index.get(1), Some(2));
+/// ```
+pub struct PipelinedDB {
+    inner: Arc<dyn Database>,
+    index_prefixes: Vec<String>,
+    /// The lock also orders the merges.
+    state: Mutex<PipelineState>,
+    writer: Option<thread::JoinHandle<()>>,
+}
+
+/// State of a `PipelinedDB` shared by the merges.
+struct PipelineState {
+    /// Prefixes of the data keys of the secondary indexes.
+    secondary: HashSet<Vec<u8>>,
+    sender: Option<mpsc::Sender<SecondaryWrite>>,
+}
+
+/// Changes of the secondary indexes passed to the writer thread.
+struct SecondaryWrite {
+    patch: Patch,
+    sync: bool,
+    result: mpsc::Sender<Result<()>>,
+}
+
+impl PipelinedDB {
+    /// Wraps the database, writing the changes of the indexes with the names starting
+    /// with `index_prefixes` in a background thread.
+    pub fn new(inner: Box<dyn Database>, index_prefixes: Vec<String>) -> Self {
+        let inner: Arc<dyn Database> = Arc::from(inner);
+        let (sender, receiver) = mpsc::channel::<SecondaryWrite>();
+        let mut state = PipelineState {
+            secondary: HashSet::new(),
+            sender: Some(sender),
+        };
+
+        let snapshot = inner.snapshot();
+        let mut indexes = snapshot.iter(INDEXES_POOL_NAME, &[]);
+        while let Some((index_name, metadata)) = indexes.next() {
+            state.assign(&index_prefixes, index_name, metadata);
+        }
+
+        let writer = {
+            let inner = Arc::clone(&inner);
+            thread::Builder::new()
+                .name("merkledb-writer".to_owned())
+                .spawn(move || {
+                    for write in receiver {
+                        let result = if write.sync {
+                            inner.merge_sync(write.patch)
+                        } else {
+                            inner.merge(write.patch)
+                        };
+                        // The merge waits for the result unless it has panicked.
+                        let _ = write.result.send(result);
+                    }
+                })
+                .expect("Unable to spawn the database writer thread")
+        };
+
+        Self {
+            inner,
+            index_prefixes,
+            state: Mutex::new(state),
+            writer: Some(writer),
+        }
+    }
+
+    fn write(&self, mut patch: Patch, sync: bool) -> Result<()> {
+        let mut state = self.state.lock().expect(LOCK_ERROR);
+        let mut created = false;
+        for (name, changes) in patch.iter() {
+            if name == INDEXES_POOL_NAME {
+                for (index_name, change) in changes.iter() {
+                    if let Change::Put(metadata) = change {
+                        created |= state.assign(&self.index_prefixes, index_name, metadata);
+                    }
+                }
+            }
+        }
+
+        // The backend assigns the column families to the new indexes while writing
+        // their metadata, so the data of the new indexes is written along with it.
+        let mut pending = None;
+        if !created {
+            let secondary = {
+                let state = &*state;
+                patch.split_off(INDEXES_DATA_NAME, self.inner.snapshot(), |key| {
+                    state.is_secondary(key)
+                })
+            };
+            if !secondary.is_empty() {
+                let (result, receiver) = mpsc::channel();
+                state
+                    .sender
+                    .as_ref()
+                    .expect("Pipelined database is closed")
+                    .send(SecondaryWrite {
+                        patch: secondary,
+                        sync,
+                        result,
+                    })
+                    .map_err(|_| Error::new(WRITER_ERROR))?;
+                pending = Some(receiver);
+            }
+        }
+
+        let result = if sync {
+            self.inner.merge_sync(patch)
+        } else {
+            self.inner.merge(patch)
+        };
+        let secondary_result = pending.map_or(Ok(()), |receiver| {
+            receiver
+                .recv()
+                .unwrap_or_else(|_| Err(Error::new(WRITER_ERROR)))
+        });
+        result.and(secondary_result)
+    }
+}
+
+impl Database for PipelinedDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        self.inner.snapshot()
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.write(patch, false)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.write(patch, true)
+    }
+
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        // The lock ensures that the checkpoint does not contain a part of a patch.
+        let _state = self.state.lock().expect(LOCK_ERROR);
+        self.inner.create_checkpoint(path)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        self.inner.checkpoint_snapshot(path)
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner.approximate_sizes()
+    }
+}
+
+impl Drop for PipelinedDB {
+    fn drop(&mut self) {
+        // Closing the channel stops the writer.
+        self.state
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .sender
+            .take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+impl fmt::Debug for PipelinedDB {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PipelinedDB")
+            .field("index_prefixes", &self.index_prefixes)
+            .finish()
+    }
+}
+
+impl PipelineState {
+    /// Marks the index as secondary if its name matches one of the prefixes. Returns
+    /// `true` if the index has not been secondary before.
+    fn assign(&mut self, index_prefixes: &[String], index_name: &[u8], metadata: &[u8]) -> bool {
+        let is_secondary = index_prefixes
+            .iter()
+            .any(|prefix| index_name.starts_with(prefix.as_bytes()));
+        match index_identifier(metadata) {
+            Some(identifier) if is_secondary => {
+                self.secondary.insert(index_data_prefix(identifier))
+            }
+            _ => false,
+        }
+    }
+
+    fn is_secondary(&self, key: &[u8]) -> bool {
+        key.len() >= DATA_PREFIX_LEN && self.secondary.contains(&key[..DATA_PREFIX_LEN])
+    }
+}
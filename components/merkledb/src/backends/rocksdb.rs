@@ -18,15 +18,41 @@
 
 pub use rocksdb::{BlockBasedOptions as RocksBlockOptions, WriteOptions as RocksDBWriteOptions};
 
-use std::{fmt, iter::Peekable, mem, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt,
+    iter::Peekable,
+    mem,
+    path::Path,
+    sync::{Arc, RwLock},
+};
 
-use rocksdb::{self, ColumnFamily, DBIterator, Options as RocksDbOptions, WriteBatch};
+use rocksdb::{
+    self, checkpoint::Checkpoint, ColumnFamily, ColumnFamilyDescriptor, DBCompactionStyle,
+    DBIterator, IteratorMode, Options as RocksDbOptions, WriteBatch,
+};
 
 use crate::{
     db::{check_database, Change},
-    Database, DbOptions, Iter, Iterator, Patch, Snapshot,
+    views::{index_data_prefix, index_identifier, INDEXES_POOL_NAME},
+    BinaryValue, ColumnFamilyOptions, ColumnFamilySize, CompactionStyle, Database, DbOptions, Iter,
+    Iterator, Patch, Snapshot,
 };
 
+/// Name of the column family storing the data of the indexes, which are not assigned
+/// to the column families configured in `DbOptions`.
+const INDEXES_DATA_NAME: &str = "";
+/// Name of the column family storing the column families assigned to the indexes.
+const COLUMN_FAMILIES_NAME: &str = "__COLUMN_FAMILIES__";
+/// Prefix of the names of the column families configured in `DbOptions`, which prevents
+/// clashes with the names of other column families.
+const COLUMN_FAMILY_PREFIX: &str = "__CF__.";
+/// Length of the prefix of the index data keys.
+const DATA_PREFIX_LEN: usize = mem::size_of::<u64>();
+
+/// Column families assigned to the indexes, keyed by the prefix of the index data.
+type Routes = Arc<RwLock<HashMap<Vec<u8>, String>>>;
+
 /// Database implementation on top of [`RocksDB`](https://rocksdb.org)
 /// backend.
 ///
@@ -35,6 +61,8 @@ use crate::{
 /// use different databases.
 pub struct RocksDB {
     db: Arc<rocksdb::DB>,
+    options: DbOptions,
+    routes: Routes,
 }
 
 impl From<DbOptions> for RocksDbOptions {
@@ -52,10 +80,31 @@ impl From<&DbOptions> for RocksDbOptions {
     }
 }
 
+impl From<&ColumnFamilyOptions> for RocksDbOptions {
+    fn from(opts: &ColumnFamilyOptions) -> Self {
+        let mut defaults = Self::default();
+        if let Some(size) = opts.block_cache_size {
+            let mut block_opts = RocksBlockOptions::default();
+            block_opts.set_lru_cache(size);
+            defaults.set_block_based_table_factory(&block_opts);
+        }
+        if let Some(size) = opts.write_buffer_size {
+            defaults.set_write_buffer_size(size);
+        }
+        defaults.set_compaction_style(match opts.compaction_style {
+            CompactionStyle::Level => DBCompactionStyle::Level,
+            CompactionStyle::Universal => DBCompactionStyle::Universal,
+            CompactionStyle::Fifo => DBCompactionStyle::Fifo,
+        });
+        defaults
+    }
+}
+
 /// A snapshot of a `RocksDB`.
 pub struct RocksDBSnapshot {
     snapshot: rocksdb::Snapshot<'static>,
     db: Arc<rocksdb::DB>,
+    routes: Routes,
 }
 
 /// An iterator over the entries of a `RocksDB`.
@@ -74,14 +123,24 @@ impl RocksDB {
     pub fn open<P: AsRef<Path>>(path: P, options: &DbOptions) -> crate::Result<Self> {
         let inner = {
             if let Ok(names) = rocksdb::DB::list_cf(&RocksDbOptions::default(), &path) {
-                let cf_names = names.iter().map(String::as_str).collect::<Vec<_>>();
-                rocksdb::DB::open_cf(&options.into(), path, cf_names)?
+                let descriptors = names
+                    .iter()
+                    .map(|name| {
+                        let cf_options = column_family_options(options, name)
+                            .unwrap_or_else(RocksDbOptions::default);
+                        ColumnFamilyDescriptor::new(name.as_str(), cf_options)
+                    })
+                    .collect::<Vec<_>>();
+                rocksdb::DB::open_cf_descriptors(&options.into(), path, descriptors)?
             } else {
                 rocksdb::DB::open(&options.into(), path)?
             }
         };
+        let routes = load_routes(&inner)?;
         let mut db = Self {
             db: Arc::new(inner),
+            options: options.clone(),
+            routes: Arc::new(RwLock::new(routes)),
         };
         check_database(&mut db)?;
         Ok(db)
@@ -89,27 +148,109 @@ impl RocksDB {
 
     fn do_merge(&self, patch: Patch, w_opts: &RocksDBWriteOptions) -> crate::Result<()> {
         let mut batch = WriteBatch::default();
-        for (cf_name, changes) in patch {
-            let cf = match self.db.cf_handle(&cf_name) {
-                Some(cf) => cf,
-                None => self
-                    .db
-                    .create_cf(&cf_name, &DbOptions::default().into())
-                    .unwrap(),
-            };
-
-            for prefix in changes.prefixes_to_remove() {
-                self.remove_with_prefix(&mut batch, cf, &cf_name, prefix)?;
+        let new_routes = self.assign_column_families(&patch);
+        // The assignment of the column families is written directly, e.g., when the state
+        // is imported from a snapshot.
+        let reload_routes = patch.iter().any(|(name, _)| *name == COLUMN_FAMILIES_NAME);
+        if !new_routes.is_empty() {
+            let routes_cf = self.cf_handle_or_create(COLUMN_FAMILIES_NAME)?;
+            for (prefix, cf_name) in &new_routes {
+                batch.put_cf(routes_cf, prefix, cf_name.as_bytes())?;
             }
+        }
+
+        {
+            let routes = self.routes.read().expect("Routes lock is poisoned");
+            for (cf_name, changes) in patch {
+                let cf = self.cf_handle_or_create(&cf_name)?;
+                // Data of the indexes is stored in the column families assigned to them.
+                let route = |key: &[u8]| -> crate::Result<ColumnFamily> {
+                    if cf_name == INDEXES_DATA_NAME && key.len() >= DATA_PREFIX_LEN {
+                        let prefix = &key[..DATA_PREFIX_LEN];
+                        if let Some(name) = new_routes.get(prefix).or_else(|| routes.get(prefix)) {
+                            return self.cf_handle_or_create(name);
+                        }
+                    }
+                    Ok(cf)
+                };
 
-            for (key, change) in changes {
-                match change {
-                    Change::Put(ref value) => batch.put_cf(cf, key, value)?,
-                    Change::Delete => batch.delete_cf(cf, &key)?,
+                for prefix in changes.prefixes_to_remove() {
+                    self.remove_with_prefix(&mut batch, route(prefix)?, &cf_name, prefix)?;
+                }
+
+                for (key, change) in changes {
+                    let cf = route(&key)?;
+                    match change {
+                        Change::Put(ref value) => batch.put_cf(cf, &key, value)?,
+                        Change::Delete => batch.delete_cf(cf, &key)?,
+                    }
                 }
             }
         }
-        self.db.write_opt(batch, w_opts).map_err(Into::into)
+        self.db.write_opt(batch, w_opts)?;
+
+        let mut routes = self.routes.write().expect("Routes lock is poisoned");
+        if reload_routes {
+            *routes = load_routes(&self.db)?;
+        } else {
+            routes.extend(new_routes);
+        }
+        Ok(())
+    }
+
+    /// Assigns the indexes created by the patch to the column families configured
+    /// in the options. Returns the prefixes of the data of the assigned indexes together
+    /// with the names of the column families.
+    fn assign_column_families(&self, patch: &Patch) -> HashMap<Vec<u8>, String> {
+        let mut new_routes = HashMap::new();
+        if self.options.column_families.is_empty() {
+            return new_routes;
+        }
+        let pool_changes = match patch.iter().find(|(name, _)| *name == INDEXES_POOL_NAME) {
+            Some((_, changes)) => changes,
+            None => return new_routes,
+        };
+
+        // New indexes increase the length of the pool, which is stored with the empty key
+        // and is used as the identifier of the next index.
+        let new_len = match pool_changes.iter().find(|(key, _)| key.is_empty()) {
+            Some((_, Change::Put(value))) => pool_len(value),
+            _ => None,
+        };
+        let new_len = match new_len {
+            Some(len) => len,
+            // Either no indexes are created, or the pool is encrypted.
+            None => return new_routes,
+        };
+        let old_len = self
+            .snapshot()
+            .get(INDEXES_POOL_NAME, &[])
+            .and_then(|value| pool_len(&value))
+            .unwrap_or_default();
+
+        for (index_name, change) in pool_changes.iter() {
+            let metadata = match change {
+                Change::Put(metadata) if !index_name.is_empty() => metadata,
+                _ => continue,
+            };
+            let identifier = match index_identifier(metadata) {
+                Some(identifier) if identifier >= old_len && identifier < new_len => identifier,
+                _ => continue,
+            };
+            if let Some(cf) = self.options.column_family_for(index_name) {
+                new_routes.insert(index_data_prefix(identifier), physical_name(&cf.name));
+            }
+        }
+        new_routes
+    }
+
+    fn cf_handle_or_create(&self, cf_name: &str) -> crate::Result<ColumnFamily> {
+        if let Some(cf) = self.db.cf_handle(cf_name) {
+            return Ok(cf);
+        }
+        let cf_options = column_family_options(&self.options, cf_name)
+            .unwrap_or_else(|| DbOptions::default().into());
+        self.db.create_cf(cf_name, &cf_options).map_err(Into::into)
     }
 
     // Removes all keys with a specified prefix from a column family.
@@ -134,11 +275,49 @@ impl RocksDB {
     }
 }
 
+/// Returns the name of the column family configured in `DbOptions` as it is stored
+/// in the database.
+fn physical_name(name: &str) -> String {
+    [COLUMN_FAMILY_PREFIX, name].concat()
+}
+
+/// Returns options of the column family if it is configured in `DbOptions`.
+fn column_family_options(options: &DbOptions, cf_name: &str) -> Option<RocksDbOptions> {
+    if !cf_name.starts_with(COLUMN_FAMILY_PREFIX) {
+        return None;
+    }
+    options
+        .column_families
+        .iter()
+        .find(|cf| cf_name[COLUMN_FAMILY_PREFIX.len()..] == *cf.name)
+        .map(Into::into)
+}
+
+fn pool_len(value: &[u8]) -> Option<u64> {
+    if value.len() == mem::size_of::<u64>() {
+        u64::from_bytes(value.into()).ok()
+    } else {
+        None
+    }
+}
+
+fn load_routes(db: &rocksdb::DB) -> crate::Result<HashMap<Vec<u8>, String>> {
+    let mut routes = HashMap::new();
+    if let Some(cf) = db.cf_handle(COLUMN_FAMILIES_NAME) {
+        for (prefix, cf_name) in db.iterator_cf(cf, IteratorMode::Start)? {
+            let cf_name = String::from_utf8_lossy(&cf_name).into_owned();
+            routes.insert(prefix.into_vec(), cf_name);
+        }
+    }
+    Ok(routes)
+}
+
 impl Database for RocksDB {
     fn snapshot(&self) -> Box<dyn Snapshot> {
         Box::new(RocksDBSnapshot {
             snapshot: unsafe { mem::transmute(self.db.snapshot()) },
             db: Arc::clone(&self.db),
+            routes: Arc::clone(&self.routes),
         })
     }
 
@@ -152,11 +331,58 @@ impl Database for RocksDB {
         w_opts.set_sync(true);
         self.do_merge(patch, &w_opts)
     }
+
+    fn create_checkpoint(&self, path: &Path) -> crate::Result<()> {
+        let checkpoint = Checkpoint::new(&self.db)?;
+        checkpoint.create_checkpoint(path).map_err(Into::into)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> crate::Result<Box<dyn Snapshot>> {
+        Ok(Self::open(path, &self.options)?.snapshot())
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        // The default column family is listed as well, although it is not used.
+        let names =
+            rocksdb::DB::list_cf(&RocksDbOptions::default(), self.db.path()).unwrap_or_default();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let cf = self.db.cf_handle(&name)?;
+                let property = |property| {
+                    self.db
+                        .property_int_value_cf(cf, property)
+                        .ok()
+                        .and_then(|value| value)
+                        .unwrap_or_default()
+                };
+                Some(ColumnFamilySize {
+                    entries: property("rocksdb.estimate-num-keys"),
+                    size: property("rocksdb.estimate-live-data-size"),
+                    name,
+                })
+            })
+            .collect()
+    }
+}
+
+impl RocksDBSnapshot {
+    /// Returns the column family storing the key of the given column family, taking
+    /// into account the column families assigned to the indexes.
+    fn cf_handle(&self, name: &str, key: &[u8]) -> Option<ColumnFamily> {
+        if name == INDEXES_DATA_NAME && key.len() >= DATA_PREFIX_LEN {
+            let routes = self.routes.read().expect("Routes lock is poisoned");
+            if let Some(cf_name) = routes.get(&key[..DATA_PREFIX_LEN]) {
+                return self.db.cf_handle(cf_name);
+            }
+        }
+        self.db.cf_handle(name)
+    }
 }
 
 impl Snapshot for RocksDBSnapshot {
     fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
-        if let Some(cf) = self.db.cf_handle(name) {
+        if let Some(cf) = self.cf_handle(name, key) {
             match self.snapshot.get_cf(cf, key) {
                 Ok(value) => value.map(|v| v.to_vec()),
                 Err(e) => panic!(e),
@@ -167,8 +393,8 @@ impl Snapshot for RocksDBSnapshot {
     }
 
     fn iter<'a>(&'a self, name: &str, from: &[u8]) -> Iter<'a> {
-        use rocksdb::{Direction, IteratorMode};
-        let iter = match self.db.cf_handle(name) {
+        use rocksdb::Direction;
+        let iter = match self.cf_handle(name, from) {
             Some(cf) => self
                 .snapshot
                 .iterator_cf(cf, IteratorMode::From(from, Direction::Forward))
@@ -17,8 +17,8 @@
 use tempfile::TempDir;
 
 use super::rocksdb::RocksDB;
-use crate::{Database, DbOptions, Patch, Result, Snapshot};
-use std::sync::Arc;
+use crate::{ColumnFamilySize, Database, DbOptions, Patch, Result, Snapshot};
+use std::{path::Path, sync::Arc};
 
 /// Wrapper over the `RocksDB` backend which stores data in the temporary directory
 /// using the `tempfile` crate.
@@ -53,6 +53,18 @@ impl Database for TemporaryDB {
     fn merge_sync(&self, patch: Patch) -> Result<()> {
         self.inner.merge_sync(patch)
     }
+
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        self.inner.create_checkpoint(path)
+    }
+
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        self.inner.checkpoint_snapshot(path)
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner.approximate_sizes()
+    }
 }
 
 impl Default for TemporaryDB {
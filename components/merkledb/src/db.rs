@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use serde_derive::{Deserialize, Serialize};
+
 use std::{
     cell::RefCell,
     cmp::Ordering::{Equal, Greater, Less},
@@ -25,10 +27,15 @@ use std::{
     iter::{Iterator as StdIterator, Peekable},
     mem,
     ops::{Deref, DerefMut},
+    path::Path,
+    sync::Arc,
 };
 
 use crate::{
-    views::{IndexAccess, IndexAddress, View},
+    views::{
+        in_namespace, index_data_prefix, index_data_stats, index_identifier, IndexAccess,
+        IndexAddress, View, INDEXES_POOL_NAME,
+    },
     Error, Result,
 };
 
@@ -93,9 +100,38 @@ impl Changes {
     pub fn prefixes_to_remove(&self) -> &[Vec<u8>] {
         &self.prefixes_to_remove
     }
+
+    /// Moves the changes of the keys accepted by the predicate into separate changes.
+    /// Removed prefixes are checked by the predicate in the same way as keys.
+    fn split_off<F>(&mut self, predicate: F) -> Self
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        let mut other = Self::new();
+        let (moved, kept) = mem::replace(&mut self.prefixes_to_remove, Vec::new())
+            .into_iter()
+            .partition(|prefix| predicate(prefix));
+        self.prefixes_to_remove = kept;
+        other.prefixes_to_remove = moved;
+        let (moved, kept) = mem::replace(&mut self.data, BTreeMap::new())
+            .into_iter()
+            .partition(|(key, _)| predicate(key));
+        self.data = kept;
+        other.data = moved;
+        other
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty() && self.prefixes_to_remove.is_empty()
+    }
 }
 
 impl ViewChanges {
+    /// Returns an iterator over the changes starting from the specified key.
+    fn iter_from(&self, from: &[u8]) -> BtmRange<Vec<u8>, Change> {
+        self.data.range::<[u8], _>((Included(from), Unbounded))
+    }
+
     fn new() -> Self {
         Self {
             data: BTreeMap::new(),
@@ -354,6 +390,72 @@ pub enum Change {
 pub struct Fork {
     patch: Patch,
     working_patch: WorkingPatch,
+    write_log: Option<WriteLog>,
+    namespaces: Option<Vec<String>>,
+}
+
+/// A key written by several writers of a fork.
+///
+/// Write conflicts are reported by the [`write_conflicts`] method of `Fork` if tracking
+/// of writes has been enabled with [`track_writes`].
+///
+/// [`write_conflicts`]: struct.Fork.html#method.write_conflicts
+/// [`track_writes`]: struct.Fork.html#method.track_writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteConflict {
+    /// Name of the column family containing the key.
+    pub name: String,
+    /// Key within the column family. If an index has been cleared, the key is the prefix
+    /// of the index keys.
+    pub key: Vec<u8>,
+    /// Writers of the key in the order of their first write.
+    pub writers: Vec<String>,
+}
+
+/// Writers of the keys changed in a fork.
+#[derive(Debug, Default)]
+struct WriteLog {
+    writers: BTreeMap<(String, Vec<u8>), Vec<String>>,
+}
+
+impl WriteLog {
+    fn record(&mut self, working_patch: &WorkingPatch, writer: &str) {
+        for (address, changes) in working_patch.changes.borrow().iter() {
+            let changes = match changes {
+                Some(changes) => changes,
+                // Changes are still borrowed; `flush` will panic anyway.
+                None => continue,
+            };
+
+            if changes.is_empty() {
+                let prefix = address.bytes().map_or_else(Vec::new, <[u8]>::to_vec);
+                self.record_key(address.name(), prefix, writer);
+            }
+            for key in changes.data.keys() {
+                let full_key = address.keyed(key).1.into_owned();
+                self.record_key(address.name(), full_key, writer);
+            }
+        }
+    }
+
+    fn record_key(&mut self, name: &str, key: Vec<u8>, writer: &str) {
+        let writers = self.writers.entry((name.to_owned(), key)).or_default();
+        if !writers.iter().any(|w| w == writer) {
+            writers.push(writer.to_owned());
+        }
+    }
+
+    fn conflicts(&self) -> Vec<WriteConflict> {
+        self.writers
+            .iter()
+            .filter(|(_, writers)| writers.len() > 1)
+            .map(|((name, key), writers)| WriteConflict {
+                name: name.clone(),
+                key: key.clone(),
+                writers: writers.clone(),
+            })
+            .collect()
+    }
 }
 
 /// A set of serial changes that should be applied to a storage atomically.
@@ -427,6 +529,8 @@ pub trait Database: Send + Sync + 'static {
                 changes: HashMap::new(),
             },
             working_patch: WorkingPatch::new(),
+            write_log: None,
+            namespaces: None,
         }
     }
 
@@ -453,6 +557,57 @@ pub trait Database: Send + Sync + 'static {
     /// will be returned. In case of an error, the method guarantees no changes are applied to
     /// the database.
     fn merge_sync(&self, patch: Patch) -> Result<()>;
+
+    /// Creates a consistent point-in-time checkpoint of the database at the specified path.
+    ///
+    /// The checkpoint can be opened as a separate database. The default implementation
+    /// returns an error, as not every backend supports checkpoints.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the backend does not support checkpoints, the path
+    /// already exists or an I/O error occurs while creating the checkpoint.
+    fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        let _ = path;
+        Err(Error::new("Checkpoints are not supported by the database"))
+    }
+
+    /// Opens the checkpoint created by `create_checkpoint` at the specified path and
+    /// returns its snapshot. The data of the checkpoint is read in the same way as
+    /// the data of the database, e.g., it is decrypted if the database is encrypted.
+    ///
+    /// The checkpoint stays open while the snapshot is used. The default implementation
+    /// returns an error, as not every backend supports checkpoints.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the backend does not support checkpoints or the checkpoint
+    /// cannot be opened.
+    fn checkpoint_snapshot(&self, path: &Path) -> Result<Box<dyn Snapshot>> {
+        let _ = path;
+        Err(Error::new("Checkpoints are not supported by the database"))
+    }
+
+    /// Returns the approximate sizes of the column families of the database. The sizes
+    /// are estimated by the backend without reading the data, so they are cheap to obtain
+    /// even for large databases, unlike `indexes_stats`.
+    ///
+    /// The default implementation returns an empty list, as not every backend can
+    /// estimate the sizes.
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        Vec::new()
+    }
+}
+
+/// Approximate size of a column family of the database, as estimated by the backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColumnFamilySize {
+    /// Name of the column family as it is stored in the database.
+    pub name: String,
+    /// Estimated number of entries in the column family.
+    pub entries: u64,
+    /// Estimated size of the live data of the column family in bytes.
+    pub size: u64,
 }
 
 /// A read-only snapshot of a storage backend.
@@ -501,6 +656,61 @@ impl Patch {
     pub fn iter(&self) -> HmIter<String, Changes> {
         self.changes.iter()
     }
+
+    /// Moves the changes of the keys accepted by the predicate from the specified
+    /// table into a separate patch based on the provided snapshot.
+    pub(crate) fn split_off<F>(
+        &mut self,
+        name: &str,
+        snapshot: Box<dyn Snapshot>,
+        predicate: F,
+    ) -> Self
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        let mut changes = HashMap::new();
+        if let Some(table_changes) = self.changes.get_mut(name) {
+            let moved = table_changes.split_off(predicate);
+            if !moved.is_empty() {
+                changes.insert(name.to_owned(), moved);
+            }
+        }
+        Self { snapshot, changes }
+    }
+
+    /// Returns `true` if the patch contains no changes.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.changes.values().all(Changes::is_empty)
+    }
+
+    /// Transforms the values put into the storage by the patch, leaving deletions intact.
+    /// The transformation receives the column family name, the key and the value.
+    pub(crate) fn map_values<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(&str, &[u8], Vec<u8>) -> Vec<u8>,
+    {
+        let changes = self
+            .changes
+            .into_iter()
+            .map(|(name, mut changes)| {
+                changes.data = mem::replace(&mut changes.data, BTreeMap::new())
+                    .into_iter()
+                    .map(|(key, change)| match change {
+                        Change::Put(value) => {
+                            let value = f(&name, &key, value);
+                            (key, Change::Put(value))
+                        }
+                        Change::Delete => (key, Change::Delete),
+                    })
+                    .collect();
+                (name, changes)
+            })
+            .collect();
+        Self {
+            snapshot: self.snapshot,
+            changes,
+        }
+    }
 }
 
 impl Snapshot for Patch {
@@ -551,6 +761,114 @@ impl Fork {
         working_patch.merge_into(&mut self.patch);
     }
 
+    /// Finalizes the changes like [`flush`] and attributes them to the given writer,
+    /// e.g., a transaction, if tracking of writes is enabled. Changes finalized by `flush`
+    /// are not attributed to any writer.
+    ///
+    /// [`flush`]: #method.flush
+    pub fn flush_as(&mut self, writer: &str) {
+        if let Some(write_log) = self.write_log.as_mut() {
+            write_log.record(&self.working_patch, writer);
+        }
+        self.flush();
+    }
+
+    /// Enables tracking of writes. With tracking enabled, the fork records
+    /// the writers of the changed keys passed to [`flush_as`], which allows detecting
+    /// keys changed by several writers with [`write_conflicts`].
+    ///
+    /// Tracking of writes is intended for debugging, since it keeps all the changed
+    /// keys in memory.
+    ///
+    /// [`flush_as`]: #method.flush_as
+    /// [`write_conflicts`]: #method.write_conflicts
+    pub fn track_writes(&mut self) {
+        if self.write_log.is_none() {
+            self.write_log = Some(WriteLog::default());
+        }
+    }
+
+    /// Returns the keys changed by more than one writer, ordered by the column family name
+    /// and the key. Returns an empty list if tracking of writes is disabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{Database, Entry, TemporaryDB};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let mut fork = db.fork();
+    /// fork.track_writes();
+    ///
+    /// Entry::new("entry", &fork).set(1_u64);
+    /// fork.flush_as("first");
+    /// Entry::new("entry", &fork).set(2_u64);
+    /// fork.flush_as("second");
+    ///
+    /// let conflicts = fork.write_conflicts();
+    /// assert_eq!(conflicts.len(), 1);
+    /// assert_eq!(conflicts[0].writers, vec!["first", "second"]);
+    /// ```
+    pub fn write_conflicts(&self) -> Vec<WriteConflict> {
+        self.write_log
+            .as_ref()
+            .map_or_else(Vec::new, WriteLog::conflicts)
+    }
+
+    /// Restricts access to the indexes of the fork to the given namespaces. An index
+    /// belongs to a namespace if its name is equal to the namespace or starts with the namespace
+    /// followed by a dot, e.g., the index `wallets.history` belongs to the namespace `wallets`.
+    /// Creating an index outside of the allowed namespaces from the fork panics.
+    ///
+    /// The indexes of other namespaces may still be read with the [`Snapshot`] returned
+    /// by `as_ref`. Note that changes of the fork which were not flushed yet are not visible
+    /// through this snapshot.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{Database, Entry, TemporaryDB};
+    /// use std::panic::{self, AssertUnwindSafe};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let mut fork = db.fork();
+    /// Entry::new("other.entry", &fork).set(1_u64);
+    /// fork.flush();
+    ///
+    /// fork.restrict_to_namespaces(vec!["wallets"]);
+    /// Entry::new("wallets.entry", &fork).set(2_u64);
+    /// // Reads from other namespaces require explicit access through the snapshot.
+    /// let entry: Entry<_, u64> = Entry::new("other.entry", fork.as_ref());
+    /// assert_eq!(entry.get(), Some(1));
+    ///
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     Entry::new("other.entry", &fork).set(3_u64);
+    /// }));
+    /// assert!(result.is_err());
+    /// ```
+    ///
+    /// [`Snapshot`]: trait.Snapshot.html
+    pub fn restrict_to_namespaces<I, S>(&mut self, namespaces: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.namespaces = Some(namespaces.into_iter().map(Into::into).collect());
+    }
+
+    /// Lifts the restriction set by [`restrict_to_namespaces`].
+    ///
+    /// [`restrict_to_namespaces`]: #method.restrict_to_namespaces
+    pub fn lift_namespace_restriction(&mut self) {
+        self.namespaces = None;
+    }
+
+    /// Returns the namespaces the access to the indexes of the fork is restricted to,
+    /// or `None` if the access is not restricted.
+    pub fn allowed_namespaces(&self) -> Option<&[String]> {
+        self.namespaces.as_ref().map(Vec::as_slice)
+    }
+
     /// Rolls back all changes that were made after the latest execution
     /// of the `flush` method.
     pub fn rollback(&mut self) {
@@ -563,10 +881,61 @@ impl Fork {
         self.patch
     }
 
-    /// Merges a patch from another fork to this fork.
+    /// Creates a child fork on top of the shared `snapshot`.
+    ///
+    /// The snapshot is neither copied nor merged into the database, so any number of cheap
+    /// child forks can be created from the same snapshot, e.g., to execute transactions
+    /// speculatively. The snapshot may itself be a frozen fork, see [`into_snapshot`].
+    /// Changes of a child fork are not visible to its siblings, and its patch contains
+    /// only its own changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{Database, Entry, Fork, TemporaryDB};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// Entry::new("entry", &fork).set(1_u64);
+    /// // The parent changes are not merged into the database.
+    /// let parent = fork.into_snapshot();
+    ///
+    /// let child = Fork::from_snapshot(parent.clone());
+    /// Entry::new("entry", &child).set(2_u64);
+    /// let other_child = Fork::from_snapshot(parent.clone());
+    /// let entry: Entry<_, u64> = Entry::new("entry", &other_child);
+    /// assert_eq!(entry.get(), Some(1));
+    /// ```
+    ///
+    /// [`into_snapshot`]: #method.into_snapshot
+    pub fn from_snapshot(snapshot: Arc<dyn Snapshot>) -> Self {
+        Self {
+            patch: Patch {
+                snapshot: Box::new(snapshot),
+                changes: HashMap::new(),
+            },
+            working_patch: WorkingPatch::new(),
+            write_log: None,
+            namespaces: None,
+        }
+    }
+
+    /// Converts the fork into a read-only snapshot consuming the fork instance.
+    /// The snapshot can be shared by the child forks created with [`from_snapshot`].
+    ///
+    /// [`from_snapshot`]: #method.from_snapshot
+    pub fn into_snapshot(self) -> Arc<dyn Snapshot> {
+        Arc::new(self.into_patch())
+    }
+
+    /// Merges a patch from another fork to this fork. Changes of the patch, including
+    /// the cleared indexes, are applied on top of the changes of this fork.
     ///
     /// If both forks have changed the same data, this can lead to an inconsistent state. Hence,
     /// this method is useful only if you are sure that forks interacted with different indices.
+    /// Note that new indexes created by both forks conflict as well, see [`indexes_count`].
+    ///
+    /// [`indexes_count`]: fn.indexes_count.html
     ///
     /// # Panics
     ///
@@ -576,7 +945,7 @@ impl Fork {
 
         for (name, changes) in patch {
             if let Some(in_changes) = self.patch.changes.get_mut(&name) {
-                in_changes.data.extend(changes.into_iter());
+                in_changes.apply(&changes);
                 continue;
             }
 
@@ -591,6 +960,97 @@ impl Fork {
         !self.working_patch.is_empty()
     }
 
+    /// Returns the change of the size of the namespace made by the unflushed changes
+    /// of the fork, in bytes. The size is measured in the same way as by [`namespace_size`],
+    /// but only the changed entries of the namespace indexes are read, so the size
+    /// of the namespace after the changes are flushed can be computed without iterating
+    /// over the namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{namespace_size, Database, Entry, TemporaryDB};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let mut fork = db.fork();
+    /// Entry::new("wallets.entry", &fork).set(1_u8);
+    /// // 8-byte index identifier followed by a 1-byte value.
+    /// assert_eq!(fork.unflushed_namespace_size_change("wallets"), 9);
+    /// fork.flush();
+    /// assert_eq!(fork.unflushed_namespace_size_change("wallets"), 0);
+    /// assert_eq!(namespace_size(fork.as_ref(), "wallets"), 9);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index of the fork is borrowed.
+    ///
+    /// [`namespace_size`]: fn.namespace_size.html
+    pub fn unflushed_namespace_size_change(&self, namespace: &str) -> i64 {
+        let snapshot: &dyn Snapshot = &self.patch;
+        let changes = self.working_patch.changes.borrow();
+        let namespace = namespace.as_bytes();
+
+        // Indexes created after the latest flush are taken into account as well.
+        let mut identifiers = Vec::new();
+        let mut pool = snapshot.iter(INDEXES_POOL_NAME, namespace);
+        while let Some((index_name, metadata)) = pool.next() {
+            if !index_name.starts_with(namespace) {
+                break;
+            }
+            if in_namespace(index_name, namespace) {
+                identifiers.extend(index_identifier(metadata));
+            }
+        }
+        if let Some(pool_changes) = changes.get(&IndexAddress::with_root(INDEXES_POOL_NAME)) {
+            let pool_changes = pool_changes.as_ref().expect("indexes pool is borrowed");
+            for (index_name, change) in pool_changes.iter_from(namespace) {
+                if !index_name.starts_with(namespace) {
+                    break;
+                }
+                if let Change::Put(metadata) = change {
+                    if in_namespace(index_name, namespace) {
+                        identifiers.extend(index_identifier(metadata));
+                    }
+                }
+            }
+        }
+        identifiers.sort();
+        identifiers.dedup();
+
+        let mut size_change = 0;
+        for identifier in identifiers {
+            let address = IndexAddress::new().append_bytes(&identifier);
+            let index_changes = match changes.get(&address) {
+                Some(index_changes) => index_changes
+                    .as_ref()
+                    .unwrap_or_else(|| panic!("index is borrowed at {:?}", address)),
+                None => continue,
+            };
+            let prefix = index_data_prefix(identifier);
+            if index_changes.is_empty() {
+                // The index is cleared, so all its entries are replaced by the changes.
+                size_change -= index_data_stats(snapshot, identifier).1 as i64;
+            }
+            for (key, change) in &index_changes.data {
+                let key = [prefix.as_slice(), key.as_slice()].concat();
+                let old_size = if index_changes.is_empty() {
+                    0
+                } else {
+                    snapshot
+                        .get("", &key)
+                        .map_or(0, |value| (key.len() + value.len()) as i64)
+                };
+                let new_size = match change {
+                    Change::Put(value) => (key.len() + value.len()) as i64,
+                    Change::Delete => 0,
+                };
+                size_change += new_size - old_size;
+            }
+        }
+        size_change
+    }
+
     /// Patch containing current changes made in this fork.
     pub fn working_patch(&self) -> &WorkingPatch {
         &self.working_patch
@@ -607,6 +1067,8 @@ impl From<Patch> for Fork {
         Self {
             patch,
             working_patch: WorkingPatch::new(),
+            write_log: None,
+            namespaces: None,
         }
     }
 }
@@ -621,6 +1083,23 @@ impl<'a> IndexAccess for &'a Fork {
     fn changes(&self, address: &IndexAddress) -> Self::Changes {
         self.working_patch.changes_mut(address)
     }
+
+    fn is_accessible(&self, index_name: &str) -> bool {
+        self.namespaces.as_ref().map_or(true, |namespaces| {
+            namespaces
+                .iter()
+                .any(|namespace| is_in_namespace(index_name, namespace))
+        })
+    }
+}
+
+/// Checks whether the index with the given name belongs to the `namespace`.
+fn is_in_namespace(index_name: &str, namespace: &str) -> bool {
+    index_name.starts_with(namespace)
+        && index_name[namespace.len()..]
+            .chars()
+            .next()
+            .map_or(true, |c| c == '.')
 }
 
 impl AsRef<dyn Snapshot> for Fork {
@@ -629,6 +1108,20 @@ impl AsRef<dyn Snapshot> for Fork {
     }
 }
 
+impl Snapshot for Arc<dyn Snapshot> {
+    fn get(&self, name: &str, key: &[u8]) -> Option<Vec<u8>> {
+        (**self).get(name, key)
+    }
+
+    fn contains(&self, name: &str, key: &[u8]) -> bool {
+        (**self).contains(name, key)
+    }
+
+    fn iter(&self, name: &str, from: &[u8]) -> Iter {
+        (**self).iter(name, from)
+    }
+}
+
 impl AsRef<dyn Snapshot> for dyn Snapshot + 'static {
     fn as_ref(&self) -> &dyn Snapshot {
         self
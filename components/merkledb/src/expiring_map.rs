@@ -0,0 +1,238 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a map with expiring entries.
+//!
+//! Each entry of `ExpiringMap` carries the height at which it expires. Expired entries
+//! are hidden by the getters and are removed from the storage by the [`purge_expired`]
+//! method, which should be invoked with the height of the block being created,
+//! for example, in the `before_commit` handler of a service. Since the purging depends
+//! only on the height, all validators remove the same entries and get the same state.
+//!
+//! [`purge_expired`]: struct.ExpiringMap.html#method.purge_expired
+
+use std::{borrow::Borrow, mem};
+
+use crate::{
+    key_set_index::KeySetIndex, map_index::MapIndex, views::IndexAccess, BinaryKey, BinaryValue,
+};
+
+/// A map of keys and values, in which each entry expires at the specified height.
+///
+/// An entry put with the expiration height `h` is available at the heights below `h`.
+/// Besides the map itself, `ExpiringMap` stores the expiration heights of the entries
+/// in the `{name}.expirations` index, and the queue of the entries ordered
+/// by the expiration height in the `{name}.expiration_queue` index.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{Database, ExpiringMap, TemporaryDB};
+///
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// let mut tokens = ExpiringMap::new("tokens", &fork);
+///
+/// tokens.put(&1_u64, "one-time token".to_owned(), 10);
+/// assert!(tokens.contains(&1_u64, 9));
+/// assert_eq!(tokens.get(&1_u64, 10), None);
+///
+/// assert_eq!(tokens.purge_expired(10), 1);
+/// assert_eq!(tokens.expiration_height(&1_u64), None);
+/// ```
+#[derive(Debug)]
+pub struct ExpiringMap<T: IndexAccess, K, V> {
+    values: MapIndex<T, K, V>,
+    expirations: MapIndex<T, K, u64>,
+    queue: KeySetIndex<T, Vec<u8>>,
+}
+
+impl<T, K, V> ExpiringMap<T, K, V>
+where
+    T: IndexAccess,
+    K: BinaryKey,
+    V: BinaryValue,
+{
+    /// Creates a new index representation based on the name and storage view.
+    ///
+    /// Storage view can be specified as [`&Snapshot`] or [`&mut Fork`]. In the first case, only
+    /// immutable methods are available. In the second case, both immutable and mutable methods are
+    /// available.
+    ///
+    /// [`&Snapshot`]: ../trait.Snapshot.html
+    /// [`&mut Fork`]: ../struct.Fork.html
+    pub fn new<S: Into<String>>(index_name: S, access: T) -> Self {
+        let name = index_name.into();
+        Self {
+            expirations: MapIndex::new(format!("{}.expirations", name), access.clone()),
+            queue: KeySetIndex::new(format!("{}.expiration_queue", name), access.clone()),
+            values: MapIndex::new(name, access),
+        }
+    }
+
+    /// Returns a value corresponding to the key if the entry has not expired
+    /// at the given height.
+    pub fn get<Q>(&self, key: &Q, height: u64) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        if self.is_expired(key, height) {
+            None
+        } else {
+            self.values.get(key)
+        }
+    }
+
+    /// Returns `true` if the map contains an entry for the specified key,
+    /// which has not expired at the given height.
+    pub fn contains<Q>(&self, key: &Q, height: u64) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        !self.is_expired(key, height) && self.values.contains(key)
+    }
+
+    /// Returns the expiration height of the entry for the specified key. Entries
+    /// which have expired, but have not been purged yet, are taken into account.
+    pub fn expiration_height<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        self.expirations.get(key)
+    }
+
+    /// Inserts an entry expiring at the given height into the map. If the map
+    /// already contains an entry for the key, its value and expiration height are replaced.
+    pub fn put(&mut self, key: &K, value: V, expiration_height: u64) {
+        if let Some(old_height) = self.expirations.get(key) {
+            self.queue.remove(&concat_keys!(old_height, key));
+        }
+        self.queue.insert(concat_keys!(expiration_height, key));
+        self.expirations.put(key, expiration_height);
+        self.values.put(key, value);
+    }
+
+    /// Removes an entry from the map.
+    pub fn remove(&mut self, key: &K) {
+        if let Some(height) = self.expirations.get(key) {
+            self.queue.remove(&concat_keys!(height, key));
+            self.expirations.remove(key);
+            self.values.remove(key);
+        }
+    }
+
+    /// Removes the entries which have expired at the given height, and returns the number
+    /// of the removed entries.
+    pub fn purge_expired(&mut self, height: u64) -> usize {
+        let height_size = mem::size_of::<u64>();
+        let expired = self
+            .queue
+            .iter()
+            .take_while(|entry| u64::read(&entry[..height_size]) <= height)
+            .collect::<Vec<_>>();
+
+        for entry in &expired {
+            let owned_key = K::read(&entry[height_size..]);
+            let key: &K = owned_key.borrow();
+            self.expirations.remove(key);
+            self.values.remove(key);
+            self.queue.remove(entry);
+        }
+        expired.len()
+    }
+
+    /// Clears the map, removing all entries.
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.expirations.clear();
+        self.queue.clear();
+    }
+
+    fn is_expired<Q>(&self, key: &Q, height: u64) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        self.expirations
+            .get(key)
+            .map_or(false, |expiration_height| expiration_height <= height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, TemporaryDB};
+
+    const IDX_NAME: &str = "tokens";
+
+    #[test]
+    fn test_expiration() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut index = ExpiringMap::new(IDX_NAME, &fork);
+
+        index.put(&1_u8, 10_u64, 5);
+        index.put(&2_u8, 20_u64, 3);
+        index.put(&3_u8, 30_u64, 7);
+        assert_eq!(index.get(&1_u8, 4), Some(10));
+        assert_eq!(index.get(&1_u8, 5), None);
+        assert!(index.contains(&2_u8, 2));
+        assert!(!index.contains(&2_u8, 3));
+        assert_eq!(index.expiration_height(&3_u8), Some(7));
+
+        // Replacing the entry updates its expiration height.
+        index.put(&2_u8, 21_u64, 6);
+        assert_eq!(index.get(&2_u8, 5), Some(21));
+
+        assert_eq!(index.purge_expired(4), 0);
+        assert_eq!(index.purge_expired(6), 2);
+        assert_eq!(index.expiration_height(&1_u8), None);
+        assert_eq!(index.expiration_height(&2_u8), None);
+        assert_eq!(index.get(&3_u8, 6), Some(30));
+
+        index.remove(&3_u8);
+        assert_eq!(index.get(&3_u8, 0), None);
+        assert_eq!(index.purge_expired(u64::max_value()), 0);
+    }
+
+    #[test]
+    fn test_purge_persistence() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        {
+            let mut index = ExpiringMap::new(IDX_NAME, &fork);
+            for i in 0..10_u8 {
+                index.put(&i, u64::from(i), u64::from(10 - i));
+            }
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        {
+            let mut index: ExpiringMap<_, u8, u64> = ExpiringMap::new(IDX_NAME, &fork);
+            assert_eq!(index.purge_expired(5), 5);
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let index: ExpiringMap<_, u8, u64> = ExpiringMap::new(IDX_NAME, &snapshot);
+        assert_eq!(index.get(&4_u8, 5), Some(4));
+        assert_eq!(index.get(&5_u8, 0), None);
+        assert_eq!(index.expiration_height(&5_u8), None);
+    }
+}
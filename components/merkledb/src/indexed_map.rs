@@ -0,0 +1,401 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a map with automatically maintained secondary indexes.
+//!
+//! `IndexedMap` is a [`MapIndex`] that keeps a set of derived indexes consistent with
+//! its entries. Secondary indexes are described declaratively by implementing
+//! the [`SecondaryIndex`] trait, and are updated whenever an entry of the map is
+//! inserted, replaced or removed within the same fork.
+//!
+//! [`MapIndex`]: ../map_index/struct.MapIndex.html
+//! [`SecondaryIndex`]: trait.SecondaryIndex.html
+
+use std::{borrow::Borrow, fmt, marker::PhantomData};
+
+use crate::{
+    key_set_index::KeySetIndex,
+    map_index::{MapIndex, MapIndexIter, MapIndexKeys, MapIndexValues},
+    views::IndexAccess,
+    BinaryKey, BinaryValue,
+};
+
+/// A description of a secondary index derived from the entries of an [`IndexedMap`].
+///
+/// Each entry of the map may have any number of secondary keys, including none.
+/// The secondary index maps every secondary key to the keys of the map entries
+/// it has been derived from.
+///
+/// [`IndexedMap`]: struct.IndexedMap.html
+pub trait SecondaryIndex<K, V> {
+    /// Type of the secondary keys.
+    type Key: BinaryKey;
+
+    /// Name of the secondary index. The index is stored under the name of the map
+    /// followed by a dot and this name, hence the name should satisfy the same
+    /// requirements as index names.
+    const NAME: &'static str;
+
+    /// Returns the secondary keys of the map entry.
+    fn secondary_keys(key: &K, value: &V) -> Vec<Self::Key>;
+}
+
+/// A set of secondary indexes maintained by an [`IndexedMap`].
+///
+/// This trait is implemented for tuples of up to four [`SecondaryIndex`] types.
+///
+/// [`IndexedMap`]: struct.IndexedMap.html
+/// [`SecondaryIndex`]: trait.SecondaryIndex.html
+pub trait SecondaryIndexes<K, V> {
+    /// Adds the entry of the map to the secondary indexes.
+    fn insert_entry<T: IndexAccess>(access: &T, name: &str, key: &K, value: &V);
+
+    /// Removes the entry of the map from the secondary indexes.
+    fn remove_entry<T: IndexAccess>(access: &T, name: &str, key: &K, value: &V);
+
+    /// Removes all entries from the secondary indexes.
+    fn clear<T: IndexAccess>(access: &T, name: &str);
+}
+
+macro_rules! impl_secondary_indexes {
+    ($($index:ident),+) => {
+        impl<K, V, $($index),+> SecondaryIndexes<K, V> for ($($index,)+)
+        where
+            K: BinaryKey,
+            $($index: SecondaryIndex<K, V>,)+
+        {
+            fn insert_entry<T: IndexAccess>(access: &T, name: &str, key: &K, value: &V) {
+                $({
+                    let mut index = secondary_index::<T, K, V, $index>(access, name);
+                    let secondary_keys =
+                        <$index as SecondaryIndex<K, V>>::secondary_keys(key, value);
+                    for secondary_key in secondary_keys {
+                        index.insert(secondary_entry(&secondary_key, key));
+                    }
+                })+
+            }
+
+            fn remove_entry<T: IndexAccess>(access: &T, name: &str, key: &K, value: &V) {
+                $({
+                    let mut index = secondary_index::<T, K, V, $index>(access, name);
+                    let secondary_keys =
+                        <$index as SecondaryIndex<K, V>>::secondary_keys(key, value);
+                    for secondary_key in secondary_keys {
+                        index.remove(&secondary_entry(&secondary_key, key));
+                    }
+                })+
+            }
+
+            fn clear<T: IndexAccess>(access: &T, name: &str) {
+                $(
+                    secondary_index::<T, K, V, $index>(access, name).clear();
+                )+
+            }
+        }
+    };
+}
+
+impl_secondary_indexes!(A);
+impl_secondary_indexes!(A, B);
+impl_secondary_indexes!(A, B, C);
+impl_secondary_indexes!(A, B, C, D);
+
+/// Returns the set of the entries of the secondary index. Each entry is a secondary key
+/// prefixed by its length followed by the key of the map.
+fn secondary_index<T, K, V, I>(access: &T, name: &str) -> KeySetIndex<T, Vec<u8>>
+where
+    T: IndexAccess,
+    I: SecondaryIndex<K, V>,
+{
+    KeySetIndex::new(format!("{}.{}", name, I::NAME), access.clone())
+}
+
+fn secondary_prefix<Q: BinaryKey + ?Sized>(secondary_key: &Q) -> Vec<u8> {
+    let len = secondary_key.size() as u32;
+    concat_keys!(len, secondary_key)
+}
+
+fn secondary_entry<Q, K>(secondary_key: &Q, key: &K) -> Vec<u8>
+where
+    Q: BinaryKey + ?Sized,
+    K: BinaryKey,
+{
+    let len = secondary_key.size() as u32;
+    concat_keys!(len, secondary_key, key)
+}
+
+/// A map of keys and values with automatically maintained secondary indexes.
+///
+/// `IndexedMap` requires that keys implement the [`BinaryKey`] trait and values implement
+/// the [`BinaryValue`] trait. Secondary indexes are specified as a tuple of types
+/// implementing the [`SecondaryIndex`] trait.
+///
+/// [`BinaryKey`]: ../trait.BinaryKey.html
+/// [`BinaryValue`]: ../trait.BinaryValue.html
+/// [`SecondaryIndex`]: trait.SecondaryIndex.html
+///
+/// # Examples
+///
+/// ```
+/// use exonum_merkledb::{Database, IndexedMap, SecondaryIndex, TemporaryDB};
+///
+/// // Index of the wallets by the name of their owner.
+/// struct ByOwner;
+///
+/// impl SecondaryIndex<u64, String> for ByOwner {
+///     type Key = String;
+///     const NAME: &'static str = "by_owner";
+///
+///     fn secondary_keys(_id: &u64, owner: &String) -> Vec<String> {
+///         vec![owner.clone()]
+///     }
+/// }
+///
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// let mut wallets: IndexedMap<_, u64, String, (ByOwner,)> = IndexedMap::new("wallets", &fork);
+///
+/// wallets.put(&1, "Alice".to_owned());
+/// wallets.put(&2, "Bob".to_owned());
+/// wallets.put(&3, "Alice".to_owned());
+/// assert_eq!(wallets.find::<ByOwner, _>("Alice"), vec![1, 3]);
+///
+/// wallets.put(&3, "Bob".to_owned());
+/// assert_eq!(wallets.find::<ByOwner, _>("Alice"), vec![1]);
+/// assert_eq!(wallets.find::<ByOwner, _>("Bob"), vec![2, 3]);
+/// ```
+pub struct IndexedMap<T: IndexAccess, K, V, S> {
+    access: T,
+    name: String,
+    primary: MapIndex<T, K, V>,
+    _s: PhantomData<S>,
+}
+
+impl<T, K, V, S> fmt::Debug for IndexedMap<T, K, V, S>
+where
+    T: IndexAccess,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IndexedMap")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl<T, K, V, S> IndexedMap<T, K, V, S>
+where
+    T: IndexAccess,
+    K: BinaryKey,
+    V: BinaryValue,
+    S: SecondaryIndexes<K, V>,
+{
+    /// Creates a new index representation based on the name and storage view.
+    ///
+    /// Storage view can be specified as [`&Snapshot`] or [`&mut Fork`]. In the first case, only
+    /// immutable methods are available. In the second case, both immutable and mutable methods are
+    /// available.
+    ///
+    /// [`&Snapshot`]: ../trait.Snapshot.html
+    /// [`&mut Fork`]: ../struct.Fork.html
+    pub fn new<N: Into<String>>(index_name: N, access: T) -> Self {
+        let name = index_name.into();
+        Self {
+            primary: MapIndex::new(name.clone(), access.clone()),
+            access,
+            name,
+            _s: PhantomData,
+        }
+    }
+
+    /// Returns a value corresponding to the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        self.primary.get(key)
+    }
+
+    /// Returns `true` if the map contains a value corresponding to the specified key.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        self.primary.contains(key)
+    }
+
+    /// Returns an iterator over the entries of the map in ascending order.
+    pub fn iter(&self) -> MapIndexIter<K, V> {
+        self.primary.iter()
+    }
+
+    /// Returns an iterator over the keys of the map in ascending order.
+    pub fn keys(&self) -> MapIndexKeys<K> {
+        self.primary.keys()
+    }
+
+    /// Returns an iterator over the values of the map in ascending order of the keys.
+    pub fn values(&self) -> MapIndexValues<V> {
+        self.primary.values()
+    }
+
+    /// Returns the keys of the map entries with the specified secondary key
+    /// in the secondary index `I`, in ascending order.
+    ///
+    /// `I` should be one of the secondary indexes of the map; otherwise, nothing is found.
+    pub fn find<I, Q>(&self, secondary_key: &Q) -> Vec<K::Owned>
+    where
+        I: SecondaryIndex<K, V>,
+        I::Key: Borrow<Q>,
+        Q: BinaryKey + ?Sized,
+    {
+        let prefix = secondary_prefix(secondary_key);
+        secondary_index::<T, K, V, I>(&self.access, &self.name)
+            .iter_from(&prefix)
+            .take_while(|entry| entry.starts_with(&prefix))
+            .map(|entry| K::read(&entry[prefix.len()..]))
+            .collect()
+    }
+
+    /// Inserts a key-value pair into the map, updating the secondary indexes.
+    pub fn put(&mut self, key: &K, value: V) {
+        if let Some(old_value) = self.primary.get(key) {
+            S::remove_entry(&self.access, &self.name, key, &old_value);
+        }
+        S::insert_entry(&self.access, &self.name, key, &value);
+        self.primary.put(key, value);
+    }
+
+    /// Removes a key from the map, updating the secondary indexes.
+    pub fn remove(&mut self, key: &K) {
+        if let Some(old_value) = self.primary.get(key) {
+            S::remove_entry(&self.access, &self.name, key, &old_value);
+            self.primary.remove(key);
+        }
+    }
+
+    /// Clears the map and its secondary indexes, removing all entries.
+    pub fn clear(&mut self) {
+        S::clear(&self.access, &self.name);
+        self.primary.clear();
+    }
+
+    /// Rebuilds the secondary indexes from the entries of the map.
+    ///
+    /// This method should be called after a secondary index has been added
+    /// to an existing map, or after its `secondary_keys` have been changed.
+    pub fn rebuild_secondary_indexes(&mut self) {
+        S::clear(&self.access, &self.name);
+        for (key, value) in self.primary.iter() {
+            S::insert_entry(&self.access, &self.name, key.borrow(), &value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, TemporaryDB};
+
+    const IDX_NAME: &str = "wallets";
+
+    struct ByOwner;
+
+    impl SecondaryIndex<u8, String> for ByOwner {
+        type Key = String;
+        const NAME: &'static str = "by_owner";
+
+        fn secondary_keys(_key: &u8, value: &String) -> Vec<String> {
+            vec![value.clone()]
+        }
+    }
+
+    struct ByLetter;
+
+    impl SecondaryIndex<u8, String> for ByLetter {
+        type Key = u8;
+        const NAME: &'static str = "by_letter";
+
+        fn secondary_keys(_key: &u8, value: &String) -> Vec<u8> {
+            value.bytes().collect()
+        }
+    }
+
+    type Wallets<T> = IndexedMap<T, u8, String, (ByOwner, ByLetter)>;
+
+    #[test]
+    fn test_secondary_indexes() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut index: Wallets<_> = IndexedMap::new(IDX_NAME, &fork);
+
+        index.put(&1, "ab".to_owned());
+        index.put(&2, "b".to_owned());
+        index.put(&3, "ab".to_owned());
+        assert_eq!(index.find::<ByOwner, _>("ab"), vec![1, 3]);
+        assert_eq!(index.find::<ByOwner, _>("a"), Vec::<u8>::new());
+        assert_eq!(index.find::<ByLetter, _>(&b'a'), vec![1, 3]);
+        assert_eq!(index.find::<ByLetter, _>(&b'b'), vec![1, 2, 3]);
+
+        index.put(&1, "c".to_owned());
+        assert_eq!(index.find::<ByOwner, _>("ab"), vec![3]);
+        assert_eq!(index.find::<ByOwner, _>("c"), vec![1]);
+        assert_eq!(index.find::<ByLetter, _>(&b'b'), vec![2, 3]);
+
+        index.remove(&3);
+        assert_eq!(index.find::<ByOwner, _>("ab"), Vec::<u8>::new());
+        assert_eq!(index.find::<ByLetter, _>(&b'a'), Vec::<u8>::new());
+        assert_eq!(index.get(&3), None);
+
+        index.clear();
+        assert_eq!(index.find::<ByOwner, _>("c"), Vec::<u8>::new());
+        assert_eq!(index.find::<ByLetter, _>(&b'b'), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_secondary_indexes_persistence() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        {
+            let mut index: Wallets<_> = IndexedMap::new(IDX_NAME, &fork);
+            index.put(&1, "ab".to_owned());
+            index.put(&2, "b".to_owned());
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let index: Wallets<_> = IndexedMap::new(IDX_NAME, &snapshot);
+        assert_eq!(index.find::<ByOwner, _>("b"), vec![2]);
+        assert_eq!(index.find::<ByLetter, _>(&b'b'), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_rebuild_secondary_indexes() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        {
+            let mut index: IndexedMap<_, u8, String, (ByOwner,)> = IndexedMap::new(IDX_NAME, &fork);
+            index.put(&1, "ab".to_owned());
+            index.put(&2, "b".to_owned());
+        }
+
+        let mut index: Wallets<_> = IndexedMap::new(IDX_NAME, &fork);
+        assert_eq!(index.find::<ByLetter, _>(&b'b'), Vec::<u8>::new());
+
+        index.rebuild_secondary_indexes();
+        assert_eq!(index.find::<ByOwner, _>("ab"), vec![1]);
+        assert_eq!(index.find::<ByLetter, _>(&b'b'), vec![1, 2]);
+    }
+}
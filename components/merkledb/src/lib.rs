@@ -24,7 +24,10 @@
 //! that is, the Exonum process has exclusive access to the DB during blockchain operation.
 //! You can interact with the `Database` from multiple threads by cloning its instance.
 //!
-//! Exonum provides two database types: [`RocksDB`] and [`TemporaryDB`].
+//! Exonum provides two database types: [`RocksDB`] and [`TemporaryDB`]. Any of them
+//! can be wrapped into [`EncryptedDB`] to encrypt the stored values, and into [`CompressedDB`]
+//! to compress them. [`PipelinedDB`] writes the secondary indexes in a background thread,
+//! and [`CachedDB`] keeps recently read entries of the frequently read indexes in memory.
 //!
 //! # Snapshot and Fork
 //!
@@ -78,10 +81,18 @@
 //!   proofs of existence and is implemented as a binary Merkle Patricia tree.
 //! - [`KeySetIndex`] and [`ValueSetIndex`] are sets of items, similar to [`BTreeSet`] and
 //!   [`HashSet`] accordingly.
+//! - [`IndexedMap`] is a `MapIndex` with secondary indexes, which are derived from its entries
+//!   and updated automatically.
+//! - [`ExpiringMap`] is a map, in which entries expire at the specified height and are purged
+//!   deterministically.
 //!
 //! [`Database`]: trait.Database.html
 //! [`RocksDB`]: struct.RocksDB.html
 //! [`TemporaryDB`]: struct.TemporaryDB.html
+//! [`EncryptedDB`]: struct.EncryptedDB.html
+//! [`CompressedDB`]: struct.CompressedDB.html
+//! [`PipelinedDB`]: struct.PipelinedDB.html
+//! [`CachedDB`]: struct.CachedDB.html
 //! [`Snapshot`]: trait.Snapshot.html
 //! [`Fork`]: struct.Fork.html
 //! [`Patch`]: struct.Patch.html
@@ -98,6 +109,8 @@
 //! [`ProofMapIndex`]: proof_map_index/struct.ProofMapIndex.html
 //! [`KeySetIndex`]: key_set_index/struct.KeySetIndex.html
 //! [`ValueSetIndex`]: value_set_index/struct.ValueSetIndex.html
+//! [`IndexedMap`]: indexed_map/struct.IndexedMap.html
+//! [`ExpiringMap`]: expiring_map/struct.ExpiringMap.html
 //! [doc:storage]: https://exonum.com/doc/architecture/storage
 //! [`Option`]: https://doc.rust-lang.org/std/option/enum.Option.html
 //! [`Box`]: https://doc.rust-lang.org/std/boxed/struct.Box.html
@@ -129,24 +142,41 @@
 #[doc(no_inline)]
 pub use self::proof_map_index::{MapProof, ProofMapIndex};
 pub use self::{
-    backends::{rocksdb::RocksDB, temporarydb::TemporaryDB},
+    backends::{
+        cached::CachedDB,
+        compressed::CompressedDB,
+        encrypted::EncryptedDB,
+        metered::{MeteredDB, StorageMetrics, StorageMetricsInfo},
+        pipelined::PipelinedDB,
+        rocksdb::RocksDB,
+        temporarydb::TemporaryDB,
+    },
     db::{
-        Change, Changes, ChangesIterator, Database, Fork, Iter, Iterator, Patch, PatchIterator,
-        Snapshot,
+        Change, Changes, ChangesIterator, ColumnFamilySize, Database, Fork, Iter, Iterator, Patch,
+        PatchIterator, Snapshot, WriteConflict,
     },
     entry::Entry,
     error::Error,
+    expiring_map::ExpiringMap,
     hash::{root_hash, HashTag, ObjectHash},
+    indexed_map::{IndexedMap, SecondaryIndex, SecondaryIndexes},
     key_set_index::KeySetIndex,
     keys::BinaryKey,
     list_index::ListIndex,
     map_index::MapIndex,
-    options::DbOptions,
+    options::{
+        CacheOptions, ColumnFamilyOptions, CompactionStyle, CompressionAlgorithm,
+        CompressionOptions, DbOptions,
+    },
     proof_list_index::{ListProof, ProofListIndex, ProofOfAbsence},
     sparse_list_index::SparseListIndex,
     value_set_index::ValueSetIndex,
     values::BinaryValue,
-    views::{IndexAccess, IndexAddress, IndexBuilder, ObjectAccess, Ref, RefMut, View},
+    views::{
+        index_type, indexes_count, indexes_info, indexes_stats, namespace_size, IndexAccess,
+        IndexAddress, IndexBuilder, IndexInfo, IndexStats, IndexType, ObjectAccess, Ref, RefMut,
+        View,
+    },
 };
 
 #[macro_use]
@@ -167,6 +197,8 @@ mod options;
 mod values;
 mod views;
 
+pub mod expiring_map;
+pub mod indexed_map;
 pub mod key_set_index;
 pub mod list_index;
 pub mod map_index;
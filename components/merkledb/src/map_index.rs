@@ -401,6 +401,103 @@ where
         }
     }
 
+    /// Returns an iterator over the entries of a map in ascending order, which keys start
+    /// with the specified prefix. The iterator element type is (K, V).
+    ///
+    /// The prefix is compared with the binary representation of the keys, so the iteration
+    /// is efficient only if the keys sharing the prefix are stored next to each other,
+    /// e.g., for composite keys starting with the same component.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let name = "name";
+    /// let fork = db.fork();
+    /// let mut index = MapIndex::new(name, &fork);
+    /// index.put(&vec![1_u8, 1], 1_u8);
+    /// index.put(&vec![1_u8, 2], 2_u8);
+    /// index.put(&vec![2_u8, 1], 3_u8);
+    ///
+    /// let values = index.iter_prefix(&[1_u8][..]).map(|(_, v)| v).collect::<Vec<_>>();
+    /// assert_eq!(values, vec![1, 2]);
+    /// ```
+    pub fn iter_prefix<P>(&self, prefix: &P) -> MapIndexIter<K, V>
+    where
+        P: BinaryKey + ?Sized,
+    {
+        MapIndexIter {
+            base_iter: self.base.iter(prefix),
+        }
+    }
+
+    /// Returns an iterator over the keys of a map in ascending order, which start
+    /// with the specified prefix. The iterator element type is K.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let name = "name";
+    /// let snapshot = db.snapshot();
+    /// let index: MapIndex<_, Vec<u8>, u8> = MapIndex::new(name, &snapshot);
+    ///
+    /// for key in index.keys_prefix(&[1_u8][..]) {
+    ///     println!("{:?}", key);
+    /// }
+    /// ```
+    pub fn keys_prefix<P>(&self, prefix: &P) -> MapIndexKeys<K>
+    where
+        P: BinaryKey + ?Sized,
+    {
+        MapIndexKeys {
+            base_iter: self.base.iter(prefix),
+        }
+    }
+
+    /// Returns an iterator over the entries of a map in ascending order, which keys start
+    /// with the specified prefix, seeking to the specified key. The iterator element type
+    /// is (K, V).
+    ///
+    /// If `from` precedes all keys with the prefix, the iteration starts from the first
+    /// of them; if `from` follows them, the iterator is empty. This allows to continue
+    /// a paginated range query from the last returned key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let name = "name";
+    /// let fork = db.fork();
+    /// let mut index = MapIndex::new(name, &fork);
+    /// index.put(&vec![1_u8, 1], 1_u8);
+    /// index.put(&vec![1_u8, 2], 2_u8);
+    /// index.put(&vec![1_u8, 3], 3_u8);
+    /// index.put(&vec![2_u8, 1], 4_u8);
+    ///
+    /// let values = index
+    ///     .iter_prefix_from(&[1_u8][..], &[1_u8, 2][..])
+    ///     .map(|(_, v)| v)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(values, vec![2, 3]);
+    /// ```
+    pub fn iter_prefix_from<P, Q>(&self, prefix: &P, from: &Q) -> MapIndexIter<K, V>
+    where
+        K: Borrow<Q>,
+        P: BinaryKey + ?Sized,
+        Q: BinaryKey + ?Sized,
+    {
+        MapIndexIter {
+            base_iter: self.base.iter_from(prefix, from),
+        }
+    }
+
     /// Inserts a key-value pair into a map.
     ///
     /// # Examples
@@ -588,6 +685,46 @@ mod tests {
         assert!(!map_index.contains(&3_u8));
     }
 
+    #[test]
+    fn test_iter_prefix() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut map_index = MapIndex::new(IDX_NAME, &fork);
+
+        map_index.put(&vec![0_u8, 1], 0_u8);
+        map_index.put(&vec![1_u8], 1_u8);
+        map_index.put(&vec![1_u8, 1], 2_u8);
+        map_index.put(&vec![1_u8, 2], 3_u8);
+        map_index.put(&vec![2_u8, 0], 4_u8);
+
+        let prefix: &[u8] = &[1];
+        assert_eq!(
+            map_index.iter_prefix(prefix).collect::<Vec<_>>(),
+            vec![(vec![1], 1), (vec![1, 1], 2), (vec![1, 2], 3)]
+        );
+        assert_eq!(
+            map_index.keys_prefix(prefix).collect::<Vec<_>>(),
+            vec![vec![1], vec![1, 1], vec![1, 2]]
+        );
+        assert_eq!(
+            map_index
+                .iter_prefix(&[3_u8][..])
+                .collect::<Vec<(Vec<u8>, u8)>>(),
+            vec![]
+        );
+
+        let values = |from: &[u8]| {
+            map_index
+                .iter_prefix_from(prefix, from)
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(&[0, 5]), vec![1, 2, 3]);
+        assert_eq!(values(&[1, 1]), vec![2, 3]);
+        assert_eq!(values(&[1, 1, 0]), vec![3]);
+        assert_eq!(values(&[2]), Vec::<u8>::new());
+    }
+
     #[test]
     fn test_iter() {
         let db = TemporaryDB::default();
@@ -19,7 +19,7 @@ use serde_derive::{Deserialize, Serialize};
 /// Options for the database.
 ///
 /// These parameters apply to the underlying database of Exonum, currently `RocksDB`.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct DbOptions {
     /// Number of open files that can be used by the database.
     ///
@@ -40,6 +40,19 @@ pub struct DbOptions {
     ///
     /// Defaults to `true`.
     pub create_if_missing: bool,
+    /// Column families storing the data of the indexes with the specified name prefixes.
+    ///
+    /// Indexes are assigned to a column family when they are created, and keep
+    /// the assignment afterwards even if the options change. The data of the indexes
+    /// not matching any prefix, as well as the indexes created before the column family
+    /// has been configured, is stored in the common column family.
+    ///
+    /// Column families are not applied to databases wrapped in `EncryptedDB`, since
+    /// the index metadata is encrypted; the data of all the indexes of such databases
+    /// is stored in the common column family. Exonum nodes refuse to open an encrypted
+    /// database with column families configured. Defaults to an empty list.
+    #[serde(default)]
+    pub column_families: Vec<ColumnFamilyOptions>,
 }
 
 impl Default for DbOptions {
@@ -47,6 +60,143 @@ impl Default for DbOptions {
         Self {
             max_open_files: None,
             create_if_missing: true,
+            column_families: Vec::new(),
+        }
+    }
+}
+
+impl DbOptions {
+    /// Returns options of the column family which stores the data of the index
+    /// with the given fully qualified name.
+    pub(crate) fn column_family_for(&self, index_name: &[u8]) -> Option<&ColumnFamilyOptions> {
+        self.column_families.iter().find(|cf| {
+            cf.index_prefixes
+                .iter()
+                .any(|prefix| index_name.starts_with(prefix.as_bytes()))
+        })
+    }
+}
+
+/// Options of a column family for a class of indexes, e.g., blocks, transactions
+/// or caches.
+///
+/// Column families have separate memtables, caches and compaction settings, so
+/// the indexes of one class do not compete for them with the indexes of other classes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ColumnFamilyOptions {
+    /// Name of the column family.
+    pub name: String,
+    /// Prefixes of the names of the indexes stored in the column family, e.g., `core.blocks`.
+    /// If an index matches prefixes of several column families, the first family is used.
+    pub index_prefixes: Vec<String>,
+    /// Size of the block cache of the column family in bytes.
+    ///
+    /// Defaults to `None`, meaning that the default cache of the database is used.
+    #[serde(default)]
+    pub block_cache_size: Option<usize>,
+    /// Size of a memtable of the column family in bytes.
+    ///
+    /// Defaults to `None`, meaning that the default size is used.
+    #[serde(default)]
+    pub write_buffer_size: Option<usize>,
+    /// Compaction style of the column family. Defaults to `Level`.
+    #[serde(default)]
+    pub compaction_style: CompactionStyle,
+}
+
+impl ColumnFamilyOptions {
+    /// Creates options of a column family for the indexes with the given name prefixes
+    /// with the default settings.
+    pub fn new<S, I>(name: S, index_prefixes: I) -> Self
+    where
+        S: Into<String>,
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            name: name.into(),
+            index_prefixes: index_prefixes.into_iter().map(Into::into).collect(),
+            block_cache_size: None,
+            write_buffer_size: None,
+            compaction_style: CompactionStyle::default(),
+        }
+    }
+}
+
+/// Compaction style of a column family.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CompactionStyle {
+    /// Level compaction, which is suitable for read-heavy workloads.
+    Level,
+    /// Universal compaction, which reduces write amplification for write-heavy workloads.
+    Universal,
+    /// FIFO compaction, which drops the oldest data and is suitable for caches.
+    Fifo,
+}
+
+impl Default for CompactionStyle {
+    fn default() -> Self {
+        CompactionStyle::Level
+    }
+}
+
+/// Options of a cache of recently read entries in `CachedDB`, which is shared
+/// by the indexes with the specified name prefixes, e.g., configurations or wallets.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CacheOptions {
+    /// Prefixes of the names of the cached indexes, e.g., `core.configs`.
+    /// If an index matches prefixes of several caches, the first cache is used.
+    pub index_prefixes: Vec<String>,
+    /// Maximal number of the entries kept in the cache.
+    pub capacity: usize,
+}
+
+impl CacheOptions {
+    /// Creates options of a cache for the indexes with the given name prefixes.
+    pub fn new<I>(index_prefixes: I, capacity: usize) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self {
+            index_prefixes: index_prefixes.into_iter().map(Into::into).collect(),
+            capacity,
+        }
+    }
+}
+
+/// Options of the value compression in `CompressedDB`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct CompressionOptions {
+    /// Compression algorithm.
+    pub algorithm: CompressionAlgorithm,
+    /// Minimal size of a value in bytes to be compressed. Shorter values are stored as is,
+    /// since compression is not effective for them.
+    ///
+    /// Defaults to 256 bytes.
+    #[serde(default = "CompressionOptions::default_threshold")]
+    pub threshold: usize,
+}
+
+impl CompressionOptions {
+    /// Creates compression options with the given algorithm and the default threshold.
+    pub fn new(algorithm: CompressionAlgorithm) -> Self {
+        Self {
+            algorithm,
+            threshold: Self::default_threshold(),
         }
     }
+
+    fn default_threshold() -> usize {
+        256
+    }
+}
+
+/// Algorithm of the value compression.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CompressionAlgorithm {
+    /// LZ4, which is fast but has a lower compression ratio.
+    Lz4,
+    /// Zstandard, which has a higher compression ratio.
+    Zstd,
 }
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{borrow::Cow, cell::Cell, io::Error, mem};
+use std::{borrow::Cow, cell::Cell, collections::BTreeMap, io::Error, mem};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use enum_primitive_derive::Primitive;
@@ -20,12 +20,12 @@ use failure::{self, ensure, format_err};
 use num_traits::FromPrimitive;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::BinaryValue;
+use crate::{BinaryValue, Snapshot};
 
-use super::{IndexAccess, IndexAddress, View};
+use super::{key_bytes, IndexAccess, IndexAddress, View, INDEX_NAME_SEPARATOR};
 
 /// Name of the column family used to store `IndexesPool`.
-const INDEXES_POOL_NAME: &str = "__INDEXES_POOL__";
+pub(crate) const INDEXES_POOL_NAME: &str = "__INDEXES_POOL__";
 
 /// Type of the index stored in `IndexMetadata`.
 /// `IndexType` is used for type checking indexes when they are created/accessed.
@@ -172,6 +172,11 @@ where
     T: IndexAccess,
     V: BinaryAttribute + Copy + Default,
 {
+    assert!(
+        index_access.is_accessible(index_address.name()),
+        "Index `{}` is outside of the allowed storage namespaces",
+        index_address.name()
+    );
     let index_name = index_address.fully_qualified_name();
 
     let mut pool = IndexesPool::new(index_access.clone());
@@ -190,6 +195,196 @@ where
     (index_address, index_state)
 }
 
+/// Returns the identifier of the index from its serialized metadata, or `None`
+/// if the metadata is malformed.
+pub(crate) fn index_identifier(metadata: &[u8]) -> Option<u64> {
+    IndexMetadata::<()>::from_bytes(Cow::Borrowed(metadata))
+        .ok()
+        .map(|metadata| metadata.identifier)
+}
+
+/// Returns the prefix of the keys of the index data.
+pub(crate) fn index_data_prefix(identifier: u64) -> Vec<u8> {
+    key_bytes(&identifier)
+}
+
+/// Returns the type of the index with the provided `index_address`, or `None`
+/// if such an index does not exist.
+///
+/// Unlike index constructors, this function does not panic if the index has
+/// a type different from the expected one, thus it can be used to check indexes
+/// addressed by an untrusted input.
+pub fn index_type<T, I>(index_access: T, index_address: I) -> Option<IndexType>
+where
+    T: IndexAccess,
+    I: Into<IndexAddress>,
+{
+    let index_name = index_address.into().fully_qualified_name();
+    IndexesPool::new(index_access)
+        .index_metadata::<()>(&index_name)
+        .map(|metadata| metadata.index_type)
+}
+
+/// Approximate storage statistics of the indexes with the same name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexStats {
+    /// Index name. Indexes of the same family are accounted together.
+    pub name: String,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// Number of indexes with this name, which is greater than one for index families.
+    pub indexes: u64,
+    /// Number of entries in the indexes.
+    pub entries: u64,
+    /// Total size of the keys and values of the entries in bytes, excluding
+    /// the overhead of the database backend.
+    pub size: u64,
+}
+
+/// Returns storage statistics of all the indexes ordered by the index name.
+///
+/// This function iterates over all the entries in the storage, so it may take
+/// a long time for large databases.
+pub fn indexes_stats(snapshot: &dyn Snapshot) -> Vec<IndexStats> {
+    let mut stats: BTreeMap<String, IndexStats> = BTreeMap::new();
+    let mut pool = snapshot.iter(INDEXES_POOL_NAME, &[]);
+    while let Some((index_name, value)) = pool.next() {
+        // The pool length is stored with the empty key.
+        if index_name.is_empty() {
+            continue;
+        }
+        let metadata = match IndexMetadata::<()>::from_bytes(Cow::Borrowed(value)) {
+            Ok(metadata) => metadata,
+            // Metadata is checked when the index is accessed, so it is skipped here.
+            Err(_) => continue,
+        };
+        let name = index_name
+            .split(|byte| INDEX_NAME_SEPARATOR.contains(byte))
+            .next()
+            .unwrap_or_default();
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        let index_stats = stats.entry(name.clone()).or_insert_with(|| IndexStats {
+            name,
+            index_type: metadata.index_type,
+            indexes: 0,
+            entries: 0,
+            size: 0,
+        });
+        index_stats.indexes += 1;
+
+        let (entries, size) = index_data_stats(snapshot, metadata.identifier);
+        index_stats.entries += entries;
+        index_stats.size += size;
+    }
+    stats.into_iter().map(|(_, stats)| stats).collect()
+}
+
+/// Returns the total size in bytes of the keys and values of the entries in the indexes
+/// of the namespace, i.e., the indexes named `namespace` or starting with `namespace.`.
+///
+/// Unlike `indexes_stats`, this function iterates only over the entries
+/// of the namespace indexes.
+pub fn namespace_size(snapshot: &dyn Snapshot, namespace: &str) -> u64 {
+    let namespace = namespace.as_bytes();
+    let mut size = 0;
+    let mut pool = snapshot.iter(INDEXES_POOL_NAME, namespace);
+    while let Some((index_name, value)) = pool.next() {
+        if !index_name.starts_with(namespace) {
+            break;
+        }
+        if !in_namespace(index_name, namespace) {
+            continue;
+        }
+        if let Ok(metadata) = IndexMetadata::<()>::from_bytes(Cow::Borrowed(value)) {
+            size += index_data_stats(snapshot, metadata.identifier).1;
+        }
+    }
+    size
+}
+
+/// Checks whether the index with the given name, as stored in the indexes pool,
+/// belongs to the namespace. Indexes of another namespace sharing the prefix, e.g.,
+/// `wallets_history` for `wallets`, do not belong to it.
+pub(crate) fn in_namespace(index_name: &[u8], namespace: &[u8]) -> bool {
+    index_name.starts_with(namespace)
+        && index_name.get(namespace.len()).map_or(true, |byte| {
+            *byte == b'.' || INDEX_NAME_SEPARATOR.contains(byte)
+        })
+}
+
+/// Returns the number of entries and their total size in the index with the given identifier.
+pub(crate) fn index_data_stats(snapshot: &dyn Snapshot, identifier: u64) -> (u64, u64) {
+    let prefix = key_bytes(&identifier);
+    let (mut entries, mut size) = (0, 0);
+    let mut iter = snapshot.iter("", &prefix);
+    while let Some((key, value)) = iter.next() {
+        if !key.starts_with(&prefix) {
+            break;
+        }
+        entries += 1;
+        size += (key.len() + value.len()) as u64;
+    }
+    (entries, size)
+}
+
+/// Description of an index stored in the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexInfo {
+    /// Index name. Indexes of the same family have the same name.
+    pub name: String,
+    /// Key of the index in the family, or `None` if the index does not belong to a family.
+    pub family_key: Option<Vec<u8>>,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// Address of the index data. Raw entries of the index can be read by a `View`
+    /// with this address.
+    pub data_address: IndexAddress,
+}
+
+/// Returns the number of the indexes created in the database, which is also the identifier
+/// of the next created index.
+///
+/// Since identifiers of the new indexes are allocated sequentially, forks created
+/// from the same snapshot must not both create new indexes if their patches are to be
+/// merged together. Comparing the numbers of indexes of the snapshot and of the patch
+/// allows detecting whether a fork has created new indexes.
+pub fn indexes_count(snapshot: &dyn Snapshot) -> u64 {
+    IndexesPool::new(snapshot).len()
+}
+
+/// Returns descriptions of all the indexes ordered by their names and family keys.
+pub fn indexes_info(snapshot: &dyn Snapshot) -> Vec<IndexInfo> {
+    let mut indexes = Vec::new();
+    let mut pool = snapshot.iter(INDEXES_POOL_NAME, &[]);
+    while let Some((index_name, value)) = pool.next() {
+        // The pool length is stored with the empty key.
+        if index_name.is_empty() {
+            continue;
+        }
+        let metadata = match IndexMetadata::<()>::from_bytes(Cow::Borrowed(value)) {
+            Ok(metadata) => metadata,
+            // Metadata is checked when the index is accessed, so it is skipped here.
+            Err(_) => continue,
+        };
+        let (name, family_key) = match index_name
+            .iter()
+            .position(|byte| INDEX_NAME_SEPARATOR.contains(byte))
+        {
+            Some(pos) => (&index_name[..pos], Some(index_name[pos + 1..].to_vec())),
+            None => (index_name, None),
+        };
+
+        indexes.push(IndexInfo {
+            name: String::from_utf8_lossy(name).into_owned(),
+            family_key,
+            index_type: metadata.index_type,
+            data_address: metadata.index_address(),
+        });
+    }
+    indexes
+}
+
 /// Persistent pool used to store indexes metadata in the database.
 /// Pool size is used as an identifier of newly created indexes.
 struct IndexesPool<T: IndexAccess>(View<T>);
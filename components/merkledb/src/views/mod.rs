@@ -15,11 +15,18 @@
 #![warn(missing_docs)]
 
 pub use self::{
-    metadata::{BinaryAttribute, IndexState, IndexType},
+    metadata::{
+        index_type, indexes_count, indexes_info, indexes_stats, namespace_size, BinaryAttribute,
+        IndexInfo, IndexState, IndexStats, IndexType,
+    },
     refs::{AnyObject, ObjectAccess, Ref, RefMut},
 };
 
-use std::{borrow::Cow, fmt, iter::Peekable, marker::PhantomData, ops::Deref};
+pub(crate) use self::metadata::{
+    in_namespace, index_data_prefix, index_data_stats, index_identifier, INDEXES_POOL_NAME,
+};
+
+use std::{borrow::Cow, cmp, fmt, iter::Peekable, marker::PhantomData, ops::Deref};
 
 use super::{
     db::{Change, ChangesRef, ForkIter, ViewChanges},
@@ -86,6 +93,13 @@ pub trait IndexAccess: Clone {
     fn snapshot(&self) -> &dyn Snapshot;
     /// Returns changes related to specific `address`.
     fn changes(&self, address: &IndexAddress) -> Self::Changes;
+    /// Checks whether the index with the given name may be accessed, i.e., whether
+    /// it belongs to the namespaces the access is restricted to.
+    ///
+    /// *Default implementation allows access to all indexes*
+    fn is_accessible(&self, _index_name: &str) -> bool {
+        true
+    }
 }
 
 /// Struct responsible for creating `view` and `state` for index with
@@ -494,16 +508,17 @@ impl<T: IndexAccess> View<T> {
 
     /// Returns an iterator over the entries of the index in ascending order starting from the
     /// specified key. The iterator element type is *any* key-value pair. An argument `subprefix`
-    /// allows specifying a subset of iteration.
+    /// allows specifying a subset of iteration. If `from` precedes all keys with `subprefix`,
+    /// the iteration starts from the first of them.
     pub fn iter_from<P, F, K, V>(&self, subprefix: &P, from: &F) -> Iter<K, V>
     where
-        P: BinaryKey,
+        P: BinaryKey + ?Sized,
         F: BinaryKey + ?Sized,
         K: BinaryKey,
         V: BinaryValue,
     {
         let iter_prefix = key_bytes(subprefix);
-        let iter_from = key_bytes(from);
+        let iter_from = cmp::max(key_bytes(from), iter_prefix.clone());
         Iter {
             base_iter: self.iter_bytes(&iter_from),
             prefix: iter_prefix,
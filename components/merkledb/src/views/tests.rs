@@ -13,12 +13,17 @@
 // limitations under the License.
 use url::form_urlencoded::byte_serialize;
 
-use std::panic;
+use std::{panic, sync::Arc};
 
 use crate::{
     db,
-    views::{is_valid_name, IndexAccess, IndexAddress, IndexBuilder, IndexType, View},
-    Database, DbOptions, Entry, Fork, ListIndex, MapIndex, RocksDB, TemporaryDB,
+    views::{
+        index_type, indexes_count, indexes_info, indexes_stats, is_valid_name, namespace_size,
+        IndexAccess, IndexAddress, IndexBuilder, IndexStats, IndexType, View,
+    },
+    CacheOptions, CachedDB, ColumnFamilyOptions, CompressedDB, CompressionAlgorithm,
+    CompressionOptions, Database, DbOptions, EncryptedDB, Entry, Fork, ListIndex, MapIndex,
+    MeteredDB, Patch, PipelinedDB, RocksDB, Snapshot, TemporaryDB,
 };
 
 const IDX_NAME: &str = "idx_name";
@@ -374,6 +379,613 @@ fn test_database_check_incorrect_version() {
     RocksDB::open(&dir, &opts).unwrap();
 }
 
+#[test]
+fn test_database_checkpoint() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let checkpoint_path = dir.path().join("checkpoint");
+    let db = RocksDB::open(dir.path().join("db"), &DbOptions::default()).unwrap();
+
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+    db.create_checkpoint(&checkpoint_path).unwrap();
+    // Changes made after the checkpoint should not be visible in it.
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(2_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let checkpoint = RocksDB::open(&checkpoint_path, &DbOptions::default()).unwrap();
+    let snapshot = checkpoint.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1]);
+    // The checkpoint cannot overwrite an existing directory.
+    assert!(db.create_checkpoint(&checkpoint_path).is_err());
+}
+
+#[test]
+fn test_encrypted_database_checkpoint_snapshot() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let checkpoint_path = dir.path().join("checkpoint");
+    let inner = RocksDB::open(dir.path().join("db"), &DbOptions::default()).unwrap();
+    let db = EncryptedDB::new(Box::new(inner), b"passphrase").unwrap();
+
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+    db.create_checkpoint(&checkpoint_path).unwrap();
+
+    // The checkpoint is read with the same encryption key as the database.
+    let snapshot = db.checkpoint_snapshot(&checkpoint_path).unwrap();
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1]);
+}
+
+#[test]
+fn test_database_column_families() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions {
+        column_families: vec![ColumnFamilyOptions::new("blocks", vec!["blocks"])],
+        ..DbOptions::default()
+    };
+    // The index is created before the column family is configured.
+    {
+        let db = RocksDB::open(&dir, &DbOptions::default()).unwrap();
+        let fork = db.fork();
+        ListIndex::new("blocks_legacy", &fork).push(1_u8);
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    {
+        let db = RocksDB::open(&dir, &opts).unwrap();
+        let fork = db.fork();
+        ListIndex::new("blocks", &fork).extend(vec![1_u8, 2, 3]);
+        ListIndex::new("blocks_legacy", &fork).push(2_u8);
+        ListIndex::new(IDX_NAME, &fork).push(4_u8);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        {
+            let mut list = ListIndex::new("blocks", &fork);
+            list.clear();
+            list.push(5_u8);
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+    let cf_names = rocksdb::DB::list_cf(&rocksdb::Options::default(), &dir).unwrap();
+    assert!(cf_names.contains(&"__CF__.blocks".to_owned()));
+
+    // Indexes keep their column families if the options change.
+    let db = RocksDB::open(&dir, &DbOptions::default()).unwrap();
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new("blocks", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![5]);
+    let list: ListIndex<_, u8> = ListIndex::new("blocks_legacy", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2]);
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![4]);
+}
+
+#[test]
+fn test_database_column_families_copied() {
+    let source_dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions {
+        column_families: vec![ColumnFamilyOptions::new("blocks", vec!["blocks"])],
+        ..DbOptions::default()
+    };
+    let source = RocksDB::open(&source_dir, &opts).unwrap();
+    let fork = source.fork();
+    ListIndex::new("blocks", &fork).extend(vec![1_u8, 2, 3]);
+    ListIndex::new(IDX_NAME, &fork).push(4_u8);
+    source.merge(fork.into_patch()).unwrap();
+
+    // The assignment of the column families is copied together with the data,
+    // which is written to the common column family.
+    let dir = tempfile::TempDir::new().unwrap();
+    let db = RocksDB::open(&dir, &DbOptions::default()).unwrap();
+    let source_snapshot = source.snapshot();
+    for (from, to) in &[
+        ("__COLUMN_FAMILIES__", "__COLUMN_FAMILIES__"),
+        ("__INDEXES_POOL__", "__INDEXES_POOL__"),
+        ("__CF__.blocks", ""),
+        ("", ""),
+    ] {
+        let fork = db.fork();
+        {
+            let mut view = View::new(&fork, *to);
+            let mut iter = source_snapshot.iter(from, &[]);
+            while let Some((key, value)) = iter.next() {
+                view.put(key, value.to_vec());
+            }
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new("blocks", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![4]);
+    // The data of the index is moved to the assigned column family.
+    let mut iter = snapshot.iter("__CF__.blocks", &[]);
+    assert!(iter.next().is_some());
+}
+
+#[test]
+fn test_encrypted_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions::default();
+    {
+        let inner = RocksDB::open(&dir, &opts).unwrap();
+        let db = EncryptedDB::new(Box::new(inner), b"passphrase").unwrap();
+        let fork = db.fork();
+        {
+            let mut map = MapIndex::new(IDX_NAME, &fork);
+            map.put(&1_u8, "secret value".to_owned());
+            map.put(&2_u8, "another secret".to_owned());
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map: MapIndex<_, u8, String> = MapIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(map.get(&1), Some("secret value".to_owned()));
+        assert_eq!(
+            map.values().collect::<Vec<_>>(),
+            vec!["secret value".to_owned(), "another secret".to_owned()]
+        );
+    }
+
+    // Values are not stored in plaintext.
+    {
+        let db = RocksDB::open(&dir, &opts).unwrap();
+        let snapshot = db.snapshot();
+        let mut iter = snapshot.iter("", &[]);
+        while let Some((_, value)) = iter.next() {
+            assert!(!value.windows(6).any(|window| window == b"secret"));
+        }
+    }
+
+    let inner = RocksDB::open(&dir, &opts).unwrap();
+    assert!(EncryptedDB::new(Box::new(inner), b"wrong passphrase").is_err());
+    let inner = RocksDB::open(&dir, &opts).unwrap();
+    let db = EncryptedDB::new(Box::new(inner), b"passphrase").unwrap();
+    let snapshot = db.snapshot();
+    let map: MapIndex<_, u8, String> = MapIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(map.get(&2), Some("another secret".to_owned()));
+}
+
+#[test]
+fn test_encrypted_database_with_unencrypted_data() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    assert!(EncryptedDB::new(Box::new(db), b"passphrase").is_err());
+}
+
+#[test]
+#[should_panic(expected = "Unable to decrypt the value")]
+fn test_encrypted_database_with_moved_value() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions::default();
+    {
+        let inner = RocksDB::open(&dir, &opts).unwrap();
+        let db = EncryptedDB::new(Box::new(inner), b"passphrase").unwrap();
+        let fork = db.fork();
+        {
+            let mut view = View::new(&fork, IDX_NAME);
+            view.put(&1_u8, vec![1_u8]);
+            view.put(&2_u8, vec![2_u8]);
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    // The ciphertext of the first value is moved to the key of the second one.
+    {
+        let db = RocksDB::open(&dir, &opts).unwrap();
+        let ciphertext = db.snapshot().get(IDX_NAME, &[1]).unwrap();
+        let fork = db.fork();
+        View::new(&fork, IDX_NAME).put(&2_u8, ciphertext);
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    let inner = RocksDB::open(&dir, &opts).unwrap();
+    let db = EncryptedDB::new(Box::new(inner), b"passphrase").unwrap();
+    let snapshot = db.snapshot();
+    assert_eq!(snapshot.get(IDX_NAME, &[1]), Some(vec![1]));
+    snapshot.get(IDX_NAME, &[2]);
+}
+
+#[test]
+fn test_compressed_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions::default();
+    let large_value = "compressible value ".repeat(100);
+    for &algorithm in &[CompressionAlgorithm::Lz4, CompressionAlgorithm::Zstd] {
+        let inner = RocksDB::open(&dir, &opts).unwrap();
+        let db = CompressedDB::new(Box::new(inner), CompressionOptions::new(algorithm)).unwrap();
+        let fork = db.fork();
+        {
+            let mut map = MapIndex::new(IDX_NAME, &fork);
+            map.put(&(algorithm as u8), large_value.clone());
+            map.put(&10_u8, "short value".to_owned());
+        }
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    // Large values are compressed, while short ones are stored with a tag only.
+    {
+        let db = RocksDB::open(&dir, &opts).unwrap();
+        let snapshot = db.snapshot();
+        let mut iter = snapshot.iter("", &[]);
+        let mut sizes = Vec::new();
+        while let Some((_, value)) = iter.next() {
+            sizes.push(value.len());
+        }
+        assert_eq!(sizes.len(), 3);
+        assert!(sizes[..2].iter().all(|&size| size < large_value.len() / 4));
+        assert_eq!(sizes[2], "short value".len() + 1);
+    }
+
+    // Values compressed with any algorithm can be read.
+    let inner = RocksDB::open(&dir, &opts).unwrap();
+    let options = CompressionOptions::new(CompressionAlgorithm::Lz4);
+    let db = CompressedDB::new(Box::new(inner), options).unwrap();
+    let snapshot = db.snapshot();
+    let map: MapIndex<_, u8, String> = MapIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(
+        map.get(&(CompressionAlgorithm::Zstd as u8)),
+        Some(large_value.clone())
+    );
+    assert_eq!(
+        map.values().collect::<Vec<_>>(),
+        vec![large_value.clone(), large_value, "short value".to_owned()]
+    );
+}
+
+#[test]
+fn test_compressed_database_with_uncompressed_data() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let options = CompressionOptions::new(CompressionAlgorithm::Zstd);
+    assert!(CompressedDB::new(Box::new(db), options).is_err());
+}
+
+#[test]
+fn test_pipelined_database() {
+    let dir = tempfile::TempDir::new().unwrap();
+    {
+        let db = RocksDB::open(&dir, &DbOptions::default()).unwrap();
+        let db = PipelinedDB::new(Box::new(db), vec!["map".to_owned()]);
+        for i in 0..10_u8 {
+            let fork = db.fork();
+            ListIndex::new(IDX_NAME, &fork).push(i);
+            MapIndex::new("map", &fork).put(&i, i);
+            db.merge(fork.into_patch()).unwrap();
+
+            // Both parts of the patch are written once the merge returns.
+            let snapshot = db.snapshot();
+            let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+            assert_eq!(list.len(), u64::from(i) + 1);
+            assert_eq!(list.last(), Some(i));
+            let map: MapIndex<_, u8, u8> = MapIndex::new("map", &snapshot);
+            assert_eq!(map.get(&i), Some(i));
+        }
+
+        let fork = db.fork();
+        MapIndex::<_, u8, u8>::new("map", &fork).clear();
+        ListIndex::new(IDX_NAME, &fork).push(42_u8);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+        assert_eq!(list.len(), 11);
+        let map: MapIndex<_, u8, u8> = MapIndex::new("map", &snapshot);
+        assert_eq!(map.keys().count(), 0);
+
+        let fork = db.fork();
+        MapIndex::new("map", &fork).put(&100_u8, 100_u8);
+        db.merge_sync(fork.into_patch()).unwrap();
+    }
+
+    let db = RocksDB::open(&dir, &DbOptions::default()).unwrap();
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(list.last(), Some(42));
+    let map: MapIndex<_, u8, u8> = MapIndex::new("map", &snapshot);
+    assert_eq!(map.keys().collect::<Vec<_>>(), vec![100]);
+}
+
+#[test]
+fn test_pipelined_database_write_errors() {
+    struct ReadOnlyDB(TemporaryDB);
+
+    impl Database for ReadOnlyDB {
+        fn snapshot(&self) -> Box<dyn Snapshot> {
+            self.0.snapshot()
+        }
+
+        fn merge(&self, patch: Patch) -> crate::Result<()> {
+            if patch.is_empty() {
+                Ok(())
+            } else {
+                Err(crate::Error::new("Database is read-only"))
+            }
+        }
+
+        fn merge_sync(&self, patch: Patch) -> crate::Result<()> {
+            self.merge(patch)
+        }
+    }
+
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    MapIndex::new("map", &fork).put(&1_u8, 1_u8);
+    ListIndex::new(IDX_NAME, &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+    let db = PipelinedDB::new(Box::new(ReadOnlyDB(db)), vec!["map".to_owned()]);
+
+    // The failure of the secondary index write is reported by the failed merge.
+    let fork = db.fork();
+    MapIndex::new("map", &fork).put(&2_u8, 2_u8);
+    assert!(db.merge(fork.into_patch()).is_err());
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(2_u8);
+    assert!(db.merge(fork.into_patch()).is_err());
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).push(2_u8);
+    MapIndex::new("map", &fork).put(&2_u8, 2_u8);
+    assert!(db.merge_sync(fork.into_patch()).is_err());
+}
+
+#[test]
+fn test_cached_database() {
+    let db = MeteredDB::new(TemporaryDB::new());
+    let metrics = db.metrics().clone();
+    let db = CachedDB::new(Box::new(db), vec![CacheOptions::new(vec!["map"], 2)]);
+    let fork = db.fork();
+    MapIndex::new("map", &fork).put(&1_u8, 1_u8);
+    ListIndex::new(IDX_NAME, &fork).push(1_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let map: MapIndex<_, u8, u8> = MapIndex::new("map", &snapshot);
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    let reads = metrics.info().reads;
+    assert_eq!(map.get(&1), Some(1));
+    assert_eq!(map.get(&2), None);
+    assert_eq!(list.get(0), Some(1));
+    assert_eq!(metrics.info().reads, reads + 3);
+    // Present and absent entries of the cached index are read from the cache.
+    assert_eq!(map.get(&1), Some(1));
+    assert_eq!(map.get(&2), None);
+    assert_eq!(list.get(0), Some(1));
+    assert_eq!(metrics.info().reads, reads + 4);
+
+    let fork = db.fork();
+    MapIndex::new("map", &fork).put(&2_u8, 2_u8);
+    db.merge(fork.into_patch()).unwrap();
+    // Snapshots created before the merge are not affected by the changes.
+    assert_eq!(map.get(&2), None);
+    let new_snapshot = db.snapshot();
+    let new_map: MapIndex<_, u8, u8> = MapIndex::new("map", &new_snapshot);
+    assert_eq!(new_map.get(&1), Some(1));
+    assert_eq!(new_map.get(&2), Some(2));
+    // The least recently read entry is evicted.
+    assert_eq!(new_map.get(&3), None);
+    let reads = metrics.info().reads;
+    assert_eq!(new_map.get(&2), Some(2));
+    assert_eq!(new_map.get(&3), None);
+    assert_eq!(metrics.info().reads, reads);
+    assert_eq!(new_map.get(&1), Some(1));
+    assert_eq!(metrics.info().reads, reads + 1);
+
+    let fork = db.fork();
+    MapIndex::<_, u8, u8>::new("map", &fork).clear();
+    db.merge(fork.into_patch()).unwrap();
+    let snapshot = db.snapshot();
+    let map: MapIndex<_, u8, u8> = MapIndex::new("map", &snapshot);
+    assert_eq!(map.get(&1), None);
+    assert_eq!(map.get(&2), None);
+
+    // Indexes created after wrapping the database are cached as well.
+    let fork = db.fork();
+    MapIndex::new("map_new", &fork).put(&1_u8, 1_u8);
+    db.merge(fork.into_patch()).unwrap();
+    let snapshot = db.snapshot();
+    let map: MapIndex<_, u8, u8> = MapIndex::new("map_new", &snapshot);
+    assert_eq!(map.get(&1), Some(1));
+    let reads = metrics.info().reads;
+    assert_eq!(map.get(&1), Some(1));
+    assert_eq!(metrics.info().reads, reads);
+}
+
+#[test]
+fn test_metered_database() {
+    let db = MeteredDB::new(TemporaryDB::new());
+    let fork = db.fork();
+    {
+        let mut list = ListIndex::new(IDX_NAME, &fork);
+        list.extend(vec![1_u8, 2, 3]);
+    }
+    db.merge(fork.into_patch()).unwrap();
+    let merge_info = db.metrics().info();
+    assert_eq!(merge_info.merges, 1);
+    assert!(merge_info.written_entries >= 3);
+    assert_eq!(merge_info.max_merge_bytes, merge_info.written_bytes);
+    assert_eq!(merge_info.failed_merges, 0);
+    assert!(!db.metrics().last_merge_failed());
+
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(list.get(1), Some(2));
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+    let info = db.metrics().info();
+    assert!(info.reads > merge_info.reads);
+    assert!(info.read_bytes > merge_info.read_bytes);
+    assert!(info.iterated_entries >= merge_info.iterated_entries + 3);
+    assert_eq!(info.merges, 1);
+    assert_eq!(info.written_bytes, merge_info.written_bytes);
+}
+
+#[test]
+fn test_indexes_stats() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    {
+        let mut list = ListIndex::new(IDX_NAME, &fork);
+        list.extend(vec![1_u8, 2, 3]);
+        let mut map = MapIndex::new_in_family("family", &1_u8, &fork);
+        map.put(&1_u8, 1_u8);
+        let mut map = MapIndex::new_in_family("family", &2_u8, &fork);
+        map.put(&1_u8, 1_u8);
+        map.put(&2_u8, 2_u8);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let stats = indexes_stats(&*db.snapshot());
+    assert_eq!(
+        stats,
+        vec![
+            IndexStats {
+                name: "family".to_owned(),
+                index_type: IndexType::Map,
+                indexes: 2,
+                entries: 3,
+                // 8-byte index identifiers followed by 1-byte keys, and 1-byte values.
+                size: 30,
+            },
+            IndexStats {
+                name: IDX_NAME.to_owned(),
+                index_type: IndexType::List,
+                indexes: 1,
+                entries: 3,
+                // 8-byte index identifiers followed by 8-byte keys, and 1-byte values.
+                size: 51,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_indexes_stats_column_families() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let opts = DbOptions {
+        column_families: vec![ColumnFamilyOptions::new("blocks", vec!["blocks"])],
+        ..DbOptions::default()
+    };
+    let db = RocksDB::open(&dir, &opts).unwrap();
+    let fork = db.fork();
+    ListIndex::new("blocks", &fork).extend(vec![1_u8, 2, 3]);
+    ListIndex::new(IDX_NAME, &fork).push(4_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    // Data of the indexes stored in the configured column families is accounted as well.
+    let stats = indexes_stats(&*db.snapshot());
+    assert_eq!(
+        stats.iter().map(|stats| stats.entries).collect::<Vec<_>>(),
+        vec![3, 1]
+    );
+    assert_eq!(stats[0].name, "blocks");
+    assert_eq!(stats[0].size, 51);
+
+    let sizes = db.approximate_sizes();
+    let blocks = sizes
+        .iter()
+        .find(|size| size.name == "__CF__.blocks")
+        .expect("No size of the column family");
+    assert!(blocks.entries > 0);
+    assert!(!TemporaryDB::new().approximate_sizes().is_empty());
+}
+
+#[test]
+fn test_indexes_info() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).extend(vec![1_u8, 2]);
+    MapIndex::new_in_family("family", &1_u8, &fork).put(&1_u8, 1_u8);
+    MapIndex::new_in_family("family", &2_u8, &fork).put(&2_u8, 2_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let indexes = indexes_info(&*snapshot);
+    let descriptions = indexes
+        .iter()
+        .map(|info| (info.name.as_str(), info.family_key.clone(), info.index_type))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        descriptions,
+        vec![
+            ("family", Some(vec![1]), IndexType::Map),
+            ("family", Some(vec![2]), IndexType::Map),
+            (IDX_NAME, None, IndexType::List),
+        ]
+    );
+
+    let view = View::new(&snapshot, indexes[1].data_address.clone());
+    let entries = view.iter::<_, u8, u8>(&()).collect::<Vec<_>>();
+    assert_eq!(entries, vec![(2, 2)]);
+}
+
+#[test]
+fn test_namespace_size() {
+    let db = TemporaryDB::new();
+    let mut fork = db.fork();
+    Entry::new("wallets", &fork).set(1_u8);
+    MapIndex::new("wallets.history", &fork).put(&1_u8, 1_u8);
+    MapIndex::new_in_family("wallets.keys", &1_u8, &fork).put(&1_u8, 1_u8);
+    MapIndex::new("wallets_v2", &fork).put(&1_u8, 1_u8);
+    MapIndex::new("other", &fork).put(&1_u8, 1_u8);
+
+    // 8-byte index identifiers followed by 1-byte keys (or none for the entry),
+    // and 1-byte values.
+    assert_eq!(fork.unflushed_namespace_size_change("wallets"), 9 + 10 + 10);
+    assert_eq!(namespace_size(fork.snapshot(), "wallets"), 0);
+    // Flushed changes are taken into account.
+    fork.flush();
+    assert_eq!(namespace_size(fork.snapshot(), "wallets"), 9 + 10 + 10);
+    assert_eq!(fork.unflushed_namespace_size_change("wallets"), 0);
+    db.merge(fork.into_patch()).unwrap();
+    let snapshot = db.snapshot();
+    assert_eq!(namespace_size(&*snapshot, "wallets"), 29);
+    assert_eq!(namespace_size(&*snapshot, "wallets.history"), 10);
+    assert_eq!(namespace_size(&*snapshot, "unknown"), 0);
+}
+
+#[test]
+fn test_unflushed_namespace_size_change() {
+    let db = TemporaryDB::new();
+    let mut fork = db.fork();
+    MapIndex::new("wallets.history", &fork).put(&1_u8, 1_u8);
+    MapIndex::new("wallets.history", &fork).put(&2_u8, 2_u8);
+    MapIndex::new("wallets_v2", &fork).put(&1_u8, 1_u8);
+    fork.flush();
+
+    {
+        let mut history = MapIndex::new("wallets.history", &fork);
+        history.put(&1_u8, vec![1_u8, 2, 3]);
+        history.remove(&2_u8);
+    }
+    MapIndex::new("wallets_v2", &fork).put(&2_u8, 2_u8);
+    // The value is 2 bytes longer, and the other entry of 10 bytes is removed.
+    assert_eq!(fork.unflushed_namespace_size_change("wallets"), 2 - 10);
+    fork.flush();
+    assert_eq!(namespace_size(fork.snapshot(), "wallets"), 12);
+
+    {
+        let mut history: MapIndex<_, u8, u8> = MapIndex::new("wallets.history", &fork);
+        history.clear();
+        history.put(&3_u8, 3_u8);
+    }
+    assert_eq!(fork.unflushed_namespace_size_change("wallets"), 10 - 12);
+    fork.rollback();
+    assert_eq!(fork.unflushed_namespace_size_change("wallets"), 0);
+}
+
 #[test]
 fn fork_iter() {
     _fork_iter(&TemporaryDB::new(), IDX_NAME);
@@ -750,6 +1362,28 @@ fn test_metadata_index_identifiers() {
     }
 }
 
+#[test]
+fn test_index_type() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    {
+        let mut map = MapIndex::new("map", &fork);
+        map.put(&1, vec![1, 2, 3]);
+        let mut list = ListIndex::new_in_family("list", &1_u8, &fork);
+        list.push(vec![1, 2, 3]);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    assert_eq!(index_type(&snapshot, "map"), Some(IndexType::Map));
+    assert_eq!(
+        index_type(&snapshot, ("list", &1_u8)),
+        Some(IndexType::List)
+    );
+    assert_eq!(index_type(&snapshot, ("list", &2_u8)), None);
+    assert_eq!(index_type(&snapshot, "unknown"), None);
+}
+
 #[test]
 fn test_index_builder_without_type() {
     let db = TemporaryDB::new();
@@ -986,3 +1620,137 @@ fn fork_from_patch() {
     db.merge(fork.into_patch())
         .expect("Fork created from patch should be merged successfully");
 }
+
+#[test]
+fn test_fork_write_conflicts() {
+    let db = TemporaryDB::new();
+    let mut fork = db.fork();
+    fork.track_writes();
+
+    MapIndex::new(IDX_NAME, &fork).put(&1_u8, 1_u8);
+    fork.flush_as("first");
+    // Rolled back changes are not attributed to writers.
+    MapIndex::new(IDX_NAME, &fork).put(&1_u8, 2_u8);
+    fork.rollback();
+    // Changes finalized with `flush` are not attributed to writers either.
+    MapIndex::new(IDX_NAME, &fork).put(&1_u8, 3_u8);
+    fork.flush();
+    MapIndex::new(IDX_NAME, &fork).put(&2_u8, 2_u8);
+    fork.flush_as("second");
+    assert!(fork.write_conflicts().is_empty());
+
+    MapIndex::new(IDX_NAME, &fork).put(&1_u8, 4_u8);
+    fork.flush_as("third");
+    let conflicts = fork.write_conflicts();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].name, "");
+    assert_eq!(conflicts[0].key.last(), Some(&1_u8));
+    assert_eq!(conflicts[0].writers, vec!["first", "third"]);
+
+    // Writes are not tracked by default.
+    let mut fork = db.fork();
+    MapIndex::new(IDX_NAME, &fork).put(&1_u8, 1_u8);
+    fork.flush_as("first");
+    MapIndex::new(IDX_NAME, &fork).put(&1_u8, 2_u8);
+    fork.flush_as("second");
+    assert!(fork.write_conflicts().is_empty());
+}
+
+#[test]
+fn test_fork_namespaces() {
+    let db = TemporaryDB::new();
+    let mut fork = db.fork();
+    Entry::new("other", &fork).set(1_u8);
+    fork.flush();
+
+    fork.restrict_to_namespaces(vec!["wallets", "core"]);
+    assert_eq!(
+        fork.allowed_namespaces(),
+        Some(&["wallets".to_owned(), "core".to_owned()][..])
+    );
+    Entry::new("wallets", &fork).set(1_u8);
+    Entry::new("wallets.balance", &fork).set(2_u8);
+    MapIndex::new_in_family("core.history", &1_u8, &fork).put(&1_u8, 3_u8);
+    // Other namespaces can be read through the snapshot.
+    assert_eq!(Entry::new("other", fork.as_ref()).get(), Some(1_u8));
+
+    for name in &["other", "wallets_history", "walletsbalance", "corea.entry"] {
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            Entry::new(*name, &fork).set(4_u8);
+        }));
+        assert!(result.is_err(), "Index `{}` should not be accessible", name);
+    }
+
+    fork.lift_namespace_restriction();
+    assert_eq!(fork.allowed_namespaces(), None);
+    Entry::new("other", &fork).set(5_u8);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    assert_eq!(Entry::new("other", &snapshot).get(), Some(5_u8));
+    assert_eq!(Entry::new("wallets.balance", &snapshot).get(), Some(2_u8));
+}
+
+#[test]
+fn test_child_forks() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new(IDX_NAME, &fork).extend(vec![1_u8, 2]);
+    let parent = fork.into_snapshot();
+
+    let child = Fork::from_snapshot(parent.clone());
+    ListIndex::new(IDX_NAME, &child).push(3_u8);
+    let other_child = Fork::from_snapshot(parent.clone());
+    ListIndex::new(IDX_NAME, &other_child).set(0, 4_u8);
+
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &child);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &other_child);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![4, 2]);
+    // Children do not change the parent state.
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, parent.clone());
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2]);
+
+    // Forks see the changes of all their ancestors.
+    let grandchild = Fork::from_snapshot(child.into_snapshot());
+    let list: ListIndex<_, u8> = ListIndex::new(IDX_NAME, &grandchild);
+    assert_eq!(list.len(), 3);
+    // None of the changes are merged into the database.
+    assert_eq!(index_type(&db.snapshot(), IDX_NAME), None);
+}
+
+#[test]
+fn test_merge_child_forks() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    ListIndex::new("first", &fork).extend(vec![1_u8, 2]);
+    ListIndex::new("second", &fork).push(3_u8);
+    db.merge(fork.into_patch()).unwrap();
+    let base: Arc<dyn Snapshot> = Arc::from(db.snapshot());
+    assert_eq!(indexes_count(&*base), 2);
+
+    let first = Fork::from_snapshot(base.clone());
+    ListIndex::<_, u8>::new("first", &first).clear();
+    let second = Fork::from_snapshot(base.clone());
+    ListIndex::new("second", &second).push(4_u8);
+    ListIndex::new("third", &second).push(5_u8);
+    let first = first.into_patch();
+    let second = second.into_patch();
+    assert_eq!(indexes_count(&first), 2);
+    assert_eq!(indexes_count(&second), 3);
+
+    // The cleared index is kept cleared regardless of the order of merging.
+    let mut fork = db.fork();
+    fork.merge(second);
+    fork.merge(first);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u8> = ListIndex::new("first", &snapshot);
+    assert!(list.is_empty());
+    assert_eq!(list.iter().count(), 0);
+    let list: ListIndex<_, u8> = ListIndex::new("second", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![3, 4]);
+    let list: ListIndex<_, u8> = ListIndex::new("third", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), vec![5]);
+}
@@ -0,0 +1,352 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the block headers signed by the validators.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    hash::{hash, Hash},
+    protobuf::{self, Reader},
+    signature::{PublicKey, Signature, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH},
+};
+
+/// Class of the consensus messages, without the flags and the format version.
+const CONSENSUS_CLASS: u8 = 1;
+/// Type of the `Precommit` message within the consensus messages.
+const PRECOMMIT_TYPE: u8 = 0;
+/// Size of the header of a signed message: the public key of the author, the class
+/// and the type of the message.
+const SIGNED_MESSAGE_HEADER_SIZE: usize = PUBLIC_KEY_LENGTH + 2;
+
+/// Header of an Exonum block, with the same fields as `exonum::blockchain::Block`.
+///
+/// # JSON serialization
+///
+/// The header is deserialized from the same JSON as returned by the node. Hashes
+/// are represented as hex strings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct BlockHeader {
+    /// Identifier of the validator that proposed the block.
+    pub proposer_id: u16,
+    /// Height of the block.
+    pub height: u64,
+    /// Number of transactions in the block.
+    pub tx_count: u32,
+    /// Hash of the previous block.
+    pub prev_hash: Hash,
+    /// Root hash of the Merkle tree of the transactions in the block.
+    pub tx_hash: Hash,
+    /// Hash of the blockchain state after applying the transactions in the block.
+    pub state_hash: Hash,
+}
+
+impl BlockHeader {
+    /// Returns the Protobuf representation of the block, which is used to compute its hash.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(128);
+        protobuf::write_uint_field(&mut buffer, 1, u64::from(self.proposer_id));
+        protobuf::write_uint_field(&mut buffer, 2, self.height);
+        protobuf::write_uint_field(&mut buffer, 3, u64::from(self.tx_count));
+        protobuf::write_hash_field(&mut buffer, 4, &self.prev_hash);
+        protobuf::write_hash_field(&mut buffer, 5, &self.tx_hash);
+        protobuf::write_hash_field(&mut buffer, 6, &self.state_hash);
+        buffer
+    }
+
+    /// Returns the hash of the block, the same as `exonum::blockchain::Block::hash`.
+    pub fn object_hash(&self) -> Hash {
+        hash(&self.to_bytes())
+    }
+}
+
+/// Contents of a `Precommit` message with a verified signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Precommit {
+    /// Public key of the validator which signed the message.
+    pub author: PublicKey,
+    /// Identifier of the validator.
+    pub validator: u16,
+    /// Height of the block.
+    pub height: u64,
+    /// Consensus round of the block.
+    pub round: u32,
+    /// Hash of the accepted `Propose` message.
+    pub propose_hash: Hash,
+    /// Hash of the block.
+    pub block_hash: Hash,
+}
+
+impl Precommit {
+    /// Parses a signed `Precommit` message from its raw bytes (as they are transmitted
+    /// over the network) and verifies its ed25519 signature.
+    ///
+    /// Only the messages in the current message format, signed with ed25519 and with
+    /// an uncompressed payload are supported.
+    pub fn from_signed_bytes(raw: &[u8]) -> Result<Self, BlockProofError> {
+        Self::from_signed_bytes_in_network(raw, None)
+    }
+
+    /// Same as `from_signed_bytes`, but for the networks with domain separated signatures,
+    /// in which the signature also covers the identifier of the network, see
+    /// `exonum::blockchain::Schema::network_id`.
+    pub fn from_signed_bytes_in_network(
+        raw: &[u8],
+        network_id: Option<&Hash>,
+    ) -> Result<Self, BlockProofError> {
+        if raw.len() <= SIGNED_MESSAGE_HEADER_SIZE + SIGNATURE_LENGTH {
+            return Err(BlockProofError::MalformedPrecommit);
+        }
+        // The class byte also contains the signature scheme and compression flags and
+        // the format version, which must all be zero.
+        if raw[PUBLIC_KEY_LENGTH] != CONSENSUS_CLASS || raw[PUBLIC_KEY_LENGTH + 1] != PRECOMMIT_TYPE
+        {
+            return Err(BlockProofError::UnsupportedMessage);
+        }
+
+        let author = PublicKey::from_slice(&raw[..PUBLIC_KEY_LENGTH])
+            .expect("slice has the size of the public key");
+        let sign_idx = raw.len() - SIGNATURE_LENGTH;
+        let signature =
+            Signature::from_slice(&raw[sign_idx..]).expect("slice has the size of the signature");
+        let verified = match network_id {
+            Some(network_id) => {
+                let mut data = Vec::with_capacity(network_id.as_ref().len() + sign_idx);
+                data.extend_from_slice(network_id.as_ref());
+                data.extend_from_slice(&raw[..sign_idx]);
+                author.verify(&data, &signature)
+            }
+            None => author.verify(&raw[..sign_idx], &signature),
+        };
+        if !verified {
+            return Err(BlockProofError::InvalidSignature);
+        }
+
+        Self::decode_payload(author, &raw[SIGNED_MESSAGE_HEADER_SIZE..sign_idx])
+            .ok_or(BlockProofError::MalformedPrecommit)
+    }
+
+    fn decode_payload(author: PublicKey, payload: &[u8]) -> Option<Self> {
+        let mut precommit = Precommit {
+            author,
+            validator: 0,
+            height: 0,
+            round: 0,
+            propose_hash: Hash::zero(),
+            block_hash: Hash::zero(),
+        };
+        let (mut has_propose_hash, mut has_block_hash) = (false, false);
+
+        let mut reader = Reader::new(payload);
+        while let Some((field, value)) = reader.next_field()? {
+            match field {
+                1 => precommit.validator = value.as_uint().filter(|&v| v <= 0xffff)? as u16,
+                2 => precommit.height = value.as_uint()?,
+                3 => precommit.round = value.as_uint().filter(|&v| v <= 0xffff_ffff)? as u32,
+                4 => {
+                    precommit.propose_hash = value.as_hash()?;
+                    has_propose_hash = true;
+                }
+                5 => {
+                    precommit.block_hash = value.as_hash()?;
+                    has_block_hash = true;
+                }
+                // The time and the threshold signature share are not needed to verify
+                // the block.
+                _ => {}
+            }
+        }
+
+        if has_propose_hash && has_block_hash {
+            Some(precommit)
+        } else {
+            None
+        }
+    }
+}
+
+/// Block header with the `Precommit` messages of the validators, in the same format
+/// as `exonum::blockchain::BlockProof`.
+///
+/// # JSON serialization
+///
+/// The proof is deserialized from the same JSON as returned by the node; `precommits`
+/// are hex strings with the raw signed messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct BlockProof {
+    /// Header of the block.
+    pub block: BlockHeader,
+    /// Raw signed `Precommit` messages for the block.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_precommits"))]
+    pub precommits: Vec<Vec<u8>>,
+    /// Threshold signature of the block hash, if the validators use threshold signatures.
+    ///
+    /// Certificates are not verified by this crate.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub certificate: Option<Vec<u8>>,
+}
+
+impl BlockProof {
+    /// Verifies that the block is signed by the supermajority of the validators with
+    /// the given consensus keys, ordered by the validator identifiers as in the
+    /// configuration of the network.
+    ///
+    /// Returns the verified `Precommit` messages on success.
+    pub fn verify(&self, validator_keys: &[PublicKey]) -> Result<Vec<Precommit>, BlockProofError> {
+        self.verify_in_network(validator_keys, None)
+    }
+
+    /// Same as `verify`, but for the networks with domain separated signatures. The network
+    /// identifier must be specified if the configuration actual at the height of the block
+    /// enables domain separation.
+    pub fn verify_in_network(
+        &self,
+        validator_keys: &[PublicKey],
+        network_id: Option<&Hash>,
+    ) -> Result<Vec<Precommit>, BlockProofError> {
+        let block_hash = self.block.object_hash();
+        let mut seen_validators = Vec::with_capacity(self.precommits.len());
+        let mut precommits = Vec::with_capacity(self.precommits.len());
+
+        for raw in &self.precommits {
+            let precommit = Precommit::from_signed_bytes_in_network(raw, network_id)?;
+            let validator = precommit.validator;
+            if validator_keys.get(usize::from(validator)) != Some(&precommit.author) {
+                return Err(BlockProofError::UnknownValidator(validator));
+            }
+            if precommit.height != self.block.height || precommit.block_hash != block_hash {
+                return Err(BlockProofError::UnmatchedBlock(validator));
+            }
+            if seen_validators.contains(&validator) {
+                return Err(BlockProofError::DuplicatePrecommit(validator));
+            }
+            seen_validators.push(validator);
+            precommits.push(precommit);
+        }
+
+        let required = byzantine_majority_count(validator_keys.len());
+        if precommits.len() < required {
+            return Err(BlockProofError::NotEnoughPrecommits {
+                actual: precommits.len(),
+                required,
+            });
+        }
+        Ok(precommits)
+    }
+}
+
+/// Returns the number of validators that form the Byzantine majority, like
+/// `exonum::node::state::State::byzantine_majority_count`.
+fn byzantine_majority_count(total: usize) -> usize {
+    total * 2 / 3 + 1
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_precommits<'de, D>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct HexBytes(#[serde(deserialize_with = "crate::hex::deserialize_bytes")] Vec<u8>);
+
+    let precommits = Vec::<HexBytes>::deserialize(deserializer)?;
+    Ok(precommits.into_iter().map(|bytes| bytes.0).collect())
+}
+
+/// An error that is returned when the block proof is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockProofError {
+    /// A `Precommit` message has invalid binary format.
+    MalformedPrecommit,
+    /// A message is not a `Precommit` or uses an unsupported signature scheme, compression
+    /// or message format version.
+    UnsupportedMessage,
+    /// The signature of a `Precommit` message is invalid.
+    InvalidSignature,
+    /// A `Precommit` message is signed by a key that does not belong to the validator
+    /// with the given identifier.
+    UnknownValidator(u16),
+    /// A `Precommit` message of the validator refers to a different block.
+    UnmatchedBlock(u16),
+    /// The validator has several `Precommit` messages in the proof.
+    DuplicatePrecommit(u16),
+    /// The block is signed by too few validators.
+    NotEnoughPrecommits {
+        /// Number of valid precommits in the proof.
+        actual: usize,
+        /// Number of precommits necessary to accept the block.
+        required: usize,
+    },
+}
+
+impl fmt::Display for BlockProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockProofError::MalformedPrecommit => f.write_str("malformed precommit message"),
+            BlockProofError::UnsupportedMessage => f.write_str("unsupported precommit message"),
+            BlockProofError::InvalidSignature => f.write_str("invalid precommit signature"),
+            BlockProofError::UnknownValidator(id) => {
+                write!(f, "precommit is not signed by the validator #{}", id)
+            }
+            BlockProofError::UnmatchedBlock(id) => {
+                write!(f, "precommit of the validator #{} is for another block", id)
+            }
+            BlockProofError::DuplicatePrecommit(id) => {
+                write!(f, "duplicate precommit of the validator #{}", id)
+            }
+            BlockProofError::NotEnoughPrecommits { actual, required } => write!(
+                f,
+                "block has {} precommits, while {} are required",
+                actual, required
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byzantine_majority() {
+        assert_eq!(byzantine_majority_count(1), 1);
+        assert_eq!(byzantine_majority_count(4), 3);
+        assert_eq!(byzantine_majority_count(6), 5);
+        assert_eq!(byzantine_majority_count(7), 5);
+    }
+
+    #[test]
+    fn unsupported_precommit() {
+        let mut raw = [0_u8; SIGNED_MESSAGE_HEADER_SIZE + SIGNATURE_LENGTH + 1];
+        assert_eq!(
+            Precommit::from_signed_bytes(&raw[..raw.len() - 1]).unwrap_err(),
+            BlockProofError::MalformedPrecommit
+        );
+        raw[PUBLIC_KEY_LENGTH] = CONSENSUS_CLASS | 0x40;
+        assert_eq!(
+            Precommit::from_signed_bytes(&raw).unwrap_err(),
+            BlockProofError::UnsupportedMessage
+        );
+        raw[PUBLIC_KEY_LENGTH] = CONSENSUS_CLASS;
+        assert_eq!(
+            Precommit::from_signed_bytes(&raw).unwrap_err(),
+            BlockProofError::InvalidSignature
+        );
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hashes and the hashing rules of the Merkelized collections.
+
+#[cfg(feature = "blake2b-hashing")]
+use blake2::{
+    digest::{Input, VariableOutput},
+    VarBlake2b,
+};
+#[cfg(not(feature = "blake2b-hashing"))]
+use sha2::{Digest, Sha256};
+
+use core::fmt;
+
+use crate::map_proof::ProofPath;
+
+/// Number of bytes in a `Hash`.
+pub const HASH_SIZE: usize = 32;
+
+/// A digest of the hash function used by the network.
+///
+/// The bytes of the hash are the same as in `exonum_crypto::Hash`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hash([u8; HASH_SIZE]);
+
+impl Hash {
+    /// Creates a hash from the given bytes.
+    pub const fn new(bytes: [u8; HASH_SIZE]) -> Self {
+        Hash(bytes)
+    }
+
+    /// Creates a hash filled with zeros.
+    pub const fn zero() -> Self {
+        Hash([0; HASH_SIZE])
+    }
+
+    /// Creates a hash from the given slice. Returns `None` if the slice has a wrong length.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != HASH_SIZE {
+            return None;
+        }
+        let mut inner = [0; HASH_SIZE];
+        inner.copy_from_slice(bytes);
+        Some(Hash(inner))
+    }
+
+    /// Returns the bytes of the hash.
+    pub fn as_bytes(&self) -> &[u8; HASH_SIZE] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Hash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hash(")?;
+        for byte in self.0.iter().take(4) {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "...)")
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Incremental computation of a hash.
+pub(crate) struct HashStream {
+    #[cfg(not(feature = "blake2b-hashing"))]
+    state: Sha256,
+    #[cfg(feature = "blake2b-hashing")]
+    state: VarBlake2b,
+}
+
+impl HashStream {
+    pub(crate) fn new() -> Self {
+        Self {
+            #[cfg(not(feature = "blake2b-hashing"))]
+            state: Sha256::new(),
+            #[cfg(feature = "blake2b-hashing")]
+            state: VarBlake2b::new(HASH_SIZE).expect("HASH_SIZE is a valid BLAKE2b output size"),
+        }
+    }
+
+    pub(crate) fn update(mut self, chunk: &[u8]) -> Self {
+        self.state.input(chunk);
+        self
+    }
+
+    pub(crate) fn hash(self) -> Hash {
+        let mut digest = [0; HASH_SIZE];
+        #[cfg(not(feature = "blake2b-hashing"))]
+        digest.copy_from_slice(&self.state.result());
+        #[cfg(feature = "blake2b-hashing")]
+        self.state
+            .variable_result(|bytes| digest.copy_from_slice(bytes));
+        Hash(digest)
+    }
+}
+
+/// Calculates a hash of a bytes slice, like `exonum_crypto::hash`.
+pub fn hash(data: &[u8]) -> Hash {
+    HashStream::new().update(data).hash()
+}
+
+/// Hash prefixes of the Merkelized collections, the same as in `exonum_merkledb::HashTag`.
+///
+/// Different prefixes are used for the leaves and the branches of the trees to secure
+/// the proofs from the pre-image attack.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy)]
+pub enum HashTag {
+    /// Hash prefix of a blob.
+    Blob = 0,
+    /// Hash prefix of a branch node of the merkle tree.
+    ListBranchNode = 1,
+    /// Hash prefix of the list object.
+    ListNode = 2,
+    /// Hash prefix of the map object.
+    MapNode = 3,
+    /// Hash prefix of the map branch node object.
+    MapBranchNode = 4,
+}
+
+impl HashTag {
+    fn hash_stream(self) -> HashStream {
+        HashStream::new().update(&[self as u8])
+    }
+
+    /// Hash of a leaf of the list Merkle tree.
+    pub fn hash_leaf(value: &[u8]) -> Hash {
+        HashTag::Blob.hash_stream().update(value).hash()
+    }
+
+    /// Hash of a branch node of the list Merkle tree.
+    pub fn hash_node(left_hash: &Hash, right_hash: &Hash) -> Hash {
+        HashTag::ListBranchNode
+            .hash_stream()
+            .update(left_hash.as_ref())
+            .update(right_hash.as_ref())
+            .hash()
+    }
+
+    /// Hash of a branch node of the list Merkle tree with one child.
+    pub fn hash_single_node(hash: &Hash) -> Hash {
+        HashTag::ListBranchNode
+            .hash_stream()
+            .update(hash.as_ref())
+            .hash()
+    }
+
+    /// Hash of the list object.
+    ///
+    /// ```text
+    /// h = hash( HashTag::ListNode || len as u64 || merkle_root )
+    /// ```
+    pub fn hash_list_node(len: u64, root: Hash) -> Hash {
+        HashTag::ListNode
+            .hash_stream()
+            .update(&len.to_le_bytes())
+            .update(root.as_ref())
+            .hash()
+    }
+
+    /// Hash of the map object.
+    ///
+    /// ```text
+    /// h = hash( HashTag::MapNode || merkle_root )
+    /// ```
+    pub fn hash_map_node(root: Hash) -> Hash {
+        HashTag::MapNode.hash_stream().update(root.as_ref()).hash()
+    }
+
+    /// Hash of the map branch node.
+    ///
+    /// ```text
+    /// h = hash( HashTag::MapBranchNode || <left_hash> || <right_hash> || <left_key> || <right_key> )
+    /// ```
+    pub fn hash_map_branch(branch_node: &[u8]) -> Hash {
+        HashTag::MapBranchNode
+            .hash_stream()
+            .update(branch_node)
+            .hash()
+    }
+
+    /// Hash of the map with a single entry.
+    ///
+    /// ```text
+    /// h = hash( HashTag::MapBranchNode || <key> || <child_hash> )
+    /// ```
+    pub fn hash_single_entry_map(path: &ProofPath, h: &Hash) -> Hash {
+        HashTag::MapBranchNode
+            .hash_stream()
+            .update(path.as_bytes())
+            .update(h.as_ref())
+            .hash()
+    }
+}
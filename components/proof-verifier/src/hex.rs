@@ -0,0 +1,112 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deserialization of the byte values represented as hex strings in JSON.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+
+use crate::{
+    hash::{Hash, HASH_SIZE},
+    signature::{PublicKey, PUBLIC_KEY_LENGTH},
+};
+
+fn decode_digit(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decodes a hex string into bytes.
+pub(crate) fn decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    s.chunks(2)
+        .map(|pair| Some(decode_digit(pair[0])? << 4 | decode_digit(pair[1])?))
+        .collect()
+}
+
+/// Visitor of the hex strings with the expected number of bytes.
+struct HexVisitor {
+    len: Option<usize>,
+}
+
+impl<'de> Visitor<'de> for HexVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.len {
+            Some(len) => write!(f, "hex string with {} bytes", len),
+            None => write!(f, "hex string"),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Vec<u8>, E> {
+        decode(value)
+            .filter(|bytes| self.len.map_or(true, |len| bytes.len() == len))
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+/// Deserializes the bytes represented as a hex string.
+pub(crate) fn deserialize_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_str(HexVisitor { len: None })
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = deserializer.deserialize_str(HexVisitor {
+            len: Some(HASH_SIZE),
+        })?;
+        Ok(Hash::from_slice(&bytes).expect("hex string has the size of the hash"))
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = deserializer.deserialize_str(HexVisitor {
+            len: Some(PUBLIC_KEY_LENGTH),
+        })?;
+        Ok(PublicKey::from_slice(&bytes).expect("hex string has the size of the public key"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn decode_hex() {
+        assert_eq!(decode("00ff1A").unwrap(), [0x00, 0xff, 0x1a]);
+        assert_eq!(decode("").unwrap(), []);
+        assert!(decode("abc").is_none());
+        assert!(decode("zz").is_none());
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of Exonum blocks and Merkle proofs without the standard library.
+//!
+//! The crate contains the verification side of the proofs produced by an Exonum node:
+//!
+//! - [`ListProof`] and [`MapProof`] restore the root hashes of `ProofListIndex`
+//!   and `ProofMapIndex` from the proofs returned by the node.
+//! - [`BlockProof`] checks that a block header is signed by the supermajority
+//!   of the validators with their `Precommit` messages.
+//! - [`PublicKey::verify`] verifies ed25519 signatures.
+//!
+//! The crate is `no_std` and only needs an allocator, so it can be used by light clients
+//! running in embedded environments and WebAssembly, without pulling in the storage
+//! and networking dependencies of the node. The proofs can be deserialized from the JSON
+//! returned by the node with the `serde` feature (enabled by default).
+//!
+//! The hash function is SHA-256; networks using BLAKE2b (see
+//! `exonum_crypto::HashAlgorithm`) are supported with the `blake2b-hashing` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use exonum_proof_verifier::{HashTag, ListProof};
+//!
+//! // A proof for the only element of the list `[1_u32]`.
+//! let proof = ListProof::Leaf(1_u32);
+//! let list_hash = HashTag::hash_list_node(1, HashTag::hash_leaf(&1_u32.to_le_bytes()));
+//! let elements = proof.validate(list_hash, 1).unwrap();
+//! assert_eq!(elements, vec![(0, &1_u32)]);
+//! ```
+//!
+//! [`ListProof`]: enum.ListProof.html
+//! [`MapProof`]: struct.MapProof.html
+//! [`BlockProof`]: struct.BlockProof.html
+//! [`PublicKey::verify`]: struct.PublicKey.html#method.verify
+
+#![no_std]
+#![warn(missing_debug_implementations, missing_docs)]
+
+extern crate alloc;
+
+pub use self::block::{BlockHeader, BlockProof, BlockProofError, Precommit};
+pub use self::hash::{hash, Hash, HashTag, HASH_SIZE};
+pub use self::list_proof::{ListProof, ListProofError};
+pub use self::map_proof::{CheckedMapProof, MapProof, MapProofError, ProofPath};
+pub use self::signature::{PublicKey, Signature, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+pub use self::values::{BinaryValue, ObjectHash};
+
+mod block;
+mod hash;
+mod list_proof;
+mod map_proof;
+mod protobuf;
+mod signature;
+mod values;
+
+#[cfg(feature = "serde")]
+mod hex;
@@ -0,0 +1,267 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the proofs of `ProofListIndex`.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use crate::{
+    hash::{Hash, HashTag},
+    values::BinaryValue,
+};
+
+/// Position of a node in the list Merkle tree.
+#[derive(Debug, Clone, Copy)]
+struct ProofListKey {
+    height: u8,
+    index: u64,
+}
+
+impl ProofListKey {
+    fn left(self) -> Self {
+        Self {
+            height: self.height - 1,
+            index: self.index << 1,
+        }
+    }
+
+    fn right(self) -> Self {
+        Self {
+            height: self.height - 1,
+            index: (self.index << 1) + 1,
+        }
+    }
+}
+
+/// A proof of existence of the list elements or of absence of an element,
+/// in the same format as `exonum_merkledb::ListProof`.
+///
+/// # JSON serialization
+///
+/// The proof is deserialized from the same JSON as returned by the node:
+///
+/// - `{ "left": ListProof, "right": ListProof }` for `Full` branches
+/// - `{ "left": ListProof, "right": Hash }` or `{ "left": ListProof }` for `Left` branches
+/// - `{ "left": Hash, "right": ListProof }` for `Right` branches
+/// - `{ "val": V }` for `Leaf`s
+/// - `{ "length": u64, "hash": Hash }` for `Absent` proofs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListProof<V> {
+    /// A branch of proof in which both children contain requested elements.
+    Full(Box<ListProof<V>>, Box<ListProof<V>>),
+    /// A branch of proof in which only the left child contains requested elements.
+    Left(Box<ListProof<V>>, Option<Hash>),
+    /// A branch of proof in which only the right child contains requested elements.
+    Right(Hash, Box<ListProof<V>>),
+    /// A leaf of the proof with the requested element.
+    Leaf(V),
+    /// Proof of absence of the requested elements: the length and the Merkle root
+    /// of the list.
+    Absent {
+        /// Length of the list.
+        length: u64,
+        /// Merkle root of the list.
+        merkle_root: Hash,
+    },
+}
+
+/// An error that is returned when the list proof is invalid.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ListProofError {
+    /// The proof is too short and does not correspond to the height of the tree.
+    UnexpectedLeaf,
+    /// The proof is too long and does not correspond to the height of the tree.
+    UnexpectedBranch,
+    /// The hash of the proof is not equal to the trusted root hash.
+    UnmatchedRootHash,
+}
+
+impl fmt::Display for ListProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ListProofError::UnexpectedLeaf => {
+                "proof is too short and does not correspond to the height of the tree"
+            }
+            ListProofError::UnexpectedBranch => {
+                "proof is too long and does not correspond to the height of the tree"
+            }
+            ListProofError::UnmatchedRootHash => {
+                "hash of the proof is not equal to the trusted root hash"
+            }
+        })
+    }
+}
+
+impl<V: BinaryValue> ListProof<V> {
+    fn collect<'a>(
+        &'a self,
+        key: ProofListKey,
+        vec: &mut Vec<(u64, &'a V)>,
+    ) -> Result<Hash, ListProofError> {
+        if key.height == 0 {
+            return Err(ListProofError::UnexpectedBranch);
+        }
+        let hash = match *self {
+            ListProof::Full(ref left, ref right) => HashTag::hash_node(
+                &left.collect(key.left(), vec)?,
+                &right.collect(key.right(), vec)?,
+            ),
+            ListProof::Left(ref left, Some(ref right)) => {
+                HashTag::hash_node(&left.collect(key.left(), vec)?, right)
+            }
+            ListProof::Left(ref left, None) => {
+                HashTag::hash_single_node(&left.collect(key.left(), vec)?)
+            }
+            ListProof::Right(ref left, ref right) => {
+                HashTag::hash_node(left, &right.collect(key.right(), vec)?)
+            }
+            ListProof::Leaf(ref value) => {
+                if key.height > 1 {
+                    return Err(ListProofError::UnexpectedLeaf);
+                }
+                vec.push((key.index, value));
+                HashTag::hash_leaf(&value.to_bytes())
+            }
+            ListProof::Absent {
+                length,
+                merkle_root,
+            } => HashTag::hash_list_node(length, merkle_root),
+        };
+        Ok(hash)
+    }
+
+    /// Verifies the correctness of the proof by the trusted list hash and the number of
+    /// elements in the list, like `exonum_merkledb::ListProof::validate`.
+    ///
+    /// If the proof is valid, a vector with indices and references to elements is returned.
+    /// The vector is empty for the proofs of absence.
+    pub fn validate(
+        &self,
+        expected_list_hash: Hash,
+        len: u64,
+    ) -> Result<Vec<(u64, &V)>, ListProofError> {
+        let mut vec = Vec::new();
+        let height = len.next_power_of_two().trailing_zeros() as u8 + 1;
+        let root_hash = self.collect(ProofListKey { height, index: 0 }, &mut vec)?;
+
+        let list_hash = match *self {
+            ListProof::Absent { .. } => root_hash,
+            _ => HashTag::hash_list_node(len, root_hash),
+        };
+        if list_hash != expected_list_hash {
+            return Err(ListProofError::UnmatchedRootHash);
+        }
+        Ok(vec)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use alloc::boxed::Box;
+
+    use serde::{Deserialize, Deserializer};
+
+    use super::ListProof;
+    use crate::hash::Hash;
+
+    // The variants are distinguished by the names of the fields and the types of
+    // their values (hashes are strings and the nested proofs are objects), so the order
+    // of the variants matters.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ListProofRepr<V> {
+        Leaf {
+            val: V,
+        },
+        Absent {
+            length: u64,
+            hash: Hash,
+        },
+        Right {
+            left: Hash,
+            right: Box<ListProof<V>>,
+        },
+        Left {
+            left: Box<ListProof<V>>,
+            #[serde(default)]
+            right: Option<Hash>,
+        },
+        Full {
+            left: Box<ListProof<V>>,
+            right: Box<ListProof<V>>,
+        },
+    }
+
+    impl<'de, V> Deserialize<'de> for ListProof<V>
+    where
+        V: Deserialize<'de>,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(match ListProofRepr::deserialize(deserializer)? {
+                ListProofRepr::Leaf { val } => ListProof::Leaf(val),
+                ListProofRepr::Absent { length, hash } => ListProof::Absent {
+                    length,
+                    merkle_root: hash,
+                },
+                ListProofRepr::Right { left, right } => ListProof::Right(left, right),
+                ListProofRepr::Left { left, right } => ListProof::Left(left, right),
+                ListProofRepr::Full { left, right } => ListProof::Full(left, right),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec};
+
+    use super::*;
+
+    #[test]
+    fn validate_two_elements() {
+        let (first, second) = (1_u32, 2_u32);
+        let root = HashTag::hash_node(
+            &HashTag::hash_leaf(&first.to_bytes()),
+            &HashTag::hash_leaf(&second.to_bytes()),
+        );
+        let list_hash = HashTag::hash_list_node(2, root);
+
+        let proof = ListProof::Full(
+            Box::new(ListProof::Leaf(first)),
+            Box::new(ListProof::Leaf(second)),
+        );
+        assert_eq!(
+            proof.validate(list_hash, 2).unwrap(),
+            vec![(0, &first), (1, &second)]
+        );
+
+        let proof = ListProof::Right(
+            HashTag::hash_leaf(&first.to_bytes()),
+            Box::new(ListProof::Leaf(second)),
+        );
+        assert_eq!(proof.validate(list_hash, 2).unwrap(), vec![(1, &second)]);
+        assert_eq!(
+            proof.validate(list_hash, 3).unwrap_err(),
+            ListProofError::UnexpectedLeaf
+        );
+        assert_eq!(
+            proof.validate(Hash::zero(), 2).unwrap_err(),
+            ListProofError::UnmatchedRootHash
+        );
+    }
+}
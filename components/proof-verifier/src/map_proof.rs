@@ -0,0 +1,646 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the proofs of `ProofMapIndex`.
+
+use alloc::vec::Vec;
+use core::{
+    cmp::{min, Ordering},
+    fmt,
+};
+
+use crate::{
+    hash::{Hash, HashTag, HASH_SIZE},
+    values::{BinaryValue, ObjectHash},
+};
+
+/// This prefix defines a node as a branch.
+const BRANCH_KEY_PREFIX: u8 = 0;
+/// This prefix defines a node as a leaf.
+const LEAF_KEY_PREFIX: u8 = 1;
+/// Size in bytes of the key of a path.
+const KEY_SIZE: usize = HASH_SIZE;
+/// Size in bytes of the `ProofPath`.
+const PROOF_PATH_SIZE: usize = KEY_SIZE + 2;
+/// Position of the beginning of the key.
+const PROOF_PATH_KEY_POS: usize = 1;
+/// Position of the byte with total length of the branch.
+const PROOF_PATH_LEN_POS: usize = KEY_SIZE + 1;
+/// Maximal size of the compressed representation of a path: 2 bytes of the length
+/// in LEB128 and the key.
+const MAX_COMPRESSED_PATH_SIZE: usize = KEY_SIZE + 2;
+
+/// Resets bits higher than the given pos.
+fn reset_bits(value: &mut u8, pos: u16) {
+    let reset_bits_mask = !(255_u8 << pos as u8);
+    *value &= reset_bits_mask;
+}
+
+/// Path to a node of the Merkle Patricia tree of `ProofMapIndex`, in the same binary
+/// format as `exonum_merkledb::proof_map_index::ProofPath`.
+///
+/// Paths to the leaves are the hashes of the keys of the map, and paths to the branches
+/// are their prefixes.
+///
+/// # JSON serialization
+///
+/// Deserialized from a string of `'0'` and `'1'` chars, corresponding exactly to bits
+/// in the path.
+#[derive(Clone, Copy)]
+pub struct ProofPath {
+    bytes: [u8; PROOF_PATH_SIZE],
+}
+
+impl ProofPath {
+    /// Creates a path to the leaf with the given key.
+    pub fn new(key: &impl ObjectHash) -> Self {
+        Self::from_key(key.object_hash())
+    }
+
+    fn from_key(key: Hash) -> Self {
+        let mut bytes = [0; PROOF_PATH_SIZE];
+        bytes[0] = LEAF_KEY_PREFIX;
+        bytes[PROOF_PATH_KEY_POS..PROOF_PATH_KEY_POS + KEY_SIZE].copy_from_slice(key.as_ref());
+        Self { bytes }
+    }
+
+    /// Checks if this is a path to a leaf node.
+    pub fn is_leaf(&self) -> bool {
+        self.bytes[0] == LEAF_KEY_PREFIX
+    }
+
+    /// Returns the binary representation of the path.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the length of the path in bits.
+    pub fn len(&self) -> u16 {
+        if self.is_leaf() {
+            KEY_SIZE as u16 * 8
+        } else {
+            u16::from(self.bytes[PROOF_PATH_LEN_POS])
+        }
+    }
+
+    /// Returns `true` if the path has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn raw_key(&self) -> &[u8] {
+        &self.bytes[PROOF_PATH_KEY_POS..PROOF_PATH_KEY_POS + KEY_SIZE]
+    }
+
+    /// Returns a branch path with the first `len` bits of this path.
+    /// The bits after the end of the path are reset.
+    fn prefix(&self, len: u16) -> Self {
+        debug_assert!(len < KEY_SIZE as u16 * 8);
+        let mut bytes = [0; PROOF_PATH_SIZE];
+        bytes[0] = BRANCH_KEY_PREFIX;
+        let whole_bytes = (len as usize + 7) / 8;
+        bytes[PROOF_PATH_KEY_POS..PROOF_PATH_KEY_POS + whole_bytes]
+            .copy_from_slice(&self.raw_key()[..whole_bytes]);
+        if len % 8 != 0 {
+            reset_bits(&mut bytes[PROOF_PATH_KEY_POS + whole_bytes - 1], len % 8);
+        }
+        bytes[PROOF_PATH_LEN_POS] = len as u8;
+        Self { bytes }
+    }
+
+    /// Returns the number of matching bits with `other`.
+    fn common_prefix_len(&self, other: &Self) -> u16 {
+        let to = (min(self.len(), other.len()) as usize + 7) / 8;
+        let max_len = min(self.len(), other.len());
+        for i in 0..to {
+            let x = self.raw_key()[i] ^ other.raw_key()[i];
+            if x != 0 {
+                let tail = x.trailing_zeros() as u16;
+                return min(i as u16 * 8 + tail, max_len);
+            }
+        }
+        max_len
+    }
+
+    /// Checks if this path contains the other path as a prefix.
+    fn starts_with(&self, other: &Self) -> bool {
+        self.common_prefix_len(other) == other.len()
+    }
+
+    /// Writes the compressed representation of the path used in the hashes of the branches:
+    /// the length of the path in bits in LEB128 followed by the significant bytes of
+    /// the path.
+    fn write_compressed(&self, buffer: &mut [u8]) -> usize {
+        let bits_len = self.len();
+        let whole_bytes_len = (bits_len as usize + 7) / 8;
+
+        let mut pos = 0;
+        let mut value = bits_len;
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                buffer[pos] = byte;
+                pos += 1;
+                break;
+            }
+            buffer[pos] = byte | 0x80;
+            pos += 1;
+        }
+
+        buffer[pos..pos + whole_bytes_len].copy_from_slice(&self.raw_key()[..whole_bytes_len]);
+        pos += whole_bytes_len;
+        if whole_bytes_len > 0 && bits_len % 8 != 0 {
+            reset_bits(&mut buffer[pos - 1], bits_len % 8);
+        }
+        pos
+    }
+}
+
+impl PartialEq for ProofPath {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.starts_with(other)
+    }
+}
+
+impl Eq for ProofPath {}
+
+impl PartialOrd for ProofPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ProofPath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let right_bit = min(self.len(), other.len());
+        let right = (right_bit as usize + 7) / 8;
+
+        for i in 0..right {
+            let (mut self_byte, mut other_byte) = (self.raw_key()[i], other.raw_key()[i]);
+
+            if i + 1 == right && right_bit % 8 != 0 {
+                // Cut possible junk after the end of path(s).
+                let tail = right_bit % 8;
+                reset_bits(&mut self_byte, tail);
+                reset_bits(&mut other_byte, tail);
+            }
+
+            // The bits are ordered from the lowest to the highest one, so the path with
+            // the first differing bit set is larger.
+            let self_zeros = (self_byte & !other_byte).trailing_zeros();
+            let other_zeros = (!self_byte & other_byte).trailing_zeros();
+
+            let cmp = other_zeros.cmp(&self_zeros);
+            if cmp != Ordering::Equal {
+                return cmp;
+            }
+        }
+
+        self.len().cmp(&other.len())
+    }
+}
+
+impl fmt::Debug for ProofPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ProofPath(")?;
+        for index in 0..self.len() {
+            let byte = self.raw_key()[index as usize / 8];
+            let bit = (byte >> (index % 8)) & 1;
+            write!(f, "{}", bit)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// An error returned when a map proof is invalid.
+#[derive(Debug)]
+pub enum MapProofError {
+    /// Non-terminal node for a map consisting of a single node.
+    NonTerminalNode(ProofPath),
+
+    /// One path in the proof is a prefix of another path.
+    EmbeddedPaths {
+        /// Prefix key.
+        prefix: ProofPath,
+        /// Key containing the prefix.
+        path: ProofPath,
+    },
+
+    /// One path is mentioned several times in the proof.
+    DuplicatePath(ProofPath),
+
+    /// Entries in the proof are not ordered by increasing path.
+    InvalidOrdering(ProofPath, ProofPath),
+}
+
+impl fmt::Display for MapProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            MapProofError::NonTerminalNode(..) => "non-terminal node as a single key in proof",
+            MapProofError::EmbeddedPaths { .. } => "embedded paths in proof",
+            MapProofError::DuplicatePath(..) => "duplicate path in proof",
+            MapProofError::InvalidOrdering(..) => "invalid path ordering",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+struct MapProofEntry {
+    path: ProofPath,
+    hash: Hash,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+enum OptionalEntry<K, V> {
+    Missing { missing: K },
+    KV { key: K, value: V },
+}
+
+impl<K, V> OptionalEntry<K, V> {
+    fn key(&self) -> &K {
+        match *self {
+            OptionalEntry::Missing { ref missing } => missing,
+            OptionalEntry::KV { ref key, .. } => key,
+        }
+    }
+}
+
+/// A subset of the entries of a `ProofMapIndex` along with the proof, which allows
+/// restoring the root hash of the index, in the same format as `exonum_merkledb::MapProof`.
+///
+/// The proof can assert both the presence of the entries and the absence of keys.
+///
+/// # JSON serialization
+///
+/// The proof is deserialized from the same JSON as returned by the node, that is, an object
+/// with 2 array fields:
+///
+/// - `proof` is an array of `{ "path": ProofPath, "hash": Hash }` objects.
+/// - `entries` is an array with 2 kinds of objects: `{ "missing": K }` for keys missing from
+///   the underlying index, and `{ "key": K, "value": V }` for key-value pairs, existence of
+///   which is asserted by the proof.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct MapProof<K, V> {
+    entries: Vec<OptionalEntry<K, V>>,
+    proof: Vec<MapProofEntry>,
+}
+
+/// Version of `MapProof` obtained after verification.
+#[derive(Debug)]
+pub struct CheckedMapProof<K, V> {
+    entries: Vec<(K, Option<V>)>,
+    hash: Hash,
+}
+
+/// Computes the root hash of the Merkle Patricia tree backing the specified entries,
+/// in the same way as `exonum_merkledb::MapProof::check`.
+///
+/// The paths are added to the tree in the increasing order, keeping track of the rightmost
+/// nodes (the right contour) of the tree. `entries` are assumed to be sorted by the path
+/// in increasing order.
+fn collect(entries: &[MapProofEntry]) -> Result<Hash, MapProofError> {
+    fn common_prefix(x: &ProofPath, y: &ProofPath) -> ProofPath {
+        x.prefix(x.common_prefix_len(y))
+    }
+
+    fn hash_branch(left_child: &MapProofEntry, right_child: &MapProofEntry) -> Hash {
+        let mut bytes = [0_u8; 2 * HASH_SIZE + 2 * MAX_COMPRESSED_PATH_SIZE];
+        bytes[..HASH_SIZE].copy_from_slice(left_child.hash.as_ref());
+        bytes[HASH_SIZE..2 * HASH_SIZE].copy_from_slice(right_child.hash.as_ref());
+        let mut pos = 2 * HASH_SIZE;
+        pos += left_child.path.write_compressed(&mut bytes[pos..]);
+        pos += right_child.path.write_compressed(&mut bytes[pos..]);
+        HashTag::hash_map_branch(&bytes[..pos])
+    }
+
+    /// Folds two last entries in a contour and replaces them with the folded entry.
+    ///
+    /// Returns an updated common prefix between two last entries in the contour.
+    fn fold(contour: &mut Vec<MapProofEntry>, last_prefix: ProofPath) -> Option<ProofPath> {
+        let last_entry = contour.pop().unwrap();
+        let penultimate_entry = contour.pop().unwrap();
+
+        contour.push(MapProofEntry {
+            path: last_prefix,
+            hash: hash_branch(&penultimate_entry, &last_entry),
+        });
+
+        if contour.len() > 1 {
+            let penultimate_entry = contour[contour.len() - 2];
+            Some(common_prefix(&penultimate_entry.path, &last_prefix))
+        } else {
+            None
+        }
+    }
+
+    match entries.len() {
+        0 => Ok(Hash::zero()),
+
+        1 => {
+            if entries[0].path.is_leaf() {
+                Ok(HashTag::hash_single_entry_map(
+                    &entries[0].path,
+                    &entries[0].hash,
+                ))
+            } else {
+                Err(MapProofError::NonTerminalNode(entries[0].path))
+            }
+        }
+
+        _ => {
+            let mut contour: Vec<MapProofEntry> = Vec::with_capacity(8);
+            // invariant: equal to the common prefix of the 2 last nodes in the contour
+            let mut last_prefix = common_prefix(&entries[0].path, &entries[1].path);
+            contour.push(entries[0]);
+            contour.push(entries[1]);
+
+            for entry in entries.iter().skip(2) {
+                let new_prefix = common_prefix(&contour.last().unwrap().path, &entry.path);
+                let new_prefix_len = new_prefix.len();
+
+                while contour.len() > 1 && new_prefix_len < last_prefix.len() {
+                    if let Some(prefix) = fold(&mut contour, last_prefix) {
+                        last_prefix = prefix;
+                    }
+                }
+
+                contour.push(*entry);
+                last_prefix = new_prefix;
+            }
+
+            while contour.len() > 1 {
+                if let Some(prefix) = fold(&mut contour, last_prefix) {
+                    last_prefix = prefix;
+                }
+            }
+
+            Ok(contour[0].hash)
+        }
+    }
+}
+
+impl<K, V> MapProof<K, V> {
+    /// Retrieves references to existing and non-existing entries in the proof.
+    ///
+    /// Existing entries have `Some` value, non-existing have `None`.
+    /// This method does not perform any integrity checks of the proof.
+    pub fn all_entries_unchecked(&self) -> impl Iterator<Item = (&K, Option<&V>)> {
+        self.entries.iter().map(|e| match e {
+            OptionalEntry::Missing { ref missing } => (missing, None),
+            OptionalEntry::KV { ref key, ref value } => (key, Some(value)),
+        })
+    }
+}
+
+impl<K, V> MapProof<K, V>
+where
+    K: ObjectHash,
+    V: BinaryValue,
+{
+    fn precheck(&self) -> Result<(), MapProofError> {
+        use self::MapProofError::*;
+
+        // Check that entries in proof are in increasing order.
+        for w in self.proof.windows(2) {
+            let (prev_path, path) = (&w[0].path, &w[1].path);
+            match prev_path.cmp(path) {
+                Ordering::Less => {
+                    if path.starts_with(prev_path) {
+                        return Err(EmbeddedPaths {
+                            prefix: *prev_path,
+                            path: *path,
+                        });
+                    }
+                }
+                Ordering::Equal => return Err(DuplicatePath(*path)),
+                Ordering::Greater => return Err(InvalidOrdering(*prev_path, *path)),
+            }
+        }
+
+        // Check that no entry has a prefix among the paths in the proof entries.
+        // It suffices to check the closest smaller path in the proof entries.
+        for e in &self.entries {
+            let path = ProofPath::new(e.key());
+            match self.proof.binary_search_by(|pe| pe.path.cmp(&path)) {
+                Ok(_) => return Err(DuplicatePath(path)),
+                Err(index) if index > 0 => {
+                    let prev_path = &self.proof[index - 1].path;
+                    if path.starts_with(prev_path) {
+                        return Err(EmbeddedPaths {
+                            prefix: *prev_path,
+                            path,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consumes this proof producing a `CheckedMapProof` structure, which contains
+    /// the root hash of the map restored from the proof. The root hash should be compared
+    /// with the trusted hash of the map.
+    ///
+    /// Fails if the proof is malformed.
+    pub fn check(self) -> Result<CheckedMapProof<K, V>, MapProofError> {
+        self.precheck()?;
+        let (mut proof, entries) = (self.proof, self.entries);
+
+        proof.extend(entries.iter().filter_map(|e| match e {
+            OptionalEntry::KV { key, value } => Some(MapProofEntry {
+                path: ProofPath::new(key),
+                hash: HashTag::hash_leaf(&value.to_bytes()),
+            }),
+            OptionalEntry::Missing { .. } => None,
+        }));
+        proof.sort_unstable_by(|x, y| x.path.cmp(&y.path));
+
+        // Different entry keys may lead to the same path.
+        for window in proof.windows(2) {
+            if window[0].path == window[1].path {
+                return Err(MapProofError::DuplicatePath(window[0].path));
+            }
+        }
+
+        collect(&proof).map(|h| CheckedMapProof {
+            entries: entries
+                .into_iter()
+                .map(|e| match e {
+                    OptionalEntry::Missing { missing } => (missing, None),
+                    OptionalEntry::KV { key, value } => (key, Some(value)),
+                })
+                .collect(),
+            hash: HashTag::hash_map_node(h),
+        })
+    }
+}
+
+impl<K, V> CheckedMapProof<K, V> {
+    /// Retrieves references to keys that the proof shows as missing from the map.
+    pub fn missing_keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().filter_map(|kv| match *kv {
+            (ref key, None) => Some(key),
+            _ => None,
+        })
+    }
+
+    /// Retrieves references to key-value pairs that the proof shows as present in the map.
+    pub fn entries(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().filter_map(|kv| match *kv {
+            (ref key, Some(ref value)) => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns a hash of the map that this proof is constructed for.
+    pub fn root_hash(&self) -> Hash {
+        self.hash
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ProofPath {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{self, Unexpected, Visitor};
+
+        struct ProofPathVisitor;
+
+        impl<'de> Visitor<'de> for ProofPathVisitor {
+            type Value = ProofPath;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(
+                    formatter,
+                    "binary string with length between 1 and {}",
+                    KEY_SIZE * 8
+                )
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<ProofPath, E>
+            where
+                E: de::Error,
+            {
+                let len = value.len();
+                if len == 0 || len > 8 * KEY_SIZE {
+                    return Err(de::Error::invalid_value(Unexpected::Str(value), &self));
+                }
+
+                let mut bytes = [0_u8; KEY_SIZE];
+                for (i, ch) in value.chars().enumerate() {
+                    match ch {
+                        '0' => {}
+                        '1' => bytes[i / 8] += 1 << (i % 8),
+                        _ => return Err(de::Error::invalid_value(Unexpected::Str(value), &self)),
+                    }
+                }
+
+                let path = ProofPath::from_key(Hash::new(bytes));
+                Ok(if len == 8 * KEY_SIZE {
+                    path
+                } else {
+                    path.prefix(len as u16)
+                })
+            }
+        }
+
+        deserializer.deserialize_str(ProofPathVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::hash::hash;
+
+    #[test]
+    fn path_ordering() {
+        let path = ProofPath::new(&hash(&[1]));
+        let prefix = path.prefix(10);
+        assert!(prefix < path);
+        assert!(path.starts_with(&prefix));
+        assert_eq!(prefix.len(), 10);
+        assert_eq!(path.common_prefix_len(&prefix), 10);
+        assert_eq!(prefix.prefix(5), path.prefix(5));
+    }
+
+    #[test]
+    fn compressed_path() {
+        let path = ProofPath::new(&hash(&[1]));
+        let mut buffer = [0; MAX_COMPRESSED_PATH_SIZE];
+        assert_eq!(path.write_compressed(&mut buffer), KEY_SIZE + 2);
+        assert_eq!(buffer[..2], [0x80, 0x02]);
+        assert_eq!(buffer[2..], path.raw_key()[..]);
+
+        let prefix = path.prefix(9);
+        assert_eq!(prefix.write_compressed(&mut buffer), 3);
+        assert_eq!(buffer[0], 9);
+        assert_eq!(buffer[1], path.raw_key()[0]);
+        assert_eq!(buffer[2], path.raw_key()[1] & 1);
+    }
+
+    #[test]
+    fn single_entry_proof() {
+        let key = hash(&[1]);
+        let proof = MapProof {
+            entries: vec![OptionalEntry::KV { key, value: 5_u32 }],
+            proof: Vec::new(),
+        };
+        let checked = proof.check().unwrap();
+        let expected = HashTag::hash_map_node(HashTag::hash_single_entry_map(
+            &ProofPath::new(&key),
+            &HashTag::hash_leaf(&5_u32.to_bytes()),
+        ));
+        assert_eq!(checked.root_hash(), expected);
+        assert_eq!(checked.entries().collect::<Vec<_>>(), vec![(&key, &5_u32)]);
+    }
+
+    #[test]
+    fn invalid_proof_ordering() {
+        let (first, second) = (ProofPath::new(&hash(&[1])), ProofPath::new(&hash(&[2])));
+        let (first, second) = if first < second {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        let proof: MapProof<Hash, u32> = MapProof {
+            entries: Vec::new(),
+            proof: vec![
+                MapProofEntry {
+                    path: second,
+                    hash: Hash::zero(),
+                },
+                MapProofEntry {
+                    path: first,
+                    hash: Hash::zero(),
+                },
+            ],
+        };
+        match proof.check().unwrap_err() {
+            MapProofError::InvalidOrdering(..) => {}
+            e => panic!("Unexpected error: {:?}", e),
+        }
+    }
+}
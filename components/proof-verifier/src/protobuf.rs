@@ -0,0 +1,192 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal Protobuf encoding and decoding of the messages used in the block proofs.
+//!
+//! Only the wire format needed by `Block` and `Precommit` is supported: the scalar fields
+//! are varints, and the hashes are `exonum.Hash` messages with a single `bytes` field.
+
+use alloc::vec::Vec;
+
+use crate::hash::{Hash, HASH_SIZE};
+
+const WIRE_TYPE_VARINT: u8 = 0;
+const WIRE_TYPE_FIXED64: u8 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u8 = 2;
+const WIRE_TYPE_FIXED32: u8 = 5;
+
+/// Tag of the `data` field of the `exonum.Hash` message.
+const HASH_DATA_TAG: u8 = 1 << 3 | WIRE_TYPE_LENGTH_DELIMITED;
+
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+fn write_tag(buffer: &mut Vec<u8>, field: u32, wire_type: u8) {
+    write_varint(buffer, u64::from(field << 3 | u32::from(wire_type)));
+}
+
+/// Writes an unsigned integer field. Following proto3, zero values are omitted.
+pub(crate) fn write_uint_field(buffer: &mut Vec<u8>, field: u32, value: u64) {
+    if value != 0 {
+        write_tag(buffer, field, WIRE_TYPE_VARINT);
+        write_varint(buffer, value);
+    }
+}
+
+/// Writes an `exonum.Hash` field.
+pub(crate) fn write_hash_field(buffer: &mut Vec<u8>, field: u32, hash: &Hash) {
+    write_tag(buffer, field, WIRE_TYPE_LENGTH_DELIMITED);
+    write_varint(buffer, HASH_SIZE as u64 + 2);
+    buffer.push(HASH_DATA_TAG);
+    buffer.push(HASH_SIZE as u8);
+    buffer.extend_from_slice(hash.as_ref());
+}
+
+/// Value of a field read by the `Reader`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FieldValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> FieldValue<'a> {
+    /// Interprets the field value as an unsigned integer.
+    pub(crate) fn as_uint(self) -> Option<u64> {
+        match self {
+            FieldValue::Varint(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Interprets the field value as an `exonum.Hash` message.
+    pub(crate) fn as_hash(self) -> Option<Hash> {
+        let bytes = match self {
+            FieldValue::Bytes(bytes) => bytes,
+            _ => return None,
+        };
+        let mut hash = None;
+        let mut reader = Reader::new(bytes);
+        while let Some((field, value)) = reader.next_field()? {
+            if field == 1 {
+                match value {
+                    FieldValue::Bytes(data) => hash = Some(Hash::from_slice(data)?),
+                    _ => return None,
+                }
+            }
+        }
+        hash
+    }
+}
+
+/// Reader of the fields of a Protobuf message.
+#[derive(Debug)]
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn read_varint(&mut self) -> Option<u64> {
+        let mut value = 0_u64;
+        for (i, &byte) in self.bytes.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.bytes = &self.bytes[i + 1..];
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(bytes)
+    }
+
+    /// Reads the next field of the message. Returns `Some(None)` at the end of the message
+    /// and `None` if the message is malformed.
+    pub(crate) fn next_field(&mut self) -> Option<Option<(u32, FieldValue<'a>)>> {
+        if self.bytes.is_empty() {
+            return Some(None);
+        }
+        let tag = self.read_varint()?;
+        let field = (tag >> 3) as u32;
+        let value = match (tag & 7) as u8 {
+            WIRE_TYPE_VARINT => FieldValue::Varint(self.read_varint()?),
+            WIRE_TYPE_FIXED64 => {
+                self.read_bytes(8)?;
+                FieldValue::Fixed
+            }
+            WIRE_TYPE_LENGTH_DELIMITED => {
+                let len = self.read_varint()? as usize;
+                FieldValue::Bytes(self.read_bytes(len)?)
+            }
+            WIRE_TYPE_FIXED32 => {
+                self.read_bytes(4)?;
+                FieldValue::Fixed
+            }
+            _ => return None,
+        };
+        Some(Some((field, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint_roundtrip() {
+        for &value in &[
+            1_u64,
+            127,
+            128,
+            300,
+            u64::from(u32::max_value()),
+            u64::max_value(),
+        ] {
+            let mut buffer = Vec::new();
+            write_uint_field(&mut buffer, 2, value);
+            let mut reader = Reader::new(&buffer);
+            let (field, read) = reader.next_field().unwrap().unwrap();
+            assert_eq!(field, 2);
+            assert_eq!(read.as_uint(), Some(value));
+            assert!(reader.next_field().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn hash_roundtrip() {
+        let hash = Hash::new([7; HASH_SIZE]);
+        let mut buffer = Vec::new();
+        write_hash_field(&mut buffer, 4, &hash);
+        assert_eq!(buffer[..4], [4 << 3 | 2, 34, 0x0a, 0x20]);
+        let (field, read) = Reader::new(&buffer).next_field().unwrap().unwrap();
+        assert_eq!(field, 4);
+        assert_eq!(read.as_hash(), Some(hash));
+    }
+}
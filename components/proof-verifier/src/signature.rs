@@ -0,0 +1,120 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of ed25519 signatures.
+
+use core::fmt;
+
+/// Number of bytes in a public key.
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+
+/// Number of bytes in a signature.
+pub const SIGNATURE_LENGTH: usize = 64;
+
+/// Ed25519 public key, with the same bytes as `exonum_crypto::PublicKey`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PublicKey([u8; PUBLIC_KEY_LENGTH]);
+
+impl PublicKey {
+    /// Creates a public key from the given bytes.
+    pub const fn new(bytes: [u8; PUBLIC_KEY_LENGTH]) -> Self {
+        PublicKey(bytes)
+    }
+
+    /// Creates a public key from the given slice. Returns `None` if the slice
+    /// has a wrong length.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != PUBLIC_KEY_LENGTH {
+            return None;
+        }
+        let mut inner = [0; PUBLIC_KEY_LENGTH];
+        inner.copy_from_slice(bytes);
+        Some(PublicKey(inner))
+    }
+
+    /// Returns the bytes of the key.
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LENGTH] {
+        &self.0
+    }
+
+    /// Verifies that the `signature` of the `data` is made with the secret key
+    /// corresponding to this public key.
+    pub fn verify(&self, data: &[u8], signature: &Signature) -> bool {
+        let public_key = match ed25519_dalek::PublicKey::from_bytes(&self.0) {
+            Ok(key) => key,
+            Err(_) => return false,
+        };
+        let signature = match ed25519_dalek::Signature::from_bytes(&signature.0) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        public_key.verify(data, &signature).is_ok()
+    }
+}
+
+impl AsRef<[u8]> for PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PublicKey(")?;
+        for byte in self.0.iter().take(4) {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "...)")
+    }
+}
+
+/// Ed25519 signature, with the same bytes as `exonum_crypto::Signature`.
+#[derive(Clone, Copy)]
+pub struct Signature([u8; SIGNATURE_LENGTH]);
+
+impl Signature {
+    /// Creates a signature from the given bytes.
+    pub const fn new(bytes: [u8; SIGNATURE_LENGTH]) -> Self {
+        Signature(bytes)
+    }
+
+    /// Creates a signature from the given slice. Returns `None` if the slice
+    /// has a wrong length.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != SIGNATURE_LENGTH {
+            return None;
+        }
+        let mut inner = [0; SIGNATURE_LENGTH];
+        inner.copy_from_slice(bytes);
+        Some(Signature(inner))
+    }
+}
+
+impl PartialEq for Signature {
+    fn eq(&self, other: &Self) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for Signature {}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signature(")?;
+        for byte in self.0.iter().take(4) {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "...)")
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Binary representation and hashes of the values in the proofs.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{
+    hash::{hash, Hash},
+    signature::PublicKey,
+};
+
+/// The binary representation of a value stored in the Merkelized collections.
+///
+/// The representation must be the same as `exonum_merkledb::BinaryValue::to_bytes`
+/// of the corresponding type stored by the node.
+pub trait BinaryValue {
+    /// Serializes the value to the vector of bytes.
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+/// The hash of a key of the `ProofMapIndex`, which defines its position in the map.
+///
+/// The hash must be the same as `exonum_merkledb::ObjectHash::object_hash` of the
+/// corresponding type used by the node.
+pub trait ObjectHash {
+    /// Returns the hash of the value.
+    fn object_hash(&self) -> Hash;
+}
+
+macro_rules! impl_object_hash_for_binary_value {
+    ($( $type:ty ),*) => {
+        $(
+            impl ObjectHash for $type {
+                fn object_hash(&self) -> Hash {
+                    hash(&self.to_bytes())
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_binary_value_scalar {
+    ($( $type:ty ),*) => {
+        $(
+            impl BinaryValue for $type {
+                fn to_bytes(&self) -> Vec<u8> {
+                    self.to_le_bytes().to_vec()
+                }
+            }
+
+            impl_object_hash_for_binary_value! { $type }
+        )*
+    };
+}
+
+impl_binary_value_scalar! { u8, u16, u32, u64, u128, i8, i16, i32, i64, i128 }
+
+impl BinaryValue for () {
+    fn to_bytes(&self) -> Vec<u8> {
+        Vec::new()
+    }
+}
+
+impl BinaryValue for bool {
+    fn to_bytes(&self) -> Vec<u8> {
+        vec![*self as u8]
+    }
+}
+
+impl BinaryValue for Vec<u8> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+impl BinaryValue for String {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl BinaryValue for PublicKey {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+impl_object_hash_for_binary_value! { (), bool, Vec<u8>, String, PublicKey }
+
+impl BinaryValue for Hash {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+}
+
+/// Just returns the origin hash.
+impl ObjectHash for Hash {
+    fn object_hash(&self) -> Hash {
+        *self
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks the block proofs of the networks with domain separated signatures.
+
+use chrono::Utc;
+use exonum::{
+    blockchain::{Block, BlockProof as NodeBlockProof},
+    crypto::{self, gen_keypair, CryptoHash},
+    helpers::{Height, Round, ValidatorId},
+    messages::{Message, Precommit},
+};
+
+use exonum_proof_verifier::{BlockProof, BlockProofError, Hash, PublicKey};
+
+#[test]
+fn block_proofs_with_network_id() {
+    let network_id = crypto::hash(b"staging");
+
+    let keys = (0..4).map(|_| gen_keypair()).collect::<Vec<_>>();
+    let validator_keys = keys
+        .iter()
+        .map(|(pk, _)| PublicKey::from_slice(pk.as_ref()).unwrap())
+        .collect::<Vec<_>>();
+
+    let block = Block::new(
+        ValidatorId(0),
+        Height(2),
+        0,
+        &crypto::hash(&[1]),
+        &crypto::hash(&[2]),
+        &crypto::hash(&[3]),
+    );
+    let precommits = keys
+        .iter()
+        .enumerate()
+        .map(|(i, (pk, sk))| {
+            let precommit = Precommit::new(
+                ValidatorId(i as u16),
+                Height(2),
+                Round(1),
+                &crypto::hash(&[4]),
+                &block.hash(),
+                Utc::now(),
+            );
+            Message::concrete_in_network(precommit, Some(network_id), *pk, sk)
+        })
+        .collect::<Vec<_>>();
+
+    let node_proof = NodeBlockProof {
+        block,
+        precommits,
+        certificate: None,
+    };
+    let proof: BlockProof =
+        serde_json::from_value(serde_json::to_value(&node_proof).unwrap()).unwrap();
+
+    let network_id = Hash::from_slice(network_id.as_ref()).unwrap();
+    assert_eq!(
+        proof
+            .verify_in_network(&validator_keys, Some(&network_id))
+            .unwrap()
+            .len(),
+        4
+    );
+    // The precommits signed for one network are invalid in the other ones.
+    assert_eq!(
+        proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::InvalidSignature
+    );
+    let other_network_id = Hash::from_slice(crypto::hash(b"production").as_ref()).unwrap();
+    assert_eq!(
+        proof
+            .verify_in_network(&validator_keys, Some(&other_network_id))
+            .unwrap_err(),
+        BlockProofError::InvalidSignature
+    );
+}
@@ -0,0 +1,187 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checks that the proofs created by the node are verified by the crate.
+
+use chrono::Utc;
+use exonum::{
+    blockchain::{Block, BlockProof as NodeBlockProof},
+    crypto::{self, gen_keypair, CryptoHash},
+    helpers::{Height, Round, ValidatorId},
+    messages::{Message, Precommit},
+};
+use exonum_merkledb::{Database, ObjectHash, ProofListIndex, ProofMapIndex, TemporaryDB};
+use serde_json::json;
+
+use exonum_proof_verifier::{BlockProof, BlockProofError, Hash, ListProof, MapProof, PublicKey};
+
+fn convert_hash(hash: &crypto::Hash) -> Hash {
+    Hash::from_slice(hash.as_ref()).unwrap()
+}
+
+#[test]
+fn list_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut list = ProofListIndex::new("list", &fork);
+    list.extend(0_u64..10);
+    let list_hash = convert_hash(&list.object_hash());
+
+    let node_proof = list.get_range_proof(3..7);
+    let proof: ListProof<u64> =
+        serde_json::from_value(serde_json::to_value(&node_proof).unwrap()).unwrap();
+    let elements = proof.validate(list_hash, list.len()).unwrap();
+    assert_eq!(elements, vec![(3, &3), (4, &4), (5, &5), (6, &6)]);
+    assert!(proof.validate(Hash::zero(), list.len()).is_err());
+
+    let node_proof = list.get_range_proof(20..30);
+    let proof: ListProof<u64> =
+        serde_json::from_value(serde_json::to_value(&node_proof).unwrap()).unwrap();
+    assert!(proof.validate(list_hash, list.len()).unwrap().is_empty());
+}
+
+#[test]
+fn map_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut map = ProofMapIndex::new("map", &fork);
+    for i in 0_u64..10 {
+        map.put(&crypto::hash(&i.to_le_bytes()), i);
+    }
+    let map_hash = convert_hash(&map.object_hash());
+
+    let present_key = crypto::hash(&3_u64.to_le_bytes());
+    let missing_key = crypto::hash(&[1, 2, 3]);
+    let node_proof = map.get_multiproof(vec![present_key, missing_key]);
+    let proof: MapProof<Hash, u64> =
+        serde_json::from_value(serde_json::to_value(&node_proof).unwrap()).unwrap();
+    let checked = proof.check().unwrap();
+    assert_eq!(checked.root_hash(), map_hash);
+    assert_eq!(
+        checked.entries().collect::<Vec<_>>(),
+        vec![(&convert_hash(&present_key), &3)]
+    );
+    assert_eq!(
+        checked.missing_keys().collect::<Vec<_>>(),
+        vec![&convert_hash(&missing_key)]
+    );
+
+    // A map with a single entry.
+    let mut map = ProofMapIndex::new("single", &fork);
+    map.put(&present_key, 3_u64);
+    let proof: MapProof<Hash, u64> =
+        serde_json::from_value(serde_json::to_value(&map.get_proof(present_key)).unwrap()).unwrap();
+    assert_eq!(
+        proof.check().unwrap().root_hash(),
+        convert_hash(&map.object_hash())
+    );
+}
+
+#[test]
+fn block_proofs() {
+    let keys = (0..4).map(|_| gen_keypair()).collect::<Vec<_>>();
+    let validator_keys = keys
+        .iter()
+        .map(|(pk, _)| PublicKey::from_slice(pk.as_ref()).unwrap())
+        .collect::<Vec<_>>();
+
+    let block = Block::new(
+        ValidatorId(1),
+        Height(5),
+        3,
+        &crypto::hash(&[1]),
+        &crypto::hash(&[2]),
+        &crypto::hash(&[3]),
+    );
+    let precommits = keys
+        .iter()
+        .enumerate()
+        .map(|(i, (pk, sk))| {
+            let precommit = Precommit::new(
+                ValidatorId(i as u16),
+                Height(5),
+                Round(1),
+                &crypto::hash(&[4]),
+                &block.hash(),
+                Utc::now(),
+            );
+            Message::concrete(precommit, *pk, sk)
+        })
+        .collect::<Vec<_>>();
+
+    let node_proof = NodeBlockProof {
+        block: block.clone(),
+        precommits: precommits[..3].to_vec(),
+        certificate: None,
+    };
+    let proof: BlockProof =
+        serde_json::from_value(serde_json::to_value(&node_proof).unwrap()).unwrap();
+    assert_eq!(proof.block.object_hash(), convert_hash(&block.hash()));
+    let verified = proof.verify(&validator_keys).unwrap();
+    assert_eq!(verified.len(), 3);
+    assert_eq!(verified[2].validator, 2);
+    assert_eq!(verified[2].block_hash, convert_hash(&block.hash()));
+
+    // Not enough precommits.
+    let mut invalid_proof = proof.clone();
+    invalid_proof.precommits.pop();
+    assert_eq!(
+        invalid_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::NotEnoughPrecommits {
+            actual: 2,
+            required: 3
+        }
+    );
+
+    // Duplicate precommit.
+    let mut invalid_proof = proof.clone();
+    invalid_proof.precommits[2] = invalid_proof.precommits[1].clone();
+    assert_eq!(
+        invalid_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::DuplicatePrecommit(1)
+    );
+
+    // Corrupted signature.
+    let mut invalid_proof = proof.clone();
+    *invalid_proof.precommits[0].last_mut().unwrap() ^= 1;
+    assert_eq!(
+        invalid_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::InvalidSignature
+    );
+
+    // Modified block.
+    let mut invalid_proof = proof.clone();
+    invalid_proof.block.tx_count += 1;
+    assert_eq!(
+        invalid_proof.verify(&validator_keys).unwrap_err(),
+        BlockProofError::UnmatchedBlock(0)
+    );
+
+    // Keys of other validators.
+    let mut other_keys = validator_keys.clone();
+    other_keys.swap(0, 1);
+    assert_eq!(
+        proof.verify(&other_keys).unwrap_err(),
+        BlockProofError::UnknownValidator(0)
+    );
+
+    // Block proofs with certificates are deserialized.
+    let proof: BlockProof = serde_json::from_value(json!({
+        "block": serde_json::to_value(&block).unwrap(),
+        "precommits": [],
+        "certificate": [1, 2, 3],
+    }))
+    .unwrap();
+    assert_eq!(proof.certificate, Some(vec![1, 2, 3]));
+}
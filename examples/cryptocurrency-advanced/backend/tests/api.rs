@@ -22,10 +22,16 @@
 extern crate serde_json;
 
 use exonum::{
-    api::node::public::explorer::{TransactionQuery, TransactionResponse},
+    api::node::public::explorer::{
+        IndexEntriesQuery, IndexProof, IndexProofQuery, ProofBundle, ProofBundleQuery,
+        TransactionQuery, TransactionResponse,
+    },
+    blockchain::{light::LightChain, Schema, CORE_SERVICE},
     crypto::{self, Hash, PublicKey, SecretKey},
+    helpers::Height,
     messages::{self, RawTransaction, Signed},
 };
+use exonum_merkledb::{BinaryValue, TemporaryDB};
 use exonum_testkit::{ApiKind, TestKit, TestKitApi, TestKitBuilder};
 
 // Import data types used in tests from the crate where the service is defined.
@@ -202,6 +208,194 @@ fn test_unknown_wallet_request() {
     api.assert_no_wallet(tx.author());
 }
 
+/// Check that the wallet can be proven with the generic proof endpoint of the explorer API.
+#[test]
+fn test_wallet_index_proof() {
+    let (mut testkit, api) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME);
+    testkit.create_block();
+
+    let proof: IndexProof = api
+        .inner
+        .public(ApiKind::Explorer)
+        .query(&IndexProofQuery {
+            service: "cryptocurrency".to_owned(),
+            index: "cryptocurrency.wallets".to_owned(),
+            key: tx_alice.author().to_hex(),
+            key_is_hash: false,
+            height: None,
+        })
+        .get("v1/proofs")
+        .unwrap();
+
+    let to_table = proof.to_table.check().unwrap();
+    assert_eq!(to_table.root_hash(), *proof.block_proof.block.state_hash());
+    let to_entry = proof.to_entry.check().unwrap();
+    assert!(to_table
+        .entries()
+        .any(|(_, &table_hash)| table_hash == to_entry.root_hash()));
+    let (key, value) = to_entry.entries().next().unwrap();
+    assert_eq!(*key.hash(), crypto::hash(tx_alice.author().as_ref()));
+    let wallet = Wallet::from_bytes(value.0.as_slice().into()).unwrap();
+    assert_eq!(wallet.name, ALICE_NAME);
+    assert_eq!(wallet.balance, 100);
+}
+
+/// Check that the wallet can be proven at an earlier height if the state history is kept.
+#[test]
+fn test_wallet_index_proof_at_height() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(Service)
+        .with_state_history(1)
+        .create();
+    let api = CryptocurrencyApi {
+        inner: testkit.api(),
+    };
+    let (tx_alice, key_alice) = api.create_wallet(ALICE_NAME);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME);
+    testkit.create_block();
+    let tx = Transfer::sign(&tx_alice.author(), &tx_bob.author(), 10, 0, &key_alice);
+    api.transfer(&tx);
+    testkit.create_block();
+
+    let query = |height| IndexProofQuery {
+        service: "cryptocurrency".to_owned(),
+        index: "cryptocurrency.wallets".to_owned(),
+        key: tx_alice.author().to_hex(),
+        key_is_hash: false,
+        height: Some(Height(height)),
+    };
+    let proof: IndexProof = api
+        .inner
+        .public(ApiKind::Explorer)
+        .query(&query(1))
+        .get("v1/proofs")
+        .unwrap();
+
+    assert_eq!(proof.block_proof.block.height(), Height(1));
+    let to_table = proof.to_table.check().unwrap();
+    assert_eq!(to_table.root_hash(), *proof.block_proof.block.state_hash());
+    let to_entry = proof.to_entry.check().unwrap();
+    assert!(to_table
+        .entries()
+        .any(|(_, &table_hash)| table_hash == to_entry.root_hash()));
+    let (_, value) = to_entry.entries().next().unwrap();
+    let wallet = Wallet::from_bytes(value.0.as_slice().into()).unwrap();
+    assert_eq!(wallet.balance, 100);
+
+    // The state at the genesis height is not kept.
+    let response = api
+        .inner
+        .public(ApiKind::Explorer)
+        .query(&query(0))
+        .get::<IndexProof>("v1/proofs");
+    assert!(response.is_err());
+}
+
+/// Check that the light chain accepts the proof of the wallet only for the wallets table.
+#[test]
+fn test_wallet_index_proof_verification() {
+    let (mut testkit, api) = create_testkit();
+    let light_chain = {
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        LightChain::new(
+            TemporaryDB::new(),
+            schema.block_and_precommits(Height(0)).unwrap().block,
+            schema.configuration_by_height(Height(0)),
+        )
+        .unwrap()
+    };
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME);
+    testkit.create_block();
+    let snapshot = testkit.snapshot();
+    let schema = Schema::new(&snapshot);
+    light_chain
+        .add_block(schema.block_and_precommits(Height(1)).unwrap())
+        .unwrap();
+
+    let proof = || -> IndexProof {
+        api.inner
+            .public(ApiKind::Explorer)
+            .query(&IndexProofQuery {
+                service: "cryptocurrency".to_owned(),
+                index: "cryptocurrency.wallets".to_owned(),
+                key: tx_alice.author().to_hex(),
+                key_is_hash: false,
+                height: None,
+            })
+            .get("v1/proofs")
+            .unwrap()
+    };
+    let verified = light_chain.verify_index_proof(proof()).unwrap();
+    assert_eq!(verified.height, Height(1));
+    assert_eq!((verified.service_id, verified.table_idx), (128, 0));
+    assert_eq!(verified.entries.len(), 1);
+    assert!(verified.entries[0].value.is_some());
+
+    // The proof of the wallets table is not accepted as the proof of another table,
+    // even if the proof of the state hash contains both tables.
+    let mut swapped = proof();
+    swapped.to_table =
+        schema.get_proof_to_service_tables(vec![(CORE_SERVICE, 0), (128, 0)].into_iter());
+    swapped.service_id = CORE_SERVICE;
+    assert!(light_chain.verify_index_proof(swapped).is_err());
+    let mut swapped = proof();
+    swapped.table_idx = 1;
+    assert!(light_chain.verify_index_proof(swapped).is_err());
+}
+
+/// Check that a single proof bundle covers several wallets, including a missing one.
+#[test]
+fn test_wallets_proof_bundle() {
+    let (mut testkit, api) = create_testkit();
+    let (tx_alice, _) = api.create_wallet(ALICE_NAME);
+    let (tx_bob, _) = api.create_wallet(BOB_NAME);
+    testkit.create_block();
+    let (missing_key, _) = crypto::gen_keypair();
+
+    let bundle: ProofBundle = api
+        .inner
+        .public(ApiKind::Explorer)
+        .query(&ProofBundleQuery {
+            indexes: vec![IndexEntriesQuery {
+                service: "cryptocurrency".to_owned(),
+                index: "cryptocurrency.wallets".to_owned(),
+                keys: vec![
+                    tx_alice.author().to_hex(),
+                    tx_bob.author().to_hex(),
+                    missing_key.to_hex(),
+                ],
+                key_is_hash: false,
+            }],
+            height: None,
+            compact: false,
+        })
+        .post("v1/proofs/bundle")
+        .unwrap();
+
+    let to_tables = bundle.to_tables.check().unwrap();
+    assert_eq!(
+        to_tables.root_hash(),
+        *bundle.block_proof.block.state_hash()
+    );
+    assert_eq!(bundle.indexes.len(), 1);
+    let to_entries = bundle.indexes[0].to_entries.check().unwrap();
+    assert!(to_tables
+        .entries()
+        .any(|(_, &table_hash)| table_hash == to_entries.root_hash()));
+
+    let mut names = to_entries
+        .entries()
+        .map(|(_, value)| Wallet::from_bytes(value.0.as_slice().into()).unwrap().name)
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec![ALICE_NAME, BOB_NAME]);
+    let missing = to_entries.missing_keys().collect::<Vec<_>>();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(*missing[0].hash(), crypto::hash(missing_key.as_ref()));
+}
+
 /// Wrapper for the cryptocurrency service API allowing to easily use it
 /// (compared to `TestKitApi` calls).
 struct CryptocurrencyApi {
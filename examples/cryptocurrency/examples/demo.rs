@@ -52,6 +52,25 @@ fn node_config() -> NodeConfig {
         services_configs: Default::default(),
         database: Default::default(),
         thread_pool_size: Default::default(),
+        max_pending_verifications: None,
+        pruning: None,
+        checkpoints: None,
+        garbage_collection: None,
+        integrity_check: None,
+        state_history: None,
+        liveness: None,
+        parallel_execution: None,
+        watchdog: None,
+        catch_up: None,
+        diagnostics: None,
+        peer_stats: None,
+        signing_record: None,
+        remote_signer: None,
+        threshold_key: None,
+        key_derivation: None,
+        peer_bans: None,
+        proxy: None,
+        connect_list_watch: None,
     }
 }
 
@@ -70,6 +70,11 @@ impl blockchain::Service for Service {
         schema.state_hash()
     }
 
+    fn read_namespaces(&self) -> Option<Vec<&str>> {
+        // Timestamps are checked against the time of the validators.
+        Some(vec![exonum_time::SERVICE_NAME])
+    }
+
     fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
         let tx = TimeTransactions::tx_from_raw(raw)?;
         Ok(tx.into())
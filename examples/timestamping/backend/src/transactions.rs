@@ -72,7 +72,7 @@ impl TxTimestamp {
 impl Transaction for TxTimestamp {
     fn execute(&self, context: TransactionContext) -> ExecutionResult {
         let tx_hash = context.tx_hash();
-        let time = TimeSchema::new(context.fork())
+        let time = TimeSchema::new(context.fork().as_ref())
             .time()
             .get()
             .expect("Can't get the time");
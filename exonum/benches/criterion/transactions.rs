@@ -22,6 +22,7 @@ use futures::{stream, sync::mpsc::Sender, sync::oneshot, Future, Sink};
 use tokio_core::reactor::Core;
 
 use std::{
+    net::SocketAddr,
     sync::{Arc, RwLock},
     thread::{self, JoinHandle},
 };
@@ -153,6 +154,9 @@ impl MessageVerifier {
         let internal_part = InternalPart {
             internal_tx: channel.internal_events.0,
             internal_requests_rx: channel.internal_requests.1,
+            max_pending_verifications: None,
+            verification_metrics: Default::default(),
+            peer_bans: Default::default(),
         };
 
         let network_thread = thread::spawn(move || {
@@ -178,13 +182,12 @@ impl MessageVerifier {
     fn send_all<'a>(&self, messages: Vec<Vec<u8>>) -> impl Future<Item = (), Error = ()> + 'a {
         let tx_sender = self.tx_sender.as_ref().unwrap().clone();
         let finish_signal = self.tx_handler.reset(messages.len());
+        let address: SocketAddr = "127.0.0.1:6333".parse().unwrap();
 
         tx_sender
-            .send_all(stream::iter_ok(
-                messages
-                    .into_iter()
-                    .map(|message| InternalRequest::VerifyMessage(message)),
-            ))
+            .send_all(stream::iter_ok(messages.into_iter().map(move |message| {
+                InternalRequest::VerifyMessage(address, message, None, None)
+            })))
             .map(drop)
             .map_err(drop)
             .and_then(|()| finish_signal.map_err(drop))
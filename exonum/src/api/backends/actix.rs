@@ -23,29 +23,45 @@ use actix::{Addr, System};
 use actix_net::server::Server;
 use actix_web::{
     error::ResponseError,
+    fs::{file_extension_to_mime, StaticFiles},
+    http::header::{self, HeaderName, HeaderValue},
+    middleware::{Middleware, Response, Started},
     server::{HttpServer, StopServer},
     AsyncResponder, FromRequest, HttpMessage, HttpResponse, Query,
 };
 use futures::{Future, IntoFuture};
+use protobuf::Message;
 use serde::{
     de::{self, DeserializeOwned},
     ser, Serialize,
 };
 
 use std::{
-    fmt,
-    net::SocketAddr,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fmt, io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
     result,
     str::FromStr,
     sync::{mpsc, Arc},
     thread::{self, JoinHandle},
+    time::Instant,
 };
 
 use crate::api::{
-    error::Error as ApiError, ApiAccess, ApiAggregator, ExtendApiBackend, FutureResult, Immutable,
-    Mutable, NamedWith, Result, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+    error::Error as ApiError, stats::ApiStats, ApiAccess, ApiAggregator, ApiVersion, Deprecation,
+    ExtendApiBackend, FutureResult, Immutable, Mutable, NamedWith, ProtobufResponse, Result,
+    ServiceApiBackend, ServiceApiScope, ServiceApiState,
 };
 
+/// MIME type of the protobuf-encoded responses.
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+/// MIME type of the raw binary request bodies, such as signed transactions.
+pub const OCTET_STREAM_CONTENT_TYPE: &str = "application/octet-stream";
+/// Name of the file returned for the directory requests to the mounted static files.
+pub const INDEX_FILE: &str = "index.html";
+
 /// Type alias for the concrete `actix-web` HTTP response.
 pub type FutureResponse = actix_web::FutureResponse<HttpResponse, actix_web::Error>;
 /// Type alias for the concrete `actix-web` HTTP request.
@@ -56,6 +72,10 @@ pub type RawHandler = dyn Fn(HttpRequest) -> FutureResponse + 'static + Send + S
 pub type App = actix_web::App<ServiceApiState>;
 /// Type alias for the `actix-web::App` configuration.
 pub type AppConfig = Arc<dyn Fn(App) -> App + 'static + Send + Sync>;
+/// Type alias for the `actix-web::Scope` with the `ServiceApiState`.
+pub type Scope = actix_web::Scope<ServiceApiState>;
+/// Type alias for the `actix-web::Scope` configuration.
+pub type ScopeConfig = Arc<dyn Fn(Scope) -> Scope + 'static + Send + Sync>;
 
 /// Raw `actix-web` backend requests handler.
 #[derive(Clone)]
@@ -68,6 +88,62 @@ pub struct RequestHandler {
     pub inner: Arc<RawHandler>,
 }
 
+impl RequestHandler {
+    /// Makes the handler add the deprecation headers to all its responses: `Deprecation`,
+    /// `Sunset` if the sunset date is specified, and `Warning` with the description
+    /// of the deprecation if it is specified.
+    pub fn deprecated(self, deprecation: &Deprecation) -> Self {
+        let inner = self.inner;
+        let headers = deprecation_headers(deprecation);
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let headers = headers.clone();
+            let future = inner(request).then(move |result| {
+                let mut response =
+                    result.unwrap_or_else(|e| e.as_response_error().error_response());
+                for (name, value) in headers {
+                    response.headers_mut().insert(name, value);
+                }
+                Ok::<_, actix_web::Error>(response)
+            });
+            Box::new(future)
+        };
+
+        Self {
+            inner: Arc::from(index) as Arc<RawHandler>,
+            ..self
+        }
+    }
+
+    /// Makes the handler record the served requests to the given statistics
+    /// under the given access level and endpoint name.
+    pub fn with_stats(self, stats: ApiStats, access: ApiAccess, endpoint: String) -> Self {
+        let inner = self.inner;
+        let method = self.method.to_string();
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let stats = stats.clone();
+            let method = method.clone();
+            let endpoint = endpoint.clone();
+            let start = Instant::now();
+            let future = inner(request).then(move |result| {
+                let is_error = match result {
+                    Ok(ref response) => {
+                        response.status().is_client_error() || response.status().is_server_error()
+                    }
+                    Err(_) => true,
+                };
+                stats.record(access, &method, &endpoint, start.elapsed(), is_error);
+                result
+            });
+            Box::new(future)
+        };
+
+        Self {
+            inner: Arc::from(index) as Arc<RawHandler>,
+            ..self
+        }
+    }
+}
+
 impl fmt::Debug for RequestHandler {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("RequestHandler")
@@ -77,10 +153,36 @@ impl fmt::Debug for RequestHandler {
     }
 }
 
+fn deprecation_headers(deprecation: &Deprecation) -> Vec<(HeaderName, HeaderValue)> {
+    let mut headers = vec![(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    )];
+    if let Some(sunset) = deprecation.sunset {
+        let sunset = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        headers.push((
+            HeaderName::from_static("sunset"),
+            HeaderValue::from_str(&sunset).expect("Invalid HTTP date"),
+        ));
+    }
+    if let Some(ref description) = deprecation.description {
+        let warning = format!("299 - \"{}\"", description.replace('"', "'"));
+        match HeaderValue::from_str(&warning) {
+            Ok(warning) => headers.push((header::WARNING, warning)),
+            Err(_) => warn!(
+                "Deprecation description {:?} is not a valid header",
+                description
+            ),
+        }
+    }
+    headers
+}
+
 /// API builder for the `actix-web` backend.
-#[derive(Debug, Clone, Default)]
+#[derive(Clone, Default)]
 pub struct ApiBuilder {
     handlers: Vec<RequestHandler>,
+    scope_configs: Vec<ScopeConfig>,
 }
 
 impl ApiBuilder {
@@ -88,11 +190,186 @@ impl ApiBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Adds the given middleware to the scope. The middleware is applied to all
+    /// handlers of the scope, regardless of the order in which they were added.
+    pub fn middleware<M>(&mut self, middleware: M) -> &mut Self
+    where
+        M: Middleware<ServiceApiState> + Clone + Send + Sync,
+    {
+        self.scope_config(Arc::new(move |scope: Scope| {
+            scope.middleware(middleware.clone())
+        }))
+    }
+
+    /// Adds the given configuration hook to the scope. The hook is invoked after
+    /// all handlers are bound to the scope.
+    pub fn scope_config(&mut self, scope_config: ScopeConfig) -> &mut Self {
+        self.scope_configs.push(scope_config);
+        self
+    }
+
+    /// Adds the given GET endpoint handler which responds in JSON by default and
+    /// in protobuf if the request has the `Accept: application/x-protobuf` header.
+    pub fn negotiated_endpoint<Q, I, F>(&mut self, name: &str, handler: F) -> &mut Self
+    where
+        Q: DeserializeOwned + 'static,
+        I: Serialize + ProtobufResponse + 'static,
+        F: for<'r> Fn(&'r ServiceApiState, Q) -> Result<I> + 'static + Send + Sync,
+    {
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let context = request.state();
+            let accepts_protobuf = accepts_protobuf(&request);
+            let future = Query::from_request(&request, &Default::default())
+                .map(Query::into_inner)
+                .and_then(|query| handler(context, query).map_err(From::from))
+                .and_then(|value| {
+                    if accepts_protobuf {
+                        protobuf_response(&value)
+                    } else {
+                        Ok(HttpResponse::Ok().json(value))
+                    }
+                })
+                .into_future();
+            Box::new(future)
+        };
+
+        self.raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: actix_web::http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        })
+    }
+
+    /// Adds the given handler under each of the given version prefixes, e.g., `v1/name`
+    /// and `v2/name`. Handlers of the deprecated versions add the deprecation headers
+    /// to their responses.
+    pub fn versioned_handler(
+        &mut self,
+        versions: &[ApiVersion],
+        handler: RequestHandler,
+    ) -> &mut Self {
+        for version in versions {
+            let mut handler = RequestHandler {
+                name: format!("{}/{}", version.prefix(), handler.name),
+                ..handler.clone()
+            };
+            if let Some(deprecation) = version.deprecation() {
+                handler = handler.deprecated(deprecation);
+            }
+            self.raw_handler(handler);
+        }
+        self
+    }
+
+    /// Returns a copy of the builder with all the handlers recording the served requests
+    /// to the given statistics. Endpoint names are prefixed with the given prefix.
+    pub fn with_stats(&self, stats: &ApiStats, access: ApiAccess, prefix: &str) -> Self {
+        let handlers = self
+            .handlers
+            .iter()
+            .cloned()
+            .map(|handler| {
+                let endpoint = format!("{}/{}", prefix, handler.name);
+                handler.with_stats(stats.clone(), access, endpoint)
+            })
+            .collect();
+        Self {
+            handlers,
+            scope_configs: self.scope_configs.clone(),
+        }
+    }
+
+    /// Mounts the files from the given directory at the given path of the scope.
+    ///
+    /// The directory is opened when the API server starts; if it does not exist,
+    /// an error is logged and the files are not mounted.
+    pub fn static_files(&mut self, path: &str, dir: impl Into<PathBuf>) -> &mut Self {
+        let path = path.to_owned();
+        let dir = dir.into();
+        self.scope_config(Arc::new(move |scope: Scope| match StaticFiles::new(&dir) {
+            Ok(files) => scope.handler(&path, files.index_file(INDEX_FILE)),
+            Err(e) => {
+                error!("Unable to mount static files from {}: {}", dir.display(), e);
+                scope
+            }
+        }))
+    }
+
+    /// Mounts the files embedded into the binary at the given path of the scope.
+    /// Files are specified by their paths relative to the mount point and their contents,
+    /// e.g., `("index.html", &include_bytes!("frontend/index.html")[..])`.
+    pub fn embedded_files<I>(&mut self, path: &str, files: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (&'static str, &'static [u8])>,
+    {
+        let files: HashMap<_, _> = files.into_iter().collect();
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let tail = request.match_info().get("tail").unwrap_or_default();
+            let name = if tail.is_empty() || tail.ends_with('/') {
+                format!("{}{}", tail, INDEX_FILE)
+            } else {
+                tail.to_owned()
+            };
+            let response: result::Result<_, actix_web::Error> = match files.get(name.as_str()) {
+                Some(content) => {
+                    let extension = Path::new(&name)
+                        .extension()
+                        .and_then(OsStr::to_str)
+                        .unwrap_or_default();
+                    Ok(HttpResponse::Ok()
+                        .content_type(file_extension_to_mime(extension))
+                        .body(*content))
+                }
+                None => Err(ApiError::NotFound(format!("File {} not found", name)).into()),
+            };
+            Box::new(response.into_future())
+        };
+
+        self.raw_handler(RequestHandler {
+            name: format!("{}/{{tail:.*}}", path.trim_end_matches('/')),
+            method: actix_web::http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        })
+    }
+}
+
+/// Checks whether the client accepts protobuf-encoded responses.
+fn accepts_protobuf(request: &HttpRequest) -> bool {
+    request
+        .headers()
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|media_range| media_range.split(';').next())
+        .any(|media_type| media_type.trim() == PROTOBUF_CONTENT_TYPE)
+}
+
+fn protobuf_response<I: ProtobufResponse>(
+    value: &I,
+) -> result::Result<HttpResponse, actix_web::Error> {
+    let body = value
+        .to_protobuf()
+        .write_to_bytes()
+        .map_err(|e| ApiError::InternalError(e.into()))?;
+    Ok(HttpResponse::Ok()
+        .content_type(PROTOBUF_CONTENT_TYPE)
+        .body(body))
+}
+
+impl fmt::Debug for ApiBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApiBuilder")
+            .field("handlers", &self.handlers)
+            .field("scope_configs", &self.scope_configs.len())
+            .finish()
+    }
 }
 
 impl ServiceApiBackend for ApiBuilder {
     type Handler = RequestHandler;
-    type Backend = actix_web::Scope<ServiceApiState>;
+    type Backend = Scope;
 
     fn raw_handler(&mut self, handler: Self::Handler) -> &mut Self {
         self.handlers.push(handler);
@@ -106,11 +383,14 @@ impl ServiceApiBackend for ApiBuilder {
                 inner(request)
             });
         }
+        for scope_config in &self.scope_configs {
+            output = scope_config(output);
+        }
         output
     }
 }
 
-impl ExtendApiBackend for actix_web::Scope<ServiceApiState> {
+impl ExtendApiBackend for Scope {
     fn extend<'a, I>(mut self, items: I) -> Self
     where
         I: IntoIterator<Item = (&'a str, &'a ServiceApiScope)>,
@@ -132,6 +412,11 @@ impl ResponseError for ApiError {
             ApiError::Io(err) => HttpResponse::InternalServerError().body(err.to_string()),
             ApiError::Storage(err) => HttpResponse::InternalServerError().body(err.to_string()),
             ApiError::NotFound(err) => HttpResponse::NotFound().body(err.to_string()),
+            ApiError::Pruned(err) => HttpResponse::Gone().body(err.to_string()),
+            ApiError::Conflict(err) => HttpResponse::Conflict().body(err.to_string()),
+            ApiError::ServiceUnavailable(err) => {
+                HttpResponse::ServiceUnavailable().body(err.to_string())
+            }
             ApiError::Unauthorized => HttpResponse::Unauthorized().finish(),
             ApiError::PayloadTooLarge {
                 length_limit,
@@ -270,11 +555,35 @@ pub(crate) fn create_app(aggregator: &ApiAggregator, runtime_config: ApiRuntimeC
     app
 }
 
+/// Address an API server listens on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddress {
+    /// TCP socket address.
+    Tcp(SocketAddr),
+    /// Path to the Unix domain socket. Supported on Unix platforms only.
+    Unix(PathBuf),
+}
+
+impl From<SocketAddr> for ListenAddress {
+    fn from(address: SocketAddr) -> Self {
+        ListenAddress::Tcp(address)
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => address.fmt(f),
+            ListenAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
 /// Configuration parameters for the `App` runtime.
 #[derive(Clone)]
 pub struct ApiRuntimeConfig {
-    /// The socket address to bind.
-    pub listen_address: SocketAddr,
+    /// The address to bind.
+    pub listen_address: ListenAddress,
     /// API access level.
     pub access: ApiAccess,
     /// Optional App configuration.
@@ -283,9 +592,9 @@ pub struct ApiRuntimeConfig {
 
 impl ApiRuntimeConfig {
     /// Creates API runtime configuration for the given address and access level.
-    pub fn new(listen_address: SocketAddr, access: ApiAccess) -> Self {
+    pub fn new(listen_address: impl Into<ListenAddress>, access: ApiAccess) -> Self {
         Self {
-            listen_address,
+            listen_address: listen_address.into(),
             access,
             app_config: Default::default(),
         }
@@ -343,14 +652,26 @@ impl SystemRuntime {
             let api_handlers = config.api_runtimes.into_iter().map(|runtime_config| {
                 debug!("Runtime: {:?}", runtime_config);
                 let access = runtime_config.access;
-                let listen_address = runtime_config.listen_address;
+                let listen_address = runtime_config.listen_address.clone();
                 info!("Starting {} web api on {}", access, listen_address);
 
                 let aggregator = aggregator.clone();
-                HttpServer::new(move || create_app(&aggregator, runtime_config.clone()))
-                    .disable_signals()
-                    .bind(listen_address)
-                    .map(HttpServer::start)
+                let server =
+                    HttpServer::new(move || create_app(&aggregator, runtime_config.clone()))
+                        .disable_signals();
+                match listen_address {
+                    ListenAddress::Tcp(address) => server.bind(address),
+                    #[cfg(unix)]
+                    ListenAddress::Unix(path) => {
+                        remove_stale_socket(&path).and_then(|_| server.bind_uds(path))
+                    }
+                    #[cfg(not(unix))]
+                    ListenAddress::Unix(_) => Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "Unix domain sockets are not supported on this platform",
+                    )),
+                }
+                .map(HttpServer::start)
             });
             // Sends addresses to the control thread.
             system_tx.send(System::current())?;
@@ -416,6 +737,17 @@ impl SystemRuntime {
     }
 }
 
+/// Removes the Unix domain socket left by the previous run of the server, if any.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    use std::{fs, os::unix::fs::FileTypeExt};
+
+    match fs::symlink_metadata(path) {
+        Ok(ref metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
 impl fmt::Debug for SystemRuntime {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("SystemRuntime").finish()
@@ -529,6 +861,211 @@ impl From<AllowOrigin> for Cors {
     }
 }
 
+impl AllowOrigin {
+    /// Checks whether the given origin is allowed.
+    pub fn allows(&self, origin: &str) -> bool {
+        match *self {
+            AllowOrigin::Any => true,
+            AllowOrigin::Whitelist(ref hosts) => hosts.iter().any(|host| host == origin),
+        }
+    }
+}
+
+/// CORS rule for requests from the specified origins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorsRule {
+    /// Origins the rule applies to.
+    pub origins: AllowOrigin,
+    /// Allowed HTTP methods. If not set, all methods are allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+    /// Allowed request headers. If not set, all headers are allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_headers: Option<Vec<String>>,
+    /// Response headers exposed to the browser in addition to the simple ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exposed_headers: Vec<String>,
+    /// Whether credentialed requests, e.g., with cookies or the `Authorization` header,
+    /// are allowed. The default value is `false`.
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// How long the results of a preflight request may be cached, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<usize>,
+}
+
+impl From<AllowOrigin> for CorsRule {
+    fn from(origins: AllowOrigin) -> Self {
+        Self {
+            origins,
+            allowed_methods: None,
+            allowed_headers: None,
+            exposed_headers: Vec::new(),
+            allow_credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+impl<'a> From<&'a CorsRule> for Cors {
+    fn from(rule: &'a CorsRule) -> Self {
+        let mut builder = Self::build();
+        if let AllowOrigin::Whitelist(ref hosts) = rule.origins {
+            for host in hosts {
+                builder.allowed_origin(host);
+            }
+        }
+        if let Some(ref methods) = rule.allowed_methods {
+            builder.allowed_methods(valid_values(methods, |method| {
+                actix_web::http::Method::from_bytes(method.as_bytes()).is_ok()
+            }));
+        }
+        if let Some(ref headers) = rule.allowed_headers {
+            builder.allowed_headers(valid_values(headers, |header| {
+                HeaderName::from_bytes(header.as_bytes()).is_ok()
+            }));
+        }
+        let exposed_headers = valid_values(&rule.exposed_headers, |header| {
+            HeaderName::from_bytes(header.as_bytes()).is_ok()
+        });
+        if !exposed_headers.is_empty() {
+            builder.expose_headers(exposed_headers);
+        }
+        if rule.allow_credentials {
+            builder.supports_credentials();
+        }
+        if let Some(max_age) = rule.max_age {
+            builder.max_age(max_age);
+        }
+        builder.finish()
+    }
+}
+
+/// Filters out the values which are not valid, logging a warning for each of them.
+fn valid_values<'a, F>(values: &'a [String], is_valid: F) -> Vec<&'a str>
+where
+    F: Fn(&str) -> bool,
+{
+    values
+        .iter()
+        .map(String::as_str)
+        .filter(|value| {
+            let valid = is_valid(value);
+            if !valid {
+                warn!("Ignoring invalid value {:?} in the CORS rule", value);
+            }
+            valid
+        })
+        .collect()
+}
+
+/// Middleware applying the first of the CORS rules matching the origin of the request.
+/// Requests from the origins not matching any rule are passed without the CORS headers,
+/// so browsers reject them.
+pub struct CorsRules {
+    rules: Vec<(AllowOrigin, Cors)>,
+}
+
+impl fmt::Debug for CorsRules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let origins = self
+            .rules
+            .iter()
+            .map(|(origins, _)| origins)
+            .collect::<Vec<_>>();
+        f.debug_struct("CorsRules")
+            .field("origins", &origins)
+            .finish()
+    }
+}
+
+impl CorsRules {
+    /// Creates the middleware for the given rules.
+    pub fn new<'a, I: IntoIterator<Item = &'a CorsRule>>(rules: I) -> Self {
+        Self {
+            rules: rules
+                .into_iter()
+                .map(|rule| (rule.origins.clone(), Cors::from(rule)))
+                .collect(),
+        }
+    }
+
+    fn rule<S>(&self, request: &actix_web::HttpRequest<S>) -> Option<&Cors> {
+        let origin = request.headers().get(header::ORIGIN)?.to_str().ok()?;
+        self.rules
+            .iter()
+            .find(|(origins, _)| origins.allows(origin))
+            .map(|(_, cors)| cors)
+    }
+}
+
+impl<S: 'static> Middleware<S> for CorsRules {
+    fn start(&self, request: &actix_web::HttpRequest<S>) -> actix_web::Result<Started> {
+        match self.rule(request) {
+            Some(cors) => cors.start(request),
+            None => Ok(Started::Done),
+        }
+    }
+
+    fn response(
+        &self,
+        request: &actix_web::HttpRequest<S>,
+        response: HttpResponse,
+    ) -> actix_web::Result<Response> {
+        match self.rule(request) {
+            Some(cors) => cors.response(request, response),
+            None => Ok(Response::Done(response)),
+        }
+    }
+}
+
+/// Middleware rejecting requests from the IP addresses which are not in the allowlist
+/// with the `403 Forbidden` status.
+///
+/// Requests without the peer address are rejected as well, thus the middleware should
+/// not be used with the servers listening on Unix domain sockets.
+#[derive(Debug, Clone)]
+pub struct IpAllowlist {
+    addresses: Arc<HashSet<IpAddr>>,
+}
+
+impl IpAllowlist {
+    /// Creates the middleware allowing requests from the given addresses only.
+    pub fn new<I: IntoIterator<Item = IpAddr>>(addresses: I) -> Self {
+        Self {
+            addresses: Arc::new(addresses.into_iter().collect()),
+        }
+    }
+
+    /// Checks whether requests from the given address are allowed.
+    pub fn allows(&self, address: IpAddr) -> bool {
+        let address = match address {
+            IpAddr::V6(address) => address
+                .to_ipv4()
+                .filter(|_| address.segments()[..6] == [0, 0, 0, 0, 0, 0xffff])
+                .map_or(IpAddr::V6(address), IpAddr::V4),
+            address => address,
+        };
+        self.addresses.contains(&address)
+    }
+}
+
+impl<S> Middleware<S> for IpAllowlist {
+    fn start(&self, request: &actix_web::HttpRequest<S>) -> actix_web::Result<Started> {
+        match request.peer_addr() {
+            Some(address) if self.allows(address.ip()) => Ok(Started::Done),
+            Some(address) => {
+                warn!("Rejected API request from {}", address);
+                Ok(Started::Response(HttpResponse::Forbidden().finish()))
+            }
+            None => {
+                warn!("Rejected API request without the peer address");
+                Ok(Started::Response(HttpResponse::Forbidden().finish()))
+            }
+        }
+    }
+}
+
 #[test]
 fn allow_origin_from_str() {
     fn check(text: &str, expected: AllowOrigin) {
@@ -554,3 +1091,56 @@ fn allow_origin_from_str() {
         AllowOrigin::Whitelist(vec!["http://a.org".to_string(), "http://b.org".to_string()]),
     );
 }
+
+#[test]
+fn allow_origin_allows() {
+    assert!(AllowOrigin::Any.allows("http://example.com"));
+    let whitelist = AllowOrigin::Whitelist(vec!["http://a.org".to_owned()]);
+    assert!(whitelist.allows("http://a.org"));
+    assert!(!whitelist.allows("http://b.org"));
+    assert!(!whitelist.allows("https://a.org"));
+}
+
+#[test]
+fn ip_allowlist_allows() {
+    let allowlist = IpAllowlist::new(vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()]);
+
+    assert!(allowlist.allows("127.0.0.1".parse().unwrap()));
+    assert!(allowlist.allows("::1".parse().unwrap()));
+    assert!(allowlist.allows("::ffff:127.0.0.1".parse().unwrap()));
+    assert!(!allowlist.allows("::127.0.0.1".parse().unwrap()));
+    assert!(!allowlist.allows("10.0.0.1".parse().unwrap()));
+}
+
+#[test]
+fn ip_allowlist_rejects_requests_without_peer_address() {
+    let allowlist = IpAllowlist::new(vec!["127.0.0.1".parse().unwrap()]);
+    let request = actix_web::test::TestRequest::default().finish();
+    match allowlist.start(&request) {
+        Ok(Started::Response(response)) => {
+            assert_eq!(response.status(), actix_web::http::StatusCode::FORBIDDEN)
+        }
+        _ => panic!("Request without the peer address is not rejected"),
+    }
+}
+
+#[test]
+fn deprecation_headers_format() {
+    use chrono::TimeZone;
+
+    let headers = deprecation_headers(&Deprecation::default());
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[0].0, "deprecation");
+    assert_eq!(headers[0].1, "true");
+
+    let deprecation = Deprecation {
+        sunset: Some(chrono::Utc.ymd(2019, 11, 6).and_hms(8, 49, 37)),
+        description: Some("Use \"v2\" instead".to_owned()),
+    };
+    let headers = deprecation_headers(&deprecation);
+    assert_eq!(headers.len(), 3);
+    assert_eq!(headers[1].0, "sunset");
+    assert_eq!(headers[1].1, "Wed, 06 Nov 2019 08:49:37 GMT");
+    assert_eq!(headers[2].0, header::WARNING);
+    assert_eq!(headers[2].1, "299 - \"Use 'v2' instead\"");
+}
@@ -48,6 +48,21 @@ pub enum Error {
     #[fail(display = "Internal server error: {}", _0)]
     InternalError(failure::Error),
 
+    /// Pruned. This error occurs when the requested data has been removed from
+    /// the node storage by pruning.
+    #[fail(display = "Pruned: {}", _0)]
+    Pruned(String),
+
+    /// Conflict. This error occurs when the request cannot be performed in the current
+    /// state of the node, e.g., when the transaction to remove is already proposed.
+    #[fail(display = "Conflict: {}", _0)]
+    Conflict(String),
+
+    /// Service unavailable. This error occurs when the node is temporarily unable
+    /// to process the request, e.g., because it is overloaded.
+    #[fail(display = "Service unavailable: {}", _0)]
+    ServiceUnavailable(String),
+
     /// Unauthorized error. This error occurs when the request lacks valid
     /// authentication credentials.
     #[fail(display = "Unauthorized")]
@@ -19,11 +19,15 @@ pub use self::{
     with::{FutureResult, Immutable, Mutable, NamedWith, Result, With},
 };
 
+use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, Serialize};
 
-use std::{collections::BTreeMap, fmt};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
 
-use self::{backends::actix, node::public::ExplorerApi};
+use self::{
+    backends::actix,
+    node::public::{ExplorerApi, LightApi},
+};
 use crate::blockchain::{Blockchain, SharedNodeState};
 use crate::crypto::PublicKey;
 use crate::node::ApiSender;
@@ -32,9 +36,13 @@ pub mod backends;
 pub mod error;
 pub mod node;
 mod state;
+pub mod stats;
 pub mod websocket;
 mod with;
 
+/// Name of the websocket endpoint streaming changes in the pool of unconfirmed transactions.
+const MEMPOOL_SUBSCRIBE_ENDPOINT: &str = "v1/mempool/subscribe";
+
 /// Defines an object that could be used as an API backend.
 ///
 /// This trait is used to implement an API backend for Exonum.
@@ -79,6 +87,65 @@ pub trait ServiceApiBackend: Sized {
     fn wire(&self, output: Self::Backend) -> Self::Backend;
 }
 
+/// Version of API endpoints, which is used as the prefix of the endpoint names, e.g., `v1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiVersion {
+    prefix: String,
+    deprecation: Option<Deprecation>,
+}
+
+impl ApiVersion {
+    /// Creates a new version with the given prefix.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            deprecation: None,
+        }
+    }
+
+    /// Marks the version as deprecated.
+    pub fn deprecated(mut self, deprecation: Deprecation) -> Self {
+        self.deprecation = Some(deprecation);
+        self
+    }
+
+    /// Returns the prefix of the endpoint names.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the deprecation information if the version is deprecated.
+    pub fn deprecation(&self) -> Option<&Deprecation> {
+        self.deprecation.as_ref()
+    }
+}
+
+/// Information about the deprecation of an API version.
+///
+/// For the web backend, responses of the deprecated endpoints contain the `Deprecation: true`
+/// header, the `Sunset` header with the sunset date and the `Warning` header with
+/// the description.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Deprecation {
+    /// Date after which the deprecated endpoints may be removed.
+    pub sunset: Option<DateTime<Utc>>,
+    /// Description of the deprecation, e.g., the version clients should migrate to.
+    pub description: Option<String>,
+}
+
+/// API response which can be encoded in the protobuf format in addition to JSON.
+///
+/// See [`ServiceApiScope::negotiated_endpoint`] for details.
+///
+/// [`ServiceApiScope::negotiated_endpoint`]: struct.ServiceApiScope.html#method.negotiated_endpoint
+pub trait ProtobufResponse {
+    /// Protobuf message representing the response.
+    type Message: protobuf::Message;
+
+    /// Converts the response into the protobuf message.
+    fn to_protobuf(&self) -> Self::Message;
+}
+
 /// Service API builder for the concrete API scope or in other words
 /// access level (public or private).
 ///
@@ -134,6 +201,85 @@ impl ServiceApiScope {
         self
     }
 
+    /// Adds the given endpoint handler to the API scope, allowing clients to choose
+    /// the encoding of the response. These endpoints are designed for reading operations.
+    ///
+    /// For the web backend, the response is encoded in JSON by default; if the request has
+    /// the `Accept: application/x-protobuf` header, the protobuf encoding of the response
+    /// is returned instead. Query parameters have the same requirements as in `endpoint`.
+    pub fn negotiated_endpoint<Q, I, F>(&mut self, name: &'static str, handler: F) -> &mut Self
+    where
+        Q: DeserializeOwned + 'static,
+        I: Serialize + ProtobufResponse + 'static,
+        F: for<'r> Fn(&'r ServiceApiState, Q) -> Result<I> + 'static + Send + Sync,
+    {
+        self.actix_backend.negotiated_endpoint(name, handler);
+        self
+    }
+
+    /// Adds the given endpoint handler to the API scope under each of the given versions,
+    /// e.g., as `v1/name` and `v2/name`. Endpoints of the deprecated versions surface
+    /// the deprecation information in their responses.
+    pub fn versioned_endpoint<Q, I, R, F, E>(
+        &mut self,
+        versions: &[ApiVersion],
+        name: &'static str,
+        endpoint: E,
+    ) -> &mut Self
+    where
+        Q: DeserializeOwned + 'static,
+        I: Serialize + 'static,
+        F: for<'r> Fn(&'r ServiceApiState, Q) -> R + 'static + Clone,
+        E: Into<With<Q, I, R, F>>,
+        actix::RequestHandler: From<NamedWith<Q, I, R, F, Immutable>>,
+    {
+        let named_with = NamedWith::<_, _, _, _, Immutable>::new(name, endpoint);
+        self.actix_backend
+            .versioned_handler(versions, actix::RequestHandler::from(named_with));
+        self
+    }
+
+    /// Adds the given mutable endpoint handler to the API scope under each of the given
+    /// versions, e.g., as `v1/name` and `v2/name`. Endpoints of the deprecated versions
+    /// surface the deprecation information in their responses.
+    pub fn versioned_endpoint_mut<Q, I, R, F, E>(
+        &mut self,
+        versions: &[ApiVersion],
+        name: &'static str,
+        endpoint: E,
+    ) -> &mut Self
+    where
+        Q: DeserializeOwned + 'static,
+        I: Serialize + 'static,
+        F: for<'r> Fn(&'r ServiceApiState, Q) -> R + 'static + Clone,
+        E: Into<With<Q, I, R, F>>,
+        actix::RequestHandler: From<NamedWith<Q, I, R, F, Mutable>>,
+    {
+        let named_with = NamedWith::<_, _, _, _, Mutable>::new(name, endpoint);
+        self.actix_backend
+            .versioned_handler(versions, actix::RequestHandler::from(named_with));
+        self
+    }
+
+    /// Mounts the files from the given directory under the given path of the API scope,
+    /// e.g., to serve the frontend of the service by the node itself. Directory requests
+    /// are answered with the `index.html` file of the directory.
+    pub fn static_files(&mut self, path: &'static str, dir: impl Into<PathBuf>) -> &mut Self {
+        self.actix_backend.static_files(path, dir);
+        self
+    }
+
+    /// Mounts the files embedded into the binary under the given path of the API scope.
+    /// Files are specified by their paths relative to the mount point and their contents.
+    /// Directory requests are answered with the `index.html` file of the directory.
+    pub fn embedded_files<I>(&mut self, path: &'static str, files: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (&'static str, &'static [u8])>,
+    {
+        self.actix_backend.embedded_files(path, files);
+        self
+    }
+
     /// Returns a mutable reference to the underlying web backend.
     pub fn web_backend(&mut self) -> &mut actix::ApiBuilder {
         &mut self.actix_backend
@@ -343,19 +489,60 @@ impl ApiAggregator {
     }
 
     /// Extends the given API backend by handlers with the given access level.
+    ///
+    /// Requests served by the handlers are recorded to the API statistics of the node.
     pub fn extend_backend<B: ExtendApiBackend>(&self, access: ApiAccess, backend: B) -> B {
-        match access {
-            ApiAccess::Public => backend.extend(
-                self.inner
-                    .iter()
-                    .map(|(name, builder)| (name.as_ref(), &builder.public_scope)),
-            ),
-            ApiAccess::Private => backend.extend(
-                self.inner
-                    .iter()
-                    .map(|(name, builder)| (name.as_ref(), &builder.private_scope)),
-            ),
-        }
+        let stats = self.node_state.api_stats();
+        let scopes = self
+            .inner
+            .iter()
+            .map(|(name, builder)| {
+                let scope = match access {
+                    ApiAccess::Public => &builder.public_scope,
+                    ApiAccess::Private => &builder.private_scope,
+                };
+                let actix_backend = scope.actix_backend.with_stats(stats, access, name);
+                (name.as_str(), ServiceApiScope { actix_backend })
+            })
+            .collect::<Vec<_>>();
+        backend.extend(scopes.iter().map(|(name, scope)| (*name, scope)))
+    }
+
+    /// Exposes the feed of changes in the pool of unconfirmed transactions
+    /// via the public API. By default this feed is available only via the private API.
+    pub fn with_public_mempool_events(mut self) -> Self {
+        let service_api_state = ServiceApiState::new(self.blockchain.clone());
+        let builder = self
+            .inner
+            .get_mut("explorer")
+            .expect("Explorer API is always present");
+        ExplorerApi::handle_mempool_ws(
+            MEMPOOL_SUBSCRIBE_ENDPOINT,
+            builder.public_scope().web_backend(),
+            service_api_state,
+            self.node_state.clone(),
+        );
+        self
+    }
+
+    /// Aggregates only the light client API with the `light` prefix, which is served
+    /// by the light node.
+    pub(crate) fn light(blockchain: Blockchain, node_state: SharedNodeState) -> Self {
+        let aggregator = Self {
+            inner: BTreeMap::new(),
+            blockchain,
+            node_state,
+        };
+        aggregator.with_light_api()
+    }
+
+    /// Exposes the light client API with the `light` prefix. The API serves the light
+    /// chain kept in the database of the blockchain used by the aggregator.
+    pub fn with_light_api(mut self) -> Self {
+        let mut builder = ServiceApiBuilder::new();
+        LightApi::wire(builder.public_scope());
+        self.inner.insert("light".to_owned(), builder);
+        self
     }
 
     /// Adds API factory with the given prefix to the aggregator.
@@ -369,6 +556,12 @@ impl ApiAggregator {
     ) -> ServiceApiBuilder {
         let mut builder = ServiceApiBuilder::new();
         let service_api_state = ServiceApiState::new(blockchain.clone());
+        ExplorerApi::handle_mempool_ws(
+            MEMPOOL_SUBSCRIBE_ENDPOINT,
+            builder.private_scope().web_backend(),
+            service_api_state.clone(),
+            shared_node_state.clone(),
+        );
         ExplorerApi::wire(builder.public_scope(), service_api_state, shared_node_state);
         builder
     }
@@ -17,13 +17,38 @@
 //! Private API includes requests that are available only to the blockchain
 //! administrators, e.g. view the list of services on the current node.
 
-use std::{collections::HashMap, net::SocketAddr};
+use actix_web::{http, AsyncResponder, HttpMessage, HttpResponse};
+use chrono::{DateTime, Utc};
+use futures::{sync::oneshot, Future, IntoFuture};
+use hex::FromHex;
 
-use crate::api::{Error as ApiError, ServiceApiScope, ServiceApiState};
-use crate::blockchain::{Service, SharedNodeState};
-use crate::crypto::PublicKey;
-use crate::messages::PROTOCOL_MAJOR_VERSION;
-use crate::node::{ConnectInfo, ExternalMessage};
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use crate::api::{
+    backends::actix::{FutureResponse, HttpRequest, RawHandler, RequestHandler},
+    Error as ApiError, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+};
+use crate::blockchain::{HeightDiagnostics, PeerStats, Schema, Service, SharedNodeState};
+use crate::crypto::{Hash, PublicKey};
+use crate::events::BanInfo;
+use crate::helpers::Height;
+use crate::messages::{RawTransaction, Signed, PROTOCOL_MAJOR_VERSION};
+use crate::node::{
+    ConnectInfo, ConsensusStateInfo, ExternalMessage, NodeApiConfig, RemoveTransactionError,
+};
+use exonum_merkledb::{
+    indexes_stats, ColumnFamilySize, IndexAccess, IndexStats, StorageMetricsInfo,
+};
+
+/// The maximum number of transactions to return per mempool request.
+pub const MAX_MEMPOOL_TXS_PER_REQUEST: usize = 1000;
+
+/// The maximum number of heights to return per consensus diagnostics request.
+pub const MAX_DIAGNOSTICS_PER_REQUEST: u64 = 100;
 
 /// Short information about the service.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -101,6 +126,116 @@ struct ConsensusEnabledQuery {
     enabled: bool,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PausedQuery {
+    paused: bool,
+}
+
+/// Parameters of the API servers restart.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ApiRestartQuery {
+    /// New configuration of the API servers. If not specified, the servers are restarted
+    /// with the current configuration. The `state_update_timeout` parameter
+    /// is not affected by the restart.
+    #[serde(default)]
+    pub config: Option<NodeApiConfig>,
+}
+
+/// Parameters of the manual peer ban.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BanQuery {
+    /// IP address to ban.
+    pub address: IpAddr,
+    /// Duration of the ban in seconds. If not specified, the address is banned
+    /// until it is unbanned manually.
+    #[serde(default)]
+    pub duration: Option<u64>,
+}
+
+/// Memory pool query parameters.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct MempoolQuery {
+    /// If specified, only transactions of the service with the given identifier are returned.
+    pub service_id: Option<u16>,
+    /// If specified, only transactions signed by the given key are returned.
+    pub author: Option<PublicKey>,
+    /// The number of matching transactions to skip. The default value is zero.
+    #[serde(default)]
+    pub offset: usize,
+    /// The maximum number of transactions to return. Should not be greater than
+    /// `MAX_MEMPOOL_TXS_PER_REQUEST`, which is also the default value.
+    pub count: Option<usize>,
+}
+
+/// Consensus diagnostics query parameters.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct DiagnosticsQuery {
+    /// The latest height to return. The height of the latest committed block
+    /// is used if not specified.
+    pub latest: Option<Height>,
+    /// The maximum number of heights to return. Should not be greater than
+    /// `MAX_DIAGNOSTICS_PER_REQUEST`, which is also the default value.
+    pub count: Option<u64>,
+}
+
+/// Information about an uncommitted transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MempoolTransactionInfo {
+    /// Transaction hash.
+    pub tx_hash: Hash,
+    /// Identifier of the service the transaction belongs to.
+    pub service_id: u16,
+    /// Public key of the transaction author.
+    pub author: PublicKey,
+    /// Time at which the transaction was received by the node. The time is unknown
+    /// for transactions restored from the persistent pool after the node restart.
+    pub arrival_time: Option<DateTime<Utc>>,
+    /// Size of the signed transaction message in bytes.
+    pub size: usize,
+}
+
+/// Uncommitted transactions matching the mempool query.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MempoolInfo {
+    /// Total number of transactions matching the query filters.
+    pub total: usize,
+    /// Transactions within the requested page, ordered by hash.
+    pub transactions: Vec<MempoolTransactionInfo>,
+}
+
+/// Statistics of a peer recorded by the node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PeerStatsInfo {
+    /// Consensus key of the peer.
+    pub public_key: PublicKey,
+    /// Statistics of the peer.
+    pub stats: PeerStats,
+}
+
+/// Database information query parameters.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct DbInfoQuery {
+    /// Whether to return the sizes of the indexes. Accounting of the indexes reads
+    /// the whole database, so the sizes are not returned by default.
+    #[serde(default)]
+    pub indexes: bool,
+}
+
+/// Information about the node database.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DbInfo {
+    /// Counters of the storage operations since the node start.
+    pub metrics: StorageMetricsInfo,
+    /// Approximate sizes of the column families, as estimated by the database backend
+    /// without reading the data.
+    pub column_families: Vec<ColumnFamilySize>,
+    /// Sizes of the indexes, ordered by the index name. The sizes are returned only
+    /// if requested with the `indexes` query parameter.
+    pub indexes: Vec<IndexStats>,
+    /// Statistics of the peers, if the peer statistics are enabled.
+    pub peer_stats: Vec<PeerStatsInfo>,
+}
+
 /// Private system API.
 #[derive(Clone, Debug)]
 pub struct SystemApi {
@@ -121,11 +256,26 @@ impl SystemApi {
     pub fn wire(self, api_scope: &mut ServiceApiScope) -> &mut ServiceApiScope {
         self.handle_peers_info("v1/peers", api_scope)
             .handle_peer_add("v1/peers", api_scope)
+            .handle_peer_bans("v1/peers/bans", api_scope)
+            .handle_peer_ban("v1/peers/bans", api_scope)
+            .handle_peer_unban("v1/peers/bans/{address}", api_scope)
+            .handle_peer_stats("v1/peers/stats", api_scope)
+            // Resources are matched in the order of registration, so the endpoint
+            // with the path parameter goes after the static paths.
+            .handle_peer_remove("v1/peers/{public_key}", api_scope)
             .handle_network_info("v1/network", api_scope)
             .handle_is_consensus_enabled("v1/consensus_enabled", api_scope)
             .handle_set_consensus_enabled("v1/consensus_enabled", api_scope)
+            .handle_is_paused("v1/paused", api_scope)
+            .handle_set_paused("v1/paused", api_scope)
+            .handle_consensus_state("v1/consensus/state", api_scope)
+            .handle_consensus_diagnostics("v1/consensus/diagnostics", api_scope)
             .handle_shutdown("v1/shutdown", api_scope)
-            .handle_rebroadcast("v1/rebroadcast", api_scope);
+            .handle_rebroadcast("v1/rebroadcast", api_scope)
+            .handle_api_restart("v1/api/restart", api_scope)
+            .handle_mempool("v1/mempool", api_scope)
+            .handle_mempool_remove("v1/mempool/{hash}", api_scope)
+            .handle_db_info("v1/db-info", api_scope);
         api_scope
     }
 
@@ -172,6 +322,104 @@ impl SystemApi {
         self
     }
 
+    fn handle_peer_remove(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let index = move |request: HttpRequest| -> FutureResponse {
+            Self::remove_peer(&request)
+                .map(|()| HttpResponse::Ok().json(()))
+                .map_err(From::from)
+                .into_future()
+                .responder()
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::DELETE,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self
+    }
+
+    fn remove_peer(request: &HttpRequest) -> Result<(), ApiError> {
+        let public_key = request
+            .match_info()
+            .get("public_key")
+            .ok_or_else(|| ApiError::BadRequest("Peer public key is not specified".to_owned()))
+            .and_then(|key| {
+                PublicKey::from_hex(key)
+                    .map_err(|e| ApiError::BadRequest(format!("Invalid peer public key: {}", e)))
+            })?;
+
+        request
+            .state()
+            .sender()
+            .peer_remove(public_key)
+            .map_err(ApiError::from)
+    }
+
+    fn handle_peer_bans(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<Vec<BanInfo>, ApiError> {
+                Ok(self.shared_api_state.peer_bans().banned())
+            },
+        );
+        self_
+    }
+
+    fn handle_peer_ban(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint_mut(
+            name,
+            move |_state: &ServiceApiState, query: BanQuery| -> Result<(), ApiError> {
+                self.shared_api_state
+                    .peer_bans()
+                    .ban(query.address, query.duration);
+                Ok(())
+            },
+        );
+        self_
+    }
+
+    fn handle_peer_unban(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        let index = move |request: HttpRequest| -> FutureResponse {
+            self.unban_peer(&request)
+                .map(|()| HttpResponse::Ok().json(()))
+                .map_err(From::from)
+                .into_future()
+                .responder()
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::DELETE,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self_
+    }
+
+    fn unban_peer(&self, request: &HttpRequest) -> Result<(), ApiError> {
+        let address: IpAddr = request
+            .match_info()
+            .get("address")
+            .ok_or_else(|| ApiError::BadRequest("Peer address is not specified".to_owned()))
+            .and_then(|address| {
+                address
+                    .parse()
+                    .map_err(|e| ApiError::BadRequest(format!("Invalid peer address: {}", e)))
+            })?;
+
+        if self.shared_api_state.peer_bans().unban(&address) {
+            Ok(())
+        } else {
+            Err(ApiError::NotFound(format!(
+                "Peer address {} is not banned",
+                address
+            )))
+        }
+    }
+
     fn handle_network_info(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
         let self_ = self.clone();
         api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
@@ -210,6 +458,104 @@ impl SystemApi {
         self_
     }
 
+    fn handle_is_paused(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
+            Ok(self.shared_api_state.is_paused())
+        });
+        self_
+    }
+
+    fn handle_set_paused(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint_mut(
+            name,
+            move |state: &ServiceApiState, query: PausedQuery| -> Result<(), ApiError> {
+                let sender = state.sender();
+                if query.paused {
+                    sender.pause()
+                } else {
+                    sender.resume()
+                }
+                .map_err(ApiError::from)
+            },
+        );
+        self
+    }
+
+    fn handle_consensus_state(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |_state: &ServiceApiState, _query: ()| -> Result<ConsensusStateInfo, ApiError> {
+                self.shared_api_state.consensus_state().ok_or_else(|| {
+                    ApiError::NotFound("Consensus state is not available yet".to_owned())
+                })
+            },
+        );
+        self_
+    }
+
+    fn handle_consensus_diagnostics(
+        self,
+        name: &'static str,
+        api_scope: &mut ServiceApiScope,
+    ) -> Self {
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState,
+                  query: DiagnosticsQuery|
+                  -> Result<Vec<HeightDiagnostics>, ApiError> {
+                let count = query.count.unwrap_or(MAX_DIAGNOSTICS_PER_REQUEST);
+                if count > MAX_DIAGNOSTICS_PER_REQUEST {
+                    return Err(ApiError::BadRequest(format!(
+                        "Max height count per request exceeded ({})",
+                        MAX_DIAGNOSTICS_PER_REQUEST
+                    )));
+                }
+
+                let snapshot = state.snapshot();
+                let schema = Schema::new(&snapshot);
+                let latest = query.latest.unwrap_or_else(|| schema.height()).0;
+                let earliest = (latest + 1).saturating_sub(count);
+                Ok(schema
+                    .height_diagnostics()
+                    .iter_from(&earliest)
+                    .take_while(|(height, _)| *height <= latest)
+                    .map(|(_, diagnostics)| diagnostics)
+                    .collect())
+            },
+        );
+        self
+    }
+
+    fn handle_peer_stats(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(name, move |state: &ServiceApiState, _query: ()| {
+            Ok(peer_stats(&Schema::new(&state.snapshot())))
+        });
+        self
+    }
+
+    fn handle_db_info(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(name, move |state: &ServiceApiState, query: DbInfoQuery| {
+            // Counters are read first, since accounting of the indexes reads
+            // the whole database.
+            let metrics = state.blockchain().storage_metrics().info();
+            let snapshot = state.snapshot();
+            let indexes = if query.indexes {
+                indexes_stats(&*snapshot)
+            } else {
+                Vec::new()
+            };
+            Ok(DbInfo {
+                metrics,
+                column_families: state.blockchain().database().approximate_sizes(),
+                indexes,
+                peer_stats: peer_stats(&Schema::new(&snapshot)),
+            })
+        });
+        self
+    }
+
     fn handle_shutdown(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
         api_scope.endpoint_mut(
             name,
@@ -235,4 +581,160 @@ impl SystemApi {
         );
         self
     }
+
+    fn handle_api_restart(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        // The servers are stopped gracefully, so the response is sent before the server
+        // handling the request stops. The connection is closed after the response,
+        // otherwise the stop waits until the client closes the connection.
+        let shared_api_state = self.shared_api_state.clone();
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let shared_api_state = shared_api_state.clone();
+            request
+                .json()
+                .from_err()
+                .and_then(move |query: ApiRestartQuery| {
+                    shared_api_state
+                        .restart_api(query.config)
+                        .map(|()| HttpResponse::Ok().force_close().json(()))
+                        .map_err(|e| ApiError::from(e).into())
+                })
+                .responder()
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::POST,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self
+    }
+
+    fn handle_mempool(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(
+            name,
+            move |state: &ServiceApiState, query: MempoolQuery| -> Result<MempoolInfo, ApiError> {
+                let count = query.count.unwrap_or(MAX_MEMPOOL_TXS_PER_REQUEST);
+                if count > MAX_MEMPOOL_TXS_PER_REQUEST {
+                    return Err(ApiError::BadRequest(format!(
+                        "Max transaction count per request exceeded ({})",
+                        MAX_MEMPOOL_TXS_PER_REQUEST
+                    )));
+                }
+
+                let is_matching = |tx: &Signed<RawTransaction>| {
+                    query
+                        .service_id
+                        .map_or(true, |id| tx.payload().service_id() == id)
+                        && query.author.map_or(true, |key| tx.author() == key)
+                };
+
+                // The cache is read before the snapshot is taken, so that the transactions
+                // flushed into the persistent pool in between are not missed.
+                let tx_cache = self.shared_api_state.tx_cache();
+                let mut matching: BTreeMap<Hash, Signed<RawTransaction>> = tx_cache
+                    .txs()
+                    .iter()
+                    .filter(|(_, tx)| is_matching(tx))
+                    .map(|(tx_hash, tx)| (*tx_hash, tx.clone()))
+                    .collect();
+                let snapshot = state.snapshot();
+                let schema = Schema::new(&snapshot);
+                let transactions = schema.transactions();
+                for tx_hash in schema.transactions_pool().iter() {
+                    if let Some(tx) = transactions.get(&tx_hash) {
+                        if is_matching(&tx) {
+                            matching.insert(tx_hash, tx);
+                        }
+                    }
+                }
+
+                let total = matching.len();
+                let arrival_times = tx_cache.arrival_times();
+                let transactions = matching
+                    .into_iter()
+                    .skip(query.offset)
+                    .take(count)
+                    .map(|(tx_hash, tx)| MempoolTransactionInfo {
+                        tx_hash,
+                        service_id: tx.payload().service_id(),
+                        author: tx.author(),
+                        arrival_time: arrival_times.get(&tx_hash).cloned().map(DateTime::from),
+                        size: tx.signed_message().raw().len(),
+                    })
+                    .collect();
+
+                Ok(MempoolInfo {
+                    total,
+                    transactions,
+                })
+            },
+        );
+        self_
+    }
+
+    fn handle_mempool_remove(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        // The response is sent once the node has processed the removal, so that
+        // the refused removals are reported to the client.
+        let index = move |request: HttpRequest| -> FutureResponse {
+            tx_hash_from_path(&request)
+                .and_then(|tx_hash| {
+                    let outcome = request.state().sender().remove_transaction(tx_hash)?;
+                    Ok((tx_hash, outcome))
+                })
+                .into_future()
+                .and_then(|(tx_hash, outcome)| {
+                    outcome.then(move |outcome| removal_response(tx_hash, outcome))
+                })
+                .map_err(From::from)
+                .responder()
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::DELETE,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self
+    }
+}
+
+fn tx_hash_from_path(request: &HttpRequest) -> Result<Hash, ApiError> {
+    request
+        .match_info()
+        .get("hash")
+        .ok_or_else(|| ApiError::BadRequest("Transaction hash is not specified".to_owned()))
+        .and_then(|hash| {
+            Hash::from_hex(hash)
+                .map_err(|e| ApiError::BadRequest(format!("Invalid transaction hash: {}", e)))
+        })
+}
+
+fn removal_response(
+    tx_hash: Hash,
+    outcome: Result<Result<(), RemoveTransactionError>, oneshot::Canceled>,
+) -> Result<HttpResponse, ApiError> {
+    match outcome {
+        Ok(Ok(())) => Ok(HttpResponse::Ok().json(())),
+        Ok(Err(RemoveTransactionError::NotFound)) => Err(ApiError::NotFound(format!(
+            "Transaction {:?} is not found in the pool",
+            tx_hash
+        ))),
+        Ok(Err(RemoveTransactionError::Proposed)) => Err(ApiError::Conflict(format!(
+            "Transaction {:?} is a part of the current height proposes",
+            tx_hash
+        ))),
+        Err(oneshot::Canceled) => Err(ApiError::InternalError(format_err!(
+            "The node has stopped before removing transaction {:?}",
+            tx_hash
+        ))),
+    }
+}
+
+fn peer_stats<T: IndexAccess>(schema: &Schema<T>) -> Vec<PeerStatsInfo> {
+    schema
+        .peer_stats()
+        .iter()
+        .map(|(public_key, stats)| PeerStatsInfo { public_key, stats })
+        .collect()
 }
@@ -14,40 +14,61 @@
 
 //! Exonum blockchain explorer API.
 
-use actix::Arbiter;
+use actix::{Addr, Arbiter};
 use actix_web::{
-    http, http::header::CONTENT_LENGTH, ws, AsyncResponder, Error as ActixError, FromRequest,
-    HttpMessage, HttpResponse, Query,
+    error::PayloadError, http, http::header::CONTENT_LENGTH, ws, AsyncResponder,
+    Error as ActixError, FromRequest, HttpMessage, HttpResponse, Query,
 };
 use chrono::{DateTime, Utc};
+use exonum_merkledb::{
+    index_type, BinaryKey, BinaryValue, IndexType, MapProof, ObjectHash, ProofMapIndex, Snapshot,
+};
 use futures::{Future, IntoFuture};
+use hex::FromHex;
+use protobuf::RepeatedField;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
 
+use std::borrow::Cow;
 use std::ops::{Bound, Range};
 use std::sync::{Arc, Mutex};
 
 use crate::api::error::LengthLimit;
-use crate::blockchain::Schema;
+use crate::blockchain::{Schema, Service};
 use crate::{
     api::{
         backends::actix::{
             self as actix_backend, FutureResponse, HttpRequest, RawHandler, RequestHandler,
+            OCTET_STREAM_CONTENT_TYPE,
         },
         error::into_api_error,
         websocket::{Server, Session, SubscriptionType, TransactionFilter},
-        Error as ApiError, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+        Error as ApiError, ProtobufResponse, ServiceApiBackend, ServiceApiScope, ServiceApiState,
+    },
+    blockchain::{
+        Block, BlockProof, DoubleSignEvidence, SharedNodeState, StoredConfiguration,
+        TransactionResult, ValidatorLiveness, CORE_SERVICE,
+    },
+    crypto::{self, Hash, PublicKey},
+    events::{error::into_failure, internal::VerificationMetrics},
+    explorer::{
+        self, median_precommits_time, BlockchainExplorer, DecodedTransaction, TransactionDecoder,
+        TransactionInfo, TxStatus,
     },
-    blockchain::{Block, SharedNodeState},
-    crypto::Hash,
-    events::error::into_failure,
-    explorer::{self, median_precommits_time, BlockchainExplorer, TransactionInfo},
-    helpers::Height,
-    messages::{Message, Precommit, ProtocolMessage, RawTransaction, Signed, SignedMessage},
+    helpers::{Height, ValidatorId},
+    messages::{
+        HexStringRepresentation, Message, Precommit, ProtocolMessage, RawTransaction, Signed,
+        SignedMessage,
+    },
+    proto::{self, ProtobufConvert},
 };
 
 /// The maximum number of blocks to return per blocks request, in this way
 /// the parameter limits the maximum execution time for such requests.
 pub const MAX_BLOCKS_PER_REQUEST: usize = 1000;
 
+/// The maximum total number of keys in a proof bundle request.
+pub const MAX_KEYS_PER_PROOF_BUNDLE: usize = 1000;
+
 /// Information on blocks coupled with the corresponding range in the blockchain.
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct BlocksRange {
@@ -143,6 +164,89 @@ pub struct TransactionResponse {
     pub tx_hash: Hash,
 }
 
+/// Response to a signed transaction submitted in the request body.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SignedTransactionResponse {
+    /// Hash of the transaction computed by the node.
+    pub tx_hash: Hash,
+    /// Author of the transaction, whose signature has been verified.
+    pub author: PublicKey,
+}
+
+/// Reason of the rejection of a signed transaction submitted in the request body.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedTransactionErrorKind {
+    /// The body is neither raw bytes nor a valid hex string.
+    InvalidEncoding,
+    /// The body is too short to be a signed message, or its header is invalid.
+    MalformedEnvelope,
+    /// The signature does not match the author of the message.
+    InvalidSignature,
+    /// The message is correctly signed, but it is not a transaction.
+    NotTransaction,
+}
+
+/// Error returned in the body of the response to a rejected signed transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignedTransactionError {
+    /// Reason of the rejection.
+    pub kind: SignedTransactionErrorKind,
+    /// Human-readable description of the error.
+    pub description: String,
+}
+
+impl SignedTransactionError {
+    fn new(kind: SignedTransactionErrorKind, description: impl ToString) -> Self {
+        Self {
+            kind,
+            description: description.to_string(),
+        }
+    }
+}
+
+impl From<SignedTransactionError> for ApiError {
+    fn from(error: SignedTransactionError) -> Self {
+        let body = serde_json::to_string(&error).expect("Cannot serialize error");
+        ApiError::BadRequest(body)
+    }
+}
+
+/// Raw transaction to validate in hex representation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransactionValidateQuery {
+    /// The hex value of the transaction to be validated.
+    pub tx_body: String,
+    /// Whether to execute the transaction against the current blockchain state.
+    /// The default value is `false`.
+    #[serde(default)]
+    pub execute: bool,
+}
+
+/// Result of the transaction validation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TransactionValidateResponse {
+    /// The hash of the transaction.
+    pub tx_hash: Hash,
+    /// Identifier of the service the transaction belongs to.
+    pub service_id: u16,
+    /// Public key of the transaction author.
+    pub author: PublicKey,
+    /// Whether the transaction is already known to the blockchain, either committed,
+    /// possibly in a pruned block, or in the persistent pool of unconfirmed transactions.
+    pub is_known: bool,
+    /// Predicted result of the transaction execution, if the execution was requested.
+    pub execution: Option<ExecutionInfo>,
+}
+
+/// Result of the transaction execution against the current blockchain state.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionInfo {
+    /// Execution status.
+    #[serde(with = "TxStatus")]
+    pub status: TransactionResult,
+}
+
 /// Transaction query parameters.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct TransactionQuery {
@@ -157,6 +261,217 @@ impl TransactionQuery {
     }
 }
 
+/// Query parameters for the proof of an entry in an arbitrary `ProofMapIndex` of a service.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexProofQuery {
+    /// The name of the service owning the index.
+    pub service: String,
+    /// The name of the index.
+    pub index: String,
+    /// The binary representation of the key in hex.
+    pub key: String,
+    /// If true, then the key is considered to be a hash which is used to address the entry
+    /// in the index as is, like in indexes with `Hash` keys. Otherwise, the entry is
+    /// addressed by the hash of the key. The default value is false.
+    #[serde(default)]
+    pub key_is_hash: bool,
+    /// The height of the block whose state is proven. The state at earlier heights
+    /// is available only if the node keeps the state history. The default value is
+    /// the height of the latest block.
+    #[serde(default)]
+    pub height: Option<Height>,
+}
+
+/// Query parameters for the proof of the latest scheduled configuration.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ConfigProofQuery {
+    /// The height of the block whose state is proven, like in `IndexProofQuery`.
+    #[serde(default)]
+    pub height: Option<Height>,
+}
+
+/// Entries of an arbitrary `ProofMapIndex` of a service requested in a proof bundle.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexEntriesQuery {
+    /// The name of the service owning the index.
+    pub service: String,
+    /// The name of the index.
+    pub index: String,
+    /// The binary representations of the keys in hex.
+    pub keys: Vec<String>,
+    /// If true, then the keys are considered to be hashes, like in `IndexProofQuery`.
+    /// The default value is false.
+    #[serde(default)]
+    pub key_is_hash: bool,
+}
+
+/// Request body for the proof bundle covering entries of several indexes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProofBundleQuery {
+    /// Requested entries grouped by the index. The total number of keys should not
+    /// be greater than `MAX_KEYS_PER_PROOF_BUNDLE`.
+    pub indexes: Vec<IndexEntriesQuery>,
+    /// The height of the block whose state is proven, like in `IndexProofQuery`.
+    #[serde(default)]
+    pub height: Option<Height>,
+    /// Whether the block is proven by its certificate instead of the precommits if
+    /// the block is certified, which makes the bundle smaller.
+    #[serde(default)]
+    pub compact: bool,
+}
+
+/// Key of an entry in an arbitrary `ProofMapIndex`.
+///
+/// The key is serialized as the hash addressing the entry in the index, thus
+/// the `MapProof` with such keys can be verified without knowing the actual key type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexKey {
+    bytes: Vec<u8>,
+    hash: Hash,
+}
+
+impl IndexKey {
+    /// Returns the hash addressing the entry in the index.
+    pub fn hash(&self) -> &Hash {
+        &self.hash
+    }
+}
+
+impl BinaryKey for IndexKey {
+    fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn write(&self, buffer: &mut [u8]) -> usize {
+        buffer[..self.bytes.len()].copy_from_slice(&self.bytes);
+        self.bytes.len()
+    }
+
+    fn read(buffer: &[u8]) -> Self {
+        Self {
+            bytes: buffer.to_vec(),
+            hash: crypto::hash(buffer),
+        }
+    }
+}
+
+impl ObjectHash for IndexKey {
+    fn object_hash(&self) -> Hash {
+        self.hash
+    }
+}
+
+impl Serialize for IndexKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.hash.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IndexKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hash = Hash::deserialize(deserializer)?;
+        Ok(Self {
+            bytes: hash.as_ref().to_vec(),
+            hash,
+        })
+    }
+}
+
+/// Query parameters for the last nonce of a transaction author.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct NonceQuery {
+    /// Public key of the author.
+    pub author: PublicKey,
+}
+
+/// Last nonce of a transaction author, see `Transaction::nonce`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct NonceInfo {
+    /// Last nonce used by the author, or `None` if the author has not used nonces yet.
+    pub last_nonce: Option<u64>,
+}
+
+/// Liveness of a validator over the sliding window of the latest blocks.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ValidatorLivenessInfo {
+    /// Identifier of the validator in the current configuration.
+    pub validator_id: ValidatorId,
+    /// Consensus key of the validator.
+    pub consensus_key: PublicKey,
+    /// Numbers of the blocks and the messages missed by the validator.
+    pub liveness: ValidatorLiveness,
+}
+
+/// Value of an entry in an arbitrary `ProofMapIndex` in its binary representation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexValue(#[serde(with = "HexStringRepresentation")] pub Vec<u8>);
+
+impl BinaryValue for IndexValue {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Result<Self, failure::Error> {
+        Ok(Self(bytes.into_owned()))
+    }
+}
+
+impl ObjectHash for IndexValue {
+    fn object_hash(&self) -> Hash {
+        crypto::hash(&self.0)
+    }
+}
+
+/// Proof of an entry in an arbitrary `ProofMapIndex` of a service.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexProof {
+    /// Proof of the latest block.
+    pub block_proof: BlockProof,
+    /// Identifier of the service owning the index.
+    pub service_id: u16,
+    /// Index of the index root hash in the `Vec` returned by the `state_hash` method
+    /// of the service.
+    pub table_idx: usize,
+    /// Proof of the index root hash in the state hash of the block.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the requested entry in the index.
+    pub to_entry: MapProof<IndexKey, IndexValue>,
+}
+
+/// Proof of a configuration committed to the blockchain.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConfigProof {
+    /// Proof of the block whose state contains the configuration.
+    pub block_proof: BlockProof,
+    /// Proof of the configurations table root hash in the state hash of the block.
+    pub to_table: MapProof<Hash, Hash>,
+    /// Proof of the configuration in the configurations table.
+    pub to_config: MapProof<Hash, StoredConfiguration>,
+}
+
+/// Proof of several entries in an arbitrary `ProofMapIndex` of a service.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexEntriesProof {
+    /// The name of the service owning the index.
+    pub service: String,
+    /// The name of the index.
+    pub index: String,
+    /// Combined proof of the requested entries in the index.
+    pub to_entries: MapProof<IndexKey, IndexValue>,
+}
+
+/// Proofs of entries in several indexes anchored to the state hash of the latest block.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProofBundle {
+    /// Proof of the latest block.
+    pub block_proof: BlockProof,
+    /// Combined proof of the root hashes of all the requested indexes in the state hash
+    /// of the block.
+    pub to_tables: MapProof<Hash, Hash>,
+    /// Proofs of the requested entries, in the order of the indexes in the request.
+    pub indexes: Vec<IndexEntriesProof>,
+}
+
 /// Exonum blockchain explorer API.
 #[derive(Debug, Clone, Copy)]
 pub struct ExplorerApi;
@@ -199,6 +514,15 @@ impl ExplorerApi {
             .rev()
             .filter(|block| !query.skip_empty_blocks || !block.is_empty())
             .take(query.count)
+            .collect();
+        if query.add_blocks_time || query.add_precommits {
+            if let Some(block) = blocks.iter().find(|block| block.is_pruned()) {
+                return Err(pruned_block_error(block.height(), &explorer));
+            }
+        }
+
+        let blocks: Vec<_> = blocks
+            .into_iter()
             .map(|block| BlockInfo {
                 txs: None,
 
@@ -232,12 +556,57 @@ impl ExplorerApi {
 
     /// Returns the content for a block at a specific height.
     pub fn block(state: &ServiceApiState, query: BlockQuery) -> Result<BlockInfo, ApiError> {
-        BlockchainExplorer::new(state.blockchain())
-            .block(query.height)
-            .map(From::from)
-            .ok_or_else(|| {
-                ApiError::NotFound(format!("Block for height: {} not found", query.height))
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        let block = explorer.block(query.height).ok_or_else(|| {
+            ApiError::NotFound(format!("Block for height: {} not found", query.height))
+        })?;
+        if block.is_pruned() {
+            return Err(pruned_block_error(query.height, &explorer));
+        }
+        Ok(block.into())
+    }
+
+    /// Returns the evidence of the validators signing conflicting consensus messages
+    /// observed by the node.
+    pub fn double_sign_evidence(
+        state: &ServiceApiState,
+        _query: (),
+    ) -> Result<Vec<DoubleSignEvidence>, ApiError> {
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        Ok(explorer.double_sign_evidence())
+    }
+
+    /// Returns the liveness of the current validators. The list is empty if liveness
+    /// tracking is disabled on the node.
+    pub fn validators_liveness(
+        state: &ServiceApiState,
+        _query: (),
+    ) -> Result<Vec<ValidatorLivenessInfo>, ApiError> {
+        let snapshot = state.snapshot();
+        let schema = Schema::new(&snapshot);
+        let liveness = schema.validator_liveness();
+        Ok(schema
+            .actual_configuration()
+            .validator_keys
+            .iter()
+            .enumerate()
+            .filter_map(|(id, keys)| {
+                let info = liveness.get(&keys.consensus_key)?;
+                Some(ValidatorLivenessInfo {
+                    validator_id: ValidatorId(id as u16),
+                    consensus_key: keys.consensus_key,
+                    liveness: info,
+                })
             })
+            .collect())
+    }
+
+    /// Returns the last nonce used by the transaction author. The next transaction
+    /// of the author must have a greater nonce.
+    pub fn nonce(state: &ServiceApiState, query: NonceQuery) -> Result<NonceInfo, ApiError> {
+        let snapshot = state.snapshot();
+        let last_nonce = Schema::new(&snapshot).author_nonces().get(&query.author);
+        Ok(NonceInfo { last_nonce })
     }
 
     /// Searches for a transaction, either committed or uncommitted, by the hash.
@@ -245,13 +614,148 @@ impl ExplorerApi {
         state: &ServiceApiState,
         query: TransactionQuery,
     ) -> Result<TransactionInfo, ApiError> {
-        BlockchainExplorer::new(state.blockchain())
-            .transaction(&query.hash)
-            .ok_or_else(|| {
-                let description = serde_json::to_string(&json!({ "type": "unknown" })).unwrap();
+        let explorer = BlockchainExplorer::new(state.blockchain());
+        explorer.transaction(&query.hash).ok_or_else(|| {
+            if explorer.is_transaction_pruned(&query.hash) {
+                let description = serde_json::to_string(&json!({ "type": "pruned" })).unwrap();
                 debug!("{}", description);
-                ApiError::NotFound(description)
-            })
+                return ApiError::Pruned(description);
+            }
+            if let Some(rejected) = explorer.rejected_transaction(&query.hash) {
+                let description = serde_json::to_string(&json!({
+                    "type": "rejected",
+                    "height": rejected.height,
+                    "reason": rejected.reason,
+                }))
+                .unwrap();
+                debug!("{}", description);
+                return ApiError::NotFound(description);
+            }
+            let description = serde_json::to_string(&json!({ "type": "unknown" })).unwrap();
+            debug!("{}", description);
+            ApiError::NotFound(description)
+        })
+    }
+
+    /// Decodes a committed or uncommitted transaction with the protobuf descriptor
+    /// of its service stored in the blockchain. Unlike `transaction_info`, the transaction
+    /// is decoded even if its service is not compiled into the node.
+    pub fn decoded_transaction(
+        state: &ServiceApiState,
+        query: TransactionQuery,
+    ) -> Result<DecodedTransaction, ApiError> {
+        let snapshot = state.snapshot();
+        let transaction = Schema::new(&snapshot)
+            .transactions()
+            .get(&query.hash)
+            .ok_or_else(|| ApiError::NotFound("Transaction not found".to_owned()))?;
+        TransactionDecoder::new(&*snapshot)
+            .and_then(|decoder| decoder.decode(&transaction))
+            .map_err(ApiError::InternalError)
+    }
+
+    /// Returns the proof of an entry in an arbitrary `ProofMapIndex` of a service together
+    /// with the proof of the index in the state hash of the requested block, which is
+    /// the latest one by default.
+    pub fn index_proof(
+        state: &ServiceApiState,
+        query: IndexProofQuery,
+    ) -> Result<IndexProof, ApiError> {
+        let service = find_service(state, &query.service)?;
+        let key = parse_index_key(&query.key, query.key_is_hash)?;
+
+        let snapshot = snapshot_at(state, query.height)?;
+        let (index, table_idx) = service_proof_map(&*snapshot, service, &query.index)?;
+
+        let schema = Schema::new(&snapshot);
+        let block_proof = schema
+            .block_and_precommits(schema.height())
+            .expect("Latest block is absent");
+        let to_table = schema.get_proof_to_service_table(service.service_id(), table_idx);
+
+        Ok(IndexProof {
+            block_proof,
+            service_id: service.service_id(),
+            table_idx,
+            to_table,
+            to_entry: index.get_proof(key),
+        })
+    }
+
+    /// Returns the combined proofs of entries in several `ProofMapIndex`es together with
+    /// a single proof of the indexes in the state hash of the requested block, which is
+    /// the latest one by default.
+    pub fn proof_bundle(
+        state: &ServiceApiState,
+        query: ProofBundleQuery,
+    ) -> Result<ProofBundle, ApiError> {
+        let keys_count: usize = query.indexes.iter().map(|index| index.keys.len()).sum();
+        if keys_count > MAX_KEYS_PER_PROOF_BUNDLE {
+            return Err(ApiError::BadRequest(format!(
+                "Max key count per proof bundle exceeded ({})",
+                MAX_KEYS_PER_PROOF_BUNDLE
+            )));
+        }
+
+        let snapshot = snapshot_at(state, query.height)?;
+        let mut tables = Vec::with_capacity(query.indexes.len());
+        let mut indexes = Vec::with_capacity(query.indexes.len());
+        for index_query in query.indexes {
+            let service = find_service(state, &index_query.service)?;
+            let keys = index_query
+                .keys
+                .iter()
+                .map(|key| parse_index_key(key, index_query.key_is_hash))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (index, table_idx) = service_proof_map(&*snapshot, service, &index_query.index)?;
+
+            tables.push((service.service_id(), table_idx));
+            indexes.push(IndexEntriesProof {
+                to_entries: index.get_multiproof(keys),
+                service: index_query.service,
+                index: index_query.index,
+            });
+        }
+
+        let schema = Schema::new(&snapshot);
+        let block_proof = if query.compact {
+            schema.block_and_certificate(schema.height())
+        } else {
+            schema.block_and_precommits(schema.height())
+        }
+        .expect("Latest block is absent");
+        let to_tables = schema.get_proof_to_service_tables(tables);
+
+        Ok(ProofBundle {
+            block_proof,
+            to_tables,
+            indexes,
+        })
+    }
+
+    /// Returns the proof of the latest configuration committed to the blockchain, which
+    /// may be scheduled to become actual at a future height. The proof is anchored to
+    /// the requested block, which is the latest one by default.
+    pub fn config_proof(
+        state: &ServiceApiState,
+        query: ConfigProofQuery,
+    ) -> Result<ConfigProof, ApiError> {
+        let snapshot = snapshot_at(state, query.height)?;
+        let schema = Schema::new(&snapshot);
+        let block_proof = schema
+            .block_and_precommits(schema.height())
+            .expect("Latest block is absent");
+        let cfg_hash = *schema
+            .configs_actual_from()
+            .last()
+            .expect("Genesis configuration is absent")
+            .cfg_hash();
+
+        Ok(ConfigProof {
+            block_proof,
+            to_table: schema.get_proof_to_service_table(CORE_SERVICE, 0),
+            to_config: schema.configs().get_proof(cfg_hash),
+        })
     }
 
     /// Adds transaction into unconfirmed tx pool, and broadcast transaction to other nodes.
@@ -260,9 +764,128 @@ impl ExplorerApi {
         backend: &mut actix_backend::ApiBuilder,
         service_api_state: ServiceApiState,
     ) {
+        Self::transaction_handler(name, backend, service_api_state, None, Self::tx_handler);
+    }
+
+    /// Adds the endpoint accepting signed transactions as the request body, either as raw
+    /// bytes with `application/octet-stream` content type, or as a hex string. The node
+    /// verifies the signature of the transaction, adds it into the pool and broadcasts it
+    /// to other nodes, responding with its hash. Malformed transactions are rejected with
+    /// `SignedTransactionError` in the response body.
+    fn signed_transaction_handler(
+        name: &str,
+        backend: &mut actix_backend::ApiBuilder,
+        service_api_state: ServiceApiState,
+        verification: Option<VerificationMetrics>,
+    ) {
+        let (max_message_len, _) = get_message_limits(&service_api_state);
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let state = request.state().clone();
+            let verification = verification.clone();
+            let is_binary = request.content_type() == OCTET_STREAM_CONTENT_TYPE;
+            let content_length = request
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|length| length.to_str().ok())
+                .and_then(|length| length.parse::<usize>().ok())
+                .unwrap_or_default();
+            // Hex strings take two characters per byte, and may be surrounded by whitespace.
+            let body_limit = if is_binary {
+                max_message_len
+            } else {
+                max_message_len * 2 + 64
+            };
+            request
+                .body()
+                .limit(body_limit)
+                .map_err(move |e| match e {
+                    PayloadError::Overflow => ApiError::PayloadTooLarge {
+                        length_limit: LengthLimit::Message(max_message_len),
+                        content_length: if is_binary {
+                            content_length
+                        } else {
+                            content_length / 2
+                        },
+                    },
+                    e => ApiError::BadRequest(format!("Error while reading payload: {}", e)),
+                })
+                .from_err()
+                .and_then(move |body| {
+                    if let Some(ref verification) = verification {
+                        if !verification.try_schedule() {
+                            let message = "Too many messages are being verified".to_owned();
+                            return Err(ApiError::ServiceUnavailable(message).into());
+                        }
+                    }
+                    let network_id = state.blockchain().network_id();
+                    let max_decompressed_len = get_max_decompressed_len(&state);
+                    let result = parse_signed_transaction(
+                        &body,
+                        is_binary,
+                        max_message_len,
+                        max_decompressed_len,
+                        network_id,
+                    )
+                    .and_then(|(tx_hash, signed)| {
+                        let author = signed.author();
+                        state
+                            .sender()
+                            .broadcast_transaction(signed)
+                            .map_err(ApiError::from)?;
+                        Ok(SignedTransactionResponse { tx_hash, author })
+                    });
+                    if let Some(ref verification) = verification {
+                        verification.record_verified(result.is_ok());
+                    }
+                    result
+                        .map(|value| HttpResponse::Ok().json(value))
+                        .map_err(From::from)
+                })
+                .responder()
+        };
+
+        backend.raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::POST,
+            inner: Arc::new(index) as Arc<RawHandler>,
+        });
+    }
+
+    /// Verifies the transaction signature and checks that it can be processed
+    /// by one of the services, optionally executing it against the current blockchain
+    /// state. The transaction is neither added to the pool nor broadcast.
+    pub fn validate_transaction(
+        name: &str,
+        backend: &mut actix_backend::ApiBuilder,
+        service_api_state: ServiceApiState,
+    ) {
+        Self::transaction_handler(
+            name,
+            backend,
+            service_api_state,
+            None,
+            Self::validate_handler,
+        );
+    }
+
+    /// Adds the endpoint processing transactions with the given handler. If `verification`
+    /// is specified, the transactions share the limit of the messages verified at the same
+    /// time with the messages received from the peers.
+    fn transaction_handler<Q, R, F>(
+        name: &str,
+        backend: &mut actix_backend::ApiBuilder,
+        service_api_state: ServiceApiState,
+        verification: Option<VerificationMetrics>,
+        handler: F,
+    ) where
+        Q: DeserializeOwned + 'static,
+        R: Serialize + 'static,
+        F: Fn(&ServiceApiState, Q, usize) -> Result<R, ApiError> + Send + Sync + Copy + 'static,
+    {
         let (max_message_len, max_payload_len) = get_message_limits(&service_api_state);
         let index = move |request: HttpRequest| {
             let state = request.state().clone();
+            let verification = verification.clone();
             let content_length = match request.headers().get(CONTENT_LENGTH) {
                 Some(length) => length.to_str().unwrap_or_default().to_owned(),
                 None => {
@@ -278,8 +901,18 @@ impl ExplorerApi {
                     into_api_error(e, LengthLimit::Json(max_payload_len), content_length)
                 })
                 .from_err()
-                .and_then(move |query: TransactionHex| {
-                    Self::tx_handler(&state, query, max_message_len)
+                .and_then(move |query: Q| {
+                    if let Some(ref verification) = verification {
+                        if !verification.try_schedule() {
+                            let message = "Too many messages are being verified".to_owned();
+                            return Err(ApiError::ServiceUnavailable(message).into());
+                        }
+                    }
+                    let result = handler(&state, query, max_message_len);
+                    if let Some(ref verification) = verification {
+                        verification.record_verified(result.is_ok());
+                    }
+                    result
                         .map(|value| HttpResponse::Ok().json(value))
                         .map_err(From::from)
                 })
@@ -298,18 +931,14 @@ impl ExplorerApi {
         query: TransactionHex,
         max_message_len: usize,
     ) -> Result<TransactionResponse, ApiError> {
-        let message_len_in_bytes = query.tx_body.len() / 2; // one byte == 2 digits in hex
-        if message_len_in_bytes > max_message_len {
-            return Err(ApiError::PayloadTooLarge {
-                length_limit: LengthLimit::Message(max_message_len),
-                content_length: message_len_in_bytes,
-            });
-        }
-        let buf: Vec<u8> = ::hex::decode(query.tx_body).map_err(into_failure)?;
-        let signed = SignedMessage::from_raw_buffer(buf)?;
-        let tx_hash = signed.hash();
-        let signed = RawTransaction::try_from(Message::deserialize(signed)?)
-            .map_err(|_| format_err!("Couldn't deserialize transaction message."))?;
+        let network_id = state.blockchain().network_id();
+        let max_decompressed_len = get_max_decompressed_len(state);
+        let (tx_hash, signed) = parse_transaction(
+            &query.tx_body,
+            max_message_len,
+            max_decompressed_len,
+            network_id,
+        )?;
         state
             .sender()
             .broadcast_transaction(signed)
@@ -317,6 +946,46 @@ impl ExplorerApi {
         Ok(TransactionResponse { tx_hash })
     }
 
+    fn validate_handler(
+        state: &ServiceApiState,
+        query: TransactionValidateQuery,
+        max_message_len: usize,
+    ) -> Result<TransactionValidateResponse, ApiError> {
+        let network_id = state.blockchain().network_id();
+        let max_decompressed_len = get_max_decompressed_len(state);
+        let (tx_hash, signed) = parse_transaction(
+            &query.tx_body,
+            max_message_len,
+            max_decompressed_len,
+            network_id,
+        )?;
+        let blockchain = state.blockchain();
+        blockchain
+            .tx_from_signed(&signed)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))?;
+
+        // The same state is used both to check the transaction and to execute it.
+        let snapshot: Arc<dyn Snapshot> = Arc::from(state.snapshot());
+        let schema = Schema::new(&*snapshot);
+        // Transactions of the pruned blocks are known by their locations.
+        let is_known = schema.transactions().contains(&tx_hash)
+            || schema.transactions_locations().contains(&tx_hash);
+        let execution = if query.execute {
+            let (status, _) = blockchain.speculate(&snapshot, &signed)?;
+            Some(ExecutionInfo { status })
+        } else {
+            None
+        };
+
+        Ok(TransactionValidateResponse {
+            tx_hash,
+            service_id: signed.service_id(),
+            author: signed.author(),
+            is_known,
+            execution,
+        })
+    }
+
     /// Subscribes to events.
     pub fn handle_ws<Q>(
         name: &str,
@@ -326,6 +995,41 @@ impl ExplorerApi {
         extract_query: Q,
     ) where
         Q: Fn(&HttpRequest) -> Result<SubscriptionType, ActixError> + Send + Sync + 'static,
+    {
+        Self::ws_handler(
+            name,
+            backend,
+            service_api_state,
+            extract_query,
+            move |address| shared_node_state.set_broadcast_server_address(address),
+        );
+    }
+
+    /// Subscribes to changes in the pool of unconfirmed transactions.
+    pub fn handle_mempool_ws(
+        name: &str,
+        backend: &mut actix_backend::ApiBuilder,
+        service_api_state: ServiceApiState,
+        shared_node_state: SharedNodeState,
+    ) {
+        Self::ws_handler(
+            name,
+            backend,
+            service_api_state,
+            |_| Ok(SubscriptionType::Mempool),
+            move |address| shared_node_state.add_mempool_server_address(address),
+        );
+    }
+
+    fn ws_handler<Q, R>(
+        name: &str,
+        backend: &mut actix_backend::ApiBuilder,
+        service_api_state: ServiceApiState,
+        extract_query: Q,
+        register_server: R,
+    ) where
+        Q: Fn(&HttpRequest) -> Result<SubscriptionType, ActixError> + Send + Sync + 'static,
+        R: Fn(Addr<Server>) + Send + Sync + 'static,
     {
         let (max_message_len, max_payload_len) = get_message_limits(&service_api_state);
         let server = Arc::new(Mutex::new(None));
@@ -340,7 +1044,7 @@ impl ExplorerApi {
                 *address = Some(Arbiter::start(move |_| {
                     Server::new(service_api_state, max_message_len)
                 }));
-                shared_node_state.set_broadcast_server_address(address.to_owned().unwrap());
+                register_server(address.to_owned().unwrap());
             }
 
             let address = address.to_owned().unwrap();
@@ -379,10 +1083,23 @@ impl ExplorerApi {
         service_api_state: ServiceApiState,
         shared_node_state: SharedNodeState,
     ) -> &mut ServiceApiScope {
-        Self::add_transaction(
+        Self::transaction_handler(
             "v1/transactions",
             api_scope.web_backend(),
             service_api_state.clone(),
+            Some(shared_node_state.verification_metrics().clone()),
+            Self::tx_handler,
+        );
+        Self::signed_transaction_handler(
+            "v1/transactions/signed",
+            api_scope.web_backend(),
+            service_api_state.clone(),
+            Some(shared_node_state.verification_metrics().clone()),
+        );
+        Self::validate_transaction(
+            "v1/transactions/validate",
+            api_scope.web_backend(),
+            service_api_state.clone(),
         );
 
         // Default subscription for blocks.
@@ -426,8 +1143,15 @@ impl ExplorerApi {
 
         api_scope
             .endpoint("v1/blocks", Self::blocks)
-            .endpoint("v1/block", Self::block)
-            .endpoint("v1/transactions", Self::transaction_info)
+            .negotiated_endpoint("v1/block", Self::block)
+            .negotiated_endpoint("v1/transactions", Self::transaction_info)
+            .endpoint("v1/transactions/decoded", Self::decoded_transaction)
+            .negotiated_endpoint("v1/proofs", Self::index_proof)
+            .endpoint_mut("v1/proofs/bundle", Self::proof_bundle)
+            .endpoint("v1/configs/proof", Self::config_proof)
+            .endpoint("v1/evidence", Self::double_sign_evidence)
+            .endpoint("v1/validators/liveness", Self::validators_liveness)
+            .endpoint("v1/nonce", Self::nonce)
     }
 }
 
@@ -455,6 +1179,243 @@ impl<'a> From<explorer::BlockInfo<'a>> for BlockInfo {
     }
 }
 
+/// Encoded as `BlockResponse` without the recipient.
+impl ProtobufResponse for BlockInfo {
+    type Message = proto::BlockResponse;
+
+    fn to_protobuf(&self) -> proto::BlockResponse {
+        let mut pb = proto::BlockResponse::new();
+        pb.set_block(self.block.to_pb());
+        if let Some(ref precommits) = self.precommits {
+            pb.set_precommits(signed_messages_to_pb(precommits));
+        }
+        if let Some(ref txs) = self.txs {
+            pb.set_transactions(txs.iter().map(|tx| tx.tx_hash.to_pb()).collect());
+        }
+        pb
+    }
+}
+
+/// Encoded as `TransactionsResponse` without the recipient, which contains
+/// the signed transaction message.
+impl ProtobufResponse for TransactionInfo {
+    type Message = proto::TransactionsResponse;
+
+    fn to_protobuf(&self) -> proto::TransactionsResponse {
+        let mut pb = proto::TransactionsResponse::new();
+        let message = self.content().signed_message().raw().to_vec();
+        pb.set_transactions(vec![message].into());
+        pb
+    }
+}
+
+impl ProtobufResponse for IndexProof {
+    type Message = proto::IndexProof;
+
+    fn to_protobuf(&self) -> proto::IndexProof {
+        let mut block_proof = proto::BlockProof::new();
+        block_proof.set_block(self.block_proof.block.to_pb());
+        block_proof.set_precommits(signed_messages_to_pb(&self.block_proof.precommits));
+
+        let mut pb = proto::IndexProof::new();
+        pb.set_block_proof(block_proof);
+        pb.set_service_id(u32::from(self.service_id));
+        pb.set_table_idx(self.table_idx as u64);
+        pb.set_to_table(map_proof_to_pb(&self.to_table));
+        pb.set_to_entry(map_proof_to_pb(&self.to_entry));
+        pb
+    }
+}
+
+fn find_service<'a>(
+    state: &'a ServiceApiState,
+    service_name: &str,
+) -> Result<&'a dyn Service, ApiError> {
+    state
+        .blockchain()
+        .service_map()
+        .values()
+        .find(|service| service.service_name() == service_name)
+        .map(|service| &**service)
+        .ok_or_else(|| ApiError::NotFound(format!("Service {} not found", service_name)))
+}
+
+/// Returns the snapshot of the state at the given height, or of the latest state
+/// if the height is not specified.
+fn snapshot_at(
+    state: &ServiceApiState,
+    height: Option<Height>,
+) -> Result<Box<dyn Snapshot>, ApiError> {
+    match height {
+        Some(height) => state
+            .snapshot_at(height)
+            .map_err(|e| ApiError::NotFound(e.to_string())),
+        None => Ok(state.snapshot()),
+    }
+}
+
+fn parse_index_key(key: &str, key_is_hash: bool) -> Result<IndexKey, ApiError> {
+    let bytes = Vec::<u8>::from_hex(key)
+        .map_err(|e| ApiError::BadRequest(format!("Invalid key: {}", e)))?;
+    let hash = if key_is_hash {
+        Hash::from_slice(&bytes).ok_or_else(|| {
+            ApiError::BadRequest(format!("Key of {} bytes is not a hash", bytes.len()))
+        })?
+    } else {
+        crypto::hash(&bytes)
+    };
+    Ok(IndexKey { bytes, hash })
+}
+
+/// Returns the `ProofMapIndex` with the given name together with the index of its hash
+/// in the state hash of the service.
+fn service_proof_map<'a>(
+    snapshot: &'a dyn Snapshot,
+    service: &dyn Service,
+    index_name: &str,
+) -> Result<(ProofMapIndex<&'a dyn Snapshot, IndexKey, IndexValue>, usize), ApiError> {
+    if index_type(snapshot, index_name) != Some(IndexType::ProofMap) {
+        return Err(ApiError::NotFound(format!(
+            "ProofMapIndex {} not found",
+            index_name
+        )));
+    }
+    let index = ProofMapIndex::new(index_name, snapshot);
+
+    let index_hash = index.object_hash();
+    let table_idx = service
+        .state_hash(snapshot)
+        .iter()
+        .position(|hash| *hash == index_hash)
+        .ok_or_else(|| {
+            ApiError::NotFound(format!(
+                "Index {} is not a part of the {} service state",
+                index_name,
+                service.service_name()
+            ))
+        })?;
+    Ok((index, table_idx))
+}
+
+fn pruned_block_error(height: Height, explorer: &BlockchainExplorer) -> ApiError {
+    ApiError::Pruned(format!(
+        "Transactions and precommits of block at height {} have been pruned, \
+         the earliest available height is {}",
+        height,
+        explorer.first_unpruned_height()
+    ))
+}
+
+fn signed_messages_to_pb<T: ProtocolMessage>(messages: &[Signed<T>]) -> RepeatedField<Vec<u8>> {
+    messages
+        .iter()
+        .map(|message| message.signed_message().raw().to_vec())
+        .collect()
+}
+
+fn map_proof_to_pb<K, V>(proof: &MapProof<K, V>) -> proto::MapProof
+where
+    K: ObjectHash,
+    V: BinaryValue,
+{
+    let mut pb = proto::MapProof::new();
+    for (path, hash) in proof.proof_unchecked() {
+        let mut node = proto::MapProofNode::new();
+        node.set_path(path.as_bytes().to_vec());
+        node.set_hash(hash.to_pb());
+        pb.mut_proof().push(node);
+    }
+    for (key, value) in proof.all_entries_unchecked() {
+        let key = key.object_hash().to_pb();
+        if let Some(value) = value {
+            let mut entry = proto::MapProofEntry::new();
+            entry.set_key(key);
+            entry.set_value(value.to_bytes());
+            pb.mut_entries().push(entry);
+        } else {
+            pb.mut_missing_keys().push(key);
+        }
+    }
+    pb
+}
+
+/// Decodes the signed transaction from its hex representation and verifies its signature
+/// made for the network with the given identifier. The payload of a compressed transaction
+/// is decompressed up to `max_decompressed_len` bytes.
+fn parse_transaction(
+    tx_body: &str,
+    max_message_len: usize,
+    max_decompressed_len: Option<u32>,
+    network_id: Option<Hash>,
+) -> Result<(Hash, Signed<RawTransaction>), ApiError> {
+    let message_len_in_bytes = tx_body.len() / 2; // one byte == 2 digits in hex
+    if message_len_in_bytes > max_message_len {
+        return Err(ApiError::PayloadTooLarge {
+            length_limit: LengthLimit::Message(max_message_len),
+            content_length: message_len_in_bytes,
+        });
+    }
+    let buf: Vec<u8> = ::hex::decode(tx_body).map_err(into_failure)?;
+    let signed = SignedMessage::from_raw_buffer_in_network(buf, network_id)?;
+    let tx_hash = signed.hash();
+    let signed = RawTransaction::try_from(Message::deserialize_with_limit(
+        signed,
+        max_decompressed_len,
+    )?)
+    .map_err(|_| format_err!("Couldn't deserialize transaction message."))?;
+    Ok((tx_hash, signed))
+}
+
+/// Parses the signed transaction from the request body, classifying the errors
+/// with `SignedTransactionErrorKind`. The signature must be made for the network
+/// with the given identifier, and the payload of a compressed transaction is decompressed
+/// up to `max_decompressed_len` bytes.
+fn parse_signed_transaction(
+    body: &[u8],
+    is_binary: bool,
+    max_message_len: usize,
+    max_decompressed_len: Option<u32>,
+    network_id: Option<Hash>,
+) -> Result<(Hash, Signed<RawTransaction>), ApiError> {
+    use self::SignedTransactionErrorKind::*;
+
+    let buf = if is_binary {
+        body.to_vec()
+    } else {
+        let hex = std::str::from_utf8(body)
+            .map_err(|e| SignedTransactionError::new(InvalidEncoding, e))?
+            .trim();
+        Vec::<u8>::from_hex(hex).map_err(|e| SignedTransactionError::new(InvalidEncoding, e))?
+    };
+    if buf.len() > max_message_len {
+        return Err(ApiError::PayloadTooLarge {
+            length_limit: LengthLimit::Message(max_message_len),
+            content_length: buf.len(),
+        });
+    }
+
+    let signed = SignedMessage::from_raw_buffer_unverified(buf)
+        .map_err(|e| SignedTransactionError::new(MalformedEnvelope, e))?;
+    signed
+        .verify_signature(network_id)
+        .map_err(|e| SignedTransactionError::new(InvalidSignature, e))?;
+    let tx_hash = signed.hash();
+    let message = Message::deserialize_with_limit(signed, max_decompressed_len)
+        .map_err(|e| SignedTransactionError::new(MalformedEnvelope, e))?;
+    let signed = RawTransaction::try_from(message)
+        .map_err(|_| SignedTransactionError::new(NotTransaction, "Message is not a transaction"))?;
+    Ok((tx_hash, signed))
+}
+
+/// Returns the limit on the decompressed payload of transactions in the actual configuration.
+fn get_max_decompressed_len(api_state: &ServiceApiState) -> Option<u32> {
+    let snapshot = api_state.blockchain().snapshot();
+    Schema::new(&snapshot)
+        .actual_configuration()
+        .consensus
+        .max_decompressed_len
+}
+
 fn get_message_limits(api_state: &ServiceApiState) -> (usize, usize) {
     let snapshot = api_state.blockchain().snapshot();
     let schema = Schema::new(&snapshot);
@@ -0,0 +1,96 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Light client API.
+
+use crate::{
+    api::{
+        node::public::explorer::IndexProof, Error as ApiError, ServiceApiScope, ServiceApiState,
+    },
+    blockchain::{
+        light::{LightSchema, VerifiedEntries},
+        BlockProof, StoredConfiguration,
+    },
+    helpers::Height,
+};
+
+/// Query parameters for the block stored in the light chain.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct LightBlockQuery {
+    /// The height of the block. The default value is the height of the latest
+    /// stored block.
+    #[serde(default)]
+    pub height: Option<Height>,
+}
+
+/// Light client API.
+///
+/// The API serves the light chain kept in the database of the blockchain used by
+/// the API aggregator, see the [`light`] module for details.
+///
+/// [`light`]: ../../../../blockchain/light/index.html
+#[derive(Debug, Clone, Copy)]
+pub struct LightApi;
+
+impl LightApi {
+    /// Returns the stored block with its precommits.
+    pub fn block(state: &ServiceApiState, query: LightBlockQuery) -> Result<BlockProof, ApiError> {
+        let snapshot = state.snapshot();
+        let schema = LightSchema::new(&snapshot);
+        let height = match query.height {
+            Some(height) => height,
+            None => schema.height().ok_or_else(not_initialized)?,
+        };
+        schema
+            .block_proof(height)
+            .ok_or_else(|| ApiError::NotFound(format!("Block for height: {} not found", height)))
+    }
+
+    /// Returns the configuration actual after the latest stored block.
+    pub fn configuration(
+        state: &ServiceApiState,
+        _query: (),
+    ) -> Result<StoredConfiguration, ApiError> {
+        let snapshot = state.snapshot();
+        let schema = LightSchema::new(&snapshot);
+        let height = schema.height().ok_or_else(not_initialized)?;
+        schema
+            .configuration_by_height(height.next())
+            .ok_or_else(not_initialized)
+    }
+
+    /// Verifies the proof of entries of an index obtained from a full node against
+    /// the state hash of the stored block and returns the proven entries.
+    pub fn verify_proof(
+        state: &ServiceApiState,
+        proof: IndexProof,
+    ) -> Result<VerifiedEntries, ApiError> {
+        let snapshot = state.snapshot();
+        LightSchema::new(&snapshot)
+            .verify_index_proof(proof)
+            .map_err(|e| ApiError::BadRequest(e.to_string()))
+    }
+
+    /// Adds light client API endpoints to the corresponding scope.
+    pub fn wire(api_scope: &mut ServiceApiScope) -> &mut ServiceApiScope {
+        api_scope
+            .endpoint("v1/blocks", Self::block)
+            .endpoint("v1/configuration", Self::configuration)
+            .endpoint_mut("v1/proofs/verify", Self::verify_proof)
+    }
+}
+
+fn not_initialized() -> ApiError {
+    ApiError::NotFound("The light chain is not initialized".to_owned())
+}
@@ -17,7 +17,8 @@
 //! Public API includes requests for information which is available to outside
 //! users, e.g., for requesting proofs.
 
-pub use self::{explorer::ExplorerApi, system::SystemApi};
+pub use self::{explorer::ExplorerApi, light::LightApi, system::SystemApi};
 
 pub mod explorer;
+pub mod light;
 pub mod system;
@@ -14,19 +14,139 @@
 
 //! Public system API.
 
-use crate::api::{ServiceApiScope, ServiceApiState};
+use actix_web::{http, FromRequest, HttpResponse, Query};
+use futures::{future, IntoFuture};
+
+use std::sync::Arc;
+
+use crate::api::{
+    backends::actix::{FutureResponse, HttpRequest, RawHandler, RequestHandler},
+    stats::EndpointStats,
+    ServiceApiBackend, ServiceApiScope, ServiceApiState,
+};
 use crate::blockchain::{Schema, SharedNodeState};
-use crate::helpers::user_agent;
+use crate::crypto::PublicKey;
+use crate::helpers::{user_agent, Milliseconds};
+
+/// The default maximum number of blocks the node may lag behind its peers to be ready.
+pub const DEFAULT_MAX_HEIGHT_LAG: u64 = 1;
 
 /// Information about the current state of the node memory pool.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub struct StatsInfo {
     /// Total number of uncommitted transactions stored in persistent pool.
     pub tx_pool_size: u64,
+    /// Total size of uncommitted transactions stored in persistent pool in bytes.
+    pub tx_pool_bytes: u64,
     /// Total number of transactions in the blockchain.
     pub tx_count: u64,
     /// Size of the transaction cache.
     pub tx_cache_size: usize,
+    /// Progress of the garbage collection, or `None` if it is disabled.
+    pub gc: Option<GarbageCollectionStats>,
+    /// Transactions evicted from the full pool, or `None` if the pool size is not limited.
+    pub evictions: Option<PoolEvictionStats>,
+    /// Messages verified in the thread pool since the node start.
+    pub verification: VerificationStats,
+}
+
+/// Progress of the garbage collection since the node start.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct GarbageCollectionStats {
+    /// Number of the garbage collection runs.
+    pub runs: u64,
+    /// Number of the outdated transactions evicted from the pool.
+    pub evicted_transactions: u64,
+    /// Number of the superseded messages removed from the consensus messages cache.
+    pub removed_consensus_messages: u64,
+}
+
+/// Transactions evicted from the full pool since the node start.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct PoolEvictionStats {
+    /// Number of the times the pool has exceeded the limits.
+    pub runs: u64,
+    /// Number of the evicted transactions.
+    pub evicted_transactions: u64,
+    /// Total size of the evicted transactions in bytes.
+    pub evicted_bytes: u64,
+}
+
+/// Messages verified in the thread pool since the node start.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct VerificationStats {
+    /// Number of the messages being verified at the moment.
+    pub pending_messages: u64,
+    /// Number of the messages with valid signatures.
+    pub verified_messages: u64,
+    /// Number of the rejected messages.
+    pub rejected_messages: u64,
+    /// Number of the times the number of messages being verified has reached
+    /// the `max_pending_verifications` limit.
+    pub saturations: u64,
+    /// Number of the transactions submitted via the API which have been rejected
+    /// because the `max_pending_verifications` limit was reached.
+    pub throttled_transactions: u64,
+    /// Number of the received blocks whose precommits have been verified while
+    /// the previous block was merged into the storage.
+    pub pipelined_blocks: u64,
+}
+
+/// Clock skew between the node and its peers estimated from the times of the `Connect`
+/// and `Precommit` messages created by the peers. The estimates include the network
+/// delays of the messages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ClockSkewInfo {
+    /// Offset of the local clock relative to the clocks of the peers in milliseconds,
+    /// positive if the local clock is ahead, or `None` if no messages have been observed.
+    pub skew: Option<i64>,
+    /// Maximal skew not reported by the node, equal to the half of the first round
+    /// timeout, in milliseconds.
+    pub max_skew: Milliseconds,
+    /// Latest offsets of the clocks of the connected peers.
+    pub peers: Vec<PeerClockSkew>,
+}
+
+/// Offset of the clock of a peer relative to the local clock.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PeerClockSkew {
+    /// Consensus key of the peer.
+    pub public_key: PublicKey,
+    /// Offset in milliseconds, positive if the clock of the peer is ahead.
+    pub offset: i64,
+}
+
+/// Round changes observed by the node since the start. Only the blocks committed
+/// by the node in the consensus rounds are taken into account, the blocks received
+/// during synchronization with the other nodes are not.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RoundChangeStats {
+    /// Number of the committed heights.
+    pub heights: u64,
+    /// Number of the heights committed in a round later than the first one.
+    pub heights_with_round_changes: u64,
+    /// Average number of the rounds per height, or `None` if no heights are committed.
+    pub average_rounds_per_height: Option<f64>,
+    /// Validators which have led the rounds ended without a committed block,
+    /// ordered by the number of such rounds in descending order.
+    pub leaders: Vec<LeaderRoundChanges>,
+}
+
+/// Rounds led by a validator which have ended without a committed block.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct LeaderRoundChanges {
+    /// Consensus key of the validator.
+    pub consensus_key: PublicKey,
+    /// Number of the rounds.
+    pub failed_rounds: u64,
+}
+
+/// Statistics of requests served by the API endpoints of the node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiStatsInfo {
+    /// Statistics of the endpoints which have served at least one request since
+    /// the node start, ordered by the access level, method and endpoint name.
+    pub endpoints: Vec<EndpointStats>,
 }
 
 /// Information about whether it is possible to achieve the consensus between
@@ -39,6 +159,9 @@ pub enum ConsensusStatus {
     Enabled,
     /// Consensus enabled and the node has enough connected peers.
     Active,
+    /// Consensus enabled, but no block has been committed for longer than
+    /// the stall timeout of the watchdog.
+    Degraded,
 }
 
 /// Information about whether the node is connected to other peers and
@@ -51,6 +174,32 @@ pub struct HealthCheckInfo {
     pub connected_peers: usize,
 }
 
+/// Readiness check parameters.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct ReadinessQuery {
+    /// The maximum number of blocks the node may lag behind its peers.
+    /// The default value is `DEFAULT_MAX_HEIGHT_LAG`.
+    pub max_height_lag: Option<u64>,
+    /// The minimum number of connected peers. The default value is zero.
+    #[serde(default)]
+    pub min_peers: usize,
+}
+
+/// Information about whether the node is ready to serve requests.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct ReadinessInfo {
+    /// Whether all the checks have passed.
+    pub ready: bool,
+    /// The number of blocks the node lags behind the most advanced of its peers.
+    pub height_lag: u64,
+    /// Consensus status. The node is ready only if the status is `Active`.
+    pub consensus_status: ConsensusStatus,
+    /// The number of connected peers to the node.
+    pub connected_peers: usize,
+    /// Whether the node is able to write to the database.
+    pub db_writable: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct ServiceInfo {
     name: String,
@@ -82,13 +231,58 @@ impl SystemApi {
             let schema = Schema::new(&snapshot);
             Ok(StatsInfo {
                 tx_pool_size: schema.transactions_pool_len(),
+                tx_pool_bytes: schema.transactions_pool_size(),
                 tx_count: schema.transactions_len(),
                 tx_cache_size: self.shared_api_state.tx_cache_size(),
+                gc: self.shared_api_state.gc_stats(),
+                evictions: self.shared_api_state.eviction_stats(),
+                verification: self.shared_api_state.verification_metrics().info(),
+            })
+        });
+        self_
+    }
+
+    fn handle_api_stats_info(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
+            Ok(ApiStatsInfo {
+                endpoints: self.shared_api_state.api_stats().endpoints(),
             })
         });
         self_
     }
 
+    fn handle_storage_stats_info(
+        self,
+        name: &'static str,
+        api_scope: &mut ServiceApiScope,
+    ) -> Self {
+        api_scope.endpoint(name, move |state: &ServiceApiState, _query: ()| {
+            Ok(state.blockchain().storage_metrics().info())
+        });
+        self
+    }
+
+    fn handle_clock_skew_info(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
+            Ok(self.shared_api_state.clock_skew())
+        });
+        self_
+    }
+
+    fn handle_round_changes_info(
+        self,
+        name: &'static str,
+        api_scope: &mut ServiceApiScope,
+    ) -> Self {
+        let self_ = self.clone();
+        api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
+            Ok(self.shared_api_state.round_changes())
+        });
+        self_
+    }
+
     fn handle_user_agent_info(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
         api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| {
             Ok(user_agent::get())
@@ -107,6 +301,73 @@ impl SystemApi {
         self_
     }
 
+    fn handle_liveness(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        api_scope.endpoint(name, move |_state: &ServiceApiState, _query: ()| Ok(()));
+        self
+    }
+
+    fn handle_readiness(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        let index = move |request: HttpRequest| -> FutureResponse {
+            let response = Query::from_request(&request, &Default::default()).map(
+                |query: Query<ReadinessQuery>| {
+                    let info = self.readiness_info(request.state(), &query);
+                    if info.ready {
+                        HttpResponse::Ok().json(info)
+                    } else {
+                        HttpResponse::ServiceUnavailable().json(info)
+                    }
+                },
+            );
+            Box::new(response.into_future())
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self_
+    }
+
+    fn handle_metrics(self, name: &'static str, api_scope: &mut ServiceApiScope) -> Self {
+        let self_ = self.clone();
+        let index = move |_request: HttpRequest| -> FutureResponse {
+            let response = HttpResponse::Ok()
+                .content_type("text/plain; version=0.0.4")
+                .body(self.shared_api_state.metrics().render());
+            Box::new(future::ok(response))
+        };
+
+        api_scope.web_backend().raw_handler(RequestHandler {
+            name: name.to_owned(),
+            method: http::Method::GET,
+            inner: Arc::from(index) as Arc<RawHandler>,
+        });
+        self_
+    }
+
+    fn readiness_info(&self, state: &ServiceApiState, query: &ReadinessQuery) -> ReadinessInfo {
+        let height_lag = self.shared_api_state.height_lag();
+        let consensus_status = self.get_consensus_status();
+        let connected_peers = self.get_number_of_connected_peers();
+        // The probe does not write to the database; instead, it relies on the result
+        // of the latest merge performed by the node.
+        let db_writable = !state.blockchain().storage_metrics().last_merge_failed();
+
+        let ready = height_lag <= query.max_height_lag.unwrap_or(DEFAULT_MAX_HEIGHT_LAG)
+            && consensus_status == ConsensusStatus::Active
+            && connected_peers >= query.min_peers
+            && db_writable;
+        ReadinessInfo {
+            ready,
+            height_lag,
+            consensus_status,
+            connected_peers,
+            db_writable,
+        }
+    }
+
     fn handle_list_services_info(
         self,
         name: &'static str,
@@ -137,7 +398,9 @@ impl SystemApi {
 
     fn get_consensus_status(&self) -> ConsensusStatus {
         if self.shared_api_state.is_enabled() {
-            if self.shared_api_state.consensus_status() {
+            if self.shared_api_state.is_consensus_stalled() {
+                ConsensusStatus::Degraded
+            } else if self.shared_api_state.consensus_status() {
                 ConsensusStatus::Active
             } else {
                 ConsensusStatus::Enabled
@@ -150,7 +413,14 @@ impl SystemApi {
     /// Adds public system API endpoints to the corresponding scope.
     pub fn wire(self, api_scope: &mut ServiceApiScope) -> &mut ServiceApiScope {
         self.handle_stats_info("v1/stats", api_scope)
+            .handle_api_stats_info("v1/stats/api", api_scope)
+            .handle_storage_stats_info("v1/stats/storage", api_scope)
+            .handle_clock_skew_info("v1/stats/clock", api_scope)
+            .handle_round_changes_info("v1/stats/rounds", api_scope)
             .handle_healthcheck_info("v1/healthcheck", api_scope)
+            .handle_liveness("v1/health/live", api_scope)
+            .handle_readiness("v1/health/ready", api_scope)
+            .handle_metrics("v1/metrics", api_scope)
             .handle_user_agent_info("v1/user_agent", api_scope)
             .handle_list_services_info("v1/services", api_scope);
         api_scope
@@ -14,6 +14,7 @@
 
 use crate::blockchain::Blockchain;
 use crate::crypto::{PublicKey, SecretKey};
+use crate::helpers::Height;
 use crate::node::ApiSender;
 use exonum_merkledb::Snapshot;
 
@@ -44,6 +45,14 @@ impl ServiceApiState {
         self.blockchain.snapshot()
     }
 
+    /// Creates a read-only snapshot of the blockchain state at the given height.
+    /// See [`Blockchain::snapshot_at`] for details.
+    ///
+    /// [`Blockchain::snapshot_at`]: ../blockchain/struct.Blockchain.html#method.snapshot_at
+    pub fn snapshot_at(&self, height: Height) -> Result<Box<dyn Snapshot>, failure::Error> {
+        self.blockchain.snapshot_at(height)
+    }
+
     /// Returns the public key of the current node.
     pub fn public_key(&self) -> &PublicKey {
         &self.blockchain.service_keypair.0
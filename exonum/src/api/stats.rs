@@ -0,0 +1,198 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Accounting of requests served by the API endpoints.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::ApiAccess;
+
+/// The number of the latest requests to each endpoint used to calculate latency percentiles.
+pub const MAX_LATENCY_SAMPLES: usize = 1024;
+/// Requests taking longer than this number of milliseconds are considered slow.
+pub const SLOW_REQUEST_THRESHOLD_MS: u64 = 1000;
+
+/// Latency percentiles of an API endpoint in microseconds.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+pub struct LatencyInfo {
+    /// Median latency.
+    pub p50: u64,
+    /// 90th percentile of latency.
+    pub p90: u64,
+    /// 99th percentile of latency.
+    pub p99: u64,
+    /// Maximum latency since the node start.
+    pub max: u64,
+}
+
+/// Statistics of requests served by an API endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EndpointStats {
+    /// API access level of the endpoint, either `public` or `private`.
+    pub access: String,
+    /// HTTP method of the endpoint.
+    pub method: String,
+    /// Endpoint path relative to the `api` prefix, e.g., `explorer/v1/blocks`.
+    pub endpoint: String,
+    /// Total number of served requests.
+    pub requests: u64,
+    /// Number of requests which resulted in an error.
+    pub errors: u64,
+    /// Number of requests served slower than `SLOW_REQUEST_THRESHOLD_MS`.
+    pub slow_requests: u64,
+    /// Latency percentiles calculated over the last `MAX_LATENCY_SAMPLES` requests.
+    pub latency: LatencyInfo,
+}
+
+#[derive(Debug)]
+struct EndpointCollector {
+    stats: EndpointStats,
+    latencies: VecDeque<u64>,
+}
+
+impl EndpointCollector {
+    fn record(&mut self, latency: u64, is_error: bool) {
+        self.stats.requests += 1;
+        if is_error {
+            self.stats.errors += 1;
+        }
+        if latency >= SLOW_REQUEST_THRESHOLD_MS * 1000 {
+            self.stats.slow_requests += 1;
+        }
+        self.stats.latency.max = self.stats.latency.max.max(latency);
+
+        if self.latencies.len() == MAX_LATENCY_SAMPLES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    fn stats(&self) -> EndpointStats {
+        let mut latencies = self.latencies.iter().cloned().collect::<Vec<_>>();
+        latencies.sort_unstable();
+        let percentile = |p: usize| {
+            if latencies.is_empty() {
+                0
+            } else {
+                latencies[(latencies.len() - 1) * p / 100]
+            }
+        };
+
+        let mut stats = self.stats.clone();
+        stats.latency.p50 = percentile(50);
+        stats.latency.p90 = percentile(90);
+        stats.latency.p99 = percentile(99);
+        stats
+    }
+}
+
+/// Collector of statistics of requests served by the API endpoints.
+///
+/// The collector is shared by all API servers of the node and survives their restarts.
+#[derive(Clone, Default)]
+pub struct ApiStats {
+    endpoints: Arc<Mutex<BTreeMap<String, EndpointCollector>>>,
+}
+
+impl ApiStats {
+    /// Records the request served by the given endpoint.
+    pub fn record(
+        &self,
+        access: ApiAccess,
+        method: &str,
+        endpoint: &str,
+        latency: Duration,
+        is_error: bool,
+    ) {
+        let latency = latency.as_secs() * 1_000_000 + u64::from(latency.subsec_micros());
+        metric!(&format!("api.{}.{}", access, endpoint), latency);
+
+        let key = format!("{} {} {}", access, method, endpoint);
+        let mut endpoints = self.endpoints.lock().expect("Expected mutex lock");
+        endpoints
+            .entry(key)
+            .or_insert_with(|| EndpointCollector {
+                stats: EndpointStats {
+                    access: access.to_string(),
+                    method: method.to_owned(),
+                    endpoint: endpoint.to_owned(),
+                    requests: 0,
+                    errors: 0,
+                    slow_requests: 0,
+                    latency: LatencyInfo::default(),
+                },
+                latencies: VecDeque::new(),
+            })
+            .record(latency, is_error);
+    }
+
+    /// Returns statistics of all endpoints which have served at least one request.
+    pub fn endpoints(&self) -> Vec<EndpointStats> {
+        let endpoints = self.endpoints.lock().expect("Expected mutex lock");
+        endpoints.values().map(EndpointCollector::stats).collect()
+    }
+}
+
+impl fmt::Debug for ApiStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ApiStats").finish()
+    }
+}
+
+#[test]
+fn api_stats_record() {
+    let stats = ApiStats::default();
+    for latency in 1..=100 {
+        stats.record(
+            ApiAccess::Public,
+            "GET",
+            "explorer/v1/blocks",
+            Duration::from_millis(latency),
+            latency % 10 == 0,
+        );
+    }
+    stats.record(
+        ApiAccess::Private,
+        "POST",
+        "system/v1/shutdown",
+        Duration::from_secs(2),
+        false,
+    );
+
+    let endpoints = stats.endpoints();
+    assert_eq!(endpoints.len(), 2);
+    let blocks = &endpoints.iter().find(|e| e.access == "public").unwrap();
+    assert_eq!(blocks.endpoint, "explorer/v1/blocks");
+    assert_eq!(blocks.requests, 100);
+    assert_eq!(blocks.errors, 10);
+    assert_eq!(blocks.slow_requests, 0);
+    assert_eq!(
+        blocks.latency,
+        LatencyInfo {
+            p50: 50_000,
+            p90: 90_000,
+            p99: 99_000,
+            max: 100_000,
+        }
+    );
+    let shutdown = &endpoints.iter().find(|e| e.access == "private").unwrap();
+    assert_eq!(shutdown.method, "POST");
+    assert_eq!(shutdown.slow_requests, 1);
+    assert_eq!(shutdown.latency.max, 2_000_000);
+}
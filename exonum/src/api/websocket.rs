@@ -27,7 +27,9 @@ use std::{
 };
 
 use crate::api::{
-    node::public::explorer::{ExplorerApi, TransactionHex, TransactionResponse},
+    node::public::explorer::{
+        ExplorerApi, TransactionHex, TransactionResponse, ValidatorLivenessInfo,
+    },
     ServiceApiState,
 };
 use crate::blockchain::{Block, Schema, TransactionResult, TxLocation};
@@ -59,6 +61,10 @@ pub enum SubscriptionType {
         /// Optional filter for subscription.
         filter: Option<TransactionFilter>,
     },
+    /// Subscription on transactions entering or leaving the pool of unconfirmed transactions.
+    Mempool,
+    /// Subscription on alerts about the validators missing consensus messages.
+    Liveness,
 }
 
 /// Describe filter for transactions by ID of service and (optionally)
@@ -136,6 +142,31 @@ pub enum Notification {
     Block(Block),
     /// Notification about new transaction.
     Transaction(CommittedTransactionSummary),
+    /// Notification about a change in the pool of unconfirmed transactions.
+    Mempool(MempoolNotification),
+    /// Notification about a validator whose uptime has fallen below the threshold.
+    Liveness(ValidatorLivenessInfo),
+}
+
+/// Kind of change in the pool of unconfirmed transactions.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum MempoolEvent {
+    /// Transaction has been added to the pool.
+    Added,
+    /// Transaction has been removed from the pool without being committed.
+    Removed,
+    /// Transaction has left the pool because it has been committed in a block.
+    Committed,
+}
+
+/// Summary about a change in the pool of unconfirmed transactions.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct MempoolNotification {
+    /// Kind of change.
+    pub event: MempoolEvent,
+    /// Hash of the transaction.
+    pub tx_hash: Hash,
 }
 
 /// WebSocket message for communication between clients(`Session`) and server(`Server`).
@@ -174,6 +205,16 @@ pub(crate) struct Broadcast {
     pub block_hash: Hash,
 }
 
+#[derive(Message)]
+pub(crate) struct BroadcastMempoolEvent {
+    pub notification: MempoolNotification,
+}
+
+#[derive(Message)]
+pub(crate) struct BroadcastLivenessAlert {
+    pub info: ValidatorLivenessInfo,
+}
+
 #[derive(Message)]
 #[rtype("Result<TransactionResponse, failure::Error>")]
 pub(crate) struct Transaction {
@@ -340,6 +381,33 @@ impl Handler<Broadcast> for Server {
     }
 }
 
+impl Handler<BroadcastMempoolEvent> for Server {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        BroadcastMempoolEvent { notification }: BroadcastMempoolEvent,
+        _ctx: &mut Self::Context,
+    ) {
+        self.broadcast_message(
+            SubscriptionType::Mempool,
+            &Notification::Mempool(notification),
+        );
+    }
+}
+
+impl Handler<BroadcastLivenessAlert> for Server {
+    type Result = ();
+
+    fn handle(
+        &mut self,
+        BroadcastLivenessAlert { info }: BroadcastLivenessAlert,
+        _ctx: &mut Self::Context,
+    ) {
+        self.broadcast_message(SubscriptionType::Liveness, &Notification::Liveness(info));
+    }
+}
+
 impl Handler<Transaction> for Server {
     type Result = Result<TransactionResponse, failure::Error>;
 
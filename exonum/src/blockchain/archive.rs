@@ -0,0 +1,235 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export and import of the block archives.
+//!
+//! An archive contains a sequence of committed blocks together with their precommits
+//! and transactions encoded in Protobuf. Unlike a state snapshot, an archive does not
+//! contain the state: a node importing an archive executes the transactions of each block
+//! and checks that the resulting block matches the archived one. Archives allow new nodes
+//! to load the history from an object storage or a removable drive and to synchronize
+//! only the latest blocks over the network.
+
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use failure::Error;
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+};
+
+use super::{
+    integrity::verify_precommits,
+    snapshot::{read_bytes, write_bytes},
+    Block, Blockchain, Schema,
+};
+use crate::crypto::{CryptoHash, Hash};
+use crate::helpers::Height;
+use crate::messages::{Message, Precommit, ProtocolMessage, RawTransaction, Signed};
+use crate::proto;
+use exonum_merkledb::{BinaryValue, Snapshot};
+
+/// Magic bytes at the start of the archive file.
+const ARCHIVE_MAGIC: &[u8; 8] = b"EXONUMBA";
+/// Version of the archive format.
+const ARCHIVE_VERSION: u8 = 1;
+
+/// Range of the blocks contained in an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveInfo {
+    /// Height of the first block in the archive.
+    pub from: Height,
+    /// Height of the last block in the archive.
+    pub to: Height,
+}
+
+/// A block with its precommits and transactions as stored in the archive.
+#[derive(Debug, Clone, ProtobufConvert)]
+#[exonum(pb = "proto::ArchivedBlock", crate = "crate")]
+struct ArchivedBlock {
+    block: Block,
+    precommits: Vec<Vec<u8>>,
+    transactions: Vec<Vec<u8>>,
+}
+
+/// Writes the blocks in the given range of heights (inclusive) to the archive.
+///
+/// Transactions and precommits of the pruned blocks are not kept in the database,
+/// so an error is returned if the range contains pruned blocks.
+pub fn export_blocks<W: Write>(
+    snapshot: &dyn Snapshot,
+    from: Height,
+    to: Height,
+    writer: &mut W,
+) -> Result<ArchiveInfo, Error> {
+    let schema = Schema::new(snapshot);
+    let last_height = schema.height();
+    ensure!(from <= to, "Invalid range of heights: {}..={}", from, to);
+    ensure!(
+        to <= last_height,
+        "The latest committed height is {}, requested {}",
+        last_height,
+        to
+    );
+    ensure!(
+        !schema.is_pruned(from),
+        "The block at height {} is pruned",
+        from
+    );
+
+    writer.write_all(ARCHIVE_MAGIC)?;
+    writer.write_u8(ARCHIVE_VERSION)?;
+    let transactions = schema.transactions();
+    for height in from.0..=to.0 {
+        let height = Height(height);
+        let block_hash = schema
+            .block_hash_by_height(height)
+            .ok_or_else(|| format_err!("The block at height {} is not found", height))?;
+        let block = schema
+            .blocks()
+            .get(&block_hash)
+            .ok_or_else(|| format_err!("The block with hash {:?} is not found", block_hash))?;
+        let precommits = schema
+            .precommits(&block_hash)
+            .iter()
+            .map(BinaryValue::into_bytes)
+            .collect();
+        let transactions = schema
+            .block_transactions(height)
+            .iter()
+            .map(|tx_hash| {
+                transactions
+                    .get(&tx_hash)
+                    .map(BinaryValue::into_bytes)
+                    .ok_or_else(|| format_err!("The transaction {:?} is not found", tx_hash))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        let archived = ArchivedBlock {
+            block,
+            precommits,
+            transactions,
+        };
+        writer.write_u8(1)?;
+        write_bytes(writer, &archived.into_bytes())?;
+    }
+    writer.write_u8(0)?;
+    writer.flush()?;
+    Ok(ArchiveInfo { from, to })
+}
+
+/// Imports the blocks from the archive into the blockchain and returns the number
+/// of the committed blocks.
+///
+/// Each block is verified before commit: it must continue the chain, be signed by
+/// the supermajority of the validators and match the block obtained by executing
+/// its transactions. The blocks already present in the blockchain are skipped if their
+/// hashes match the archived ones.
+pub fn import_blocks<R: Read>(blockchain: &mut Blockchain, reader: &mut R) -> Result<u64, Error> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    ensure!(&magic == ARCHIVE_MAGIC, "The file is not a block archive");
+    let version = reader.read_u8()?;
+    ensure!(
+        version == ARCHIVE_VERSION,
+        "Unsupported archive version: {}",
+        version
+    );
+
+    let mut committed = 0;
+    while reader.read_u8()? != 0 {
+        let archived = ArchivedBlock::from_bytes(read_bytes(reader)?.into())?;
+        if import_block(blockchain, archived)? {
+            committed += 1;
+        }
+    }
+    Ok(committed)
+}
+
+/// Verifies and commits a single block. Returns `false` if the block is already committed.
+fn import_block(blockchain: &mut Blockchain, archived: ArchivedBlock) -> Result<bool, Error> {
+    let block = archived.block;
+    let height = block.height();
+    let block_hash = block.hash();
+
+    let (config, network_id) = {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let next_height = schema.height().next();
+        if height < next_height {
+            ensure!(
+                schema.block_hash_by_height(height) == Some(block_hash),
+                "The block at height {} does not match the committed one",
+                height
+            );
+            return Ok(false);
+        }
+        ensure!(
+            height == next_height,
+            "Expected the block at height {}, found {}",
+            next_height,
+            height
+        );
+        ensure!(
+            *block.prev_hash() == schema.last_block().hash(),
+            "The block at height {} does not continue the chain",
+            height
+        );
+        (
+            schema.configuration_by_height(height),
+            schema.signature_network_id(height),
+        )
+    };
+
+    let precommits = Precommit::verify_precommits(archived.precommits, network_id)?;
+    verify_precommits(
+        &config,
+        network_id,
+        height,
+        &block_hash,
+        precommits.iter().cloned(),
+    )?;
+
+    let mut tx_cache = BTreeMap::new();
+    let mut tx_hashes = Vec::with_capacity(archived.transactions.len());
+    for raw in archived.transactions {
+        let tx = parse_transaction(raw, network_id, config.consensus.max_decompressed_len)?;
+        let tx_hash = tx.hash();
+        tx_hashes.push(tx_hash);
+        tx_cache.insert(tx_hash, tx);
+    }
+
+    let (patch_hash, patch) =
+        blockchain.create_patch(block.proposer_id(), height, &tx_hashes, &mut tx_cache);
+    ensure!(
+        patch_hash == block_hash,
+        "Executing the transactions of the block at height {} results in another block",
+        height
+    );
+    blockchain.commit(patch, block_hash, precommits.into_iter(), &mut tx_cache)?;
+    Ok(true)
+}
+
+/// Parses the transaction verifying its signature made for the network with the given
+/// identifier. The payload of a compressed transaction is decompressed up to
+/// `max_decompressed_len` bytes.
+fn parse_transaction(
+    raw: Vec<u8>,
+    network_id: Option<Hash>,
+    max_decompressed_len: Option<u32>,
+) -> Result<Signed<RawTransaction>, Error> {
+    let message = Message::from_raw_buffer_with_limit(raw, network_id, max_decompressed_len)?;
+    RawTransaction::try_from(message)
+        .map_err(|_| format_err!("The archived message is not a transaction"))
+}
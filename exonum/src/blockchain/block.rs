@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use crate::crypto::Hash;
+#[cfg(feature = "threshold-precommits")]
+use crate::crypto::{self, CryptoHash};
 use crate::helpers::{Height, ValidatorId};
 use crate::messages::{Precommit, Signed};
 use crate::proto;
@@ -100,8 +102,28 @@ pub struct BlockProof {
     /// proposed the block, the height of the block, the number of transactions
     /// in the block, etc.
     pub block: Block,
-    /// List of `Precommit` messages for the block.
+    /// List of `Precommit` messages for the block. May be empty if the block
+    /// is proven by the certificate.
     pub precommits: Vec<Signed<Precommit>>,
+    /// Threshold signature of the block hash combined from the signature shares
+    /// of the precommits, if the validators use threshold signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<Vec<u8>>,
+}
+
+impl BlockProof {
+    /// Checks that the certificate of the block is a valid signature of the block hash
+    /// by the threshold key set of the validators. Returns `false` if the proof
+    /// has no certificate.
+    #[cfg(feature = "threshold-precommits")]
+    pub fn verify_certificate(&self, key_set: &crypto::threshold::PublicKeySet) -> bool {
+        self.certificate
+            .as_ref()
+            .and_then(|bytes| crypto::threshold::signature_from_slice(bytes))
+            .map_or(false, |signature| {
+                key_set.public_key().verify(&signature, self.block.hash())
+            })
+    }
 }
 
 #[cfg(test)]
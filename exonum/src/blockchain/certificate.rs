@@ -0,0 +1,138 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Certificates of the blocks combined from the threshold signature shares
+//! of the precommits.
+
+use crate::crypto::{
+    threshold::{self, PublicKeySet, Signature, SignatureShare},
+    Hash,
+};
+use crate::messages::{Precommit, Signed};
+
+/// Parses the threshold signature share of the precommit and checks that it is a valid
+/// signature of the block hash by the validator which has authored the precommit.
+pub(crate) fn verify_share(
+    key_set: &PublicKeySet,
+    precommit: &Precommit,
+) -> Option<SignatureShare> {
+    let share = threshold::signature_share_from_slice(precommit.threshold_share())?;
+    let validator = precommit.validator().0 as usize;
+    if key_set
+        .public_key_share(validator)
+        .verify(&share, precommit.block_hash())
+    {
+        Some(share)
+    } else {
+        None
+    }
+}
+
+/// Combines the shares of the precommits for the block into its certificate.
+///
+/// The combined signature is verified at once, and only if it is invalid, the valid
+/// shares are sifted out one by one. Returns `None` if there are not enough valid shares.
+pub(crate) fn certify(
+    key_set: &PublicKeySet,
+    block_hash: &Hash,
+    precommits: &[Signed<Precommit>],
+) -> Option<Signature> {
+    let shares = precommits
+        .iter()
+        .filter(|precommit| precommit.block_hash() == block_hash)
+        .filter_map(|precommit| {
+            let share = threshold::signature_share_from_slice(precommit.threshold_share())?;
+            Some((precommit.validator().0 as usize, share))
+        })
+        .collect::<Vec<_>>();
+    let combine = |shares: &[(usize, SignatureShare)]| {
+        let signature =
+            threshold::combine(key_set, shares.iter().map(|&(i, ref share)| (i, share)))?;
+        if key_set.public_key().verify(&signature, block_hash) {
+            Some(signature)
+        } else {
+            None
+        }
+    };
+
+    combine(&shares).or_else(|| {
+        let valid_shares = shares
+            .iter()
+            .filter(|&&(i, ref share)| key_set.public_key_share(i).verify(share, block_hash))
+            .cloned()
+            .collect::<Vec<_>>();
+        combine(&valid_shares)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::crypto::{gen_keypair, hash, threshold::SIG_SIZE};
+    use crate::helpers::{Height, Round, ValidatorId};
+    use crate::messages::Message;
+
+    fn precommit(validator: u16, block_hash: &Hash, share: Vec<u8>) -> Signed<Precommit> {
+        let (public_key, secret_key) = gen_keypair();
+        let precommit = Precommit::new(
+            ValidatorId(validator),
+            Height(1),
+            Round(1),
+            &hash(&[]),
+            block_hash,
+            Utc::now(),
+        )
+        .with_threshold_share(share);
+        Message::concrete(precommit, public_key, &secret_key)
+    }
+
+    #[test]
+    fn test_certify() {
+        let (key_set, shares) = threshold::gen_key_set(4, 2);
+        let block_hash = hash(&[1, 2, 3]);
+        let mut precommits = shares
+            .iter()
+            .enumerate()
+            .take(3)
+            .map(|(i, share)| {
+                let signature = share.sign(&block_hash).to_bytes().to_vec();
+                precommit(i as u16, &block_hash, signature)
+            })
+            .collect::<Vec<_>>();
+
+        for precommit in &precommits {
+            assert!(verify_share(&key_set, precommit).is_some());
+        }
+        let certificate = certify(&key_set, &block_hash, &precommits).unwrap();
+        assert!(key_set.public_key().verify(&certificate, &block_hash));
+
+        // A share of another block is not valid.
+        let other_hash = hash(&[4, 5, 6]);
+        let invalid_share = shares[3].sign(&other_hash).to_bytes().to_vec();
+        let invalid_precommit = precommit(3, &block_hash, invalid_share);
+        assert!(verify_share(&key_set, &invalid_precommit).is_none());
+        precommits.push(invalid_precommit);
+        assert_eq!(
+            certify(&key_set, &block_hash, &precommits),
+            Some(certificate)
+        );
+
+        // Precommits without shares are not enough.
+        precommits.truncate(2);
+        precommits.push(precommit(2, &block_hash, vec![0; SIG_SIZE]));
+        assert!(certify(&key_set, &block_hash, &precommits).is_none());
+    }
+}
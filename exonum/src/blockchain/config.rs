@@ -26,9 +26,15 @@ use serde_json::Error as JsonError;
 
 use std::collections::{BTreeMap, HashSet};
 
-use crate::crypto::{hash, CryptoHash, Hash, PublicKey};
+#[cfg(feature = "threshold-precommits")]
+use crate::crypto::threshold::PublicKeySet;
+use crate::crypto::{hash, CryptoHash, Hash, HashAlgorithm, PublicKey};
 use crate::helpers::{Height, Milliseconds};
-use crate::messages::EMPTY_SIGNED_MESSAGE_SIZE;
+use crate::messages::{
+    HexStringRepresentation, EMPTY_SIGNED_MESSAGE_SIZE, MAX_DECOMPRESSED_PAYLOAD_LEN,
+};
+#[cfg(feature = "threshold-precommits")]
+use crate::node::State;
 use exonum_merkledb::{BinaryValue, ObjectHash};
 
 /// Public keys of a validator. Each validator has two public keys: the
@@ -60,6 +66,42 @@ pub struct StoredConfiguration {
     /// configuration will not take effect at all; the old configuration will
     /// remain actual.
     pub actual_from: Height,
+    /// Hash function used throughout the blockchain.
+    ///
+    /// The algorithm is selected in the genesis configuration and must stay the same in
+    /// all the following configurations, since the stored hashes depend on it. SHA-256
+    /// is used if not specified.
+    #[serde(default, skip_serializing_if = "is_sha256")]
+    pub hash_algorithm: HashAlgorithm,
+    /// Whether the signatures of the messages cover the identifier of the network,
+    /// so that the messages signed for one network cannot be replayed in another one.
+    /// See `Schema::network_id` for details.
+    ///
+    /// The existing networks, which were created without domain separation, can enable it
+    /// by a configuration change: the clients must include the network identifier
+    /// into the signatures starting from the height at which the configuration becomes
+    /// actual. Domain separation cannot be disabled once enabled.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub domain_separated_signatures: bool,
+    /// Whether the transactions and the consensus messages must be encoded canonically,
+    /// see `proto::from_canonical_bytes`, so that each message has a single valid encoding
+    /// and, therefore, a single hash.
+    ///
+    /// The requirement applies to the messages received by the nodes starting from
+    /// the height at which the configuration becomes actual. The committed transactions
+    /// are executed regardless of their encoding, so the blocks created before
+    /// the requirement can be executed again. The encoding is not checked if not set.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub canonical_encoding: bool,
+    /// Whether the transactions may be signed with secp256k1 keys, see
+    /// `Message::sign_transaction_secp256k1`.
+    ///
+    /// The requirement applies to the transactions received by the nodes starting from
+    /// the height at which the configuration becomes actual, while the committed
+    /// transactions are executed regardless of their signature scheme. Transactions
+    /// signed with secp256k1 keys are rejected if not set.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub secp256k1_signatures: bool,
     /// List of validators consensus and service public keys.
     pub validator_keys: Vec<ValidatorKeys>,
     /// Consensus algorithm parameters.
@@ -121,6 +163,266 @@ pub struct ConsensusConfig {
     /// in a block if the transaction pool is almost empty, and create blocks faster when there are
     /// enough transactions in the pool.
     pub propose_timeout_threshold: u32,
+    /// Policy of choosing the round timeouts. Rounds are timed by `first_round_timeout`
+    /// if not specified.
+    #[serde(default, skip_serializing_if = "TimeoutPolicy::is_static")]
+    pub timeout_policy: TimeoutPolicy,
+    /// Maximal time between blocks if there are no transactions to commit.
+    ///
+    /// If specified, the leader does not propose a block while the transaction pool is
+    /// empty, unless this interval has passed since the start of the height. If not
+    /// specified, blocks are created regardless of the transactions.
+    /// Note that services updated in `after_commit` (e.g., the time oracle) are not
+    /// updated while blocks are not created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_idle_interval: Option<Milliseconds>,
+    /// Strategy of choosing the leader of each round. The validators become leaders
+    /// in turn if not specified.
+    #[serde(default, skip_serializing_if = "LeaderSelection::is_round_robin")]
+    pub leader_selection: LeaderSelection,
+    /// Policy of choosing the transactions of a proposed block among the uncommitted ones.
+    /// The order is unspecified if not set. Since the policy is applied only by
+    /// the leader, the validators may use different policies.
+    #[serde(default, skip_serializing_if = "TxOrdering::is_unordered")]
+    pub tx_ordering: TxOrdering,
+    /// Maximum total size of the transactions of a proposed block (in bytes).
+    ///
+    /// The leader skips the transactions which do not fit into the block, so a few
+    /// large transactions do not produce blocks too big to be propagated in time.
+    /// Blocks are limited only by `txs_block_limit` if not specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_block_size: Option<u64>,
+    /// Minimal time between blocks.
+    ///
+    /// If specified, the leader does not propose a block until this interval has passed
+    /// since the start of the height, regardless of the propose timeouts and the number
+    /// of the uncommitted transactions. Thus, the blocks are created at predictable
+    /// intervals. The interval should be less than `first_round_timeout`, otherwise
+    /// the first round of each height ends without a block.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_block_interval: Option<Milliseconds>,
+    /// Whether the leader executes the transactions of a proposed block in advance and
+    /// leaves out the ones which fail.
+    ///
+    /// The left out transactions stay in the pool and are proposed again later, since
+    /// they may succeed in another state, unless `max_failed_dry_runs` is set. At most
+    /// `txs_block_limit` transactions are executed for each proposal. Since the check is
+    /// performed only by the leader, the validators may use different settings. Failing
+    /// transactions are included into the blocks if not set.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub exclude_failing_txs: bool,
+    /// Number of the consecutive proposals for which a transaction fails the execution
+    /// in advance, after which the leader evicts it from the pool. Applies only if
+    /// `exclude_failing_txs` is set.
+    ///
+    /// Failing transactions are kept in the pool until they expire or are evicted
+    /// otherwise if not specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_failed_dry_runs: Option<u32>,
+    /// Maximum size of the decompressed payload of a compressed transaction (in bytes).
+    ///
+    /// If specified, the transactions may be compressed, see
+    /// `Message::sign_transaction_compressed`, so that large transactions take less
+    /// network bandwidth and storage. The limit must not exceed
+    /// `MAX_DECOMPRESSED_PAYLOAD_LEN`. Compressed transactions are rejected if not specified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_decompressed_len: Option<u32>,
+    /// Whether the transactions and `before_commit` handlers of a service may access
+    /// through the fork only the indexes of the service namespace, that is, the indexes
+    /// named `<service_name>` or `<service_name>.*`, and the namespaces declared by
+    /// `Service::foreign_namespaces`. Accessing other indexes fails the transaction.
+    ///
+    /// Since the enforcement changes the results of the transactions, all the validators
+    /// must apply it at the same heights. Namespaces are not enforced if not set.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub enforce_storage_namespaces: bool,
+    /// Whether the transactions of a block are split into groups accessing disjoint storage
+    /// namespaces, which are executed in parallel. The namespaces accessed by a transaction
+    /// are determined by `Service::foreign_namespaces` and `Service::read_namespaces`
+    /// of its service. Parallel execution implies the enforcement of storage namespaces.
+    ///
+    /// The number of the threads executing the groups is set in the node configuration
+    /// and does not affect the results of the transactions. Transactions are executed
+    /// sequentially if not set.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub parallel_execution: bool,
+    /// Public keys of the threshold signature shares of the validators.
+    ///
+    /// If specified, each validator signs the hash of the block with its share of
+    /// the threshold key along with the `Precommit`, and the shares are combined into
+    /// a single certificate of the block once it is committed. Any supermajority of
+    /// the validators can produce the certificate, so the threshold of the key set must be
+    /// equal to the supermajority count minus one. The key set is generated by a trusted
+    /// dealer with `crypto::threshold::gen_key_set`, and the share of a validator is
+    /// configured with `NodeConfig::threshold_key`. Blocks are certified by the precommits
+    /// only if not specified.
+    ///
+    /// The validators must be built with the `threshold-precommits` feature to sign
+    /// and combine the shares, while the other nodes only keep the key set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold_keys: Option<ThresholdKeys>,
+    /// Storage quotas keyed by the names of the service namespaces. Since quotas affect
+    /// the results of the transactions, they are a part of the consensus configuration.
+    /// Namespaces are not limited if not specified.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage_quotas: BTreeMap<String, StorageQuotaConfig>,
+    /// Versions of the service data keyed by the service names. The pending migrations
+    /// of a service (see `Service::migrations`) up to the specified version are applied
+    /// at the start of the first block executed with this configuration, so all the nodes
+    /// migrate the data at the same height. The data of the services is not migrated
+    /// if not specified.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub service_data_versions: BTreeMap<String, u32>,
+}
+
+/// Storage quota of a service namespace.
+///
+/// The size of a namespace is the total size of the keys and values of the entries
+/// in the indexes named after the service or starting with `service_name.`.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct StorageQuotaConfig {
+    /// Size of the namespace in bytes, after exceeding which the node logs a warning
+    /// on every transaction growing the namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_limit: Option<u64>,
+    /// Maximum size of the namespace in bytes. Transactions growing the namespace beyond
+    /// this size fail with the `QuotaExceeded` error and their changes are discarded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hard_limit: Option<u64>,
+}
+
+/// Public key set of the threshold signature shares of the validators.
+///
+/// The key set is kept serialized, so that the nodes built with and without
+/// the `threshold-precommits` feature read and hash the consensus configuration
+/// in the same way.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ThresholdKeys(#[serde(with = "HexStringRepresentation")] Vec<u8>);
+
+#[cfg(feature = "threshold-precommits")]
+impl ThresholdKeys {
+    /// Creates the serialized form of the key set.
+    pub fn new(key_set: &PublicKeySet) -> Self {
+        ThresholdKeys(serde_json::to_vec(key_set).expect("Unable to serialize the key set"))
+    }
+
+    /// Parses the key set.
+    pub fn key_set(&self) -> Result<PublicKeySet, JsonError> {
+        serde_json::from_slice(&self.0)
+    }
+}
+
+/// Policy of choosing the timeout of the first round at each height. The timeouts
+/// of the next rounds grow linearly from it, see `ConsensusConfig::first_round_timeout`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TimeoutPolicy {
+    /// The first round timeout is equal to `first_round_timeout`.
+    Static,
+    /// The first round timeout is derived from the network latency observed by the node,
+    /// namely, the exponentially weighted moving averages of the durations of the rounds
+    /// in which blocks are committed and of the round-trip times of the requests to peers.
+    /// The timeout is updated after each commit; `first_round_timeout` is used until
+    /// the latency is observed.
+    Adaptive {
+        /// Minimal timeout of the first round.
+        min_round_timeout: Milliseconds,
+        /// Maximal timeout of the first round.
+        max_round_timeout: Milliseconds,
+        /// Weight of the latest observation in the moving averages, in percent.
+        /// Must be in the range `1..=100`.
+        smoothing_percent: u64,
+        /// Ratio of the timeout to the observed latency, in percent. Values larger
+        /// than 100 leave a margin for the latency spikes.
+        margin_percent: u64,
+    },
+}
+
+impl TimeoutPolicy {
+    /// Returns `true` if the policy is `Static`.
+    pub fn is_static(&self) -> bool {
+        *self == TimeoutPolicy::Static
+    }
+}
+
+/// Strategy of choosing the leader, that is, the validator proposing a block in a round.
+/// See `LeaderSelector` for the implementations.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum LeaderSelection {
+    /// The validators become leaders in turn, shifted by one at each height.
+    /// The leaders are known in advance, which simplifies targeted attacks on them.
+    RoundRobin,
+    /// Each validator leads a number of consecutive rounds proportional to its weight.
+    Weighted {
+        /// Weights of the validators in the order of `validator_keys`.
+        weights: Vec<u64>,
+    },
+    /// The validators become leaders in turn starting from the one determined by the hash
+    /// of the previous block.
+    Randomized,
+}
+
+impl LeaderSelection {
+    /// Returns `true` if the strategy is `RoundRobin`.
+    pub fn is_round_robin(&self) -> bool {
+        *self == LeaderSelection::RoundRobin
+    }
+}
+
+impl Default for LeaderSelection {
+    fn default() -> Self {
+        LeaderSelection::RoundRobin
+    }
+}
+
+/// Policy of choosing the transactions of a proposed block among the uncommitted ones.
+/// See `TxOrderingPolicy` for the implementations.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum TxOrdering {
+    /// The transactions are taken in an unspecified order. This is the cheapest policy,
+    /// since only the transactions included into the block are read from the pool.
+    Unordered,
+    /// The transactions are taken in the order of their arrival to the node.
+    /// The transactions restored from the persistent pool after the node restart
+    /// are taken first.
+    Fifo,
+    /// The transactions of the services from the earlier classes are taken first,
+    /// the transactions of the services not listed in any class are taken last.
+    Priority {
+        /// Identifiers of the services of each class, starting from the highest priority.
+        classes: Vec<Vec<u16>>,
+    },
+    /// The transactions of different authors are taken in turn.
+    AuthorRoundRobin,
+}
+
+impl TxOrdering {
+    /// Returns `true` if the policy is `Unordered`.
+    pub fn is_unordered(&self) -> bool {
+        *self == TxOrdering::Unordered
+    }
+}
+
+impl Default for TxOrdering {
+    fn default() -> Self {
+        TxOrdering::Unordered
+    }
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        TimeoutPolicy::Static
+    }
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+pub(crate) fn is_sha256(value: &HashAlgorithm) -> bool {
+    *value == HashAlgorithm::Sha256
 }
 
 impl ConsensusConfig {
@@ -155,6 +457,27 @@ impl ConsensusConfig {
             );
         }
 
+        if let Some(max_block_size) = self.max_block_size {
+            if max_block_size < u64::from(self.max_message_len) {
+                warn!(
+                    "It is recommended that max_block_size ({}) is at least max_message_len ({}), \
+                     otherwise the largest transactions are never included into blocks",
+                    max_block_size, self.max_message_len
+                );
+            }
+        }
+
+        if let Some(min_block_interval) = self.min_block_interval {
+            if min_block_interval >= self.first_round_timeout {
+                warn!(
+                    "It is recommended that min_block_interval ({}) is less than \
+                     first_round_timeout ({}), otherwise the first round of each height \
+                     ends without a block",
+                    min_block_interval, self.first_round_timeout
+                );
+            }
+        }
+
         if self.max_message_len < Self::DEFAULT_MAX_MESSAGE_LEN {
             warn!(
                 "It is recommended that max_message_len ({}) is at least {}.",
@@ -176,6 +499,20 @@ impl Default for ConsensusConfig {
             min_propose_timeout: 10,
             max_propose_timeout: 200,
             propose_timeout_threshold: 500,
+            timeout_policy: TimeoutPolicy::Static,
+            max_idle_interval: None,
+            leader_selection: LeaderSelection::RoundRobin,
+            tx_ordering: TxOrdering::Unordered,
+            max_block_size: None,
+            min_block_interval: None,
+            exclude_failing_txs: false,
+            max_failed_dry_runs: None,
+            max_decompressed_len: None,
+            enforce_storage_namespaces: false,
+            parallel_execution: false,
+            threshold_keys: None,
+            storage_quotas: BTreeMap::new(),
+            service_data_versions: BTreeMap::new(),
         }
     }
 }
@@ -227,6 +564,66 @@ impl StoredConfiguration {
             )));
         }
 
+        if let TimeoutPolicy::Adaptive {
+            min_round_timeout,
+            max_round_timeout,
+            smoothing_percent,
+            ..
+        } = config.consensus.timeout_policy
+        {
+            if min_round_timeout <= config.consensus.max_propose_timeout {
+                return Err(JsonError::custom(format!(
+                    "min_round_timeout({}) must be strictly larger than max_propose_timeout({})",
+                    min_round_timeout, config.consensus.max_propose_timeout
+                )));
+            }
+            if min_round_timeout > max_round_timeout {
+                return Err(JsonError::custom(format!(
+                    "min_round_timeout({}) must not be larger than max_round_timeout({})",
+                    min_round_timeout, max_round_timeout
+                )));
+            }
+            if smoothing_percent == 0 || smoothing_percent > 100 {
+                return Err(JsonError::custom(format!(
+                    "smoothing_percent({}) must be in the range 1..=100",
+                    smoothing_percent
+                )));
+            }
+        }
+
+        // Check leader selection.
+        if let LeaderSelection::Weighted { ref weights } = config.consensus.leader_selection {
+            if weights.len() != config.validator_keys.len() {
+                return Err(JsonError::custom(format!(
+                    "The number of leader weights ({}) must be equal to the number of \
+                     validators ({})",
+                    weights.len(),
+                    config.validator_keys.len()
+                )));
+            }
+            let total = weights
+                .iter()
+                .try_fold(0_u64, |total, &weight| total.checked_add(weight));
+            if total.unwrap_or(0) == 0 {
+                return Err(JsonError::custom(
+                    "The total leader weight must be positive and fit into u64",
+                ));
+            }
+        }
+
+        // Check transactions ordering.
+        if let TxOrdering::Priority { ref classes } = config.consensus.tx_ordering {
+            let mut services = HashSet::new();
+            for &service_id in classes.iter().flat_map(|class| class.iter()) {
+                if !services.insert(service_id) {
+                    return Err(JsonError::custom(format!(
+                        "Service {} is listed in several priority classes",
+                        service_id
+                    )));
+                }
+            }
+        }
+
         // Check transactions limit.
         if config.consensus.txs_block_limit == 0 {
             return Err(JsonError::custom(
@@ -234,6 +631,79 @@ impl StoredConfiguration {
             ));
         }
 
+        // Check block size limit.
+        if config.consensus.max_block_size == Some(0) {
+            return Err(JsonError::custom(
+                "max_block_size should not be equal to zero",
+            ));
+        }
+
+        if config.consensus.max_failed_dry_runs == Some(0) {
+            return Err(JsonError::custom(
+                "max_failed_dry_runs should not be equal to zero",
+            ));
+        }
+
+        // Check block pacing.
+        if config.consensus.min_block_interval == Some(0) {
+            return Err(JsonError::custom(
+                "min_block_interval should not be equal to zero",
+            ));
+        }
+        if let (Some(min_block_interval), Some(max_idle_interval)) = (
+            config.consensus.min_block_interval,
+            config.consensus.max_idle_interval,
+        ) {
+            if min_block_interval > max_idle_interval {
+                return Err(JsonError::custom(format!(
+                    "min_block_interval ({}) must not exceed max_idle_interval ({})",
+                    min_block_interval, max_idle_interval
+                )));
+            }
+        }
+
+        // Check the threshold of the precommit key set.
+        #[cfg(feature = "threshold-precommits")]
+        {
+            if let Some(ref threshold_keys) = config.consensus.threshold_keys {
+                let key_set = threshold_keys.key_set().map_err(|e| {
+                    JsonError::custom(format!("threshold_keys is not a valid key set: {}", e))
+                })?;
+                let majority_count = State::byzantine_majority_count(config.validator_keys.len());
+                if key_set.threshold() + 1 != majority_count {
+                    return Err(JsonError::custom(format!(
+                        "The threshold of threshold_keys ({}) must be equal to the supermajority \
+                         count of the validators minus one ({})",
+                        key_set.threshold(),
+                        majority_count - 1
+                    )));
+                }
+            }
+        }
+
+        // Check the limit of the compressed transactions.
+        if let Some(max_decompressed_len) = config.consensus.max_decompressed_len {
+            if max_decompressed_len == 0 || max_decompressed_len > MAX_DECOMPRESSED_PAYLOAD_LEN {
+                return Err(JsonError::custom(format!(
+                    "max_decompressed_len ({}) must be in the range 1..={}",
+                    max_decompressed_len, MAX_DECOMPRESSED_PAYLOAD_LEN
+                )));
+            }
+        }
+
+        // Check storage quotas.
+        for (namespace, quota) in &config.consensus.storage_quotas {
+            if let (Some(soft_limit), Some(hard_limit)) = (quota.soft_limit, quota.hard_limit) {
+                if soft_limit > hard_limit {
+                    return Err(JsonError::custom(format!(
+                        "soft_limit ({}) of the `{}` storage quota must not be larger than \
+                         hard_limit ({})",
+                        soft_limit, namespace, hard_limit
+                    )));
+                }
+            }
+        }
+
         // Check maximum message length for sanity.
         if config.consensus.max_message_len < MINIMAL_MESSAGE_LENGTH {
             return Err(JsonError::custom(format!(
@@ -250,6 +720,14 @@ impl StoredConfiguration {
             )));
         }
 
+        // Check that the hash function is available.
+        if !config.hash_algorithm.is_supported() {
+            return Err(JsonError::custom(format!(
+                "Hash algorithm {} is not supported by this build",
+                config.hash_algorithm
+            )));
+        }
+
         Ok(config)
     }
 }
@@ -327,6 +805,48 @@ mod tests {
         assert_eq!(configuration, serialize_deserialize(&configuration));
     }
 
+    #[test]
+    fn default_hash_algorithm_is_not_serialized() {
+        let configuration = create_test_configuration();
+        let json = String::from_utf8(configuration.try_serialize().unwrap()).unwrap();
+        assert!(!json.contains("hash_algorithm"));
+        assert!(!json.contains("domain_separated_signatures"));
+        assert!(!json.contains("canonical_encoding"));
+        assert!(!json.contains("secp256k1_signatures"));
+    }
+
+    #[test]
+    fn domain_separated_signatures_toml() {
+        let mut configuration = create_test_configuration();
+        configuration.domain_separated_signatures = true;
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("domain_separated_signatures = true"));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
+    #[cfg(feature = "blake2b-hashing")]
+    #[test]
+    fn blake2b_hash_algorithm_toml() {
+        let mut configuration = create_test_configuration();
+        configuration.hash_algorithm = HashAlgorithm::Blake2b;
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("hash_algorithm = \"blake2b\""));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
+    #[cfg(not(feature = "blake2b-hashing"))]
+    #[test]
+    #[should_panic(expected = "Hash algorithm blake2b is not supported by this build")]
+    fn unsupported_hash_algorithm() {
+        let mut configuration = create_test_configuration();
+        configuration.hash_algorithm = HashAlgorithm::Blake2b;
+        serialize_deserialize(&configuration);
+    }
+
     #[test]
     #[should_panic(expected = "Duplicated keys are found")]
     fn duplicated_validators_keys() {
@@ -358,6 +878,100 @@ mod tests {
         serialize_deserialize(&configuration);
     }
 
+    #[test]
+    fn adaptive_timeout_policy_toml() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.timeout_policy = TimeoutPolicy::Adaptive {
+            min_round_timeout: 1000,
+            max_round_timeout: 10_000,
+            smoothing_percent: 20,
+            margin_percent: 150,
+        };
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("type = \"adaptive\""));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+
+        // The static policy is omitted, so that the hashes of the existing
+        // configurations do not change.
+        let configuration = create_test_configuration();
+        assert!(!toml::to_string(&configuration)
+            .unwrap()
+            .contains("timeout_policy"));
+    }
+
+    #[test]
+    #[should_panic(expected = "smoothing_percent(0) must be in the range 1..=100")]
+    fn invalid_adaptive_timeout_policy() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.timeout_policy = TimeoutPolicy::Adaptive {
+            min_round_timeout: 1000,
+            max_round_timeout: 10_000,
+            smoothing_percent: 0,
+            margin_percent: 150,
+        };
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    fn leader_selection_toml() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.leader_selection = LeaderSelection::Weighted {
+            weights: vec![1; configuration.validator_keys.len()],
+        };
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("type = \"weighted\""));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
+    #[test]
+    #[should_panic(expected = "The number of leader weights (1) must be equal")]
+    fn invalid_leader_weights() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.leader_selection = LeaderSelection::Weighted { weights: vec![1] };
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    fn tx_ordering_toml() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.tx_ordering = TxOrdering::Priority {
+            classes: vec![vec![1], vec![2, 3]],
+        };
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("type = \"priority\""));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
+    #[test]
+    #[should_panic(expected = "Service 2 is listed in several priority classes")]
+    fn invalid_priority_classes() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.tx_ordering = TxOrdering::Priority {
+            classes: vec![vec![1, 2], vec![2]],
+        };
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    fn max_idle_interval_toml() {
+        let mut configuration = create_test_configuration();
+        assert!(!toml::to_string(&configuration)
+            .unwrap()
+            .contains("max_idle_interval"));
+
+        configuration.consensus.max_idle_interval = Some(60_000);
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("max_idle_interval = 60000"));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+    }
+
     #[test]
     #[should_panic(expected = "txs_block_limit should not be equal to zero")]
     fn invalid_txs_block_limit() {
@@ -366,6 +980,61 @@ mod tests {
         serialize_deserialize(&configuration);
     }
 
+    #[test]
+    #[should_panic(expected = "max_block_size should not be equal to zero")]
+    fn invalid_max_block_size() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.max_block_size = Some(0);
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    fn min_block_interval_toml() {
+        let mut configuration = create_test_configuration();
+        assert!(!toml::to_string(&configuration)
+            .unwrap()
+            .contains("min_block_interval"));
+
+        configuration.consensus.min_block_interval = Some(2_000);
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("min_block_interval = 2000"));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+    }
+
+    #[test]
+    fn exclude_failing_txs_toml() {
+        let mut configuration = create_test_configuration();
+        assert!(!toml::to_string(&configuration)
+            .unwrap()
+            .contains("exclude_failing_txs"));
+
+        configuration.consensus.exclude_failing_txs = true;
+        configuration.consensus.max_failed_dry_runs = Some(3);
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("exclude_failing_txs = true"));
+        assert!(toml.contains("max_failed_dry_runs = 3"));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_failed_dry_runs should not be equal to zero")]
+    fn invalid_max_failed_dry_runs() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.max_failed_dry_runs = Some(0);
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    #[should_panic(expected = "min_block_interval (2000) must not exceed max_idle_interval (1000)")]
+    fn min_block_interval_exceeding_max_idle_interval() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.min_block_interval = Some(2_000);
+        configuration.consensus.max_idle_interval = Some(1_000);
+        serialize_deserialize(&configuration);
+    }
+
     #[test]
     #[should_panic(expected = "max_message_len (128) must be at least")]
     fn too_small_max_message_len() {
@@ -382,6 +1051,90 @@ mod tests {
         serialize_deserialize(&configuration);
     }
 
+    #[test]
+    #[should_panic(expected = "max_decompressed_len (0) must be in the range")]
+    fn zero_max_decompressed_len() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.max_decompressed_len = Some(0);
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    fn storage_quotas_toml() {
+        let mut configuration = create_test_configuration();
+        assert!(!toml::to_string(&configuration)
+            .unwrap()
+            .contains("storage_quotas"));
+
+        let quota = StorageQuotaConfig {
+            soft_limit: Some(1_000),
+            hard_limit: Some(2_000),
+        };
+        configuration
+            .consensus
+            .storage_quotas
+            .insert("wallets".to_owned(), quota);
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("[consensus.storage_quotas.wallets]"));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
+    #[test]
+    #[should_panic(expected = "soft_limit (2000) of the `wallets` storage quota must not be")]
+    fn invalid_storage_quota() {
+        let mut configuration = create_test_configuration();
+        let quota = StorageQuotaConfig {
+            soft_limit: Some(2_000),
+            hard_limit: Some(1_000),
+        };
+        configuration
+            .consensus
+            .storage_quotas
+            .insert("wallets".to_owned(), quota);
+        serialize_deserialize(&configuration);
+    }
+
+    #[test]
+    #[cfg(feature = "threshold-precommits")]
+    fn threshold_keys() {
+        let mut configuration = create_test_configuration();
+        let (key_set, _) = crate::crypto::threshold::gen_key_set(3, 2);
+        configuration.consensus.threshold_keys = Some(ThresholdKeys::new(&key_set));
+        let deserialized = serialize_deserialize(&configuration);
+        let threshold_keys = deserialized.consensus.threshold_keys.unwrap();
+        assert_eq!(threshold_keys.key_set().unwrap(), key_set);
+    }
+
+    #[test]
+    #[cfg(not(feature = "threshold-precommits"))]
+    fn threshold_keys_are_kept_without_feature() {
+        let mut configuration = create_test_configuration();
+        configuration.consensus.threshold_keys = Some(ThresholdKeys(vec![1, 2, 3]));
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("threshold_keys = \"010203\""));
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
+    #[test]
+    fn service_data_versions_toml() {
+        let mut configuration = create_test_configuration();
+        assert!(!toml::to_string(&configuration)
+            .unwrap()
+            .contains("service_data_versions"));
+
+        configuration
+            .consensus
+            .service_data_versions
+            .insert("wallets".to_owned(), 2);
+        let toml = toml::to_string(&configuration).unwrap();
+        assert!(toml.contains("[consensus.service_data_versions]"));
+        let deserialized: StoredConfiguration = toml::from_str(&toml).unwrap();
+        assert_eq!(configuration, deserialized);
+        assert_eq!(configuration, serialize_deserialize(&configuration));
+    }
+
     fn create_test_configuration() -> StoredConfiguration {
         let validator_keys = (1..4)
             .map(|i| ValidatorKeys {
@@ -393,6 +1146,10 @@ mod tests {
         StoredConfiguration {
             previous_cfg_hash: Hash::zero(),
             actual_from: Height(42),
+            hash_algorithm: HashAlgorithm::Sha256,
+            domain_separated_signatures: false,
+            canonical_encoding: false,
+            secp256k1_signatures: false,
             validator_keys,
             consensus: ConsensusConfig::default(),
             services: BTreeMap::new(),
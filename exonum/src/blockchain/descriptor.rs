@@ -0,0 +1,90 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use failure::Error;
+use protobuf::{
+    descriptor::{FileDescriptorProto, FileDescriptorSet},
+    Message, RepeatedField,
+};
+
+use std::collections::HashMap;
+
+use crate::proto;
+
+/// Protobuf descriptor of the service transactions.
+///
+/// The descriptors of the services are stored in the blockchain when the node starts,
+/// so that the explorer and external tools can decode the transactions of the services
+/// which are not compiled into their binaries, see `explorer::TransactionDecoder`.
+///
+/// # Examples
+///
+/// ```ignore
+/// // `proto::service` is the module generated from `service.proto`, which imports
+/// // `helpers.proto` of Exonum.
+/// let descriptor = ServiceDescriptor::new(&[
+///     exonum::proto::schema::helpers::file_descriptor_proto(),
+///     proto::service::file_descriptor_proto(),
+/// ])
+/// .with_transaction(0, "my_service.TxCreateWallet")
+/// .with_transaction(1, "my_service.TxTransfer");
+/// ```
+#[derive(Debug, Clone, PartialEq, ProtobufConvert)]
+#[exonum(pb = "proto::ServiceDescriptor", crate = "crate")]
+pub struct ServiceDescriptor {
+    /// Serialized `FileDescriptorSet` with the protobuf files of the service
+    /// and their dependencies.
+    file_descriptor_set: Vec<u8>,
+    /// Full names of the protobuf messages of the transactions by transaction identifiers.
+    transactions: HashMap<u32, String>,
+}
+
+impl ServiceDescriptor {
+    /// Creates a descriptor with the given protobuf files and without transactions.
+    /// The files must include the dependencies of the service files, such as
+    /// `helpers.proto` of Exonum.
+    pub fn new(files: &[&FileDescriptorProto]) -> Self {
+        let mut file_descriptor_set = FileDescriptorSet::new();
+        file_descriptor_set.set_file(RepeatedField::from_vec(
+            files.iter().map(|&file| file.clone()).collect(),
+        ));
+        Self {
+            file_descriptor_set: file_descriptor_set
+                .write_to_bytes()
+                .expect("Cannot serialize protobuf file descriptors"),
+            transactions: HashMap::new(),
+        }
+    }
+
+    /// Declares the full name of the protobuf message of the transaction with the given
+    /// identifier, such as `my_service.TxTransfer`.
+    pub fn with_transaction(mut self, transaction_id: u16, message_name: &str) -> Self {
+        self.transactions
+            .insert(u32::from(transaction_id), message_name.to_owned());
+        self
+    }
+
+    /// Returns the protobuf files of the service.
+    pub fn file_descriptor_set(&self) -> Result<FileDescriptorSet, Error> {
+        Ok(protobuf::parse_from_bytes(&self.file_descriptor_set)?)
+    }
+
+    /// Returns the full name of the protobuf message of the transaction with the given
+    /// identifier.
+    pub fn transaction_message(&self, transaction_id: u16) -> Option<&str> {
+        self.transactions
+            .get(&u32::from(transaction_id))
+            .map(String::as_str)
+    }
+}
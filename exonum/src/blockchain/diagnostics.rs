@@ -0,0 +1,80 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use exonum_merkledb::Fork;
+
+use super::Schema;
+use crate::crypto::Hash;
+use crate::helpers::{Height, Round, ValidatorId};
+use crate::proto;
+
+/// Proposal observed by the node at a height.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::ProposeDiagnostics", crate = "crate")]
+pub struct ProposeDiagnostics {
+    /// Identifier of the validator which has sent the proposal.
+    pub validator: ValidatorId,
+    /// Round of the proposal.
+    pub round: Round,
+    /// Hash of the proposal.
+    pub hash: Hash,
+    /// Number of the transactions in the proposal.
+    pub tx_count: u32,
+}
+
+/// Consensus artifacts of a committed height recorded by the node, which allow
+/// to analyze the consensus incidents after the fact.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::HeightDiagnostics", crate = "crate")]
+pub struct HeightDiagnostics {
+    /// Height of the committed block.
+    pub height: Height,
+    /// Latest round reached by the node at the height. The block may be committed
+    /// in an earlier round if the node has received it from the peers.
+    pub round: Round,
+    /// Identifier of the validator which has proposed the committed block.
+    pub proposer: ValidatorId,
+    /// Leaders of the rounds from the first one up to `round`.
+    pub leaders: Vec<ValidatorId>,
+    /// Proposals observed by the node at the height.
+    pub proposes: Vec<ProposeDiagnostics>,
+    /// Rounds finished by the timeout.
+    pub round_timeouts: Vec<Round>,
+    /// Rounds in which the propose timeout of the node has fired.
+    pub propose_timeouts: Vec<Round>,
+    /// Time at which the node has started the height.
+    pub started_at: DateTime<Utc>,
+    /// Time at which the node has committed the block.
+    pub committed_at: DateTime<Utc>,
+}
+
+/// Stores the diagnostics of a height and removes the diagnostics of the heights
+/// preceding the latest `keep_heights` ones.
+pub(crate) fn record_height_diagnostics(
+    fork: &Fork,
+    diagnostics: HeightDiagnostics,
+    keep_heights: u64,
+) {
+    let schema = Schema::new(fork);
+    let mut index = schema.height_diagnostics();
+    let height = diagnostics.height.0;
+    index.put(&height, diagnostics);
+
+    let first_kept = (height + 1).saturating_sub(keep_heights);
+    let outdated: Vec<u64> = index.keys().take_while(|h| *h < first_kept).collect();
+    for height in outdated {
+        index.remove(&height);
+    }
+}
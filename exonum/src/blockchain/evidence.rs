@@ -0,0 +1,92 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum_merkledb::BinaryValue;
+
+use crate::helpers::{Height, Round, ValidatorId};
+use crate::messages::{HexStringRepresentation, ProtocolMessage, Signed};
+use crate::proto;
+
+/// Evidence of a validator signing two conflicting consensus messages, that is,
+/// prevotes for different proposals or precommits for different blocks in the same round.
+///
+/// Since each message carries the signature of the validator, the evidence can be verified
+/// by anyone without trusting the node which has observed it.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::DoubleSignEvidence", crate = "crate")]
+pub struct DoubleSignEvidence {
+    /// Identifier of the validator which has signed the messages.
+    validator: ValidatorId,
+    /// Height of the messages.
+    height: Height,
+    /// Round of the messages.
+    round: Round,
+    /// The first of the conflicting signed messages.
+    #[serde(with = "HexStringRepresentation")]
+    first: Vec<u8>,
+    /// The second of the conflicting signed messages.
+    #[serde(with = "HexStringRepresentation")]
+    second: Vec<u8>,
+}
+
+impl DoubleSignEvidence {
+    /// Creates the evidence from the conflicting messages. The messages are ordered,
+    /// so that the same pair of messages always results in the same evidence.
+    pub(crate) fn new<T: ProtocolMessage>(
+        validator: ValidatorId,
+        height: Height,
+        round: Round,
+        first: Signed<T>,
+        second: Signed<T>,
+    ) -> Self {
+        let (first, second) = (first.into_bytes(), second.into_bytes());
+        let (first, second) = if first <= second {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        Self {
+            validator,
+            height,
+            round,
+            first,
+            second,
+        }
+    }
+
+    /// Identifier of the validator which has signed the messages.
+    pub fn validator(&self) -> ValidatorId {
+        self.validator
+    }
+
+    /// Height of the messages.
+    pub fn height(&self) -> Height {
+        self.height
+    }
+
+    /// Round of the messages.
+    pub fn round(&self) -> Round {
+        self.round
+    }
+
+    /// The first of the conflicting signed messages.
+    pub fn first(&self) -> &[u8] {
+        &self.first
+    }
+
+    /// The second of the conflicting signed messages.
+    pub fn second(&self) -> &[u8] {
+        &self.second
+    }
+}
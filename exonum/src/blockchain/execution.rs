@@ -0,0 +1,136 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rules of the transaction execution taken from the consensus configuration.
+
+use exonum_merkledb::{namespace_size, Fork};
+
+use std::collections::{BTreeMap, HashMap};
+
+use super::{config::StorageQuotaConfig, ConsensusConfig, Schema, TransactionError};
+
+/// Rules of the execution of the transactions and `before_commit` handlers within a block.
+///
+/// The rules change the results of the transactions, so they are taken from
+/// the consensus configuration actual for the block rather than from the node
+/// configuration. Thus, all the validators execute the block in the same way.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ExecutionRules {
+    /// Whether the access of the services to the storage is restricted to their namespaces.
+    pub enforce_namespaces: bool,
+    /// Whether the transactions are executed in parallel groups.
+    pub parallel: bool,
+    /// Versions of the service data which must be reached by the migrations at the start
+    /// of the block.
+    pub service_data_versions: BTreeMap<String, u32>,
+    storage_quotas: BTreeMap<String, StorageQuotaConfig>,
+    /// Sizes of the limited namespaces in the flushed state of the fork the rules are
+    /// applied to. A namespace is measured once per fork, and its size is then updated
+    /// with the changes of each transaction.
+    namespace_sizes: HashMap<String, u64>,
+}
+
+impl ExecutionRules {
+    /// Returns the rules of the block following the latest one in the fork.
+    pub fn for_next_block(fork: &Fork) -> Self {
+        Self::new(&next_block_config(fork))
+    }
+
+    fn new(config: &ConsensusConfig) -> Self {
+        Self {
+            enforce_namespaces: config.enforce_storage_namespaces || config.parallel_execution,
+            parallel: config.parallel_execution,
+            service_data_versions: config.service_data_versions.clone(),
+            storage_quotas: config.storage_quotas.clone(),
+            namespace_sizes: HashMap::new(),
+        }
+    }
+
+    /// Returns the rules to be applied to another fork.
+    pub fn for_other_fork(&self) -> Self {
+        Self {
+            namespace_sizes: HashMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Checks that the unflushed changes of the fork made by the transaction
+    /// of the service do not grow its namespace beyond the hard storage quota.
+    pub fn check_storage_quota(
+        &mut self,
+        service_name: &str,
+        fork: &Fork,
+    ) -> Result<(), TransactionError> {
+        let quota = match self.storage_quotas.get(service_name) {
+            Some(quota) => *quota,
+            None => return Ok(()),
+        };
+        let size_change = fork.unflushed_namespace_size_change(service_name);
+        // Transactions which do not grow the namespace are allowed even if the quota
+        // is already exceeded, so that the data can be cleaned up.
+        if size_change <= 0 {
+            return Ok(());
+        }
+        let size = self.namespace_size(service_name, fork) + size_change as u64;
+
+        if let Some(hard_limit) = quota.hard_limit {
+            if size > hard_limit {
+                return Err(TransactionError::quota_exceeded(Some(format!(
+                    "Size of the `{}` namespace would be {} bytes, exceeding the quota of {} bytes",
+                    service_name, size, hard_limit
+                ))));
+            }
+        }
+        if let Some(soft_limit) = quota.soft_limit {
+            if size > soft_limit {
+                warn!(
+                    "Size of the `{}` namespace is {} bytes, exceeding the soft quota of {} bytes",
+                    service_name, size, soft_limit
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Updates the sizes of the measured namespaces with the unflushed changes of the fork,
+    /// which are about to be flushed.
+    pub fn record_changes(&mut self, fork: &Fork) {
+        for (namespace, size) in &mut self.namespace_sizes {
+            let size_change = fork.unflushed_namespace_size_change(namespace);
+            *size = (*size as i64 + size_change) as u64;
+        }
+    }
+
+    fn namespace_size(&mut self, namespace: &str, fork: &Fork) -> u64 {
+        if let Some(size) = self.namespace_sizes.get(namespace) {
+            return *size;
+        }
+        let size = namespace_size(fork.as_ref(), namespace);
+        self.namespace_sizes.insert(namespace.to_owned(), size);
+        size
+    }
+}
+
+/// Returns the consensus configuration actual for the block following the latest one
+/// in the fork. The default configuration is returned if the genesis configuration
+/// has not been committed, which is the case for the blockchains used in tests
+/// without initialization.
+fn next_block_config(fork: &Fork) -> ConsensusConfig {
+    let schema = Schema::new(fork);
+    if schema.configs_actual_from().is_empty() {
+        ConsensusConfig::default()
+    } else {
+        schema.actual_configuration().consensus
+    }
+}
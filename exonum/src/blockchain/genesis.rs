@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use super::config::{ConsensusConfig, ValidatorKeys};
+use crate::crypto::HashAlgorithm;
 
 /// The initial configuration which is committed into the genesis block.
 ///
@@ -27,6 +28,21 @@ pub struct GenesisConfig {
     pub consensus: ConsensusConfig,
     /// List of public keys of validators.
     pub validator_keys: Vec<ValidatorKeys>,
+    /// Hash function used by the blockchain. SHA-256 is used if not specified.
+    #[serde(default)]
+    pub hash_algorithm: HashAlgorithm,
+    /// Whether the signatures of the messages cover the identifier of the network,
+    /// see `StoredConfiguration::domain_separated_signatures`.
+    #[serde(default)]
+    pub domain_separated_signatures: bool,
+    /// Whether the messages must be encoded canonically,
+    /// see `StoredConfiguration::canonical_encoding`.
+    #[serde(default)]
+    pub canonical_encoding: bool,
+    /// Whether the transactions may be signed with secp256k1 keys,
+    /// see `StoredConfiguration::secp256k1_signatures`.
+    #[serde(default)]
+    pub secp256k1_signatures: bool,
 }
 
 impl GenesisConfig {
@@ -44,6 +60,34 @@ impl GenesisConfig {
         Self {
             consensus,
             validator_keys: validator_keys.collect(),
+            hash_algorithm: HashAlgorithm::default(),
+            domain_separated_signatures: false,
+            canonical_encoding: false,
+            secp256k1_signatures: false,
         }
     }
+
+    /// Sets the hash function used by the blockchain.
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    /// Sets whether the signatures of the messages cover the identifier of the network.
+    pub fn with_domain_separated_signatures(mut self, enabled: bool) -> Self {
+        self.domain_separated_signatures = enabled;
+        self
+    }
+
+    /// Sets whether the messages must be encoded canonically.
+    pub fn with_canonical_encoding(mut self, enabled: bool) -> Self {
+        self.canonical_encoding = enabled;
+        self
+    }
+
+    /// Sets whether the transactions may be signed with secp256k1 keys.
+    pub fn with_secp256k1_signatures(mut self, enabled: bool) -> Self {
+        self.secp256k1_signatures = enabled;
+        self
+    }
 }
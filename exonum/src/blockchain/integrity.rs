@@ -0,0 +1,138 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the stored blocks and the blockchain state against the block headers.
+
+use failure::Error;
+
+use std::collections::HashSet;
+
+use super::{Blockchain, Schema, StoredConfiguration, CORE_SERVICE};
+use crate::crypto::Hash;
+use crate::helpers::Height;
+use crate::messages::{Precommit, Signed};
+use crate::node::state::State;
+use exonum_merkledb::{IndexAccess, ObjectHash, Snapshot};
+
+/// Verifies that the blocks starting from the given height are linked to the previous
+/// blocks and that the transactions of the blocks match the transaction roots
+/// in their headers.
+pub(crate) fn verify_blocks<T: IndexAccess>(schema: &Schema<T>, from: Height) -> Result<(), Error> {
+    let blocks = schema.blocks();
+    let block_hashes = schema.block_hashes_by_height();
+    let mut prev_hash = if from == Height::zero() {
+        Hash::zero()
+    } else {
+        let height = from.previous();
+        block_hashes
+            .get(height.0)
+            .ok_or_else(|| format_err!("Block at height {} is missing", height))?
+    };
+
+    for (height, block_hash) in (from.0..).map(Height).zip(block_hashes.iter_from(from.0)) {
+        let block = blocks
+            .get(&block_hash)
+            .ok_or_else(|| format_err!("Block at height {} is missing", height))?;
+        ensure!(
+            block.hash() == block_hash
+                && block.height() == height
+                && *block.prev_hash() == prev_hash,
+            "Block at height {} is not linked to the previous one",
+            height
+        );
+        ensure!(
+            schema.block_transactions(height).object_hash() == *block.tx_hash(),
+            "Transactions of the block at height {} do not match its header",
+            height
+        );
+        prev_hash = block_hash;
+    }
+    Ok(())
+}
+
+/// Verifies that the root hashes of the core and service tables match the state hash
+/// of the latest block.
+pub(crate) fn verify_state_hash(
+    blockchain: &Blockchain,
+    snapshot: &dyn Snapshot,
+) -> Result<(), Error> {
+    let schema = Schema::new(snapshot);
+    let state_hash_aggregator = schema.state_hash_aggregator();
+    ensure!(
+        state_hash_aggregator.object_hash() == *schema.last_block().state_hash(),
+        "The state hash does not match the latest block"
+    );
+
+    let core_tables = schema
+        .core_state_hash()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, hash)| (CORE_SERVICE, idx, hash));
+    let service_tables = blockchain.service_map().values().flat_map(|service| {
+        let service_id = service.service_id();
+        service
+            .state_hash(snapshot)
+            .into_iter()
+            .enumerate()
+            .map(move |(idx, hash)| (service_id, idx, hash))
+    });
+    for (service_id, idx, hash) in core_tables.chain(service_tables) {
+        let key = Blockchain::service_table_unique_key(service_id, idx);
+        ensure!(
+            state_hash_aggregator.get(&key) == Some(hash),
+            "The state hash of table {} of service {} does not match the latest block",
+            idx,
+            service_id
+        );
+    }
+    Ok(())
+}
+
+/// Verifies that the block at the given height is signed by the supermajority
+/// of the validators from the configuration. The precommits are verified with
+/// the network identifier of this height, see `Schema::signature_network_id`.
+pub(crate) fn verify_precommits<I>(
+    config: &StoredConfiguration,
+    network_id: Option<Hash>,
+    height: Height,
+    block_hash: &Hash,
+    precommits: I,
+) -> Result<(), Error>
+where
+    I: IntoIterator<Item = Signed<Precommit>>,
+{
+    let signed_by = precommits
+        .into_iter()
+        .filter(|precommit| {
+            let validator = config
+                .validator_keys
+                .get(precommit.validator().0 as usize)
+                .map(|keys| keys.consensus_key);
+            precommit.height() == height
+                && precommit.block_hash() == block_hash
+                && validator == Some(precommit.author())
+                && precommit
+                    .signed_message()
+                    .verify_signature(network_id)
+                    .is_ok()
+        })
+        .map(|precommit| precommit.validator())
+        .collect::<HashSet<_>>();
+    ensure!(
+        signed_by.len() >= State::byzantine_majority_count(config.validator_keys.len()),
+        "The block at height {} does not have enough valid precommits",
+        height
+    );
+    Ok(())
+}
@@ -0,0 +1,168 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use super::config::LeaderSelection;
+use crate::crypto::Hash;
+use crate::helpers::{Height, Round, ValidatorId};
+
+/// Strategy of choosing the leader, that is, the validator proposing a block in a round.
+///
+/// The strategy must be deterministic, so that all the nodes agree on the leader.
+pub trait LeaderSelector {
+    /// Returns the leader of the round at the given height. `last_hash` is the hash
+    /// of the latest committed block.
+    fn leader(
+        &self,
+        height: Height,
+        round: Round,
+        last_hash: &Hash,
+        validators_count: usize,
+    ) -> ValidatorId;
+}
+
+/// The validators become leaders in turn, shifted by one at each height.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
+
+impl LeaderSelector for RoundRobin {
+    fn leader(
+        &self,
+        height: Height,
+        round: Round,
+        _: &Hash,
+        validators_count: usize,
+    ) -> ValidatorId {
+        let height: u64 = height.into();
+        let round: u64 = round.into();
+        ValidatorId(((height + round) % (validators_count as u64)) as u16)
+    }
+}
+
+/// Each validator leads a number of consecutive rounds proportional to its weight.
+#[derive(Debug, Clone, Copy)]
+pub struct Weighted<'a> {
+    weights: &'a [u64],
+}
+
+impl<'a> Weighted<'a> {
+    /// Creates the selector with the weights of the validators.
+    pub fn new(weights: &'a [u64]) -> Self {
+        Weighted { weights }
+    }
+}
+
+impl<'a> LeaderSelector for Weighted<'a> {
+    fn leader(
+        &self,
+        height: Height,
+        round: Round,
+        _: &Hash,
+        validators_count: usize,
+    ) -> ValidatorId {
+        debug_assert_eq!(self.weights.len(), validators_count);
+        let total: u64 = self.weights.iter().sum();
+        let height: u64 = height.into();
+        let round: u64 = round.into();
+        let mut slot = (height + round) % total;
+        for (id, &weight) in self.weights.iter().enumerate() {
+            if slot < weight {
+                return ValidatorId(id as u16);
+            }
+            slot -= weight;
+        }
+        unreachable!("The slot is less than the total weight")
+    }
+}
+
+/// The validators become leaders in turn starting from the one determined by the hash
+/// of the latest block, so that the leaders of a height cannot be predicted before
+/// the previous block is committed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Randomized;
+
+impl LeaderSelector for Randomized {
+    fn leader(
+        &self,
+        _: Height,
+        round: Round,
+        last_hash: &Hash,
+        validators_count: usize,
+    ) -> ValidatorId {
+        let validators_count = validators_count as u64;
+        let offset = LittleEndian::read_u64(&last_hash.as_ref()[..8]) % validators_count;
+        let round: u64 = round.into();
+        ValidatorId(((offset + round) % validators_count) as u16)
+    }
+}
+
+impl LeaderSelector for LeaderSelection {
+    fn leader(
+        &self,
+        height: Height,
+        round: Round,
+        last_hash: &Hash,
+        validators_count: usize,
+    ) -> ValidatorId {
+        match self {
+            LeaderSelection::RoundRobin => {
+                RoundRobin.leader(height, round, last_hash, validators_count)
+            }
+            LeaderSelection::Weighted { weights } => {
+                Weighted::new(weights).leader(height, round, last_hash, validators_count)
+            }
+            LeaderSelection::Randomized => {
+                Randomized.leader(height, round, last_hash, validators_count)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash;
+
+    #[test]
+    fn weighted_selection() {
+        let weights = [1, 3, 0, 2];
+        let selector = Weighted::new(&weights);
+        let leaders = (0..6)
+            .map(|round| selector.leader(Height(0), Round(round), &Hash::zero(), 4))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            leaders,
+            vec![
+                ValidatorId(0),
+                ValidatorId(1),
+                ValidatorId(1),
+                ValidatorId(1),
+                ValidatorId(3),
+                ValidatorId(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn randomized_selection_visits_all_validators() {
+        let last_hash = hash(&[1, 2, 3]);
+        let mut leaders = (1..=4)
+            .map(|round| Randomized.leader(Height(5), Round(round), &last_hash, 4))
+            .collect::<Vec<_>>();
+        leaders.sort();
+        leaders.dedup();
+        assert_eq!(leaders.len(), 4);
+    }
+}
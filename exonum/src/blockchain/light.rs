@@ -0,0 +1,504 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Light client mode keeping only the block headers and precommits.
+//!
+//! A light chain starts from a trusted genesis block and configuration and accepts
+//! the following blocks only if they continue the chain and are signed by
+//! the supermajority of the validators actual at their height. Transactions and
+//! the service state are not stored. Instead, the proofs of the state obtained from
+//! full nodes are verified against the state hashes of the stored blocks.
+//!
+//! Changes of the validator set are accepted as proofs of the configurations committed
+//! to the state of the known blocks, such as the ones returned by the `v1/configs/proof`
+//! endpoint of the explorer API. Thus, a light node following a full node should request
+//! the proof of the latest configuration once it has synchronized the latest block, and
+//! before the configuration becomes actual. `LightChain::sync` does this for the blocks
+//! and configurations obtained from a `LightSource`, and `node::LightNode` keeps the light
+//! chain synchronized with a full node and serves the light client API.
+
+use failure::Error;
+
+use std::{fmt, sync::Arc};
+
+use super::{
+    integrity::verify_precommits, schema::ConfigReference, Block, BlockProof, Blockchain,
+    StoredConfiguration, CORE_SERVICE,
+};
+use crate::api::node::public::explorer::{ConfigProof, IndexProof, IndexValue};
+use crate::crypto::{CryptoHash, Hash};
+use crate::helpers::Height;
+use crate::messages::{Precommit, Signed};
+use exonum_merkledb::{Database, IndexAccess, ListIndex, MapIndex, Snapshot};
+
+const BLOCKS: &str = "light.blocks";
+const BLOCK_HASHES_BY_HEIGHT: &str = "light.block_hashes_by_height";
+const PRECOMMITS: &str = "light.precommits";
+const CERTIFICATES: &str = "light.certificates";
+const CONFIGS: &str = "light.configs";
+const CONFIGS_ACTUAL_FROM: &str = "light.configs_actual_from";
+
+/// Entry of an index proven by a verified proof.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedEntry {
+    /// The hash addressing the entry in the index.
+    pub key: Hash,
+    /// The value of the entry, or `None` if the proof shows the entry is absent.
+    pub value: Option<IndexValue>,
+}
+
+/// Entries of an index proven by a verified proof.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifiedEntries {
+    /// The height of the block whose state contains the entries.
+    pub height: Height,
+    /// The hash of the block whose state contains the entries.
+    pub block_hash: Hash,
+    /// Identifier of the service owning the index.
+    pub service_id: u16,
+    /// Index of the index root hash in the state hash of the service.
+    pub table_idx: usize,
+    /// The proven entries.
+    pub entries: Vec<VerifiedEntry>,
+}
+
+/// Source of the blocks and configurations followed by the light chain, such as
+/// the explorer API of a full node.
+pub trait LightSource {
+    /// Returns the block at the given height with its precommits, or `None` if the block
+    /// is not committed yet.
+    fn block_proof(&self, height: Height) -> Result<Option<BlockProof>, Error>;
+
+    /// Returns the proof of the latest configuration committed to the state of the block
+    /// at the given height, or of the latest block if the height is not specified.
+    fn config_proof(&self, height: Option<Height>) -> Result<ConfigProof, Error>;
+}
+
+/// Information schema for the indexes of the light chain.
+#[derive(Debug)]
+pub struct LightSchema<T> {
+    access: T,
+}
+
+impl<T> LightSchema<T>
+where
+    T: IndexAccess,
+{
+    /// Constructs information schema for the given `snapshot`.
+    pub fn new(access: T) -> Self {
+        Self { access }
+    }
+
+    /// Returns a table that stores a block header for every block hash.
+    pub fn blocks(&self) -> MapIndex<T, Hash, Block> {
+        MapIndex::new(BLOCKS, self.access.clone())
+    }
+
+    /// Returns a table that keeps block hashes for corresponding block heights.
+    pub fn block_hashes_by_height(&self) -> ListIndex<T, Hash> {
+        ListIndex::new(BLOCK_HASHES_BY_HEIGHT, self.access.clone())
+    }
+
+    /// Returns a table that keeps a list of precommits for the block with the given hash.
+    pub fn precommits(&self, hash: &Hash) -> ListIndex<T, Signed<Precommit>> {
+        ListIndex::new_in_family(PRECOMMITS, hash, self.access.clone())
+    }
+
+    /// Returns a table that keeps the certificates of the blocks accepted without
+    /// the precommits by the block hashes.
+    pub fn certificates(&self) -> MapIndex<T, Hash, Vec<u8>> {
+        MapIndex::new(CERTIFICATES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the accepted configurations by their hashes.
+    pub fn configs(&self) -> MapIndex<T, Hash, StoredConfiguration> {
+        MapIndex::new(CONFIGS, self.access.clone())
+    }
+
+    /// Returns a table that keeps hash references to the accepted configurations in
+    /// the increasing order of their `actual_from` height.
+    pub fn configs_actual_from(&self) -> ListIndex<T, ConfigReference> {
+        ListIndex::new(CONFIGS_ACTUAL_FROM, self.access.clone())
+    }
+
+    /// Returns the height of the latest stored block, or `None` if the light chain
+    /// is not initialized.
+    pub fn height(&self) -> Option<Height> {
+        match self.block_hashes_by_height().len() {
+            0 => None,
+            len => Some(Height(len - 1)),
+        }
+    }
+
+    /// Returns the stored block with its precommits.
+    pub fn block_proof(&self, height: Height) -> Option<BlockProof> {
+        let block_hash = self.block_hashes_by_height().get(height.0)?;
+        let block = self.blocks().get(&block_hash)?;
+        let precommits = self.precommits(&block_hash).iter().collect();
+        let certificate = self.certificates().get(&block_hash);
+        Some(BlockProof {
+            block,
+            precommits,
+            certificate,
+        })
+    }
+
+    /// Returns the accepted configuration actual at the given height.
+    pub fn configuration_by_height(&self, height: Height) -> Option<StoredConfiguration> {
+        let cfg_ref = self
+            .configs_actual_from()
+            .iter()
+            .take_while(|cfg_ref| cfg_ref.actual_from() <= height)
+            .last()?;
+        self.configs().get(cfg_ref.cfg_hash())
+    }
+
+    /// Returns the network identifier covered by the signatures of the messages
+    /// at the given height, like `Schema::signature_network_id`.
+    pub fn signature_network_id(&self, height: Height) -> Option<Hash> {
+        if self
+            .configuration_by_height(height)?
+            .domain_separated_signatures
+        {
+            self.configs_actual_from()
+                .get(0)
+                .map(|cfg_ref| *cfg_ref.cfg_hash())
+        } else {
+            None
+        }
+    }
+
+    /// Verifies the proof of entries of an index against the state hash of the stored
+    /// block and returns the proven entries. The index is identified by the service
+    /// identifier and the table index declared by the proof, which are returned together
+    /// with the entries and should be checked by the caller.
+    pub fn verify_index_proof(&self, proof: IndexProof) -> Result<VerifiedEntries, Error> {
+        let block = proof.block_proof.block;
+        self.ensure_known(&block)?;
+
+        let to_table = proof.to_table.check()?;
+        ensure!(
+            to_table.root_hash() == *block.state_hash(),
+            "The proof of the index does not match the state hash of the block at height {}",
+            block.height()
+        );
+        let table_key = Blockchain::service_table_unique_key(proof.service_id, proof.table_idx);
+        let table_hash = to_table
+            .entries()
+            .find(|&(key, _)| *key == table_key)
+            .map(|(_, &hash)| hash)
+            .ok_or_else(|| {
+                format_err!(
+                    "The proof does not contain the table {} of service {}",
+                    proof.table_idx,
+                    proof.service_id
+                )
+            })?;
+        let to_entry = proof.to_entry.check()?;
+        ensure!(
+            to_entry.root_hash() == table_hash,
+            "The proof of the entries does not match the proven index"
+        );
+
+        Ok(VerifiedEntries {
+            height: block.height(),
+            block_hash: block.hash(),
+            service_id: proof.service_id,
+            table_idx: proof.table_idx,
+            entries: to_entry
+                .all_entries()
+                .map(|(key, value)| VerifiedEntry {
+                    key: *key.hash(),
+                    value: value.cloned(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Checks that the block is stored in the light chain.
+    fn ensure_known(&self, block: &Block) -> Result<(), Error> {
+        let known = self.block_hashes_by_height().get(block.height().0);
+        ensure!(
+            known == Some(block.hash()),
+            "The block at height {} is not known to the light chain",
+            block.height()
+        );
+        Ok(())
+    }
+
+    fn push_block(&mut self, proof: BlockProof) {
+        let block_hash = proof.block.hash();
+        self.blocks().put(&block_hash, proof.block);
+        self.precommits(&block_hash).extend(proof.precommits);
+        if let Some(certificate) = proof.certificate {
+            self.certificates().put(&block_hash, certificate);
+        }
+        self.block_hashes_by_height().push(block_hash);
+    }
+
+    fn push_configuration(&mut self, config: StoredConfiguration) {
+        let cfg_hash = config.hash();
+        let cfg_ref = ConfigReference::new(config.actual_from, &cfg_hash);
+        self.configs().put(&cfg_hash, config);
+        self.configs_actual_from().push(cfg_ref);
+    }
+}
+
+/// Chain of the block headers verified without executing the transactions.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Clone)]
+pub struct LightChain {
+    db: Arc<dyn Database>,
+}
+
+impl LightChain {
+    /// Opens the light chain stored in the database or initializes it with the given
+    /// genesis block and configuration, which must be obtained from a trusted source.
+    ///
+    /// An error is returned if the database contains a light chain with another
+    /// genesis block.
+    pub fn new<D: Into<Arc<dyn Database>>>(
+        db: D,
+        genesis_block: Block,
+        genesis_config: StoredConfiguration,
+    ) -> Result<Self, Error> {
+        let chain = Self { db: db.into() };
+
+        let snapshot = chain.snapshot();
+        if let Some(genesis_hash) = LightSchema::new(&snapshot).block_hashes_by_height().get(0) {
+            ensure!(
+                genesis_hash == genesis_block.hash(),
+                "The light chain has another genesis block {:?}",
+                genesis_hash
+            );
+            return Ok(chain);
+        }
+
+        ensure!(
+            genesis_block.height() == Height::zero() && *genesis_block.prev_hash() == Hash::zero(),
+            "The genesis block must be the first block of the chain"
+        );
+        ensure!(
+            genesis_config.actual_from == Height::zero(),
+            "The genesis configuration must be actual from the genesis block"
+        );
+
+        let fork = chain.db.fork();
+        {
+            let mut schema = LightSchema::new(&fork);
+            schema.push_block(BlockProof {
+                block: genesis_block,
+                precommits: Vec::new(),
+                certificate: None,
+            });
+            schema.push_configuration(genesis_config);
+        }
+        chain.db.merge(fork.into_patch())?;
+        Ok(chain)
+    }
+
+    /// Creates a read-only snapshot of the light chain.
+    pub fn snapshot(&self) -> Box<dyn Snapshot> {
+        self.db.snapshot()
+    }
+
+    /// Returns the height of the latest stored block.
+    pub fn height(&self) -> Height {
+        LightSchema::new(&self.snapshot())
+            .height()
+            .expect("The light chain is not initialized")
+    }
+
+    /// Appends the block following the latest stored one.
+    ///
+    /// The block must be linked to the latest stored block and signed by
+    /// the supermajority of the validators from the configuration actual at its height.
+    /// The signatures of the precommits are verified in a batch. If the validators use
+    /// threshold signatures, the block may be proven by its certificate instead of
+    /// the precommits.
+    pub fn add_block(&self, mut proof: BlockProof) -> Result<(), Error> {
+        let snapshot = self.snapshot();
+        let schema = LightSchema::new(&snapshot);
+        let height = schema
+            .height()
+            .expect("The light chain is not initialized")
+            .next();
+        let last_hash = schema
+            .block_hashes_by_height()
+            .last()
+            .expect("The light chain is not initialized");
+
+        let block = &proof.block;
+        ensure!(
+            block.height() == height,
+            "Expected the block at height {}, got the block at height {}",
+            height,
+            block.height()
+        );
+        ensure!(
+            *block.prev_hash() == last_hash,
+            "The block at height {} is not linked to the previous one",
+            height
+        );
+        let config = schema
+            .configuration_by_height(height)
+            .expect("The light chain is not initialized");
+        if !Self::verify_certificate(&config, &proof) {
+            verify_precommits(
+                &config,
+                schema.signature_network_id(height),
+                height,
+                &proof.block.hash(),
+                proof.precommits.iter().cloned(),
+            )?;
+            // An invalid certificate is not stored.
+            proof.certificate = None;
+        }
+
+        let fork = self.db.fork();
+        LightSchema::new(&fork).push_block(proof);
+        self.db.merge(fork.into_patch())?;
+        Ok(())
+    }
+
+    /// Checks whether the block is proven by its certificate.
+    #[cfg(feature = "threshold-precommits")]
+    fn verify_certificate(config: &StoredConfiguration, proof: &BlockProof) -> bool {
+        match config.consensus.threshold_keys {
+            Some(ref keys) if proof.certificate.is_some() => keys
+                .key_set()
+                .map(|key_set| proof.verify_certificate(&key_set))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// Checks whether the block is proven by its certificate.
+    #[cfg(not(feature = "threshold-precommits"))]
+    fn verify_certificate(_config: &StoredConfiguration, _proof: &BlockProof) -> bool {
+        false
+    }
+
+    /// Accepts the configuration committed to the state of a stored block.
+    ///
+    /// The configuration must follow the latest accepted one and become actual after
+    /// the latest stored block. Returns `false` if the configuration has already been
+    /// accepted.
+    pub fn add_configuration(&self, proof: ConfigProof) -> Result<bool, Error> {
+        let snapshot = self.snapshot();
+        let schema = LightSchema::new(&snapshot);
+        let block = &proof.block_proof.block;
+        schema.ensure_known(block)?;
+
+        let to_table = proof.to_table.check()?;
+        ensure!(
+            to_table.root_hash() == *block.state_hash(),
+            "The proof of the configurations table does not match the state hash \
+             of the block at height {}",
+            block.height()
+        );
+        let configs_key = Blockchain::service_table_unique_key(CORE_SERVICE, 0);
+        let configs_hash = to_table
+            .entries()
+            .find(|&(key, _)| *key == configs_key)
+            .map(|(_, &hash)| hash)
+            .ok_or_else(|| format_err!("The proof does not contain the configurations table"))?;
+        let to_config = proof.to_config.check()?;
+        ensure!(
+            to_config.root_hash() == configs_hash,
+            "The proof of the configuration does not match the configurations table"
+        );
+        let (cfg_hash, config) = to_config
+            .entries()
+            .next()
+            .ok_or_else(|| format_err!("The proof does not contain a configuration"))?;
+        ensure!(
+            config.hash() == *cfg_hash,
+            "The configuration does not match its hash"
+        );
+
+        if schema.configs().contains(cfg_hash) {
+            return Ok(false);
+        }
+        let last_cfg = schema
+            .configs_actual_from()
+            .last()
+            .expect("The light chain is not initialized");
+        ensure!(
+            config.previous_cfg_hash == *last_cfg.cfg_hash(),
+            "The configuration does not follow the latest accepted one"
+        );
+        ensure!(
+            Some(config.actual_from) > schema.height()
+                && config.actual_from > last_cfg.actual_from(),
+            "The configuration actual from height {} affects the stored blocks",
+            config.actual_from
+        );
+
+        let fork = self.db.fork();
+        LightSchema::new(&fork).push_configuration(config.clone());
+        self.db.merge(fork.into_patch())?;
+        Ok(true)
+    }
+
+    /// Verifies the proof of entries of an index against the state hash of the stored
+    /// block and returns the proven entries, like `LightSchema::verify_index_proof`.
+    pub fn verify_index_proof(&self, proof: IndexProof) -> Result<VerifiedEntries, Error> {
+        LightSchema::new(&self.snapshot()).verify_index_proof(proof)
+    }
+
+    /// Appends the blocks committed by the source after the latest stored one and accepts
+    /// the latest configuration committed by the source. Returns the number of the appended
+    /// blocks.
+    ///
+    /// The configuration is requested for the latest block of the source, so it is accepted
+    /// only if no blocks are committed by the source in the meantime. If a block is signed by
+    /// the validators of a configuration which has not been accepted yet, the configuration
+    /// is requested for the latest stored block. This requires the source to keep the state
+    /// of the previous blocks, so the chain should be synchronized often enough to accept
+    /// the configurations before they become actual.
+    pub fn sync<S: LightSource + ?Sized>(&self, source: &S) -> Result<u64, Error> {
+        let mut added = 0;
+        while let Some(proof) = source.block_proof(self.height().next())? {
+            if let Err(e) = self.add_block(proof.clone()) {
+                let config = source.config_proof(Some(self.height()));
+                match config.and_then(|config| self.add_configuration(config)) {
+                    Ok(true) => self.add_block(proof)?,
+                    _ => return Err(e),
+                }
+            }
+            added += 1;
+        }
+
+        let config = source.config_proof(None)?;
+        if self.contains_block(&config.block_proof.block) {
+            self.add_configuration(config)?;
+        }
+        Ok(added)
+    }
+
+    /// Checks whether the block is stored in the light chain.
+    fn contains_block(&self, block: &Block) -> bool {
+        LightSchema::new(&self.snapshot())
+            .ensure_known(block)
+            .is_ok()
+    }
+}
+
+impl fmt::Debug for LightChain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LightChain").finish()
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum_merkledb::{Fork, IndexAccess, MapIndex};
+
+use std::collections::HashSet;
+
+use super::{LeaderSelector, Schema, StoredConfiguration};
+use crate::crypto::PublicKey;
+use crate::helpers::{Round, ValidatorId};
+use crate::messages::{Consensus as ConsensusMessage, Message, Precommit, Signed};
+use crate::node::LivenessConfig;
+use crate::proto;
+
+/// Numbers of the blocks and the messages missed by a validator over the sliding window
+/// of the latest blocks.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::ValidatorLiveness", crate = "crate")]
+pub struct ValidatorLiveness {
+    /// Number of the blocks in the window committed while the node was a validator.
+    pub blocks: u64,
+    /// Number of the blocks without a precommit of the validator.
+    pub missed_precommits: u64,
+    /// Number of the rounds in which the validator was the leader and a propose was expected,
+    /// but its proposal has not been committed. Rounds in which the validators are locked
+    /// on a propose of a previous round are not counted.
+    pub missed_proposals: u64,
+}
+
+impl ValidatorLiveness {
+    /// Returns the share of the blocks precommitted by the validator, in percent.
+    pub fn uptime_percent(&self) -> u64 {
+        if self.blocks == 0 {
+            return 100;
+        }
+        (self.blocks - self.missed_precommits) * 100 / self.blocks
+    }
+}
+
+/// Validators which have missed messages at a height.
+#[derive(Clone, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::LivenessRecord", crate = "crate")]
+pub(crate) struct LivenessRecord {
+    pub validators: Vec<PublicKey>,
+    pub missed_precommits: Vec<PublicKey>,
+    pub missed_proposals: Vec<PublicKey>,
+}
+
+/// Counts the messages missed by the validators at the height of the latest block
+/// in the fork and removes the height leaving the window from the counters.
+pub(crate) fn record_liveness(
+    fork: &Fork,
+    precommits: &[Signed<Precommit>],
+    config: LivenessConfig,
+) {
+    let schema = Schema::new(fork);
+    let block = schema.last_block();
+    let height = block.height();
+    let stored_config = schema.configuration_by_height(height);
+    let keys = stored_config
+        .validator_keys
+        .iter()
+        .map(|keys| keys.consensus_key)
+        .collect::<Vec<_>>();
+
+    let signed = precommits
+        .iter()
+        .map(|precommit| precommit.validator())
+        .collect::<HashSet<_>>();
+    let missed_precommits = keys
+        .iter()
+        .enumerate()
+        .filter(|(id, _)| !signed.contains(&ValidatorId(*id as u16)))
+        .map(|(_, key)| *key)
+        .collect::<Vec<_>>();
+    let missed_proposals = Round::first()
+        .iter_to(first_unexpected_round(&schema, precommits, &stored_config))
+        .map(|round| {
+            let leader = stored_config.consensus.leader_selection.leader(
+                height,
+                round,
+                block.prev_hash(),
+                keys.len(),
+            );
+            keys[leader.0 as usize]
+        })
+        .collect::<Vec<_>>();
+
+    let mut liveness = schema.validator_liveness();
+    let record = LivenessRecord {
+        validators: keys,
+        missed_precommits,
+        missed_proposals,
+    };
+    update_liveness(&mut liveness, &record, |counter, delta| counter + delta);
+    let mut history = schema.liveness_history();
+    let height: u64 = height.into();
+    if height >= config.window {
+        let expired = height - config.window;
+        if let Some(expired_record) = history.get(&expired) {
+            update_liveness(&mut liveness, &expired_record, u64::saturating_sub);
+            history.remove(&expired);
+        }
+    }
+    history.put(&height, record);
+}
+
+/// Returns the first round of the height in which a propose was not expected from the leader.
+///
+/// Once the validators lock on a propose, the leaders of the following rounds do not propose,
+/// so a propose is expected only in the rounds preceding the round of the committed propose.
+/// Leaders skip empty proposes while the node is idle, so no propose is expected if the idle
+/// interval is configured. The committed propose may be unknown to the node, e.g., if the block
+/// is received from a peer; then no propose is considered missed either.
+fn first_unexpected_round<T: IndexAccess>(
+    schema: &Schema<T>,
+    precommits: &[Signed<Precommit>],
+    stored_config: &StoredConfiguration,
+) -> Round {
+    if stored_config.consensus.max_idle_interval.is_some() {
+        return Round::first();
+    }
+    let propose_hash = match precommits.first() {
+        Some(precommit) => *precommit.propose_hash(),
+        None => return Round::first(),
+    };
+    schema
+        .consensus_messages_cache()
+        .iter()
+        .find_map(|message| match message {
+            Message::Consensus(ConsensusMessage::Propose(ref propose))
+                if propose.hash() == propose_hash =>
+            {
+                Some(propose.round())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(Round::first)
+}
+
+fn update_liveness<F>(
+    liveness: &mut MapIndex<&Fork, PublicKey, ValidatorLiveness>,
+    record: &LivenessRecord,
+    update: F,
+) where
+    F: Fn(u64, u64) -> u64,
+{
+    let mut entries = record
+        .validators
+        .iter()
+        .map(|key| (*key, liveness.get(key).unwrap_or_default()))
+        .collect::<Vec<_>>();
+    for (key, entry) in &mut entries {
+        let key = *key;
+        entry.blocks = update(entry.blocks, 1);
+        let missed = |keys: &[PublicKey]| keys.iter().filter(|k| **k == key).count() as u64;
+        entry.missed_precommits =
+            update(entry.missed_precommits, missed(&record.missed_precommits));
+        entry.missed_proposals = update(entry.missed_proposals, missed(&record.missed_proposals));
+    }
+    for (key, entry) in entries {
+        if entry.blocks == 0 {
+            liveness.remove(&key);
+        } else {
+            liveness.put(&key, entry);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Versioned migrations of the service data.
+
+use std::fmt;
+
+use exonum_merkledb::Fork;
+
+/// A data migration of a service.
+///
+/// Each migration upgrades the service data to the specified version. Migrations
+/// are declared by the [`migrations`] method of the service and are applied once
+/// in the order of their versions; the version of the service data is recorded
+/// in the blockchain state.
+///
+/// [`migrations`]: trait.Service.html#method.migrations
+///
+/// # Examples
+///
+/// ```
+/// use exonum::blockchain::Migration;
+/// use exonum_merkledb::{Entry, Fork, MapIndex};
+///
+/// // Moves the balances from a map to a new map keyed by the wallet name.
+/// fn rename_balances(fork: &Fork) -> Result<(), failure::Error> {
+///     let mut old_balances: MapIndex<_, u64, u64> = MapIndex::new("wallets.balances", fork);
+///     let mut new_balances = MapIndex::new("wallets.balances_by_name", fork);
+///     for (id, balance) in old_balances.iter() {
+///         new_balances.put(&format!("wallet-{}", id), balance);
+///     }
+///     old_balances.clear();
+///     Ok(())
+/// }
+///
+/// let migrations = vec![
+///     Migration::new(1, "Key balances by the wallet name", rename_balances),
+///     Migration::new(2, "Add the total supply", |fork: &Fork| {
+///         let mut total_supply = Entry::new("wallets.total_supply", fork);
+///         total_supply.set(1_000_u64);
+///         Ok(())
+///     }),
+/// ];
+/// ```
+pub struct Migration {
+    version: u32,
+    description: String,
+    migrate: Box<dyn Fn(&Fork) -> Result<(), failure::Error>>,
+}
+
+impl Migration {
+    /// Creates a migration upgrading the service data to the given version.
+    /// Versions of the migrations of a service should be positive and strictly increasing.
+    pub fn new<S, F>(version: u32, description: S, migrate: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&Fork) -> Result<(), failure::Error> + 'static,
+    {
+        Self {
+            version,
+            description: description.into(),
+            migrate: Box::new(migrate),
+        }
+    }
+
+    /// Returns the version of the service data after the migration.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the human-readable description of the migration.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub(crate) fn apply(&self, fork: &Fork) -> Result<(), failure::Error> {
+        (self.migrate)(fork)
+    }
+}
+
+impl fmt::Debug for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Migration")
+            .field("version", &self.version)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+/// Information about an applied migration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationInfo {
+    /// Name of the migrated service.
+    pub service_name: String,
+    /// Version of the service data after the migration.
+    pub version: u32,
+    /// Description of the migration.
+    pub description: String,
+}
+
+impl MigrationInfo {
+    pub(crate) fn new(service_name: &str, migration: &Migration) -> Self {
+        Self {
+            service_name: service_name.to_owned(),
+            version: migration.version,
+            description: migration.description.clone(),
+        }
+    }
+}
+
+/// Returns the latest data version declared by the migrations, checking that
+/// the versions are positive and strictly increasing.
+pub(crate) fn latest_version(
+    service_name: &str,
+    migrations: &[Migration],
+) -> Result<u32, failure::Error> {
+    let mut latest = 0;
+    for migration in migrations {
+        ensure!(
+            migration.version > latest,
+            "Migrations of service {} are not ordered by version: {} follows {}",
+            service_name,
+            migration.version,
+            latest
+        );
+        latest = migration.version;
+    }
+    Ok(latest)
+}
@@ -33,37 +33,74 @@
 
 pub use self::{
     block::{Block, BlockProof},
-    config::{ConsensusConfig, StoredConfiguration, ValidatorKeys},
+    config::{
+        ConsensusConfig, LeaderSelection, StorageQuotaConfig, StoredConfiguration, ThresholdKeys,
+        TimeoutPolicy, TxOrdering, ValidatorKeys,
+    },
+    descriptor::ServiceDescriptor,
+    diagnostics::{HeightDiagnostics, ProposeDiagnostics},
+    evidence::DoubleSignEvidence,
     genesis::GenesisConfig,
+    leader::{LeaderSelector, Randomized, RoundRobin, Weighted},
+    liveness::ValidatorLiveness,
+    migration::{Migration, MigrationInfo},
+    ordering::{AuthorRoundRobin, Fifo, PriorityClasses, TxCandidate, TxOrderingPolicy},
+    peer_stats::{PeerEvent, PeerEventKind, PeerStats},
+    rejected::{RejectedTransaction, RejectionReason},
     schema::{Schema, TxLocation},
-    service::{Service, ServiceContext, SharedNodeState},
+    service::{BlockCommitted, Service, ServiceContext, SharedNodeState},
     transaction::{
-        ExecutionError, ExecutionResult, Transaction, TransactionContext, TransactionError,
-        TransactionErrorType, TransactionMessage, TransactionResult, TransactionSet,
+        ExecutionError, ExecutionResult, StaleNonce, Transaction, TransactionContext,
+        TransactionError, TransactionErrorType, TransactionMessage, TransactionResult,
+        TransactionSet,
     },
 };
 
+pub mod archive;
 pub mod config;
+pub mod light;
+pub mod replay;
+pub mod snapshot;
 
 use byteorder::{ByteOrder, LittleEndian};
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     fmt, iter, mem, panic,
     sync::Arc,
 };
 
-use crate::crypto::{self, CryptoHash, Hash, PublicKey, SecretKey};
+use self::{execution::ExecutionRules, schema::StateChange};
+use crate::crypto::{self, CryptoHash, Hash, HashAlgorithm, PublicKey, SecretKey};
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::messages::{Connect, Message, Precommit, ProtocolMessage, RawTransaction, Signed};
-use crate::node::ApiSender;
+use crate::messages::{
+    Connect, Message, MultisigTransaction, Precommit, ProtocolMessage, RawTransaction,
+    ServiceTransaction, Signed, MULTISIG_SERVICE_ID,
+};
+use crate::node::{
+    ApiSender, LivenessConfig, ParallelExecutionConfig, PruningConfig, Signer, StateHistoryConfig,
+};
 use exonum_merkledb::{
-    self, Database, Error as StorageError, Fork, IndexAccess, MapIndex, ObjectHash, Patch,
-    Result as StorageResult, Snapshot,
+    self, Database, Error as StorageError, Fork, IndexAccess, MapIndex, MeteredDB, ObjectHash,
+    Patch, Result as StorageResult, Snapshot, StorageMetrics, View,
 };
 
 mod block;
+#[cfg(feature = "threshold-precommits")]
+pub(crate) mod certificate;
+mod descriptor;
+mod diagnostics;
+mod evidence;
+mod execution;
 mod genesis;
+mod integrity;
+mod leader;
+mod liveness;
+mod migration;
+mod ordering;
+mod peer_stats;
+mod rejected;
+mod scheduler;
 mod schema;
 mod service;
 #[macro_use]
@@ -73,6 +110,8 @@ mod tests;
 
 /// Id of core service table family.
 pub const CORE_SERVICE: u16 = 0;
+/// The maximum number of blocks pruned on a single commit.
+pub const PRUNING_BATCH_SIZE: u64 = 100;
 
 /// Exonum blockchain instance with a certain services set and data storage.
 ///
@@ -80,10 +119,17 @@ pub const CORE_SERVICE: u16 = 0;
 /// into a single network.
 pub struct Blockchain {
     db: Arc<dyn Database>,
+    storage_metrics: StorageMetrics,
     service_map: Arc<HashMap<u16, Box<dyn Service>>>,
     #[doc(hidden)]
     pub service_keypair: (PublicKey, SecretKey),
     pub(crate) api_sender: ApiSender,
+    track_write_conflicts: bool,
+    state_history: Option<StateHistoryConfig>,
+    liveness: Option<LivenessConfig>,
+    parallel_execution: Option<ParallelExecutionConfig>,
+    tx_scheduler: Option<Arc<dyn TxOrderingPolicy + Send + Sync>>,
+    service_signer: Option<Arc<dyn Signer>>,
 }
 
 impl Blockchain {
@@ -98,6 +144,12 @@ impl Blockchain {
         let mut service_map = HashMap::new();
         for service in services {
             let id = service.service_id();
+            if id == MULTISIG_SERVICE_ID {
+                panic!(
+                    "Service id={} is reserved for multisignature transactions, please change it.",
+                    id
+                );
+            }
             if service_map.contains_key(&id) {
                 panic!(
                     "Services have already contain service with id={}, please change it.",
@@ -107,11 +159,20 @@ impl Blockchain {
             service_map.insert(id, service);
         }
 
+        let db = MeteredDB::new(storage);
+        let storage_metrics = db.metrics().clone();
         Self {
-            db: storage.into(),
+            db: Arc::new(db),
+            storage_metrics,
             service_map: Arc::new(service_map),
             service_keypair: (service_public_key, service_secret_key),
             api_sender,
+            track_write_conflicts: false,
+            state_history: None,
+            liveness: None,
+            parallel_execution: None,
+            tx_scheduler: None,
+            service_signer: None,
         }
     }
 
@@ -124,17 +185,157 @@ impl Blockchain {
         }
     }
 
+    /// Enables or disables tracking of write conflicts during the block creation.
+    ///
+    /// With tracking enabled, the keys changed by more than one transaction or
+    /// `before_commit` handler within a block are logged as warnings. Tracking is intended
+    /// for debugging services and is disabled by default.
+    pub fn set_write_conflicts_tracking(&mut self, enabled: bool) {
+        self.track_write_conflicts = enabled;
+    }
+
+    /// Sets the number of the latest heights preceding the current one whose state
+    /// can be read with [`snapshot_at`]. Only the latest state is kept by default.
+    ///
+    /// [`snapshot_at`]: #method.snapshot_at
+    pub fn set_state_history(&mut self, config: Option<StateHistoryConfig>) {
+        self.state_history = config;
+    }
+
+    /// Enables or disables tracking of the messages missed by the validators, see
+    /// [`Schema::validator_liveness`]. Liveness is not tracked by default.
+    ///
+    /// [`Schema::validator_liveness`]: struct.Schema.html#method.validator_liveness
+    pub fn set_liveness(&mut self, config: Option<LivenessConfig>) {
+        self.liveness = config;
+    }
+
+    pub(crate) fn liveness(&self) -> Option<LivenessConfig> {
+        self.liveness
+    }
+
+    /// Sets the number of the threads executing the transactions of a block if parallel
+    /// execution is enabled with the `parallel_execution` field of the consensus
+    /// configuration, see [`ParallelExecutionConfig`]. The transactions are executed
+    /// on the current thread by default. The number of the threads does not affect
+    /// the results of the transactions.
+    ///
+    /// [`ParallelExecutionConfig`]: ../node/struct.ParallelExecutionConfig.html
+    pub fn set_parallel_execution(&mut self, config: Option<ParallelExecutionConfig>) {
+        self.parallel_execution = config;
+    }
+
+    /// Sets the policy choosing the transactions of the blocks proposed by the node
+    /// instead of the one configured with the `tx_ordering` field of the consensus
+    /// configuration, see [`TxOrderingPolicy`] for the requirements to the policy.
+    /// The configured policy is used by default.
+    ///
+    /// [`TxOrderingPolicy`]: trait.TxOrderingPolicy.html
+    pub fn set_tx_scheduler(&mut self, scheduler: Option<Arc<dyn TxOrderingPolicy + Send + Sync>>) {
+        self.tx_scheduler = scheduler;
+    }
+
+    pub(crate) fn tx_scheduler(&self) -> Option<&(dyn TxOrderingPolicy + Send + Sync)> {
+        self.tx_scheduler.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Sets the signer of the transactions broadcast by the services instead of
+    /// the service secret key, for example, a `RemoteSigner`. The public key of
+    /// the signer must be equal to the service public key of the node.
+    pub fn set_service_signer(&mut self, signer: Option<Arc<dyn Signer>>) {
+        self.service_signer = signer;
+    }
+
+    /// Signs the transaction with the service key of the node.
+    pub(crate) fn sign_service_transaction<T>(
+        &self,
+        transaction: T,
+        service_id: u16,
+    ) -> Result<Signed<RawTransaction>, failure::Error>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        let network_id = self.network_id();
+        match self.service_signer {
+            Some(ref signer) => Message::sign_transaction_with_signer(
+                transaction,
+                service_id,
+                network_id,
+                &**signer,
+            ),
+            None => Ok(Message::sign_transaction_in_network(
+                transaction,
+                service_id,
+                network_id,
+                self.service_keypair.0,
+                &self.service_keypair.1,
+            )),
+        }
+    }
+
     /// Returns mapping from the service identifier (`u16`) to service (`Box<dyn Service>`) for
     /// all services.
     pub fn service_map(&self) -> &Arc<HashMap<u16, Box<dyn Service>>> {
         &self.service_map
     }
 
+    /// Returns the underlying database of the blockchain.
+    pub(crate) fn database(&self) -> &Arc<dyn Database> {
+        &self.db
+    }
+
+    /// Returns the counters of the operations with the blockchain storage.
+    pub fn storage_metrics(&self) -> &StorageMetrics {
+        &self.storage_metrics
+    }
+
     /// Creates a read-only snapshot of the current storage state.
     pub fn snapshot(&self) -> Box<dyn Snapshot> {
         self.db.snapshot()
     }
 
+    /// Creates a read-only snapshot of the storage state at the given height.
+    ///
+    /// The state is restored by reverting the changes of the later blocks, which are kept
+    /// only if the state history is enabled with [`set_state_history`]. Changes made
+    /// outside of the blocks, e.g., by pruning or caching the consensus messages,
+    /// are not reverted.
+    ///
+    /// Returns an error if the height is larger than the latest one or the state
+    /// at the height is not kept.
+    ///
+    /// [`set_state_history`]: #method.set_state_history
+    pub fn snapshot_at(&self, height: Height) -> Result<Box<dyn Snapshot>, failure::Error> {
+        let snapshot: Arc<dyn Snapshot> = Arc::from(self.snapshot());
+        let schema = Schema::new(&*snapshot);
+        let latest_height = schema.height();
+        ensure!(
+            height <= latest_height,
+            "The latest committed height is {}, requested {}",
+            latest_height,
+            height
+        );
+
+        let fork = Fork::from_snapshot(Arc::clone(&snapshot));
+        for block_height in (height.next().0..=latest_height.0).rev() {
+            let changes = schema.state_history(Height(block_height));
+            ensure!(
+                !changes.is_empty(),
+                "The state at height {} is not kept",
+                height
+            );
+            for change in changes.iter() {
+                let mut view = View::new(&fork, change.column_family.as_str());
+                if change.removed {
+                    view.remove(&change.key[..]);
+                } else {
+                    view.put(&change.key[..], change.value);
+                }
+            }
+        }
+        Ok(Box::new(fork.into_snapshot()))
+    }
+
     /// Creates a snapshot of the current storage state that can be later committed into the storage
     /// via the `merge` method.
     pub fn fork(&self) -> Fork {
@@ -147,7 +348,14 @@ impl Blockchain {
     ///
     /// - Blockchain has a service with the `service_id` of the given raw message.
     /// - Service can deserialize the given raw message.
+    ///
+    /// Multisignature envelopes are rejected, since their signatures cover the author
+    /// of the envelope; use `tx_from_signed` for them.
     pub fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+        ensure!(
+            raw.service_id() != MULTISIG_SERVICE_ID,
+            "Multisignature envelopes cannot be converted without the author"
+        );
         let service = self
             .service_map
             .get(&raw.service_id())
@@ -155,6 +363,89 @@ impl Blockchain {
         service.tx_from_raw(raw)
     }
 
+    /// Tries to create a `Transaction` object from the given signed message,
+    /// see `tx_from_raw`.
+    ///
+    /// The signatures of a multisignature envelope are verified against the author
+    /// of the message, and the wrapped transaction is converted by its service,
+    /// see `MultisigTransaction`.
+    pub fn tx_from_signed(
+        &self,
+        signed: &Signed<RawTransaction>,
+    ) -> Result<Box<dyn Transaction>, failure::Error> {
+        let raw = signed.payload();
+        if raw.service_id() == MULTISIG_SERVICE_ID {
+            let (transaction, _) =
+                MultisigTransaction::from_raw(raw)?.verify(&signed.author(), self.network_id())?;
+            self.tx_from_raw(transaction)
+        } else {
+            self.tx_from_raw(raw.clone())
+        }
+    }
+
+    /// Executes the given transaction against the current blockchain state without
+    /// committing the changes and returns the execution result. The transaction is not
+    /// added to the pool of unconfirmed transactions.
+    ///
+    /// Returns an error if the transaction cannot be converted into a `Transaction` object,
+    /// see [`tx_from_raw`](#method.tx_from_raw) for details.
+    pub fn dry_run_transaction(
+        &self,
+        raw: &Signed<RawTransaction>,
+    ) -> Result<TransactionResult, failure::Error> {
+        let snapshot: Arc<dyn Snapshot> = Arc::from(self.snapshot());
+        self.speculate(&snapshot, raw).map(|(result, _)| result)
+    }
+
+    /// Executes the given transaction speculatively in a child fork of the `snapshot`
+    /// and returns the execution result along with the fork. The fork contains the changes
+    /// of the transaction, unless its execution has failed.
+    ///
+    /// The snapshot is shared rather than copied, so the same state can be used for
+    /// any number of speculations. To execute several transactions one after another,
+    /// the returned fork can be frozen with [`Fork::into_snapshot`] and used as
+    /// the snapshot for the next transaction. Neither the transactions nor their changes
+    /// are added to the blockchain.
+    ///
+    /// Returns an error if the transaction cannot be converted into a `Transaction` object,
+    /// see [`tx_from_raw`](#method.tx_from_raw) for details.
+    ///
+    /// [`Fork::into_snapshot`]: ../../exonum_merkledb/struct.Fork.html#method.into_snapshot
+    pub fn speculate(
+        &self,
+        snapshot: &Arc<dyn Snapshot>,
+        raw: &Signed<RawTransaction>,
+    ) -> Result<(TransactionResult, Fork), failure::Error> {
+        let service_name = self
+            .service_map
+            .get(&raw.executing_service_id())
+            .ok_or_else(|| format_err!("Service not found."))?
+            .service_name();
+        let tx = self.tx_from_signed(&raw)?;
+
+        let mut fork = Fork::from_snapshot(Arc::clone(snapshot));
+        let catch_result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let context = TransactionContext::new(&fork, service_name, raw);
+            tx.execute(context)
+        }));
+        let result = TransactionResult(match catch_result {
+            Ok(execution_result) => execution_result.map_err(TransactionError::from),
+            Err(err) => {
+                if err.is::<StorageError>() {
+                    // Continue panic unwind if the reason is StorageError.
+                    panic::resume_unwind(err);
+                }
+                Err(TransactionError::from_panic(&err))
+            }
+        });
+        if result.0.is_ok() {
+            fork.flush();
+        } else {
+            fork.rollback();
+        }
+        Ok((result, fork))
+    }
+
     /// Commits changes from the patch to the blockchain storage.
     /// See [`Fork`](../../exonum_merkledb/struct.Fork.html) for details.
     pub fn merge(&mut self, patch: Patch) -> StorageResult<()> {
@@ -178,33 +469,237 @@ impl Blockchain {
         Schema::new(&self.snapshot()).last_block()
     }
 
+    /// Returns the identifier of the network covered by the signatures of the messages
+    /// according to the actual configuration, see `Schema::actual_network_id`.
+    pub fn network_id(&self) -> Option<Hash> {
+        Schema::new(&self.snapshot()).actual_network_id()
+    }
+
     /// Returns the transactions pool size.
     pub fn pool_size(&self) -> u64 {
         Schema::new(&self.snapshot()).transactions_pool_len()
     }
 
     /// Creates and commits the genesis block with the given genesis configuration
-    /// if the blockchain has not been initialized.
+    /// if the blockchain has not been initialized. Then, stores the protobuf descriptors
+    /// of the services and selects the hash function of the blockchain for the process.
     ///
     /// # Panics
     ///
     /// * If the genesis block was not committed.
     /// * If storage version is not specified or not supported.
     pub fn initialize(&mut self, cfg: GenesisConfig) -> Result<(), failure::Error> {
-        let has_genesis_block = !Schema::new(&self.snapshot())
-            .block_hashes_by_height()
-            .is_empty();
-        if !has_genesis_block {
+        let snapshot = self.snapshot();
+        let schema = Schema::new(&snapshot);
+        if schema.block_hashes_by_height().is_empty() {
+            Self::select_hash_algorithm(cfg.hash_algorithm)?;
             self.create_genesis_block(cfg)?;
+        } else {
+            let hash_algorithm = schema.actual_configuration().hash_algorithm;
+            if hash_algorithm != cfg.hash_algorithm {
+                warn!(
+                    "Hash algorithm {} from the genesis configuration is ignored, \
+                     the blockchain uses {}",
+                    cfg.hash_algorithm, hash_algorithm
+                );
+            }
+            Self::select_hash_algorithm(hash_algorithm)?;
         }
+        self.store_service_descriptors()
+    }
+
+    /// Selects the hash function of the blockchain for the whole process.
+    fn select_hash_algorithm(hash_algorithm: HashAlgorithm) -> Result<(), failure::Error> {
+        ensure!(
+            hash_algorithm.is_supported(),
+            "Hash algorithm {} is not supported, enable the `blake2b-hashing` feature",
+            hash_algorithm
+        );
+        crypto::set_hash_algorithm(hash_algorithm);
         Ok(())
     }
 
+    /// Stores the protobuf descriptors of the services, replacing the outdated ones.
+    fn store_service_descriptors(&mut self) -> Result<(), failure::Error> {
+        let fork = self.fork();
+        {
+            let mut descriptors = Schema::new(&fork).service_descriptors();
+            for (&service_id, service) in self.service_map.iter() {
+                if let Some(descriptor) = service.protobuf_descriptor() {
+                    if descriptors.get(&service_id).as_ref() != Some(&descriptor) {
+                        descriptors.put(&service_id, descriptor);
+                    }
+                }
+            }
+        }
+        self.merge(fork.into_patch())?;
+        Ok(())
+    }
+
+    /// Performs the data migrations of the services declared after the recorded versions
+    /// of their data and returns information about them. Services are migrated in the order
+    /// of their identifiers, and the changes of the migrations are discarded.
+    ///
+    /// This allows checking which migrations are pending and whether they succeed
+    /// without modifying the storage. The migrations are applied by the nodes at the height
+    /// specified by `ConsensusConfig::service_data_versions`.
+    pub fn check_migrations(&self) -> Result<Vec<MigrationInfo>, failure::Error> {
+        let fork = self.fork();
+        if Schema::new(&fork).block_hashes_by_height().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut service_ids: Vec<_> = self.service_map.keys().collect();
+        service_ids.sort();
+        let mut pending = Vec::new();
+        for id in service_ids {
+            let service = &self.service_map[id];
+            let name = service.service_name();
+            let migrations = service.migrations();
+            migration::latest_version(name, &migrations)?;
+
+            let data_version = Schema::new(&fork)
+                .service_data_versions()
+                .get(name)
+                .unwrap_or_default();
+            for migration in migrations.iter().filter(|m| m.version() > data_version) {
+                migration.apply(&fork).map_err(|e| {
+                    format_err!(
+                        "Migration of service {} to version {} failed: {}",
+                        name,
+                        migration.version(),
+                        e
+                    )
+                })?;
+                pending.push(MigrationInfo::new(name, migration));
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Applies the pending data migrations of the services up to the versions required
+    /// by the consensus configuration of the block. Services are migrated in the order
+    /// of their identifiers. The changes of a failed migration are rolled back, and
+    /// the migration is retried in the next block. Returns `true` if the data of any
+    /// service has been migrated.
+    ///
+    /// # Panics
+    ///
+    /// If a required version of the service data is not declared by the service, since
+    /// the node cannot execute the block in the same way as the upgraded nodes.
+    fn migrate_service_data(&self, rules: &mut ExecutionRules, fork: &mut Fork) -> bool {
+        let mut migrated = false;
+        let mut service_ids: Vec<_> = self.service_map.keys().collect();
+        service_ids.sort();
+        for id in service_ids {
+            let service = &self.service_map[id];
+            let name = service.service_name();
+            let required_version = match rules.service_data_versions.get(name) {
+                Some(version) => *version,
+                None => continue,
+            };
+            let data_version = Schema::new(&*fork)
+                .service_data_versions()
+                .get(name)
+                .unwrap_or_default();
+            if data_version >= required_version {
+                continue;
+            }
+
+            let migrations = service.migrations();
+            match migration::latest_version(name, &migrations) {
+                Ok(latest_version) if latest_version >= required_version => {}
+                Ok(_) => panic!(
+                    "Version {} of the data of service {} required by the consensus \
+                     configuration is not supported, the node must be upgraded",
+                    required_version, name
+                ),
+                Err(e) => panic!("Unable to migrate the data of service {}: {}", name, e),
+            }
+
+            let pending = migrations
+                .iter()
+                .filter(|m| m.version() > data_version && m.version() <= required_version);
+            for migration in pending {
+                if rules.enforce_namespaces {
+                    restrict_to_service_namespaces(service.as_ref(), fork);
+                }
+                let result = {
+                    let fork = &*fork;
+                    panic::catch_unwind(panic::AssertUnwindSafe(|| migration.apply(fork)))
+                };
+                fork.lift_namespace_restriction();
+
+                let error = match result {
+                    Ok(Ok(())) => {
+                        info!(
+                            "Migrated data of service {} to version {}: {}",
+                            name,
+                            migration.version(),
+                            migration.description()
+                        );
+                        Schema::new(&*fork)
+                            .service_data_versions()
+                            .put(&name.to_owned(), migration.version());
+                        rules.record_changes(fork);
+                        fork.flush_as(&format!(
+                            "migration of service {} to version {}",
+                            name,
+                            migration.version()
+                        ));
+                        migrated = true;
+                        continue;
+                    }
+                    Ok(Err(e)) => e.to_string(),
+                    Err(err) => {
+                        if err.is::<StorageError>() {
+                            // Continue panic unwind if the reason is StorageError.
+                            panic::resume_unwind(err);
+                        }
+                        format!("{:?}", err)
+                    }
+                };
+                fork.rollback();
+                error!(
+                    "Migration of service {} to version {} failed, retrying in the next \
+                     block: {}",
+                    name,
+                    migration.version(),
+                    error
+                );
+                break;
+            }
+        }
+        migrated
+    }
+
+    /// Verifies the integrity of the stored data: the latest `blocks` blocks must be linked
+    /// to the previous ones and match the transaction roots in their headers, and the root
+    /// hashes of the core and service tables must match the state hash of the latest block.
+    ///
+    /// Returns an error describing the first detected inconsistency.
+    pub fn check_integrity(&self, blocks: u64) -> Result<(), failure::Error> {
+        let snapshot = self.snapshot();
+        let schema = Schema::new(&snapshot);
+        if schema.block_hashes_by_height().is_empty() {
+            return Ok(());
+        }
+
+        let next_height = schema.height().next();
+        let from = Height(next_height.0.saturating_sub(blocks));
+        integrity::verify_blocks(&schema, from)?;
+        integrity::verify_state_hash(self, &*snapshot)
+    }
+
     /// Creates and commits the genesis block with the given genesis configuration.
     fn create_genesis_block(&mut self, cfg: GenesisConfig) -> Result<(), failure::Error> {
         let mut config_propose = StoredConfiguration {
             previous_cfg_hash: Hash::zero(),
             actual_from: Height::zero(),
+            hash_algorithm: cfg.hash_algorithm,
+            domain_separated_signatures: cfg.domain_separated_signatures,
+            canonical_encoding: cfg.canonical_encoding,
+            secp256k1_signatures: cfg.secp256k1_signatures,
             validator_keys: cfg.validator_keys,
             consensus: cfg.consensus,
             services: BTreeMap::new(),
@@ -216,6 +711,13 @@ impl Blockchain {
             for (_, service) in self.service_map.iter() {
                 let cfg = service.initialize(&fork);
                 let name = service.service_name();
+                // The initial state of the service corresponds to its latest data version.
+                let data_version = migration::latest_version(name, &service.migrations())?;
+                if data_version > 0 {
+                    Schema::new(&fork)
+                        .service_data_versions()
+                        .put(&name.to_owned(), data_version);
+                }
                 if config_propose.services.contains_key(name) {
                     panic!(
                         "Services already contain service with '{}' name, please change it",
@@ -275,13 +777,7 @@ impl Blockchain {
                 service_id
             ));
         }
-        let msg = Message::sign_transaction(
-            tx.service_transaction(),
-            service_id,
-            self.service_keypair.0,
-            &self.service_keypair.1,
-        );
-
+        let msg = self.sign_service_transaction(tx.service_transaction(), service_id)?;
         self.api_sender.broadcast_transaction(msg)
     }
 
@@ -297,23 +793,46 @@ impl Blockchain {
     ) -> (Hash, Patch) {
         // Create fork
         let mut fork = self.fork();
+        if self.track_write_conflicts {
+            fork.track_writes();
+        }
 
+        let mut rules = ExecutionRules::for_next_block(&fork);
+        let mut write_conflicts = Vec::new();
         let block_hash = {
             // Get last hash.
             let last_hash = self.last_hash();
+            // Migrate the service data before the transactions of the block.
+            let migrated = height > Height(0) && self.migrate_service_data(&mut rules, &mut fork);
             // Save & execute transactions.
-            for (index, hash) in tx_hashes.iter().enumerate() {
-                self.execute_transaction(*hash, height, index, &mut fork, tx_cache)
-                    // Execution could fail if the transaction
-                    // cannot be deserialized or it isn't in the pool.
+            // Execution could fail if the transaction cannot be deserialized or it isn't
+            // in the pool. The parallel groups are executed on top of the database state,
+            // so the transactions of the blocks with migrations are executed sequentially
+            // in order to see the migrated data.
+            if rules.parallel && !migrated {
+                let threads = self.parallel_execution.map_or(1, |config| config.threads);
+                let conflicts = self
+                    .execute_transactions_in_parallel(
+                        &rules, tx_hashes, height, threads, &mut fork, tx_cache,
+                    )
                     .expect("Transaction execution error.");
+                write_conflicts.extend(conflicts);
+            } else {
+                for (index, hash) in tx_hashes.iter().enumerate() {
+                    self.execute_transaction(&mut rules, *hash, height, index, &mut fork, tx_cache)
+                        .expect("Transaction execution error.");
+                }
             }
 
             // Invoke execute method for all services.
             for service in self.service_map.values() {
                 // Skip execution for genesis block.
                 if height > Height(0) {
+                    if rules.enforce_namespaces {
+                        restrict_to_service_namespaces(service.as_ref(), &mut fork);
+                    }
                     before_commit(service.as_ref(), &mut fork);
+                    fork.lift_namespace_restriction();
                 }
             }
 
@@ -378,54 +897,162 @@ impl Blockchain {
             block_hash
         };
 
+        write_conflicts.extend(fork.write_conflicts());
+        for conflict in write_conflicts {
+            warn!(
+                "Write conflict at height {}: key {} of {:?} is changed by {}",
+                height,
+                hex::encode(&conflict.key),
+                conflict.name,
+                conflict.writers.join(", ")
+            );
+        }
+
         (block_hash, fork.into_patch())
     }
 
     fn execute_transaction(
         &self,
+        rules: &mut ExecutionRules,
         tx_hash: Hash,
         height: Height,
         index: usize,
         fork: &mut Fork,
         tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
     ) -> Result<(), failure::Error> {
-        let (tx, raw, service_name) = {
-            let new_fork = &*fork;
-            let snapshot = new_fork.snapshot();
-            let schema = Schema::new(snapshot);
-
-            let raw = get_tx(&tx_hash, &schema.transactions(), &tx_cache).ok_or_else(|| {
-                failure::err_msg(format!(
-                    "BUG: Cannot find transaction in database. tx: {:?}",
-                    tx_hash
-                ))
-            })?;
-
-            let service_name = self
-                .service_map
-                .get(&raw.service_id())
-                .ok_or_else(|| {
-                    failure::err_msg(format!(
-                        "Service not found. Service id: {}",
-                        raw.service_id()
-                    ))
-                })?
-                .service_name();
-
-            let tx = self.tx_from_raw(raw.payload().clone()).map_err(|error| {
-                format_err!("Service <{}>: {}, tx: {:?}", service_name, error, tx_hash)
-            })?;
-
-            (tx, raw, service_name)
-        };
+        let (tx, raw) = self.load_transaction(&tx_hash, fork, tx_cache)?;
+        let service = self.service_map[&raw.executing_service_id()].as_ref();
+        let tx_result = self.run_transaction(rules, service, tx_hash, tx.as_ref(), &raw, fork);
+        tx_cache.remove(&tx_hash);
+        Self::record_transaction(fork, tx_hash, height, index, raw, tx_result);
+        Ok(())
+    }
+
+    /// Executes at most `limit` transactions one after another in a fork, which is then
+    /// discarded. Returns the hashes of the transactions executed successfully and
+    /// the ones which have failed, in the same order. The transactions which cannot be
+    /// loaded or exceed the limit are left out of both.
+    pub(crate) fn dry_run_transactions(
+        &self,
+        tx_hashes: Vec<Hash>,
+        limit: usize,
+        tx_cache: &BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> (Vec<Hash>, Vec<Hash>) {
+        let mut fork = self.fork();
+        let mut rules = ExecutionRules::for_next_block(&fork);
+        let mut successful = Vec::new();
+        let mut failed = Vec::new();
+        for tx_hash in tx_hashes.into_iter().take(limit) {
+            let (tx, raw) = match self.load_transaction(&tx_hash, &fork, tx_cache) {
+                Ok(loaded) => loaded,
+                Err(e) => {
+                    warn!("Unable to dry-run transaction {:?}: {}", tx_hash, e);
+                    continue;
+                }
+            };
+            let service = self.service_map[&raw.executing_service_id()].as_ref();
+            let result =
+                self.run_transaction(&mut rules, service, tx_hash, tx.as_ref(), &raw, &mut fork);
+            if result.0.is_ok() {
+                successful.push(tx_hash);
+            } else {
+                failed.push(tx_hash);
+            }
+        }
+        (successful, failed)
+    }
+
+    /// Reads the transaction from the pool or the cache and deserializes it.
+    fn load_transaction(
+        &self,
+        tx_hash: &Hash,
+        fork: &Fork,
+        tx_cache: &BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> Result<(Box<dyn Transaction>, Signed<RawTransaction>), failure::Error> {
+        let snapshot = fork.snapshot();
+        let schema = Schema::new(snapshot);
+
+        let raw = get_tx(tx_hash, &schema.transactions(), tx_cache).ok_or_else(|| {
+            failure::err_msg(format!(
+                "BUG: Cannot find transaction in database. tx: {:?}",
+                tx_hash
+            ))
+        })?;
+
+        let service_id = raw.executing_service_id();
+        let service = self.service_map.get(&service_id).ok_or_else(|| {
+            failure::err_msg(format!("Service not found. Service id: {}", service_id))
+        })?;
+
+        let tx = self.tx_from_signed(&raw).map_err(|error| {
+            format_err!(
+                "Service <{}>: {}, tx: {:?}",
+                service.service_name(),
+                error,
+                tx_hash
+            )
+        })?;
 
+        Ok((tx, raw))
+    }
+
+    /// Executes the transaction in the fork and flushes its changes if the execution
+    /// succeeds, or rolls them back otherwise.
+    fn run_transaction(
+        &self,
+        rules: &mut ExecutionRules,
+        service: &dyn Service,
+        tx_hash: Hash,
+        tx: &dyn Transaction,
+        raw: &Signed<RawTransaction>,
+        fork: &mut Fork,
+    ) -> TransactionResult {
+        let service_name = service.service_name();
+        // The transaction wrapped in a multisignature envelope is executed at most once.
+        let wrapped_hash = MultisigTransaction::from_raw(raw.payload())
+            .ok()
+            .map(|envelope| envelope.transaction_hash());
+        if let Some(wrapped_hash) = wrapped_hash {
+            let schema = Schema::new(&*fork);
+            if let Some(envelope_hash) = schema.multisig_transactions().get(&wrapped_hash) {
+                info!(
+                    "Service <{}>: {:?} transaction has already been executed in {:?}",
+                    service_name, wrapped_hash, envelope_hash
+                );
+                return TransactionResult(Err(TransactionError::duplicate(Some(format!(
+                    "Transaction {:?} has already been executed in {:?}",
+                    wrapped_hash, envelope_hash
+                )))));
+            }
+        }
+
+        // The nonce is checked by the framework, so that the services do not need access
+        // to the core indexes.
+        let nonce = tx.nonce();
+        if let Some(nonce) = nonce {
+            if let Err(e) = transaction::check_nonce(&Schema::new(&*fork), &raw.author(), nonce) {
+                info!(
+                    "Service <{}>: {:?} transaction has a stale nonce: {}",
+                    service_name, tx_hash, e
+                );
+                return TransactionResult(Err(TransactionError::stale_nonce(Some(e.to_string()))));
+            }
+        }
+
+        if rules.enforce_namespaces {
+            restrict_to_service_namespaces(service, fork);
+        }
         let catch_result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-            let context = TransactionContext::new(&*fork, service_name, &raw);
+            let context = TransactionContext::new(&*fork, service_name, raw);
             tx.execute(context)
         }));
+        fork.lift_namespace_restriction();
 
         let tx_result = TransactionResult(match catch_result {
             Ok(execution_result) => {
+                let execution_result = execution_result
+                    .map_err(TransactionError::from)
+                    .and_then(|()| rules.check_storage_quota(service_name, fork));
                 if let Err(ref e) = execution_result {
                     // Unlike panic, transaction failure isn't that rare, so logging the
                     // whole transaction body is an overkill: it can be relatively big.
@@ -435,7 +1062,7 @@ impl Blockchain {
                     );
                     fork.rollback();
                 }
-                execution_result.map_err(TransactionError::from)
+                execution_result
             }
             Err(err) => {
                 if err.is::<StorageError>() {
@@ -451,16 +1078,72 @@ impl Blockchain {
                 Err(TransactionError::from_panic(&err))
             }
         });
+        if let Some(wrapped_hash) = wrapped_hash.filter(|_| tx_result.0.is_ok()) {
+            // The envelope is recorded along with the changes of the transaction, so that
+            // the repeated envelopes are rejected within the same group of transactions
+            // executed in parallel. A failed transaction may be approved again.
+            Schema::new(&*fork)
+                .multisig_transactions()
+                .put(&wrapped_hash, tx_hash);
+        }
+        if let Some(nonce) = nonce.filter(|_| tx_result.0.is_ok()) {
+            Schema::new(&*fork)
+                .author_nonces()
+                .put(&raw.author(), nonce);
+        }
+        // Core schema changes are flushed separately, so that they are not
+        // attributed to the transaction.
+        rules.record_changes(fork);
+        fork.flush_as(&format!("transaction {:?}", tx_hash));
+        tx_result
+    }
 
+    /// Saves the result and the location of the executed transaction and moves it
+    /// from the pool to the committed transactions.
+    fn record_transaction(
+        fork: &mut Fork,
+        tx_hash: Hash,
+        height: Height,
+        index: usize,
+        raw: Signed<RawTransaction>,
+        tx_result: TransactionResult,
+    ) {
         let mut schema = Schema::new(&*fork);
         schema.transaction_results().put(&tx_hash, tx_result);
         schema.commit_transaction(&tx_hash, raw);
-        tx_cache.remove(&tx_hash);
         schema.block_transactions(height).push(tx_hash);
         let location = TxLocation::new(height, index as u64);
         schema.transactions_locations().put(&tx_hash, location);
         fork.flush();
-        Ok(())
+    }
+
+    /// Combines the threshold signature shares of the precommits into the certificate
+    /// of the block if the validators use threshold signatures.
+    #[cfg(feature = "threshold-precommits")]
+    fn certify_block(
+        &self,
+        schema: &Schema<&Fork>,
+        block_hash: &Hash,
+        precommits: &[Signed<Precommit>],
+    ) {
+        // The precommits are signed by the validators actual before the block.
+        let config = Schema::new(&self.snapshot()).actual_configuration();
+        // The key set is checked when the configuration is deserialized.
+        let key_set = config
+            .consensus
+            .threshold_keys
+            .and_then(|keys| keys.key_set().ok());
+        if let Some(ref key_set) = key_set {
+            match certificate::certify(key_set, block_hash, precommits) {
+                Some(certificate) => schema
+                    .block_certificates()
+                    .put(block_hash, certificate.to_bytes().to_vec()),
+                None => warn!(
+                    "Unable to certify block {:?}: not enough valid signature shares",
+                    block_hash
+                ),
+            }
+        }
     }
 
     /// Commits to the blockchain a new block with the indicated changes (patch),
@@ -480,7 +1163,13 @@ impl Blockchain {
             let fork: Fork = patch.into();
 
             {
+                let precommits = precommits.collect::<Vec<_>>();
+                if let Some(config) = self.liveness {
+                    liveness::record_liveness(&fork, &precommits, config);
+                }
                 let mut schema = Schema::new(&fork);
+                #[cfg(feature = "threshold-precommits")]
+                self.certify_block(&schema, &block_hash, &precommits);
                 schema.precommits(&block_hash).extend(precommits);
 
                 // Consensus messages cache is useful only during one height, so it should be
@@ -501,6 +1190,10 @@ impl Blockchain {
             }
             fork.into_patch()
         };
+        let patch = match self.state_history {
+            Some(config) => self.record_state_history(patch, config),
+            None => patch,
+        };
         self.merge(patch)?;
 
         // Invokes `after_commit` for each service in order of their identifiers
@@ -511,12 +1204,74 @@ impl Blockchain {
                 self.api_sender.clone(),
                 self.fork(),
                 *service_id,
-            );
+            )
+            .with_signer(self.service_signer.clone());
             service.after_commit(&context);
         }
         Ok(())
     }
 
+    /// Saves the values of the storage keys changed by the block as they were before
+    /// the block and removes the saved values of the blocks older than the kept history.
+    fn record_state_history(&self, patch: Patch, config: StateHistoryConfig) -> Patch {
+        let snapshot = self.snapshot();
+        let mut changes = Vec::new();
+        for (column_family, patch_changes) in patch.iter() {
+            let mut keys = patch_changes
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect::<BTreeSet<_>>();
+            for prefix in patch_changes.prefixes_to_remove() {
+                let mut iter = snapshot.iter(column_family, prefix);
+                while let Some((key, _)) = iter.next() {
+                    if !key.starts_with(prefix) {
+                        break;
+                    }
+                    keys.insert(key.to_vec());
+                }
+            }
+            for key in keys {
+                let value = snapshot.get(column_family, &key);
+                changes.push(StateChange {
+                    column_family: column_family.clone(),
+                    removed: value.is_none(),
+                    value: value.unwrap_or_default(),
+                    key,
+                });
+            }
+        }
+
+        let fork: Fork = patch.into();
+        {
+            let schema = Schema::new(&fork);
+            let height = schema.height();
+            schema.state_history(height).extend(changes);
+            let mut expired = height.0.saturating_sub(config.keep_heights);
+            while expired > 0 {
+                let mut history = schema.state_history(Height(expired));
+                if history.is_empty() {
+                    break;
+                }
+                history.clear();
+                expired -= 1;
+            }
+        }
+        fork.into_patch()
+    }
+
+    /// Removes transactions and precommits of the blocks which are older than the number
+    /// of the latest blocks to keep. At most `PRUNING_BATCH_SIZE` blocks are pruned per call.
+    pub fn prune(&mut self, config: &PruningConfig) -> Result<(), failure::Error> {
+        let fork = self.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            let until = Height(schema.height().next().0.saturating_sub(config.keep_blocks));
+            schema.prune_blocks(until, PRUNING_BATCH_SIZE);
+        }
+        self.merge(fork.into_patch())?;
+        Ok(())
+    }
+
     /// Saves the `Connect` message from a peer to the cache.
     pub(crate) fn save_peer(&mut self, pubkey: &PublicKey, peer: Signed<Connect>) {
         let fork = self.fork();
@@ -561,11 +1316,60 @@ impl Blockchain {
         self.merge(fork.into_patch())
             .expect("Unable to save messages to the consensus cache");
     }
+
+    /// Saves the evidence of a validator signing conflicting consensus messages.
+    pub(crate) fn save_double_sign_evidence(&mut self, evidence: DoubleSignEvidence) {
+        let fork = self.fork();
+        Schema::new(&fork)
+            .double_sign_evidence()
+            .put(&evidence.object_hash(), evidence);
+        self.merge(fork.into_patch())
+            .expect("Unable to save double sign evidence");
+    }
+
+    /// Saves the consensus diagnostics of a committed height, keeping the diagnostics
+    /// of the latest `keep_heights` heights.
+    pub(crate) fn save_height_diagnostics(
+        &mut self,
+        diagnostics: HeightDiagnostics,
+        keep_heights: u64,
+    ) {
+        let fork = self.fork();
+        diagnostics::record_height_diagnostics(&fork, diagnostics, keep_heights);
+        self.merge(fork.into_patch())
+            .expect("Unable to save height diagnostics");
+    }
+
+    /// Adds the statistics of the peers collected since the previous call to the stored
+    /// ones, keeping the latest `keep_events` connection events of each peer.
+    pub(crate) fn save_peer_stats(
+        &mut self,
+        updates: Vec<(PublicKey, PeerStats)>,
+        keep_events: u64,
+    ) -> StorageResult<()> {
+        let fork = self.fork();
+        peer_stats::record_peer_stats(&fork, updates, keep_events);
+        self.merge(fork.into_patch())
+    }
+}
+
+/// Returns the storage namespaces accessible by the transactions of the service.
+fn service_namespaces(service: &dyn Service) -> Vec<&str> {
+    let mut namespaces = service.foreign_namespaces();
+    namespaces.push(service.service_name());
+    namespaces
+}
+
+fn restrict_to_service_namespaces(service: &dyn Service, fork: &mut Fork) {
+    fork.restrict_to_namespaces(service_namespaces(service));
 }
 
 fn before_commit(service: &dyn Service, fork: &mut Fork) {
     match panic::catch_unwind(panic::AssertUnwindSafe(|| service.before_commit(fork))) {
-        Ok(..) => fork.flush(),
+        Ok(..) => fork.flush_as(&format!(
+            "before_commit of service {}",
+            service.service_name()
+        )),
         Err(err) => {
             if err.is::<StorageError>() {
                 // Continue panic unwind if the reason is StorageError.
@@ -591,9 +1395,16 @@ impl Clone for Blockchain {
     fn clone(&self) -> Self {
         Self {
             db: Arc::clone(&self.db),
+            storage_metrics: self.storage_metrics.clone(),
             service_map: Arc::clone(&self.service_map),
             api_sender: self.api_sender.clone(),
             service_keypair: self.service_keypair.clone(),
+            track_write_conflicts: self.track_write_conflicts,
+            state_history: self.state_history,
+            liveness: self.liveness,
+            parallel_execution: self.parallel_execution,
+            tx_scheduler: self.tx_scheduler.clone(),
+            service_signer: self.service_signer.clone(),
         }
     }
 }
@@ -608,11 +1419,16 @@ pub(crate) fn get_tx<T: IndexAccess>(
     txs.get(&hash).or_else(|| tx_cache.get(&hash).cloned())
 }
 
-/// Checks that transaction exists in the persistent pool or in the transaction cache.
+/// Checks that transaction exists in the persistent pool or in the transaction cache,
+/// or has been committed. Committed transactions are checked by their locations,
+/// which are kept for the transactions of the pruned blocks, so that these transactions
+/// cannot be replayed.
 pub(crate) fn check_tx<T: IndexAccess>(
     hash: &Hash,
-    txs: &MapIndex<T, Hash, Signed<RawTransaction>>,
+    schema: &Schema<T>,
     tx_cache: &BTreeMap<Hash, Signed<RawTransaction>>,
 ) -> bool {
-    txs.contains(&hash) || tx_cache.contains_key(&hash)
+    tx_cache.contains_key(&hash)
+        || schema.transactions().contains(&hash)
+        || schema.transactions_locations().contains(&hash)
 }
@@ -0,0 +1,193 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, VecDeque};
+
+use super::config::TxOrdering;
+use crate::crypto::{Hash, PublicKey};
+
+/// Uncommitted transaction which may be included into a proposed block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TxCandidate {
+    /// Hash of the transaction.
+    pub hash: Hash,
+    /// Identifier of the service the transaction belongs to.
+    pub service_id: u16,
+    /// Author of the transaction.
+    pub author: PublicKey,
+}
+
+/// Policy of choosing the transactions of a proposed block among the uncommitted ones.
+///
+/// The policy is chosen with the `tx_ordering` field of the consensus configuration,
+/// or a custom one is plugged with [`Blockchain::set_tx_scheduler`].
+///
+/// The policy is applied by the leader only and the other validators do not check
+/// the order of the transactions in the proposals. Nevertheless, a custom policy should be
+/// deterministic, that is, the result should depend only on `candidates` and `limit`,
+/// so that the blocks proposed by different validators follow the same rules and
+/// the order of any block can be reproduced from its candidates.
+///
+/// [`Blockchain::set_tx_scheduler`]: struct.Blockchain.html#method.set_tx_scheduler
+pub trait TxOrderingPolicy {
+    /// Returns the hashes of at most `limit` transactions in the order they should appear
+    /// in the block. `candidates` are given in the order of their arrival to the node.
+    fn order(&self, candidates: Vec<TxCandidate>, limit: usize) -> Vec<Hash>;
+}
+
+/// The transactions are taken in the order of their arrival.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fifo;
+
+impl TxOrderingPolicy for Fifo {
+    fn order(&self, candidates: Vec<TxCandidate>, limit: usize) -> Vec<Hash> {
+        candidates
+            .into_iter()
+            .take(limit)
+            .map(|candidate| candidate.hash)
+            .collect()
+    }
+}
+
+/// The transactions of the services from the earlier priority classes are taken first.
+/// The transactions of the services not listed in any class are taken last. Transactions
+/// of the same class are taken in the order of their arrival.
+#[derive(Debug, Clone, Copy)]
+pub struct PriorityClasses<'a> {
+    classes: &'a [Vec<u16>],
+}
+
+impl<'a> PriorityClasses<'a> {
+    /// Creates the policy with the identifiers of the services of each class,
+    /// starting from the highest priority.
+    pub fn new(classes: &'a [Vec<u16>]) -> Self {
+        PriorityClasses { classes }
+    }
+
+    fn class(&self, service_id: u16) -> usize {
+        self.classes
+            .iter()
+            .position(|class| class.contains(&service_id))
+            .unwrap_or_else(|| self.classes.len())
+    }
+}
+
+impl<'a> TxOrderingPolicy for PriorityClasses<'a> {
+    fn order(&self, mut candidates: Vec<TxCandidate>, limit: usize) -> Vec<Hash> {
+        // The sort is stable, so the arrival order is kept within the classes.
+        candidates.sort_by_key(|candidate| self.class(candidate.service_id));
+        Fifo.order(candidates, limit)
+    }
+}
+
+/// The transactions of different authors are taken in turn, so that an author submitting
+/// many transactions does not delay the transactions of the others. The transactions of
+/// the same author are taken in the order of their arrival.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuthorRoundRobin;
+
+impl TxOrderingPolicy for AuthorRoundRobin {
+    fn order(&self, candidates: Vec<TxCandidate>, limit: usize) -> Vec<Hash> {
+        // Authors are visited in the order of their first transactions.
+        let mut authors = HashMap::new();
+        let mut queues: Vec<VecDeque<Hash>> = Vec::new();
+        for candidate in candidates {
+            let index = *authors.entry(candidate.author).or_insert_with(|| {
+                queues.push(VecDeque::new());
+                queues.len() - 1
+            });
+            queues[index].push_back(candidate.hash);
+        }
+
+        let mut ordered = Vec::new();
+        let mut position = 0;
+        while ordered.len() < limit && !queues.is_empty() {
+            if let Some(hash) = queues[position].pop_front() {
+                ordered.push(hash);
+            }
+            if queues[position].is_empty() {
+                queues.remove(position);
+            } else {
+                position += 1;
+            }
+            if position >= queues.len() {
+                position = 0;
+            }
+        }
+        ordered
+    }
+}
+
+impl TxOrderingPolicy for TxOrdering {
+    fn order(&self, candidates: Vec<TxCandidate>, limit: usize) -> Vec<Hash> {
+        match self {
+            TxOrdering::Unordered | TxOrdering::Fifo => Fifo.order(candidates, limit),
+            TxOrdering::Priority { classes } => {
+                PriorityClasses::new(classes).order(candidates, limit)
+            }
+            TxOrdering::AuthorRoundRobin => AuthorRoundRobin.order(candidates, limit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{gen_keypair, hash};
+
+    fn candidate(index: u8, service_id: u16, author: PublicKey) -> TxCandidate {
+        TxCandidate {
+            hash: hash(&[index]),
+            service_id,
+            author,
+        }
+    }
+
+    #[test]
+    fn priority_classes_ordering() {
+        let author = gen_keypair().0;
+        let candidates = vec![
+            candidate(0, 3, author),
+            candidate(1, 1, author),
+            candidate(2, 2, author),
+            candidate(3, 1, author),
+            candidate(4, 4, author),
+        ];
+        let classes = [vec![1], vec![2, 4]];
+        let ordered = PriorityClasses::new(&classes).order(candidates, 4);
+        assert_eq!(
+            ordered,
+            vec![hash(&[1]), hash(&[3]), hash(&[2]), hash(&[4])]
+        );
+    }
+
+    #[test]
+    fn author_round_robin_ordering() {
+        let (alice, bob) = (gen_keypair().0, gen_keypair().0);
+        let candidates = vec![
+            candidate(0, 1, alice),
+            candidate(1, 1, alice),
+            candidate(2, 1, alice),
+            candidate(3, 1, bob),
+            candidate(4, 1, bob),
+        ];
+        let ordered = AuthorRoundRobin.order(candidates.clone(), 10);
+        assert_eq!(
+            ordered,
+            vec![hash(&[0]), hash(&[3]), hash(&[1]), hash(&[4]), hash(&[2])]
+        );
+        let ordered = AuthorRoundRobin.order(candidates, 3);
+        assert_eq!(ordered, vec![hash(&[0]), hash(&[3]), hash(&[1])]);
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use exonum_merkledb::Fork;
+use failure::Error;
+
+use super::Schema;
+use crate::crypto::PublicKey;
+use crate::proto::{self, ProtobufConvert};
+
+/// Kind of a connection event of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PeerEventKind {
+    /// The connection with the peer has been established.
+    Connected = 0,
+    /// The connection with the peer has been closed.
+    Disconnected = 1,
+    /// The node has failed to connect to the peer.
+    ConnectionFailed = 2,
+}
+
+impl ProtobufConvert for PeerEventKind {
+    type ProtoStruct = u32;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        *self as u32
+    }
+
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, Error> {
+        Ok(match pb {
+            0 => PeerEventKind::Connected,
+            1 => PeerEventKind::Disconnected,
+            2 => PeerEventKind::ConnectionFailed,
+            value => bail!("Invalid PeerEventKind value: {}", value),
+        })
+    }
+}
+
+/// Connection event of a peer recorded by the node.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::PeerEvent", crate = "crate")]
+pub struct PeerEvent {
+    /// Kind of the event.
+    pub kind: PeerEventKind,
+    /// Time of the event.
+    pub time: DateTime<Utc>,
+}
+
+/// Statistics of a peer recorded by the node, which allow to investigate the unstable
+/// connections after the fact. The counters accumulate since the statistics have been
+/// enabled for the node.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::PeerStats", crate = "crate")]
+pub struct PeerStats {
+    /// Number of the established connections.
+    pub connects: u64,
+    /// Number of the closed connections.
+    pub disconnects: u64,
+    /// Number of the failed attempts to connect to the peer.
+    pub connection_failures: u64,
+    /// Number of the messages received from the peer.
+    pub messages: u64,
+    /// Number of the received messages which have failed to deserialize or whose
+    /// signatures are invalid.
+    pub errors: u64,
+    /// Latest connection events, from the oldest to the newest one.
+    pub events: Vec<PeerEvent>,
+}
+
+/// Adds the statistics collected by the node to the stored ones and removes the connection
+/// events preceding the latest `keep_events` ones of each peer.
+pub(crate) fn record_peer_stats(
+    fork: &Fork,
+    updates: Vec<(PublicKey, PeerStats)>,
+    keep_events: u64,
+) {
+    let schema = Schema::new(fork);
+    let mut index = schema.peer_stats();
+    for (key, update) in updates {
+        let mut stats = index.get(&key).unwrap_or_default();
+        stats.connects += update.connects;
+        stats.disconnects += update.disconnects;
+        stats.connection_failures += update.connection_failures;
+        stats.messages += update.messages;
+        stats.errors += update.errors;
+        stats.events.extend(update.events);
+        let outdated = stats.events.len().saturating_sub(keep_events as usize);
+        stats.events.drain(..outdated);
+        index.put(&key, stats);
+    }
+}
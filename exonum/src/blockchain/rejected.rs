@@ -0,0 +1,63 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use failure::Error;
+
+use crate::helpers::Height;
+use crate::proto::{self, ProtobufConvert};
+
+/// Reason of removing an uncommitted transaction from the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RejectionReason {
+    /// The transaction has stayed in the pool longer than its time-to-live.
+    Expired = 0,
+    /// The transaction has been evicted from the full pool.
+    Evicted = 1,
+    /// The transaction has been removed via the private API of the node.
+    Removed = 2,
+    /// The transaction has failed the execution in advance by the leader too many times,
+    /// see `ConsensusConfig::max_failed_dry_runs`.
+    Failed = 3,
+}
+
+impl ProtobufConvert for RejectionReason {
+    type ProtoStruct = u32;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        *self as u32
+    }
+
+    fn from_pb(pb: Self::ProtoStruct) -> Result<Self, Error> {
+        Ok(match pb {
+            0 => RejectionReason::Expired,
+            1 => RejectionReason::Evicted,
+            2 => RejectionReason::Removed,
+            3 => RejectionReason::Failed,
+            value => bail!("Invalid RejectionReason value: {}", value),
+        })
+    }
+}
+
+/// Entry of the rejected transactions log, which records the uncommitted transactions
+/// removed from the pool by the node. The log is local to the node and is not a part
+/// of the blockchain state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ProtobufConvert)]
+#[exonum(pb = "proto::RejectedTransaction", crate = "crate")]
+pub struct RejectedTransaction {
+    /// Height of the node at which the transaction was removed.
+    pub height: Height,
+    /// Reason of the removal.
+    pub reason: RejectionReason,
+}
@@ -0,0 +1,158 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic re-execution of the committed blocks.
+//!
+//! A replay re-executes the transactions of the stored blocks starting from the genesis
+//! block against a fresh state using the services of the current binary, and compares
+//! the resulting blocks with the stored ones. The first block whose execution results
+//! in another block, e.g., with another state hash, pinpoints the divergence of
+//! the service logic. Thus, service upgrades can be validated on the real history
+//! before they are rolled out.
+//!
+//! The state changes made outside of the blocks, such as the data migrations of
+//! the services, are not reproduced by the replay.
+
+use failure::Error;
+
+use std::collections::BTreeMap;
+
+use super::{Block, Blockchain, GenesisConfig, Schema};
+use crate::crypto::{CryptoHash, Hash};
+use crate::helpers::Height;
+use exonum_merkledb::{IndexAccess, Snapshot};
+
+/// Block whose re-execution results in another block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Divergence {
+    /// Height of the block.
+    pub height: Height,
+    /// The stored block.
+    pub expected: Block,
+    /// The block obtained by the re-execution.
+    pub actual: Block,
+}
+
+/// Result of the replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayReport {
+    /// Number of the blocks following the genesis one which are re-executed
+    /// with the same result.
+    pub replayed: u64,
+    /// The first diverged block, if any. The replay stops at this block.
+    pub divergence: Option<Divergence>,
+}
+
+/// Re-executes the blocks stored in the `source` snapshot up to the given height
+/// (inclusive) with the given blockchain, which must be empty. The blockchain is
+/// initialized with the genesis configuration of the source and should be set up
+/// the same way as the blockchain of the node, e.g., with the same storage quotas.
+///
+/// An error is returned if the transactions of the blocks are pruned from the source.
+pub fn replay_blocks(
+    source: &dyn Snapshot,
+    blockchain: &mut Blockchain,
+    to: Height,
+) -> Result<ReplayReport, Error> {
+    let source = Schema::new(source);
+    ensure!(
+        to <= source.height(),
+        "The latest committed height is {}, requested {}",
+        source.height(),
+        to
+    );
+    ensure!(
+        Schema::new(&blockchain.snapshot())
+            .block_hashes_by_height()
+            .is_empty(),
+        "The replayed blockchain is not empty"
+    );
+
+    let genesis = source.configuration_by_height(Height::zero());
+    blockchain.initialize(GenesisConfig::new_with_consensus(
+        genesis.consensus,
+        genesis.validator_keys.into_iter(),
+    ))?;
+    if let Some(divergence) = compare_blocks(&source, blockchain, Height::zero())? {
+        return Ok(ReplayReport {
+            replayed: 0,
+            divergence: Some(divergence),
+        });
+    }
+
+    let transactions = source.transactions();
+    for height in (1..=to.0).map(Height) {
+        ensure!(
+            !source.is_pruned(height),
+            "The transactions of the block at height {} are pruned",
+            height
+        );
+        let expected = block_at(&source, height)?;
+
+        let mut tx_cache = BTreeMap::new();
+        let tx_hashes: Vec<Hash> = source.block_transactions(height).iter().collect();
+        for tx_hash in &tx_hashes {
+            let tx = transactions
+                .get(tx_hash)
+                .ok_or_else(|| format_err!("The transaction {:?} is not found", tx_hash))?;
+            tx_cache.insert(*tx_hash, tx);
+        }
+
+        let (block_hash, patch) =
+            blockchain.create_patch(expected.proposer_id(), height, &tx_hashes, &mut tx_cache);
+        // The diverged block is committed as well to report it.
+        let precommits: Vec<_> = source.precommits(&expected.hash()).iter().collect();
+        blockchain.commit(patch, block_hash, precommits.into_iter(), &mut tx_cache)?;
+        if block_hash != expected.hash() {
+            return Ok(ReplayReport {
+                replayed: height.0 - 1,
+                divergence: compare_blocks(&source, blockchain, height)?,
+            });
+        }
+
+        if height.0 % 1000 == 0 {
+            info!("Replayed blocks up to height {}", height);
+        }
+    }
+
+    Ok(ReplayReport {
+        replayed: to.0,
+        divergence: None,
+    })
+}
+
+/// Compares the block at the given height stored in the source with the replayed one.
+fn compare_blocks<T: IndexAccess>(
+    source: &Schema<T>,
+    blockchain: &Blockchain,
+    height: Height,
+) -> Result<Option<Divergence>, Error> {
+    let expected = block_at(source, height)?;
+    let actual = block_at(&Schema::new(&blockchain.snapshot()), height)?;
+    if expected == actual {
+        return Ok(None);
+    }
+    Ok(Some(Divergence {
+        height,
+        expected,
+        actual,
+    }))
+}
+
+fn block_at<T: IndexAccess>(schema: &Schema<T>, height: Height) -> Result<Block, Error> {
+    schema
+        .block_hash_by_height(height)
+        .and_then(|block_hash| schema.blocks().get(&block_hash))
+        .ok_or_else(|| format_err!("The block at height {} is not found", height))
+}
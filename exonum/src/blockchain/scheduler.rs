@@ -0,0 +1,434 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Parallel execution of the transactions of a block.
+//!
+//! Transactions of the services with overlapping storage namespaces are put into the same
+//! group and are executed sequentially in the order of the block. The same applies to
+//! the transactions reading the namespaces changed by other transactions and the transactions
+//! with nonces of the same author, see `Transaction::nonce`. Different groups are
+//! executed in parallel, each in its own child fork of the state preceding the block,
+//! so the result does not depend on the number of the worker threads.
+//!
+//! Since the identifiers of the new indexes are allocated sequentially, the changes of only
+//! one group creating new indexes are taken. Other groups creating new indexes are executed
+//! again one by one on top of the merged changes.
+
+use exonum_merkledb::{indexes_count, Fork, Patch, Snapshot, WriteConflict};
+
+use std::{cmp::Reverse, collections::BTreeMap, panic, sync::Arc, thread};
+
+use super::{
+    execution::ExecutionRules, service_namespaces, Blockchain, Transaction, TransactionResult,
+};
+use crate::crypto::{Hash, PublicKey};
+use crate::helpers::Height;
+use crate::messages::{RawTransaction, Signed};
+
+/// Namespace of the core indexes.
+const CORE_NAMESPACE: &str = "core";
+
+/// Transaction of a block scheduled for the execution.
+struct ScheduledTx {
+    index: usize,
+    hash: Hash,
+    tx: Box<dyn Transaction>,
+    raw: Signed<RawTransaction>,
+}
+
+/// Storage namespaces accessed by a transaction.
+struct TxAccess<'a> {
+    /// Namespaces accessible through the fork.
+    writes: Vec<&'a str>,
+    /// Namespaces read through the snapshot of the fork, or `None` if any namespace
+    /// may be read.
+    reads: Option<Vec<&'a str>>,
+    /// Authors whose nonces are checked and recorded.
+    nonce_authors: Vec<PublicKey>,
+}
+
+impl<'a> TxAccess<'a> {
+    /// Checks whether the transactions cannot be executed independently of each other.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        overlap(&self.writes, &other.writes)
+            || reads_changes(&self.reads, &other.writes)
+            || reads_changes(&other.reads, &self.writes)
+            || self
+                .nonce_authors
+                .iter()
+                .any(|author| other.nonce_authors.contains(author))
+    }
+
+    /// Extends the access by the namespaces accessed by another transaction.
+    fn extend(&mut self, other: Self) {
+        self.writes.extend(other.writes);
+        self.nonce_authors.extend(other.nonce_authors);
+        self.reads = match (self.reads.take(), other.reads) {
+            (Some(mut reads), Some(other_reads)) => {
+                reads.extend(other_reads);
+                Some(reads)
+            }
+            _ => None,
+        };
+    }
+}
+
+/// Changes made by a group of transactions along with their results.
+struct ExecutedGroup {
+    patch: Patch,
+    results: Vec<(ScheduledTx, TransactionResult)>,
+    write_conflicts: Vec<WriteConflict>,
+    creates_indexes: bool,
+}
+
+impl Blockchain {
+    /// Executes the transactions of a block in the worker threads and saves their results
+    /// into the fork in the same way as the sequential execution. The transactions are
+    /// executed on top of the database state, so the fork must not contain any changes yet.
+    /// In particular, the blocks migrating the service data are executed sequentially.
+    ///
+    /// Returns the write conflicts found within the groups if tracking of writes is enabled.
+    pub(super) fn execute_transactions_in_parallel(
+        &self,
+        rules: &ExecutionRules,
+        tx_hashes: &[Hash],
+        height: Height,
+        threads: usize,
+        fork: &mut Fork,
+        tx_cache: &mut BTreeMap<Hash, Signed<RawTransaction>>,
+    ) -> Result<Vec<WriteConflict>, failure::Error> {
+        let mut transactions = Vec::with_capacity(tx_hashes.len());
+        for (index, tx_hash) in tx_hashes.iter().enumerate() {
+            let (tx, raw) = self.load_transaction(tx_hash, fork, tx_cache)?;
+            transactions.push(ScheduledTx {
+                index,
+                hash: *tx_hash,
+                tx,
+                raw,
+            });
+        }
+        if transactions.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let groups = {
+            let accesses = transactions
+                .iter()
+                .map(|scheduled| {
+                    let service = &self.service_map[&scheduled.raw.executing_service_id()];
+                    TxAccess {
+                        writes: service_namespaces(service.as_ref()),
+                        reads: service.read_namespaces(),
+                        nonce_authors: scheduled
+                            .tx
+                            .nonce()
+                            .map(|_| scheduled.raw.author())
+                            .into_iter()
+                            .collect(),
+                    }
+                })
+                .collect::<Vec<_>>();
+            schedule_transactions(accesses)
+        };
+        let mut transactions = transactions.into_iter().map(Some).collect::<Vec<_>>();
+        let mut workers = Vec::new();
+        for worker_groups in distribute_groups(groups, threads) {
+            let worker_groups = worker_groups
+                .into_iter()
+                .map(|group| {
+                    group
+                        .into_iter()
+                        .map(|index| {
+                            transactions[index]
+                                .take()
+                                .expect("Each transaction belongs to a single group")
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            workers.push(worker_groups);
+        }
+
+        let base: Arc<dyn Snapshot> = Arc::from(self.snapshot());
+        let mut workers = workers.into_iter();
+        // The groups of the first worker are executed on the current thread.
+        let own_groups = workers.next().unwrap_or_default();
+        let handles = workers
+            .map(|groups| {
+                let blockchain = self.clone();
+                let rules = rules.clone();
+                let base = Arc::clone(&base);
+                thread::spawn(move || blockchain.execute_groups(&rules, &base, groups))
+            })
+            .collect::<Vec<_>>();
+        let mut executed = self.execute_groups(rules, &base, own_groups);
+        for handle in handles {
+            // Panics of the workers, e.g., caused by storage errors, are propagated.
+            let groups = handle
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err));
+            executed.extend(groups);
+        }
+
+        // The groups change disjoint indexes, so the order of merging matters only for
+        // choosing the group whose new indexes are taken.
+        executed.sort_by_key(|group| group.results[0].0.index);
+        let mut results = Vec::with_capacity(tx_hashes.len());
+        let mut write_conflicts = Vec::new();
+        let mut creates_indexes = false;
+        let mut rerun = Vec::new();
+        for group in executed {
+            if group.creates_indexes {
+                if creates_indexes {
+                    rerun.push(group);
+                    continue;
+                }
+                creates_indexes = true;
+            }
+            fork.merge(group.patch);
+            results.extend(group.results);
+            write_conflicts.extend(group.write_conflicts);
+        }
+        let mut rerun_rules = rules.for_other_fork();
+        for group in rerun {
+            for (scheduled, _) in group.results {
+                let service = self.service_map[&scheduled.raw.executing_service_id()].as_ref();
+                let tx_result = self.run_transaction(
+                    &mut rerun_rules,
+                    service,
+                    scheduled.hash,
+                    scheduled.tx.as_ref(),
+                    &scheduled.raw,
+                    fork,
+                );
+                results.push((scheduled, tx_result));
+            }
+        }
+        results.sort_by_key(|(scheduled, _)| scheduled.index);
+        for (scheduled, tx_result) in results {
+            tx_cache.remove(&scheduled.hash);
+            Self::record_transaction(
+                fork,
+                scheduled.hash,
+                height,
+                scheduled.index,
+                scheduled.raw,
+                tx_result,
+            );
+        }
+        Ok(write_conflicts)
+    }
+
+    /// Executes the groups of transactions, each in its own child fork of the `base` state.
+    fn execute_groups(
+        &self,
+        rules: &ExecutionRules,
+        base: &Arc<dyn Snapshot>,
+        groups: Vec<Vec<ScheduledTx>>,
+    ) -> Vec<ExecutedGroup> {
+        groups
+            .into_iter()
+            .map(|group| {
+                let mut fork = Fork::from_snapshot(Arc::clone(base));
+                if self.track_write_conflicts {
+                    fork.track_writes();
+                }
+                let mut rules = rules.for_other_fork();
+                let results = group
+                    .into_iter()
+                    .map(|scheduled| {
+                        let service =
+                            self.service_map[&scheduled.raw.executing_service_id()].as_ref();
+                        let tx_result = self.run_transaction(
+                            &mut rules,
+                            service,
+                            scheduled.hash,
+                            scheduled.tx.as_ref(),
+                            &scheduled.raw,
+                            &mut fork,
+                        );
+                        (scheduled, tx_result)
+                    })
+                    .collect();
+                let write_conflicts = fork.write_conflicts();
+                let patch = fork.into_patch();
+                ExecutedGroup {
+                    creates_indexes: indexes_count(&patch) != indexes_count(&**base),
+                    patch,
+                    results,
+                    write_conflicts,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Splits the transactions of a block into groups which can be executed independently
+/// of each other. `accesses` lists the namespaces accessed by each transaction. Each group
+/// contains the indexes of its transactions in the increasing order, and the groups
+/// are ordered by their first transactions.
+fn schedule_transactions(accesses: Vec<TxAccess>) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(TxAccess, Vec<usize>)> = Vec::new();
+    for (index, tx_access) in accesses.into_iter().enumerate() {
+        // The transaction joins all the groups it conflicts with.
+        let (conflicting, mut rest): (Vec<_>, Vec<_>) = groups
+            .into_iter()
+            .partition(|(group_access, _)| group_access.conflicts_with(&tx_access));
+        let mut group = (tx_access, vec![index]);
+        for (group_access, indexes) in conflicting {
+            group.0.extend(group_access);
+            group.1.extend(indexes);
+        }
+        group.1.sort();
+        rest.push(group);
+        groups = rest;
+    }
+
+    let mut groups = groups
+        .into_iter()
+        .map(|(_, indexes)| indexes)
+        .collect::<Vec<_>>();
+    groups.sort_by_key(|indexes| indexes[0]);
+    groups
+}
+
+/// Checks whether any namespace of the first list is equal to, contains or is contained
+/// in a namespace of the second list.
+fn overlap(first: &[&str], second: &[&str]) -> bool {
+    first
+        .iter()
+        .any(|a| second.iter().any(|b| is_nested(a, b) || is_nested(b, a)))
+}
+
+/// Checks whether the transaction reading the `reads` namespaces may read the changes
+/// of the transaction accessing the `writes` namespaces. The core namespace is changed
+/// by every transaction, since the framework records the transaction results in it.
+fn reads_changes(reads: &Option<Vec<&str>>, writes: &[&str]) -> bool {
+    match reads {
+        Some(reads) => overlap(reads, writes) || overlap(reads, &[CORE_NAMESPACE]),
+        None => true,
+    }
+}
+
+/// Checks whether the `inner` namespace is equal to the `outer` one or belongs to it,
+/// e.g., `wallets.history` belongs to `wallets`.
+fn is_nested(inner: &str, outer: &str) -> bool {
+    inner.starts_with(outer)
+        && (inner.len() == outer.len() || inner[outer.len()..].starts_with('.'))
+}
+
+/// Distributes the groups among at most `threads` workers, so that the workers execute
+/// roughly the same number of transactions.
+fn distribute_groups(mut groups: Vec<Vec<usize>>, threads: usize) -> Vec<Vec<Vec<usize>>> {
+    let workers_count = threads.min(groups.len()).max(1);
+    let mut workers = vec![Vec::new(); workers_count];
+    let mut loads = vec![0; workers_count];
+    // Larger groups are distributed first.
+    groups.sort_by_key(|group| Reverse(group.len()));
+    for group in groups {
+        let worker = (0..workers_count)
+            .min_by_key(|&worker| loads[worker])
+            .expect("There is at least one worker");
+        loads[worker] += group.len();
+        workers[worker].push(group);
+    }
+    workers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::gen_keypair;
+
+    fn access<'a>(writes: Vec<&'a str>, reads: Option<Vec<&'a str>>) -> TxAccess<'a> {
+        TxAccess {
+            writes,
+            reads,
+            nonce_authors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn scheduling_by_namespaces() {
+        let accesses = vec![
+            access(vec!["wallets"], Some(vec![])),
+            access(vec!["timestamps"], Some(vec![])),
+            access(vec!["wallets.history"], Some(vec![])),
+            access(vec!["votes"], Some(vec![])),
+            access(vec!["timestamps", "votes"], Some(vec![])),
+            access(vec!["walletsfoo"], Some(vec![])),
+        ];
+        let groups = schedule_transactions(accesses);
+        assert_eq!(groups, vec![vec![0, 2], vec![1, 3, 4], vec![5]]);
+    }
+
+    #[test]
+    fn scheduling_by_read_namespaces() {
+        let accesses = vec![
+            access(vec!["wallets"], Some(vec![])),
+            access(vec!["timestamps"], Some(vec!["time"])),
+            access(vec!["time"], Some(vec![])),
+            access(vec!["votes"], Some(vec!["wallets.history"])),
+        ];
+        let groups = schedule_transactions(accesses);
+        assert_eq!(groups, vec![vec![0, 3], vec![1, 2]]);
+
+        // The core namespace is changed by every transaction.
+        let accesses = vec![
+            access(vec!["wallets"], Some(vec![])),
+            access(vec!["votes"], Some(vec!["core.transaction_results"])),
+            access(vec!["names"], Some(vec![])),
+        ];
+        let groups = schedule_transactions(accesses);
+        assert_eq!(groups, vec![vec![0, 1, 2]]);
+
+        let accesses = vec![
+            access(vec!["wallets"], Some(vec![])),
+            access(vec!["timestamps"], None),
+        ];
+        let groups = schedule_transactions(accesses);
+        assert_eq!(groups, vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn scheduling_by_nonce_authors() {
+        let author = gen_keypair().0;
+        let nonced_access = |namespace| TxAccess {
+            writes: vec![namespace],
+            reads: Some(vec![]),
+            nonce_authors: vec![author],
+        };
+        let accesses = vec![
+            nonced_access("wallets"),
+            access(vec!["timestamps"], Some(vec![])),
+            nonced_access("votes"),
+            access(vec!["names"], Some(vec![])),
+        ];
+        let groups = schedule_transactions(accesses);
+        assert_eq!(groups, vec![vec![0, 2], vec![1], vec![3]]);
+    }
+
+    #[test]
+    fn distribution_among_workers() {
+        let groups = vec![vec![0, 2], vec![1, 3, 4], vec![5], vec![6]];
+        let workers = distribute_groups(groups.clone(), 2);
+        assert_eq!(
+            workers,
+            vec![vec![vec![1, 3, 4], vec![6]], vec![vec![0, 2], vec![5]]]
+        );
+
+        let workers = distribute_groups(groups, 8);
+        assert_eq!(workers.len(), 4);
+    }
+}
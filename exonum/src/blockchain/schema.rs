@@ -12,12 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp;
+
 use exonum_merkledb::{
     Entry, IndexAccess, KeySetIndex, ListIndex, MapIndex, MapProof, ObjectHash, ProofListIndex,
     ProofMapIndex,
 };
 
-use super::{config::StoredConfiguration, Block, BlockProof, Blockchain, TransactionResult};
+use super::{
+    config::StoredConfiguration, liveness::LivenessRecord, Block, BlockProof, Blockchain,
+    DoubleSignEvidence, HeightDiagnostics, PeerStats, RejectedTransaction, RejectionReason,
+    ServiceDescriptor, TransactionResult, ValidatorLiveness,
+};
 use crate::{
     crypto::{CryptoHash, Hash, PublicKey},
     helpers::{Height, Round},
@@ -42,19 +48,39 @@ define_names!(
     TRANSACTIONS_LEN => "transactions_len";
     TRANSACTIONS_POOL => "transactions_pool";
     TRANSACTIONS_POOL_LEN => "transactions_pool_len";
+    TRANSACTIONS_POOL_SIZE => "transactions_pool_size";
+    REJECTED_TRANSACTIONS => "rejected_transactions";
     TRANSACTIONS_LOCATIONS => "transactions_locations";
+    MULTISIG_TRANSACTIONS => "multisig_transactions";
+    AUTHOR_NONCES => "author_nonces";
     BLOCKS => "blocks";
     BLOCK_HASHES_BY_HEIGHT => "block_hashes_by_height";
     BLOCK_TRANSACTIONS => "block_transactions";
     PRECOMMITS => "precommits";
+    BLOCK_CERTIFICATES => "block_certificates";
     CONFIGS => "configs";
     CONFIGS_ACTUAL_FROM => "configs_actual_from";
     STATE_HASH_AGGREGATOR => "state_hash_aggregator";
     PEERS_CACHE => "peers_cache";
     CONSENSUS_MESSAGES_CACHE => "consensus_messages_cache";
     CONSENSUS_ROUND => "consensus_round";
+    FIRST_UNPRUNED_HEIGHT => "first_unpruned_height";
+    SERVICE_DATA_VERSIONS => "service_data_versions";
+    SERVICE_DESCRIPTORS => "service_descriptors";
+    STATE_HISTORY => "state_history";
+    DOUBLE_SIGN_EVIDENCE => "double_sign_evidence";
+    VALIDATOR_LIVENESS => "validator_liveness";
+    LIVENESS_HISTORY => "liveness_history";
+    HEIGHT_DIAGNOSTICS => "height_diagnostics";
+    PEER_STATS => "peer_stats";
 );
 
+/// Returns `true` if the core index keeps the data of the node rather than of the blockchain,
+/// so it is not exported with the state.
+pub(crate) fn is_local_index(name: &str) -> bool {
+    name == PEER_STATS
+}
+
 /// Configuration index.
 #[derive(Debug, Serialize, Deserialize, ProtobufConvert)]
 #[exonum(pb = "proto::ConfigReference", crate = "crate")]
@@ -65,6 +91,21 @@ pub struct ConfigReference {
     cfg_hash: Hash,
 }
 
+/// Value of a storage key before it was changed by a block. The changes of a block
+/// are reverted to obtain the state at the previous height.
+#[derive(Debug, Clone, ProtobufConvert)]
+#[exonum(pb = "proto::StateChange", crate = "crate")]
+pub(crate) struct StateChange {
+    /// Name of the column family containing the key.
+    pub column_family: String,
+    /// The key changed by the block.
+    pub key: Vec<u8>,
+    /// The value of the key before the block, if the key existed.
+    pub value: Vec<u8>,
+    /// Whether the key did not exist before the block.
+    pub removed: bool,
+}
+
 impl ConfigReference {
     /// New ConfigReference
     pub fn new(actual_from: Height, cfg_hash: &Hash) -> Self {
@@ -178,12 +219,38 @@ where
         pool.get().unwrap_or(0)
     }
 
+    /// Returns an entry that represents the total size of uncommitted transactions in bytes.
+    pub(crate) fn transactions_pool_size_index(&self) -> Entry<T, u64> {
+        Entry::new(TRANSACTIONS_POOL_SIZE, self.access.clone())
+    }
+
+    /// Returns the total size of the transactions in the pool in bytes.
+    ///
+    /// The size of the transactions added into the pool by the previous versions
+    /// of the node is not counted.
+    pub fn transactions_pool_size(&self) -> u64 {
+        self.transactions_pool_size_index().get().unwrap_or(0)
+    }
+
     /// Returns a table that keeps the block height and transaction position inside the block for every
     /// transaction hash.
     pub fn transactions_locations(&self) -> MapIndex<T, Hash, TxLocation> {
         MapIndex::new(TRANSACTIONS_LOCATIONS, self.access.clone())
     }
 
+    /// Returns a table that maps the hashes of the transactions wrapped in the successfully
+    /// executed multisignature envelopes to the hashes of the envelopes, see
+    /// `MultisigTransaction`. A wrapped transaction is not executed again in another envelope.
+    pub fn multisig_transactions(&self) -> MapIndex<T, Hash, Hash> {
+        MapIndex::new(MULTISIG_TRANSACTIONS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the last nonce used by every transaction author,
+    /// see `Transaction::nonce`.
+    pub fn author_nonces(&self) -> MapIndex<T, PublicKey, u64> {
+        MapIndex::new(AUTHOR_NONCES, self.access.clone())
+    }
+
     /// Returns a table that stores a block object for every block height.
     pub fn blocks(&self) -> MapIndex<T, Hash, Block> {
         MapIndex::new(BLOCKS, self.access.clone())
@@ -205,6 +272,68 @@ where
         ListIndex::new_in_family(PRECOMMITS, hash, self.access.clone())
     }
 
+    /// Returns a table that keeps the threshold signature certificates of the blocks
+    /// by their hashes. The blocks are certified only if the validators use threshold
+    /// signatures, see `ConsensusConfig::threshold_keys`.
+    pub fn block_certificates(&self) -> MapIndex<T, Hash, Vec<u8>> {
+        MapIndex::new(BLOCK_CERTIFICATES, self.access.clone())
+    }
+
+    /// Returns a table that keeps the values of the storage keys changed by the block
+    /// at the given height as they were before the block.
+    pub(crate) fn state_history(&self, height: Height) -> ListIndex<T, StateChange> {
+        let height: u64 = height.into();
+        ListIndex::new_in_family(STATE_HISTORY, &height, self.access.clone())
+    }
+
+    /// Returns the rejected transactions log, that is, a table that keeps the records
+    /// of the uncommitted transactions removed from the pool by the node indexed by the hash
+    /// of the transaction.
+    pub fn rejected_transactions(&self) -> MapIndex<T, Hash, RejectedTransaction> {
+        MapIndex::new(REJECTED_TRANSACTIONS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the evidence of the validators signing conflicting
+    /// consensus messages indexed by the hash of the evidence. The evidence is recorded
+    /// by the node when it observes such messages, so it may differ between the nodes.
+    pub fn double_sign_evidence(&self) -> MapIndex<T, Hash, DoubleSignEvidence> {
+        MapIndex::new(DOUBLE_SIGN_EVIDENCE, self.access.clone())
+    }
+
+    /// Returns a table that keeps the numbers of the blocks and the messages missed by
+    /// the validators over the sliding window of the latest blocks, indexed by
+    /// the consensus keys of the validators. The table is filled only if liveness
+    /// tracking is enabled with [`Blockchain::set_liveness`]. Since a node stores only
+    /// the precommits it has collected, the numbers may differ between the nodes.
+    ///
+    /// [`Blockchain::set_liveness`]: struct.Blockchain.html#method.set_liveness
+    pub fn validator_liveness(&self) -> MapIndex<T, PublicKey, ValidatorLiveness> {
+        MapIndex::new(VALIDATOR_LIVENESS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the validators which have missed messages at each
+    /// height of the liveness window.
+    pub(crate) fn liveness_history(&self) -> MapIndex<T, u64, LivenessRecord> {
+        MapIndex::new(LIVENESS_HISTORY, self.access.clone())
+    }
+
+    /// Returns a table that keeps the consensus diagnostics of the latest committed heights
+    /// indexed by the height. The table is filled only if the diagnostics are enabled
+    /// in the node configuration. Since the diagnostics reflect the messages observed
+    /// by the node, they differ between the nodes.
+    pub fn height_diagnostics(&self) -> MapIndex<T, u64, HeightDiagnostics> {
+        MapIndex::new(HEIGHT_DIAGNOSTICS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the connection history and the message counters
+    /// of the peers indexed by their consensus keys. The table is filled only if peer
+    /// statistics are enabled in the node configuration. The statistics are local
+    /// to the node and are kept across the node restarts, but they are not exported
+    /// with the state snapshots.
+    pub fn peer_stats(&self) -> MapIndex<T, PublicKey, PeerStats> {
+        MapIndex::new(PEER_STATS, self.access.clone())
+    }
+
     /// Returns a table that represents a map with a key-value pair of a
     /// configuration hash and contents.
     pub fn configs(&self) -> ProofMapIndex<T, Hash, StoredConfiguration> {
@@ -257,6 +386,33 @@ where
             .unwrap_or_else(Round::first)
     }
 
+    /// Returns the height of the earliest block whose transactions and precommits are kept
+    /// in the storage. Transactions and precommits of the earlier blocks have been
+    /// removed by pruning; their headers are still available.
+    pub fn first_unpruned_height(&self) -> Height {
+        let entry: Entry<T, u64> = Entry::new(FIRST_UNPRUNED_HEIGHT, self.access.clone());
+        Height(entry.get().unwrap_or_default())
+    }
+
+    /// Returns `true` if transactions and precommits of the block at the given height
+    /// have been removed by pruning.
+    pub fn is_pruned(&self, height: Height) -> bool {
+        height < self.first_unpruned_height()
+    }
+
+    /// Returns a table that keeps the versions of the service data, which are updated
+    /// by the service migrations. The table is keyed by the service name.
+    pub fn service_data_versions(&self) -> MapIndex<T, String, u32> {
+        MapIndex::new(SERVICE_DATA_VERSIONS, self.access.clone())
+    }
+
+    /// Returns a table that keeps the protobuf descriptors of the services by their
+    /// identifiers. The table is updated when the node starts and is not a part
+    /// of the blockchain state.
+    pub fn service_descriptors(&self) -> MapIndex<T, u16, ServiceDescriptor> {
+        MapIndex::new(SERVICE_DESCRIPTORS, self.access.clone())
+    }
+
     /// Returns the block hash for the given height.
     pub fn block_hash_by_height(&self, height: Height) -> Option<Hash> {
         self.block_hashes_by_height().get(height.into())
@@ -271,10 +427,26 @@ where
         let block = self.blocks().get(&block_hash).unwrap();
         let precommits_table = self.precommits(&block_hash);
         let precommits = precommits_table.iter().collect();
-        let res = BlockProof { block, precommits };
+        let certificate = self.block_certificates().get(&block_hash);
+        let res = BlockProof {
+            block,
+            precommits,
+            certificate,
+        };
         Some(res)
     }
 
+    /// Returns the block for the given height with its certificate instead of
+    /// the precommits if the block is certified, see `block_certificates`.
+    /// Otherwise, the block is returned with the precommits.
+    pub fn block_and_certificate(&self, height: Height) -> Option<BlockProof> {
+        let mut proof = self.block_and_precommits(height)?;
+        if proof.certificate.is_some() {
+            proof.precommits.clear();
+        }
+        Some(proof)
+    }
+
     /// Returns the latest committed block.
     ///
     /// # Panics
@@ -367,6 +539,52 @@ where
         self.configs().get(hash)
     }
 
+    /// Returns the identifier of the network, which is the hash of the genesis configuration.
+    ///
+    /// If the network enables domain separation of the signatures with
+    /// `StoredConfiguration::domain_separated_signatures`, the identifier is prepended
+    /// to the data covered by the signatures of the messages and by the signatures
+    /// of the co-signers of `MultisigTransaction`s, but it is not included in the messages
+    /// themselves. Thus a message signed for one network is rejected by the nodes of any
+    /// other network. The identifier is passed explicitly to the methods signing and
+    /// verifying the messages, such as `Message::sign_transaction_in_network`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the "genesis block" was not created.
+    pub fn network_id(&self) -> Hash {
+        *self
+            .configs_actual_from()
+            .get(0)
+            .expect("An attempt to get the network identifier before creating the genesis block")
+            .cfg_hash()
+    }
+
+    /// Returns the network identifier covered by the signatures of the messages
+    /// at the given height, or `None` if the configuration for this height does not
+    /// enable domain separation of the signatures.
+    pub fn signature_network_id(&self, height: Height) -> Option<Hash> {
+        if self
+            .configuration_by_height(height)
+            .domain_separated_signatures
+        {
+            Some(self.network_id())
+        } else {
+            None
+        }
+    }
+
+    /// Returns the network identifier covered by the signatures of the messages according
+    /// to the actual configuration, or `None` if it does not enable domain separation
+    /// of the signatures.
+    pub fn actual_network_id(&self) -> Option<Hash> {
+        if self.actual_configuration().domain_separated_signatures {
+            Some(self.network_id())
+        } else {
+            None
+        }
+    }
+
     /// Returns the `state_hash` table for core tables.
     pub fn core_state_hash(&self) -> Vec<Hash> {
         vec![
@@ -408,12 +626,56 @@ where
         sum_table.get_proof(key)
     }
 
+    /// Returns a single proof for several service tables, which is anchored to the same
+    /// state hash as the proofs returned by [`get_proof_to_service_table`].
+    ///
+    /// Each table is specified by the service identifier and the index of the table
+    /// in the `Vec` returned by the `state_hash` method of the service.
+    ///
+    /// [`get_proof_to_service_table`]: #method.get_proof_to_service_table
+    pub fn get_proof_to_service_tables<I>(&self, tables: I) -> MapProof<Hash, Hash>
+    where
+        I: IntoIterator<Item = (u16, usize)>,
+    {
+        let keys = tables.into_iter().map(|(service_id, table_idx)| {
+            Blockchain::service_table_unique_key(service_id, table_idx)
+        });
+        self.state_hash_aggregator().get_multiproof(keys)
+    }
+
     /// Saves the given consensus round value into the storage.
     pub(crate) fn set_consensus_round(&mut self, round: Round) {
         let mut entry: Entry<T, _> = Entry::new(CONSENSUS_ROUND, self.access.clone());
         entry.set(round);
     }
 
+    /// Removes bodies of transactions and precommits of the blocks below the given height,
+    /// keeping at most `max_blocks` blocks per call so that pruning of a long chain
+    /// is spread across several commits.
+    pub(crate) fn prune_blocks(&mut self, until: Height, max_blocks: u64) {
+        let from = self.first_unpruned_height();
+        if until <= from {
+            return;
+        }
+        let until = Height(cmp::min(until.0, from.0 + max_blocks));
+
+        for height in from.0..until.0 {
+            let height = Height(height);
+            let block_hash = match self.block_hash_by_height(height) {
+                Some(block_hash) => block_hash,
+                None => break,
+            };
+            self.precommits(&block_hash).clear();
+            let mut transactions = self.transactions();
+            for tx_hash in self.block_transactions(height).iter() {
+                transactions.remove(&tx_hash);
+            }
+        }
+
+        let mut entry: Entry<T, u64> = Entry::new(FIRST_UNPRUNED_HEIGHT, self.access.clone());
+        entry.set(until.0);
+    }
+
     /// Adds a new configuration to the blockchain, which will become actual at
     /// the `actual_from` height in `config_data`.
     pub fn commit_configuration(&mut self, config_data: StoredConfiguration) {
@@ -452,13 +714,14 @@ where
     }
 
     /// Adds transaction into the persistent pool.
-    /// This method increment `transactions_pool_len_index`,
-    /// be sure to decrement it when transaction committed.
+    /// This method increment `transactions_pool_len_index` and `transactions_pool_size_index`,
+    /// be sure to decrement them when transaction committed.
     #[doc(hidden)]
     pub fn add_transaction_into_pool(&mut self, tx: Signed<RawTransaction>) {
         self.transactions_pool().insert(tx.hash());
         let x = self.transactions_pool_len_index().get().unwrap_or(0);
         self.transactions_pool_len_index().set(x + 1);
+        self.change_transactions_pool_size(&tx, true);
         self.transactions().put(&tx.hash(), tx);
     }
 
@@ -472,6 +735,7 @@ where
             self.transactions_pool().remove(hash);
             let txs_pool_len = self.transactions_pool_len_index().get().unwrap();
             self.transactions_pool_len_index().set(txs_pool_len - 1);
+            self.change_transactions_pool_size(&tx, false);
         }
     }
 
@@ -482,20 +746,45 @@ where
         len_index.set(new_len);
     }
 
-    /// Removes transaction from the persistent pool.
-    #[cfg(test)]
-    pub(crate) fn reject_transaction(&mut self, hash: &Hash) -> Result<(), ()> {
-        let contains = self.transactions_pool().contains(hash);
+    /// Removes the uncommitted transaction from the persistent pool along with its body.
+    /// Returns `false` if the transaction is not in the pool; committed transactions
+    /// are never affected.
+    ///
+    /// The transactions cache of the node is not affected, so the transactions of
+    /// a running node should be removed via `ApiSender::remove_transaction` instead.
+    pub fn reject_transaction(&mut self, hash: &Hash) -> bool {
+        if !self.transactions_pool().contains(hash) {
+            return false;
+        }
+
+        if let Some(tx) = self.transactions().get(hash) {
+            self.change_transactions_pool_size(&tx, false);
+        }
         self.transactions_pool().remove(hash);
         self.transactions().remove(hash);
+        let x = self.transactions_pool_len_index().get().unwrap();
+        self.transactions_pool_len_index().set(x - 1);
+        true
+    }
+
+    /// Records the uncommitted transaction removed from the pool in the rejected
+    /// transactions log.
+    pub(crate) fn log_rejected_transaction(&mut self, hash: &Hash, reason: RejectionReason) {
+        let height = self.next_height();
+        self.rejected_transactions()
+            .put(hash, RejectedTransaction { height, reason });
+    }
 
-        if contains {
-            let x = self.transactions_pool_len_index().get().unwrap();
-            self.transactions_pool_len_index().set(x - 1);
-            Ok(())
+    /// Adds the size of the transaction to the total size of the pool or subtracts it.
+    fn change_transactions_pool_size(&mut self, tx: &Signed<RawTransaction>, added: bool) {
+        let tx_size = tx.signed_message().raw().len() as u64;
+        let pool_size = self.transactions_pool_size();
+        let pool_size = if added {
+            pool_size + tx_size
         } else {
-            Err(())
-        }
+            pool_size.saturating_sub(tx_size)
+        };
+        self.transactions_pool_size_index().set(pool_size);
     }
 
     fn find_configurations_index_by_height(&self, height: Height) -> u64 {
@@ -18,23 +18,36 @@
 use exonum_merkledb::{Fork, Snapshot};
 
 use actix::Addr;
+use futures::sync::mpsc;
 use serde_json::Value;
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt,
     net::SocketAddr,
     sync::{Arc, RwLock},
 };
 
 use crate::{
-    api::{websocket, ServiceApiBuilder},
-    blockchain::{ConsensusConfig, Schema, StoredConfiguration, ValidatorKeys},
+    api::{
+        node::public::{
+            explorer::ValidatorLivenessInfo,
+            system::{ClockSkewInfo, GarbageCollectionStats, PoolEvictionStats, RoundChangeStats},
+        },
+        stats::ApiStats,
+        websocket, ServiceApiBuilder,
+    },
+    blockchain::{
+        ConsensusConfig, Migration, Schema, ServiceDescriptor, StoredConfiguration, ValidatorKeys,
+    },
     crypto::{Hash, PublicKey, SecretKey},
-    events::network::ConnectedPeerAddr,
-    helpers::{Height, Milliseconds, ValidatorId},
+    events::{internal::VerificationMetrics, network::ConnectedPeerAddr, PeerBans},
+    helpers::{metrics::MetricsRegistry, Height, Milliseconds, ValidatorId},
     messages::{Message, RawTransaction, ServiceTransaction, Signed},
-    node::{ApiSender, ConnectInfo, NodeRole, State},
+    node::{
+        state::SharedTxCache, ApiManagerRequest, ApiSender, ConnectInfo, ConsensusStateInfo,
+        NodeApiConfig, NodeRole, Signer, State,
+    },
 };
 
 use super::transaction::Transaction;
@@ -190,16 +203,72 @@ pub trait Service: Send + Sync + 'static {
         Value::Null
     }
 
+    /// Returns the data migrations of the service ordered by their versions.
+    ///
+    /// Migrations newer than the version of the service data recorded in the blockchain
+    /// state are applied at the start of the first block whose consensus configuration
+    /// requires a newer version with `ConsensusConfig::service_data_versions`. Thus, all
+    /// the nodes migrate the data at the same height, and the nodes which do not declare
+    /// the required version must be upgraded before that height. The data of a service
+    /// initialized in the genesis block is considered to be of the latest declared version.
+    ///
+    /// *Default implementation returns an empty list*
+    fn migrations(&self) -> Vec<Migration> {
+        Vec::new()
+    }
+
+    /// Returns the protobuf descriptor of the service transactions. The descriptor is stored
+    /// in the blockchain when the node starts, so that the explorer and external tools
+    /// can decode the transactions of the service without the service itself.
+    ///
+    /// *Default implementation returns `None`*
+    fn protobuf_descriptor(&self) -> Option<ServiceDescriptor> {
+        None
+    }
+
     /// A service execution. This method is invoked for each service after execution
     /// of all transactions in the block but before `after_commit` handler.
     ///
+    /// The changes made by the handler are a part of the block: they are taken into account
+    /// in the state hash of the block, so the handler must be deterministic. This makes
+    /// the method suitable for per-block computations, such as interest accrual or
+    /// expiration of the stored entries, which do not need a dedicated transaction.
+    /// The handler is not invoked for the genesis block.
+    ///
     /// The order of invoking `before_commit` method for every service depends on the
     /// service ID. `before_commit` for the service with the smallest ID is invoked
     /// first up to the largest one.
     /// Effectively, this means that services should not rely on a particular ordering of
-    /// Service::execute invocations.
+    /// `before_commit` invocations.
     fn before_commit(&self, fork: &Fork) {}
 
+    /// Returns the storage namespaces of other services or the core which the service
+    /// accesses directly through the fork, in addition to its own namespace equal to
+    /// the service name. The list is taken into account only if the enforcement of storage
+    /// namespaces or the parallel execution of transactions is enabled for the blockchain;
+    /// in the latter case, transactions of the services with overlapping namespaces
+    /// are executed sequentially.
+    ///
+    /// The indexes of other namespaces can be read without declaring them through the snapshot
+    /// returned by `fork.as_ref()`, see [`read_namespaces`](#method.read_namespaces).
+    ///
+    /// *Default implementation returns an empty list*
+    fn foreign_namespaces(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Returns the storage namespaces which the transactions of the service read through
+    /// the snapshot returned by `fork.as_ref()`, or `None` if the transactions may read
+    /// any namespace. The list is taken into account only if the parallel execution
+    /// of transactions is enabled for the blockchain: transactions reading the namespaces
+    /// changed by other transactions of the block, or the core namespace changed by every
+    /// transaction, are executed sequentially with them.
+    ///
+    /// *Default implementation returns `None`*
+    fn read_namespaces(&self) -> Option<Vec<&str>> {
+        None
+    }
+
     /// Handles block commit. This handler is invoked for each service after commit of the block.
     /// For example, a service can create one or more transactions if a specific condition
     /// has occurred.
@@ -228,6 +297,7 @@ pub struct ServiceContext {
     stored_configuration: StoredConfiguration,
     height: Height,
     service_id: u16,
+    signer: Option<Arc<dyn Signer>>,
 }
 
 impl ServiceContext {
@@ -263,9 +333,16 @@ impl ServiceContext {
             stored_configuration,
             service_id,
             height,
+            signer: None,
         }
     }
 
+    /// Sets the signer of the broadcast transactions used instead of the service secret key.
+    pub(crate) fn with_signer(mut self, signer: Option<Arc<dyn Signer>>) -> Self {
+        self.signer = signer;
+        self
+    }
+
     /// If the current node is a validator, returns its identifier.
     /// For other nodes return `None`.
     pub fn validator_id(&self) -> Option<ValidatorId> {
@@ -313,12 +390,30 @@ impl ServiceContext {
     where
         T: Into<ServiceTransaction> + Transaction,
     {
-        let msg = Message::sign_transaction(
-            tx,
-            self.service_id,
-            self.service_keypair.0,
-            &self.service_keypair.1,
-        );
+        let network_id = Schema::new(&self.fork).actual_network_id();
+        let msg = match self.signer {
+            Some(ref signer) => {
+                match Message::sign_transaction_with_signer(
+                    tx,
+                    self.service_id,
+                    network_id,
+                    &**signer,
+                ) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        error!("Couldn't sign transaction {}.", e);
+                        return;
+                    }
+                }
+            }
+            None => Message::sign_transaction_in_network(
+                tx,
+                self.service_id,
+                network_id,
+                self.service_keypair.0,
+                &self.service_keypair.1,
+            ),
+        };
 
         if let Err(e) = self.api_sender.broadcast_transaction(msg) {
             error!("Couldn't broadcast transaction {}.", e);
@@ -339,6 +434,20 @@ impl ServiceContext {
     }
 }
 
+/// Event about a block committed by the node. See `SharedNodeState::subscribe_blocks`
+/// and `SharedNodeState::subscribe_blocks_at_depth`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockCommitted {
+    /// Height of the block.
+    pub height: Height,
+    /// Hash of the block.
+    pub block_hash: Hash,
+    /// Hashes of the transactions of the block in the order of their execution.
+    pub tx_hashes: Vec<Hash>,
+    /// Hash of the blockchain state after the block.
+    pub state_hash: Hash,
+}
+
 #[derive(Default)]
 pub struct ApiNodeState {
     // TODO: Update on event? (ECR-1632)
@@ -346,11 +455,24 @@ pub struct ApiNodeState {
     outgoing_connections: HashSet<ConnectInfo>,
     reconnects_timeout: HashMap<SocketAddr, Milliseconds>,
     is_enabled: bool,
+    is_paused: bool,
     node_role: NodeRole,
     majority_count: usize,
     validators: Vec<ValidatorKeys>,
     broadcast_server_address: Option<Addr<websocket::Server>>,
+    mempool_server_addresses: Vec<Addr<websocket::Server>>,
     tx_cache_len: usize,
+    tx_cache: SharedTxCache,
+    consensus_state: Option<ConsensusStateInfo>,
+    height_lag: u64,
+    gc_stats: Option<GarbageCollectionStats>,
+    eviction_stats: Option<PoolEvictionStats>,
+    consensus_stalled: bool,
+    clock_skew: ClockSkewInfo,
+    round_changes: RoundChangeStats,
+    api_manager: Option<mpsc::UnboundedSender<ApiManagerRequest>>,
+    /// Subscribers to the committed blocks with the depths of the blocks.
+    block_subscribers: Vec<(u64, mpsc::UnboundedSender<BlockCommitted>)>,
 }
 
 impl fmt::Debug for ApiNodeState {
@@ -360,6 +482,7 @@ impl fmt::Debug for ApiNodeState {
             .field("outgoing_connections", &self.outgoing_connections)
             .field("reconnects_timeout", &self.reconnects_timeout)
             .field("is_enabled", &self.is_enabled)
+            .field("is_paused", &self.is_paused)
             .field("node_role", &self.node_role)
             .field("majority_count", &self.majority_count)
             .field("validators", &self.validators)
@@ -383,6 +506,10 @@ impl ApiNodeState {
 #[derive(Clone, Debug)]
 pub struct SharedNodeState {
     state: Arc<RwLock<ApiNodeState>>,
+    api_stats: ApiStats,
+    verification_metrics: VerificationMetrics,
+    peer_bans: PeerBans,
+    metrics: MetricsRegistry,
     /// Timeout to update API state.
     pub state_update_timeout: Milliseconds,
 }
@@ -392,6 +519,10 @@ impl SharedNodeState {
     pub fn new(state_update_timeout: Milliseconds) -> Self {
         Self {
             state: Arc::new(RwLock::new(ApiNodeState::new())),
+            api_stats: ApiStats::default(),
+            verification_metrics: VerificationMetrics::default(),
+            peer_bans: PeerBans::default(),
+            metrics: MetricsRegistry::default(),
             state_update_timeout,
         }
     }
@@ -438,12 +569,19 @@ impl SharedNodeState {
         lock.node_role = NodeRole::new(state.validator_id());
         lock.validators = state.validators().to_vec();
         lock.tx_cache_len = state.tx_cache_len();
+        lock.height_lag = state
+            .nodes_with_bigger_height()
+            .into_iter()
+            .map(|key| state.node_height(key).0 - state.height().0)
+            .max()
+            .unwrap_or_default();
 
         for (p, a) in state.connections() {
             match a {
                 ConnectedPeerAddr::In(addr) => {
                     let conn_info = ConnectInfo {
                         address: addr.to_string(),
+                        fallback_addresses: Vec::new(),
                         public_key: *p,
                     };
                     lock.incoming_connections.insert(conn_info);
@@ -451,6 +589,7 @@ impl SharedNodeState {
                 ConnectedPeerAddr::Out(_, addr) => {
                     let conn_info = ConnectInfo {
                         address: addr.to_string(),
+                        fallback_addresses: Vec::new(),
                         public_key: *p,
                     };
                     lock.outgoing_connections.insert(conn_info);
@@ -459,6 +598,45 @@ impl SharedNodeState {
         }
     }
 
+    /// Updates the consensus state of the node.
+    pub(crate) fn set_consensus_state(&self, consensus_state: ConsensusStateInfo) {
+        let mut lock = self.state.write().expect("Expected write lock.");
+        lock.consensus_state = Some(consensus_state);
+    }
+
+    /// Returns the consensus state of the node as of the last API state update.
+    pub fn consensus_state(&self) -> Option<ConsensusStateInfo> {
+        let lock = self.state.read().expect("Expected read lock.");
+        lock.consensus_state.clone()
+    }
+
+    /// Returns the number of blocks the node lags behind the most advanced of its peers
+    /// as of the last API state update.
+    pub fn height_lag(&self) -> u64 {
+        let lock = self.state.read().expect("Expected read lock.");
+        lock.height_lag
+    }
+
+    /// Returns statistics of requests served by the API endpoints of the node.
+    pub fn api_stats(&self) -> &ApiStats {
+        &self.api_stats
+    }
+
+    /// Returns the counters of the messages verified in the thread pool.
+    pub(crate) fn verification_metrics(&self) -> &VerificationMetrics {
+        &self.verification_metrics
+    }
+
+    /// Returns the list of the banned peer addresses.
+    pub fn peer_bans(&self) -> &PeerBans {
+        &self.peer_bans
+    }
+
+    /// Returns the registry of the consensus and node metrics.
+    pub fn metrics(&self) -> &MetricsRegistry {
+        &self.metrics
+    }
+
     /// Returns a boolean value which indicates whether the consensus is achieved.
     pub fn consensus_status(&self) -> bool {
         let lock = self.state.read().expect("Expected read lock.");
@@ -498,6 +676,25 @@ impl SharedNodeState {
         state.is_enabled = is_enabled;
     }
 
+    /// Returns `true` if the message processing and the block production of the node
+    /// are paused.
+    pub fn is_paused(&self) -> bool {
+        let state = self.state.read().expect("Expected read lock.");
+        state.is_paused
+    }
+
+    pub(crate) fn set_paused(&self, is_paused: bool) {
+        let mut state = self.state.write().expect("Expected write lock.");
+        state.is_paused = is_paused;
+    }
+
+    /// Returns the role of the node in the consensus, which changes once a configuration
+    /// adding or removing the node from the validators becomes actual.
+    pub fn node_role(&self) -> NodeRole {
+        let state = self.state.read().expect("Expected read lock.");
+        state.node_role
+    }
+
     pub(crate) fn set_node_role(&self, role: NodeRole) {
         let mut state = self.state.write().expect("Expected write lock.");
         state.node_role = role;
@@ -549,17 +746,195 @@ impl SharedNodeState {
         }
     }
 
+    /// Broadcast an alert about a lagging validator to all subscribers.
+    pub(crate) fn broadcast_liveness_alert(&self, info: ValidatorLivenessInfo) {
+        if let Some(ref address) = self
+            .state
+            .read()
+            .expect("Expected read lock")
+            .broadcast_server_address
+        {
+            address.do_send(websocket::BroadcastLivenessAlert { info })
+        }
+    }
+
+    /// Subscribes to the blocks committed by the node. The returned stream yields an event
+    /// for each block committed after the subscription, including the blocks received
+    /// during synchronization with the other nodes. The subscription is cancelled once
+    /// the stream is dropped.
+    ///
+    /// Unlike websocket notifications, the events are delivered in-process, so they are
+    /// suitable for explorer caches, background tasks of the services and webhooks.
+    pub fn subscribe_blocks(&self) -> mpsc::UnboundedReceiver<BlockCommitted> {
+        self.subscribe_blocks_at_depth(0)
+    }
+
+    /// Subscribes to the blocks reaching the given depth, that is, followed by `depth`
+    /// committed blocks. The event for the block at height `h` is delivered once the node
+    /// commits the block at height `h + depth`, so integrations requiring additional
+    /// settlement assurance do not need to track the heights themselves. The subscription
+    /// with zero depth is equivalent to `subscribe_blocks`.
+    ///
+    /// The returned stream yields an event for each block reaching the depth after
+    /// the subscription, except for the genesis block. The subscription is cancelled
+    /// once the stream is dropped.
+    pub fn subscribe_blocks_at_depth(&self, depth: u64) -> mpsc::UnboundedReceiver<BlockCommitted> {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut state = self.state.write().expect("Expected write lock");
+        state.block_subscribers.push((depth, sender));
+        receiver
+    }
+
+    /// Returns the distinct depths of the subscriptions to the committed blocks.
+    pub(crate) fn block_subscription_depths(&self) -> BTreeSet<u64> {
+        let state = self.state.read().expect("Expected read lock");
+        state
+            .block_subscribers
+            .iter()
+            .map(|(depth, _)| *depth)
+            .collect()
+    }
+
+    /// Delivers the event about a block which has reached the given depth to the subscribers
+    /// with this depth, forgetting the subscribers whose streams are dropped.
+    pub(crate) fn notify_block_committed(&self, depth: u64, event: &BlockCommitted) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state
+            .block_subscribers
+            .retain(|(subscribed_depth, sender)| {
+                *subscribed_depth != depth || sender.unbounded_send(event.clone()).is_ok()
+            });
+    }
+
+    pub(crate) fn add_mempool_server_address(&self, address: Addr<websocket::Server>) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.mempool_server_addresses.push(address);
+    }
+
+    /// Broadcast change in the pool of unconfirmed transactions to all mempool subscribers.
+    pub(crate) fn broadcast_mempool_event(&self, event: websocket::MempoolEvent, tx_hash: &Hash) {
+        let state = self.state.read().expect("Expected read lock");
+        for address in &state.mempool_server_addresses {
+            address.do_send(websocket::BroadcastMempoolEvent {
+                notification: websocket::MempoolNotification {
+                    event,
+                    tx_hash: *tx_hash,
+                },
+            })
+        }
+    }
+
     pub(crate) fn shutdown_broadcast_server(&self) {
         let state = self.state.read().expect("Expected read lock");
-        if let Some(server) = state.broadcast_server_address.as_ref() {
+        let servers = state
+            .broadcast_server_address
+            .iter()
+            .chain(&state.mempool_server_addresses);
+        for server in servers {
             server.do_send(websocket::Terminate);
         }
     }
 
+    pub(crate) fn reset_broadcast_servers(&self) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.broadcast_server_address = None;
+        state.mempool_server_addresses.clear();
+    }
+
+    pub(crate) fn set_api_manager(&self, sender: mpsc::UnboundedSender<ApiManagerRequest>) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.api_manager = Some(sender);
+    }
+
+    /// Restarts the HTTP API servers of the node without affecting the consensus.
+    /// If the configuration is specified, the servers are started with it, otherwise
+    /// the current configuration is used.
+    pub fn restart_api(&self, api_config: Option<NodeApiConfig>) -> Result<(), failure::Error> {
+        let state = self.state.read().expect("Expected read lock");
+        let sender = state
+            .api_manager
+            .as_ref()
+            .ok_or_else(|| format_err!("API servers are not managed by the node"))?;
+        sender
+            .unbounded_send(ApiManagerRequest::Restart(api_config))
+            .map_err(|_| format_err!("API manager is stopped"))
+    }
+
     pub(crate) fn tx_cache_size(&self) -> usize {
         let state = self.state.read().expect("Expected read lock");
         state.tx_cache_len
     }
+
+    /// Returns the in-memory cache of the node with the transactions which have not been
+    /// flushed into the persistent pool yet, and the arrival times of the transactions.
+    pub(crate) fn tx_cache(&self) -> SharedTxCache {
+        let state = self.state.read().expect("Expected read lock");
+        state.tx_cache.clone()
+    }
+
+    /// Shares the transactions cache of the node with the API. The cache is shared
+    /// once, when the node is created.
+    pub(crate) fn set_tx_cache(&self, tx_cache: SharedTxCache) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.tx_cache = tx_cache;
+    }
+
+    /// Returns the progress of the garbage collection, if it is enabled.
+    pub(crate) fn gc_stats(&self) -> Option<GarbageCollectionStats> {
+        let state = self.state.read().expect("Expected read lock");
+        state.gc_stats
+    }
+
+    pub(crate) fn set_gc_stats(&self, stats: GarbageCollectionStats) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.gc_stats = Some(stats);
+    }
+
+    /// Returns the statistics of the transactions evicted from the full pool,
+    /// if the pool size is limited.
+    pub(crate) fn eviction_stats(&self) -> Option<PoolEvictionStats> {
+        let state = self.state.read().expect("Expected read lock");
+        state.eviction_stats
+    }
+
+    pub(crate) fn set_eviction_stats(&self, stats: PoolEvictionStats) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.eviction_stats = Some(stats);
+    }
+
+    /// Returns `true` if the consensus stall watchdog has detected that no block
+    /// has been committed for too long.
+    pub fn is_consensus_stalled(&self) -> bool {
+        let state = self.state.read().expect("Expected read lock");
+        state.consensus_stalled
+    }
+
+    pub(crate) fn set_consensus_stalled(&self, stalled: bool) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.consensus_stalled = stalled;
+    }
+
+    /// Returns the clock skew between the node and its peers.
+    pub fn clock_skew(&self) -> ClockSkewInfo {
+        let state = self.state.read().expect("Expected read lock");
+        state.clock_skew.clone()
+    }
+
+    pub(crate) fn set_clock_skew(&self, info: ClockSkewInfo) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.clock_skew = info;
+    }
+
+    /// Returns the statistics of the round changes observed by the node.
+    pub fn round_changes(&self) -> RoundChangeStats {
+        let state = self.state.read().expect("Expected read lock");
+        state.round_changes.clone()
+    }
+
+    pub(crate) fn set_round_changes(&self, stats: RoundChangeStats) {
+        let mut state = self.state.write().expect("Expected write lock");
+        state.round_changes = stats;
+    }
 }
 
 impl<'a, S: Service> From<S> for Box<dyn Service + 'a> {
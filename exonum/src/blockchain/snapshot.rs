@@ -0,0 +1,450 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export and import of the blockchain state snapshots.
+//!
+//! A snapshot contains the whole content of the database, including the chain of block
+//! headers, at the latest committed height, except for the data local to the node, such
+//! as the peer statistics. A node importing a snapshot verifies the chain
+//! of blocks against its own genesis block, the precommits for the latest block and
+//! the state hash of all Merkelized tables, and then synchronizes only the blocks committed
+//! after the snapshot height.
+//!
+//! The indexes of the selected services can also be exported to canonical JSON files
+//! together with a manifest, which allows inspecting the state without Exonum tooling.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use failure::Error;
+
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    mem,
+    path::Path,
+};
+
+use super::{
+    integrity::{verify_blocks, verify_precommits, verify_state_hash},
+    schema::is_local_index,
+    Blockchain, Schema,
+};
+use crate::crypto::{CryptoHash, Hash, HashStream, HASH_SIZE};
+use crate::helpers::Height;
+use exonum_merkledb::{indexes_info, IndexType, Snapshot, View};
+
+/// Magic bytes at the start of the snapshot file.
+const SNAPSHOT_MAGIC: &[u8; 8] = b"EXONUMSS";
+/// Version of the snapshot format.
+const SNAPSHOT_VERSION: u8 = 2;
+/// Column family holding the data of the indexes, which are distinguished by their
+/// identifiers kept in the indexes pool.
+const INDEXES_DATA: &str = "";
+/// Column family holding the metadata of the indexes.
+const INDEXES_POOL: &str = "__INDEXES_POOL__";
+/// Column family holding the names of the `RocksDB` column families the data
+/// of the indexes is assigned to, see `DbOptions::column_families`.
+const COLUMN_FAMILIES: &str = "__COLUMN_FAMILIES__";
+/// The number of entries written to the database in a single patch during import.
+const IMPORT_BATCH_SIZE: usize = 10_000;
+/// Name of the manifest file of the state exported to JSON.
+pub const JSON_MANIFEST_FILE: &str = "manifest.json";
+
+/// Header of the state snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    /// Height of the latest block included into the snapshot.
+    pub height: Height,
+    /// Hash of the latest block included into the snapshot.
+    pub block_hash: Hash,
+}
+
+/// Writes the snapshot of the state at the given height.
+///
+/// Only the state at the latest committed height is kept in the database, so
+/// an error is returned for any other height.
+pub fn export_snapshot<W: Write>(
+    snapshot: &dyn Snapshot,
+    height: Height,
+    writer: &mut W,
+) -> Result<SnapshotHeader, Error> {
+    let schema = Schema::new(snapshot);
+    let last_block = schema.last_block();
+    ensure!(
+        height == last_block.height(),
+        "The state is available only at the latest height {}, requested {}",
+        last_block.height(),
+        height
+    );
+    let header = SnapshotHeader {
+        height,
+        block_hash: last_block.hash(),
+    };
+
+    writer.write_all(SNAPSHOT_MAGIC)?;
+    writer.write_u8(SNAPSHOT_VERSION)?;
+    writer.write_u64::<LittleEndian>(header.height.0)?;
+    writer.write_all(header.block_hash.as_ref())?;
+    let names = column_families(snapshot);
+    let local_indexes = local_indexes(snapshot);
+    writer.write_u32::<LittleEndian>(names.len() as u32)?;
+    for name in &names {
+        write_bytes(writer, name.as_bytes())?;
+        let mut iter = snapshot.iter(name, &[]);
+        while let Some((key, value)) = iter.next() {
+            if is_local_entry(&local_indexes, name, key) {
+                continue;
+            }
+            writer.write_u8(1)?;
+            write_bytes(writer, key)?;
+            write_bytes(writer, value)?;
+        }
+        writer.write_u8(0)?;
+    }
+    writer.flush()?;
+    Ok(header)
+}
+
+/// Manifest of the state exported to JSON files by [`export_json`].
+///
+/// [`export_json`]: fn.export_json.html
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonManifest {
+    /// Hash of the latest block included into the export.
+    pub block_hash: Hash,
+    /// Height of the latest block included into the export.
+    pub height: Height,
+    /// Exported indexes ordered by their names and family keys.
+    pub indexes: Vec<JsonIndexFile>,
+    /// State hash of the latest block, which aggregates the root hashes
+    /// of the Merkelized indexes.
+    pub state_hash: Hash,
+}
+
+/// An index exported to a JSON file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonIndexFile {
+    /// Number of the entries of the index.
+    pub entries: u64,
+    /// Hex-encoded key of the index in the family, or `None` if the index does not
+    /// belong to a family.
+    pub family_key: Option<String>,
+    /// Name of the file relative to the export directory.
+    pub file: String,
+    /// Hash of the file contents.
+    pub file_hash: Hash,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// Name of the index.
+    pub name: String,
+}
+
+/// Exports the indexes of the given services at the given height to canonical JSON files
+/// in the `directory` and writes the manifest of the export to the `manifest.json` file.
+/// The core indexes are exported if `core` is among the services; all the indexes are
+/// exported if the list of services is empty. The core indexes local to the node, such as
+/// the peer statistics, are never exported.
+///
+/// Each index is written to a separate file as an array of the hex-encoded raw keys
+/// and values ordered by the keys, e.g., `[["00","0a"],["01","0b"]]`. Values are
+/// encoded with the Protobuf schemas of the services. JSON files are written without
+/// whitespace and with the object keys sorted, so exports of the same state are identical.
+///
+/// Only the state at the latest committed height is kept in the database, so
+/// an error is returned for any other height.
+pub fn export_json(
+    snapshot: &dyn Snapshot,
+    height: Height,
+    services: &[&str],
+    directory: &Path,
+) -> Result<JsonManifest, Error> {
+    let schema = Schema::new(snapshot);
+    let last_block = schema.last_block();
+    ensure!(
+        height == last_block.height(),
+        "The state is available only at the latest height {}, requested {}",
+        last_block.height(),
+        height
+    );
+    fs::create_dir_all(directory)?;
+
+    let mut indexes = Vec::new();
+    for info in indexes_info(snapshot) {
+        if is_local_index(&info.name) {
+            continue;
+        }
+        let is_selected = services.is_empty()
+            || services
+                .iter()
+                .any(|service| is_in_namespace(&info.name, service));
+        if !is_selected {
+            continue;
+        }
+
+        let family_key = info.family_key.as_ref().map(hex::encode);
+        let file = match family_key {
+            Some(ref key) => format!("{}.{}.json", info.name, key),
+            None => format!("{}.json", info.name),
+        };
+        let mut writer = HashingWriter::new(BufWriter::new(File::create(directory.join(&file))?));
+        let view = View::new(snapshot, info.data_address);
+        let mut entries = 0;
+        writer.write_all(b"[")?;
+        for (key, value) in view.iter::<_, Vec<u8>, Vec<u8>>(&()) {
+            if entries > 0 {
+                writer.write_all(b",")?;
+            }
+            write!(
+                writer,
+                "[\"{}\",\"{}\"]",
+                hex::encode(key),
+                hex::encode(value)
+            )?;
+            entries += 1;
+        }
+        writer.write_all(b"]")?;
+
+        indexes.push(JsonIndexFile {
+            entries,
+            family_key,
+            file,
+            file_hash: writer.finish()?,
+            index_type: info.index_type,
+            name: info.name,
+        });
+    }
+
+    let manifest = JsonManifest {
+        block_hash: last_block.hash(),
+        height,
+        indexes,
+        state_hash: *last_block.state_hash(),
+    };
+    let mut writer = BufWriter::new(File::create(directory.join(JSON_MANIFEST_FILE))?);
+    serde_json::to_writer(&mut writer, &manifest)?;
+    writer.flush()?;
+    Ok(manifest)
+}
+
+/// Imports the snapshot into the blockchain containing only the genesis block and verifies
+/// the imported state.
+///
+/// The state is written in several patches, so the database should be discarded
+/// if the import fails.
+pub fn import_snapshot<R: Read>(
+    blockchain: &mut Blockchain,
+    reader: &mut R,
+) -> Result<SnapshotHeader, Error> {
+    let genesis_hash = {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        ensure!(
+            schema.height() == Height::zero(),
+            "The snapshot can be imported only into a node without committed blocks"
+        );
+        schema.last_block().hash()
+    };
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    ensure!(&magic == SNAPSHOT_MAGIC, "The file is not a state snapshot");
+    let version = reader.read_u8()?;
+    ensure!(
+        version == SNAPSHOT_VERSION,
+        "Unsupported snapshot version: {}",
+        version
+    );
+    let height = Height(reader.read_u64::<LittleEndian>()?);
+    let mut block_hash = [0; HASH_SIZE];
+    reader.read_exact(&mut block_hash)?;
+    let header = SnapshotHeader {
+        height,
+        block_hash: Hash::new(block_hash),
+    };
+
+    let fork = blockchain.fork();
+    for name in column_families(&*blockchain.snapshot()) {
+        View::new(&fork, name.as_str()).clear();
+    }
+    blockchain.merge(fork.into_patch())?;
+
+    let sections = reader.read_u32::<LittleEndian>()?;
+    for _ in 0..sections {
+        let name = String::from_utf8(read_bytes(reader)?)?;
+        // The data of the indexes is written to the common column family, and the database
+        // moves it to the column families assigned to the indexes. This also allows
+        // importing the snapshot into a database without column families.
+        let name = if name == COLUMN_FAMILIES || name == INDEXES_POOL {
+            name
+        } else {
+            INDEXES_DATA.to_owned()
+        };
+        let mut finished = false;
+        while !finished {
+            let fork = blockchain.fork();
+            {
+                let mut view = View::new(&fork, name.as_str());
+                for _ in 0..IMPORT_BATCH_SIZE {
+                    if reader.read_u8()? == 0 {
+                        finished = true;
+                        break;
+                    }
+                    let key = read_bytes(reader)?;
+                    let value = read_bytes(reader)?;
+                    view.put(&key[..], value);
+                }
+            }
+            blockchain.merge(fork.into_patch())?;
+        }
+    }
+
+    verify_snapshot(blockchain, &header, &genesis_hash)?;
+
+    let fork = blockchain.fork();
+    Schema::new(&fork).consensus_messages_cache().clear();
+    blockchain.merge(fork.into_patch())?;
+    Ok(header)
+}
+
+/// Verifies the chain of blocks, the precommits for the latest block and the state hash
+/// of the imported state.
+fn verify_snapshot(
+    blockchain: &Blockchain,
+    header: &SnapshotHeader,
+    genesis_hash: &Hash,
+) -> Result<(), Error> {
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+
+    let last_block = schema.last_block();
+    ensure!(
+        last_block.height() == header.height && last_block.hash() == header.block_hash,
+        "The latest block does not match the snapshot header"
+    );
+    ensure!(
+        schema.block_hash_by_height(Height::zero()).as_ref() == Some(genesis_hash),
+        "The snapshot belongs to another blockchain"
+    );
+
+    verify_blocks(&schema, Height::zero())?;
+
+    if header.height > Height::zero() {
+        let config = schema.configuration_by_height(header.height);
+        let precommits = schema.precommits(&header.block_hash);
+        verify_precommits(
+            &config,
+            schema.signature_network_id(header.height),
+            header.height,
+            &header.block_hash,
+            precommits.iter(),
+        )?;
+    }
+
+    verify_state_hash(blockchain, &*snapshot)
+}
+
+/// Returns the column families of the state in the order they are exported: the assignment
+/// of the column families to the indexes, the metadata of the indexes, the column families
+/// assigned to the indexes and the common column family of the index data.
+fn column_families(snapshot: &dyn Snapshot) -> Vec<String> {
+    let mut assigned = BTreeSet::new();
+    let mut iter = snapshot.iter(COLUMN_FAMILIES, &[]);
+    while let Some((_, name)) = iter.next() {
+        assigned.insert(String::from_utf8_lossy(name).into_owned());
+    }
+    let mut names = vec![COLUMN_FAMILIES.to_owned(), INDEXES_POOL.to_owned()];
+    names.extend(assigned);
+    names.push(INDEXES_DATA.to_owned());
+    names
+}
+
+/// Returns the keys of the metadata and the prefixes of the data of the core indexes
+/// local to the node, which are not exported. The local indexes do not belong to families.
+fn local_indexes(snapshot: &dyn Snapshot) -> Vec<(Vec<u8>, Vec<u8>)> {
+    indexes_info(snapshot)
+        .into_iter()
+        .filter(|info| info.family_key.is_none() && is_local_index(&info.name))
+        .filter_map(|info| {
+            let data_prefix = info.data_address.bytes()?.to_vec();
+            Some((info.name.into_bytes(), data_prefix))
+        })
+        .collect()
+}
+
+/// Checks whether the entry of the column family belongs to one of the local indexes.
+fn is_local_entry(local_indexes: &[(Vec<u8>, Vec<u8>)], name: &str, key: &[u8]) -> bool {
+    local_indexes
+        .iter()
+        .any(|(pool_key, data_prefix)| match name {
+            INDEXES_POOL => key == &pool_key[..],
+            COLUMN_FAMILIES => false,
+            _ => key.starts_with(data_prefix),
+        })
+}
+
+pub(crate) fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), Error> {
+    writer.write_u32::<LittleEndian>(bytes.len() as u32)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+pub(crate) fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, Error> {
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Checks whether the index with the given name belongs to the service namespace.
+fn is_in_namespace(index_name: &str, service_name: &str) -> bool {
+    index_name.starts_with(service_name)
+        && index_name[service_name.len()..]
+            .chars()
+            .next()
+            .map_or(true, |c| c == '.')
+}
+
+/// A writer computing the hash of the written bytes.
+struct HashingWriter<W> {
+    inner: W,
+    hash: HashStream,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hash: HashStream::new(),
+        }
+    }
+
+    /// Flushes the writer and returns the hash of the written bytes.
+    fn finish(mut self) -> io::Result<Hash> {
+        self.inner.flush()?;
+        Ok(self.hash.hash())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let hash = mem::replace(&mut self.hash, HashStream::new());
+        self.hash = hash.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -17,12 +17,14 @@
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 
 use crate::blockchain::{
-    Blockchain, ExecutionError, ExecutionResult, Schema, Service, Transaction, TransactionContext,
-    TransactionSet,
+    check_tx, transaction, Blockchain, ExecutionError, ExecutionResult, GenesisConfig, Schema,
+    Service, StaleNonce, Transaction, TransactionContext, TransactionErrorType, TransactionSet,
+    TxLocation, ValidatorKeys,
 };
 use crate::crypto::{gen_keypair, Hash};
 use crate::helpers::{Height, ValidatorId};
-use crate::messages::{Message, RawTransaction};
+use crate::messages::{Message, MultisigTransaction, RawTransaction, MULTISIG_SERVICE_ID};
+use crate::node::PruningConfig;
 use crate::proto;
 use exonum_merkledb::{Database, Error as StorageError, Fork, ListIndex, Snapshot};
 use std::collections::BTreeMap;
@@ -138,6 +140,249 @@ fn handling_tx_panic(blockchain: &mut Blockchain) {
     assert_eq!(index.get(3), Some(10));
 }
 
+fn dry_run_transactions(blockchain: &mut Blockchain) {
+    let (pk, sec_key) = gen_keypair();
+    let tx_ok1 = Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key);
+    let tx_ok2 = Message::sign_transaction(Tx::new(4), TEST_SERVICE_ID, pk, &sec_key);
+    let tx_failed = Message::sign_transaction(Tx::new(0), TEST_SERVICE_ID, pk, &sec_key);
+
+    let patch = {
+        let fork = blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            schema.add_transaction_into_pool(tx_ok1.clone());
+            schema.add_transaction_into_pool(tx_failed.clone());
+        }
+        fork.into_patch()
+    };
+    blockchain.merge(patch).unwrap();
+    let mut tx_cache = BTreeMap::new();
+    tx_cache.insert(tx_ok2.hash(), tx_ok2.clone());
+
+    let unknown_hash = Hash::zero();
+    let tx_hashes = vec![tx_ok1.hash(), tx_failed.hash(), unknown_hash, tx_ok2.hash()];
+    let (successful, failed) = blockchain.dry_run_transactions(tx_hashes.clone(), 10, &tx_cache);
+    assert_eq!(successful, vec![tx_ok1.hash(), tx_ok2.hash()]);
+    assert_eq!(failed, vec![tx_failed.hash()]);
+
+    // The transactions exceeding the limit are not executed.
+    let (successful, failed) = blockchain.dry_run_transactions(tx_hashes, 1, &tx_cache);
+    assert_eq!(successful, vec![tx_ok1.hash()]);
+    assert!(failed.is_empty());
+
+    // The changes of the transactions are discarded.
+    let snapshot = blockchain.snapshot();
+    let index: ListIndex<_, u64> = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(index.len(), 0);
+    assert_eq!(Schema::new(&snapshot).transactions_pool_len(), 2);
+}
+
+fn pruned_transactions_are_known(blockchain: &mut Blockchain) {
+    let (pk, sec_key) = gen_keypair();
+    let tx = Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key);
+
+    let patch = {
+        let fork = blockchain.fork();
+        Schema::new(&fork).add_transaction_into_pool(tx.clone());
+        fork.into_patch()
+    };
+    blockchain.merge(patch).unwrap();
+
+    let mut tx_cache = BTreeMap::new();
+    let (_, patch) =
+        blockchain.create_patch(ValidatorId::zero(), Height(0), &[tx.hash()], &mut tx_cache);
+    blockchain.merge(patch).unwrap();
+    let (_, patch) = blockchain.create_patch(ValidatorId::zero(), Height(1), &[], &mut tx_cache);
+    blockchain.merge(patch).unwrap();
+
+    blockchain.prune(&PruningConfig { keep_blocks: 1 }).unwrap();
+
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+    assert!(!schema.transactions().contains(&tx.hash()));
+    // The transaction cannot be replayed after its body has been pruned.
+    assert!(check_tx(&tx.hash(), &schema, &tx_cache));
+}
+
+fn multisig_transaction(blockchain: &mut Blockchain) {
+    let (pk, sec_key) = gen_keypair();
+    let cosigners = (0..3).map(|_| gen_keypair()).collect::<Vec<_>>();
+    let cosigner_keys = cosigners.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+    let inner_tx = RawTransaction::new(TEST_SERVICE_ID, Tx::new(3).into());
+
+    let mut envelope = MultisigTransaction::new(inner_tx, 2, cosigner_keys.clone());
+    envelope.sign(&pk, None, cosigners[0].0, &cosigners[0].1);
+    envelope.sign(&pk, None, cosigners[2].0, &cosigners[2].1);
+    let tx = Message::sign_transaction(envelope.clone(), MULTISIG_SERVICE_ID, pk, &sec_key);
+    // The same transaction approved by another set of co-signers.
+    envelope.sign(&pk, None, cosigners[1].0, &cosigners[1].1);
+    let tx_replayed = Message::sign_transaction(envelope, MULTISIG_SERVICE_ID, pk, &sec_key);
+
+    // A failed transaction is not recorded as executed, so it may be approved again.
+    let failing_tx = RawTransaction::new(TEST_SERVICE_ID, Tx::new(0).into());
+    let mut envelope = MultisigTransaction::new(failing_tx, 1, cosigner_keys);
+    envelope.sign(&pk, None, cosigners[0].0, &cosigners[0].1);
+    let tx_failed = Message::sign_transaction(envelope.clone(), MULTISIG_SERVICE_ID, pk, &sec_key);
+    envelope.sign(&pk, None, cosigners[1].0, &cosigners[1].1);
+    let tx_retried = Message::sign_transaction(envelope, MULTISIG_SERVICE_ID, pk, &sec_key);
+
+    let patch = {
+        let fork = blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            schema.add_transaction_into_pool(tx.clone());
+            schema.add_transaction_into_pool(tx_replayed.clone());
+            schema.add_transaction_into_pool(tx_failed.clone());
+            schema.add_transaction_into_pool(tx_retried.clone());
+        }
+        fork.into_patch()
+    };
+    blockchain.merge(patch).unwrap();
+
+    let (_, patch) = blockchain.create_patch(
+        ValidatorId::zero(),
+        Height::zero(),
+        &[
+            tx.hash(),
+            tx_replayed.hash(),
+            tx_failed.hash(),
+            tx_retried.hash(),
+        ],
+        &mut BTreeMap::new(),
+    );
+    blockchain.merge(patch).unwrap();
+    let snapshot = blockchain.snapshot();
+
+    let schema = Schema::new(&snapshot);
+    let results = schema.transaction_results();
+    assert!(results.get(&tx.hash()).unwrap().0.is_ok());
+    assert_eq!(
+        results
+            .get(&tx_replayed.hash())
+            .unwrap()
+            .0
+            .unwrap_err()
+            .error_type(),
+        TransactionErrorType::Duplicate
+    );
+    for hash in &[tx_failed.hash(), tx_retried.hash()] {
+        let error = results.get(hash).unwrap().0.unwrap_err();
+        assert_eq!(error.error_type(), TransactionErrorType::Panic);
+    }
+
+    let index = ListIndex::new(IDX_NAME, &snapshot);
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.get(0), Some(3));
+    assert_eq!(index.get(1), Some(14));
+}
+
+struct NoncedService;
+
+impl Service for NoncedService {
+    fn service_id(&self) -> u16 {
+        TEST_SERVICE_ID
+    }
+
+    fn service_name(&self) -> &'static str {
+        "nonced"
+    }
+
+    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        vec![]
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+        let TestServiceTxs::Tx(tx) = TestServiceTxs::tx_from_raw(raw)?;
+        Ok(Box::new(NoncedTx { nonce: tx.value }))
+    }
+}
+
+/// Transaction using its value as the nonce, which fails for the nonce 8.
+#[derive(Serialize, Debug)]
+struct NoncedTx {
+    nonce: u64,
+}
+
+impl Transaction for NoncedTx {
+    fn execute(&self, _: TransactionContext) -> ExecutionResult {
+        if self.nonce == 8 {
+            Err(ExecutionError::new(0))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn nonce(&self) -> Option<u64> {
+        Some(self.nonce)
+    }
+}
+
+fn transaction_nonces(blockchain: &mut Blockchain) {
+    let (pk, sec_key) = gen_keypair();
+    let (other_pk, other_sec_key) = gen_keypair();
+    let txs = [
+        (pk, &sec_key, 5),
+        (pk, &sec_key, 7),
+        (pk, &sec_key, 6),
+        (pk, &sec_key, 8),
+    ]
+    .iter()
+    .chain(&[(other_pk, &other_sec_key, 0)])
+    .map(|&(author, secret_key, nonce)| {
+        Message::sign_transaction(Tx::new(nonce), TEST_SERVICE_ID, author, secret_key)
+    })
+    .collect::<Vec<_>>();
+    let mut tx_cache = txs.iter().map(|tx| (tx.hash(), tx.clone())).collect();
+    let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+
+    let (_, patch) = blockchain.create_patch(
+        ValidatorId::zero(),
+        Height::zero(),
+        &tx_hashes,
+        &mut tx_cache,
+    );
+    blockchain.merge(patch).unwrap();
+
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+    let results = schema.transaction_results();
+    let error_types = tx_hashes
+        .iter()
+        .map(|hash| results.get(hash).unwrap().0.err().map(|e| e.error_type()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        error_types,
+        vec![
+            None,
+            None,
+            Some(TransactionErrorType::StaleNonce),
+            Some(TransactionErrorType::Code(0)),
+            None,
+        ]
+    );
+    // The nonce of the failed transaction is not recorded.
+    assert_eq!(schema.author_nonces().get(&pk), Some(7));
+    assert_eq!(schema.author_nonces().get(&other_pk), Some(0));
+    assert_eq!(
+        transaction::check_nonce(&schema, &pk, 7),
+        Err(StaleNonce {
+            nonce: 7,
+            last_nonce: 7
+        })
+    );
+}
+
+fn dry_run_transaction_storage_error(blockchain: &Blockchain) {
+    let (pk, sec_key) = gen_keypair();
+    let tx_failed = Message::sign_transaction(Tx::new(0), TEST_SERVICE_ID, pk, &sec_key);
+    let tx_storage_error = Message::sign_transaction(Tx::new(42), TEST_SERVICE_ID, pk, &sec_key);
+
+    // Panics of the transactions are caught, unlike the storage errors.
+    let result = blockchain.dry_run_transaction(&tx_failed).unwrap();
+    assert!(result.0.is_err());
+    blockchain.dry_run_transaction(&tx_storage_error).unwrap();
+}
+
 fn handling_tx_panic_storage_error(blockchain: &mut Blockchain) {
     let (pk, sec_key) = gen_keypair();
     let tx_ok1 = Message::sign_transaction(Tx::new(3), TEST_SERVICE_ID, pk, &sec_key);
@@ -365,12 +610,131 @@ fn assert_service_execute_panic(blockchain: &Blockchain, db: &mut dyn Database)
     assert!(index.is_empty());
 }
 
+struct NamespacedService {
+    id: u16,
+    name: &'static str,
+}
+
+impl Service for NamespacedService {
+    fn service_id(&self) -> u16 {
+        self.id
+    }
+
+    fn service_name(&self) -> &'static str {
+        self.name
+    }
+
+    fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+        vec![]
+    }
+
+    fn read_namespaces(&self) -> Option<Vec<&str>> {
+        Some(Vec::new())
+    }
+
+    fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+        let TestServiceTxs::Tx(tx) = TestServiceTxs::tx_from_raw(raw)?;
+        Ok(Box::new(NamespacedTx {
+            namespace: self.name,
+            value: tx.value,
+        }))
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct NamespacedTx {
+    namespace: &'static str,
+    value: u64,
+}
+
+impl Transaction for NamespacedTx {
+    fn execute(&self, tc: TransactionContext) -> ExecutionResult {
+        let mut index = ListIndex::new(format!("{}.values", self.namespace), tc.fork());
+        index.push(self.value);
+        index.push(42 / self.value);
+        Ok(())
+    }
+}
+
+fn namespaced_services() -> Vec<Box<dyn Service>> {
+    vec![
+        Box::new(NamespacedService {
+            id: 1,
+            name: "first",
+        }),
+        Box::new(NamespacedService {
+            id: 2,
+            name: "second",
+        }),
+    ]
+}
+
+fn parallel_execution(sequential: &mut Blockchain, parallel: &mut Blockchain) {
+    let validator_keys = ValidatorKeys {
+        consensus_key: gen_keypair().0,
+        service_key: gen_keypair().0,
+    };
+    let genesis = GenesisConfig::new(vec![validator_keys].into_iter());
+    let mut parallel_genesis = genesis.clone();
+    parallel_genesis.consensus.parallel_execution = true;
+    sequential.initialize(genesis).unwrap();
+    parallel.initialize(parallel_genesis).unwrap();
+
+    let (pk, sec_key) = gen_keypair();
+    let txs = [(1, 3), (2, 4), (1, 0), (2, 6), (1, 7)]
+        .iter()
+        .map(|&(service_id, value)| {
+            Message::sign_transaction(Tx::new(value), service_id, pk, &sec_key)
+        })
+        .collect::<Vec<_>>();
+    let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+
+    for blockchain in vec![&mut *sequential, &mut *parallel] {
+        let fork = blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            for tx in &txs {
+                schema.add_transaction_into_pool(tx.clone());
+            }
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+
+        let (_, patch) = blockchain.create_patch(
+            ValidatorId::zero(),
+            Height(1),
+            &tx_hashes,
+            &mut BTreeMap::new(),
+        );
+        blockchain.merge(patch).unwrap();
+    }
+
+    // The configurations of the blockchains differ, so only the service indexes
+    // and the results of the transactions are compared.
+    for blockchain in vec![sequential, parallel] {
+        let snapshot = blockchain.snapshot();
+        let first: ListIndex<_, u64> = ListIndex::new("first.values", &snapshot);
+        assert_eq!(first.iter().collect::<Vec<_>>(), vec![3, 14, 7, 6]);
+        let second: ListIndex<_, u64> = ListIndex::new("second.values", &snapshot);
+        assert_eq!(second.iter().collect::<Vec<_>>(), vec![4, 10, 6, 7]);
+
+        let schema = Schema::new(&snapshot);
+        assert_eq!(schema.block_transactions(Height(1)).len(), 5);
+        let results = schema.transaction_results();
+        assert!(results.get(&tx_hashes[2]).unwrap().0.is_err());
+        assert!(results.get(&tx_hashes[4]).unwrap().0.is_ok());
+        assert_eq!(
+            schema.transactions_locations().get(&tx_hashes[3]).unwrap(),
+            TxLocation::new(Height(1), 3)
+        );
+    }
+}
+
 mod memorydb_tests {
     use futures::sync::mpsc;
 
     use crate::blockchain::{Blockchain, Service};
     use crate::crypto::gen_keypair;
-    use crate::node::ApiSender;
+    use crate::node::{ApiSender, ParallelExecutionConfig};
     use exonum_merkledb::{Database, TemporaryDB};
 
     use super::{ServiceGood, ServicePanic, ServicePanicStorageError};
@@ -392,11 +756,15 @@ mod memorydb_tests {
     }
 
     fn create_blockchain_with_service(service: Box<dyn Service>) -> Blockchain {
+        create_blockchain_with_services(vec![service])
+    }
+
+    fn create_blockchain_with_services(services: Vec<Box<dyn Service>>) -> Blockchain {
         let service_keypair = gen_keypair();
         let api_channel = mpsc::channel(0);
         Blockchain::new(
             TemporaryDB::new(),
-            vec![service],
+            services,
             service_keypair.0,
             service_keypair.1,
             ApiSender::new(api_channel.0),
@@ -416,6 +784,37 @@ mod memorydb_tests {
         super::handling_tx_panic_storage_error(&mut blockchain);
     }
 
+    #[test]
+    fn multisig_transaction() {
+        let mut blockchain = create_blockchain();
+        super::multisig_transaction(&mut blockchain);
+    }
+
+    #[test]
+    fn transaction_nonces() {
+        let mut blockchain = create_blockchain_with_service(Box::new(super::NoncedService));
+        super::transaction_nonces(&mut blockchain);
+    }
+
+    #[test]
+    fn pruned_transactions_are_known() {
+        let mut blockchain = create_blockchain();
+        super::pruned_transactions_are_known(&mut blockchain);
+    }
+
+    #[test]
+    fn dry_run_transactions() {
+        let mut blockchain = create_blockchain();
+        super::dry_run_transactions(&mut blockchain);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dry_run_transaction_storage_error() {
+        let blockchain = create_blockchain();
+        super::dry_run_transaction_storage_error(&blockchain);
+    }
+
     #[test]
     fn service_execute() {
         let blockchain = create_blockchain_with_service(Box::new(ServiceGood));
@@ -437,6 +836,14 @@ mod memorydb_tests {
         let mut db = create_database();
         super::assert_service_execute(&blockchain, db.as_mut());
     }
+
+    #[test]
+    fn parallel_execution() {
+        let mut sequential = create_blockchain_with_services(super::namespaced_services());
+        let mut parallel = create_blockchain_with_services(super::namespaced_services());
+        parallel.set_parallel_execution(Some(ParallelExecutionConfig { threads: 2 }));
+        super::parallel_execution(&mut sequential, &mut parallel);
+    }
 }
 
 mod rocksdb_tests {
@@ -531,3 +938,421 @@ mod rocksdb_tests {
         super::assert_service_execute(&blockchain, db.as_mut());
     }
 }
+
+mod migration_tests {
+    use futures::sync::mpsc;
+
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use super::{NamespacedService, Tx};
+    use crate::blockchain::{
+        Blockchain, GenesisConfig, Migration, MigrationInfo, Schema, Service, Transaction,
+        ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, RawTransaction};
+    use crate::node::{ApiSender, ParallelExecutionConfig};
+    use exonum_merkledb::{Database, Entry, Fork, ListIndex, Snapshot, TemporaryDB};
+
+    const SERVICE_NAME: &str = "migrated";
+    const DATA_NAME: &str = "migrated.data";
+
+    struct MigratedService {
+        versions: u32,
+        failing_version: Option<u32>,
+    }
+
+    impl Service for MigratedService {
+        fn service_id(&self) -> u16 {
+            super::TEST_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &'static str {
+            SERVICE_NAME
+        }
+
+        fn state_hash(&self, _: &dyn Snapshot) -> Vec<Hash> {
+            vec![]
+        }
+
+        fn tx_from_raw(
+            &self,
+            _raw: RawTransaction,
+        ) -> Result<Box<dyn Transaction>, failure::Error> {
+            unimplemented!();
+        }
+
+        fn migrations(&self) -> Vec<Migration> {
+            let failing_version = self.failing_version;
+            (1..=self.versions)
+                .map(|version| {
+                    Migration::new(
+                        version,
+                        format!("Version {}", version),
+                        move |fork: &Fork| {
+                            ensure!(Some(version) != failing_version, "Failed");
+                            Entry::new(DATA_NAME, fork).set(version);
+                            Ok(())
+                        },
+                    )
+                })
+                .collect()
+        }
+    }
+
+    fn create_blockchain(
+        db: &Arc<dyn Database>,
+        versions: u32,
+        failing_version: Option<u32>,
+    ) -> Blockchain {
+        let service_keypair = gen_keypair();
+        let api_channel = mpsc::channel(0);
+        Blockchain::new(
+            Arc::clone(db),
+            vec![Box::new(MigratedService {
+                versions,
+                failing_version,
+            }) as Box<dyn Service>],
+            service_keypair.0,
+            service_keypair.1,
+            ApiSender::new(api_channel.0),
+        )
+    }
+
+    fn genesis_config() -> GenesisConfig {
+        let validator_keys = ValidatorKeys {
+            consensus_key: gen_keypair().0,
+            service_key: gen_keypair().0,
+        };
+        GenesisConfig::new(vec![validator_keys].into_iter())
+    }
+
+    fn data_version(db: &Arc<dyn Database>) -> Option<u32> {
+        Schema::new(&db.snapshot())
+            .service_data_versions()
+            .get(SERVICE_NAME)
+    }
+
+    fn migrated_data(db: &Arc<dyn Database>) -> Option<u32> {
+        Entry::new(DATA_NAME, &db.snapshot()).get()
+    }
+
+    /// Commits a configuration requiring the given version of the service data from
+    /// the given height.
+    fn require_data_version(blockchain: &mut Blockchain, version: u32, actual_from: Height) {
+        let fork = blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            let mut config = schema.actual_configuration();
+            config.previous_cfg_hash = config.hash();
+            config.actual_from = actual_from;
+            config
+                .consensus
+                .service_data_versions
+                .insert(SERVICE_NAME.to_owned(), version);
+            schema.commit_configuration(config);
+        }
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+
+    fn commit_block(blockchain: &mut Blockchain, height: Height) {
+        let (_, patch) =
+            blockchain.create_patch(ValidatorId::zero(), height, &[], &mut BTreeMap::new());
+        blockchain.merge(patch).unwrap();
+    }
+
+    #[test]
+    fn migrations() {
+        let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+        let genesis = genesis_config();
+
+        // Genesis block is created with the latest version of the service data.
+        let mut blockchain = create_blockchain(&db, 1, None);
+        blockchain.initialize(genesis.clone()).unwrap();
+        assert_eq!(data_version(&db), Some(1));
+        assert_eq!(migrated_data(&db), None);
+
+        let mut blockchain = create_blockchain(&db, 3, None);
+        blockchain.initialize(genesis).unwrap();
+        let pending = blockchain.check_migrations().unwrap();
+        let expected = vec![
+            MigrationInfo {
+                service_name: SERVICE_NAME.to_owned(),
+                version: 2,
+                description: "Version 2".to_owned(),
+            },
+            MigrationInfo {
+                service_name: SERVICE_NAME.to_owned(),
+                version: 3,
+                description: "Version 3".to_owned(),
+            },
+        ];
+        assert_eq!(pending, expected);
+        assert_eq!(data_version(&db), Some(1));
+        assert_eq!(migrated_data(&db), None);
+
+        // Migrations are applied at the height required by the configuration.
+        require_data_version(&mut blockchain, 2, Height(2));
+        commit_block(&mut blockchain, Height(1));
+        assert_eq!(data_version(&db), Some(1));
+        commit_block(&mut blockchain, Height(2));
+        assert_eq!(data_version(&db), Some(2));
+        assert_eq!(migrated_data(&db), Some(2));
+        blockchain.check_integrity(3).unwrap();
+        assert_eq!(blockchain.check_migrations().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn failed_migrations() {
+        let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+        let mut blockchain = create_blockchain(&db, 3, None);
+        blockchain.initialize(genesis_config()).unwrap();
+        assert!(blockchain.check_migrations().unwrap().is_empty());
+
+        let mut blockchain = create_blockchain(&db, 4, Some(4));
+        assert!(blockchain.check_migrations().is_err());
+
+        // Failed migrations are not recorded and are retried in the next blocks.
+        require_data_version(&mut blockchain, 4, Height(1));
+        commit_block(&mut blockchain, Height(1));
+        commit_block(&mut blockchain, Height(2));
+        assert_eq!(data_version(&db), Some(3));
+        assert_eq!(migrated_data(&db), None);
+        blockchain.check_integrity(3).unwrap();
+    }
+
+    #[test]
+    fn migrations_with_parallel_execution() {
+        let (pk, sec_key) = gen_keypair();
+        let txs = [(2, 3), (3, 4), (2, 5)]
+            .iter()
+            .map(|&(service_id, value)| {
+                Message::sign_transaction(Tx::new(value), service_id, pk, &sec_key)
+            })
+            .collect::<Vec<_>>();
+        let tx_hashes = txs.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+
+        for &parallel in &[false, true] {
+            let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+            let mut genesis = genesis_config();
+            genesis.consensus.parallel_execution = parallel;
+            create_blockchain(&db, 1, None).initialize(genesis).unwrap();
+
+            // The migration and both groups of the transactions create new indexes.
+            let service_keypair = gen_keypair();
+            let mut blockchain = Blockchain::new(
+                Arc::clone(&db),
+                vec![
+                    Box::new(MigratedService {
+                        versions: 2,
+                        failing_version: None,
+                    }) as Box<dyn Service>,
+                    Box::new(NamespacedService {
+                        id: 2,
+                        name: "second",
+                    }),
+                    Box::new(NamespacedService {
+                        id: 3,
+                        name: "third",
+                    }),
+                ],
+                service_keypair.0,
+                service_keypair.1,
+                ApiSender::new(mpsc::channel(0).0),
+            );
+            blockchain.set_parallel_execution(Some(ParallelExecutionConfig { threads: 2 }));
+            require_data_version(&mut blockchain, 2, Height(1));
+            let fork = blockchain.fork();
+            {
+                let mut schema = Schema::new(&fork);
+                for tx in &txs {
+                    schema.add_transaction_into_pool(tx.clone());
+                }
+            }
+            blockchain.merge(fork.into_patch()).unwrap();
+
+            let (_, patch) = blockchain.create_patch(
+                ValidatorId::zero(),
+                Height(1),
+                &tx_hashes,
+                &mut BTreeMap::new(),
+            );
+            blockchain.merge(patch).unwrap();
+
+            // The transactions see the migrated data, and the new indexes do not collide.
+            assert_eq!(data_version(&db), Some(2));
+            assert_eq!(migrated_data(&db), Some(2));
+            let snapshot = db.snapshot();
+            let second: ListIndex<_, u64> = ListIndex::new("second.values", &snapshot);
+            assert_eq!(second.iter().collect::<Vec<_>>(), vec![3, 14, 5, 8]);
+            let third: ListIndex<_, u64> = ListIndex::new("third.values", &snapshot);
+            assert_eq!(third.iter().collect::<Vec<_>>(), vec![4, 10]);
+            blockchain.check_integrity(2).unwrap();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Version 2 of the data of service migrated required")]
+    fn unsupported_data_version() {
+        let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+        let mut blockchain = create_blockchain(&db, 1, None);
+        blockchain.initialize(genesis_config()).unwrap();
+        require_data_version(&mut blockchain, 2, Height(1));
+        commit_block(&mut blockchain, Height(1));
+    }
+
+    #[test]
+    fn unordered_migrations() {
+        let db: Arc<dyn Database> = Arc::new(TemporaryDB::new());
+        let mut blockchain = create_blockchain(&db, 0, None);
+        blockchain.initialize(genesis_config()).unwrap();
+        assert_eq!(data_version(&db), None);
+
+        let migrations = vec![
+            Migration::new(2, "Second", |_: &Fork| Ok(())),
+            Migration::new(1, "First", |_: &Fork| Ok(())),
+        ];
+        assert!(crate::blockchain::migration::latest_version(SERVICE_NAME, &migrations).is_err());
+    }
+}
+
+mod replay_tests {
+    use futures::sync::mpsc;
+
+    use std::{collections::BTreeMap, iter};
+
+    use super::{TestServiceTxs, Tx};
+    use crate::blockchain::{
+        replay, Blockchain, ExecutionResult, GenesisConfig, Schema, Service, Transaction,
+        TransactionContext, TransactionSet, ValidatorKeys,
+    };
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, ValidatorId};
+    use crate::messages::{Message, MultisigTransaction, RawTransaction, MULTISIG_SERVICE_ID};
+    use crate::node::ApiSender;
+    use exonum_merkledb::{ObjectHash, ProofListIndex, Snapshot, TemporaryDB};
+
+    const VALUES_NAME: &str = "replayed.values";
+
+    /// Service storing the values of the transactions, the values starting from
+    /// `changed_from` are doubled.
+    struct ReplayedService {
+        changed_from: u64,
+    }
+
+    impl Service for ReplayedService {
+        fn service_id(&self) -> u16 {
+            super::TEST_SERVICE_ID
+        }
+
+        fn service_name(&self) -> &'static str {
+            "replayed"
+        }
+
+        fn state_hash(&self, snapshot: &dyn Snapshot) -> Vec<Hash> {
+            let values: ProofListIndex<_, u64> = ProofListIndex::new(VALUES_NAME, snapshot);
+            vec![values.object_hash()]
+        }
+
+        fn tx_from_raw(&self, raw: RawTransaction) -> Result<Box<dyn Transaction>, failure::Error> {
+            let TestServiceTxs::Tx(tx) = TestServiceTxs::tx_from_raw(raw)?;
+            Ok(Box::new(ReplayedTx {
+                value: tx.value,
+                changed_from: self.changed_from,
+            }))
+        }
+    }
+
+    #[derive(Serialize, Debug)]
+    struct ReplayedTx {
+        value: u64,
+        changed_from: u64,
+    }
+
+    impl Transaction for ReplayedTx {
+        fn execute(&self, tc: TransactionContext) -> ExecutionResult {
+            let value = if self.value >= self.changed_from {
+                self.value * 2
+            } else {
+                self.value
+            };
+            ProofListIndex::new(VALUES_NAME, tc.fork()).push(value);
+            Ok(())
+        }
+    }
+
+    fn create_blockchain(changed_from: u64) -> Blockchain {
+        let service_keypair = gen_keypair();
+        let api_channel = mpsc::channel(0);
+        Blockchain::new(
+            TemporaryDB::new(),
+            vec![Box::new(ReplayedService { changed_from }) as Box<dyn Service>],
+            service_keypair.0,
+            service_keypair.1,
+            ApiSender::new(api_channel.0),
+        )
+    }
+
+    /// Creates the blockchain with the blocks containing a transaction with the value
+    /// equal to the block height.
+    fn create_source(heights: u64) -> Blockchain {
+        let mut blockchain = create_blockchain(u64::max_value());
+        let validator_keys = ValidatorKeys {
+            consensus_key: gen_keypair().0,
+            service_key: gen_keypair().0,
+        };
+        blockchain
+            .initialize(GenesisConfig::new(vec![validator_keys].into_iter()))
+            .unwrap();
+
+        let (pk, sec_key) = gen_keypair();
+        for height in (1..=heights).map(Height) {
+            let tx =
+                Message::sign_transaction(Tx::new(height.0), super::TEST_SERVICE_ID, pk, &sec_key);
+            let mut tx_cache = BTreeMap::new();
+            tx_cache.insert(tx.hash(), tx.clone());
+            let (block_hash, patch) =
+                blockchain.create_patch(ValidatorId::zero(), height, &[tx.hash()], &mut tx_cache);
+            blockchain
+                .commit(patch, block_hash, iter::empty(), &mut tx_cache)
+                .unwrap();
+        }
+        blockchain
+    }
+
+    #[test]
+    fn replay_without_divergence() {
+        let source = create_source(3);
+        let mut replayed = create_blockchain(u64::max_value());
+        let report = replay::replay_blocks(&*source.snapshot(), &mut replayed, Height(3)).unwrap();
+        assert_eq!(report.replayed, 3);
+        assert_eq!(report.divergence, None);
+        assert_eq!(replayed.last_hash(), source.last_hash());
+    }
+
+    #[test]
+    fn replay_with_divergence() {
+        let source = create_source(3);
+        let mut replayed = create_blockchain(2);
+        let report = replay::replay_blocks(&*source.snapshot(), &mut replayed, Height(3)).unwrap();
+        assert_eq!(report.replayed, 1);
+
+        let divergence = report.divergence.unwrap();
+        let expected = Schema::new(&source.snapshot())
+            .block_and_precommits(Height(2))
+            .unwrap()
+            .block;
+        assert_eq!(divergence.height, Height(2));
+        assert_eq!(divergence.expected, expected);
+        assert_ne!(divergence.actual.state_hash(), expected.state_hash());
+        assert_eq!(divergence.actual.tx_hash(), expected.tx_hash());
+    }
+
+    #[test]
+    fn replay_into_non_empty_blockchain() {
+        let source = create_source(1);
+        let mut replayed = create_source(1);
+        assert!(replay::replay_blocks(&*source.snapshot(), &mut replayed, Height(1)).is_err());
+    }
+}
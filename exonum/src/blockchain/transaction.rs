@@ -20,10 +20,13 @@ use serde::{de::DeserializeOwned, Serialize};
 
 use std::{any::Any, borrow::Cow, convert::Into, error::Error, fmt, u8};
 
+use super::Schema;
 use crate::crypto::{CryptoHash, Hash, PublicKey};
-use crate::messages::{HexStringRepresentation, RawTransaction, Signed, SignedMessage};
+use crate::messages::{
+    HexStringRepresentation, MultisigTransaction, RawTransaction, Signed, SignedMessage,
+};
 use crate::proto::{self, ProtobufConvert};
-use exonum_merkledb::{BinaryValue, Fork, ObjectHash};
+use exonum_merkledb::{BinaryValue, Fork, IndexAccess, ObjectHash};
 
 //  User-defined error codes (`TransactionErrorType::Code(u8)`) have a `0...255` range.
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_lossless))]
@@ -32,6 +35,12 @@ const MAX_ERROR_CODE: u16 = u8::max_value() as u16;
 const TRANSACTION_STATUS_OK: u16 = MAX_ERROR_CODE + 1;
 // `Err(TransactionErrorType::Panic)`.
 const TRANSACTION_STATUS_PANIC: u16 = TRANSACTION_STATUS_OK + 1;
+// `Err(TransactionErrorType::QuotaExceeded)`.
+const TRANSACTION_STATUS_QUOTA_EXCEEDED: u16 = TRANSACTION_STATUS_PANIC + 1;
+// `Err(TransactionErrorType::Duplicate)`.
+const TRANSACTION_STATUS_DUPLICATE: u16 = TRANSACTION_STATUS_QUOTA_EXCEEDED + 1;
+// `Err(TransactionErrorType::StaleNonce)`.
+const TRANSACTION_STATUS_STALE_NONCE: u16 = TRANSACTION_STATUS_DUPLICATE + 1;
 
 /// Returns a result of the `Transaction` `execute` method. This result may be
 /// either an empty unit type, in case of success, or an `ExecutionError`, if execution has
@@ -45,6 +54,10 @@ pub struct TransactionResult(pub Result<(), TransactionError>);
 /// Data transfer object for transaction.
 /// This structure is used to send api info about transaction,
 /// and take some new transaction into pool from user input.
+///
+/// The `debug` field contains the transaction decoded from the payload, which is
+/// decompressed if the message is compressed, while `message` is the signed message
+/// as it was sent.
 #[derive(Serialize, Deserialize)]
 pub struct TransactionMessage {
     service_id: u16,
@@ -76,7 +89,7 @@ impl TransactionMessage {
     pub fn signed_message(&self) -> &SignedMessage {
         self.message.signed_message()
     }
-    /// Returns `RawTransaction`.
+    /// Returns `RawTransaction`, decompressed if the message is compressed.
     pub fn raw_transaction(&self) -> RawTransaction {
         self.message.payload().clone()
     }
@@ -180,6 +193,23 @@ pub trait Transaction: ::std::fmt::Debug + Send + 'static + ::erased_serde::Seri
     /// }
     /// # fn main() {}
     fn execute<'a>(&self, context: TransactionContext<'a>) -> ExecutionResult;
+
+    /// Returns the nonce protecting the transaction from replays.
+    ///
+    /// If a nonce is returned, the framework checks before the execution that it is greater
+    /// than the last nonce used by the author of the transaction, and fails the transaction
+    /// with the `StaleNonce` error otherwise. The nonce of a successfully executed transaction
+    /// is recorded as the last one in `Schema::author_nonces`, while the nonce of a failed
+    /// transaction can be used again.
+    ///
+    /// The nonces are shared by all the services, so that the authors keep a single
+    /// sequence number instead of a `seed` field in every transaction. The nonces need
+    /// not be consecutive.
+    ///
+    /// *Default implementation returns `None`, so the nonce is not checked.*
+    fn nonce(&self) -> Option<u64> {
+        None
+    }
 }
 
 //TODO: Add doc/examples.
@@ -191,6 +221,7 @@ pub struct TransactionContext<'a> {
     service_name: &'a str,
     tx_hash: Hash,
     author: PublicKey,
+    cosigners: Vec<PublicKey>,
 }
 
 impl<'a> TransactionContext<'a> {
@@ -200,12 +231,26 @@ impl<'a> TransactionContext<'a> {
         service_name: &'a str,
         raw_message: &Signed<RawTransaction>,
     ) -> Self {
+        // The signatures of the envelope have been verified when the transaction was
+        // dispatched, so the co-signers are taken as is.
+        let cosigners = MultisigTransaction::from_raw(raw_message.payload())
+            .map(|envelope| {
+                let signatures = envelope.signatures();
+                envelope
+                    .cosigners()
+                    .iter()
+                    .filter(|&cosigner| signatures.iter().any(|s| s.cosigner == *cosigner))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
         TransactionContext {
             fork,
-            service_id: raw_message.service_id(),
+            service_id: raw_message.executing_service_id(),
             service_name,
             tx_hash: raw_message.hash(),
             author: raw_message.author(),
+            cosigners,
         }
     }
 
@@ -229,6 +274,13 @@ impl<'a> TransactionContext<'a> {
         self.author
     }
 
+    /// Returns the co-signers who have approved the transaction if it has been sent
+    /// in a multisignature envelope, see `MultisigTransaction`. Empty for the other
+    /// transactions.
+    pub fn cosigners(&self) -> &[PublicKey] {
+        &self.cosigners
+    }
+
     /// Returns current transaction message hash.
     /// This hash could be used to link some data in storage for external usage.
     pub fn tx_hash(&self) -> Hash {
@@ -236,6 +288,33 @@ impl<'a> TransactionContext<'a> {
     }
 }
 
+/// Checks that the nonce of the transaction is greater than the last nonce used
+/// by its author, see `Transaction::nonce`.
+pub(crate) fn check_nonce<T: IndexAccess>(
+    schema: &Schema<T>,
+    author: &PublicKey,
+    nonce: u64,
+) -> Result<(), StaleNonce> {
+    match schema.author_nonces().get(author) {
+        Some(last_nonce) if nonce <= last_nonce => Err(StaleNonce { nonce, last_nonce }),
+        _ => Ok(()),
+    }
+}
+
+/// Error returned if the nonce of the transaction has already been used,
+/// see `Transaction::nonce`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Fail)]
+#[fail(
+    display = "Nonce {} is not greater than the last nonce {} of the author",
+    nonce, last_nonce
+)]
+pub struct StaleNonce {
+    /// Nonce of the transaction.
+    pub nonce: u64,
+    /// Last nonce used by the author.
+    pub last_nonce: u64,
+}
+
 /// Result of unsuccessful transaction execution.
 ///
 /// An execution error consists
@@ -276,6 +355,12 @@ impl ExecutionError {
 pub enum TransactionErrorType {
     /// Panic occurred during transaction execution.
     Panic,
+    /// Transaction has exceeded the hard storage quota of the service namespace.
+    QuotaExceeded,
+    /// Transaction wrapped in a multisignature envelope has already been executed.
+    Duplicate,
+    /// Nonce of the transaction is not greater than the last nonce of its author.
+    StaleNonce,
     /// User-defined error code. Can have different meanings for different transactions and
     /// services.
     Code(u8),
@@ -291,6 +376,12 @@ pub enum TransactionErrorType {
 ///   implementation for the details).
 /// - `TransactionErrorType::Panic` is set by the framework if panic is raised during transaction
 ///   execution.
+/// - `TransactionErrorType::QuotaExceeded` is set by the framework if the transaction
+///   has exceeded the hard storage quota of its service.
+/// - `TransactionErrorType::Duplicate` is set by the framework if the transaction wrapped
+///   in a multisignature envelope has already been executed.
+/// - `TransactionErrorType::StaleNonce` is set by the framework if the nonce of the transaction
+///   has already been used by its author.
 /// - `TransactionError` implements `Display` which can be used for obtaining a simple error
 ///   description.
 ///
@@ -348,13 +439,31 @@ impl TransactionError {
         Self::new(TransactionErrorType::Panic, description)
     }
 
+    /// Creates a new `TransactionError` representing an exceeded storage quota
+    /// with the given description.
+    pub(crate) fn quota_exceeded(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::QuotaExceeded, description)
+    }
+
+    /// Creates a new `TransactionError` representing a repeated execution of the transaction
+    /// wrapped in a multisignature envelope with the given description.
+    pub(crate) fn duplicate(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::Duplicate, description)
+    }
+
+    /// Creates a new `TransactionError` representing a stale nonce of the transaction
+    /// with the given description.
+    pub(crate) fn stale_nonce(description: Option<String>) -> Self {
+        Self::new(TransactionErrorType::StaleNonce, description)
+    }
+
     /// Creates a new `TransactionError` instance from `std::thread::Result`'s `Err`.
     pub(crate) fn from_panic(panic: &Box<dyn Any + Send>) -> Self {
         Self::panic(panic_description(panic))
     }
 
     /// Returns an error type of this `TransactionError` instance. This can be
-    /// either a panic or a user-defined error code.
+    /// a panic, an exceeded storage quota or a user-defined error code.
     pub fn error_type(&self) -> TransactionErrorType {
         self.error_type
     }
@@ -375,6 +484,9 @@ impl fmt::Display for TransactionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.error_type {
             TransactionErrorType::Panic => write!(f, "Panic during execution")?,
+            TransactionErrorType::QuotaExceeded => write!(f, "Storage quota exceeded")?,
+            TransactionErrorType::Duplicate => write!(f, "Transaction already executed")?,
+            TransactionErrorType::StaleNonce => write!(f, "Stale nonce")?,
             TransactionErrorType::Code(c) => write!(f, "Error code: {}", c)?,
         }
 
@@ -428,6 +540,9 @@ impl ProtobufConvert for TransactionResult {
             value @ 0..=MAX_ERROR_CODE => Err(TransactionError::code(value as u8, description)),
             TRANSACTION_STATUS_OK => Ok(()),
             TRANSACTION_STATUS_PANIC => Err(TransactionError::panic(description)),
+            TRANSACTION_STATUS_QUOTA_EXCEEDED => Err(TransactionError::quota_exceeded(description)),
+            TRANSACTION_STATUS_DUPLICATE => Err(TransactionError::duplicate(description)),
+            TRANSACTION_STATUS_STALE_NONCE => Err(TransactionError::stale_nonce(description)),
             value => bail!("Invalid TransactionResult value: {}", value),
         }))
     }
@@ -456,6 +571,9 @@ fn status_as_u16(status: &TransactionResult) -> u16 {
         Ok(()) => TRANSACTION_STATUS_OK,
         Err(ref e) => match e.error_type {
             TransactionErrorType::Panic => TRANSACTION_STATUS_PANIC,
+            TransactionErrorType::QuotaExceeded => TRANSACTION_STATUS_QUOTA_EXCEEDED,
+            TransactionErrorType::Duplicate => TRANSACTION_STATUS_DUPLICATE,
+            TransactionErrorType::StaleNonce => TRANSACTION_STATUS_STALE_NONCE,
             TransactionErrorType::Code(c) => u16::from(c),
         },
     }
@@ -469,6 +587,10 @@ pub trait TransactionSet:
     Into<Box<dyn Transaction>> + Clone + Serialize + DeserializeOwned
 {
     /// Parses a transaction from this set from a `RawTransaction`.
+    ///
+    /// The derived implementation rejects the payloads which are not encoded canonically
+    /// if the network requires it, see
+    /// [`proto::from_payload_bytes`](../proto/fn.from_payload_bytes.html).
     fn tx_from_raw(raw: RawTransaction) -> Result<Self, failure::Error>;
 }
 
@@ -535,6 +657,9 @@ mod tests {
         let values = vec![
             (TransactionErrorType::Panic, None),
             (TransactionErrorType::Panic, Some("panic")),
+            (TransactionErrorType::QuotaExceeded, None),
+            (TransactionErrorType::Duplicate, Some("duplicate")),
+            (TransactionErrorType::StaleNonce, None),
             (TransactionErrorType::Code(0), None),
             (TransactionErrorType::Code(1), Some("")),
             (TransactionErrorType::Code(100), None),
@@ -577,6 +702,8 @@ mod tests {
             Err(TransactionError::panic(Some(
                 "Panic error description".to_owned(),
             ))),
+            Err(TransactionError::quota_exceeded(None)),
+            Err(TransactionError::stale_nonce(Some("stale".to_owned()))),
             Err(TransactionError::code(0, None)),
             Err(TransactionError::code(
                 0,
@@ -0,0 +1,401 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Misbehaviour scoring and temporary bans of the peer addresses.
+
+use chrono::{DateTime, Utc};
+
+use std::{
+    cmp,
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use crate::node::PeerBansConfig;
+
+/// Kind of the peer misbehaviour, which increases the score of its address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+    /// The peer has sent a message with an invalid signature or format.
+    InvalidMessage,
+    /// The peer has failed the handshake or sent an invalid `Connect` message. I/O errors
+    /// and the failures of the addresses from the connect list are not counted.
+    HandshakeFailure,
+    /// The peer has sent a frame which cannot be decoded, e.g., a too long message.
+    Spam,
+}
+
+impl Misbehavior {
+    /// Returns the number of points added to the score of the peer address.
+    pub fn penalty(self) -> u32 {
+        match self {
+            Misbehavior::InvalidMessage => 10,
+            Misbehavior::HandshakeFailure => 10,
+            Misbehavior::Spam => 25,
+        }
+    }
+}
+
+/// Information about a banned address.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BanInfo {
+    /// Banned address.
+    pub address: IpAddr,
+    /// Time at which the ban expires, or `None` if the address is banned
+    /// until it is unbanned manually.
+    pub until: Option<DateTime<Utc>>,
+    /// Whether the address has been banned manually.
+    pub manual: bool,
+    /// Number of the automatic bans of the address, which determines the duration
+    /// of the next one.
+    pub count: u32,
+}
+
+/// Returns the time after the given number of seconds, or `None` if it cannot be represented,
+/// in which case the ban does not expire.
+fn expiry(time: SystemTime, secs: u64) -> Option<SystemTime> {
+    time.checked_add(Duration::from_secs(secs))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Ban {
+    until: Option<SystemTime>,
+    manual: bool,
+}
+
+#[derive(Debug, Default)]
+struct PeerRecord {
+    score: u32,
+    updated: Option<SystemTime>,
+    ban: Option<Ban>,
+    /// Number of the automatic bans.
+    count: u32,
+    /// Time at which the latest ban has expired or has been lifted.
+    ban_end: Option<SystemTime>,
+}
+
+impl PeerRecord {
+    /// Lifts the expired ban and decays the score.
+    fn refresh(&mut self, config: Option<&PeerBansConfig>, now: SystemTime) {
+        if let Some(Ban {
+            until: Some(until), ..
+        }) = self.ban
+        {
+            if until <= now {
+                self.ban = None;
+                self.ban_end = Some(until);
+            }
+        }
+
+        let decay = config.map_or(0, |config| config.score_decay);
+        if let Some(updated) = self.updated {
+            let elapsed = now.duration_since(updated).unwrap_or_default().as_secs();
+            let decayed = elapsed.saturating_mul(u64::from(decay));
+            if decayed > 0 {
+                // The result is not larger than the current score.
+                self.score = u64::from(self.score).saturating_sub(decayed) as u32;
+                self.updated = Some(now);
+            }
+        }
+    }
+
+    /// Returns `true` if the record no longer affects the address.
+    fn is_stale(&self, config: Option<&PeerBansConfig>, now: SystemTime) -> bool {
+        if self.ban.is_some() || self.score > 0 {
+            return false;
+        }
+        // The escalation is kept for `max_ban_duration` after the latest ban.
+        match (self.ban_end, config) {
+            (Some(ban_end), Some(config)) if self.count > 0 => {
+                expiry(ban_end, config.max_ban_duration).map_or(false, |forget_at| forget_at <= now)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PeerBansInner {
+    config: Option<PeerBansConfig>,
+    peers: HashMap<IpAddr, PeerRecord>,
+}
+
+impl PeerBansInner {
+    fn prune(&mut self, now: SystemTime) {
+        let config = self.config.as_ref();
+        self.peers.retain(|_, record| {
+            record.refresh(config, now);
+            !record.is_stale(config, now)
+        });
+    }
+}
+
+/// Shared list of the banned peer addresses along with the misbehaviour scores
+/// of the addresses.
+///
+/// Each misbehaviour of a peer adds its penalty to the score of the peer address,
+/// and the score decays over time. Once the score reaches the maximum, the address
+/// is banned, and each following automatic ban lasts twice as long as the previous one.
+/// Scores are not tracked unless the configuration is set; the manual bans
+/// are applied regardless of it.
+///
+/// Clones of the list share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct PeerBans(Arc<Mutex<PeerBansInner>>);
+
+impl PeerBans {
+    /// Sets the configuration of the automatic bans, or disables them if `None`.
+    pub(crate) fn set_config(&self, config: Option<PeerBansConfig>) {
+        self.0.lock().expect("PeerBans lock").config = config;
+    }
+
+    /// Returns `true` if the address is banned.
+    pub fn is_banned(&self, address: &IpAddr) -> bool {
+        self.is_banned_at(address, SystemTime::now())
+    }
+
+    /// Increases the score of the address according to the misbehaviour and bans
+    /// the address if the score reaches the maximum. Returns `true` if the address
+    /// has been banned as the result.
+    pub fn record(&self, address: IpAddr, misbehavior: Misbehavior) -> bool {
+        self.record_at(address, misbehavior, SystemTime::now())
+    }
+
+    /// Bans the address for the given duration in seconds, or until it is unbanned
+    /// manually if the duration is not specified.
+    pub fn ban(&self, address: IpAddr, duration: Option<u64>) {
+        self.ban_at(address, duration, SystemTime::now())
+    }
+
+    /// Lifts the ban of the address. Returns `false` if the address is not banned.
+    pub fn unban(&self, address: &IpAddr) -> bool {
+        self.unban_at(address, SystemTime::now())
+    }
+
+    /// Returns the banned addresses.
+    pub fn banned(&self) -> Vec<BanInfo> {
+        self.banned_at(SystemTime::now())
+    }
+
+    fn is_banned_at(&self, address: &IpAddr, now: SystemTime) -> bool {
+        let mut inner = self.0.lock().expect("PeerBans lock");
+        let config = inner.config;
+        inner.peers.get_mut(address).map_or(false, |record| {
+            record.refresh(config.as_ref(), now);
+            record.ban.is_some()
+        })
+    }
+
+    fn record_at(&self, address: IpAddr, misbehavior: Misbehavior, now: SystemTime) -> bool {
+        let mut inner = self.0.lock().expect("PeerBans lock");
+        let config = match inner.config {
+            Some(config) => config,
+            None => return false,
+        };
+        inner.prune(now);
+
+        let record = inner
+            .peers
+            .entry(address)
+            .or_insert_with(PeerRecord::default);
+        if record.ban.is_some() {
+            return false;
+        }
+        record.score = record.score.saturating_add(misbehavior.penalty());
+        record.updated = Some(now);
+        debug!(
+            "Peer {} misbehaved ({:?}), the score is {}",
+            address, misbehavior, record.score
+        );
+        if record.score < config.max_score {
+            return false;
+        }
+
+        let duration = 1_u64
+            .checked_shl(record.count)
+            .and_then(|factor| config.ban_duration.checked_mul(factor))
+            .map_or(config.max_ban_duration, |duration| {
+                cmp::min(duration, config.max_ban_duration)
+            });
+        record.score = 0;
+        record.count += 1;
+        record.ban = Some(Ban {
+            until: expiry(now, duration),
+            manual: false,
+        });
+        warn!(
+            "Banned peer {} for {} s after the repeated misbehaviour ({:?})",
+            address, duration, misbehavior
+        );
+        true
+    }
+
+    fn ban_at(&self, address: IpAddr, duration: Option<u64>, now: SystemTime) {
+        let mut inner = self.0.lock().expect("PeerBans lock");
+        inner.prune(now);
+        let record = inner
+            .peers
+            .entry(address)
+            .or_insert_with(PeerRecord::default);
+        record.ban = Some(Ban {
+            until: duration.and_then(|duration| expiry(now, duration)),
+            manual: true,
+        });
+        info!("Banned peer {} manually", address);
+    }
+
+    fn unban_at(&self, address: &IpAddr, now: SystemTime) -> bool {
+        let mut inner = self.0.lock().expect("PeerBans lock");
+        inner.prune(now);
+        let record = match inner.peers.get_mut(address) {
+            Some(record) => record,
+            None => return false,
+        };
+        if record.ban.take().is_none() {
+            return false;
+        }
+        record.score = 0;
+        record.ban_end = Some(now);
+        info!("Unbanned peer {}", address);
+        true
+    }
+
+    fn banned_at(&self, now: SystemTime) -> Vec<BanInfo> {
+        let mut inner = self.0.lock().expect("PeerBans lock");
+        inner.prune(now);
+        let mut banned = inner
+            .peers
+            .iter()
+            .filter_map(|(address, record)| {
+                record.ban.map(|ban| BanInfo {
+                    address: *address,
+                    until: ban.until.map(DateTime::from),
+                    manual: ban.manual,
+                    count: record.count,
+                })
+            })
+            .collect::<Vec<_>>();
+        banned.sort_by_key(|info| info.address);
+        banned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: PeerBansConfig = PeerBansConfig {
+        max_score: 100,
+        score_decay: 1,
+        ban_duration: 60,
+        max_ban_duration: 200,
+    };
+
+    fn bans() -> PeerBans {
+        let bans = PeerBans::default();
+        bans.set_config(Some(CONFIG));
+        bans
+    }
+
+    fn address() -> IpAddr {
+        "10.0.0.1".parse().unwrap()
+    }
+
+    fn misbehave(bans: &PeerBans, times: usize, now: SystemTime) -> bool {
+        (0..times).fold(false, |banned, _| {
+            bans.record_at(address(), Misbehavior::InvalidMessage, now) || banned
+        })
+    }
+
+    #[test]
+    fn ban_after_max_score() {
+        let bans = bans();
+        let now = SystemTime::now();
+
+        assert!(!misbehave(&bans, 9, now));
+        assert!(!bans.is_banned_at(&address(), now));
+        assert!(misbehave(&bans, 1, now));
+        assert!(bans.is_banned_at(&address(), now));
+        assert!(!bans.is_banned_at(&"10.0.0.2".parse().unwrap(), now));
+
+        let info = bans.banned_at(now);
+        assert_eq!(info.len(), 1);
+        assert_eq!(info[0].address, address());
+        assert_eq!(info[0].count, 1);
+        assert!(!info[0].manual);
+
+        let expired = now + Duration::from_secs(60);
+        assert!(!bans.is_banned_at(&address(), expired));
+        assert!(bans.banned_at(expired).is_empty());
+    }
+
+    #[test]
+    fn score_decay() {
+        let bans = bans();
+        let now = SystemTime::now();
+
+        assert!(!misbehave(&bans, 9, now));
+        // 50 points are forgiven after 50 seconds.
+        let later = now + Duration::from_secs(50);
+        assert!(!misbehave(&bans, 5, later));
+        assert!(!bans.is_banned_at(&address(), later));
+        assert!(misbehave(&bans, 1, later));
+    }
+
+    #[test]
+    fn escalating_bans() {
+        let bans = bans();
+        let mut now = SystemTime::now();
+
+        for &duration in &[60, 120, 200, 200] {
+            assert!(misbehave(&bans, 10, now));
+            let until = bans.banned_at(now)[0].until.unwrap();
+            assert_eq!(
+                until,
+                DateTime::<Utc>::from(now + Duration::from_secs(duration))
+            );
+            now += Duration::from_secs(duration);
+            assert!(!bans.is_banned_at(&address(), now));
+        }
+
+        // The escalation is forgotten after `max_ban_duration`.
+        now += Duration::from_secs(200);
+        assert!(misbehave(&bans, 10, now));
+        assert_eq!(bans.banned_at(now)[0].count, 1);
+    }
+
+    #[test]
+    fn manual_bans() {
+        let bans = PeerBans::default();
+        let now = SystemTime::now();
+
+        // Scores are not tracked without the configuration.
+        assert!(!misbehave(&bans, 100, now));
+        assert!(!bans.is_banned_at(&address(), now));
+
+        bans.ban_at(address(), None, now);
+        let later = now + Duration::from_secs(1_000_000);
+        assert!(bans.is_banned_at(&address(), later));
+        assert!(bans.banned_at(later)[0].manual);
+        assert!(bans.unban_at(&address(), later));
+        assert!(!bans.is_banned_at(&address(), later));
+        assert!(!bans.unban_at(&address(), later));
+
+        bans.ban_at(address(), Some(10), now);
+        assert!(bans.is_banned_at(&address(), now));
+        assert!(!bans.is_banned_at(&address(), now + Duration::from_secs(10)));
+    }
+}
@@ -19,6 +19,7 @@ use std::{net::SocketAddr, thread};
 use events::{
     network::NetworkConfiguration,
     tests::{raw_message, ConnectionParams, TestEvents},
+    PeerBans, ProxyConfig,
 };
 use node::{state::SharedConnectList, ConnectList, EventsPoolCapacity};
 
@@ -42,6 +43,8 @@ fn test_events(
         network_config,
         events_config: EventsPoolCapacity::default(),
         connect_list,
+        peer_bans: PeerBans::default(),
+        proxy: ProxyConfig::default(),
     }
 }
 
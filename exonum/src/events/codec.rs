@@ -17,22 +17,61 @@ use bytes::BytesMut;
 use std::mem;
 use tokio_io::codec::{Decoder, Encoder};
 
-use crate::events::noise::{TransportWrapper, HEADER_LENGTH as NOISE_HEADER_LENGTH};
+use crate::events::{
+    compression::TransportCompression,
+    noise::{
+        TransportWrapper, HEADER_LENGTH as NOISE_HEADER_LENGTH, MAX_MESSAGE_LENGTH, TAG_LENGTH,
+    },
+};
 use crate::messages::{SignedMessage, EMPTY_SIGNED_MESSAGE_SIZE};
 
+/// Length of the flag preceding each chunk of a message.
+const CHUNK_FLAG_LENGTH: usize = 1;
+/// Maximum length of a message chunk. Messages are split into chunks of this length, so
+/// that each frame fits into a single Noise packet.
+pub const MAX_CHUNK_LENGTH: usize = MAX_MESSAGE_LENGTH - TAG_LENGTH - CHUNK_FLAG_LENGTH;
+/// Flag of a chunk which is followed by other chunks of the same message.
+const MORE_CHUNKS: u8 = 1;
+/// Offset of the identifier of the message compression in the chunk flag.
+const COMPRESSION_SHIFT: u8 = 1;
+/// Minimum length of the messages which are compressed. Shorter messages, such as
+/// most of the consensus messages, are not worth compressing.
+const MIN_COMPRESSED_LENGTH: usize = 256;
+
 #[derive(Debug)]
 pub struct MessagesCodec {
-    /// Maximum message length (in bytes), gets populated from `ConsensusConfig`.
+    /// Maximum message length (in bytes), gets populated from `NetworkConfiguration`
+    /// or `ConsensusConfig`.
     max_message_len: u32,
     /// Noise session to encrypt/decrypt messages.
     session: TransportWrapper,
+    /// Compression negotiated with the peer.
+    compression: Option<TransportCompression>,
+    /// Chunks of the message being received.
+    chunks: Vec<u8>,
 }
 
 impl MessagesCodec {
-    pub fn new(max_message_len: u32, session: TransportWrapper) -> Self {
+    pub fn new(
+        max_message_len: u32,
+        session: TransportWrapper,
+        compression: Option<TransportCompression>,
+    ) -> Self {
         Self {
             max_message_len,
             session,
+            compression,
+            chunks: Vec::new(),
+        }
+    }
+
+    fn frame_compression(&self, flag: u8) -> Result<Option<TransportCompression>, failure::Error> {
+        match flag >> COMPRESSION_SHIFT {
+            0 => Ok(None),
+            id => match TransportCompression::from_id(id) {
+                compression @ Some(_) if compression == self.compression => Ok(compression),
+                _ => bail!("Received message with unexpected compression: id = {}", id),
+            },
         }
     }
 }
@@ -42,36 +81,63 @@ impl Decoder for MessagesCodec {
     type Error = failure::Error;
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // Framing level
-        if buf.len() < mem::size_of::<u32>() {
-            return Ok(None);
-        }
-
-        let len = LittleEndian::read_u32(buf) as usize;
-
-        if buf.len() < NOISE_HEADER_LENGTH + len {
-            return Ok(None);
-        }
-
-        let buf = self.session.decrypt_msg(len, buf)?;
-
-        if buf.len() > self.max_message_len as usize {
-            bail!(
-                "Received message is too long: received_len = {}, allowed_len = {}",
-                buf.len(),
-                self.max_message_len
-            )
-        }
-
-        if buf.len() <= EMPTY_SIGNED_MESSAGE_SIZE {
-            bail!(
-                "Received malicious message with wrong length: received_len = {}, min_len = {}",
-                buf.len(),
-                EMPTY_SIGNED_MESSAGE_SIZE
-            )
+        loop {
+            // Framing level
+            if buf.len() < mem::size_of::<u32>() {
+                return Ok(None);
+            }
+
+            let len = LittleEndian::read_u32(buf) as usize;
+
+            // Each frame is a single Noise packet, so longer frames are rejected before
+            // they are buffered.
+            if len > MAX_MESSAGE_LENGTH {
+                bail!(
+                    "Received frame is too long: received_len = {}, allowed_len = {}",
+                    len,
+                    MAX_MESSAGE_LENGTH
+                )
+            }
+
+            if buf.len() < NOISE_HEADER_LENGTH + len {
+                return Ok(None);
+            }
+
+            let frame = self.session.decrypt_msg(len, buf)?;
+            let (flag, chunk) = match frame.split_first() {
+                Some((&flag, chunk)) => (flag, chunk),
+                None => bail!("Received malformed message chunk"),
+            };
+            let compression = self.frame_compression(flag)?;
+
+            let received_len = self.chunks.len() + chunk.len();
+            if received_len > self.max_message_len as usize {
+                bail!(
+                    "Received message is too long: received_len = {}, allowed_len = {}",
+                    received_len,
+                    self.max_message_len
+                )
+            }
+
+            self.chunks.extend_from_slice(chunk);
+            if flag & MORE_CHUNKS != 0 {
+                continue;
+            }
+
+            let mut message = mem::replace(&mut self.chunks, Vec::new());
+            if let Some(compression) = compression {
+                message = compression.decompress(&message, self.max_message_len as usize)?;
+            }
+            if message.len() <= EMPTY_SIGNED_MESSAGE_SIZE {
+                bail!(
+                    "Received malicious message with wrong length: received_len = {}, min_len = {}",
+                    message.len(),
+                    EMPTY_SIGNED_MESSAGE_SIZE
+                )
+            }
+
+            return Ok(Some(message));
         }
-
-        Ok(Some(buf.to_vec()))
     }
 }
 
@@ -80,7 +146,32 @@ impl Encoder for MessagesCodec {
     type Error = failure::Error;
 
     fn encode(&mut self, msg: Self::Item, buf: &mut BytesMut) -> Result<(), Self::Error> {
-        self.session.encrypt_msg(msg.raw(), buf)?;
+        let compressed;
+        let (raw, compression_flag) = match self.compression {
+            Some(compression) if msg.raw().len() >= MIN_COMPRESSED_LENGTH => {
+                compressed = compression.compress(msg.raw())?;
+                if compressed.len() < msg.raw().len() {
+                    (&compressed[..], compression.id() << COMPRESSION_SHIFT)
+                } else {
+                    (msg.raw(), 0)
+                }
+            }
+            _ => (msg.raw(), 0),
+        };
+
+        let mut chunks = raw.chunks(MAX_CHUNK_LENGTH).peekable();
+        let mut frame = Vec::with_capacity(CHUNK_FLAG_LENGTH + MAX_CHUNK_LENGTH);
+        while let Some(chunk) = chunks.next() {
+            let flag = if chunks.peek().is_some() {
+                compression_flag | MORE_CHUNKS
+            } else {
+                compression_flag
+            };
+            frame.clear();
+            frame.push(flag);
+            frame.extend_from_slice(chunk);
+            self.session.encrypt_msg(&frame, buf)?;
+        }
         Ok(())
     }
 }
@@ -90,7 +181,8 @@ mod test {
     use bytes::BytesMut;
     use tokio_io::codec::{Decoder, Encoder};
 
-    use super::MessagesCodec;
+    use super::{MessagesCodec, MAX_CHUNK_LENGTH};
+    use crate::events::compression::TransportCompression;
     use crate::events::noise::{HandshakeParams, NoiseWrapper, TransportWrapper};
     use crate::messages::{SignedMessage, EMPTY_SIGNED_MESSAGE_SIZE};
 
@@ -140,6 +232,82 @@ mod test {
         assert!(responder.decode_eof(&mut bytes).unwrap().is_none());
     }
 
+    #[test]
+    fn decode_chunked_message() {
+        let (ref mut responder, ref mut initiator) = create_encrypted_codecs();
+        responder.max_message_len = 3 * MAX_CHUNK_LENGTH as u32;
+
+        let data: Vec<u8> = (0..2 * MAX_CHUNK_LENGTH + 10).map(|i| i as u8).collect();
+        let mut bytes: BytesMut = BytesMut::new();
+        initiator
+            .encode(raw_message(data.clone()), &mut bytes)
+            .unwrap();
+
+        // The message is not decoded until all of its chunks are received.
+        let (head, tail) = bytes.split_at(bytes.len() - 1);
+        let mut partial = BytesMut::from(head);
+        assert!(responder.decode(&mut partial).unwrap().is_none());
+        partial.extend_from_slice(tail);
+        assert_eq!(responder.decode(&mut partial).unwrap(), Some(data));
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Received message is too long")]
+    fn decode_chunked_message_too_long() {
+        let (ref mut responder, ref mut initiator) = create_encrypted_codecs();
+        responder.max_message_len = MAX_CHUNK_LENGTH as u32;
+
+        let data = vec![1_u8; MAX_CHUNK_LENGTH + 1];
+        let mut bytes: BytesMut = BytesMut::new();
+        initiator.encode(raw_message(data), &mut bytes).unwrap();
+
+        responder.decode(&mut bytes).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Received frame is too long")]
+    fn decode_frame_too_long() {
+        let (ref mut responder, _) = create_encrypted_codecs();
+
+        // Only the header of the frame is received.
+        let mut bytes: BytesMut = BytesMut::from(vec![0xFF_u8; 8]);
+        responder.decode(&mut bytes).unwrap();
+    }
+
+    #[test]
+    fn decode_compressed_message() {
+        for &compression in &[TransportCompression::Lz4, TransportCompression::Zstd] {
+            let (ref mut responder, ref mut initiator) = create_encrypted_codecs();
+            responder.compression = Some(compression);
+            initiator.compression = Some(compression);
+
+            let data = vec![1_u8; 2 * MAX_CHUNK_LENGTH];
+            responder.max_message_len = data.len() as u32;
+            let mut bytes: BytesMut = BytesMut::new();
+            initiator
+                .encode(raw_message(data.clone()), &mut bytes)
+                .unwrap();
+
+            // The compressed message fits into a single chunk.
+            assert!(bytes.len() < MAX_CHUNK_LENGTH);
+            assert_eq!(responder.decode(&mut bytes).unwrap(), Some(data));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Received message with unexpected compression")]
+    fn decode_message_with_unexpected_compression() {
+        let (ref mut responder, ref mut initiator) = create_encrypted_codecs();
+        initiator.compression = Some(TransportCompression::Zstd);
+
+        let data = vec![1_u8; MAX_CHUNK_LENGTH];
+        let mut bytes: BytesMut = BytesMut::new();
+        initiator.encode(raw_message(data), &mut bytes).unwrap();
+
+        responder.decode(&mut bytes).unwrap();
+    }
+
     fn get_decoded_message(data: &[u8]) -> Result<Option<Vec<u8>>, failure::Error> {
         let (ref mut responder, ref mut initiator) = create_encrypted_codecs();
         let raw = raw_message(data.to_vec());
@@ -189,11 +357,15 @@ mod test {
         let responder_codec = MessagesCodec {
             max_message_len: 10000,
             session: initiator,
+            compression: None,
+            chunks: Vec::new(),
         };
 
         let initiator_codec = MessagesCodec {
             max_message_len: 10000,
             session: responder,
+            compression: None,
+            chunks: Vec::new(),
         };
 
         (responder_codec, initiator_codec)
@@ -0,0 +1,158 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compression of the messages exchanged between peers.
+
+use failure::Error;
+
+use std::{
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+/// Compression algorithm of the messages exchanged between peers.
+///
+/// The nodes advertise the algorithm they use in the `Connect` messages, and a connection
+/// is compressed only if both peers use the same algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportCompression {
+    /// LZ4, which is fast and suits links with high bandwidth.
+    Lz4,
+    /// Zstandard, which compresses better at the cost of CPU time.
+    Zstd,
+}
+
+impl TransportCompression {
+    /// Returns the identifier of the algorithm in the message frames.
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            TransportCompression::Lz4 => 1,
+            TransportCompression::Zstd => 2,
+        }
+    }
+
+    /// Returns the algorithm with the given identifier in the message frames.
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(TransportCompression::Lz4),
+            2 => Some(TransportCompression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the algorithm, as used in the configuration.
+    pub fn name(self) -> &'static str {
+        match self {
+            TransportCompression::Lz4 => "lz4",
+            TransportCompression::Zstd => "zstd",
+        }
+    }
+
+    /// Compresses the data.
+    pub(crate) fn compress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            TransportCompression::Lz4 => {
+                let mut encoder = lz4::EncoderBuilder::new().build(Vec::new())?;
+                encoder.write_all(data)?;
+                let (compressed, result) = encoder.finish();
+                result?;
+                Ok(compressed)
+            }
+            // Level 0 selects the default compression level.
+            TransportCompression::Zstd => Ok(zstd::stream::encode_all(data, 0)?),
+        }
+    }
+
+    /// Decompresses the data, which must not exceed `max_len` bytes when decompressed.
+    pub(crate) fn decompress(self, data: &[u8], max_len: usize) -> Result<Vec<u8>, Error> {
+        let limit = max_len as u64 + 1;
+        let mut decompressed = Vec::new();
+        match self {
+            TransportCompression::Lz4 => {
+                lz4::Decoder::new(data)?
+                    .take(limit)
+                    .read_to_end(&mut decompressed)?;
+            }
+            TransportCompression::Zstd => {
+                zstd::stream::Decoder::new(data)?
+                    .take(limit)
+                    .read_to_end(&mut decompressed)?;
+            }
+        }
+        ensure!(
+            decompressed.len() <= max_len,
+            "Decompressed message is larger than {} bytes",
+            max_len
+        );
+        Ok(decompressed)
+    }
+}
+
+impl fmt::Display for TransportCompression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TransportCompression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lz4" => Ok(TransportCompression::Lz4),
+            "zstd" => Ok(TransportCompression::Zstd),
+            _ => bail!("Unknown compression algorithm: {}", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TransportCompression;
+
+    const ALGORITHMS: [TransportCompression; 2] =
+        [TransportCompression::Lz4, TransportCompression::Zstd];
+
+    #[test]
+    fn compress_decompress() {
+        let data = vec![7_u8; 10_000];
+        for &algorithm in &ALGORITHMS {
+            let compressed = algorithm.compress(&data).unwrap();
+            assert!(compressed.len() < data.len());
+            assert_eq!(algorithm.decompress(&compressed, data.len()).unwrap(), data);
+            assert_eq!(
+                TransportCompression::from_id(algorithm.id()),
+                Some(algorithm)
+            );
+            assert_eq!(
+                algorithm.name().parse::<TransportCompression>().unwrap(),
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn decompress_too_long() {
+        let data = vec![7_u8; 10_000];
+        for &algorithm in &ALGORITHMS {
+            let compressed = algorithm.compress(&data).unwrap();
+            let err = algorithm
+                .decompress(&compressed, data.len() - 1)
+                .unwrap_err();
+            assert!(err.to_string().contains("Decompressed message is larger"));
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection of the candidate addresses of peers based on the connection failures.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+/// Numbers of the consecutive connection failures of the peer addresses.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AddressHealth {
+    failures: Rc<RefCell<HashMap<String, u32>>>,
+}
+
+impl AddressHealth {
+    /// Orders the candidate addresses so that the addresses with fewer connection failures
+    /// are tried first. The addresses with the same number of failures keep their order.
+    pub(crate) fn order<'a, I>(&self, addresses: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let failures = self.failures.borrow();
+        let mut addresses: Vec<_> = addresses.into_iter().map(str::to_owned).collect();
+        addresses.sort_by_key(|address| failures.get(address).cloned().unwrap_or_default());
+        addresses
+    }
+
+    /// Records the successful connection to the address.
+    pub(crate) fn record_success(&self, address: &str) {
+        self.failures.borrow_mut().remove(address);
+    }
+
+    /// Records the failed connection to the address.
+    pub(crate) fn record_failure(&self, address: &str) {
+        let mut failures = self.failures.borrow_mut();
+        let count = failures.entry(address.to_owned()).or_insert(0);
+        *count = count.saturating_add(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AddressHealth;
+
+    #[test]
+    fn order_by_failures() {
+        let addresses = ["127.0.0.1:2000", "[::1]:2000", "localhost:2000"];
+        let health = AddressHealth::default();
+        assert_eq!(health.order(addresses.iter().cloned()), addresses);
+
+        health.record_failure(addresses[0]);
+        assert_eq!(
+            health.order(addresses.iter().cloned()),
+            [addresses[1], addresses[2], addresses[0]]
+        );
+
+        health.record_failure(addresses[1]);
+        health.record_failure(addresses[0]);
+        assert_eq!(
+            health.order(addresses.iter().cloned()),
+            [addresses[2], addresses[1], addresses[0]]
+        );
+
+        health.record_success(addresses[0]);
+        assert_eq!(
+            health.order(addresses.iter().cloned()),
+            [addresses[0], addresses[2], addresses[1]]
+        );
+    }
+}
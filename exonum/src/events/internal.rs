@@ -14,21 +14,114 @@
 
 use futures::{
     future::{self, Either, Executor},
-    sync::mpsc,
+    stream,
+    sync::{mpsc, oneshot},
     Future, Sink, Stream,
 };
 
 use tokio_core::reactor::{Handle, Timeout};
 
-use std::time::{Duration, SystemTime};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
 
-use super::{InternalEvent, InternalRequest, TimeoutRequest};
+use super::{InternalEvent, InternalRequest, Misbehavior, PeerBans, TimeoutRequest};
+use crate::api::node::public::system::VerificationStats;
+use crate::crypto::Hash;
 use crate::messages::{Message, SignedMessage};
 
+/// Counters of the message verification pipeline.
+#[derive(Debug, Default)]
+struct Counters {
+    pending: AtomicU64,
+    verified: AtomicU64,
+    rejected: AtomicU64,
+    saturations: AtomicU64,
+    throttled: AtomicU64,
+    pipelined: AtomicU64,
+    /// Maximum number of messages verified at the same time, or zero if not limited.
+    max_pending: AtomicU64,
+}
+
+/// Shared counters of the messages verified in the thread pool.
+///
+/// Clones of the metrics share the same counters.
+#[derive(Debug, Clone, Default)]
+pub struct VerificationMetrics(Arc<Counters>);
+
+impl VerificationMetrics {
+    /// Returns the current values of the counters.
+    pub fn info(&self) -> VerificationStats {
+        let counters = &self.0;
+        VerificationStats {
+            pending_messages: counters.pending.load(Ordering::Relaxed),
+            verified_messages: counters.verified.load(Ordering::Relaxed),
+            rejected_messages: counters.rejected.load(Ordering::Relaxed),
+            saturations: counters.saturations.load(Ordering::Relaxed),
+            throttled_transactions: counters.throttled.load(Ordering::Relaxed),
+            pipelined_blocks: counters.pipelined.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sets the limit of the messages verified at the same time, which is applied
+    /// to the transactions submitted via the API.
+    pub(crate) fn set_max_pending(&self, max_pending: Option<usize>) {
+        let max_pending = max_pending.map_or(0, |max_pending| max_pending as u64);
+        self.0.max_pending.store(max_pending, Ordering::Relaxed);
+    }
+
+    /// Reserves a place in the pipeline for the transaction submitted via the API,
+    /// which is verified in the API thread. Returns `false` if the pipeline is full,
+    /// in which case the transaction should be rejected.
+    pub(crate) fn try_schedule(&self) -> bool {
+        let max_pending = self.0.max_pending.load(Ordering::Relaxed);
+        let pending = self.0.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        if max_pending != 0 && pending > max_pending {
+            self.0.pending.fetch_sub(1, Ordering::Relaxed);
+            self.0.throttled.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        if pending == max_pending {
+            self.0.saturations.fetch_add(1, Ordering::Relaxed);
+        }
+        true
+    }
+
+    fn record_scheduled(&self, max_pending: usize) {
+        let pending = self.0.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        if pending == max_pending as u64 {
+            self.0.saturations.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_pipelined(&self) {
+        self.0.pipelined.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_verified(&self, is_valid: bool) {
+        self.0.pending.fetch_sub(1, Ordering::Relaxed);
+        if is_valid {
+            self.0.verified.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.0.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct InternalPart {
     pub internal_tx: mpsc::Sender<InternalEvent>,
     pub internal_requests_rx: mpsc::Receiver<InternalRequest>,
+    /// Maximum number of messages verified at the same time, or `None` if not limited.
+    pub max_pending_verifications: Option<usize>,
+    pub verification_metrics: VerificationMetrics,
+    /// Scores of the peers which have sent invalid messages.
+    pub peer_bans: PeerBans,
 }
 
 impl InternalPart {
@@ -46,36 +139,129 @@ impl InternalPart {
         })
     }
 
+    // `verified` is notified as soon as the message is verified, so that the place
+    // in the pipeline is released even if the handler is busy and the verified message
+    // waits for the delivery. Invalid messages are penalized in the scores of their senders
+    // and reported to the handler.
     fn verify_message(
+        address: SocketAddr,
         raw: Vec<u8>,
+        network_id: Option<Hash>,
+        max_decompressed_len: Option<u32>,
         internal_tx: mpsc::Sender<InternalEvent>,
+        verified: oneshot::Sender<()>,
+        metrics: VerificationMetrics,
+        peer_bans: PeerBans,
     ) -> impl Future<Item = (), Error = ()> {
-        future::lazy(|| SignedMessage::from_raw_buffer(raw).and_then(Message::deserialize))
-            .map_err(drop)
-            .and_then(|protocol| {
-                let event = future::ok(InternalEvent::MessageVerified(Box::new(protocol)));
-                Self::send_event(event, internal_tx)
+        let event = future::lazy(move || -> Result<InternalEvent, ()> {
+            let result = Message::from_raw_buffer_with_limit(raw, network_id, max_decompressed_len);
+            if let Err(ref e) = result {
+                debug!("Received invalid message from {}: {}", address, e);
+                peer_bans.record(address.ip(), Misbehavior::InvalidMessage);
+            }
+            metrics.record_verified(result.is_ok());
+            verified.send(()).ok();
+            Ok(match result {
+                Ok(protocol) => InternalEvent::MessageVerified(Box::new(protocol)),
+                Err(_) => InternalEvent::MessageRejected(address),
             })
+        });
+        Self::send_event(event, internal_tx)
+    }
+
+    // The signatures of the messages are verified in a batch. The messages are delivered
+    // one by one, as if they were verified separately.
+    fn verify_messages(
+        raw: Vec<Vec<u8>>,
+        network_id: Option<Hash>,
+        max_decompressed_len: Option<u32>,
+        internal_tx: mpsc::Sender<InternalEvent>,
+        verified: oneshot::Sender<()>,
+        metrics: VerificationMetrics,
+    ) -> impl Future<Item = (), Error = ()> {
+        future::lazy(move || {
+            let messages = SignedMessage::from_raw_buffers_lossy(raw, network_id)
+                .into_iter()
+                .filter_map(|result| {
+                    let result = result.and_then(|message| {
+                        Message::deserialize_with_limit(message, max_decompressed_len)
+                    });
+                    metrics.record_verified(result.is_ok());
+                    result.ok()
+                })
+                .collect::<Vec<_>>();
+            verified.send(()).ok();
+            Ok::<_, ()>(messages)
+        })
+        .and_then(|messages| {
+            let events = messages
+                .into_iter()
+                .map(|message| InternalEvent::MessageVerified(Box::new(message)));
+            internal_tx
+                .send_all(stream::iter_ok::<_, mpsc::SendError<InternalEvent>>(events))
+                .map(drop)
+                .map_err(|_| panic!("cannot send internal event"))
+        })
     }
 
     /// Represents a task that processes Internal Requests and produces Internal Events.
     /// `handle` is used to schedule additional tasks within this task.
     /// `verify_executor` is where transaction verification task is executed.
+    ///
+    /// Messages are verified in the pool while the node handler executes and commits
+    /// blocks. If `max_pending_verifications` is reached, no more requests are taken
+    /// until some of the messages are verified, which in turn makes the node handler
+    /// wait once the requests channel is full.
     pub fn run<E>(self, handle: Handle, verify_executor: E) -> impl Future<Item = (), Error = ()>
     where
         E: Executor<Box<dyn Future<Item = (), Error = ()> + Send>>,
     {
         let internal_tx = self.internal_tx;
+        let metrics = self.verification_metrics;
+        let peer_bans = self.peer_bans;
+        let max_pending = self
+            .max_pending_verifications
+            .unwrap_or_else(usize::max_value);
 
         self.internal_requests_rx
             .map(move |request| {
                 let event = match request {
-                    InternalRequest::VerifyMessage(tx) => {
-                        let fut = Self::verify_message(tx, internal_tx.clone());
+                    InternalRequest::VerifyMessage(address, tx, network_id, max_len) => {
+                        let (verified_tx, verified_rx) = oneshot::channel();
+                        metrics.record_scheduled(max_pending);
+                        let fut = Self::verify_message(
+                            address,
+                            tx,
+                            network_id,
+                            max_len,
+                            internal_tx.clone(),
+                            verified_tx,
+                            metrics.clone(),
+                            peer_bans.clone(),
+                        );
                         verify_executor
                             .execute(Box::new(fut))
                             .expect("cannot schedule message verification");
-                        return;
+                        return Either::A(verified_rx.then(|_| Ok::<(), ()>(())));
+                    }
+
+                    InternalRequest::VerifyMessages(messages, network_id, max_len) => {
+                        let (verified_tx, verified_rx) = oneshot::channel();
+                        for _ in &messages {
+                            metrics.record_scheduled(max_pending);
+                        }
+                        let fut = Self::verify_messages(
+                            messages,
+                            network_id,
+                            max_len,
+                            internal_tx.clone(),
+                            verified_tx,
+                            metrics.clone(),
+                        );
+                        verify_executor
+                            .execute(Box::new(fut))
+                            .expect("cannot schedule message verification");
+                        return Either::A(verified_rx.then(|_| Ok::<(), ()>(())));
                     }
 
                     InternalRequest::Timeout(TimeoutRequest(time, timeout)) => {
@@ -104,7 +290,9 @@ impl InternalPart {
 
                 let send_event = Self::send_event(event, internal_tx.clone());
                 handle.spawn(send_event);
+                Either::B(future::ok(()))
             })
+            .buffer_unordered(max_pending)
             .for_each(Ok)
     }
 }
@@ -117,14 +305,30 @@ mod tests {
 
     use super::*;
     use crate::crypto::{gen_keypair, Signature};
+    use crate::node::PeerBansConfig;
+
+    fn address() -> SocketAddr {
+        "127.0.0.1:6333".parse().unwrap()
+    }
 
     fn verify_message(msg: Vec<u8>) -> Option<InternalEvent> {
+        verify_message_with(msg, VerificationMetrics::default(), PeerBans::default())
+    }
+
+    fn verify_message_with(
+        msg: Vec<u8>,
+        metrics: VerificationMetrics,
+        peer_bans: PeerBans,
+    ) -> Option<InternalEvent> {
         let (internal_tx, internal_rx) = mpsc::channel(16);
         let (internal_requests_tx, internal_requests_rx) = mpsc::channel(16);
 
         let internal_part = InternalPart {
             internal_tx,
             internal_requests_rx,
+            max_pending_verifications: Some(1),
+            verification_metrics: metrics,
+            peer_bans,
         };
 
         let thread = thread::spawn(|| {
@@ -140,7 +344,7 @@ mod tests {
             core.run(task).unwrap()
         });
 
-        let request = InternalRequest::VerifyMessage(msg);
+        let request = InternalRequest::VerifyMessage(address(), msg, None, None);
         internal_requests_tx.wait().send(request).unwrap();
         thread.join().unwrap()
     }
@@ -148,7 +352,7 @@ mod tests {
     #[test]
     fn verify_msg() {
         let (pk, sk) = gen_keypair();
-        let tx = SignedMessage::new(0, 0, &[0; 200], pk, &sk);
+        let tx = SignedMessage::new(0, 0, &[0; 200], None, pk, &sk);
 
         let expected_event =
             InternalEvent::MessageVerified(Box::new(Message::deserialize(tx.clone()).unwrap()));
@@ -162,6 +366,184 @@ mod tests {
         let tx = SignedMessage::new_with_signature(0, 0, &[0; 200], pk, Signature::zero());
 
         let event = verify_message(tx.raw().to_vec());
-        assert_eq!(event, None);
+        assert_eq!(event, Some(InternalEvent::MessageRejected(address())));
+    }
+
+    #[test]
+    fn verification_metrics() {
+        let (pk, sk) = gen_keypair();
+        let metrics = VerificationMetrics::default();
+
+        let tx = SignedMessage::new(0, 0, &[0; 200], None, pk, &sk);
+        let peer_bans = PeerBans::default();
+        assert!(
+            verify_message_with(tx.raw().to_vec(), metrics.clone(), peer_bans.clone()).is_some()
+        );
+        let tx = SignedMessage::new_with_signature(0, 0, &[0; 200], pk, Signature::zero());
+        assert_eq!(
+            verify_message_with(tx.raw().to_vec(), metrics.clone(), peer_bans),
+            Some(InternalEvent::MessageRejected(address()))
+        );
+
+        let stats = metrics.info();
+        assert_eq!(stats.pending_messages, 0);
+        assert_eq!(stats.verified_messages, 1);
+        assert_eq!(stats.rejected_messages, 1);
+        assert_eq!(stats.saturations, 2);
+    }
+
+    #[test]
+    fn ban_sender_of_invalid_messages() {
+        let (pk, sk) = gen_keypair();
+        let peer_bans = PeerBans::default();
+        peer_bans.set_config(Some(PeerBansConfig {
+            max_score: Misbehavior::InvalidMessage.penalty() * 2,
+            score_decay: 0,
+            ban_duration: 60,
+            max_ban_duration: 60,
+        }));
+        let metrics = VerificationMetrics::default();
+
+        let tx = SignedMessage::new_with_signature(0, 0, &[0; 200], pk, Signature::zero());
+        assert_eq!(
+            verify_message_with(tx.raw().to_vec(), metrics.clone(), peer_bans.clone()),
+            Some(InternalEvent::MessageRejected(address()))
+        );
+        assert!(!peer_bans.is_banned(&address().ip()));
+        // Valid messages do not affect the score.
+        let tx = SignedMessage::new(0, 0, &[0; 200], None, pk, &sk);
+        assert!(
+            verify_message_with(tx.raw().to_vec(), metrics.clone(), peer_bans.clone()).is_some()
+        );
+        assert!(!peer_bans.is_banned(&address().ip()));
+
+        let tx = SignedMessage::new_with_signature(0, 0, &[1; 200], pk, Signature::zero());
+        assert_eq!(
+            verify_message_with(tx.raw().to_vec(), metrics, peer_bans.clone()),
+            Some(InternalEvent::MessageRejected(address()))
+        );
+        assert!(peer_bans.is_banned(&address().ip()));
+    }
+
+    #[test]
+    fn bounded_verification_pipeline() {
+        const MESSAGES_COUNT: usize = 8;
+
+        let (internal_tx, internal_rx) = mpsc::channel(MESSAGES_COUNT);
+        let (internal_requests_tx, internal_requests_rx) = mpsc::channel(MESSAGES_COUNT);
+        let metrics = VerificationMetrics::default();
+
+        let internal_part = InternalPart {
+            internal_tx,
+            internal_requests_rx,
+            max_pending_verifications: Some(2),
+            verification_metrics: metrics.clone(),
+            peer_bans: PeerBans::default(),
+        };
+
+        let thread = thread::spawn(|| {
+            let mut core = Core::new().unwrap();
+            let handle = core.handle();
+            let verifier = core.handle();
+
+            let task = internal_part
+                .run(handle, verifier)
+                .map_err(drop)
+                .and_then(|()| internal_rx.collect().map_err(drop));
+            core.run(task).unwrap()
+        });
+
+        let (pk, sk) = gen_keypair();
+        let mut sender = internal_requests_tx.wait();
+        for i in 0..MESSAGES_COUNT {
+            let tx = SignedMessage::new(0, 0, &[i as u8; 200], None, pk, &sk);
+            let request = InternalRequest::VerifyMessage(address(), tx.raw().to_vec(), None, None);
+            sender.send(request).unwrap();
+        }
+        drop(sender);
+
+        let events = thread.join().unwrap();
+        assert_eq!(events.len(), MESSAGES_COUNT);
+        let stats = metrics.info();
+        assert_eq!(stats.pending_messages, 0);
+        assert_eq!(stats.verified_messages, MESSAGES_COUNT as u64);
+    }
+
+    #[test]
+    fn verify_messages_batch() {
+        let (internal_tx, internal_rx) = mpsc::channel(4);
+        let (internal_requests_tx, internal_requests_rx) = mpsc::channel(4);
+        let metrics = VerificationMetrics::default();
+
+        let internal_part = InternalPart {
+            internal_tx,
+            internal_requests_rx,
+            max_pending_verifications: Some(1),
+            verification_metrics: metrics.clone(),
+            peer_bans: PeerBans::default(),
+        };
+
+        let thread = thread::spawn(|| {
+            let mut core = Core::new().unwrap();
+            let handle = core.handle();
+            let verifier = core.handle();
+
+            let task = internal_part
+                .run(handle, verifier)
+                .map_err(drop)
+                .and_then(|()| internal_rx.collect().map_err(drop));
+            core.run(task).unwrap()
+        });
+
+        let (pk, sk) = gen_keypair();
+        let mut messages = (0..3_u8)
+            .map(|i| {
+                SignedMessage::new(0, 0, &[i; 200], None, pk, &sk)
+                    .raw()
+                    .to_vec()
+            })
+            .collect::<Vec<_>>();
+        // An invalid message does not prevent the others from being delivered.
+        let invalid_tx = SignedMessage::new_with_signature(0, 0, &[3; 200], pk, Signature::zero());
+        messages.insert(1, invalid_tx.raw().to_vec());
+
+        let mut sender = internal_requests_tx.wait();
+        sender
+            .send(InternalRequest::VerifyMessages(messages, None, None))
+            .unwrap();
+        drop(sender);
+
+        let events = thread.join().unwrap();
+        assert_eq!(events.len(), 3);
+        let stats = metrics.info();
+        assert_eq!(stats.pending_messages, 0);
+        assert_eq!(stats.verified_messages, 3);
+        assert_eq!(stats.rejected_messages, 1);
+    }
+
+    #[test]
+    fn throttle_api_transactions() {
+        let metrics = VerificationMetrics::default();
+        metrics.set_max_pending(Some(2));
+
+        assert!(metrics.try_schedule());
+        assert!(metrics.try_schedule());
+        assert!(!metrics.try_schedule());
+        metrics.record_verified(true);
+        assert!(metrics.try_schedule());
+        metrics.record_verified(false);
+        metrics.record_verified(true);
+
+        let stats = metrics.info();
+        assert_eq!(stats.pending_messages, 0);
+        assert_eq!(stats.verified_messages, 2);
+        assert_eq!(stats.rejected_messages, 1);
+        assert_eq!(stats.saturations, 2);
+        assert_eq!(stats.throttled_transactions, 1);
+
+        // The number of messages is not limited by default.
+        let metrics = VerificationMetrics::default();
+        assert!((0..100).all(|_| metrics.try_schedule()));
+        assert_eq!(metrics.info().pending_messages, 100);
     }
 }
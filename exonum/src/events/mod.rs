@@ -14,14 +14,26 @@
 
 #![allow(missing_debug_implementations, missing_docs)]
 
+pub use self::bans::{BanInfo, Misbehavior, PeerBans};
+pub use self::compression::TransportCompression;
 pub use self::internal::InternalPart;
 pub use self::network::{NetworkConfiguration, NetworkEvent, NetworkPart, NetworkRequest};
+pub use self::proxy::{Proxy, ProxyConfig};
+pub use self::transport::Transport;
 
+pub mod bans;
 pub mod codec;
+pub mod compression;
 pub mod error;
+pub mod health;
 pub mod internal;
 pub mod network;
 pub mod noise;
+pub mod proxy;
+#[cfg(feature = "quic")]
+pub mod quic;
+pub mod throttle;
+pub mod transport;
 
 use futures::{
     sink::Wait,
@@ -29,8 +41,9 @@ use futures::{
     Async, Future, Poll, Stream,
 };
 
-use std::{cmp::Ordering, time::SystemTime};
+use std::{cmp::Ordering, net::SocketAddr, time::SystemTime};
 
+use crate::crypto::Hash;
 use crate::helpers::{Height, Round};
 use crate::messages::Message;
 use crate::node::{ExternalMessage, NodeTimeout};
@@ -55,6 +68,8 @@ pub enum InternalEvent {
     /// Message has been successfully verified.
     /// Message is boxed here so that enum variants have similar size.
     MessageVerified(Box<Message>),
+    /// Message received from the given address has failed the verification.
+    MessageRejected(SocketAddr),
 }
 
 #[derive(Debug)]
@@ -63,8 +78,15 @@ pub enum InternalRequest {
     Timeout(TimeoutRequest),
     JumpToRound(Height, Round),
     Shutdown,
-    /// Async request to verify a message in the thread pool.
-    VerifyMessage(Vec<u8>),
+    /// Async request to verify a message received from the given address in the thread pool.
+    /// The message must be signed for the network with the given identifier. The payload
+    /// of a compressed message is decompressed up to the given length, see
+    /// `ConsensusConfig::max_decompressed_len`.
+    VerifyMessage(SocketAddr, Vec<u8>, Option<Hash>, Option<u32>),
+    /// Async request to verify several messages signed for the network with the given
+    /// identifier at once in the thread pool. The payloads of compressed messages are
+    /// decompressed up to the given length.
+    VerifyMessages(Vec<Vec<u8>>, Option<Hash>, Option<u32>),
 }
 
 #[derive(Debug, PartialEq, Eq)]
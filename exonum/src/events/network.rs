@@ -18,7 +18,6 @@ use futures::{
     sync::mpsc,
     unsync, Future, IntoFuture, Sink, Stream,
 };
-use tokio::net::{TcpListener, TcpStream};
 use tokio_codec::Framed;
 use tokio_core::reactor::Handle;
 
@@ -27,23 +26,29 @@ use tokio_retry::{
     Retry,
 };
 
-use std::{cell::RefCell, collections::HashMap, net::SocketAddr, rc::Rc, time::Duration};
+use std::{cell::RefCell, collections::HashMap, io, net::SocketAddr, rc::Rc};
 
-use super::{error::log_error, to_box};
+use super::{
+    error::log_error, to_box, Misbehavior, PeerBans, Proxy, ProxyConfig, TransportCompression,
+};
 use crate::{
     crypto::PublicKey,
     events::{
         codec::MessagesCodec,
         error::into_failure,
+        health::AddressHealth,
         noise::{Handshake, HandshakeParams, NoiseHandshake},
+        throttle::{
+            fair_queue, peer_queue, throttle, FairQueueSender, PeerQueueReceiver, PeerQueueSender,
+            DEFAULT_PEER_QUEUE_LEN,
+        },
+        transport::{self, Connector, Incoming, PeerStream, Transport},
     },
     helpers::Milliseconds,
-    messages::{Connect, Message, Service, Signed, SignedMessage},
-    node::state::SharedConnectList,
+    messages::{Connect, Message, Service, Signed, SignedMessage, PROTOCOL_MAJOR_VERSION},
+    node::{state::SharedConnectList, PeerAddress},
 };
 
-const OUTGOING_CHANNEL_SIZE: usize = 10;
-
 #[derive(Debug, Clone)]
 pub enum ConnectedPeerAddr {
     In(SocketAddr),
@@ -57,11 +62,18 @@ impl ConnectedPeerAddr {
             ConnectedPeerAddr::Out(_, _) => false,
         }
     }
+
+    /// Returns the socket address of the peer.
+    pub fn socket_address(&self) -> SocketAddr {
+        match self {
+            ConnectedPeerAddr::In(address) | ConnectedPeerAddr::Out(_, address) => *address,
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum NetworkEvent {
-    MessageReceived(Vec<u8>),
+    MessageReceived(SocketAddr, Vec<u8>),
     PeerConnected(ConnectedPeerAddr, Signed<Connect>),
     PeerDisconnected(PublicKey),
     UnableConnectToPeer(PublicKey),
@@ -83,6 +95,37 @@ pub struct NetworkConfiguration {
     pub tcp_keep_alive: Option<u64>,
     pub tcp_connect_retry_timeout: Milliseconds,
     pub tcp_connect_max_retries: u64,
+    /// Maximum length (in bytes) of messages received from peers. Large messages, such as
+    /// block responses, are transferred in chunks and may exceed `max_message_len` of the
+    /// consensus configuration, which is used if this value is not specified.
+    #[serde(default)]
+    pub max_message_len: Option<u32>,
+    /// Compression of the messages exchanged with peers. A connection is compressed
+    /// only if the peer uses the same compression, which it advertises in its `Connect`
+    /// message. Defaults to `None`, meaning that messages are not compressed.
+    #[serde(default)]
+    pub compression: Option<TransportCompression>,
+    /// Maximum rate in bytes per second of the messages sent to each peer. The messages
+    /// exceeding the rate are delayed. Defaults to `None`, meaning that the rate is not limited.
+    #[serde(default)]
+    pub peer_send_rate: Option<u32>,
+    /// Maximum rate in bytes per second of the messages received from each peer. Reading
+    /// from the peer is paused once the rate is exceeded. Defaults to `None`, meaning that
+    /// the rate is not limited.
+    #[serde(default)]
+    pub peer_receive_rate: Option<u32>,
+    /// Maximum number of the messages queued for sending to each peer. The consensus
+    /// messages are sent before the queued transactions and responses, and the limit
+    /// applies to both kinds of the messages separately. Further messages to the peer
+    /// are dropped until the queue is drained, so that a slow peer does not accumulate
+    /// the messages sent to all peers. Defaults to `None`, meaning that
+    /// `DEFAULT_PEER_QUEUE_LEN` messages may be queued.
+    #[serde(default)]
+    pub peer_queue_len: Option<usize>,
+    /// Transport of the connections with peers. The `tcp_*` options apply only to
+    /// the TCP transport. Defaults to TCP.
+    #[serde(default)]
+    pub transport: Transport,
 }
 
 impl Default for NetworkConfiguration {
@@ -94,6 +137,12 @@ impl Default for NetworkConfiguration {
             tcp_nodelay: true,
             tcp_connect_retry_timeout: 15_000,
             tcp_connect_max_retries: 10,
+            max_message_len: None,
+            compression: None,
+            peer_send_rate: None,
+            peer_receive_rate: None,
+            peer_queue_len: None,
+            transport: Transport::default(),
         }
     }
 }
@@ -107,26 +156,37 @@ pub struct NetworkPart {
     pub network_requests: (mpsc::Sender<NetworkRequest>, mpsc::Receiver<NetworkRequest>),
     pub network_tx: mpsc::Sender<NetworkEvent>,
     pub connect_list: SharedConnectList,
+    pub peer_bans: PeerBans,
+    pub proxy: ProxyConfig,
 }
 
 #[derive(Clone, Debug)]
 struct ConnectionPoolEntry {
-    sender: mpsc::Sender<SignedMessage>,
+    sender: PeerQueueSender,
     address: ConnectedPeerAddr,
 }
 
 #[derive(Clone, Debug)]
 struct ConnectionPool {
     peers: Rc<RefCell<HashMap<PublicKey, ConnectionPoolEntry>>>,
+    queue_len: usize,
+    /// Queue of the messages received from the peers.
+    incoming: FairQueueSender<NetworkEvent>,
 }
 
 impl ConnectionPool {
-    fn new() -> Self {
+    fn new(queue_len: usize, incoming: FairQueueSender<NetworkEvent>) -> Self {
         ConnectionPool {
             peers: Rc::new(RefCell::new(HashMap::new())),
+            queue_len,
+            incoming,
         }
     }
 
+    fn queue(&self) -> (PeerQueueSender, PeerQueueReceiver) {
+        peer_queue(self.queue_len)
+    }
+
     fn count_outgoing(&self) -> usize {
         let peers = self.peers.borrow();
         peers
@@ -135,12 +195,7 @@ impl ConnectionPool {
             .count()
     }
 
-    fn add(
-        &self,
-        key: &PublicKey,
-        address: ConnectedPeerAddr,
-        sender: mpsc::Sender<SignedMessage>,
-    ) {
+    fn add(&self, key: &PublicKey, address: ConnectedPeerAddr, sender: PeerQueueSender) {
         let mut peers = self.peers.borrow_mut();
         peers.insert(*key, ConnectionPoolEntry { sender, address });
     }
@@ -159,38 +214,36 @@ impl ConnectionPool {
         &self,
         key: &PublicKey,
         address: &ConnectedPeerAddr,
-    ) -> mpsc::Receiver<SignedMessage> {
-        let (sender_tx, receiver_rx) = mpsc::channel::<SignedMessage>(OUTGOING_CHANNEL_SIZE);
+    ) -> PeerQueueReceiver {
+        let (sender_tx, receiver_rx) = self.queue();
         self.add(key, address.clone(), sender_tx);
         receiver_rx
     }
 
+    // The message is dropped if the outgoing queue of the peer is full.
     fn send_message(
         &self,
         address: &PublicKey,
         message: SignedMessage,
     ) -> impl Future<Item = (), Error = failure::Error> {
-        let address = *address;
-        let sender_tx = self.peers.borrow();
-        let write_pool = self.clone();
-
-        if let Some(entry) = sender_tx.get(&address) {
-            let sender = &entry.sender;
-            Either::A(
-                sender
-                    .clone()
-                    .send(message)
-                    .map(drop)
-                    .or_else(move |e| {
-                        log_error(e);
-                        write_pool.remove(&address);
-                        Ok(())
-                    })
-                    .map(drop),
-            )
-        } else {
-            Either::B(future::ok(()))
+        let sent = self
+            .peers
+            .borrow()
+            .get(address)
+            .map(|entry| entry.sender.send(message));
+
+        match sent {
+            Some(Ok(false)) => warn!(
+                "Outgoing queue of peer={} is full, the message is dropped",
+                address
+            ),
+            Some(Err(e)) => {
+                log_error(e);
+                self.remove(address);
+            }
+            _ => {}
         }
+        future::ok(())
     }
 
     fn disconnect_with_peer(
@@ -213,8 +266,8 @@ impl ConnectionPool {
 
 struct Connection {
     handle: Handle,
-    socket: Framed<TcpStream, MessagesCodec>,
-    receiver_rx: mpsc::Receiver<SignedMessage>,
+    socket: Framed<PeerStream, MessagesCodec>,
+    receiver_rx: PeerQueueReceiver,
     address: ConnectedPeerAddr,
     key: PublicKey,
 }
@@ -222,8 +275,8 @@ struct Connection {
 impl Connection {
     fn new(
         handle: Handle,
-        socket: Framed<TcpStream, MessagesCodec>,
-        receiver_rx: mpsc::Receiver<SignedMessage>,
+        socket: Framed<PeerStream, MessagesCodec>,
+        receiver_rx: PeerQueueReceiver,
         address: ConnectedPeerAddr,
         key: PublicKey,
     ) -> Self {
@@ -246,9 +299,14 @@ struct NetworkHandler {
     network_tx: mpsc::Sender<NetworkEvent>,
     handshake_params: HandshakeParams,
     connect_list: SharedConnectList,
+    peer_bans: PeerBans,
+    proxy: ProxyConfig,
+    connector: Connector,
+    health: AddressHealth,
 }
 
 impl NetworkHandler {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         handle: Handle,
         address: SocketAddr,
@@ -257,6 +315,9 @@ impl NetworkHandler {
         network_tx: mpsc::Sender<NetworkEvent>,
         handshake_params: HandshakeParams,
         connect_list: SharedConnectList,
+        peer_bans: PeerBans,
+        proxy: ProxyConfig,
+        connector: Connector,
     ) -> Self {
         NetworkHandler {
             handle,
@@ -266,12 +327,15 @@ impl NetworkHandler {
             network_tx,
             handshake_params,
             connect_list,
+            peer_bans,
+            proxy,
+            connector,
+            health: AddressHealth::default(),
         }
     }
 
-    fn listener(self) -> impl Future<Item = (), Error = failure::Error> {
+    fn listener(self, server: Incoming) -> impl Future<Item = (), Error = failure::Error> {
         let listen_address = self.listen_address;
-        let server = TcpListener::bind(&listen_address).unwrap().incoming();
         let pool = self.pool.clone();
 
         let handshake_params = self.handshake_params.clone();
@@ -284,7 +348,6 @@ impl NetworkHandler {
         let incoming_connections_counter: Rc<()> = Rc::default();
 
         server
-            .map_err(into_failure)
             .for_each(move |incoming_connection| {
                 let address = incoming_connection
                     .peer_addr()
@@ -306,11 +369,32 @@ impl NetworkHandler {
                     );
                     return Ok(());
                 }
+                if self.peer_bans.is_banned(&address.ip()) {
+                    warn!(
+                        "Rejected incoming connection with peer={}, the address is banned.",
+                        address
+                    );
+                    return Ok(());
+                }
 
                 let connect_list = self.connect_list.clone();
+                let network_config = self.network_config;
+                let peer_bans = self.peer_bans.clone();
+                let handshake_bans = self.peer_bans.clone();
+                let handshake_connect_list = self.connect_list.clone();
                 let listener = handshake
                     .listen(incoming_connection)
                     .and_then(move |(socket, raw)| (Ok(socket), Self::parse_connect_msg(Some(raw))))
+                    .map_err(move |e| {
+                        // I/O errors, such as a reset connection, are not caused by the peer.
+                        // The peers from the connect list are not penalized, since their
+                        // handshakes may fail while they restart.
+                        let is_io_error = e.downcast_ref::<io::Error>().is_some();
+                        if !is_io_error && !handshake_connect_list.is_ip_listed(&address.ip()) {
+                            handshake_bans.record(address.ip(), Misbehavior::HandshakeFailure);
+                        }
+                        e
+                    })
                     .and_then(move |(socket, message)| {
                         if pool.contains(&message.author()) {
                             Box::new(future::ok(()))
@@ -328,6 +412,8 @@ impl NetworkHandler {
                                 connection,
                                 message,
                                 pool,
+                                peer_bans,
+                                network_config,
                                 &network_tx,
                             ))
                         } else {
@@ -363,43 +449,55 @@ impl NetworkHandler {
             .map(jitter)
             .take(max_tries);
 
-        let unresolved_address = self
-            .connect_list
-            .find_address_by_key(&key)
-            .map(|a| a.address.clone());
-
-        if let Some(unresolved_address) = unresolved_address {
+        if let Some(peer_address) = self.connect_list.find_address_by_key(&key) {
+            let proxy = self.proxy.proxy(&key);
+            let is_proxied = proxy.is_proxied();
             let action = {
-                let unresolved_address = unresolved_address.clone();
-                move || tokio_dns::TcpStream::connect(unresolved_address.as_str())
+                let health = self.health.clone();
+                let connector = self.connector.clone();
+                move || Self::connect_to_candidates(&connector, &proxy, &peer_address, &health)
             };
 
-            let (sender_tx, receiver_rx) = mpsc::channel::<SignedMessage>(OUTGOING_CHANNEL_SIZE);
+            let (sender_tx, receiver_rx) = self.pool.queue();
             let pool = self.pool.clone();
+            let peer_bans = self.peer_bans.clone();
+            let connection_bans = self.peer_bans.clone();
             Either::A(
                 Retry::spawn(strategy, action)
                     .map_err(into_failure)
-                    .and_then(move |socket| Self::configure_socket(socket, network_config))
-                    .and_then(move |outgoing_connection| {
+                    .and_then(
+                        move |(socket, unresolved_address)| -> Result<_, failure::Error> {
+                            // The socket of a proxied connection is connected to the proxy,
+                            // so the address of the peer is known only if it is an IP address.
+                            let address = match unresolved_address.parse::<SocketAddr>() {
+                                Ok(address) if is_proxied => address,
+                                _ => socket.peer_addr()?,
+                            };
+                            ensure!(
+                                !connection_bans.is_banned(&address.ip()),
+                                "Trying to connect to banned peer={}",
+                                address
+                            );
+                            Ok((socket, ConnectedPeerAddr::Out(unresolved_address, address)))
+                        },
+                    )
+                    .and_then(move |(socket, conn_addr)| -> Result<_, failure::Error> {
+                        socket.configure(network_config)?;
+                        Ok((socket, conn_addr))
+                    })
+                    .and_then(move |(outgoing_connection, conn_addr)| {
                         Self::build_handshake_initiator(outgoing_connection, key, &handshake_params)
+                            .map(move |(socket, raw)| (socket, raw, conn_addr))
                     })
-                    .and_then(move |(socket, raw)| (Ok(socket), Self::parse_connect_msg(Some(raw))))
-                    .and_then(move |(socket, message)| {
+                    .and_then(move |(socket, raw, conn_addr)| {
+                        (Ok((socket, conn_addr)), Self::parse_connect_msg(Some(raw)))
+                    })
+                    .and_then(move |((socket, conn_addr), message)| {
                         let connection_limit_reached = pool.count_outgoing() >= max_connections;
                         if pool.contains(&message.author()) || connection_limit_reached {
                             Box::new(future::ok(()))
+                                as Box<dyn Future<Error = failure::Error, Item = ()>>
                         } else {
-                            let addr = match socket.get_ref().peer_addr() {
-                                Ok(addr) => addr,
-                                Err(e) => {
-                                    return Box::new(err(format_err!(
-                                        "Couldn't take peer addr from socket = {}",
-                                        e
-                                    )))
-                                        as Box<dyn Future<Error = failure::Error, Item = ()>>;
-                                }
-                            };
-                            let conn_addr = ConnectedPeerAddr::Out(unresolved_address, addr);
                             pool.add(&key, conn_addr.clone(), sender_tx);
                             let connection = Connection::new(
                                 handle,
@@ -412,6 +510,8 @@ impl NetworkHandler {
                                 connection,
                                 message,
                                 pool,
+                                peer_bans,
+                                network_config,
                                 &network_tx,
                             ))
                         }
@@ -426,8 +526,45 @@ impl NetworkHandler {
         }
     }
 
+    // The candidate addresses of the peer are tried one by one, starting with the ones
+    // with the fewest connection failures.
+    fn connect_to_candidates(
+        connector: &Connector,
+        proxy: &Proxy,
+        peer_address: &PeerAddress,
+        health: &AddressHealth,
+    ) -> Box<dyn Future<Item = (PeerStream, String), Error = io::Error>> {
+        let no_address = io::Error::new(io::ErrorKind::NotFound, "Peer has no addresses");
+        let attempt: Box<dyn Future<Item = _, Error = _>> = Box::new(future::err(no_address));
+        health
+            .order(peer_address.addresses())
+            .into_iter()
+            .fold(attempt, |attempt, address| {
+                let connector = connector.clone();
+                let proxy = proxy.clone();
+                let health = health.clone();
+                Box::new(attempt.or_else(move |_| {
+                    connector
+                        .connect(&proxy, address.clone())
+                        .then(move |result| match result {
+                            Ok(socket) => {
+                                health.record_success(&address);
+                                Ok((socket, address))
+                            }
+                            Err(e) => {
+                                trace!("Failed to connect to {}: {}", address, e);
+                                health.record_failure(&address);
+                                Err(e)
+                            }
+                        })
+                }))
+            })
+    }
+
     fn process_messages(
         pool: &ConnectionPool,
+        peer_bans: PeerBans,
+        network_config: NetworkConfiguration,
         handle: &Handle,
         connection: Connection,
         network_tx: &mpsc::Sender<NetworkEvent>,
@@ -437,11 +574,20 @@ impl NetworkHandler {
         let incoming = Self::process_incoming_messages(
             stream,
             pool.clone(),
+            peer_bans,
+            network_config.peer_receive_rate,
+            handle,
             &connection.key,
+            connection.address.socket_address(),
             network_tx.clone(),
         );
 
-        let outgoing = Self::process_outgoing_messages(sink, connection.receiver_rx);
+        let outgoing = Self::process_outgoing_messages(
+            sink,
+            connection.receiver_rx,
+            network_config.peer_send_rate,
+            handle,
+        );
 
         handle.spawn(incoming);
         handle.spawn(outgoing);
@@ -450,60 +596,92 @@ impl NetworkHandler {
 
     fn process_outgoing_messages<S>(
         sink: SplitSink<S>,
-        receiver_rx: mpsc::Receiver<SignedMessage>,
+        receiver_rx: PeerQueueReceiver,
+        send_rate: Option<u32>,
+        handle: &Handle,
     ) -> impl Future<Item = (), Error = ()>
     where
         S: Sink<SinkItem = SignedMessage, SinkError = failure::Error>,
     {
-        receiver_rx
-            .map_err(|_| format_err!("Receiver is gone."))
-            .forward(sink)
-            .map(drop)
-            .map_err(|e| {
-                error!("Connection terminated: {}: {}", e, e.find_root_cause());
-            })
+        throttle(
+            receiver_rx,
+            send_rate,
+            |message: &SignedMessage| message.raw().len(),
+            handle,
+        )
+        .forward(sink)
+        .map(drop)
+        .map_err(|e| {
+            error!("Connection terminated: {}: {}", e, e.find_root_cause());
+        })
     }
 
+    // The connection is closed once the address of the peer is banned.
+    #[allow(clippy::too_many_arguments)]
     fn process_incoming_messages<S>(
         stream: SplitStream<S>,
         pool: ConnectionPool,
+        peer_bans: PeerBans,
+        receive_rate: Option<u32>,
+        handle: &Handle,
         key: &PublicKey,
+        address: SocketAddr,
         network_tx: mpsc::Sender<NetworkEvent>,
     ) -> impl Future<Item = (), Error = ()>
     where
         S: Stream<Item = Vec<u8>, Error = failure::Error>,
     {
         let key = *key;
-        network_tx
-            .clone()
-            .sink_map_err(into_failure)
-            .send_all(stream.map(NetworkEvent::MessageReceived))
+        let frame_bans = peer_bans.clone();
+        let stream = stream.map_err(move |e| {
+            // I/O errors, such as a reset connection, are not caused by the peer.
+            if e.downcast_ref::<io::Error>().is_none() {
+                frame_bans.record(address.ip(), Misbehavior::Spam);
+            }
+            e
+        });
+        // The next message is not read from the peer until the previous one is let through.
+        let messages = throttle(stream, receive_rate, |raw: &Vec<u8>| raw.len(), handle);
+        let messages = messages.and_then(move |raw| -> Result<NetworkEvent, failure::Error> {
+            ensure!(
+                !peer_bans.is_banned(&address.ip()),
+                "Disconnecting from banned peer={}",
+                address
+            );
+            Ok(NetworkEvent::MessageReceived(address, raw))
+        });
+        // The messages are passed to the node through the fair queue shared by all peers.
+        pool.incoming
+            .register()
+            .into_future()
+            .and_then(|incoming| incoming.sink_map_err(into_failure).send_all(messages))
             .then(move |_| pool.disconnect_with_peer(&key, &network_tx))
             .map_err(|e| {
                 error!("Connection terminated: {}: {}", e, e.find_root_cause());
             })
     }
 
-    fn configure_socket(
-        socket: TcpStream,
-        network_config: NetworkConfiguration,
-    ) -> Result<TcpStream, failure::Error> {
-        socket.set_nodelay(network_config.tcp_nodelay)?;
-        let duration = network_config.tcp_keep_alive.map(Duration::from_millis);
-        socket.set_keepalive(duration)?;
-        Ok(socket)
-    }
-
     fn handle_connection(
         connection: Connection,
         message: Signed<Connect>,
         pool: ConnectionPool,
+        peer_bans: PeerBans,
+        network_config: NetworkConfiguration,
         network_tx: &mpsc::Sender<NetworkEvent>,
     ) -> impl Future<Item = (), Error = failure::Error> {
         trace!("Established connection with peer={:?}", connection.address);
         let handle = connection.handle.clone();
         Self::send_peer_connected_event(&connection.address, message, &network_tx).and_then(
-            move |network_tx| Self::process_messages(&pool, &handle, connection, &network_tx),
+            move |network_tx| {
+                Self::process_messages(
+                    &pool,
+                    peer_bans,
+                    network_config,
+                    &handle,
+                    connection,
+                    &network_tx,
+                )
+            },
         )
     }
 
@@ -511,7 +689,16 @@ impl NetworkHandler {
         let raw = raw.ok_or_else(|| format_err!("Incoming socket closed"))?;
         let message = Message::from_raw_buffer(raw)?;
         match message {
-            Message::Service(Service::Connect(connect)) => Ok(connect),
+            Message::Service(Service::Connect(connect)) => {
+                ensure!(
+                    connect.is_compatible(),
+                    "Remote peer {} uses incompatible protocol version {}, expected {}",
+                    connect.author(),
+                    connect.protocol_version(),
+                    PROTOCOL_MAJOR_VERSION
+                );
+                Ok(connect)
+            }
             other => bail!(
                 "First message from a remote peer is not Connect, got={:?}",
                 other
@@ -613,10 +800,10 @@ impl NetworkHandler {
     }
 
     fn build_handshake_initiator(
-        stream: TcpStream,
+        stream: PeerStream,
         key: PublicKey,
         handshake_params: &HandshakeParams,
-    ) -> impl Future<Item = (Framed<TcpStream, MessagesCodec>, Vec<u8>), Error = failure::Error>
+    ) -> impl Future<Item = (Framed<PeerStream, MessagesCodec>, Vec<u8>), Error = failure::Error>
     {
         let mut handshake_params = handshake_params.clone();
         handshake_params.set_remote_key(key);
@@ -636,17 +823,33 @@ impl NetworkPart {
         // After that completes `cancel_handler` and event loop stopped.
         let (cancel_sender, cancel_handler) = unsync::oneshot::channel::<()>();
 
+        let queue_len = self
+            .network_config
+            .peer_queue_len
+            .unwrap_or(DEFAULT_PEER_QUEUE_LEN);
+        let (incoming_tx, incoming_rx) = fair_queue();
+        let incoming = incoming_rx.forward(self.network_tx.clone().sink_map_err(into_failure));
+
+        let (connector, server) =
+            match transport::bind(self.network_config.transport, &listen_address) {
+                Ok(bound) => bound,
+                Err(e) => return Either::B(future::err(e)),
+            };
+
         let handler = NetworkHandler::new(
             handle.clone(),
             listen_address,
-            ConnectionPool::new(),
+            ConnectionPool::new(queue_len, incoming_tx),
             self.network_config,
             self.network_tx.clone(),
             handshake_params.clone(),
             self.connect_list.clone(),
+            self.peer_bans.clone(),
+            self.proxy,
+            connector,
         );
 
-        let listener = handler.clone().listener();
+        let listener = handler.clone().listener(server);
         let request_handler = handler.request_handler(self.network_requests.1, cancel_sender);
 
         let cancel_handler = cancel_handler.or_else(|e| {
@@ -654,11 +857,12 @@ impl NetworkPart {
             Ok(())
         });
 
-        listener
-            .join(request_handler)
+        let network = listener
+            .join3(request_handler, incoming)
             .map(drop)
             .select(cancel_handler)
             .map_err(|(e, _)| e)
-            .map(drop)
+            .map(drop);
+        Either::A(network)
     }
 }
@@ -28,9 +28,10 @@ use crate::{
     },
     events::{
         codec::MessagesCodec,
+        compression::TransportCompression,
         noise::{Handshake, HandshakeRawMessage, HandshakeResult},
     },
-    messages::{Connect, Signed},
+    messages::{Connect, Message, Service, Signed, SignedMessage},
     node::state::SharedConnectList,
 };
 
@@ -143,11 +144,31 @@ impl NoiseHandshake {
             bail!("peer is not in ConnectList")
         }
 
+        let compression = self.negotiate_compression(&message);
         let noise = self.noise.into_transport_wrapper()?;
-        let framed = MessagesCodec::new(self.max_message_len, noise).framed(stream);
+        let framed = MessagesCodec::new(self.max_message_len, noise, compression).framed(stream);
         Ok((framed, message))
     }
 
+    /// Returns the compression if it is used both by this node and by the peer which
+    /// has sent the `Connect` message. The signature of the message is verified after
+    /// the handshake.
+    fn negotiate_compression(&self, message: &[u8]) -> Option<TransportCompression> {
+        let remote = SignedMessage::from_raw_buffer_unverified(message.to_vec())
+            .and_then(Message::deserialize)
+            .ok()
+            .and_then(|message| match message {
+                Message::Service(Service::Connect(connect)) => connect.compression(),
+                _ => None,
+            });
+        let local = self.connect.compression();
+        if local == remote {
+            local
+        } else {
+            None
+        }
+    }
+
     fn is_peer_allowed(&self, remote_static_key: &x25519::PublicKey) -> bool {
         self.connect_list
             .peers()
@@ -0,0 +1,252 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! QUIC transport of the connections between peers.
+//!
+//! Each connection with a peer is a QUIC connection with a single bidirectional stream,
+//! over which the Noise handshake and the messages are sent in the same way as over TCP.
+//! QUIC encrypts the connection with TLS, but the nodes use self-signed certificates
+//! which are not verified, since the peers are authenticated by the Noise handshake.
+//!
+//! The endpoint and the connections are driven by a separate runtime, since `quinn`
+//! is based on the `std` futures, and the streams are polled by the tasks of the node
+//! through the compatibility layer of `futures`.
+
+use futures::{
+    sync::{mpsc, oneshot},
+    Future, Poll,
+};
+use futures03::{compat::Compat, future, StreamExt, TryFutureExt};
+use quinn::{
+    Certificate, CertificateChain, ClientConfigBuilder, Connecting, Connection, ConnectionDriver,
+    Endpoint, NewConnection, PrivateKey, RecvStream, SendStream, ServerConfigBuilder,
+};
+use rustls::{Certificate as TlsCertificate, RootCertStore, ServerCertVerified, TLSError};
+use tokio02::runtime::{self, Runtime};
+use tokio_io::{AsyncRead, AsyncWrite};
+use webpki::DNSNameRef;
+
+use std::{
+    io::{self, Read, Write},
+    net::SocketAddr,
+    sync::Arc,
+};
+
+/// Server name in the certificates of the nodes.
+const SERVER_NAME: &str = "exonum";
+
+/// QUIC endpoint of the node, which accepts and initiates the connections with peers.
+#[derive(Clone)]
+pub(crate) struct QuicEndpoint {
+    endpoint: Endpoint,
+    // The runtime is stopped once the last clone of the endpoint is dropped.
+    runtime: Arc<Runtime>,
+}
+
+impl QuicEndpoint {
+    /// Binds the endpoint to the address. Returns the endpoint and the streams
+    /// of the accepted connections.
+    pub fn bind(
+        address: &SocketAddr,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<QuicStream>), failure::Error> {
+        let certificate = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_owned()])?;
+        let key = PrivateKey::from_der(&certificate.serialize_private_key_der())?;
+        let certificate = Certificate::from_der(&certificate.serialize_der()?)?;
+        let mut server_config = ServerConfigBuilder::default();
+        server_config.certificate(CertificateChain::from_certs(vec![certificate]), key)?;
+
+        let mut client_config = ClientConfigBuilder::default().build();
+        Arc::get_mut(&mut client_config.crypto)
+            .expect("Client TLS configuration is not shared")
+            .dangerous()
+            .set_certificate_verifier(Arc::new(SkipServerVerification));
+
+        let runtime = runtime::Builder::new()
+            .threaded_scheduler()
+            .core_threads(1)
+            .enable_all()
+            .thread_name("exonum-quic")
+            .build()?;
+        let mut builder = Endpoint::builder();
+        builder.listen(server_config.build());
+        builder.default_client_config(client_config);
+        // The socket of the endpoint is registered in the reactor of the runtime.
+        let (driver, endpoint, incoming) = runtime.enter(|| builder.bind(address))?;
+        runtime.spawn(driver.unwrap_or_else(|e| error!("QUIC endpoint failed: {}", e)));
+
+        let (incoming_tx, incoming_rx) = mpsc::unbounded();
+        runtime.spawn(incoming.for_each(move |connecting| {
+            let incoming_tx = incoming_tx.clone();
+            tokio02::spawn(async move {
+                match accept_stream(connecting).await {
+                    Ok(stream) => {
+                        let _ = incoming_tx.unbounded_send(stream);
+                    }
+                    Err(e) => trace!("Failed to accept QUIC stream: {}", e),
+                }
+            });
+            future::ready(())
+        }));
+
+        let endpoint = QuicEndpoint {
+            endpoint,
+            runtime: Arc::new(runtime),
+        };
+        Ok((endpoint, incoming_rx))
+    }
+
+    /// Connects to the `target` address, which is `host:port`, and opens the stream.
+    /// The resolved addresses of the target are tried one by one, like in the TCP
+    /// connections.
+    pub fn connect(&self, target: String) -> impl Future<Item = QuicStream, Error = io::Error> {
+        let endpoint = self.endpoint.clone();
+        let runtime = Arc::clone(&self.runtime);
+        tokio_dns::resolve_sock_addr(target.as_str()).and_then(move |addresses| {
+            let (stream_tx, stream_rx) = oneshot::channel();
+            runtime.spawn(async move {
+                let _ = stream_tx.send(connect_to_any(endpoint, addresses).await);
+            });
+            stream_rx.then(|result| {
+                result.unwrap_or_else(|_| {
+                    Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "QUIC endpoint is closed",
+                    ))
+                })
+            })
+        })
+    }
+}
+
+/// Connects to the first of the addresses which accepts the connection.
+async fn connect_to_any(endpoint: Endpoint, addresses: Vec<SocketAddr>) -> io::Result<QuicStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "Peer address is not resolved");
+    for address in addresses {
+        let stream = match endpoint.connect(&address, SERVER_NAME) {
+            Ok(connecting) => open_stream(connecting).await,
+            Err(e) => Err(into_io_error(e)),
+        };
+        match stream {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                trace!("Failed to connect to {} over QUIC: {}", address, e);
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Establishes the outgoing connection and opens the stream.
+async fn open_stream(connecting: Connecting) -> io::Result<QuicStream> {
+    let NewConnection {
+        driver, connection, ..
+    } = connecting.await.map_err(into_io_error)?;
+    spawn_driver(driver, connection.remote_address());
+    let (send, recv) = connection.open_bi().await.map_err(into_io_error)?;
+    Ok(QuicStream::new(connection, send, recv))
+}
+
+/// Establishes the incoming connection and accepts the stream. The connections are
+/// accepted once the peer opens the stream, which happens when it starts the handshake.
+async fn accept_stream(connecting: Connecting) -> io::Result<QuicStream> {
+    let NewConnection {
+        driver,
+        connection,
+        mut bi_streams,
+        ..
+    } = connecting.await.map_err(into_io_error)?;
+    spawn_driver(driver, connection.remote_address());
+    match bi_streams.next().await {
+        Some(Ok((send, recv))) => Ok(QuicStream::new(connection, send, recv)),
+        Some(Err(e)) => Err(into_io_error(e)),
+        None => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "QUIC connection is closed",
+        )),
+    }
+}
+
+/// Spawns the driver of the connection with the peer on the current runtime.
+fn spawn_driver(driver: ConnectionDriver, address: SocketAddr) {
+    tokio02::spawn(driver.unwrap_or_else(move |e| {
+        trace!("QUIC connection with {} is closed: {}", address, e);
+    }));
+}
+
+/// Bidirectional stream of the QUIC connection with a peer.
+pub(crate) struct QuicStream {
+    // The connection is kept so that it is not closed while the stream is in use.
+    connection: Connection,
+    send: Compat<SendStream>,
+    recv: Compat<RecvStream>,
+}
+
+impl QuicStream {
+    fn new(connection: Connection, send: SendStream, recv: RecvStream) -> Self {
+        QuicStream {
+            connection,
+            send: Compat::new(send),
+            recv: Compat::new(recv),
+        }
+    }
+
+    /// Returns the current address of the peer.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.connection.remote_address()
+    }
+}
+
+impl Read for QuicStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.recv.read(buf)
+    }
+}
+
+impl Write for QuicStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.send.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send.flush()
+    }
+}
+
+impl AsyncRead for QuicStream {}
+
+impl AsyncWrite for QuicStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        AsyncWrite::shutdown(&mut self.send)
+    }
+}
+
+/// Verifier accepting any certificate of the peer.
+struct SkipServerVerification;
+
+impl rustls::ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _roots: &RootCertStore,
+        _presented_certs: &[TlsCertificate],
+        _dns_name: DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn into_io_error<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
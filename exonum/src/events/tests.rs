@@ -17,7 +17,8 @@ use tokio::util::FutureExt;
 use tokio_core::reactor::Core;
 
 use std::{
-    net::SocketAddr,
+    io::{self, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
     thread,
     time::{self, Duration, SystemTime},
 };
@@ -28,7 +29,7 @@ use crate::events::{
     error::log_error,
     network::{NetworkConfiguration, NetworkPart},
     noise::HandshakeParams,
-    NetworkEvent, NetworkRequest,
+    NetworkEvent, NetworkRequest, PeerBans, Proxy, ProxyConfig, TransportCompression,
 };
 use crate::helpers::user_agent;
 use crate::messages::{Connect, Message, Signed, SignedMessage};
@@ -112,7 +113,7 @@ impl TestHandler {
 
     pub fn wait_for_message(&mut self) -> SignedMessage {
         match self.wait_for_event() {
-            Ok(NetworkEvent::MessageReceived(msg)) => SignedMessage::from_vec_unchecked(msg),
+            Ok(NetworkEvent::MessageReceived(_, msg)) => SignedMessage::from_vec_unchecked(msg),
             Ok(other) => panic!("Unexpected message received, {:?}", other),
             Err(e) => panic!("An error during wait for message occurred, {:?}", e),
         }
@@ -142,6 +143,8 @@ pub struct TestEvents {
     pub network_config: NetworkConfiguration,
     pub events_config: EventsPoolCapacity,
     pub connect_list: SharedConnectList,
+    pub peer_bans: PeerBans,
+    pub proxy: ProxyConfig,
 }
 
 impl TestEvents {
@@ -151,6 +154,8 @@ impl TestEvents {
             network_config: NetworkConfiguration::default(),
             events_config: EventsPoolCapacity::default(),
             connect_list: connect_list.clone(),
+            peer_bans: PeerBans::default(),
+            proxy: ProxyConfig::default(),
         }
     }
 
@@ -184,6 +189,8 @@ impl TestEvents {
             network_requests: channel.network_requests,
             network_tx: network_tx.clone(),
             connect_list: self.connect_list,
+            peer_bans: self.peer_bans,
+            proxy: self.proxy,
         };
 
         let handler_part = TestHandler::new(self.listen_address, network_requests_tx, network_rx);
@@ -259,6 +266,7 @@ impl ConnectionParams {
         );
         let connect_info = ConnectInfo {
             address: address.to_string(),
+            fallback_addresses: Vec::new(),
             public_key,
         };
 
@@ -272,6 +280,18 @@ impl ConnectionParams {
         }
     }
 
+    pub fn with_compression(mut self, compression: Option<TransportCompression>) -> Self {
+        let connect = Connect::new(
+            &self.address.to_string(),
+            time::UNIX_EPOCH.into(),
+            &user_agent::get(),
+        )
+        .with_compression(compression);
+        self.connect = Message::concrete(connect, self.public_key, &self.secret_key);
+        self.handshake_params.connect = self.connect.clone();
+        self
+    }
+
     pub fn spawn(&mut self, events: TestEvents, connect_list: SharedConnectList) -> TestHandler {
         self.handshake_params.connect_list = connect_list.clone();
         events.spawn(&self.handshake_params, self.connect.clone())
@@ -596,3 +616,362 @@ fn test_connect_list_ignore_when_listening() {
     e1.wait_for_connect();
     e2.wait_for_connect();
 }
+
+#[test]
+#[should_panic(expected = "An error during wait for connect occurred")]
+fn test_banned_peer_ignore_when_listening() {
+    let first = "127.0.0.1:20240".parse().unwrap();
+    let second = "127.0.0.1:20241".parse().unwrap();
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    let first_key = t1.connect_info.public_key;
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    connect_list.add(t2.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let e1 = TestEvents::with_addr(first, &connect_list);
+    e1.peer_bans.ban(second.ip(), None);
+    let e2 = TestEvents::with_addr(second, &connect_list);
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    e2.connect_with(first_key, t1.connect.clone());
+    e1.wait_for_connect();
+    e2.wait_for_connect();
+}
+
+#[test]
+fn test_disconnect_banned_peer() {
+    let first = "127.0.0.1:20250".parse().unwrap();
+    let second = "127.0.0.1:20251".parse().unwrap();
+
+    let message = raw_message(1000);
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    let first_key = t1.connect_info.public_key;
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(t2.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let e1 = TestEvents::with_addr(first, &connect_list);
+    let peer_bans = e1.peer_bans.clone();
+    let e2 = TestEvents::with_addr(second, &connect_list);
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    e1.connect_with(second_key, t1.connect.clone());
+    assert_eq!(e2.wait_for_connect(), t1.connect.clone());
+    assert_eq!(e1.wait_for_connect(), t2.connect.clone());
+
+    e2.send_to(first_key, message.clone());
+    assert_eq!(e1.wait_for_message(), message);
+
+    // The connection is closed once a message is received from the banned address.
+    peer_bans.ban(second.ip(), None);
+    e2.send_to(first_key, message);
+    assert_eq!(e1.wait_for_disconnect(), second_key);
+}
+
+#[test]
+fn test_network_compression() {
+    let first = "127.0.0.1:20260".parse().unwrap();
+    let second = "127.0.0.1:20261".parse().unwrap();
+    let third = "127.0.0.1:20262".parse().unwrap();
+
+    let message = raw_message(200_000);
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 =
+        ConnectionParams::from_address(first).with_compression(Some(TransportCompression::Lz4));
+    let first_key = t1.connect_info.public_key;
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 =
+        ConnectionParams::from_address(second).with_compression(Some(TransportCompression::Lz4));
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(t2.connect_info.clone());
+
+    // The third peer uses another compression, so its connection is not compressed.
+    let mut t3 =
+        ConnectionParams::from_address(third).with_compression(Some(TransportCompression::Zstd));
+    let third_key = t3.connect_info.public_key;
+    connect_list.add(t3.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let e1 = TestEvents::with_addr(first, &connect_list);
+    let e2 = TestEvents::with_addr(second, &connect_list);
+    let e3 = TestEvents::with_addr(third, &connect_list);
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list.clone());
+    let mut e3 = t3.spawn(e3, connect_list);
+
+    e1.connect_with(second_key, t1.connect.clone());
+    e2.wait_for_connect();
+    e1.wait_for_connect();
+
+    e1.send_to(second_key, message.clone());
+    assert_eq!(e2.wait_for_message(), message);
+    e2.send_to(first_key, message.clone());
+    assert_eq!(e1.wait_for_message(), message);
+
+    e1.connect_with(third_key, t1.connect.clone());
+    e3.wait_for_connect();
+    e1.wait_for_connect();
+
+    e1.send_to(third_key, message.clone());
+    assert_eq!(e3.wait_for_message(), message);
+    e3.send_to(first_key, message.clone());
+    assert_eq!(e1.wait_for_message(), message);
+}
+
+#[test]
+fn test_network_send_rate() {
+    let first = "127.0.0.1:20290".parse().unwrap();
+    let second = "127.0.0.1:20291".parse().unwrap();
+
+    let message = raw_message(100_000);
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(t2.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let mut e1 = TestEvents::with_addr(first, &connect_list);
+    e1.network_config.peer_send_rate = Some(200_000);
+    let e2 = TestEvents::with_addr(second, &connect_list);
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    e1.connect_with(second_key, t1.connect.clone());
+    e2.wait_for_connect();
+    e1.wait_for_connect();
+
+    // The first two messages fit into the allowance, while the other two are delayed
+    // by half a second each.
+    let start = time::Instant::now();
+    for _ in 0..4 {
+        e1.send_to(second_key, message.clone());
+    }
+    for _ in 0..4 {
+        assert_eq!(e2.wait_for_message(), message);
+    }
+    assert!(start.elapsed() >= Duration::from_millis(900));
+}
+
+#[test]
+fn test_connect_to_fallback_address() {
+    let first = "127.0.0.1:20300".parse().unwrap();
+    let second = "127.0.0.1:20301".parse().unwrap();
+    // Nobody listens at the main address of the second peer.
+    let unreachable = "127.0.0.1:20302";
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(ConnectInfo {
+        address: unreachable.to_owned(),
+        fallback_addresses: vec![second.to_string()],
+        public_key: second_key,
+    });
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let e1 = TestEvents::with_addr(first, &connect_list);
+    let e2 = TestEvents::with_addr(second, &connect_list);
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    e1.connect_with(second_key, t1.connect.clone());
+    assert_eq!(e2.wait_for_connect(), t1.connect.clone());
+    assert_eq!(e1.wait_for_connect(), t2.connect.clone());
+}
+
+/// Accepts the SOCKS5 request of the test proxy and returns the target address.
+fn accept_socks5(client: &mut TcpStream) -> String {
+    let mut greeting = [0; 3];
+    client.read_exact(&mut greeting).unwrap();
+    assert_eq!(greeting, [5, 1, 0]);
+    client.write_all(&[5, 0]).unwrap();
+
+    let mut request = [0; 10];
+    client.read_exact(&mut request).unwrap();
+    assert_eq!(request[..4], [5, 1, 0, 1]);
+    let ip = Ipv4Addr::new(request[4], request[5], request[6], request[7]);
+    let port = u16::from(request[8]) << 8 | u16::from(request[9]);
+    client.write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0]).unwrap();
+    SocketAddr::from((ip, port)).to_string()
+}
+
+/// Accepts the HTTP `CONNECT` request of the test proxy and returns the target address.
+fn accept_http(client: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") {
+        let mut byte = [0; 1];
+        client.read_exact(&mut byte).unwrap();
+        request.push(byte[0]);
+    }
+    let request = String::from_utf8(request).unwrap();
+    let mut words = request.split_whitespace();
+    assert_eq!(words.next(), Some("CONNECT"));
+    let target = words.next().unwrap().to_owned();
+    client
+        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+        .unwrap();
+    target
+}
+
+/// Spawns the proxy relaying a single connection.
+fn spawn_test_proxy(address: SocketAddr, accept: fn(&mut TcpStream) -> String) {
+    let listener = TcpListener::bind(address).unwrap();
+    thread::spawn(move || {
+        let (mut client, _) = listener.accept().unwrap();
+        let target = accept(&mut client);
+        let server = TcpStream::connect(target.as_str()).unwrap();
+
+        let (mut client_reader, mut server_writer) =
+            (client.try_clone().unwrap(), server.try_clone().unwrap());
+        thread::spawn(move || io::copy(&mut client_reader, &mut server_writer));
+        let (mut server_reader, mut client_writer) = (server, client);
+        io::copy(&mut server_reader, &mut client_writer).ok();
+    });
+}
+
+fn check_proxied_connection(
+    addresses: [SocketAddr; 3],
+    proxy: impl FnOnce(PublicKey) -> ProxyConfig,
+    accept: fn(&mut TcpStream) -> String,
+) {
+    let [first, second, proxy_address] = addresses;
+    spawn_test_proxy(proxy_address, accept);
+
+    let msg = raw_message(1000);
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    let first_key = t1.connect_info.public_key;
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(t2.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let mut e1 = TestEvents::with_addr(first, &connect_list);
+    e1.proxy = proxy(second_key);
+    let e2 = TestEvents::with_addr(second, &connect_list);
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    e1.connect_with(second_key, t1.connect.clone());
+    assert_eq!(e2.wait_for_connect(), t1.connect.clone());
+    assert_eq!(e1.wait_for_connect(), t2.connect.clone());
+
+    e1.send_to(second_key, msg.clone());
+    assert_eq!(e2.wait_for_message(), msg);
+    e2.send_to(first_key, msg.clone());
+    assert_eq!(e1.wait_for_message(), msg);
+}
+
+#[test]
+fn test_connect_through_socks5_proxy() {
+    let first = "127.0.0.1:20270".parse().unwrap();
+    let second = "127.0.0.1:20271".parse().unwrap();
+    let proxy_address: SocketAddr = "127.0.0.1:20272".parse().unwrap();
+
+    // The proxy is used only for the second peer.
+    let proxy = |key| {
+        let mut proxy = ProxyConfig::default();
+        proxy
+            .peers
+            .insert(key, Proxy::Socks5(proxy_address.to_string()));
+        proxy
+    };
+    check_proxied_connection([first, second, proxy_address], proxy, accept_socks5);
+}
+
+#[test]
+fn test_connect_through_http_proxy() {
+    let first = "127.0.0.1:20280".parse().unwrap();
+    let second = "127.0.0.1:20281".parse().unwrap();
+    let proxy_address: SocketAddr = "127.0.0.1:20282".parse().unwrap();
+
+    let proxy = |_: PublicKey| ProxyConfig {
+        default: Some(Proxy::Http(proxy_address.to_string())),
+        ..ProxyConfig::default()
+    };
+    check_proxied_connection([first, second, proxy_address], proxy, accept_http);
+}
+
+#[test]
+#[cfg(feature = "quic")]
+fn test_network_quic_transport() {
+    use crate::events::Transport;
+
+    let first = "127.0.0.1:20330".parse().unwrap();
+    let second = "127.0.0.1:20331".parse().unwrap();
+
+    let message = raw_message(200_000);
+
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    let first_key = t1.connect_info.public_key;
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(t2.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+
+    let mut e1 = TestEvents::with_addr(first, &connect_list);
+    e1.network_config.transport = Transport::Quic;
+    let mut e2 = TestEvents::with_addr(second, &connect_list);
+    e2.network_config.transport = Transport::Quic;
+
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    e1.connect_with(second_key, t1.connect.clone());
+    assert_eq!(e2.wait_for_connect(), t1.connect.clone());
+    assert_eq!(e1.wait_for_connect(), t2.connect.clone());
+
+    e1.send_to(second_key, message.clone());
+    assert_eq!(e2.wait_for_message(), message);
+    e2.send_to(first_key, message.clone());
+    assert_eq!(e1.wait_for_message(), message);
+
+    e1.disconnect_with(second_key);
+    assert_eq!(e1.wait_for_disconnect(), second_key);
+}
@@ -0,0 +1,410 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Throttling and queuing of the messages exchanged with peers.
+
+use futures::{
+    future::{self, Either},
+    sync::mpsc,
+    Async, Future, IntoFuture, Poll, Stream,
+};
+use tokio_core::reactor::{Handle, Timeout};
+
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use super::error::into_failure;
+use crate::messages::{BlockResponse, ProtocolMessage, RawTransaction, SignedMessage};
+
+/// Token bucket limiting the rate of the messages exchanged with a peer.
+///
+/// The bucket holds up to a second worth of bytes, so that short bursts are not delayed.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    rate: f64,
+    allowance: f64,
+    last_update: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a limiter with the given rate in bytes per second.
+    pub(crate) fn new(rate: u32) -> Self {
+        RateLimiter {
+            rate: f64::from(rate),
+            allowance: f64::from(rate),
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Accounts a message of the given length. Returns the time to wait before the message
+    /// is processed if the rate is exceeded.
+    pub(crate) fn delay(&mut self, len: usize) -> Option<Duration> {
+        self.delay_at(len, Instant::now())
+    }
+
+    fn delay_at(&mut self, len: usize, now: Instant) -> Option<Duration> {
+        let elapsed = now.duration_since(self.last_update);
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9;
+        self.last_update = now;
+        self.allowance = (self.allowance + elapsed * self.rate).min(self.rate);
+        self.allowance -= len as f64;
+        if self.allowance >= 0.0 {
+            None
+        } else {
+            let micros = (-self.allowance / self.rate * 1e6).ceil();
+            Some(Duration::from_micros(micros as u64))
+        }
+    }
+}
+
+/// Delays the items of the stream so that their total length does not exceed the rate
+/// in bytes per second. The items are not delayed if the rate is not specified.
+pub(crate) fn throttle<S, F>(
+    stream: S,
+    rate: Option<u32>,
+    len: F,
+    handle: &Handle,
+) -> impl Stream<Item = S::Item, Error = failure::Error>
+where
+    S: Stream<Error = failure::Error>,
+    F: Fn(&S::Item) -> usize,
+{
+    let mut limiter = rate.map(RateLimiter::new);
+    let handle = handle.clone();
+    stream.and_then(move |item| {
+        match limiter
+            .as_mut()
+            .and_then(|limiter| limiter.delay(len(&item)))
+        {
+            Some(delay) => Either::A(
+                Timeout::new(delay, &handle)
+                    .into_future()
+                    .flatten()
+                    .map(move |()| item)
+                    .map_err(into_failure),
+            ),
+            None => Either::B(future::ok(item)),
+        }
+    })
+}
+
+/// Returns `true` if the message is a part of the bulk traffic, that is, a transaction
+/// or a response, which is sent after the consensus messages queued for the same peer.
+fn is_bulk(message: &SignedMessage) -> bool {
+    let class = message.message_class();
+    class == BlockResponse::message_type().0
+        || (class, message.message_type()) == RawTransaction::message_type()
+}
+
+/// Default maximum number of the messages queued for sending to a peer, which applies
+/// separately to the consensus messages and to the bulk ones.
+pub const DEFAULT_PEER_QUEUE_LEN: usize = 1024;
+/// Number of the messages received from a peer that are buffered until they are taken
+/// by the fair queue. Reading from the peer is paused once the buffer is full.
+const PEER_EVENTS_LEN: usize = 16;
+
+/// Creates the queue of the messages sent to a peer. The consensus messages and the bulk
+/// ones are bounded separately by `max_len`, so that the capacity for the consensus messages
+/// is not taken by the transactions and the responses.
+pub(crate) fn peer_queue(max_len: usize) -> (PeerQueueSender, PeerQueueReceiver) {
+    let (consensus_tx, consensus_rx) = mpsc::unbounded();
+    let (bulk_tx, bulk_rx) = mpsc::unbounded();
+    let consensus_len = Rc::new(Cell::new(0));
+    let bulk_len = Rc::new(Cell::new(0));
+    let sender = PeerQueueSender {
+        consensus: consensus_tx,
+        bulk: bulk_tx,
+        consensus_len: Rc::clone(&consensus_len),
+        bulk_len: Rc::clone(&bulk_len),
+        max_len,
+    };
+    let receiver = PeerQueueReceiver {
+        consensus: consensus_rx,
+        bulk: bulk_rx,
+        consensus_len,
+        bulk_len,
+    };
+    (sender, receiver)
+}
+
+/// Sending half of the queue of the messages sent to a peer.
+#[derive(Clone, Debug)]
+pub(crate) struct PeerQueueSender {
+    consensus: mpsc::UnboundedSender<SignedMessage>,
+    bulk: mpsc::UnboundedSender<SignedMessage>,
+    consensus_len: Rc<Cell<usize>>,
+    bulk_len: Rc<Cell<usize>>,
+    max_len: usize,
+}
+
+impl PeerQueueSender {
+    /// Queues the message. Returns `false` if the queue of the messages of the same kind
+    /// is full and the message is dropped.
+    pub(crate) fn send(&self, message: SignedMessage) -> Result<bool, failure::Error> {
+        let (sender, len) = if is_bulk(&message) {
+            (&self.bulk, &self.bulk_len)
+        } else {
+            (&self.consensus, &self.consensus_len)
+        };
+        if len.get() >= self.max_len {
+            return Ok(false);
+        }
+        sender
+            .unbounded_send(message)
+            .map_err(|_| format_err!("Receiver is gone."))?;
+        len.set(len.get() + 1);
+        Ok(true)
+    }
+}
+
+/// Receiving half of the queue of the messages sent to a peer. The consensus messages
+/// are received before the bulk ones.
+#[derive(Debug)]
+pub(crate) struct PeerQueueReceiver {
+    consensus: mpsc::UnboundedReceiver<SignedMessage>,
+    bulk: mpsc::UnboundedReceiver<SignedMessage>,
+    consensus_len: Rc<Cell<usize>>,
+    bulk_len: Rc<Cell<usize>>,
+}
+
+impl Stream for PeerQueueReceiver {
+    type Item = SignedMessage;
+    type Error = failure::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        let consensus = self
+            .consensus
+            .poll()
+            .map_err(|()| format_err!("Sender is gone."))?;
+        if let Async::Ready(Some(message)) = consensus {
+            self.consensus_len.set(self.consensus_len.get() - 1);
+            return Ok(Async::Ready(Some(message)));
+        }
+
+        let bulk = self
+            .bulk
+            .poll()
+            .map_err(|()| format_err!("Sender is gone."))?;
+        match bulk {
+            Async::Ready(Some(message)) => {
+                self.bulk_len.set(self.bulk_len.get() - 1);
+                Ok(Async::Ready(Some(message)))
+            }
+            // Both queues are closed.
+            Async::Ready(None) if consensus.is_ready() => Ok(Async::Ready(None)),
+            _ => Ok(Async::NotReady),
+        }
+    }
+}
+
+/// Creates the queue merging the items received from the peers. The items are taken
+/// from each peer in turn, so that a peer flooding the node does not delay the items
+/// received from the other peers.
+pub(crate) fn fair_queue<T>() -> (FairQueueSender<T>, FairQueue<T>) {
+    let (peers_tx, peers_rx) = mpsc::unbounded();
+    let sender = FairQueueSender { peers: peers_tx };
+    let queue = FairQueue {
+        new_peers: Some(peers_rx),
+        peers: VecDeque::new(),
+    };
+    (sender, queue)
+}
+
+/// Registers the peers in the fair queue.
+#[derive(Debug)]
+pub(crate) struct FairQueueSender<T> {
+    peers: mpsc::UnboundedSender<mpsc::Receiver<T>>,
+}
+
+impl<T> Clone for FairQueueSender<T> {
+    fn clone(&self) -> Self {
+        FairQueueSender {
+            peers: self.peers.clone(),
+        }
+    }
+}
+
+impl<T> FairQueueSender<T> {
+    /// Registers a peer and returns the sender of the items received from it.
+    pub(crate) fn register(&self) -> Result<mpsc::Sender<T>, failure::Error> {
+        let (tx, rx) = mpsc::channel(PEER_EVENTS_LEN);
+        self.peers
+            .unbounded_send(rx)
+            .map_err(|_| format_err!("Fair queue is gone."))?;
+        Ok(tx)
+    }
+}
+
+/// Stream of the items received from all peers registered in the queue.
+#[derive(Debug)]
+pub(crate) struct FairQueue<T> {
+    new_peers: Option<mpsc::UnboundedReceiver<mpsc::Receiver<T>>>,
+    /// The peers in the order they are polled.
+    peers: VecDeque<mpsc::Receiver<T>>,
+}
+
+impl<T> Stream for FairQueue<T> {
+    type Item = T;
+    type Error = failure::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        while let Some(ref mut new_peers) = self.new_peers {
+            match new_peers.poll() {
+                Ok(Async::Ready(Some(peer))) => self.peers.push_back(peer),
+                Ok(Async::NotReady) => break,
+                Ok(Async::Ready(None)) | Err(()) => self.new_peers = None,
+            }
+        }
+
+        // The peer which has an item is moved to the end of the queue, so the next item
+        // is taken from the other peers first.
+        for _ in 0..self.peers.len() {
+            let mut peer = self.peers.pop_front().expect("Peer is not found");
+            match peer.poll() {
+                Ok(Async::Ready(Some(item))) => {
+                    self.peers.push_back(peer);
+                    return Ok(Async::Ready(Some(item)));
+                }
+                Ok(Async::NotReady) => self.peers.push_back(peer),
+                // The peer is disconnected.
+                Ok(Async::Ready(None)) | Err(()) => {}
+            }
+        }
+
+        if self.new_peers.is_none() && self.peers.is_empty() {
+            Ok(Async::Ready(None))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{future, Async, Future, Stream};
+
+    use std::time::{Duration, Instant};
+
+    use super::{fair_queue, peer_queue, RateLimiter};
+    use crate::crypto::{gen_keypair, Hash};
+    use crate::helpers::{Height, Round, ValidatorId};
+    use crate::messages::{Message, Precommit, ServiceTransaction};
+
+    #[test]
+    fn rate_limiter() {
+        let mut limiter = RateLimiter::new(1000);
+        let start = Instant::now();
+        // The burst of a second worth of bytes is not delayed.
+        assert_eq!(limiter.delay_at(600, start), None);
+        assert_eq!(limiter.delay_at(400, start), None);
+        assert_eq!(
+            limiter.delay_at(500, start),
+            Some(Duration::from_millis(500))
+        );
+        // The allowance is restored with time.
+        assert_eq!(limiter.delay_at(500, start + Duration::from_secs(2)), None);
+    }
+
+    #[test]
+    fn peer_queue_priorities_and_limit() {
+        let (public_key, secret_key) = gen_keypair();
+        let tx = Message::sign_transaction(
+            ServiceTransaction::from_raw_unchecked(0, vec![1; 32]),
+            0,
+            public_key,
+            &secret_key,
+        );
+        let precommit = Message::concrete(
+            Precommit::new(
+                ValidatorId(0),
+                Height(1),
+                Round(1),
+                &Hash::zero(),
+                &Hash::zero(),
+                chrono::Utc::now(),
+            ),
+            public_key,
+            &secret_key,
+        );
+
+        // The receiver is polled within a task, since it is parked once the queue is empty.
+        future::lazy(move || -> Result<(), ()> {
+            let (sender, mut receiver) = peer_queue(1);
+            assert!(sender.send(tx.signed_message().clone()).unwrap());
+            // The transaction does not take the capacity of the consensus messages.
+            assert!(sender.send(precommit.signed_message().clone()).unwrap());
+            // The queue is full.
+            assert!(!sender.send(precommit.signed_message().clone()).unwrap());
+            assert!(!sender.send(tx.signed_message().clone()).unwrap());
+
+            // The precommit is received before the transaction.
+            let received = match receiver.poll().unwrap() {
+                Async::Ready(Some(message)) => message,
+                other => panic!("Unexpected poll result: {:?}", other),
+            };
+            assert_eq!(&received, precommit.signed_message());
+            assert!(sender.send(precommit.signed_message().clone()).unwrap());
+
+            let received: Vec<_> = (0..2)
+                .map(|_| match receiver.poll().unwrap() {
+                    Async::Ready(Some(message)) => message,
+                    other => panic!("Unexpected poll result: {:?}", other),
+                })
+                .collect();
+            assert_eq!(&received[0], precommit.signed_message());
+            assert_eq!(&received[1], tx.signed_message());
+
+            drop(sender);
+            assert_eq!(receiver.poll().unwrap(), Async::Ready(None));
+            Ok(())
+        })
+        .wait()
+        .unwrap();
+    }
+
+    #[test]
+    fn fair_queue_alternates_peers() {
+        future::lazy(|| -> Result<(), ()> {
+            let (sender, mut queue) = fair_queue();
+            let mut first = sender.register().unwrap();
+            let mut second = sender.register().unwrap();
+            for i in 0..3 {
+                first.try_send(i).unwrap();
+            }
+            second.try_send(10).unwrap();
+
+            let mut poll = || match queue.poll().unwrap() {
+                Async::Ready(item) => item,
+                Async::NotReady => panic!("Queue is not ready"),
+            };
+            // The item of the second peer is not delayed by the items of the first one.
+            assert_eq!(poll(), Some(0));
+            assert_eq!(poll(), Some(10));
+            assert_eq!(poll(), Some(1));
+            assert_eq!(poll(), Some(2));
+
+            drop(first);
+            drop(second);
+            drop(sender);
+            assert_eq!(poll(), None);
+            Ok(())
+        })
+        .wait()
+        .unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports of the connections between peers.
+
+use futures::{Future, Poll, Stream};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_io::{AsyncRead, AsyncWrite};
+
+use std::{
+    io::{self, Read, Write},
+    net::SocketAddr,
+    time::Duration,
+};
+
+#[cfg(feature = "quic")]
+use super::quic::{QuicEndpoint, QuicStream};
+use super::{error::into_failure, NetworkConfiguration, Proxy};
+
+/// Transport of the connections between peers.
+///
+/// All nodes of the network must use the same transport, since the nodes using
+/// different transports cannot connect to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// TCP, which is used by default.
+    Tcp,
+    /// QUIC, which requires the `quic` feature. QUIC connections survive the change
+    /// of the peer address, for example, after a NAT rebinding, and recover from
+    /// the packet loss faster than TCP. Proxies are not supported by this transport.
+    Quic,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::Tcp
+    }
+}
+
+/// Stream of the connections with peers accepted by the listener.
+pub(crate) type Incoming = Box<dyn Stream<Item = PeerStream, Error = failure::Error>>;
+
+/// Binds the listener of the connections with peers to the address and returns
+/// the connector of the outgoing connections, which uses the same transport.
+pub(crate) fn bind(
+    transport: Transport,
+    address: &SocketAddr,
+) -> Result<(Connector, Incoming), failure::Error> {
+    match transport {
+        Transport::Tcp => {
+            let incoming = TcpListener::bind(address)?
+                .incoming()
+                .map(PeerStream::Tcp)
+                .map_err(into_failure);
+            Ok((Connector::Tcp, Box::new(incoming)))
+        }
+        #[cfg(feature = "quic")]
+        Transport::Quic => {
+            let (endpoint, incoming) = QuicEndpoint::bind(address)?;
+            let incoming = incoming
+                .map(PeerStream::Quic)
+                .map_err(|()| format_err!("QUIC endpoint is closed"));
+            Ok((Connector::Quic(endpoint), Box::new(incoming)))
+        }
+        #[cfg(not(feature = "quic"))]
+        Transport::Quic => {
+            bail!("QUIC transport requires Exonum to be built with the `quic` feature")
+        }
+    }
+}
+
+/// Connector of the outgoing connections with peers.
+#[derive(Clone)]
+pub(crate) enum Connector {
+    Tcp,
+    #[cfg(feature = "quic")]
+    Quic(QuicEndpoint),
+}
+
+impl Connector {
+    /// Connects to the `target` address, which is `host:port`, through the proxy.
+    pub fn connect(
+        &self,
+        proxy: &Proxy,
+        target: String,
+    ) -> Box<dyn Future<Item = PeerStream, Error = io::Error>> {
+        match self {
+            Connector::Tcp => Box::new(proxy.connect(target).map(PeerStream::Tcp)),
+            #[cfg(feature = "quic")]
+            Connector::Quic(endpoint) => {
+                if proxy.is_proxied() {
+                    let e = io::Error::new(
+                        io::ErrorKind::Other,
+                        "Proxies are not supported by the QUIC transport",
+                    );
+                    return Box::new(futures::future::err(e));
+                }
+                Box::new(endpoint.connect(target).map(PeerStream::Quic))
+            }
+        }
+    }
+}
+
+/// Stream of the connection with a peer.
+pub(crate) enum PeerStream {
+    Tcp(TcpStream),
+    #[cfg(feature = "quic")]
+    Quic(QuicStream),
+}
+
+impl PeerStream {
+    /// Returns the current address of the peer. The address of a QUIC connection
+    /// changes if the peer migrates to another address.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            PeerStream::Tcp(stream) => stream.peer_addr(),
+            #[cfg(feature = "quic")]
+            PeerStream::Quic(stream) => Ok(stream.peer_addr()),
+        }
+    }
+
+    /// Applies the TCP options of the configuration. The options do not apply to QUIC.
+    pub fn configure(&self, network_config: NetworkConfiguration) -> io::Result<()> {
+        match self {
+            PeerStream::Tcp(stream) => {
+                stream.set_nodelay(network_config.tcp_nodelay)?;
+                let duration = network_config.tcp_keep_alive.map(Duration::from_millis);
+                stream.set_keepalive(duration)
+            }
+            #[cfg(feature = "quic")]
+            PeerStream::Quic(_) => Ok(()),
+        }
+    }
+}
+
+impl Read for PeerStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            PeerStream::Tcp(stream) => stream.read(buf),
+            #[cfg(feature = "quic")]
+            PeerStream::Quic(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for PeerStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            PeerStream::Tcp(stream) => stream.write(buf),
+            #[cfg(feature = "quic")]
+            PeerStream::Quic(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            PeerStream::Tcp(stream) => stream.flush(),
+            #[cfg(feature = "quic")]
+            PeerStream::Quic(stream) => stream.flush(),
+        }
+    }
+}
+
+impl AsyncRead for PeerStream {}
+
+impl AsyncWrite for PeerStream {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        match self {
+            PeerStream::Tcp(stream) => AsyncWrite::shutdown(stream),
+            #[cfg(feature = "quic")]
+            PeerStream::Quic(stream) => AsyncWrite::shutdown(stream),
+        }
+    }
+}
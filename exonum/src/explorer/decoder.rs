@@ -0,0 +1,708 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Decoding of transactions with the protobuf descriptors of their services, and encoding
+//! of the transaction payloads from JSON for offline signing.
+
+use failure::Error;
+use protobuf::{
+    descriptor::{
+        DescriptorProto, EnumDescriptorProto, FieldDescriptorProto, FieldDescriptorProto_Label,
+        FieldDescriptorProto_Type,
+    },
+    wire_format::WireType,
+    CodedInputStream, CodedOutputStream,
+};
+use serde_json::{Map, Value};
+
+use std::collections::HashMap;
+
+use crate::blockchain::{Schema, ServiceDescriptor};
+use crate::crypto::PublicKey;
+use crate::messages::{RawTransaction, ServiceTransaction, Signed};
+use exonum_merkledb::Snapshot;
+
+/// Messages rendered as hex strings of their `data` field, like in the JSON
+/// serialization of the transactions.
+const HEX_MESSAGES: [&str; 3] = [".exonum.Hash", ".exonum.PublicKey", ".exonum.Signature"];
+/// Maximal nesting depth of the decoded messages.
+const MAX_NESTING_DEPTH: usize = 32;
+
+/// Transaction decoded with the protobuf descriptor of its service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecodedTransaction {
+    /// Identifier of the service of the transaction.
+    pub service_id: u16,
+    /// Identifier of the transaction within the service.
+    pub message_id: u16,
+    /// Author of the transaction.
+    pub author: PublicKey,
+    /// Full name of the protobuf message of the transaction, if the descriptor
+    /// of the service declares it.
+    pub message_name: Option<String>,
+    /// Fields of the protobuf message of the transaction as a JSON object, or the hex-encoded
+    /// payload if the message is unknown. Byte fields are encoded as hex strings.
+    pub payload: Value,
+}
+
+/// Decoder of the transactions of any services, including the ones not compiled
+/// into the binary, with the protobuf descriptors of the services, see
+/// `Service::protobuf_descriptor`.
+#[derive(Debug, Default)]
+pub struct TransactionDecoder {
+    services: HashMap<u16, ServiceTypes>,
+}
+
+impl TransactionDecoder {
+    /// Creates a decoder with the descriptors of the services stored in the blockchain.
+    pub fn new(snapshot: &dyn Snapshot) -> Result<Self, Error> {
+        let descriptors = Schema::new(snapshot).service_descriptors().iter().collect();
+        Self::with_descriptors(descriptors)
+    }
+
+    /// Creates a decoder with the given descriptors of the services.
+    pub fn with_descriptors(descriptors: HashMap<u16, ServiceDescriptor>) -> Result<Self, Error> {
+        let services = descriptors
+            .into_iter()
+            .map(|(service_id, descriptor)| {
+                ServiceTypes::new(descriptor).map(|types| (service_id, types))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { services })
+    }
+
+    /// Decodes the transaction. Returns an error if the payload of the transaction
+    /// does not match its protobuf message.
+    pub fn decode(
+        &self,
+        transaction: &Signed<RawTransaction>,
+    ) -> Result<DecodedTransaction, Error> {
+        let service_id = transaction.payload().service_id();
+        let (message_id, payload) = transaction
+            .payload()
+            .clone()
+            .service_transaction()
+            .into_raw_parts();
+
+        let types = self.services.get(&service_id);
+        let message_name = types
+            .and_then(|types| types.descriptor.transaction_message(message_id))
+            .map(str::to_owned);
+        let payload = match (types, &message_name) {
+            (Some(types), Some(message_name)) => {
+                types.decode_message(&qualified_name(message_name), &payload, 0)?
+            }
+            _ => Value::String(hex::encode(&payload)),
+        };
+
+        Ok(DecodedTransaction {
+            service_id,
+            message_id,
+            author: transaction.author(),
+            message_name,
+            payload,
+        })
+    }
+
+    /// Encodes the payload of a transaction from JSON in the same format as
+    /// `DecodedTransaction::payload`, so that the transaction can be signed without
+    /// the service itself. If the descriptor of the service does not declare the message
+    /// of the transaction, the payload must be a hex string with the serialized message.
+    pub fn encode(
+        &self,
+        service_id: u16,
+        message_id: u16,
+        payload: &Value,
+    ) -> Result<ServiceTransaction, Error> {
+        let types = self.services.get(&service_id);
+        let message_name = types.and_then(|types| types.descriptor.transaction_message(message_id));
+        let payload = match (types, message_name) {
+            (Some(types), Some(message_name)) => {
+                types.encode_message(&qualified_name(message_name), payload, 0)?
+            }
+            _ => {
+                let payload = payload.as_str().ok_or_else(|| {
+                    format_err!(
+                        "Transaction {} of service {} is unknown, its payload must be a hex string",
+                        message_id,
+                        service_id
+                    )
+                })?;
+                hex::decode(payload)?
+            }
+        };
+        Ok(ServiceTransaction::from_raw_unchecked(message_id, payload))
+    }
+}
+
+/// Returns the name of the protobuf type as it is referenced in the field descriptors.
+fn qualified_name(name: &str) -> String {
+    if name.starts_with('.') {
+        name.to_owned()
+    } else {
+        format!(".{}", name)
+    }
+}
+
+/// Wire type of the elements of the packed repeated field.
+fn packed_wire_type(field_type: FieldDescriptorProto_Type) -> WireType {
+    use self::FieldDescriptorProto_Type::*;
+
+    match field_type {
+        TYPE_FIXED32 | TYPE_SFIXED32 | TYPE_FLOAT => WireType::WireTypeFixed32,
+        TYPE_FIXED64 | TYPE_SFIXED64 | TYPE_DOUBLE => WireType::WireTypeFixed64,
+        _ => WireType::WireTypeVarint,
+    }
+}
+
+/// Value of a scalar field in its wire representation.
+#[derive(Debug, Clone, Copy)]
+enum RawScalar {
+    Varint(u64),
+    Fixed32(u32),
+    Fixed64(u64),
+}
+
+impl RawScalar {
+    fn is_default(self) -> bool {
+        match self {
+            RawScalar::Varint(value) | RawScalar::Fixed64(value) => value == 0,
+            RawScalar::Fixed32(value) => value == 0,
+        }
+    }
+
+    fn write(self, output: &mut CodedOutputStream) -> Result<(), Error> {
+        match self {
+            RawScalar::Varint(value) => output.write_raw_varint64(value)?,
+            RawScalar::Fixed32(value) => output.write_raw_little_endian32(value)?,
+            RawScalar::Fixed64(value) => output.write_raw_little_endian64(value)?,
+        }
+        Ok(())
+    }
+}
+
+/// Reads a signed integer from a JSON number or a string.
+fn json_i64(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// Reads an unsigned integer from a JSON number or a string.
+fn json_u64(value: &Value) -> Option<u64> {
+    value
+        .as_u64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// Reads a floating-point number from a JSON number or a string.
+fn json_f64(value: &Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+/// Types declared in the protobuf files of a service, by their qualified names.
+#[derive(Debug)]
+struct ServiceTypes {
+    descriptor: ServiceDescriptor,
+    messages: HashMap<String, DescriptorProto>,
+    enums: HashMap<String, EnumDescriptorProto>,
+}
+
+impl ServiceTypes {
+    fn new(descriptor: ServiceDescriptor) -> Result<Self, Error> {
+        let files = descriptor.file_descriptor_set()?;
+        let mut types = Self {
+            descriptor,
+            messages: HashMap::new(),
+            enums: HashMap::new(),
+        };
+        for file in files.get_file() {
+            let package = if file.get_package().is_empty() {
+                String::new()
+            } else {
+                format!(".{}", file.get_package())
+            };
+            types.add_enums(&package, file.get_enum_type());
+            types.add_messages(&package, file.get_message_type());
+        }
+        Ok(types)
+    }
+
+    fn add_messages(&mut self, scope: &str, messages: &[DescriptorProto]) {
+        for message in messages {
+            let name = format!("{}.{}", scope, message.get_name());
+            self.add_enums(&name, message.get_enum_type());
+            self.add_messages(&name, message.get_nested_type());
+            self.messages.insert(name, message.clone());
+        }
+    }
+
+    fn add_enums(&mut self, scope: &str, enums: &[EnumDescriptorProto]) {
+        for enum_type in enums {
+            let name = format!("{}.{}", scope, enum_type.get_name());
+            self.enums.insert(name, enum_type.clone());
+        }
+    }
+
+    fn decode_message(&self, type_name: &str, bytes: &[u8], depth: usize) -> Result<Value, Error> {
+        ensure!(
+            depth < MAX_NESTING_DEPTH,
+            "Protobuf message is nested too deeply"
+        );
+        let message = self
+            .messages
+            .get(type_name)
+            .ok_or_else(|| format_err!("Unknown protobuf message {}", type_name))?;
+
+        let mut fields = Map::new();
+        let mut input = CodedInputStream::from_bytes(bytes);
+        while !input.eof()? {
+            let (number, wire_type) = input.read_tag_unpack()?;
+            let field = message
+                .get_field()
+                .iter()
+                .find(|field| field.get_number() as u32 == number)
+                .ok_or_else(|| {
+                    format_err!("Unknown field {} of protobuf message {}", number, type_name)
+                })?;
+            let values = self.decode_field(field, wire_type, &mut input, depth)?;
+
+            if field.get_label() == FieldDescriptorProto_Label::LABEL_REPEATED {
+                let entry = fields
+                    .entry(field.get_name())
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                if let Value::Array(array) = entry {
+                    array.extend(values);
+                }
+            } else if let Some(value) = values.into_iter().last() {
+                fields.insert(field.get_name().to_owned(), value);
+            }
+        }
+
+        if HEX_MESSAGES.contains(&type_name) {
+            return Ok(fields
+                .remove("data")
+                .unwrap_or_else(|| Value::String(String::new())));
+        }
+        Ok(Value::Object(fields))
+    }
+
+    fn decode_field(
+        &self,
+        field: &FieldDescriptorProto,
+        wire_type: WireType,
+        input: &mut CodedInputStream,
+        depth: usize,
+    ) -> Result<Vec<Value>, Error> {
+        use self::FieldDescriptorProto_Type::*;
+
+        if wire_type != WireType::WireTypeLengthDelimited {
+            return Ok(vec![self.decode_scalar(field, wire_type, input)?]);
+        }
+        let bytes = input.read_bytes()?;
+        let value = match field.get_field_type() {
+            TYPE_STRING => Value::String(String::from_utf8(bytes)?),
+            TYPE_BYTES => Value::String(hex::encode(&bytes)),
+            TYPE_MESSAGE => self.decode_message(field.get_type_name(), &bytes, depth + 1)?,
+            field_type => {
+                // Packed repeated scalar field.
+                let wire_type = packed_wire_type(field_type);
+                let mut packed = CodedInputStream::from_bytes(&bytes);
+                let mut values = Vec::new();
+                while !packed.eof()? {
+                    values.push(self.decode_scalar(field, wire_type, &mut packed)?);
+                }
+                return Ok(values);
+            }
+        };
+        Ok(vec![value])
+    }
+
+    fn decode_scalar(
+        &self,
+        field: &FieldDescriptorProto,
+        wire_type: WireType,
+        input: &mut CodedInputStream,
+    ) -> Result<Value, Error> {
+        use self::{FieldDescriptorProto_Type::*, WireType::*};
+
+        let value = match (wire_type, field.get_field_type()) {
+            (WireTypeVarint, TYPE_INT32) => json!(input.read_raw_varint64()? as i32),
+            (WireTypeVarint, TYPE_INT64) => json!(input.read_raw_varint64()? as i64),
+            (WireTypeVarint, TYPE_UINT32) => json!(input.read_raw_varint64()? as u32),
+            (WireTypeVarint, TYPE_UINT64) => json!(input.read_raw_varint64()?),
+            (WireTypeVarint, TYPE_SINT32) => json!(input.read_sint32()?),
+            (WireTypeVarint, TYPE_SINT64) => json!(input.read_sint64()?),
+            (WireTypeVarint, TYPE_BOOL) => json!(input.read_raw_varint64()? != 0),
+            (WireTypeVarint, TYPE_ENUM) => {
+                let number = input.read_raw_varint64()? as i32;
+                self.enum_value(field.get_type_name(), number)
+            }
+            (WireTypeFixed32, TYPE_FIXED32) => json!(input.read_fixed32()?),
+            (WireTypeFixed32, TYPE_SFIXED32) => json!(input.read_sfixed32()?),
+            (WireTypeFixed32, TYPE_FLOAT) => json!(input.read_float()?),
+            (WireTypeFixed64, TYPE_FIXED64) => json!(input.read_fixed64()?),
+            (WireTypeFixed64, TYPE_SFIXED64) => json!(input.read_sfixed64()?),
+            (WireTypeFixed64, TYPE_DOUBLE) => json!(input.read_double()?),
+            (wire_type, field_type) => bail!(
+                "Field {} of type {:?} cannot be encoded with wire type {:?}",
+                field.get_name(),
+                field_type,
+                wire_type
+            ),
+        };
+        Ok(value)
+    }
+
+    fn encode_message(
+        &self,
+        type_name: &str,
+        value: &Value,
+        depth: usize,
+    ) -> Result<Vec<u8>, Error> {
+        ensure!(
+            depth < MAX_NESTING_DEPTH,
+            "Protobuf message is nested too deeply"
+        );
+        let mut bytes = Vec::new();
+        if HEX_MESSAGES.contains(&type_name) {
+            let data = value
+                .as_str()
+                .ok_or_else(|| format_err!("Expected hex string for {}", type_name))?;
+            let data = hex::decode(data)?;
+            if !data.is_empty() {
+                let mut output = CodedOutputStream::vec(&mut bytes);
+                output.write_bytes(1, &data)?;
+                output.flush()?;
+            }
+            return Ok(bytes);
+        }
+
+        let message = self
+            .messages
+            .get(type_name)
+            .ok_or_else(|| format_err!("Unknown protobuf message {}", type_name))?;
+        let object = value.as_object().ok_or_else(|| {
+            format_err!("Expected JSON object for protobuf message {}", type_name)
+        })?;
+        if let Some(name) = object.keys().find(|&name| {
+            !message
+                .get_field()
+                .iter()
+                .any(|field| field.get_name() == name)
+        }) {
+            bail!("Unknown field {} of protobuf message {}", name, type_name);
+        }
+
+        {
+            let mut output = CodedOutputStream::vec(&mut bytes);
+            self.encode_fields(message, object, &mut output, depth)?;
+            output.flush()?;
+        }
+        Ok(bytes)
+    }
+
+    fn encode_fields(
+        &self,
+        message: &DescriptorProto,
+        object: &Map<String, Value>,
+        output: &mut CodedOutputStream,
+        depth: usize,
+    ) -> Result<(), Error> {
+        // Fields are written in the order of declaration, like in the generated code.
+        for field in message.get_field() {
+            let value = match object.get(field.get_name()) {
+                Some(Value::Null) | None => continue,
+                Some(value) => value,
+            };
+            if field.get_label() == FieldDescriptorProto_Label::LABEL_REPEATED {
+                let values = value.as_array().ok_or_else(|| {
+                    format_err!("Expected JSON array for field {}", field.get_name())
+                })?;
+                self.encode_repeated(field, values, output, depth)?;
+            } else {
+                self.encode_field(field, value, output, depth, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_repeated(
+        &self,
+        field: &FieldDescriptorProto,
+        values: &[Value],
+        output: &mut CodedOutputStream,
+        depth: usize,
+    ) -> Result<(), Error> {
+        use self::FieldDescriptorProto_Type::*;
+
+        match field.get_field_type() {
+            TYPE_STRING | TYPE_BYTES | TYPE_MESSAGE => {
+                for value in values {
+                    self.encode_field(field, value, output, depth, false)?;
+                }
+            }
+            // Repeated scalar fields are packed in proto3.
+            _ if !values.is_empty() => {
+                let mut packed = Vec::new();
+                {
+                    let mut packed_output = CodedOutputStream::vec(&mut packed);
+                    for value in values {
+                        self.encode_scalar(field, value)?
+                            .write(&mut packed_output)?;
+                    }
+                    packed_output.flush()?;
+                }
+                output.write_tag(field.get_number() as u32, WireType::WireTypeLengthDelimited)?;
+                output.write_bytes_no_tag(&packed)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Encodes the value of the field. Singular fields with default values are omitted
+    /// as in proto3.
+    fn encode_field(
+        &self,
+        field: &FieldDescriptorProto,
+        value: &Value,
+        output: &mut CodedOutputStream,
+        depth: usize,
+        singular: bool,
+    ) -> Result<(), Error> {
+        use self::FieldDescriptorProto_Type::*;
+
+        let number = field.get_number() as u32;
+        match field.get_field_type() {
+            TYPE_STRING => {
+                let string = value
+                    .as_str()
+                    .ok_or_else(|| format_err!("Expected string for field {}", field.get_name()))?;
+                if !(singular && string.is_empty()) {
+                    output.write_string(number, string)?;
+                }
+            }
+            TYPE_BYTES => {
+                let bytes = value.as_str().ok_or_else(|| {
+                    format_err!("Expected hex string for field {}", field.get_name())
+                })?;
+                let bytes = hex::decode(bytes)?;
+                if !(singular && bytes.is_empty()) {
+                    output.write_bytes(number, &bytes)?;
+                }
+            }
+            TYPE_MESSAGE => {
+                let bytes = self.encode_message(field.get_type_name(), value, depth + 1)?;
+                output.write_bytes(number, &bytes)?;
+            }
+            field_type => {
+                let scalar = self.encode_scalar(field, value)?;
+                if !(singular && scalar.is_default()) {
+                    output.write_tag(number, packed_wire_type(field_type))?;
+                    scalar.write(output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_scalar(
+        &self,
+        field: &FieldDescriptorProto,
+        value: &Value,
+    ) -> Result<RawScalar, Error> {
+        use self::FieldDescriptorProto_Type::*;
+
+        let invalid = || {
+            format_err!(
+                "Invalid value {} of field {} of type {:?}",
+                value,
+                field.get_name(),
+                field.get_field_type()
+            )
+        };
+        let int32 = || {
+            json_i64(value)
+                .filter(|&v| v >= i64::from(i32::min_value()) && v <= i64::from(i32::max_value()))
+                .map(|v| v as i32)
+                .ok_or_else(invalid)
+        };
+        let uint32 = || {
+            json_u64(value)
+                .filter(|&v| v <= u64::from(u32::max_value()))
+                .map(|v| v as u32)
+                .ok_or_else(invalid)
+        };
+
+        let scalar = match field.get_field_type() {
+            TYPE_INT32 => RawScalar::Varint(i64::from(int32()?) as u64),
+            TYPE_INT64 => RawScalar::Varint(json_i64(value).ok_or_else(invalid)? as u64),
+            TYPE_UINT32 => RawScalar::Varint(u64::from(uint32()?)),
+            TYPE_UINT64 => RawScalar::Varint(json_u64(value).ok_or_else(invalid)?),
+            TYPE_SINT32 => {
+                let v = int32()?;
+                RawScalar::Varint(u64::from(((v << 1) ^ (v >> 31)) as u32))
+            }
+            TYPE_SINT64 => {
+                let v = json_i64(value).ok_or_else(invalid)?;
+                RawScalar::Varint(((v << 1) ^ (v >> 63)) as u64)
+            }
+            TYPE_BOOL => RawScalar::Varint(value.as_bool().ok_or_else(invalid)? as u64),
+            TYPE_ENUM => {
+                let number = match value.as_str() {
+                    Some(name) => self
+                        .enum_number(field.get_type_name(), name)
+                        .ok_or_else(invalid)?,
+                    None => int32()?,
+                };
+                RawScalar::Varint(i64::from(number) as u64)
+            }
+            TYPE_FIXED32 => RawScalar::Fixed32(uint32()?),
+            TYPE_SFIXED32 => RawScalar::Fixed32(int32()? as u32),
+            TYPE_FLOAT => {
+                RawScalar::Fixed32((json_f64(value).ok_or_else(invalid)? as f32).to_bits())
+            }
+            TYPE_FIXED64 => RawScalar::Fixed64(json_u64(value).ok_or_else(invalid)?),
+            TYPE_SFIXED64 => RawScalar::Fixed64(json_i64(value).ok_or_else(invalid)? as u64),
+            TYPE_DOUBLE => RawScalar::Fixed64(json_f64(value).ok_or_else(invalid)?.to_bits()),
+            _ => return Err(invalid()),
+        };
+        Ok(scalar)
+    }
+
+    /// Returns the number of the enum value with the given name.
+    fn enum_number(&self, type_name: &str, name: &str) -> Option<i32> {
+        self.enums.get(type_name).and_then(|enum_type| {
+            enum_type
+                .get_value()
+                .iter()
+                .find(|value| value.get_name() == name)
+                .map(|value| value.get_number())
+        })
+    }
+
+    /// Returns the name of the enum value, or its number if the value is unknown.
+    fn enum_value(&self, type_name: &str, number: i32) -> Value {
+        self.enums
+            .get(type_name)
+            .and_then(|enum_type| {
+                enum_type
+                    .get_value()
+                    .iter()
+                    .find(|value| value.get_number() == number)
+            })
+            .map(|value| Value::String(value.get_name().to_owned()))
+            .unwrap_or_else(|| json!(number))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::gen_keypair;
+    use crate::messages::{Message, ServiceTransaction};
+    use crate::proto::{schema, ProtobufConvert};
+    use protobuf::Message as ProtobufMessage;
+
+    const SERVICE_ID: u16 = 5;
+
+    fn simple_transaction() -> (PublicKey, Signed<RawTransaction>) {
+        let (public_key, secret_key) = gen_keypair();
+        let mut message = schema::tests::TxSimple::new();
+        message.set_public_key(public_key.to_pb());
+        message.set_msg("Hello, World!".to_owned());
+        let payload = message.write_to_bytes().unwrap();
+
+        let transaction = Message::sign_transaction(
+            ServiceTransaction::from_raw_unchecked(3, payload),
+            SERVICE_ID,
+            public_key,
+            &secret_key,
+        );
+        (public_key, transaction)
+    }
+
+    #[test]
+    fn decode_known_transaction() {
+        let descriptor = ServiceDescriptor::new(&[
+            schema::helpers::file_descriptor_proto(),
+            schema::tests::file_descriptor_proto(),
+        ])
+        .with_transaction(3, "exonum.tests.TxSimple");
+        let decoder = TransactionDecoder::with_descriptors(
+            vec![(SERVICE_ID, descriptor)].into_iter().collect(),
+        )
+        .unwrap();
+
+        let (public_key, transaction) = simple_transaction();
+        let decoded = decoder.decode(&transaction).unwrap();
+        assert_eq!(decoded.service_id, SERVICE_ID);
+        assert_eq!(decoded.message_id, 3);
+        assert_eq!(decoded.author, public_key);
+        assert_eq!(
+            decoded.message_name.as_ref().unwrap(),
+            "exonum.tests.TxSimple"
+        );
+        assert_eq!(
+            decoded.payload,
+            json!({
+                "public_key": public_key.to_hex(),
+                "msg": "Hello, World!",
+            })
+        );
+    }
+
+    #[test]
+    fn encode_known_transaction() {
+        let descriptor = ServiceDescriptor::new(&[
+            schema::helpers::file_descriptor_proto(),
+            schema::tests::file_descriptor_proto(),
+        ])
+        .with_transaction(3, "exonum.tests.TxSimple");
+        let decoder = TransactionDecoder::with_descriptors(
+            vec![(SERVICE_ID, descriptor)].into_iter().collect(),
+        )
+        .unwrap();
+
+        let (public_key, transaction) = simple_transaction();
+        let payload = json!({
+            "public_key": public_key.to_hex(),
+            "msg": "Hello, World!",
+        });
+        let encoded = decoder.encode(SERVICE_ID, 3, &payload).unwrap();
+        assert_eq!(encoded, transaction.payload().clone().service_transaction());
+
+        let payload = json!({ "public_key": public_key.to_hex(), "unknown": 1 });
+        assert!(decoder.encode(SERVICE_ID, 3, &payload).is_err());
+        let payload = json!({ "public_key": public_key.to_hex(), "msg": 1 });
+        assert!(decoder.encode(SERVICE_ID, 3, &payload).is_err());
+    }
+
+    #[test]
+    fn decode_unknown_transaction() {
+        let decoder = TransactionDecoder::default();
+        let (_, transaction) = simple_transaction();
+        let decoded = decoder.decode(&transaction).unwrap();
+        let (_, payload) = transaction
+            .payload()
+            .clone()
+            .service_transaction()
+            .into_raw_parts();
+        assert_eq!(decoded.message_name, None);
+        assert_eq!(decoded.payload, Value::String(hex::encode(&payload)));
+    }
+}
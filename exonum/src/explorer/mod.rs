@@ -17,6 +17,8 @@
 //!
 //! See the `explorer` example in the crate for examples of usage.
 
+pub use self::decoder::{DecodedTransaction, TransactionDecoder};
+
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::{
@@ -28,8 +30,8 @@ use std::{
 };
 
 use crate::blockchain::{
-    Block, Blockchain, Schema, TransactionError, TransactionErrorType, TransactionMessage,
-    TransactionResult, TxLocation,
+    Block, Blockchain, DoubleSignEvidence, RejectedTransaction, Schema, TransactionError,
+    TransactionErrorType, TransactionMessage, TransactionResult, TxLocation,
 };
 use crate::crypto::{CryptoHash, Hash};
 use crate::helpers::Height;
@@ -38,6 +40,8 @@ use chrono::{DateTime, Utc};
 use exonum_merkledb::{ListProof, Snapshot};
 use std::time::UNIX_EPOCH;
 
+mod decoder;
+
 /// Transaction parsing result.
 type ParseResult = Result<TransactionMessage, failure::Error>;
 
@@ -149,11 +153,18 @@ impl<'a> BlockInfo<'a> {
         Ref::map(self.txs.borrow(), |cache| cache.as_ref().unwrap().as_ref())
     }
 
-    /// Returns a transaction with the specified index in the block.
+    /// Returns `true` if transactions and precommits of this block have been removed
+    /// by pruning.
+    pub fn is_pruned(&self) -> bool {
+        self.height() < self.explorer.first_unpruned_height()
+    }
+
+    /// Returns a transaction with the specified index in the block, or `None` if there
+    /// is no such transaction or the block has been pruned.
     pub fn transaction(&self, index: usize) -> Option<CommittedTransaction> {
         self.transaction_hashes()
             .get(index)
-            .map(|hash| self.explorer.committed_transaction(hash, None))
+            .and_then(|hash| self.explorer.committed_transaction(hash, None))
     }
 
     /// Iterates over transactions in the block.
@@ -165,7 +176,8 @@ impl<'a> BlockInfo<'a> {
         }
     }
 
-    /// Loads transactions and precommits for the block.
+    /// Loads transactions and precommits for the block. Both are empty if the block
+    /// has been pruned.
     pub fn with_transactions(self) -> BlockWithTransactions {
         let (explorer, header, precommits, transactions) =
             (self.explorer, self.header, self.precommits, self.txs);
@@ -177,7 +189,7 @@ impl<'a> BlockInfo<'a> {
             .into_inner()
             .unwrap_or_else(|| explorer.transaction_hashes(&header))
             .iter()
-            .map(|tx_hash| explorer.committed_transaction(tx_hash, None))
+            .filter_map(|tx_hash| explorer.committed_transaction(tx_hash, None))
             .collect();
 
         BlockWithTransactions {
@@ -416,6 +428,9 @@ pub struct CommittedTransaction {
 pub(crate) enum TxStatus<'a> {
     Success,
     Panic { description: &'a str },
+    QuotaExceeded { description: &'a str },
+    Duplicate { description: &'a str },
+    StaleNonce { description: &'a str },
     Error { code: u8, description: &'a str },
 }
 
@@ -447,6 +462,9 @@ impl<'a> From<&'a TransactionResult> for TxStatus<'a> {
                 let description = e.description().unwrap_or_default();
                 match e.error_type() {
                     Panic => TxStatus::Panic { description },
+                    QuotaExceeded => TxStatus::QuotaExceeded { description },
+                    Duplicate => TxStatus::Duplicate { description },
+                    StaleNonce => TxStatus::StaleNonce { description },
                     Code(code) => TxStatus::Error { code, description },
                 }
             }
@@ -467,6 +485,15 @@ impl<'a> From<TxStatus<'a>> for TransactionResult {
         TransactionResult(match status {
             TxStatus::Success => Ok(()),
             TxStatus::Panic { description } => Err(TransactionError::panic(to_option(description))),
+            TxStatus::QuotaExceeded { description } => {
+                Err(TransactionError::quota_exceeded(to_option(description)))
+            }
+            TxStatus::Duplicate { description } => {
+                Err(TransactionError::duplicate(to_option(description)))
+            }
+            TxStatus::StaleNonce { description } => {
+                Err(TransactionError::stale_nonce(to_option(description)))
+            }
             TxStatus::Error { code, description } => {
                 Err(TransactionError::code(code, to_option(description)))
             }
@@ -651,24 +678,61 @@ impl<'a> BlockchainExplorer<'a> {
         BlockchainExplorer {
             snapshot: blockchain.snapshot(),
             transaction_parser: Box::new(move |raw| {
-                let tx = blockchain.tx_from_raw(raw.payload().clone())?;
+                let tx = blockchain.tx_from_signed(&raw)?;
                 Ok(TransactionMessage::new(raw, tx))
             }),
         }
     }
 
     /// Returns information about the transaction identified by the hash.
+    ///
+    /// Transactions from the pruned blocks are not available; use
+    /// [`is_transaction_pruned`](#method.is_transaction_pruned) to distinguish them
+    /// from unknown transactions.
     pub fn transaction(&self, tx_hash: &Hash) -> Option<TransactionInfo> {
         let schema = Schema::new(&self.snapshot);
         let content = self.transaction_without_proof(tx_hash)?;
         if schema.transactions_pool().contains(tx_hash) {
             Some(TransactionInfo::InPool { content })
         } else {
-            let tx = self.committed_transaction(tx_hash, Some(content));
-            Some(TransactionInfo::Committed(tx))
+            self.committed_transaction(tx_hash, Some(content))
+                .map(TransactionInfo::Committed)
         }
     }
 
+    /// Returns `true` if the transaction has been committed in the block which
+    /// has been pruned afterwards.
+    pub fn is_transaction_pruned(&self, tx_hash: &Hash) -> bool {
+        let schema = Schema::new(&self.snapshot);
+        schema
+            .transactions_locations()
+            .get(tx_hash)
+            .map_or(false, |location| schema.is_pruned(location.block_height()))
+    }
+
+    /// Returns the record of the rejected transactions log if the transaction has been
+    /// removed from the pool by the node without being committed.
+    pub fn rejected_transaction(&self, tx_hash: &Hash) -> Option<RejectedTransaction> {
+        Schema::new(&self.snapshot)
+            .rejected_transactions()
+            .get(tx_hash)
+    }
+
+    /// Returns the height of the earliest block whose transactions and precommits
+    /// have not been pruned.
+    pub fn first_unpruned_height(&self) -> Height {
+        Schema::new(&self.snapshot).first_unpruned_height()
+    }
+
+    /// Returns the evidence of the validators signing conflicting consensus messages
+    /// observed by the node.
+    pub fn double_sign_evidence(&self) -> Vec<DoubleSignEvidence> {
+        Schema::new(&self.snapshot)
+            .double_sign_evidence()
+            .values()
+            .collect()
+    }
+
     /// Returns transaction message without proof.
     pub fn transaction_without_proof(&self, tx_hash: &Hash) -> Option<TransactionMessage> {
         let schema = Schema::new(&self.snapshot);
@@ -699,12 +763,13 @@ impl<'a> BlockchainExplorer<'a> {
         tx_hashes
     }
 
-    /// Retrieves a transaction that is known to be committed.
+    /// Retrieves a transaction that is known to be committed. Returns `None` if the
+    /// transaction has been pruned.
     fn committed_transaction(
         &self,
         tx_hash: &Hash,
         maybe_content: Option<TransactionMessage>,
-    ) -> CommittedTransaction {
+    ) -> Option<CommittedTransaction> {
         let schema = Schema::new(&self.snapshot);
 
         let location = schema
@@ -724,17 +789,21 @@ impl<'a> BlockchainExplorer<'a> {
         // Unwrap is OK here, because we already know that transaction is committed.
         let status = schema.transaction_results().get(tx_hash).unwrap();
 
-        CommittedTransaction {
-            content: maybe_content.unwrap_or_else(|| {
-                let raw_tx = schema.transactions().get(tx_hash).unwrap();
+        let content = match maybe_content {
+            Some(content) => content,
+            None => {
+                let raw_tx = schema.transactions().get(tx_hash)?;
                 (self.transaction_parser)(raw_tx).unwrap()
-            }),
+            }
+        };
 
+        Some(CommittedTransaction {
+            content,
             location,
             location_proof,
             status,
             time,
-        }
+        })
     }
 
     /// Returns the height of the blockchain.
@@ -753,9 +822,12 @@ impl<'a> BlockchainExplorer<'a> {
     }
 
     /// Returns block together with its transactions for the specified height, or `None`
-    /// if there is no such block.
+    /// if there is no such block or the block has been pruned.
     pub fn block_with_txs(&self, height: Height) -> Option<BlockWithTransactions> {
         let schema = Schema::new(&self.snapshot);
+        if schema.is_pruned(height) {
+            return None;
+        }
         let txs_table = schema.block_transactions(height);
         let block_proof = schema.block_and_precommits(height);
 
@@ -764,7 +836,7 @@ impl<'a> BlockchainExplorer<'a> {
             precommits: proof.precommits,
             transactions: txs_table
                 .iter()
-                .map(|tx_hash| self.committed_transaction(&tx_hash, None))
+                .filter_map(|tx_hash| self.committed_transaction(&tx_hash, None))
                 .collect(),
         })
     }
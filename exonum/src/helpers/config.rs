@@ -22,11 +22,14 @@ use std::{
     io::{Read, Write},
     mem::drop,
     path::{Path, PathBuf},
-    sync::mpsc,
+    sync::mpsc::{self, RecvTimeoutError},
     thread,
+    time::{Duration, SystemTime},
 };
 
-use crate::node::{ConnectListConfig, NodeConfig};
+use crate::node::{
+    ApiSender, ConnectListConfig, ConnectListWatchConfig, ExternalMessage, NodeConfig,
+};
 
 /// Implements loading and saving TOML-encoded configurations.
 #[derive(Debug)]
@@ -90,22 +93,60 @@ pub enum ConfigRequest {
 impl ConfigManager {
     /// Creates a new `ConfigManager` instance for the given path.
     pub fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path> + Send + 'static,
+    {
+        Self::spawn(path, None)
+    }
+
+    /// Creates a new `ConfigManager` instance for the given path, which also watches
+    /// the connect list in the config file and sends its changes to the node.
+    pub fn with_watch<P>(path: P, watch: ConnectListWatchConfig, api_sender: ApiSender) -> Self
+    where
+        P: AsRef<Path> + Send + 'static,
+    {
+        Self::spawn(path, Some((watch, api_sender)))
+    }
+
+    fn spawn<P>(path: P, watch: Option<(ConnectListWatchConfig, ApiSender)>) -> Self
     where
         P: AsRef<Path> + Send + 'static,
     {
         let (tx, rx) = mpsc::channel();
         let handle = thread::spawn(move || {
             info!("ConfigManager started");
-            for request in rx {
+            let mut watcher = watch
+                .map(|(config, api_sender)| ConnectListWatcher::new(&path, config, api_sender));
+            loop {
+                let request = match watcher {
+                    Some(ref mut watcher) => match rx.recv_timeout(watcher.interval) {
+                        Ok(request) => request,
+                        Err(RecvTimeoutError::Timeout) => {
+                            watcher.check(path.as_ref());
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    },
+                    None => match rx.recv() {
+                        Ok(request) => request,
+                        Err(_) => break,
+                    },
+                };
+
                 match request {
                     ConfigRequest::UpdateConnectList(connect_list) => {
                         info!("Updating connect list. New value: {:?}", connect_list);
 
-                        let res = Self::update_connect_list(connect_list, &path);
+                        let res = Self::update_connect_list(connect_list.clone(), &path);
 
                         if let Err(ref error) = res {
                             error!("Unable to update config: {}", error);
                         }
+                        // The node already uses the stored list, so it is not sent back
+                        // to the node once the watcher notices the file change.
+                        if let Some(ref mut watcher) = watcher {
+                            watcher.connect_list = Some(connect_list);
+                        }
                     }
                 }
             }
@@ -142,3 +183,66 @@ impl ConfigManager {
         Ok(())
     }
 }
+
+/// Watcher of the connect list in the config file.
+#[derive(Debug)]
+struct ConnectListWatcher {
+    interval: Duration,
+    api_sender: ApiSender,
+    modified: Option<SystemTime>,
+    connect_list: Option<ConnectListConfig>,
+}
+
+impl ConnectListWatcher {
+    fn new<P: AsRef<Path>>(path: P, config: ConnectListWatchConfig, api_sender: ApiSender) -> Self {
+        let path = path.as_ref();
+        ConnectListWatcher {
+            interval: Duration::from_millis(config.interval),
+            api_sender,
+            modified: modified_time(path),
+            connect_list: load_connect_list(path).ok(),
+        }
+    }
+
+    /// Sends the connect list from the config file to the node if the list has been changed.
+    fn check(&mut self, path: &Path) {
+        let modified = modified_time(path);
+        if modified == self.modified {
+            return;
+        }
+
+        // The file may be partially written, in which case it is read again on the next check.
+        let connect_list = match load_connect_list(path) {
+            Ok(connect_list) => connect_list,
+            Err(error) => {
+                warn!("Unable to read connect list from config: {}", error);
+                return;
+            }
+        };
+        self.modified = modified;
+        if self.connect_list.as_ref() == Some(&connect_list) {
+            return;
+        }
+
+        info!(
+            "Connect list has been changed in config. New value: {:?}",
+            connect_list
+        );
+        self.connect_list = Some(connect_list.clone());
+        let message = ExternalMessage::UpdateConnectList(connect_list);
+        if let Err(error) = self.api_sender.send_external_message(message) {
+            error!("Unable to send connect list to the node: {}", error);
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn load_connect_list(path: &Path) -> Result<ConnectListConfig, Error> {
+    let config: NodeConfig<PathBuf> = ConfigFile::load(path)?;
+    Ok(config.connect_list)
+}
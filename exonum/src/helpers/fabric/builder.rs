@@ -16,23 +16,32 @@ use std::{
     collections::HashMap,
     ffi::OsString,
     fmt,
+    fs::File,
+    io::BufReader,
     panic::{self, PanicInfo},
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
+use exonum_merkledb::TemporaryDB;
+use futures::sync::mpsc;
+
 use super::{
     clap_backend::ClapBackend,
     details::{Finalize, GenerateCommonConfig, GenerateNodeConfig, Run, RunDev},
     info::Info,
     internal::{CollectedCommand, Command, Feedback},
     keys,
+    keytool::Keytool,
     maintenance::Maintenance,
     password::{PassInputMethod, SecretKeyType},
-    CommandName, Context, ServiceFactory,
+    tx::SignTransaction,
+    CommandName, Context, DatabaseFactories, DatabaseFactory, ServiceFactory,
 };
 
-use crate::blockchain::Service;
-use crate::node::{ExternalMessage, Node};
+use crate::blockchain::{replay, Blockchain, Schema, Service};
+use crate::crypto::{self, CryptoHash};
+use crate::node::{ApiSender, ExternalMessage, Node, ReadReplica};
 
 /// `NodeBuilder` is a high level object,
 /// usable for fast prototyping and creating app from services list.
@@ -40,14 +49,17 @@ use crate::node::{ExternalMessage, Node};
 pub struct NodeBuilder {
     commands: HashMap<CommandName, CollectedCommand>,
     service_factories: Vec<Box<dyn ServiceFactory>>,
+    database_factories: DatabaseFactories,
 }
 
 impl NodeBuilder {
     /// Creates a new empty `NodeBuilder`.
     pub fn new() -> Self {
+        let database_factories = DatabaseFactories::default();
         Self {
-            commands: Self::commands(),
+            commands: Self::commands(&database_factories),
             service_factories: Vec::new(),
+            database_factories,
         }
     }
 
@@ -62,6 +74,16 @@ impl NodeBuilder {
         self
     }
 
+    /// Registers the factory for databases of the given type, which can then be selected
+    /// with the `database.type` field of the node configuration.
+    pub fn with_database_factory<F>(self, db_type: &str, factory: F) -> Self
+    where
+        F: DatabaseFactory,
+    {
+        self.database_factories.register(db_type, factory);
+        self
+    }
+
     #[doc(hidden)]
     pub fn parse_cmd_string<I, T>(self, cmd_line: I) -> bool
     where
@@ -70,7 +92,17 @@ impl NodeBuilder {
     {
         let feedback = ClapBackend::execute_cmd_string(&self.commands, cmd_line);
         if let Feedback::RunNode(ref ctx) = feedback {
-            self.node_from_run_context(ctx);
+            if Run::migrations_dry_run(ctx) {
+                self.check_migrations(ctx);
+            } else if Run::replay_blocks(ctx) {
+                self.replay_blocks(ctx);
+            } else if Run::replica(ctx) {
+                self.run_replica(ctx);
+            } else {
+                self.node_from_run_context(ctx);
+            }
+        } else if let Feedback::SignTransaction(ref ctx) = feedback {
+            self.sign_transaction(ctx);
         }
         feedback != Feedback::None
     }
@@ -78,10 +110,26 @@ impl NodeBuilder {
     /// Parse cmd args, return `Node`, if run command found
     pub fn parse_cmd(self) -> Option<Node> {
         match ClapBackend::execute(&self.commands) {
+            Feedback::RunNode(ref ctx) if Run::migrations_dry_run(ctx) => {
+                self.check_migrations(ctx);
+                None
+            }
+            Feedback::RunNode(ref ctx) if Run::replay_blocks(ctx) => {
+                self.replay_blocks(ctx);
+                None
+            }
+            Feedback::RunNode(ref ctx) if Run::replica(ctx) => {
+                self.run_replica(ctx);
+                None
+            }
             Feedback::RunNode(ref ctx) => {
                 let node = self.node_from_run_context(ctx);
                 Some(node)
             }
+            Feedback::SignTransaction(ref ctx) => {
+                self.sign_transaction(ctx);
+                None
+            }
             _ => None,
         }
     }
@@ -129,20 +177,158 @@ impl NodeBuilder {
         }
     }
 
-    fn commands() -> HashMap<CommandName, CollectedCommand> {
+    fn commands(database_factories: &DatabaseFactories) -> HashMap<CommandName, CollectedCommand> {
         vec![
             Box::new(Run) as Box<dyn Command>,
             Box::new(RunDev),
             Box::new(GenerateNodeConfig),
             Box::new(GenerateCommonConfig),
             Box::new(Finalize),
-            Box::new(Maintenance),
+            Box::new(Maintenance::new(database_factories.clone())),
+            Box::new(Keytool),
+            Box::new(SignTransaction),
         ]
         .into_iter()
         .map(|c| (c.name(), CollectedCommand::new(c)))
         .collect()
     }
 
+    /// Performs the pending data migrations of the services without saving their results
+    /// and prints them.
+    fn check_migrations(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let db = Run::db_helper(ctx, &config.database, &self.database_factories);
+        let services: Vec<Box<dyn Service>> = self
+            .service_factories
+            .into_iter()
+            .map(|mut factory| factory.make_service(ctx))
+            .collect();
+
+        // Service keys are not used by the migrations, so the secret key is not decrypted.
+        let (service_public_key, service_secret_key) = crypto::gen_keypair();
+        let blockchain = Blockchain::new(
+            db,
+            services,
+            service_public_key,
+            service_secret_key,
+            ApiSender::new(mpsc::channel(0).0),
+        );
+        let migrations = blockchain
+            .check_migrations()
+            .unwrap_or_else(|e| panic!("Migrations failed: {}", e));
+        if migrations.is_empty() {
+            println!("No pending migrations");
+        }
+        for migration in migrations {
+            println!(
+                "Pending migration of service {} to version {}: {}",
+                migration.service_name, migration.version, migration.description
+            );
+        }
+    }
+
+    /// Builds and signs a transaction with the protobuf descriptors of the services
+    /// of the builder.
+    fn sign_transaction(self, ctx: &Context) {
+        let descriptors = self
+            .service_factories
+            .into_iter()
+            .filter_map(|mut factory| {
+                let service = factory.make_service(ctx);
+                service
+                    .protobuf_descriptor()
+                    .map(|descriptor| (service.service_id(), descriptor))
+            })
+            .collect::<HashMap<_, _>>();
+        let transaction = SignTransaction::sign(ctx, descriptors)
+            .unwrap_or_else(|e| panic!("Could not sign transaction: {}", e));
+        SignTransaction::output(ctx, &transaction);
+    }
+
+    /// Re-executes the stored blocks with the services of the builder against a temporary
+    /// database and prints the first diverged block.
+    fn replay_blocks(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let db = Run::db_helper(ctx, &config.database, &self.database_factories);
+        let services: Vec<Box<dyn Service>> = self
+            .service_factories
+            .into_iter()
+            .map(|mut factory| factory.make_service(ctx))
+            .collect();
+
+        // Service keys are not used during the execution of the blocks.
+        let (service_public_key, service_secret_key) = crypto::gen_keypair();
+        let mut blockchain = Blockchain::new(
+            TemporaryDB::new(),
+            services,
+            service_public_key,
+            service_secret_key,
+            ApiSender::new(mpsc::channel(0).0),
+        );
+        blockchain.set_liveness(config.liveness);
+
+        let snapshot = db.snapshot();
+        let height = Schema::new(&snapshot).height();
+        let report = replay::replay_blocks(&*snapshot, &mut blockchain, height)
+            .unwrap_or_else(|e| panic!("Replay failed: {}", e));
+        match report.divergence {
+            None => println!("Replayed {} blocks, no divergence found", report.replayed),
+            Some(divergence) => {
+                println!(
+                    "Replayed {} blocks, the block at height {} diverges",
+                    report.replayed, divergence.height
+                );
+                println!(
+                    "Stored block: hash {:?}, state hash {:?}, tx hash {:?}",
+                    divergence.expected.hash(),
+                    divergence.expected.state_hash(),
+                    divergence.expected.tx_hash()
+                );
+                println!(
+                    "Replayed block: hash {:?}, state hash {:?}, tx hash {:?}",
+                    divergence.actual.hash(),
+                    divergence.actual.state_hash(),
+                    divergence.actual.tx_hash()
+                );
+            }
+        }
+    }
+
+    /// Runs a read replica serving the public API from the checkpoints of the node.
+    fn run_replica(self, ctx: &Context) {
+        let config = ctx
+            .get(keys::NODE_CONFIG)
+            .expect("could not find node_config");
+        let checkpoints = config
+            .checkpoints
+            .as_ref()
+            .map(|checkpoints| checkpoints.directory.clone())
+            .expect("Read replicas require checkpoints to be enabled in the node config");
+        let directory = PathBuf::from(Run::database_path(ctx));
+        let passphrase = Run::database_passphrase(ctx, &config.database);
+        let database = config.database.clone();
+        let database_factories = self.database_factories.clone();
+        let open_db = Box::new(move |path: &Path| match passphrase {
+            Some(ref passphrase) => {
+                database_factories.open_encrypted(path, &database, passphrase.as_bytes())
+            }
+            None => database_factories.open(path, &database),
+        });
+        let services: Vec<Box<dyn Service>> = self
+            .service_factories
+            .into_iter()
+            .map(|mut factory| factory.make_service(ctx))
+            .collect();
+
+        ReadReplica::new(checkpoints, directory, open_db, services, config.api)
+            .and_then(ReadReplica::run)
+            .unwrap_or_else(|e| panic!("Read replica failed: {}", e));
+    }
+
     fn node_from_run_context(self, ctx: &Context) -> Node {
         let config_file_path = ctx
             .get(keys::NODE_CONFIG_PATH)
@@ -150,7 +336,7 @@ impl NodeBuilder {
         let config = ctx
             .get(keys::NODE_CONFIG)
             .expect("could not find node_config");
-        let db = Run::db_helper(ctx, &config.database);
+        let db = Run::db_helper(ctx, &config.database, &self.database_factories);
         let services: Vec<Box<dyn Service>> = self
             .service_factories
             .into_iter()
@@ -172,7 +358,34 @@ impl NodeBuilder {
                 service_passphrase.as_bytes(),
             )
         };
-        Node::new(db, services, config, Some(config_file_path))
+        match (Run::import_snapshot_path(ctx), Run::import_blocks_path(ctx)) {
+            (Some(_), Some(_)) => {
+                panic!("The state snapshot and the block archive can't be imported together")
+            }
+            (Some(snapshot_path), None) => {
+                let snapshot = File::open(&snapshot_path).expect("Can't open snapshot file");
+                Node::from_snapshot(
+                    db,
+                    services,
+                    config,
+                    Some(config_file_path),
+                    &mut BufReader::new(snapshot),
+                )
+                .expect("Can't import state snapshot")
+            }
+            (None, Some(archive_path)) => {
+                let archive = File::open(&archive_path).expect("Can't open block archive file");
+                Node::from_block_archive(
+                    db,
+                    services,
+                    config,
+                    Some(config_file_path),
+                    &mut BufReader::new(archive),
+                )
+                .expect("Can't import block archive")
+            }
+            (None, None) => Node::new(db, services, config, Some(config_file_path)),
+        }
     }
 }
 
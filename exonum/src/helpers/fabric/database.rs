@@ -0,0 +1,266 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Selection of the database backend by the type specified in the node configuration.
+
+use failure::Error;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use crate::node::DatabaseConfig;
+use exonum_merkledb::{
+    CachedDB, CompressedDB, Database, DbOptions, EncryptedDB, PipelinedDB, RocksDB, TemporaryDB,
+};
+
+/// Database type of the `RocksDB` backend, which is used by default.
+pub const ROCKSDB_DATABASE_TYPE: &str = "rocksdb";
+/// Database type of the in-memory `TemporaryDB` backend. The database path is ignored.
+pub const TEMPORARY_DATABASE_TYPE: &str = "temporary";
+
+/// Factory creating databases of a certain type.
+///
+/// Any closure with the matching signature is a `DatabaseFactory`.
+pub trait DatabaseFactory: Send + Sync + 'static {
+    /// Opens the database at the given path with the given options.
+    fn open(&self, path: &Path, options: &DbOptions) -> Result<Box<dyn Database>, Error>;
+}
+
+impl<F> DatabaseFactory for F
+where
+    F: Fn(&Path, &DbOptions) -> Result<Box<dyn Database>, Error> + Send + Sync + 'static,
+{
+    fn open(&self, path: &Path, options: &DbOptions) -> Result<Box<dyn Database>, Error> {
+        self(path, options)
+    }
+}
+
+/// Registry of the database factories keyed by the database type.
+///
+/// The registry contains factories for the `rocksdb` and `temporary` types by default.
+/// Clones of the registry share the registered factories.
+#[derive(Clone)]
+pub struct DatabaseFactories {
+    factories: Arc<RwLock<HashMap<String, Arc<dyn DatabaseFactory>>>>,
+}
+
+impl DatabaseFactories {
+    /// Registers the factory for the given database type. The factory previously
+    /// registered for this type, including a built-in one, is replaced.
+    pub fn register<F>(&self, db_type: &str, factory: F)
+    where
+        F: DatabaseFactory,
+    {
+        self.factories
+            .write()
+            .expect("Expected write lock")
+            .insert(db_type.to_owned(), Arc::new(factory));
+    }
+
+    /// Opens the database at the given path using the factory registered for
+    /// the database type from the configuration.
+    ///
+    /// If compression is enabled in the configuration, the database is wrapped
+    /// into `CompressedDB`. If secondary indexes are specified, the database is
+    /// wrapped into `PipelinedDB`.
+    pub fn open(&self, path: &Path, config: &DatabaseConfig) -> Result<Box<dyn Database>, Error> {
+        let db = self.open_backend(path, config)?;
+        Self::wrap(db, config)
+    }
+
+    /// Opens the database like [`open`](#method.open) and wraps it into `EncryptedDB`
+    /// with the key derived from the passphrase. Values are compressed before
+    /// the encryption.
+    ///
+    /// Fails if the passphrase does not match the one the database was encrypted with,
+    /// or if column families are configured, since the index metadata they are assigned
+    /// by is encrypted.
+    pub fn open_encrypted(
+        &self,
+        path: &Path,
+        config: &DatabaseConfig,
+        passphrase: &[u8],
+    ) -> Result<Box<dyn Database>, Error> {
+        ensure!(
+            config.options.column_families.is_empty(),
+            "Column families cannot be used with an encrypted database"
+        );
+        let db = self.open_backend(path, config)?;
+        Self::wrap(Box::new(EncryptedDB::new(db, passphrase)?), config)
+    }
+
+    fn open_backend(
+        &self,
+        path: &Path,
+        config: &DatabaseConfig,
+    ) -> Result<Box<dyn Database>, Error> {
+        let factory = self
+            .factories
+            .read()
+            .expect("Expected read lock")
+            .get(&config.db_type)
+            .cloned()
+            .ok_or_else(|| format_err!("Unknown database type: {}", config.db_type))?;
+        factory.open(path, &config.options)
+    }
+
+    fn wrap(db: Box<dyn Database>, config: &DatabaseConfig) -> Result<Box<dyn Database>, Error> {
+        let db: Box<dyn Database> = match config.compression {
+            Some(options) => Box::new(CompressedDB::new(db, options)?),
+            None => db,
+        };
+        let db: Box<dyn Database> = if config.secondary_indexes.is_empty() {
+            db
+        } else {
+            Box::new(PipelinedDB::new(db, config.secondary_indexes.clone()))
+        };
+        Ok(if config.caches.is_empty() {
+            db
+        } else {
+            Box::new(CachedDB::new(db, config.caches.clone()))
+        })
+    }
+}
+
+impl Default for DatabaseFactories {
+    fn default() -> Self {
+        let factories = Self {
+            factories: Arc::default(),
+        };
+        factories.register(
+            ROCKSDB_DATABASE_TYPE,
+            |path: &Path, options: &DbOptions| -> Result<Box<dyn Database>, Error> {
+                Ok(Box::new(RocksDB::open(path, options)?))
+            },
+        );
+        factories.register(
+            TEMPORARY_DATABASE_TYPE,
+            |_: &Path, _: &DbOptions| -> Result<Box<dyn Database>, Error> {
+                Ok(Box::new(TemporaryDB::new()))
+            },
+        );
+        factories
+    }
+}
+
+impl fmt::Debug for DatabaseFactories {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let factories = self.factories.read().expect("Expected read lock");
+        f.debug_struct("DatabaseFactories")
+            .field("types", &factories.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[test]
+fn database_factories_open() {
+    let factories = DatabaseFactories::default();
+    let config = DatabaseConfig {
+        db_type: TEMPORARY_DATABASE_TYPE.to_owned(),
+        ..DatabaseConfig::default()
+    };
+    let db = factories.open(Path::new("unused"), &config).unwrap();
+    let fork = db.fork();
+    db.merge_sync(fork.into_patch()).unwrap();
+
+    let config = DatabaseConfig {
+        db_type: "custom".to_owned(),
+        ..DatabaseConfig::default()
+    };
+    assert!(factories.open(Path::new("unused"), &config).is_err());
+
+    factories.clone().register(
+        "custom",
+        |_: &Path, _: &DbOptions| -> Result<Box<dyn Database>, Error> {
+            Ok(Box::new(TemporaryDB::new()))
+        },
+    );
+    assert!(factories.open(Path::new("unused"), &config).is_ok());
+}
+
+#[test]
+fn database_factories_open_compressed() {
+    use exonum_merkledb::{CompressionAlgorithm, CompressionOptions, Entry};
+
+    let factories = DatabaseFactories::default();
+    let config = DatabaseConfig {
+        db_type: TEMPORARY_DATABASE_TYPE.to_owned(),
+        compression: Some(CompressionOptions::new(CompressionAlgorithm::Lz4)),
+        ..DatabaseConfig::default()
+    };
+    let db = factories.open(Path::new("unused"), &config).unwrap();
+    let fork = db.fork();
+    Entry::new("entry", &fork).set(vec![1_u8; 1024]);
+    db.merge_sync(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let entry: Entry<_, Vec<u8>> = Entry::new("entry", &snapshot);
+    assert_eq!(entry.get(), Some(vec![1_u8; 1024]));
+}
+
+#[test]
+fn database_factories_open_pipelined() {
+    use exonum_merkledb::ListIndex;
+
+    let factories = DatabaseFactories::default();
+    let config = DatabaseConfig {
+        db_type: TEMPORARY_DATABASE_TYPE.to_owned(),
+        secondary_indexes: vec!["lookup".to_owned()],
+        ..DatabaseConfig::default()
+    };
+    let db = factories.open(Path::new("unused"), &config).unwrap();
+    for i in 0..10_u64 {
+        let fork = db.fork();
+        ListIndex::new("list", &fork).push(i);
+        ListIndex::new("lookup", &fork).push(i);
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    let snapshot = db.snapshot();
+    let list: ListIndex<_, u64> = ListIndex::new("list", &snapshot);
+    assert_eq!(list.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+    let lookup: ListIndex<_, u64> = ListIndex::new("lookup", &snapshot);
+    assert_eq!(
+        lookup.iter().collect::<Vec<_>>(),
+        (0..10).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn database_factories_open_cached() {
+    use exonum_merkledb::{CacheOptions, MapIndex};
+
+    let factories = DatabaseFactories::default();
+    let config = DatabaseConfig {
+        db_type: TEMPORARY_DATABASE_TYPE.to_owned(),
+        secondary_indexes: vec!["map".to_owned()],
+        caches: vec![CacheOptions::new(vec!["map"], 16)],
+        ..DatabaseConfig::default()
+    };
+    let db = factories.open(Path::new("unused"), &config).unwrap();
+    for i in 0..10_u64 {
+        let fork = db.fork();
+        MapIndex::new("map", &fork).put(&0_u8, i);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map: MapIndex<_, u8, u64> = MapIndex::new("map", &snapshot);
+        assert_eq!(map.get(&0), Some(i));
+        assert_eq!(map.get(&0), Some(i));
+    }
+}
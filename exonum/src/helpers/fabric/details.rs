@@ -22,6 +22,7 @@ use std::{
     fs,
     net::{IpAddr, SocketAddr},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use super::{
@@ -32,18 +33,27 @@ use super::{
         AbstractConfig, CommonConfigTemplate, NodePrivateConfig, NodePublicConfig, NodeRunConfig,
         SharedConfig,
     },
-    Argument, CommandName, Context, DEFAULT_EXONUM_LISTEN_PORT,
+    Argument, CommandName, Context, DatabaseFactories, DEFAULT_EXONUM_LISTEN_PORT,
 };
 use crate::api::backends::actix::AllowOrigin;
 use crate::blockchain::{config::ValidatorKeys, GenesisConfig};
-use crate::crypto::{generate_keys_file, PublicKey};
+use crate::crypto::{
+    create_keys_file_with_options, gen_keypair, mnemonic::KeyPurpose, HashAlgorithm,
+    KeyFileOptions, PublicKey,
+};
 use crate::helpers::{config::ConfigFile, ZeroizeOnDrop};
-use crate::node::{ConnectListConfig, NodeApiConfig, NodeConfig};
-use exonum_merkledb::{Database, DbOptions, RocksDB};
+use crate::node::{ConnectListConfig, DatabaseConfig, NodeApiConfig, NodeConfig};
+use exonum_merkledb::Database;
 
 const CONSENSUS_KEY_PASS_METHOD: &str = "CONSENSUS_KEY_PASS_METHOD";
+const DATABASE_PASS_METHOD: &str = "DATABASE_PASS_METHOD";
 const DATABASE_PATH: &str = "DATABASE_PATH";
+const DOMAIN_SEPARATED_SIGNATURES: &str = "DOMAIN_SEPARATED_SIGNATURES";
+const HASH_ALGORITHM: &str = "HASH_ALGORITHM";
+const IMPORT_BLOCKS_PATH: &str = "IMPORT_BLOCKS_PATH";
+const IMPORT_SNAPSHOT_PATH: &str = "IMPORT_SNAPSHOT_PATH";
 const LISTEN_ADDRESS: &str = "LISTEN_ADDRESS";
+const MIGRATIONS_DRY_RUN: &str = "MIGRATIONS_DRY_RUN";
 const NO_PASSWORD: &str = "NO_PASSWORD";
 const NODE_CONFIG_PATH: &str = "NODE_CONFIG_PATH";
 const PEER_ADDRESS: &str = "PEER_ADDRESS";
@@ -51,18 +61,84 @@ const PRIVATE_ALLOW_ORIGIN: &str = "PRIVATE_ALLOW_ORIGIN";
 const PRIVATE_API_ADDRESS: &str = "PRIVATE_API_ADDRESS";
 const PUBLIC_ALLOW_ORIGIN: &str = "PUBLIC_ALLOW_ORIGIN";
 const PUBLIC_API_ADDRESS: &str = "PUBLIC_API_ADDRESS";
+const REPLAY_BLOCKS: &str = "REPLAY_BLOCKS";
+const REPLICA: &str = "REPLICA";
 const SERVICE_KEY_PASS_METHOD: &str = "SERVICE_KEY_PASS_METHOD";
 
 /// Run command.
 pub struct Run;
 
 impl Run {
-    /// Returns created database instance.
-    pub fn db_helper(ctx: &Context, options: &DbOptions) -> Box<dyn Database> {
-        let path = ctx
-            .arg::<String>(DATABASE_PATH)
-            .unwrap_or_else(|_| panic!("{} not found.", DATABASE_PATH));
-        Box::new(RocksDB::open(Path::new(&path), options).expect("Can't load database file"))
+    /// Returns database instance created by the factory for the configured database type.
+    ///
+    /// If the database is encrypted, the passphrase is requested using the input method
+    /// specified by the `--db-pass` argument.
+    pub fn db_helper(
+        ctx: &Context,
+        config: &DatabaseConfig,
+        database_factories: &DatabaseFactories,
+    ) -> Box<dyn Database> {
+        let path = Self::database_path(ctx);
+        let path = Path::new(&path);
+        match Self::database_passphrase(ctx, config) {
+            Some(passphrase) => database_factories
+                .open_encrypted(path, config, passphrase.as_bytes())
+                .expect("Can't load encrypted database file"),
+            None => database_factories
+                .open(path, config)
+                .expect("Can't load database file"),
+        }
+    }
+
+    /// Returns the path to the database.
+    pub(crate) fn database_path(ctx: &Context) -> String {
+        ctx.arg::<String>(DATABASE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", DATABASE_PATH))
+    }
+
+    /// Requests the passphrase of the database using the input method specified by
+    /// the `--db-pass` argument, or returns `None` if the database is not encrypted.
+    pub(crate) fn database_passphrase(
+        ctx: &Context,
+        config: &DatabaseConfig,
+    ) -> Option<ZeroizeOnDrop<String>> {
+        if config.encrypted {
+            let passphrase =
+                PassInputMethod::from_str(&Self::pass_input_method(ctx, SecretKeyType::Database))
+                    .expect("Incorrect passphrase input method for database.")
+                    .get_passphrase(SecretKeyType::Database, true);
+            Some(passphrase)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the path to the state snapshot to be imported before the node start.
+    pub(crate) fn import_snapshot_path(ctx: &Context) -> Option<String> {
+        ctx.arg::<String>(IMPORT_SNAPSHOT_PATH).ok()
+    }
+
+    /// Returns the path to the block archive to be imported before the node start.
+    pub(crate) fn import_blocks_path(ctx: &Context) -> Option<String> {
+        ctx.arg::<String>(IMPORT_BLOCKS_PATH).ok()
+    }
+
+    /// Returns `true` if the pending data migrations should be checked instead of
+    /// running the node.
+    pub(crate) fn migrations_dry_run(ctx: &Context) -> bool {
+        ctx.has_flag(MIGRATIONS_DRY_RUN)
+    }
+
+    /// Returns `true` if the stored blocks should be re-executed with the current services
+    /// instead of running the node.
+    pub(crate) fn replay_blocks(ctx: &Context) -> bool {
+        ctx.has_flag(REPLAY_BLOCKS)
+    }
+
+    /// Returns `true` if a read replica serving the checkpoints of the node should be run
+    /// instead of the node.
+    pub(crate) fn replica(ctx: &Context) -> bool {
+        ctx.has_flag(REPLICA)
     }
 
     fn node_config_path(ctx: &Context) -> String {
@@ -86,6 +162,7 @@ impl Run {
         let arg_key = match key_type {
             SecretKeyType::Consensus => CONSENSUS_KEY_PASS_METHOD,
             SecretKeyType::Service => SERVICE_KEY_PASS_METHOD,
+            SecretKeyType::Database => DATABASE_PASS_METHOD,
         };
         ctx.arg(arg_key).unwrap_or_default()
     }
@@ -110,6 +187,50 @@ impl Command for Run {
                 "db-path",
                 false,
             ),
+            Argument::new_named(
+                IMPORT_SNAPSHOT_PATH,
+                false,
+                "Import the state snapshot from the given path into the database \
+                 containing only the genesis block before the node start.",
+                None,
+                "import-snapshot",
+                false,
+            ),
+            Argument::new_named(
+                IMPORT_BLOCKS_PATH,
+                false,
+                "Import the blocks from the archive at the given path into the database \
+                 before the node start.",
+                None,
+                "import-blocks",
+                false,
+            ),
+            Argument::new_flag(
+                MIGRATIONS_DRY_RUN,
+                "Check the pending data migrations of the services without applying them \
+                 and exit instead of running the node.",
+                None,
+                "migrations-dry-run",
+                false,
+            ),
+            Argument::new_flag(
+                REPLAY_BLOCKS,
+                "Re-execute the stored blocks with the current services against a fresh state, \
+                 report the first block resulting in another state and exit instead of \
+                 running the node.",
+                None,
+                "replay",
+                false,
+            ),
+            Argument::new_flag(
+                REPLICA,
+                "Run a read replica serving the public API from the newest database checkpoint \
+                 of the node instead of running the node. The checkpoints are copied into \
+                 the database directory.",
+                None,
+                "replica",
+                false,
+            ),
             Argument::new_named(
                 PUBLIC_API_ADDRESS,
                 false,
@@ -146,6 +267,16 @@ impl Command for Run {
                 "service-key-pass",
                 false,
             ),
+            Argument::new_named(
+                DATABASE_PASS_METHOD,
+                false,
+                "Passphrase entry method for the encrypted database.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_DATABASE_PASS is used",
+                None,
+                "db-pass",
+                false,
+            ),
         ]
     }
 
@@ -360,6 +491,21 @@ impl Command for GenerateCommonConfig {
                 "validators-count",
                 false,
             ),
+            Argument::new_named(
+                HASH_ALGORITHM,
+                false,
+                "Hash function used by the blockchain: `sha256` (default) or `blake2b`.",
+                None,
+                "hash-algorithm",
+                false,
+            ),
+            Argument::new_flag(
+                DOMAIN_SEPARATED_SIGNATURES,
+                "Include the identifier of the network into the signatures of the messages.",
+                None,
+                "domain-separated-signatures",
+                false,
+            ),
         ]
     }
 
@@ -384,6 +530,20 @@ impl Command for GenerateCommonConfig {
         let validators_count = context
             .arg::<u16>("VALIDATORS_COUNT")
             .expect("VALIDATORS_COUNT not found");
+        let hash_algorithm = context
+            .arg::<String>(HASH_ALGORITHM)
+            .ok()
+            .map_or_else(HashAlgorithm::default, |name| {
+                name.parse().expect("Invalid hash algorithm")
+            });
+        if !hash_algorithm.is_supported() {
+            panic!(
+                "Hash algorithm {} is not supported by this build",
+                hash_algorithm
+            );
+        }
+
+        let domain_separated_signatures = context.has_flag(DOMAIN_SEPARATED_SIGNATURES);
 
         context.set(keys::SERVICES_CONFIG, AbstractConfig::default());
         let new_context = exts(context);
@@ -396,6 +556,8 @@ impl Command for GenerateCommonConfig {
         );
 
         let template = CommonConfigTemplate {
+            hash_algorithm,
+            domain_separated_signatures,
             services_config,
             general_config,
             ..CommonConfigTemplate::default()
@@ -588,7 +750,11 @@ impl Command for GenerateNodeConfig {
                 consensus_key_pass_method,
                 SecretKeyType::Consensus,
             );
-            create_secret_key_file(&consensus_secret_key_path, passphrase.as_bytes())
+            create_secret_key_file(
+                &consensus_secret_key_path,
+                passphrase.as_bytes(),
+                KeyPurpose::Consensus,
+            )
         };
         let service_public_key = {
             let passphrase = Self::get_passphrase(
@@ -596,7 +762,11 @@ impl Command for GenerateNodeConfig {
                 service_key_pass_method,
                 SecretKeyType::Service,
             );
-            create_secret_key_file(&service_secret_key_path, passphrase.as_bytes())
+            create_secret_key_file(
+                &service_secret_key_path,
+                passphrase.as_bytes(),
+                KeyPurpose::Service,
+            )
         };
 
         let validator_keys = ValidatorKeys {
@@ -646,6 +816,8 @@ impl Finalize {
             template.consensus_config,
             configs.iter().map(|c| c.validator_keys),
         )
+        .with_hash_algorithm(template.hash_algorithm)
+        .with_domain_separated_signatures(template.domain_separated_signatures)
     }
 
     fn reduce_configs(
@@ -821,6 +993,25 @@ impl Command for Finalize {
                 database: Default::default(),
                 connect_list,
                 thread_pool_size: Default::default(),
+                max_pending_verifications: None,
+                pruning: None,
+                checkpoints: None,
+                garbage_collection: None,
+                integrity_check: None,
+                state_history: None,
+                liveness: None,
+                parallel_execution: None,
+                watchdog: None,
+                catch_up: None,
+                diagnostics: None,
+                peer_stats: None,
+                signing_record: None,
+                remote_signer: None,
+                threshold_key: None,
+                key_derivation: None,
+                peer_bans: None,
+                proxy: None,
+                connect_list_watch: None,
             }
         };
 
@@ -846,6 +1037,7 @@ impl Command for Finalize {
 fn create_secret_key_file(
     secret_key_path: impl AsRef<Path>,
     passphrase: impl AsRef<[u8]>,
+    purpose: KeyPurpose,
 ) -> PublicKey {
     let secret_key_path = secret_key_path.as_ref();
     if secret_key_path.exists() {
@@ -857,7 +1049,17 @@ fn create_secret_key_file(
         if let Some(dir) = secret_key_path.parent() {
             fs::create_dir_all(dir).unwrap();
         }
-        generate_keys_file(&secret_key_path, &passphrase).unwrap()
+        let (public_key, secret_key) = gen_keypair();
+        let options = KeyFileOptions::new().with_purpose(purpose);
+        create_keys_file_with_options(
+            &secret_key_path,
+            &passphrase,
+            public_key,
+            &secret_key,
+            &options,
+        )
+        .unwrap();
+        public_key
     }
 }
 
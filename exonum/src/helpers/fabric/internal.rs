@@ -23,6 +23,8 @@ use super::{Argument, CommandExtension, CommandName, Context};
 pub enum Feedback {
     /// Run node with current context.
     RunNode(Context),
+    /// Sign a transaction with the services of the node.
+    SignTransaction(Context),
     /// Do nothing
     None,
 }
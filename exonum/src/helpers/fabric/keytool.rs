@@ -0,0 +1,283 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the generation and recovery of the node keys
+//! from mnemonic phrases.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use super::{
+    internal::{CollectedCommand, Command, Feedback},
+    password::{PassInputMethod, SecretKeyType},
+    Argument, CommandName, Context,
+};
+use crate::crypto::{
+    create_keys_file_with_options, create_seed_file,
+    hd::{DerivationPath, ExtendedKey},
+    mnemonic::{self, KeyPurpose},
+    KeyFileOptions,
+};
+use crate::helpers::ZeroizeOnDrop;
+
+// Context entry for the type of action to be performed.
+const KEYTOOL_ACTION: &str = "KEYTOOL_ACTION";
+// Context entry for the directory of the key files.
+const OUTPUT_DIR: &str = "OUTPUT_DIR";
+// Context entry for the entry method of the mnemonic phrase.
+const MNEMONIC_METHOD: &str = "MNEMONIC_METHOD";
+// Context entry for the passphrase entry method of the mnemonic phrase.
+const MNEMONIC_PASS_METHOD: &str = "MNEMONIC_PASS_METHOD";
+// Context entry for the passphrase entry method of the consensus key.
+const CONSENSUS_KEY_PASS_METHOD: &str = "CONSENSUS_KEY_PASS_METHOD";
+// Context entry for the passphrase entry method of the service key.
+const SERVICE_KEY_PASS_METHOD: &str = "SERVICE_KEY_PASS_METHOD";
+
+const CONSENSUS_SECRET_KEY_NAME: &str = "consensus.key.toml";
+const SERVICE_SECRET_KEY_NAME: &str = "service.key.toml";
+const MASTER_SEED_NAME: &str = "master.seed.toml";
+
+/// Keytool command. Supported actions:
+///
+/// - `generate` - generate a new mnemonic phrase, print it and write the consensus
+///   and service key files derived from it.
+/// - `recover` - write the consensus and service key files derived from an existing
+///   mnemonic phrase.
+/// - `seed` - write the master seed file of an existing mnemonic phrase, from which
+///   the keys are derived according to `NodeConfig::key_derivation`. The seed file
+///   is encrypted with the consensus key passphrase.
+///
+/// The key files are written to the output directory with the same names as
+/// the `generate-config` command uses.
+#[derive(Debug, Default)]
+pub struct Keytool;
+
+impl Keytool {
+    fn pass_method(ctx: &Context, name: &str) -> Option<PassInputMethod> {
+        ctx.arg::<String>(name).ok().map(|method| {
+            method
+                .parse()
+                .unwrap_or_else(|_| panic!("Incorrect passphrase input method: {}", method))
+        })
+    }
+
+    fn mnemonic_passphrase(ctx: &Context, recover: bool) -> ZeroizeOnDrop<String> {
+        // The mnemonic passphrase is optional, so it is only requested if the input method
+        // is set explicitly.
+        Self::pass_method(ctx, MNEMONIC_PASS_METHOD)
+            .map(|method| method.get_passphrase(SecretKeyType::MnemonicPassphrase, recover))
+            .unwrap_or_default()
+    }
+
+    fn output_dir(ctx: &Context) -> PathBuf {
+        let output_dir: PathBuf = ctx
+            .arg(OUTPUT_DIR)
+            .unwrap_or_else(|_| panic!("{} not found.", OUTPUT_DIR));
+        fs::create_dir_all(&output_dir).expect("Could not create output directory");
+        output_dir
+    }
+
+    fn write_keys(ctx: &Context, phrase: &str, passphrase: &str) {
+        let output_dir = Self::output_dir(ctx);
+
+        let key_files = [
+            (
+                KeyPurpose::Consensus,
+                SecretKeyType::Consensus,
+                CONSENSUS_KEY_PASS_METHOD,
+                CONSENSUS_SECRET_KEY_NAME,
+            ),
+            (
+                KeyPurpose::Service,
+                SecretKeyType::Service,
+                SERVICE_KEY_PASS_METHOD,
+                SERVICE_SECRET_KEY_NAME,
+            ),
+        ];
+        for &(purpose, key_type, pass_method, file_name) in &key_files {
+            let path = output_dir.join(file_name);
+            if path.exists() {
+                panic!(
+                    "Failed to create secret key file. File exists: {}",
+                    path.to_string_lossy()
+                );
+            }
+
+            let (public_key, secret_key) =
+                mnemonic::keypair_from_mnemonic(phrase, passphrase, purpose)
+                    .expect("Invalid mnemonic phrase");
+            let key_passphrase = Self::pass_method(ctx, pass_method)
+                .unwrap_or_default()
+                .get_passphrase(key_type, false);
+            create_keys_file_with_options(
+                &path,
+                key_passphrase.as_bytes(),
+                public_key,
+                &secret_key,
+                &KeyFileOptions::new().with_purpose(purpose),
+            )
+            .expect("Could not write secret key file");
+            println!("{:?} public key: {}", purpose, hex::encode(&public_key));
+        }
+    }
+
+    fn generate(ctx: &Context) {
+        let phrase = ZeroizeOnDrop(mnemonic::generate_mnemonic());
+        let passphrase = Self::mnemonic_passphrase(ctx, false);
+        Self::write_keys(ctx, &phrase, &passphrase);
+
+        println!(
+            "Write down the recovery phrase and keep it in a safe place. \
+             It restores the keys with the `recover` action:\n\n{}\n",
+            *phrase
+        );
+    }
+
+    fn read_mnemonic(ctx: &Context) -> ZeroizeOnDrop<String> {
+        let phrase = Self::pass_method(ctx, MNEMONIC_METHOD)
+            .unwrap_or_default()
+            .get_passphrase(SecretKeyType::Mnemonic, true);
+        mnemonic::validate_mnemonic(&phrase).expect("Invalid mnemonic phrase");
+        phrase
+    }
+
+    fn recover(ctx: &Context) {
+        let phrase = Self::read_mnemonic(ctx);
+        let passphrase = Self::mnemonic_passphrase(ctx, true);
+        Self::write_keys(ctx, &phrase, &passphrase);
+    }
+
+    fn seed(ctx: &Context) {
+        let phrase = Self::read_mnemonic(ctx);
+        let passphrase = Self::mnemonic_passphrase(ctx, true);
+        let path = Self::output_dir(ctx).join(MASTER_SEED_NAME);
+        if path.exists() {
+            panic!(
+                "Failed to create master seed file. File exists: {}",
+                path.to_string_lossy()
+            );
+        }
+
+        let seed = ZeroizeOnDrop(
+            mnemonic::seed_from_mnemonic(&phrase, &passphrase).expect("Invalid mnemonic phrase"),
+        );
+        let seed_passphrase = Self::pass_method(ctx, CONSENSUS_KEY_PASS_METHOD)
+            .unwrap_or_default()
+            .get_passphrase(SecretKeyType::Consensus, false);
+        create_seed_file(&path, seed_passphrase.as_bytes(), &seed)
+            .expect("Could not write master seed file");
+
+        let master_key = ExtendedKey::master(&seed);
+        for &purpose in &[KeyPurpose::Consensus, KeyPurpose::Service] {
+            let derivation_path = DerivationPath::for_purpose(purpose, 0);
+            let public_key = master_key.derive_path(&derivation_path).keypair().0;
+            println!(
+                "{:?} public key at {}: {}",
+                purpose,
+                derivation_path,
+                hex::encode(&public_key)
+            );
+        }
+    }
+}
+
+impl Command for Keytool {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                KEYTOOL_ACTION,
+                true,
+                "Action to be performed: generate, recover or seed.",
+                "a",
+                "action",
+                false,
+            ),
+            Argument::new_named(
+                OUTPUT_DIR,
+                true,
+                "Path to the directory of the secret key files.",
+                "o",
+                "output-dir",
+                false,
+            ),
+            Argument::new_named(
+                MNEMONIC_METHOD,
+                false,
+                "Entry method of the recovery phrase for the `recover` and `seed` actions.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME} (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_MNEMONIC is used",
+                None,
+                "mnemonic",
+                false,
+            ),
+            Argument::new_named(
+                MNEMONIC_PASS_METHOD,
+                false,
+                "Passphrase entry method for the recovery phrase (default: no passphrase).\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_MNEMONIC_PASS is used",
+                None,
+                "mnemonic-pass",
+                false,
+            ),
+            Argument::new_named(
+                CONSENSUS_KEY_PASS_METHOD,
+                false,
+                "Passphrase entry method for consensus key.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_CONSENSUS_PASS is used",
+                None,
+                "consensus-key-pass",
+                false,
+            ),
+            Argument::new_named(
+                SERVICE_KEY_PASS_METHOD,
+                false,
+                "Passphrase entry method for service key.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_SERVICE_PASS is used",
+                None,
+                "service-key-pass",
+                false,
+            ),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "keytool"
+    }
+
+    fn about(&self) -> &str {
+        "Generates the node keys from a mnemonic phrase. Available actions: generate, recover, seed."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        context: Context,
+        _: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        let action = context
+            .arg::<String>(KEYTOOL_ACTION)
+            .unwrap_or_else(|_| panic!("{} not found.", KEYTOOL_ACTION));
+
+        match action.as_str() {
+            "generate" => Self::generate(&context),
+            "recover" => Self::recover(&context),
+            "seed" => Self::seed(&context),
+            _ => println!("Unsupported keytool action: {}", action),
+        }
+
+        Feedback::None
+    }
+}
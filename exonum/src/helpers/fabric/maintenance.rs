@@ -14,24 +14,46 @@
 
 //! This module implements node maintenance actions.
 
-use std::{collections::HashMap, path::{Path, PathBuf}, str::FromStr};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use super::{
+    details::Run,
     internal::{CollectedCommand, Command, Feedback},
-    Argument, CommandName, Context,
+    Argument, CommandName, Context, DatabaseFactories,
 };
-use crate::blockchain::Schema;
+use crate::blockchain::{archive, snapshot, Schema};
+use crate::helpers::Height;
 use crate::helpers::config::ConfigFile;
-use crate::node::NodeConfig;
-use exonum_merkledb::{Database, DbOptions, RocksDB};
+use crate::node::{DatabaseConfig, NodeConfig};
+use exonum_merkledb::Database;
 use crate::helpers::fabric::password::{PassInputMethod, SecretKeyType};
 
 // Context entry for the path to the node config.
 const NODE_CONFIG_PATH: &str = "NODE_CONFIG_PATH";
 // Context entry for the path to the database.
 const DATABASE_PATH: &str = "DATABASE_PATH";
+// Context entry for the passphrase entry method of the encrypted database.
+const DATABASE_PASS_METHOD: &str = "DATABASE_PASS_METHOD";
 // Context entry for the type of action to be performed.
 const MAINTENANCE_ACTION_PATH: &str = "MAINTENANCE_ACTION_PATH";
+// Context entry for the path to the state snapshot.
+const SNAPSHOT_PATH: &str = "SNAPSHOT_PATH";
+// Context entry for the height of the state snapshot.
+const SNAPSHOT_HEIGHT: &str = "SNAPSHOT_HEIGHT";
+// Context entry for the path to the block archive.
+const ARCHIVE_PATH: &str = "ARCHIVE_PATH";
+// Context entry for the height of the first exported block.
+const FROM_HEIGHT: &str = "FROM_HEIGHT";
+// Context entry for the directory of the state exported to JSON.
+const EXPORT_DIRECTORY: &str = "EXPORT_DIRECTORY";
+// Context entry for the services whose state is exported to JSON.
+const EXPORT_SERVICES: &str = "EXPORT_SERVICES";
 
 const CONSENSUS_KEY_PASS_METHOD: &str = "CONSENSUS_KEY_PASS_METHOD";
 
@@ -40,10 +62,22 @@ const SERVICE_KEY_PASS_METHOD: &str = "SERVICE_KEY_PASS_METHOD";
 /// Maintenance command. Supported actions:
 ///
 /// - `clear-cache` - clear message cache.
-#[derive(Debug)]
-pub struct Maintenance;
+/// - `export-snapshot` - export the state snapshot at the latest height.
+/// - `export-json` - export the state of the selected services at the latest height
+///   to JSON files.
+/// - `export-blocks` - export the committed blocks with their transactions and precommits
+///   to the block archive.
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    database_factories: DatabaseFactories,
+}
 
 impl Maintenance {
+    /// Creates the maintenance command opening databases with the given factories.
+    pub fn new(database_factories: DatabaseFactories) -> Self {
+        Self { database_factories }
+    }
+
     fn node_config(ctx: &Context) -> NodeConfig {
         let path = ctx
             .arg::<String>(NODE_CONFIG_PATH)
@@ -77,18 +111,15 @@ impl Maintenance {
         )
     }
 
-    fn database(ctx: &Context, options: &DbOptions) -> Box<dyn Database> {
-        let path = ctx
-            .arg::<String>(DATABASE_PATH)
-            .unwrap_or_else(|_| panic!("{} not found.", DATABASE_PATH));
-        Box::new(RocksDB::open(Path::new(&path), options).expect("Can't load database file"))
+    fn database(&self, ctx: &Context, config: &DatabaseConfig) -> Box<dyn Database> {
+        Run::db_helper(ctx, config, &self.database_factories)
     }
 
-    fn clear_cache(context: &Context) {
+    fn clear_cache(&self, context: &Context) {
         info!("Clearing node cache");
 
         let config = Self::node_config(context);
-        let db = Self::database(context, &config.database);
+        let db = self.database(context, &config.database);
         let fork = db.fork();
         let schema = Schema::new(&fork);
         schema.consensus_messages_cache().clear();
@@ -98,6 +129,87 @@ impl Maintenance {
 
         info!("Cache cleared successfully");
     }
+
+    fn export_snapshot(&self, context: &Context) {
+        let path = context
+            .arg::<String>(SNAPSHOT_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", SNAPSHOT_PATH));
+        info!("Exporting state snapshot to {}", path);
+
+        let config = Self::node_config(context);
+        let db = self.database(context, &config.database);
+        let snapshot = db.snapshot();
+        let height = context
+            .arg::<u64>(SNAPSHOT_HEIGHT)
+            .map(Height)
+            .unwrap_or_else(|_| Schema::new(&snapshot).height());
+
+        let mut writer = BufWriter::new(File::create(&path).expect("Can't create snapshot file"));
+        let header = snapshot::export_snapshot(&*snapshot, height, &mut writer)
+            .expect("Can't export state snapshot");
+
+        info!(
+            "State snapshot exported at height {}, block hash {:?}",
+            header.height, header.block_hash
+        );
+    }
+
+    fn export_blocks(&self, context: &Context) {
+        let path = context
+            .arg::<String>(ARCHIVE_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", ARCHIVE_PATH));
+        info!("Exporting blocks to {}", path);
+
+        let config = Self::node_config(context);
+        let db = self.database(context, &config.database);
+        let snapshot = db.snapshot();
+        let schema = Schema::new(&snapshot);
+        let from = context
+            .arg::<u64>(FROM_HEIGHT)
+            .map(Height)
+            .unwrap_or_else(|_| schema.first_unpruned_height());
+        let to = context
+            .arg::<u64>(SNAPSHOT_HEIGHT)
+            .map(Height)
+            .unwrap_or_else(|_| schema.height());
+
+        let mut writer = BufWriter::new(File::create(&path).expect("Can't create archive file"));
+        let info = archive::export_blocks(&*snapshot, from, to, &mut writer)
+            .expect("Can't export blocks");
+
+        info!("Exported blocks at heights {}..={}", info.from, info.to);
+    }
+
+    fn export_json(&self, context: &Context) {
+        let directory = context
+            .arg::<String>(EXPORT_DIRECTORY)
+            .unwrap_or_else(|_| panic!("{} not found.", EXPORT_DIRECTORY));
+        let services = context.arg::<String>(EXPORT_SERVICES).unwrap_or_default();
+        let services = services
+            .split(',')
+            .map(str::trim)
+            .filter(|service| !service.is_empty())
+            .collect::<Vec<_>>();
+        info!("Exporting state to JSON files in {}", directory);
+
+        let config = Self::node_config(context);
+        let db = self.database(context, &config.database);
+        let snapshot = db.snapshot();
+        let height = context
+            .arg::<u64>(SNAPSHOT_HEIGHT)
+            .map(Height)
+            .unwrap_or_else(|_| Schema::new(&snapshot).height());
+
+        let manifest = snapshot::export_json(&*snapshot, height, &services, Path::new(&directory))
+            .expect("Can't export state to JSON");
+
+        info!(
+            "Exported {} indexes at height {}, state hash {:?}",
+            manifest.indexes.len(),
+            manifest.height,
+            manifest.state_hash
+        );
+    }
 }
 
 impl Command for Maintenance {
@@ -127,6 +239,57 @@ impl Command for Maintenance {
                 "action",
                 false,
             ),
+            Argument::new_named(
+                SNAPSHOT_PATH,
+                false,
+                "Path to the state snapshot file, required for the `export-snapshot` action.",
+                None,
+                "snapshot-path",
+                false,
+            ),
+            Argument::new_named(
+                SNAPSHOT_HEIGHT,
+                false,
+                "Height of the exported state snapshot or of the last exported block \
+                 (default: the latest height).",
+                None,
+                "height",
+                false,
+            ),
+            Argument::new_named(
+                ARCHIVE_PATH,
+                false,
+                "Path to the block archive file, required for the `export-blocks` action.",
+                None,
+                "archive-path",
+                false,
+            ),
+            Argument::new_named(
+                FROM_HEIGHT,
+                false,
+                "Height of the first block exported by the `export-blocks` action \
+                 (default: the earliest height not removed by pruning).",
+                None,
+                "from-height",
+                false,
+            ),
+            Argument::new_named(
+                EXPORT_DIRECTORY,
+                false,
+                "Directory of the JSON files, required for the `export-json` action.",
+                None,
+                "export-dir",
+                false,
+            ),
+            Argument::new_named(
+                EXPORT_SERVICES,
+                false,
+                "Comma-separated names of the services exported by the `export-json` action, \
+                 `core` stands for the core indexes (default: all indexes).",
+                None,
+                "services",
+                false,
+            ),
             Argument::new_named(
                 CONSENSUS_KEY_PASS_METHOD,
                 false,
@@ -147,6 +310,16 @@ impl Command for Maintenance {
                 "service-key-pass",
                 false,
             ),
+            Argument::new_named(
+                DATABASE_PASS_METHOD,
+                false,
+                "Passphrase entry method for the encrypted database.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_DATABASE_PASS is used",
+                None,
+                "db-pass",
+                false,
+            ),
         ]
     }
 
@@ -155,7 +328,8 @@ impl Command for Maintenance {
     }
 
     fn about(&self) -> &str {
-        "Maintenance module. Available actions: clear-cache."
+        "Maintenance module. Available actions: clear-cache, export-snapshot, export-json, \
+         export-blocks."
     }
 
     fn execute(
@@ -169,7 +343,13 @@ impl Command for Maintenance {
             .unwrap_or_else(|_| panic!("{} not found.", MAINTENANCE_ACTION_PATH));
 
         if action == "clear-cache" {
-            Self::clear_cache(&context);
+            self.clear_cache(&context);
+        } else if action == "export-snapshot" {
+            self.export_snapshot(&context);
+        } else if action == "export-json" {
+            self.export_json(&context);
+        } else if action == "export-blocks" {
+            self.export_blocks(&context);
         } else {
             println!("Unsupported maintenance action: {}", action);
         }
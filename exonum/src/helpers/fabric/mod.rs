@@ -17,10 +17,15 @@
 pub use self::{
     builder::NodeBuilder,
     context_key::ContextKey,
+    database::{
+        DatabaseFactories, DatabaseFactory, ROCKSDB_DATABASE_TYPE, TEMPORARY_DATABASE_TYPE,
+    },
     details::{Finalize, GenerateCommonConfig, GenerateNodeConfig, Run, RunDev},
     internal::Command,
+    keytool::Keytool,
     maintenance::Maintenance,
     shared::{AbstractConfig, CommonConfigTemplate, NodePrivateConfig, NodePublicConfig},
+    tx::SignTransaction,
 };
 
 use serde::{Deserialize, Serialize};
@@ -32,11 +37,14 @@ use crate::blockchain::Service;
 
 mod builder;
 mod clap_backend;
+mod database;
 mod details;
 mod info;
 mod internal;
+mod keytool;
 mod maintenance;
 mod shared;
+mod tx;
 #[macro_use]
 mod context_key;
 mod password;
@@ -37,6 +37,9 @@ pub enum PassInputMethod {
 pub enum SecretKeyType {
     Consensus,
     Service,
+    Database,
+    Mnemonic,
+    MnemonicPassphrase,
 }
 
 impl PassInputMethod {
@@ -49,6 +52,9 @@ impl PassInputMethod {
                 let prompt = match key_type {
                     SecretKeyType::Consensus => "Enter consensus key passphrase",
                     SecretKeyType::Service => "Enter service key passphrase",
+                    SecretKeyType::Database => "Enter database encryption passphrase",
+                    SecretKeyType::Mnemonic => "Enter recovery phrase",
+                    SecretKeyType::MnemonicPassphrase => "Enter recovery phrase passphrase",
                 };
                 prompt_passphrase(prompt, node_run).expect("Failed to read password from stdin")
             }
@@ -59,6 +65,9 @@ impl PassInputMethod {
                     match key_type {
                         SecretKeyType::Consensus => "EXONUM_CONSENSUS_PASS",
                         SecretKeyType::Service => "EXONUM_SERVICE_PASS",
+                        SecretKeyType::Database => "EXONUM_DATABASE_PASS",
+                        SecretKeyType::Mnemonic => "EXONUM_MNEMONIC",
+                        SecretKeyType::MnemonicPassphrase => "EXONUM_MNEMONIC_PASS",
                     }
                 };
                 ZeroizeOnDrop(env::var(var).unwrap_or_else(|e| {
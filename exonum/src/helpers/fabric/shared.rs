@@ -16,8 +16,8 @@
 
 use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf};
 
-use crate::blockchain::config::{ConsensusConfig, ValidatorKeys};
-use crate::crypto::PublicKey;
+use crate::blockchain::config::{is_false, is_sha256, ConsensusConfig, ValidatorKeys};
+use crate::crypto::{HashAlgorithm, PublicKey};
 
 /// Abstract configuration.
 pub type AbstractConfig = BTreeMap<String, toml::Value>;
@@ -58,6 +58,12 @@ impl NodePublicConfig {
 /// Base config.
 #[derive(PartialEq, Clone, Debug, Serialize, Deserialize, Default)]
 pub struct CommonConfigTemplate {
+    /// Hash function used by the blockchain.
+    #[serde(default, skip_serializing_if = "is_sha256")]
+    pub hash_algorithm: HashAlgorithm,
+    /// Whether the signatures of the messages cover the identifier of the network.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub domain_separated_signatures: bool,
     /// Consensus configuration.
     pub consensus_config: ConsensusConfig,
     /// Services configuration.
@@ -0,0 +1,205 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements the offline signing of transactions.
+
+use failure::Error;
+use hex::FromHex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use std::{collections::HashMap, fs};
+
+use super::{
+    internal::{CollectedCommand, Command, Feedback},
+    keys,
+    password::{PassInputMethod, SecretKeyType},
+    Argument, CommandName, Context,
+};
+use crate::blockchain::ServiceDescriptor;
+use crate::crypto::{read_keys_from_file, Hash};
+use crate::explorer::TransactionDecoder;
+use crate::helpers::config::ConfigFile;
+use crate::messages::{to_hex_string, Message};
+
+// Context entry for the path to the JSON file with the transaction.
+const PAYLOAD_PATH: &str = "PAYLOAD_PATH";
+// Context entry for the path to the secret key file.
+const SECRET_KEY_PATH: &str = "SECRET_KEY_PATH";
+// Context entry for the passphrase entry method of the secret key.
+const SERVICE_KEY_PASS_METHOD: &str = "SERVICE_KEY_PASS_METHOD";
+// Context entry for the path to the node configuration.
+const NODE_CONFIG_PATH: &str = "NODE_CONFIG_PATH";
+// Context entry for the path to the output file.
+const OUTPUT_PATH: &str = "OUTPUT_PATH";
+// Context entry for the hex identifier of the network the transaction is signed for.
+const NETWORK_ID: &str = "NETWORK_ID";
+
+/// Unsigned transaction in the same format as `DecodedTransaction`, without the author.
+#[derive(Debug, Deserialize)]
+struct TransactionTemplate {
+    service_id: u16,
+    message_id: u16,
+    payload: Value,
+}
+
+/// Command that builds a transaction of any service of the node from JSON and signs it
+/// without connecting to the network, so that it can be prepared on an air-gapped machine
+/// and submitted later. The transaction is printed (or written to the output file) as
+/// a hex string that is accepted by the transaction endpoints of the explorer.
+///
+/// The JSON file contains the `service_id`, `message_id` and `payload` of the transaction,
+/// with the payload in the same format as the explorer returns for the decoded
+/// transactions. The payload is encoded with the protobuf descriptors of the services,
+/// see `Service::protobuf_descriptor`; if a service has no descriptor, the payload must
+/// be a hex string with the serialized message.
+///
+/// The transaction is signed with the key pair from a key file, for example the service
+/// key of the node. Services which need the node configuration to be created, such as
+/// the configuration service, require the `--node-config` argument. The transactions
+/// for the networks with domain separated signatures are signed with the `--network-id`
+/// argument, see `Schema::network_id`.
+#[derive(Debug, Default)]
+pub struct SignTransaction;
+
+impl SignTransaction {
+    /// Builds and signs the transaction with the descriptors of the services.
+    pub(crate) fn sign(
+        ctx: &Context,
+        descriptors: HashMap<u16, ServiceDescriptor>,
+    ) -> Result<String, Error> {
+        let path = ctx
+            .arg::<String>(PAYLOAD_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", PAYLOAD_PATH));
+        let template: TransactionTemplate = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let decoder = TransactionDecoder::with_descriptors(descriptors)?;
+        let transaction =
+            decoder.encode(template.service_id, template.message_id, &template.payload)?;
+
+        let key_path = ctx
+            .arg::<String>(SECRET_KEY_PATH)
+            .unwrap_or_else(|_| panic!("{} not found.", SECRET_KEY_PATH));
+        let pass_method: PassInputMethod = ctx
+            .arg::<String>(SERVICE_KEY_PASS_METHOD)
+            .unwrap_or_default()
+            .parse()
+            .expect("Incorrect passphrase input method");
+        let passphrase = pass_method.get_passphrase(SecretKeyType::Service, true);
+        let (public_key, secret_key) = read_keys_from_file(&key_path, passphrase.as_bytes())?;
+
+        let network_id = match ctx.arg::<String>(NETWORK_ID) {
+            Ok(network_id) => Some(Hash::from_hex(network_id)?),
+            Err(_) => None,
+        };
+
+        let signed = Message::sign_transaction_in_network(
+            transaction,
+            template.service_id,
+            network_id,
+            public_key,
+            &secret_key,
+        );
+        Ok(to_hex_string(&signed))
+    }
+
+    /// Outputs the signed transaction.
+    pub(crate) fn output(ctx: &Context, transaction: &str) {
+        match ctx.arg::<String>(OUTPUT_PATH) {
+            Ok(path) => fs::write(path, transaction).expect("Could not write transaction file"),
+            Err(_) => println!("{}", transaction),
+        }
+    }
+}
+
+impl Command for SignTransaction {
+    fn args(&self) -> Vec<Argument> {
+        vec![
+            Argument::new_named(
+                PAYLOAD_PATH,
+                true,
+                "Path to the JSON file with the service_id, message_id and payload \
+                 of the transaction.",
+                "p",
+                "payload",
+                false,
+            ),
+            Argument::new_named(
+                SECRET_KEY_PATH,
+                true,
+                "Path to the key file to sign the transaction with.",
+                "k",
+                "secret-key",
+                false,
+            ),
+            Argument::new_named(
+                SERVICE_KEY_PASS_METHOD,
+                false,
+                "Passphrase entry method for the key file.\n\
+                 Possible values are: stdin, env{:ENV_VAR_NAME}, pass:PASSWORD (default: stdin)\n\
+                 If ENV_VAR_NAME is not specified $EXONUM_SERVICE_PASS is used",
+                None,
+                "service-key-pass",
+                false,
+            ),
+            Argument::new_named(
+                NODE_CONFIG_PATH,
+                false,
+                "Path to the node configuration, required by some services.",
+                "c",
+                "node-config",
+                false,
+            ),
+            Argument::new_named(
+                OUTPUT_PATH,
+                false,
+                "Path to write the signed transaction to instead of printing it.",
+                "o",
+                "output",
+                false,
+            ),
+            Argument::new_named(
+                NETWORK_ID,
+                false,
+                "Hex identifier of the network with domain separated signatures.",
+                None,
+                "network-id",
+                false,
+            ),
+        ]
+    }
+
+    fn name(&self) -> CommandName {
+        "tx"
+    }
+
+    fn about(&self) -> &str {
+        "Builds and signs a transaction offline."
+    }
+
+    fn execute(
+        &self,
+        _commands: &HashMap<CommandName, CollectedCommand>,
+        mut context: Context,
+        exts: &dyn Fn(Context) -> Context,
+    ) -> Feedback {
+        if let Ok(path) = context.arg::<String>(NODE_CONFIG_PATH) {
+            let config = ConfigFile::load(&path).expect("Can't load node config file");
+            context.set(keys::NODE_CONFIG, config);
+            context.set(keys::NODE_CONFIG_PATH, path);
+        }
+        // The transaction is signed by the `NodeBuilder`, which has the services.
+        Feedback::SignTransaction(exts(context))
+    }
+}
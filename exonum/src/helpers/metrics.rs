@@ -16,6 +16,12 @@
 
 use chrono::offset::Utc;
 
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, RwLock},
+};
+
 /// Adds given metric with given value.
 ///
 /// Metric name should be in the following format: `module_name.metric_name`, where `module_name`
@@ -54,3 +60,184 @@ pub fn add_metric(metric_name: &str, value: i64) {
         trace!("{} {} {}", metric_name, value, time);
     }
 }
+
+/// Description of a metric in the `MetricsRegistry`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricDesc {
+    /// Name of the metric, e.g., `exonum_consensus_proposes_sent_total`.
+    pub name: &'static str,
+    /// Human-readable description of the metric.
+    pub help: &'static str,
+}
+
+/// Value of a metric in the `MetricsRegistry`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum MetricValue {
+    /// Number of the events since the node start.
+    Counter {
+        /// Current value.
+        value: u64,
+    },
+    /// Value which may go up and down.
+    Gauge {
+        /// Current value.
+        value: i64,
+    },
+    /// Number and sum of the observed values, e.g., durations.
+    Summary {
+        /// Number of the observations.
+        count: u64,
+        /// Sum of the observed values.
+        sum: u64,
+    },
+}
+
+impl MetricValue {
+    fn type_name(&self) -> &'static str {
+        match *self {
+            MetricValue::Counter { .. } => "counter",
+            MetricValue::Gauge { .. } => "gauge",
+            MetricValue::Summary { .. } => "summary",
+        }
+    }
+}
+
+/// Registry of the metrics of the node exposed via the system API in
+/// the [Prometheus text format][format].
+///
+/// Clones of the registry share the same metrics.
+///
+/// [format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+#[derive(Debug, Clone, Default)]
+pub struct MetricsRegistry {
+    metrics: Arc<RwLock<BTreeMap<&'static str, (MetricDesc, MetricValue)>>>,
+}
+
+impl MetricsRegistry {
+    /// Increments the counter.
+    pub fn increment(&self, desc: &MetricDesc) {
+        self.update(desc, MetricValue::Counter { value: 0 }, |metric| {
+            if let MetricValue::Counter { ref mut value } = *metric {
+                *value += 1;
+            }
+        });
+    }
+
+    /// Sets the value of the gauge.
+    pub fn set(&self, desc: &MetricDesc, new_value: i64) {
+        self.update(desc, MetricValue::Gauge { value: 0 }, |metric| {
+            if let MetricValue::Gauge { ref mut value } = *metric {
+                *value = new_value;
+            }
+        });
+    }
+
+    /// Adds the observed value to the summary.
+    pub fn observe(&self, desc: &MetricDesc, value: u64) {
+        self.update(desc, MetricValue::Summary { count: 0, sum: 0 }, |metric| {
+            if let MetricValue::Summary {
+                ref mut count,
+                ref mut sum,
+            } = *metric
+            {
+                *count += 1;
+                *sum += value;
+            }
+        });
+    }
+
+    /// Returns the current value of the metric with the given name.
+    pub fn value(&self, name: &str) -> Option<MetricValue> {
+        let metrics = self.metrics.read().expect("Expected read lock");
+        metrics.get(name).map(|&(_, value)| value)
+    }
+
+    /// Returns the current values of all metrics ordered by their names.
+    pub fn values(&self) -> BTreeMap<String, MetricValue> {
+        let metrics = self.metrics.read().expect("Expected read lock");
+        metrics
+            .iter()
+            .map(|(&name, &(_, value))| (name.to_owned(), value))
+            .collect()
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.read().expect("Expected read lock");
+        let mut output = String::new();
+        for &(desc, value) in metrics.values() {
+            writeln!(output, "# HELP {} {}", desc.name, desc.help).unwrap();
+            writeln!(output, "# TYPE {} {}", desc.name, value.type_name()).unwrap();
+            match value {
+                MetricValue::Counter { value } => {
+                    writeln!(output, "{} {}", desc.name, value).unwrap();
+                }
+                MetricValue::Gauge { value } => {
+                    writeln!(output, "{} {}", desc.name, value).unwrap();
+                }
+                MetricValue::Summary { count, sum } => {
+                    writeln!(output, "{}_sum {}", desc.name, sum).unwrap();
+                    writeln!(output, "{}_count {}", desc.name, count).unwrap();
+                }
+            }
+        }
+        output
+    }
+
+    fn update<F>(&self, desc: &MetricDesc, initial: MetricValue, update: F)
+    where
+        F: FnOnce(&mut MetricValue),
+    {
+        let mut metrics = self.metrics.write().expect("Expected write lock");
+        let entry = metrics.entry(desc.name).or_insert((*desc, initial));
+        update(&mut entry.1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROPOSES: MetricDesc = MetricDesc {
+        name: "test_proposes_total",
+        help: "Number of the proposes.",
+    };
+    const HEIGHT: MetricDesc = MetricDesc {
+        name: "test_height",
+        help: "Current height.",
+    };
+    const BLOCK_INTERVAL: MetricDesc = MetricDesc {
+        name: "test_block_interval_milliseconds",
+        help: "Time between blocks.",
+    };
+
+    #[test]
+    fn render_metrics() {
+        let registry = MetricsRegistry::default();
+        registry.increment(&PROPOSES);
+        registry.clone().increment(&PROPOSES);
+        registry.set(&HEIGHT, 5);
+        registry.set(&HEIGHT, 7);
+        registry.observe(&BLOCK_INTERVAL, 1000);
+        registry.observe(&BLOCK_INTERVAL, 1500);
+
+        assert_eq!(
+            registry.value(PROPOSES.name),
+            Some(MetricValue::Counter { value: 2 })
+        );
+        assert_eq!(
+            registry.render(),
+            "# HELP test_block_interval_milliseconds Time between blocks.\n\
+             # TYPE test_block_interval_milliseconds summary\n\
+             test_block_interval_milliseconds_sum 2500\n\
+             test_block_interval_milliseconds_count 2\n\
+             # HELP test_height Current height.\n\
+             # TYPE test_height gauge\n\
+             test_height 7\n\
+             # HELP test_proposes_total Number of the proposes.\n\
+             # TYPE test_proposes_total counter\n\
+             test_proposes_total 2\n"
+        );
+    }
+}
@@ -19,7 +19,7 @@ use std::{fmt, num::ParseIntError, ops::Deref, ops::DerefMut, str::FromStr};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use zeroize::Zeroize;
 
-use crate::crypto::{CryptoHash, Hash};
+use crate::crypto::{constant_time_eq, CryptoHash, Hash};
 use exonum_merkledb::{impl_object_hash_for_binary_value, BinaryValue, ObjectHash};
 use std::borrow::Cow;
 
@@ -379,9 +379,19 @@ impl Iterator for RoundRangeIter {
 }
 
 /// Struct used to call zeroize on inner type on drop.
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+///
+/// The wrapped values are compared in constant time, see `crypto::constant_time_eq`.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ZeroizeOnDrop<T: Zeroize>(pub T);
 
+impl<T: Zeroize + AsRef<[u8]>> PartialEq for ZeroizeOnDrop<T> {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.0.as_ref(), other.0.as_ref())
+    }
+}
+
+impl<T: Zeroize + AsRef<[u8]>> Eq for ZeroizeOnDrop<T> {}
+
 impl<T: Zeroize> Drop for ZeroizeOnDrop<T> {
     fn drop(&mut self) {
         self.0.zeroize()
@@ -85,6 +85,8 @@ pub mod api;
 pub mod events;
 pub mod explorer;
 pub mod node;
+#[cfg(feature = "simulation")]
+pub mod simulation;
 
 //TODO: revert sandbox
 #[cfg(test)]
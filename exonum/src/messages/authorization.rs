@@ -1,12 +1,53 @@
 use failure::Error;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use hex::{FromHex, ToHex};
 
-use std::fmt;
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{Read, Write},
+};
 
-use super::EMPTY_SIGNED_MESSAGE_SIZE;
+use super::{
+    Connect, ProtocolMessage, RawTransaction, EMPTY_SIGNED_MESSAGE_SIZE, MESSAGE_FORMAT_VERSION,
+};
 use crate::crypto::{
-    self, hash, Hash, PublicKey, SecretKey, Signature, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
+    self, hash, Hash, PublicKey, SecretKey, SignStream, Signature, SignatureScheme,
+    PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
 };
+use crate::node::Signer;
+
+/// Bit of the message class set if the message is signed with a secp256k1 key.
+const SECP256K1_CLASS_FLAG: u8 = 0x80;
+/// Bit of the message class set if the payload of the message is compressed.
+const COMPRESSED_CLASS_FLAG: u8 = 0x40;
+/// Bit of the message class set if the message is signed in the prehashed ed25519 mode.
+const PREHASHED_CLASS_FLAG: u8 = 0x20;
+/// Bits of the message class storing the version of the message format.
+const VERSION_CLASS_MASK: u8 = 0x18;
+/// Offset of the version of the message format in the message class.
+const VERSION_CLASS_SHIFT: u8 = 3;
+
+/// Returns the data covered by the signature of a message: the message without
+/// the signature, prepended with the network identifier if it is specified.
+///
+/// The identifier is prepended to the signed data, but it is not included in the messages
+/// themselves, so a message signed for one network is rejected by the nodes of any other
+/// network. The identifier is the hash of the genesis configuration of the blockchain,
+/// see `Schema::network_id`, and it is used only by the networks which have enabled
+/// domain separation of the signatures with
+/// `StoredConfiguration::domain_separated_signatures`.
+pub(crate) fn signed_data(network_id: Option<Hash>, data: &[u8]) -> Cow<[u8]> {
+    match network_id {
+        Some(network_id) => {
+            let mut buffer = Vec::with_capacity(network_id.as_ref().len() + data.len());
+            buffer.extend_from_slice(network_id.as_ref());
+            buffer.extend_from_slice(data);
+            Cow::Owned(buffer)
+        }
+        None => Cow::Borrowed(data),
+    }
+}
 
 /// `SignedMessage` can be constructed from a raw byte buffer which must have the following
 /// data layout:
@@ -19,6 +60,28 @@ use crate::crypto::{
 /// | `34..N`   | payload                 |
 /// | `N..N+64` | signature               |
 ///
+/// The highest bit of the message class is set if the message is signed with a secp256k1
+/// key rather than the ed25519 key of the author, see the `crypto::secp256k1` module.
+/// Only transactions can be signed with secp256k1 keys.
+///
+/// The second highest bit of the message class is set if the payload is compressed
+/// with DEFLATE. The signature covers the compressed payload, and the payload is
+/// decompressed when the message is deserialized. Only transactions can be compressed.
+///
+/// The third highest bit of the message class is set if the message is signed with
+/// the ed25519 key of the author in the prehashed mode (ed25519ph), which allows to sign
+/// large messages incrementally. Only transactions can be signed in the prehashed mode.
+///
+/// The fourth and fifth highest bits of the message class store the version of the message
+/// format, see `MESSAGE_FORMAT_VERSION`. The messages of newer versions are rejected.
+///
+/// The signatures of the messages of the networks with domain separation also cover
+/// the network identifier, see `Schema::network_id`. The identifier is passed explicitly
+/// to the methods signing and verifying the messages; `None` means that the network does
+/// not use domain separation. `Connect` messages never cover the identifier, since they
+/// are verified by the network layer, which does not track the configuration
+/// of the blockchain; the connections are authenticated by the handshake anyway.
+///
 /// `SignedMessage` will verify the size of the buffer and the signature provided in it.
 /// This allows to keep the raw message buffer, but avoid verifying its signature again
 /// as every `SignedMessage` instance is guaranteed to have a correct signature.
@@ -28,20 +91,118 @@ pub struct SignedMessage {
 }
 
 impl SignedMessage {
-    /// Creates `SignedMessage` from parts.
+    /// Creates `SignedMessage` from parts signed for the network with the given identifier.
     pub(crate) fn new(
         class: u8,
         tag: u8,
         value: &[u8],
+        network_id: Option<Hash>,
         author: PublicKey,
         secret_key: &SecretKey,
     ) -> SignedMessage {
+        let network_id = covered_network_id(class, tag, network_id);
+        let mut buffer = Vec::with_capacity(2 + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH);
+        buffer.extend_from_slice(author.as_ref());
+        buffer.push(versioned_class(class));
+        buffer.push(tag);
+        buffer.extend_from_slice(value);
+        let signature = Self::sign(&signed_data(network_id, &buffer), secret_key)
+            .expect("Couldn't form signature");
+        buffer.extend_from_slice(signature.as_ref());
+        SignedMessage { raw: buffer }
+    }
+
+    /// Creates `SignedMessage` from parts with the payload compressed with DEFLATE.
+    pub(crate) fn new_compressed(
+        class: u8,
+        tag: u8,
+        value: &[u8],
+        network_id: Option<Hash>,
+        author: PublicKey,
+        secret_key: &SecretKey,
+    ) -> SignedMessage {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(value).expect("Couldn't compress payload");
+        let compressed = encoder.finish().expect("Couldn't compress payload");
+        Self::new(
+            class | COMPRESSED_CLASS_FLAG,
+            tag,
+            &compressed,
+            network_id,
+            author,
+            secret_key,
+        )
+    }
+
+    /// Creates `SignedMessage` from parts signed in the prehashed ed25519 mode.
+    /// The message is fed to the signature in chunks, so it is hashed only once
+    /// and is not copied before signing.
+    pub(crate) fn new_prehashed(
+        class: u8,
+        tag: u8,
+        value: &[u8],
+        network_id: Option<Hash>,
+        author: PublicKey,
+        secret_key: &SecretKey,
+    ) -> SignedMessage {
+        let network_id = covered_network_id(class, tag, network_id);
+        let class = versioned_class(class | PREHASHED_CLASS_FLAG);
+        let mut stream = SignStream::new();
+        if let Some(network_id) = network_id {
+            stream = stream.update(network_id.as_ref());
+        }
+        let signature = stream
+            .update(author.as_ref())
+            .update(&[class, tag])
+            .update(value)
+            .sign(secret_key);
         let mut buffer = Vec::with_capacity(2 + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH);
         buffer.extend_from_slice(author.as_ref());
         buffer.push(class);
         buffer.push(tag);
         buffer.extend_from_slice(value);
-        let signature = Self::sign(&buffer, secret_key).expect("Couldn't form signature");
+        buffer.extend_from_slice(signature.as_ref());
+        SignedMessage { raw: buffer }
+    }
+
+    /// Creates `SignedMessage` from parts signed by the signer. The author of the message
+    /// is the public key of the signer.
+    pub(crate) fn new_with_signer(
+        class: u8,
+        tag: u8,
+        value: &[u8],
+        network_id: Option<Hash>,
+        signer: &dyn Signer,
+    ) -> Result<SignedMessage, Error> {
+        let network_id = covered_network_id(class, tag, network_id);
+        let mut buffer = Vec::with_capacity(2 + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH);
+        buffer.extend_from_slice(signer.public_key().as_ref());
+        buffer.push(versioned_class(class));
+        buffer.push(tag);
+        buffer.extend_from_slice(value);
+        let signature = signer.sign(&signed_data(network_id, &buffer))?;
+        buffer.extend_from_slice(signature.as_ref());
+        Ok(SignedMessage { raw: buffer })
+    }
+
+    /// Creates `SignedMessage` from parts signed with the secp256k1 key. The author
+    /// of the message is derived from the public key.
+    pub(crate) fn new_secp256k1(
+        class: u8,
+        tag: u8,
+        value: &[u8],
+        network_id: Option<Hash>,
+        secret_key: &crypto::secp256k1::SecretKey,
+    ) -> SignedMessage {
+        let network_id = covered_network_id(class, tag, network_id);
+        let public_key = crypto::secp256k1::public_key(secret_key);
+        let author = crypto::secp256k1::author_key(&public_key);
+        let mut buffer = Vec::with_capacity(2 + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH);
+        buffer.extend_from_slice(author.as_ref());
+        buffer.push(versioned_class(class | SECP256K1_CLASS_FLAG));
+        buffer.push(tag);
+        buffer.extend_from_slice(value);
+        let signature = crypto::secp256k1::sign(&signed_data(network_id, &buffer), secret_key);
         buffer.extend_from_slice(signature.as_ref());
         SignedMessage { raw: buffer }
     }
@@ -57,7 +218,7 @@ impl SignedMessage {
     ) -> SignedMessage {
         let mut buffer = Vec::with_capacity(2 + value.len() + PUBLIC_KEY_LENGTH + SIGNATURE_LENGTH);
         buffer.extend_from_slice(author.as_ref());
-        buffer.push(class);
+        buffer.push(versioned_class(class));
         buffer.push(tag);
         buffer.extend_from_slice(value);
         buffer.extend_from_slice(signature.as_ref());
@@ -65,20 +226,85 @@ impl SignedMessage {
     }
 
     /// Creates `SignedMessage` wrapper from the raw buffer.
-    /// Checks binary format and signature.
+    /// Checks binary format and signature, which must not cover the network identifier.
     pub fn from_raw_buffer(buffer: Vec<u8>) -> Result<Self, Error> {
+        Self::from_raw_buffer_in_network(buffer, None)
+    }
+
+    /// Creates `SignedMessage` wrapper from the raw buffer of a message signed
+    /// for the network with the given identifier.
+    /// Checks binary format and signature.
+    pub fn from_raw_buffer_in_network(
+        buffer: Vec<u8>,
+        network_id: Option<Hash>,
+    ) -> Result<Self, Error> {
+        let signed = Self::from_raw_buffer_unverified(buffer)?;
+        signed.verify_signature(network_id)?;
+        Ok(signed)
+    }
+
+    /// Creates `SignedMessage` from the raw buffer, checking its size and header,
+    /// but not its signature, which should be checked with `verify_signature`.
+    pub(crate) fn from_raw_buffer_unverified(buffer: Vec<u8>) -> Result<Self, Error> {
         ensure!(
             buffer.len() > EMPTY_SIGNED_MESSAGE_SIZE,
             "Message too short message_len = {}",
             buffer.len()
         );
         let signed = SignedMessage { raw: buffer };
-        let pk = signed.author();
-        let signature = signed.signature();
+        signed.check_flags()?;
+        Ok(signed)
+    }
 
-        Self::verify(signed.data_without_signature(), &signature, &pk)?;
+    /// Verifies the signature of the message signed for the network with the given
+    /// identifier with the public key of its author.
+    pub(crate) fn verify_signature(&self, network_id: Option<Hash>) -> Result<(), Error> {
+        Self::verify(
+            self.signature_scheme(),
+            &signed_data(
+                self.covered_network_id(network_id),
+                self.data_without_signature(),
+            ),
+            &self.signature(),
+            &self.author(),
+        )
+    }
 
-        Ok(signed)
+    /// Creates `SignedMessage` wrappers from the raw buffers.
+    /// Checks binary format of each buffer and verifies all the signatures, failing
+    /// if any of the buffers is invalid.
+    pub fn from_raw_buffers(
+        buffers: Vec<Vec<u8>>,
+        network_id: Option<Hash>,
+    ) -> Result<Vec<Self>, Error> {
+        for buffer in &buffers {
+            ensure!(
+                buffer.len() > EMPTY_SIGNED_MESSAGE_SIZE,
+                "Message too short message_len = {}",
+                buffer.len()
+            );
+        }
+        let messages = buffers
+            .into_iter()
+            .map(|raw| SignedMessage { raw })
+            .collect::<Vec<_>>();
+        for message in &messages {
+            message.check_flags()?;
+        }
+        Self::verify_signatures(&messages.iter().collect::<Vec<_>>(), network_id)?;
+        Ok(messages)
+    }
+
+    /// Creates `SignedMessage` wrappers from the raw buffers like `from_raw_buffers`.
+    /// Unlike `from_raw_buffers`, an invalid buffer does not reject the other ones.
+    pub(crate) fn from_raw_buffers_lossy(
+        buffers: Vec<Vec<u8>>,
+        network_id: Option<Hash>,
+    ) -> Vec<Result<Self, Error>> {
+        buffers
+            .into_iter()
+            .map(|raw| Self::from_raw_buffer_in_network(raw, network_id))
+            .collect()
     }
 
     fn data_without_signature(&self) -> &[u8] {
@@ -101,6 +327,71 @@ impl SignedMessage {
     //TODO: revert privacy
     pub fn message_class(&self) -> u8 {
         self.raw[PUBLIC_KEY_LENGTH]
+            & !(SECP256K1_CLASS_FLAG
+                | COMPRESSED_CLASS_FLAG
+                | PREHASHED_CLASS_FLAG
+                | VERSION_CLASS_MASK)
+    }
+
+    /// Returns the version of the message format.
+    pub fn format_version(&self) -> u8 {
+        (self.raw[PUBLIC_KEY_LENGTH] & VERSION_CLASS_MASK) >> VERSION_CLASS_SHIFT
+    }
+
+    /// Returns `true` if the payload of the message is compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.raw[PUBLIC_KEY_LENGTH] & COMPRESSED_CLASS_FLAG != 0
+    }
+
+    /// Returns the scheme of the message signature.
+    pub fn signature_scheme(&self) -> SignatureScheme {
+        let class = self.raw[PUBLIC_KEY_LENGTH];
+        if class & SECP256K1_CLASS_FLAG != 0 {
+            SignatureScheme::Secp256k1
+        } else if class & PREHASHED_CLASS_FLAG != 0 {
+            SignatureScheme::Ed25519ph
+        } else {
+            SignatureScheme::Ed25519
+        }
+    }
+
+    /// Checks that the message has a supported format version and may be signed with
+    /// the scheme of its signature and compressed.
+    fn check_flags(&self) -> Result<(), Error> {
+        ensure!(
+            self.format_version() <= MESSAGE_FORMAT_VERSION,
+            "Message has format version {}, which is newer than the supported version {}",
+            self.format_version(),
+            MESSAGE_FORMAT_VERSION
+        );
+        let is_transaction =
+            (self.message_class(), self.message_type()) == RawTransaction::message_type();
+        let class = self.raw[PUBLIC_KEY_LENGTH];
+        ensure!(
+            class & SECP256K1_CLASS_FLAG == 0 || class & PREHASHED_CLASS_FLAG == 0,
+            "Message cannot be signed with several signature schemes"
+        );
+        match self.signature_scheme() {
+            SignatureScheme::Secp256k1 => ensure!(
+                is_transaction,
+                "Only transactions can be signed with secp256k1 keys"
+            ),
+            SignatureScheme::Ed25519ph => ensure!(
+                is_transaction,
+                "Only transactions can be signed in the prehashed mode"
+            ),
+            SignatureScheme::Ed25519 => {}
+        }
+        if self.is_compressed() {
+            ensure!(is_transaction, "Only transactions can be compressed");
+        }
+        Ok(())
+    }
+
+    /// Returns the network identifier covered by the signature of the message
+    /// in the network with the given identifier.
+    fn covered_network_id(&self, network_id: Option<Hash>) -> Option<Hash> {
+        covered_network_id(self.message_class(), self.message_type(), network_id)
     }
 
     /// Returns message type, which is an ID inside some class of messages.
@@ -115,7 +406,32 @@ impl SignedMessage {
         &self.raw[PUBLIC_KEY_LENGTH + 2..sign_idx]
     }
 
-    /// Returns ed25519 signature for this message.
+    /// Returns the payload of the message, decompressed if the message is compressed.
+    ///
+    /// The decompressed payload is limited by `max_len`, which is checked while the payload
+    /// is inflated. Compressed messages are rejected before inflating if `max_len`
+    /// is not specified.
+    pub(in crate::messages) fn decompressed_payload(
+        &self,
+        max_len: Option<u32>,
+    ) -> Result<Cow<[u8]>, Error> {
+        if !self.is_compressed() {
+            return Ok(Cow::from(self.payload()));
+        }
+        let max_len = max_len.ok_or_else(|| format_err!("Compressed messages are not allowed"))?;
+        let mut payload = Vec::new();
+        DeflateDecoder::new(self.payload())
+            .take(u64::from(max_len) + 1)
+            .read_to_end(&mut payload)?;
+        ensure!(
+            payload.len() <= max_len as usize,
+            "Decompressed payload is larger than {} bytes",
+            max_len
+        );
+        Ok(Cow::from(payload))
+    }
+
+    /// Returns signature for this message.
     pub(in crate::messages) fn signature(&self) -> Signature {
         let sign_idx = self.raw.len() - SIGNATURE_LENGTH;
         Signature::from_slice(&self.raw[sign_idx..]).expect("Couldn't read signature")
@@ -140,15 +456,47 @@ impl SignedMessage {
 
     /// Verifies buffer integrity, and authenticate buffer.
     fn verify(
+        scheme: SignatureScheme,
         full_buffer: &[u8],
         signature: &Signature,
         public_key: &PublicKey,
     ) -> Result<(), Error> {
-        if !crypto::verify(signature, &full_buffer, &public_key) {
+        if !scheme.verify(signature, &full_buffer, &public_key) {
             bail!("Cannot verify message.");
         }
         Ok(())
     }
+
+    /// Verifies the signatures of several messages signed for the network with the given
+    /// identifier.
+    ///
+    /// The signatures are verified one by one even with the `batch-verification` feature:
+    /// the batch verification of `ed25519-dalek` accepts some signatures rejected by
+    /// `verify_signature`, while all the nodes must agree on the validity of the messages.
+    pub(crate) fn verify_signatures(
+        messages: &[&SignedMessage],
+        network_id: Option<Hash>,
+    ) -> Result<(), Error> {
+        for message in messages {
+            message.verify_signature(network_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the network identifier covered by the signature of the message with the given
+/// class and type, which is never the case for `Connect` messages.
+fn covered_network_id(class: u8, tag: u8, network_id: Option<Hash>) -> Option<Hash> {
+    if (class, tag) == Connect::message_type() {
+        None
+    } else {
+        network_id
+    }
+}
+
+/// Returns the message class with the current version of the message format.
+fn versioned_class(class: u8) -> u8 {
+    class | (MESSAGE_FORMAT_VERSION << VERSION_CLASS_SHIFT)
 }
 
 impl ToHex for SignedMessage {
@@ -162,6 +510,8 @@ impl ToHex for SignedMessage {
 }
 
 /// Warning: This implementation checks signature which is slow operation.
+/// The signature must not cover the network identifier, use `from_raw_buffer_in_network`
+/// for the messages of the networks with domain separation.
 impl FromHex for SignedMessage {
     type Error = Error;
 
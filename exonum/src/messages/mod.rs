@@ -42,17 +42,31 @@ use std::{borrow::Cow, cmp::PartialEq, fmt, mem, ops::Deref};
 use crate::crypto::{hash, CryptoHash, Hash, PublicKey, Signature};
 
 pub(crate) use self::helpers::HexStringRepresentation;
-pub use self::{authorization::SignedMessage, helpers::to_hex_string, protocol::*};
+pub use self::{
+    authorization::SignedMessage,
+    helpers::to_hex_string,
+    multisig::{CosignerSignature, MultisigTransaction, MULTISIG_SERVICE_ID},
+    protocol::*,
+};
 use exonum_merkledb::BinaryValue;
 
 mod authorization;
 mod helpers;
+mod multisig;
 mod protocol;
 #[cfg(test)]
 mod tests;
 
 /// Version of the protocol. Different versions are incompatible.
-pub const PROTOCOL_MAJOR_VERSION: u8 = 1;
+///
+/// The nodes exchange their versions in `Connect` messages during the handshake
+/// and drop the connections with the nodes of incompatible versions. Version 2 transfers
+/// the messages in chunks, which cannot be decoded by the nodes of previous versions.
+pub const PROTOCOL_MAJOR_VERSION: u8 = 2;
+/// Version of the serialization format of the signed messages, which is stored in the header
+/// of each message. The messages are created with this version; the messages of newer
+/// versions are rejected with an explicit error.
+pub const MESSAGE_FORMAT_VERSION: u8 = 0;
 pub(crate) const RAW_TRANSACTION_HEADER: usize = mem::size_of::<u16>() * 2;
 
 /// Transaction raw buffer.
@@ -212,6 +226,17 @@ impl<T: ProtocolMessage> Signed<T> {
     pub fn signature(&self) -> Signature {
         self.message.signature()
     }
+
+    /// Returns `true` if the payload of the message is encoded canonically, see
+    /// [`proto::from_canonical_bytes`](../proto/fn.from_canonical_bytes.html).
+    /// The payload of a compressed message is checked after decompression.
+    ///
+    /// The payloads of the transactions are checked by their services.
+    pub fn is_canonical(&self) -> bool {
+        self.message
+            .decompressed_payload(Some(MAX_DECOMPRESSED_PAYLOAD_LEN))
+            .map_or(false, |payload| *payload == *self.payload.to_bytes())
+    }
 }
 
 impl fmt::Debug for ServiceTransaction {
@@ -0,0 +1,248 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Multisignature envelopes of transactions.
+
+use byteorder::{ByteOrder, LittleEndian};
+use failure::Error;
+
+use std::borrow::Cow;
+
+use super::{authorization::signed_data, RawTransaction, ServiceTransaction};
+use crate::crypto::{self, hash, Hash, PublicKey, SecretKey, Signature};
+use crate::proto;
+use exonum_merkledb::BinaryValue;
+
+/// Identifier of the service reserved for the multisignature envelopes, see
+/// [`MultisigTransaction`](struct.MultisigTransaction.html).
+pub const MULTISIG_SERVICE_ID: u16 = u16::max_value();
+/// Identifier of the multisignature envelope among the transactions of the reserved service.
+const MULTISIG_TRANSACTION_ID: u16 = 0;
+
+/// Signature of the multisignature envelope by one of its co-signers.
+#[derive(Clone, PartialEq, Eq, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::CosignerSignature", crate = "crate")]
+pub struct CosignerSignature {
+    /// Public key of the co-signer.
+    pub cosigner: PublicKey,
+    /// Signature of the `MultisigTransaction::signing_hash` by the co-signer.
+    pub signature: Signature,
+}
+
+/// Envelope of a transaction approved by at least `threshold` of the declared co-signers.
+///
+/// The envelope is sent as a transaction of the reserved [`MULTISIG_SERVICE_ID`] service,
+/// signed by the author who has collected the signatures of the co-signers. The signatures
+/// cover the author, so that the envelope cannot be resent on behalf of another author.
+/// The blockchain verifies the signatures before the wrapped transaction is executed by
+/// its service, which can get the co-signers who have approved it with
+/// `TransactionContext::cosigners`. The author of the wrapped transaction
+/// is the author of the envelope.
+///
+/// The wrapped transaction is successfully executed at most once, even if it is sent
+/// in several envelopes with different signatures.
+///
+/// [`MULTISIG_SERVICE_ID`]: constant.MULTISIG_SERVICE_ID.html
+#[derive(Clone, PartialEq, Eq, Debug, ProtobufConvert)]
+#[exonum(pb = "proto::MultisigTransaction", crate = "crate")]
+pub struct MultisigTransaction {
+    /// Serialized wrapped transaction.
+    transaction: Vec<u8>,
+    /// Number of the co-signers whose signatures are required.
+    threshold: u32,
+    /// Public keys of the co-signers.
+    cosigners: Vec<PublicKey>,
+    /// Signatures of the co-signers.
+    signatures: Vec<CosignerSignature>,
+}
+
+impl MultisigTransaction {
+    /// Creates an envelope of the transaction without signatures.
+    pub fn new(transaction: RawTransaction, threshold: u32, cosigners: Vec<PublicKey>) -> Self {
+        Self {
+            transaction: transaction.to_bytes(),
+            threshold,
+            cosigners,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Parses the envelope sent as a transaction of the reserved service.
+    pub fn from_raw(raw: &RawTransaction) -> Result<Self, Error> {
+        ensure!(
+            raw.service_id() == MULTISIG_SERVICE_ID
+                && raw.transaction_id() == MULTISIG_TRANSACTION_ID,
+            "Transaction is not a multisignature envelope"
+        );
+        proto::from_payload_bytes(&raw.service_transaction.payload)
+    }
+
+    /// Converts the envelope into a transaction of the reserved service.
+    pub fn into_raw(self) -> RawTransaction {
+        RawTransaction::new(MULTISIG_SERVICE_ID, self.into())
+    }
+
+    /// Returns the wrapped transaction.
+    pub fn transaction(&self) -> Result<RawTransaction, Error> {
+        RawTransaction::from_bytes(Cow::from(&self.transaction[..]))
+    }
+
+    /// Returns the number of the co-signers whose signatures are required.
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Returns the public keys of the co-signers.
+    pub fn cosigners(&self) -> &[PublicKey] {
+        &self.cosigners
+    }
+
+    /// Returns the signatures of the co-signers.
+    pub fn signatures(&self) -> &[CosignerSignature] {
+        &self.signatures
+    }
+
+    /// Returns the hash signed by the co-signers. It covers the wrapped transaction,
+    /// the threshold and the co-signers, the author of the envelope, and the identifier
+    /// of the network if it uses domain separation of the signatures, see `Schema::network_id`.
+    pub fn signing_hash(&self, author: &PublicKey, network_id: Option<Hash>) -> Hash {
+        let mut data = signed_data(network_id, &self.transaction).into_owned();
+        let mut threshold = [0; 4];
+        LittleEndian::write_u32(&mut threshold, self.threshold);
+        data.extend_from_slice(&threshold);
+        for cosigner in &self.cosigners {
+            data.extend_from_slice(cosigner.as_ref());
+        }
+        data.extend_from_slice(author.as_ref());
+        hash(&data)
+    }
+
+    /// Returns the hash of the wrapped transaction, which identifies it regardless
+    /// of the signatures.
+    pub fn transaction_hash(&self) -> Hash {
+        hash(&self.transaction)
+    }
+
+    /// Signs the envelope to be sent by the given author to the network with the given
+    /// identifier by the co-signer.
+    pub fn sign(
+        &mut self,
+        author: &PublicKey,
+        network_id: Option<Hash>,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) {
+        let signature = crypto::sign(self.signing_hash(author, network_id).as_ref(), secret_key);
+        self.add_signature(public_key, signature);
+    }
+
+    /// Adds the signature of the co-signer made elsewhere, replacing the previous one
+    /// of the same co-signer.
+    pub fn add_signature(&mut self, cosigner: PublicKey, signature: Signature) {
+        self.signatures.retain(|s| s.cosigner != cosigner);
+        self.signatures.push(CosignerSignature {
+            cosigner,
+            signature,
+        });
+    }
+
+    /// Verifies the envelope sent by the given author and returns the wrapped transaction
+    /// along with the co-signers who have signed it, in the declared order.
+    ///
+    /// The envelope is valid if the co-signers are unique, each signature is a valid
+    /// signature of a distinct co-signer, and there are at least `threshold` signatures.
+    /// Envelopes cannot be nested.
+    pub fn verify(
+        &self,
+        author: &PublicKey,
+        network_id: Option<Hash>,
+    ) -> Result<(RawTransaction, Vec<PublicKey>), Error> {
+        let transaction = self.transaction()?;
+        ensure!(
+            transaction.service_id() != MULTISIG_SERVICE_ID,
+            "Multisignature envelopes cannot be nested"
+        );
+        ensure!(
+            self.threshold > 0 && self.threshold as usize <= self.cosigners.len(),
+            "Multisignature threshold must be between 1 and the number of co-signers"
+        );
+        for (i, cosigner) in self.cosigners.iter().enumerate() {
+            ensure!(
+                !self.cosigners[..i].contains(cosigner),
+                "Duplicate co-signer {:?}",
+                cosigner
+            );
+        }
+
+        let signing_hash = self.signing_hash(author, network_id);
+        let mut signed = vec![false; self.cosigners.len()];
+        for signature in &self.signatures {
+            let index = self
+                .cosigners
+                .iter()
+                .position(|cosigner| *cosigner == signature.cosigner)
+                .ok_or_else(|| format_err!("Unknown co-signer {:?}", signature.cosigner))?;
+            ensure!(
+                !signed[index],
+                "Duplicate signature of co-signer {:?}",
+                signature.cosigner
+            );
+            ensure!(
+                crypto::verify(
+                    &signature.signature,
+                    signing_hash.as_ref(),
+                    &signature.cosigner
+                ),
+                "Invalid signature of co-signer {:?}",
+                signature.cosigner
+            );
+            signed[index] = true;
+        }
+        ensure!(
+            self.signatures.len() >= self.threshold as usize,
+            "Multisignature transaction has {} of {} required signatures",
+            self.signatures.len(),
+            self.threshold
+        );
+
+        let cosigners = self
+            .cosigners
+            .iter()
+            .zip(signed)
+            .filter(|&(_, signed)| signed)
+            .map(|(cosigner, _)| *cosigner)
+            .collect();
+        Ok((transaction, cosigners))
+    }
+}
+
+impl From<MultisigTransaction> for ServiceTransaction {
+    fn from(envelope: MultisigTransaction) -> Self {
+        ServiceTransaction::from_raw_unchecked(MULTISIG_TRANSACTION_ID, envelope.to_bytes())
+    }
+}
+
+impl RawTransaction {
+    /// Returns the identifier of the service executing the transaction, that is,
+    /// the service of the wrapped transaction for the multisignature envelopes.
+    pub(crate) fn executing_service_id(&self) -> u16 {
+        if self.service_id() != MULTISIG_SERVICE_ID {
+            return self.service_id();
+        }
+        MultisigTransaction::from_raw(self)
+            .and_then(|envelope| envelope.transaction())
+            .map(|transaction| transaction.service_id())
+            .unwrap_or(MULTISIG_SERVICE_ID)
+    }
+}
@@ -31,10 +31,12 @@ use chrono::{DateTime, Utc};
 
 use std::{borrow::Cow, fmt::Debug, mem};
 
-use super::{RawTransaction, ServiceTransaction, Signed, SignedMessage};
+use super::{RawTransaction, ServiceTransaction, Signed, SignedMessage, PROTOCOL_MAJOR_VERSION};
 use crate::blockchain;
 use crate::crypto::{CryptoHash, Hash, PublicKey, SecretKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use crate::events::TransportCompression;
 use crate::helpers::{Height, Round, ValidatorId};
+use crate::node::Signer;
 use crate::proto;
 use exonum_merkledb::{BinaryValue, HashTag};
 
@@ -48,12 +50,18 @@ pub const EMPTY_SIGNED_MESSAGE_SIZE: usize =
 pub const TRANSACTION_RESPONSE_EMPTY_SIZE: usize =
     EMPTY_SIGNED_MESSAGE_SIZE + PUBLIC_KEY_LENGTH + mem::size_of::<u8>() * 4;
 
+/// Maximal size of the decompressed payload of a compressed message. The networks may set
+/// a smaller limit with `ConsensusConfig::max_decompressed_len`.
+pub const MAX_DECOMPRESSED_PAYLOAD_LEN: u32 = 64 * 1024 * 1024;
+
 /// `Signed<RawTransaction>` size with empty transaction inside.
 pub const RAW_TRANSACTION_EMPTY_SIZE: usize = EMPTY_SIGNED_MESSAGE_SIZE + mem::size_of::<u16>() * 2;
 
 /// Connect to a node.
 ///
 /// ### Validation
+/// The connection is dropped if the protocol version of the peer is incompatible
+/// with the version of the node, see `Connect::is_compatible`.
 /// The message is ignored if its time is earlier than in the previous
 /// `Connect` message received from the same peer.
 ///
@@ -73,18 +81,30 @@ pub struct Connect {
     time: DateTime<Utc>,
     /// String containing information about this node including Exonum, Rust and OS versions.
     user_agent: String,
+    /// Major version of the protocol used by the node.
+    protocol_version: u32,
+    /// Name of the compression algorithm used by the node, or an empty string.
+    compression: String,
 }
 
 impl Connect {
-    /// Create new `Connect` message.
+    /// Create new `Connect` message with the current protocol version.
     pub fn new(addr: &str, time: DateTime<Utc>, user_agent: &str) -> Self {
         Connect {
             pub_addr: addr.to_owned(),
             time,
             user_agent: user_agent.to_owned(),
+            protocol_version: u32::from(PROTOCOL_MAJOR_VERSION),
+            compression: String::new(),
         }
     }
 
+    /// Sets the compression of the messages used by the node.
+    pub fn with_compression(mut self, compression: Option<TransportCompression>) -> Self {
+        self.compression = compression.map(|c| c.name().to_owned()).unwrap_or_default();
+        self
+    }
+
     /// The node's address.
     pub fn pub_addr(&self) -> &str {
         &self.pub_addr
@@ -99,6 +119,28 @@ impl Connect {
     pub fn user_agent(&self) -> &str {
         &self.user_agent
     }
+
+    /// Major version of the protocol used by the node. The nodes predating the version
+    /// negotiation do not send their version, so it is `0` for them.
+    pub fn protocol_version(&self) -> u32 {
+        self.protocol_version
+    }
+
+    /// Returns `true` if the node which has sent the message can communicate with this node,
+    /// that is, if it uses the same major version of the protocol.
+    ///
+    /// The nodes predating the version negotiation (version `0`) are incompatible,
+    /// as they do not transfer the messages in chunks.
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == u32::from(PROTOCOL_MAJOR_VERSION)
+    }
+
+    /// Compression of the messages used by the node. The connections with the node are
+    /// compressed if the other peer uses the same compression. Unknown algorithms are
+    /// treated as no compression.
+    pub fn compression(&self) -> Option<TransportCompression> {
+        self.compression.parse().ok()
+    }
 }
 
 /// Current node status.
@@ -330,6 +372,10 @@ pub struct Precommit {
     block_hash: Hash,
     /// Time of the `Precommit`.
     time: DateTime<Utc>,
+    /// Threshold signature share of the block hash, or an empty buffer if the validators
+    /// do not use threshold signatures.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    threshold_share: Vec<u8>,
 }
 
 impl Precommit {
@@ -349,8 +395,14 @@ impl Precommit {
             propose_hash: *propose_hash,
             block_hash: *block_hash,
             time,
+            threshold_share: Vec::new(),
         }
     }
+    /// Attaches the threshold signature share of the block hash to the `Precommit`.
+    pub fn with_threshold_share(mut self, threshold_share: Vec<u8>) -> Self {
+        self.threshold_share = threshold_share;
+        self
+    }
     /// The validator id.
     pub fn validator(&self) -> ValidatorId {
         self.validator
@@ -375,6 +427,11 @@ impl Precommit {
     pub fn time(&self) -> DateTime<Utc> {
         self.time
     }
+    /// Threshold signature share of the block hash, which is empty if the validators
+    /// do not use threshold signatures.
+    pub fn threshold_share(&self) -> &[u8] {
+        &self.threshold_share
+    }
 }
 
 /// Information about a block.
@@ -670,12 +727,20 @@ impl BlockResponse {
 }
 
 impl Precommit {
-    /// Verify precommits signature and return it's safer wrapper
-    pub(crate) fn verify_precommit(buffer: Vec<u8>) -> Result<Signed<Precommit>, ::failure::Error> {
-        let signed = SignedMessage::from_raw_buffer(buffer)?;
-        let protocol = Message::deserialize(signed)?;
-        ProtocolMessage::try_from(protocol)
-            .map_err(|_| format_err!("Couldn't verify precommit from message"))
+    /// Verify signatures of several precommits signed for the network with the given
+    /// identifier at once and return their safer wrappers.
+    pub(crate) fn verify_precommits(
+        buffers: Vec<Vec<u8>>,
+        network_id: Option<Hash>,
+    ) -> Result<Vec<Signed<Precommit>>, ::failure::Error> {
+        SignedMessage::from_raw_buffers(buffers, network_id)?
+            .into_iter()
+            .map(|signed| {
+                let protocol = Message::deserialize(signed)?;
+                ProtocolMessage::try_from(protocol)
+                    .map_err(|_| format_err!("Couldn't verify precommit from message"))
+            })
+            .collect()
     }
 }
 
@@ -778,12 +843,30 @@ macro_rules! impl_protocol {
         impl $protocol_name {
             /// Converts raw `SignedMessage` into concrete `Message` message.
             /// Returns error if fails.
+            ///
+            /// Compressed messages are decompressed up to `MAX_DECOMPRESSED_PAYLOAD_LEN`,
+            /// so this method should be used for trusted messages only, such as the ones
+            /// stored in the blockchain. The messages received from other parties are
+            /// deserialized with `deserialize_with_limit`.
             pub fn deserialize(message: SignedMessage) -> Result<Self, failure::Error> {
+                Self::deserialize_with_limit(message, Some(MAX_DECOMPRESSED_PAYLOAD_LEN))
+            }
+
+            /// Converts raw `SignedMessage` into concrete `Message` message, decompressing
+            /// the payload of compressed messages up to `max_decompressed_len` bytes.
+            /// Compressed messages are rejected if `max_decompressed_len` is not specified,
+            /// see `ConsensusConfig::max_decompressed_len`.
+            pub fn deserialize_with_limit(
+                message: SignedMessage,
+                max_decompressed_len: Option<u32>,
+            ) -> Result<Self, failure::Error> {
                 match message.message_class() {
                     $($class_num =>
                         match message.message_type() {
                             $($type_num =>{
-                                let payload = $type::from_bytes(Cow::from(message.payload()))?;
+                                let payload = <$type as BinaryValue>::from_bytes(
+                                    message.decompressed_payload(max_decompressed_len)?,
+                                )?;
                                 let message = Signed::new(payload, message);
                                 Ok($protocol_name::$class($class::$type(message)))
                             }),+
@@ -794,6 +877,22 @@ macro_rules! impl_protocol {
                 }
             }
 
+            /// Returns `true` if the payload of the message is encoded canonically,
+            /// see `Signed::is_canonical`.
+            pub fn is_canonical(&self) -> bool {
+                match *self {
+                    $(
+                        $protocol_name::$class(ref c) => {
+                            match *c {
+                                $(
+                                    $class::$type(ref t) => t.is_canonical()
+                                ),+
+                            }
+                        }
+                    ),+
+                }
+            }
+
             /// Returns reference to inner `SignedMessage`.
             pub fn signed_message(&self) -> &SignedMessage {
                 match *self {
@@ -886,19 +985,93 @@ impl Message {
         message: T,
         author: PublicKey,
         secret_key: &SecretKey,
+    ) -> Signed<T> {
+        Self::concrete_in_network(message, None, author, secret_key)
+    }
+
+    /// Creates new protocol message signed for the network with the given identifier,
+    /// see `Schema::network_id`. The identifier is `None` for the networks
+    /// without domain separation of the signatures.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn concrete_in_network<T: ProtocolMessage>(
+        message: T,
+        network_id: Option<Hash>,
+        author: PublicKey,
+        secret_key: &SecretKey,
     ) -> Signed<T> {
         let value = message.to_bytes();
         let (cls, typ) = T::message_type();
-        let signed = SignedMessage::new(cls, typ, &value, author, secret_key);
+        let signed = SignedMessage::new(cls, typ, &value, network_id, author, secret_key);
         T::into_message_from_parts(message, signed)
     }
 
+    /// Creates new protocol message signed by the signer for the network with the given
+    /// identifier. The author of the message is the public key of the signer.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn concrete_with_signer<T: ProtocolMessage>(
+        message: T,
+        network_id: Option<Hash>,
+        signer: &dyn Signer,
+    ) -> Result<Signed<T>, failure::Error> {
+        let value = message.to_bytes();
+        let (cls, typ) = T::message_type();
+        let signed = SignedMessage::new_with_signer(cls, typ, &value, network_id, signer)?;
+        Ok(T::into_message_from_parts(message, signed))
+    }
+
+    /// Creates a new raw transaction message signed by the signer for the network
+    /// with the given identifier.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn sign_transaction_with_signer<T>(
+        transaction: T,
+        service_id: u16,
+        network_id: Option<Hash>,
+        signer: &dyn Signer,
+    ) -> Result<Signed<RawTransaction>, failure::Error>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        let raw_tx = RawTransaction::new(service_id, transaction.into());
+        Self::concrete_with_signer(raw_tx, network_id, signer)
+    }
+
     /// Checks buffer and return instance of `Message`.
     pub fn from_raw_buffer(buffer: Vec<u8>) -> Result<Message, failure::Error> {
-        let signed = SignedMessage::from_raw_buffer(buffer)?;
+        Self::from_raw_buffer_in_network(buffer, None)
+    }
+
+    /// Checks buffer of a message signed for the network with the given identifier
+    /// and return instance of `Message`.
+    pub fn from_raw_buffer_in_network(
+        buffer: Vec<u8>,
+        network_id: Option<Hash>,
+    ) -> Result<Message, failure::Error> {
+        let signed = SignedMessage::from_raw_buffer_in_network(buffer, network_id)?;
         Self::deserialize(signed)
     }
 
+    /// Checks buffer of a message received from another party and return instance
+    /// of `Message`. The signature must be made for the network with the given identifier,
+    /// and the payload of a compressed message is decompressed up to `max_decompressed_len`
+    /// bytes, see `deserialize_with_limit`.
+    pub fn from_raw_buffer_with_limit(
+        buffer: Vec<u8>,
+        network_id: Option<Hash>,
+        max_decompressed_len: Option<u32>,
+    ) -> Result<Message, failure::Error> {
+        let signed = SignedMessage::from_raw_buffer_in_network(buffer, network_id)?;
+        Self::deserialize_with_limit(signed, max_decompressed_len)
+    }
+
     /// Creates a new raw transaction message.
     ///
     /// # Panics
@@ -910,12 +1083,112 @@ impl Message {
         public_key: PublicKey,
         secret_key: &SecretKey,
     ) -> Signed<RawTransaction>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        Self::sign_transaction_in_network(transaction, service_id, None, public_key, secret_key)
+    }
+
+    /// Creates a new raw transaction message signed for the network with the given
+    /// identifier, see `concrete_in_network`.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn sign_transaction_in_network<T>(
+        transaction: T,
+        service_id: u16,
+        network_id: Option<Hash>,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) -> Signed<RawTransaction>
     where
         T: Into<ServiceTransaction>,
     {
         let set: ServiceTransaction = transaction.into();
         let raw_tx = RawTransaction::new(service_id, set);
-        Self::concrete(raw_tx, public_key, secret_key)
+        Self::concrete_in_network(raw_tx, network_id, public_key, secret_key)
+    }
+
+    /// Creates a new raw transaction message with the payload compressed with DEFLATE.
+    ///
+    /// Compressed transactions are accepted only by the networks which set
+    /// `ConsensusConfig::max_decompressed_len`. Note that the compressed and uncompressed
+    /// messages of the same transaction have different hashes. The transaction is signed
+    /// for the network with the given identifier, see `concrete_in_network`.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn sign_transaction_compressed<T>(
+        transaction: T,
+        service_id: u16,
+        network_id: Option<Hash>,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) -> Signed<RawTransaction>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        let raw_tx = RawTransaction::new(service_id, transaction.into());
+        let value = raw_tx.to_bytes();
+        let (cls, typ) = RawTransaction::message_type();
+        let signed =
+            SignedMessage::new_compressed(cls, typ, &value, network_id, public_key, secret_key);
+        RawTransaction::into_message_from_parts(raw_tx, signed)
+    }
+
+    /// Creates a new raw transaction message signed in the prehashed ed25519 mode
+    /// (ed25519ph). Unlike `sign_transaction`, the signature is computed over the message
+    /// in chunks, which reduces the memory used to sign large transactions.
+    ///
+    /// Note that the prehashed and plain messages of the same transaction have different
+    /// hashes. The transaction is signed for the network with the given identifier,
+    /// see `concrete_in_network`.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn sign_transaction_prehashed<T>(
+        transaction: T,
+        service_id: u16,
+        network_id: Option<Hash>,
+        public_key: PublicKey,
+        secret_key: &SecretKey,
+    ) -> Signed<RawTransaction>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        let raw_tx = RawTransaction::new(service_id, transaction.into());
+        let value = raw_tx.to_bytes();
+        let (cls, typ) = RawTransaction::message_type();
+        let signed =
+            SignedMessage::new_prehashed(cls, typ, &value, network_id, public_key, secret_key);
+        RawTransaction::into_message_from_parts(raw_tx, signed)
+    }
+
+    /// Creates a new raw transaction message signed with the secp256k1 key. The author
+    /// of the transaction is derived from the public key, see `crypto::secp256k1::author_key`.
+    /// The transaction is signed for the network with the given identifier,
+    /// see `concrete_in_network`.
+    ///
+    /// # Panics
+    ///
+    /// This method can panic on serialization failure.
+    pub fn sign_transaction_secp256k1<T>(
+        transaction: T,
+        service_id: u16,
+        network_id: Option<Hash>,
+        secret_key: &crate::crypto::secp256k1::SecretKey,
+    ) -> Signed<RawTransaction>
+    where
+        T: Into<ServiceTransaction>,
+    {
+        let raw_tx = RawTransaction::new(service_id, transaction.into());
+        let value = raw_tx.to_bytes();
+        let (cls, typ) = RawTransaction::message_type();
+        let signed = SignedMessage::new_secp256k1(cls, typ, &value, network_id, secret_key);
+        RawTransaction::into_message_from_parts(raw_tx, signed)
     }
 }
 
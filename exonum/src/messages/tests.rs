@@ -164,6 +164,7 @@ fn test_block() {
     let block_proof = BlockProof {
         block: content.clone(),
         precommits: precommits.clone(),
+        certificate: None,
     };
     let json_str = serde_json::to_string(&block_proof).unwrap();
     let block_proof_1: BlockProof = serde_json::from_str(&json_str).unwrap();
@@ -224,6 +225,40 @@ fn test_precommit_serde_wrong_signature() {
     assert_eq!(precommit2, precommit);
 }
 
+#[test]
+fn test_verify_precommits_batch() {
+    use crate::crypto::SIGNATURE_LENGTH;
+
+    let ts = Utc::now();
+    let mut precommits = (0..4)
+        .map(|id| {
+            let (pub_key, secret_key) = gen_keypair();
+            let precommit = Message::concrete(
+                Precommit::new(
+                    ValidatorId(id),
+                    Height(15),
+                    Round(1),
+                    &hash(&[1, 2, 3]),
+                    &hash(&[3, 2, 1]),
+                    ts,
+                ),
+                pub_key,
+                &secret_key,
+            );
+            precommit.signed_message().raw().to_vec()
+        })
+        .collect::<Vec<_>>();
+
+    let verified = Precommit::verify_precommits(precommits.clone(), None).unwrap();
+    assert_eq!(verified.len(), 4);
+    assert_eq!(verified[3].validator(), ValidatorId(3));
+
+    // Break one of the signatures.
+    let raw_len = precommits[2].len();
+    precommits[2][raw_len - SIGNATURE_LENGTH] ^= 1;
+    assert!(Precommit::verify_precommits(precommits, None).is_err());
+}
+
 #[test]
 fn test_raw_transaction_small_size() {
     assert!(ServiceTransaction::from_bytes(Cow::from(&vec![0_u8; 1])).is_err());
@@ -233,3 +268,285 @@ fn test_raw_transaction_small_size() {
     assert_eq!(tx.service_id, 0);
     assert_eq!(tx.service_transaction.transaction_id, 0);
 }
+
+#[test]
+fn test_secp256k1_signed_transaction() {
+    use crate::crypto::{secp256k1, SignatureScheme};
+
+    let (public_key, secret_key) = secp256k1::gen_keypair();
+    let set = ServiceTransaction::from_raw_unchecked(0, vec![1, 2, 3]);
+    let tx = Message::sign_transaction_secp256k1(set.clone(), 128, None, &secret_key);
+    assert_eq!(tx.author(), secp256k1::author_key(&public_key));
+    assert_eq!(
+        tx.signed_message().signature_scheme(),
+        SignatureScheme::Secp256k1
+    );
+
+    let raw = tx.signed_message().raw().to_vec();
+    assert!(Message::from_raw_buffer(raw.clone()).is_ok());
+    // Transactions signed with different schemes are verified together.
+    let (ed25519_key, ed25519_secret_key) = gen_keypair();
+    let other_tx = Message::sign_transaction(set, 128, ed25519_key, &ed25519_secret_key);
+    let other_raw = other_tx.signed_message().raw().to_vec();
+    assert!(SignedMessage::from_raw_buffers(vec![raw.clone(), other_raw.clone()], None).is_ok());
+
+    // Break the signature.
+    let mut broken_raw = raw;
+    let raw_len = broken_raw.len();
+    broken_raw[raw_len - 1] ^= 1;
+    assert!(SignedMessage::from_raw_buffer(broken_raw.clone()).is_err());
+    assert!(SignedMessage::from_raw_buffers(vec![broken_raw, other_raw], None).is_err());
+
+    // Only transactions can be signed with secp256k1 keys.
+    let status = Status::new(Height(1), &hash(&[]), 0);
+    let (class, tag) = Status::message_type();
+    let status = SignedMessage::new_secp256k1(class, tag, &status.to_bytes(), None, &secret_key);
+    assert!(SignedMessage::from_raw_buffer(status.raw().to_vec()).is_err());
+}
+
+#[test]
+fn test_multisig_transaction() {
+    use super::{MultisigTransaction, MULTISIG_SERVICE_ID};
+
+    let cosigners = (0..3).map(|_| gen_keypair()).collect::<Vec<_>>();
+    let cosigner_keys = cosigners.iter().map(|(pk, _)| *pk).collect::<Vec<_>>();
+    let (author, _) = gen_keypair();
+    let inner_tx = RawTransaction::new(
+        128,
+        ServiceTransaction::from_raw_unchecked(0, vec![1, 2, 3]),
+    );
+
+    let mut envelope = MultisigTransaction::new(inner_tx.clone(), 2, cosigner_keys.clone());
+    envelope.sign(&author, None, cosigners[2].0, &cosigners[2].1);
+    assert!(envelope.verify(&author, None).is_err());
+    envelope.sign(&author, None, cosigners[0].0, &cosigners[0].1);
+    let (transaction, signed) = envelope.verify(&author, None).unwrap();
+    assert_eq!(transaction, inner_tx);
+    assert_eq!(signed, vec![cosigner_keys[0], cosigner_keys[2]]);
+    // The signatures are bound to the author of the envelope.
+    assert!(envelope.verify(&gen_keypair().0, None).is_err());
+
+    // The envelope survives the round trip through a transaction of the reserved service.
+    let raw = envelope.clone().into_raw();
+    assert_eq!(raw.service_id(), MULTISIG_SERVICE_ID);
+    assert_eq!(raw.executing_service_id(), 128);
+    assert_eq!(MultisigTransaction::from_raw(&raw).unwrap(), envelope);
+    assert!(MultisigTransaction::from_raw(&inner_tx).is_err());
+
+    // Signatures of unknown co-signers are rejected.
+    let (outsider_key, outsider_secret_key) = gen_keypair();
+    let mut invalid_envelope = envelope.clone();
+    invalid_envelope.sign(&author, None, outsider_key, &outsider_secret_key);
+    assert!(invalid_envelope.verify(&author, None).is_err());
+
+    // So are the signatures of a different envelope.
+    let mut invalid_envelope = MultisigTransaction::new(inner_tx.clone(), 1, cosigner_keys.clone());
+    for signature in envelope.signatures() {
+        invalid_envelope.add_signature(signature.cosigner, signature.signature);
+    }
+    assert!(invalid_envelope.verify(&author, None).is_err());
+
+    // Envelopes cannot be nested.
+    let mut nested_envelope = MultisigTransaction::new(raw, 1, cosigner_keys.clone());
+    nested_envelope.sign(&author, None, cosigners[1].0, &cosigners[1].1);
+    assert!(nested_envelope.verify(&author, None).is_err());
+
+    // The threshold cannot exceed the number of co-signers.
+    let mut invalid_envelope = MultisigTransaction::new(inner_tx, 4, cosigner_keys);
+    for (public_key, secret_key) in &cosigners {
+        invalid_envelope.sign(&author, None, *public_key, secret_key);
+    }
+    assert!(invalid_envelope.verify(&author, None).is_err());
+}
+
+#[test]
+fn test_compressed_transaction() {
+    let (public_key, secret_key) = gen_keypair();
+    let set = ServiceTransaction::from_raw_unchecked(0, vec![7; 4096]);
+    let tx = Message::sign_transaction_compressed(set.clone(), 128, None, public_key, &secret_key);
+    assert!(tx.signed_message().is_compressed());
+    assert!(tx.signed_message().raw().len() < 1024);
+
+    let message = Message::from_raw_buffer(tx.signed_message().raw().to_vec()).unwrap();
+    let decoded = RawTransaction::try_from(message).unwrap();
+    assert_eq!(decoded, tx);
+    assert_eq!(*decoded.payload(), RawTransaction::new(128, set.clone()));
+
+    // The limit on the decompressed payload is checked before it is inflated completely.
+    let raw = tx.signed_message().raw().to_vec();
+    assert!(Message::from_raw_buffer_with_limit(raw.clone(), None, Some(8192)).is_ok());
+    assert!(Message::from_raw_buffer_with_limit(raw.clone(), None, Some(1024)).is_err());
+    // Compressed messages are rejected if the limit is not specified.
+    assert!(Message::from_raw_buffer_with_limit(raw, None, None).is_err());
+
+    // The uncompressed message of the same transaction is a different message.
+    let uncompressed_tx = Message::sign_transaction(set, 128, public_key, &secret_key);
+    assert!(!uncompressed_tx.signed_message().is_compressed());
+    assert_eq!(uncompressed_tx.payload(), tx.payload());
+    assert_ne!(uncompressed_tx.hash(), tx.hash());
+
+    // Only transactions can be compressed.
+    let status = Status::new(Height(1), &hash(&[]), 0);
+    let (class, tag) = Status::message_type();
+    let status = SignedMessage::new_compressed(
+        class,
+        tag,
+        &status.to_bytes(),
+        None,
+        public_key,
+        &secret_key,
+    );
+    assert!(SignedMessage::from_raw_buffer(status.raw().to_vec()).is_err());
+}
+
+#[test]
+fn test_prehashed_transaction() {
+    use crate::crypto::SignatureScheme;
+
+    let (public_key, secret_key) = gen_keypair();
+    let set = ServiceTransaction::from_raw_unchecked(0, vec![7; 4096]);
+    let tx = Message::sign_transaction_prehashed(set.clone(), 128, None, public_key, &secret_key);
+    assert_eq!(
+        tx.signed_message().signature_scheme(),
+        SignatureScheme::Ed25519ph
+    );
+    assert_eq!(tx.author(), public_key);
+
+    let raw = tx.signed_message().raw().to_vec();
+    let message = Message::from_raw_buffer(raw.clone()).unwrap();
+    assert_eq!(RawTransaction::try_from(message).unwrap(), tx);
+    // Transactions signed in different modes are verified together.
+    let other_tx = Message::sign_transaction(set, 128, public_key, &secret_key);
+    let other_raw = other_tx.signed_message().raw().to_vec();
+    assert_eq!(other_tx.payload(), tx.payload());
+    assert_ne!(other_tx.hash(), tx.hash());
+    assert!(SignedMessage::from_raw_buffers(vec![raw.clone(), other_raw.clone()], None).is_ok());
+
+    // Break the signature.
+    let mut broken_raw = raw;
+    let raw_len = broken_raw.len();
+    broken_raw[raw_len - 1] ^= 1;
+    assert!(SignedMessage::from_raw_buffer(broken_raw.clone()).is_err());
+    assert!(SignedMessage::from_raw_buffers(vec![broken_raw, other_raw], None).is_err());
+
+    // Only transactions can be signed in the prehashed mode.
+    let status = Status::new(Height(1), &hash(&[]), 0);
+    let (class, tag) = Status::message_type();
+    let status = SignedMessage::new_prehashed(
+        class,
+        tag,
+        &status.to_bytes(),
+        None,
+        public_key,
+        &secret_key,
+    );
+    assert!(SignedMessage::from_raw_buffer(status.raw().to_vec()).is_err());
+}
+
+#[test]
+fn test_connect_compression() {
+    use super::Connect;
+    use crate::events::TransportCompression;
+    use crate::proto::ProtobufConvert;
+
+    let connect = Connect::new("127.0.0.1:8000", Utc::now(), "user_agent");
+    assert_eq!(connect.compression(), None);
+
+    let connect = connect.with_compression(Some(TransportCompression::Zstd));
+    assert_eq!(connect.compression(), Some(TransportCompression::Zstd));
+    let connect = Connect::from_pb(connect.to_pb()).unwrap();
+    assert_eq!(connect.compression(), Some(TransportCompression::Zstd));
+
+    // Unknown algorithms are treated as no compression.
+    let mut pb_connect = connect.to_pb();
+    pb_connect.set_compression("brotli".to_owned());
+    assert_eq!(Connect::from_pb(pb_connect).unwrap().compression(), None);
+}
+
+#[test]
+fn test_protocol_versions() {
+    use super::{Connect, MESSAGE_FORMAT_VERSION, PROTOCOL_MAJOR_VERSION};
+    use crate::proto::ProtobufConvert;
+
+    let connect = Connect::new("127.0.0.1:8000", Utc::now(), "user_agent");
+    assert_eq!(
+        connect.protocol_version(),
+        u32::from(PROTOCOL_MAJOR_VERSION)
+    );
+    assert!(connect.is_compatible());
+
+    // Nodes predating the version negotiation do not send their version
+    // and cannot decode chunked messages.
+    let mut pb_connect = connect.to_pb();
+    pb_connect.set_protocol_version(0);
+    let legacy_connect = Connect::from_pb(pb_connect.clone()).unwrap();
+    assert_eq!(legacy_connect.protocol_version(), 0);
+    assert!(!legacy_connect.is_compatible());
+
+    pb_connect.set_protocol_version(u32::from(PROTOCOL_MAJOR_VERSION) - 1);
+    assert!(!Connect::from_pb(pb_connect.clone())
+        .unwrap()
+        .is_compatible());
+
+    pb_connect.set_protocol_version(u32::from(PROTOCOL_MAJOR_VERSION) + 1);
+    assert!(!Connect::from_pb(pb_connect).unwrap().is_compatible());
+
+    // Messages of newer formats are rejected.
+    let (public_key, secret_key) = gen_keypair();
+    let signed = Message::concrete(connect, public_key, &secret_key);
+    assert_eq!(
+        signed.signed_message().format_version(),
+        MESSAGE_FORMAT_VERSION
+    );
+    let (class, tag) = Connect::message_type();
+    let newer_class = class | ((MESSAGE_FORMAT_VERSION + 1) << 3);
+    let newer_message = SignedMessage::new(
+        newer_class,
+        tag,
+        &signed.payload().to_bytes(),
+        None,
+        public_key,
+        &secret_key,
+    );
+    assert_eq!(newer_message.message_class(), class);
+    assert!(SignedMessage::from_raw_buffer(newer_message.raw().to_vec()).is_err());
+}
+
+#[test]
+fn test_domain_separated_signatures() {
+    use super::Connect;
+
+    let (public_key, secret_key) = gen_keypair();
+    let network_id = hash(b"staging");
+    let set = ServiceTransaction::from_raw_unchecked(0, vec![1, 2, 3]);
+    let tx = Message::sign_transaction(set.clone(), 128, public_key, &secret_key);
+    let message = tx.signed_message();
+    assert!(message.verify_signature(None).is_ok());
+    assert!(message.verify_signature(Some(network_id)).is_err());
+
+    // The same transaction signed for the network.
+    let network_tx =
+        Message::sign_transaction_in_network(set, 128, Some(network_id), public_key, &secret_key);
+    let network_message = network_tx.signed_message();
+    assert_eq!(network_message.payload(), message.payload());
+    assert!(network_message.verify_signature(Some(network_id)).is_ok());
+    assert!(network_message
+        .verify_signature(Some(hash(b"production")))
+        .is_err());
+    assert!(network_message.verify_signature(None).is_err());
+    let raw = network_message.raw().to_vec();
+    assert!(SignedMessage::from_raw_buffer(raw.clone()).is_err());
+    assert!(SignedMessage::from_raw_buffer_in_network(raw, Some(network_id)).is_ok());
+
+    assert!(SignedMessage::verify_signatures(&[network_message], Some(network_id)).is_ok());
+    assert!(
+        SignedMessage::verify_signatures(&[network_message, message], Some(network_id)).is_err()
+    );
+
+    // `Connect` messages never cover the network identifier.
+    let connect = Connect::new("127.0.0.1:8000", Utc::now(), "user-agent");
+    let connect = Message::concrete_in_network(connect, Some(network_id), public_key, &secret_key);
+    let raw = connect.signed_message().raw().to_vec();
+    assert!(SignedMessage::from_raw_buffer(raw.clone()).is_ok());
+    assert!(SignedMessage::from_raw_buffer_in_network(raw, Some(network_id)).is_ok());
+}
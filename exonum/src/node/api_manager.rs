@@ -0,0 +1,238 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Management of the HTTP API servers of the node, which can be restarted
+//! independently of the consensus.
+// spell-checker:ignore cors
+
+use failure::Error;
+use futures::{sync::mpsc, Stream};
+
+use std::{net::IpAddr, sync::Arc};
+
+use super::NodeApiConfig;
+use crate::api::{
+    backends::actix::{
+        AllowOrigin, ApiRuntimeConfig, App, AppConfig, Cors, CorsRule, CorsRules, IpAllowlist,
+        ListenAddress, SystemRuntime, SystemRuntimeConfig,
+    },
+    ApiAccess, ApiAggregator,
+};
+use crate::blockchain::{Blockchain, SharedNodeState};
+
+/// Request to the API manager.
+#[derive(Debug)]
+pub(crate) enum ApiManagerRequest {
+    /// Restart the API servers with the given configuration. If the configuration
+    /// is not specified, the current one is used.
+    Restart(Option<NodeApiConfig>),
+    /// Stop the API servers.
+    Stop,
+}
+
+/// Owner of the actix runtime with the API servers of the node.
+pub(crate) struct ApiManager {
+    blockchain: Blockchain,
+    api_state: SharedNodeState,
+    app_configs: Vec<(ApiAccess, AppConfig)>,
+    api_options: NodeApiConfig,
+    runtime: Option<SystemRuntime>,
+}
+
+impl ApiManager {
+    /// Creates a manager for the API servers with the given configuration and hooks.
+    pub fn new(
+        blockchain: Blockchain,
+        api_state: SharedNodeState,
+        app_configs: Vec<(ApiAccess, AppConfig)>,
+        api_options: NodeApiConfig,
+    ) -> Self {
+        Self {
+            blockchain,
+            api_state,
+            app_configs,
+            api_options,
+            runtime: None,
+        }
+    }
+
+    /// Starts the API servers with the current configuration.
+    pub fn start(&mut self) -> Result<(), Error> {
+        let runtime = self.runtime_config(&self.api_options).start()?;
+        self.runtime = Some(runtime);
+        Ok(())
+    }
+
+    /// Handles requests until the `Stop` request is received, and then stops the API servers.
+    pub fn run(
+        mut self,
+        requests: mpsc::UnboundedReceiver<ApiManagerRequest>,
+    ) -> Result<(), Error> {
+        for request in requests.wait() {
+            match request {
+                // The manager keeps running if the servers cannot be started, so that
+                // they can be restarted later.
+                Ok(ApiManagerRequest::Restart(api_options)) => {
+                    if let Err(e) = self.restart(api_options) {
+                        error!("Unable to restart web api: {}", e);
+                    }
+                }
+                Ok(ApiManagerRequest::Stop) | Err(()) => break,
+            }
+        }
+        self.stop()
+    }
+
+    /// Stops the API servers and starts them again with the given configuration.
+    /// If the servers cannot be started with the new configuration, the previous one
+    /// is restored.
+    fn restart(&mut self, api_options: Option<NodeApiConfig>) -> Result<(), Error> {
+        info!("Restarting web api");
+        if let Err(e) = self.stop() {
+            warn!("Unable to stop web api before the restart: {}", e);
+        }
+
+        let api_options = api_options.unwrap_or_else(|| self.api_options.clone());
+        match self.runtime_config(&api_options).start() {
+            Ok(runtime) => {
+                self.runtime = Some(runtime);
+                self.api_options = api_options;
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    "Unable to start web api with the new configuration, \
+                     restoring the previous one: {}",
+                    e
+                );
+                self.start()
+            }
+        }
+    }
+
+    fn stop(&mut self) -> Result<(), Error> {
+        // Websocket servers are bound to the actix runtime, so they are recreated
+        // along with the API servers.
+        self.api_state.shutdown_broadcast_server();
+        self.api_state.reset_broadcast_servers();
+        match self.runtime.take() {
+            Some(runtime) => runtime.stop(),
+            None => Ok(()),
+        }
+    }
+
+    fn runtime_config(&self, api_options: &NodeApiConfig) -> SystemRuntimeConfig {
+        fn into_app_config(allow_origin: AllowOrigin) -> AppConfig {
+            let app_config = move |app: App| -> App {
+                let cors = Cors::from(allow_origin.clone());
+                app.middleware(cors)
+            };
+            Arc::new(app_config)
+        };
+
+        fn into_rules_app_config(rules: Vec<CorsRule>) -> AppConfig {
+            let app_config = move |app: App| -> App { app.middleware(CorsRules::new(&rules)) };
+            Arc::new(app_config)
+        };
+
+        fn into_allowlist_app_config(addresses: Vec<IpAddr>) -> AppConfig {
+            let allowlist = IpAllowlist::new(addresses);
+            let app_config = move |app: App| -> App { app.middleware(allowlist.clone()) };
+            Arc::new(app_config)
+        };
+
+        let public_api_handler = api_options
+            .public_api_address
+            .map(|listen_address| ApiRuntimeConfig {
+                listen_address: listen_address.into(),
+                access: ApiAccess::Public,
+                app_config: self.app_config(
+                    ApiAccess::Public,
+                    api_options
+                        .public_cors_rules
+                        .clone()
+                        .map(into_rules_app_config)
+                        .or_else(|| api_options.public_allow_origin.clone().map(into_app_config)),
+                ),
+            })
+            .into_iter();
+        let private_api_handler = api_options
+            .private_api_socket
+            .clone()
+            .map(ListenAddress::Unix)
+            .or_else(|| api_options.private_api_address.map(ListenAddress::Tcp))
+            .map(|listen_address| ApiRuntimeConfig {
+                access: ApiAccess::Private,
+                app_config: self.app_config(
+                    ApiAccess::Private,
+                    api_options
+                        .private_api_allowlist
+                        .clone()
+                        // Requests received via a Unix domain socket have no peer address.
+                        .filter(|_| match listen_address {
+                            ListenAddress::Tcp(_) => true,
+                            ListenAddress::Unix(_) => false,
+                        })
+                        .map(into_allowlist_app_config)
+                        .into_iter()
+                        .chain(
+                            api_options
+                                .private_cors_rules
+                                .clone()
+                                .map(into_rules_app_config)
+                                .or_else(|| {
+                                    api_options.private_allow_origin.clone().map(into_app_config)
+                                }),
+                        ),
+                ),
+                listen_address,
+            })
+            .into_iter();
+
+        let aggregator = ApiAggregator::new(self.blockchain.clone(), self.api_state.clone());
+        SystemRuntimeConfig {
+            // Collects API handlers.
+            api_runtimes: public_api_handler.chain(private_api_handler).collect(),
+            api_aggregator: if api_options.public_mempool_events {
+                aggregator.with_public_mempool_events()
+            } else {
+                aggregator
+            },
+        }
+    }
+
+    /// Combines the given default configuration with the hooks for the given access level.
+    fn app_config<I>(&self, access: ApiAccess, defaults: I) -> Option<AppConfig>
+    where
+        I: IntoIterator<Item = AppConfig>,
+    {
+        let app_configs = defaults
+            .into_iter()
+            .chain(
+                self.app_configs
+                    .iter()
+                    .filter(|(hook_access, _)| *hook_access == access)
+                    .map(|(_, app_config)| app_config.clone()),
+            )
+            .collect::<Vec<_>>();
+        if app_configs.is_empty() {
+            return None;
+        }
+
+        let app_config = move |app: App| -> App {
+            app_configs.iter().fold(app, |app, app_config| app_config(app))
+        };
+        Some(Arc::new(app_config))
+    }
+}
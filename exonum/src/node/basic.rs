@@ -14,16 +14,28 @@
 
 use rand::Rng;
 
-use super::{NodeHandler, NodeRole, RequestData};
+use super::{ConnectList, ConnectListConfig, NodeHandler, NodeRole, RequestData};
+use crate::blockchain::PeerEventKind;
 use crate::crypto::PublicKey;
 use crate::events::error::LogError;
-use crate::events::network::ConnectedPeerAddr;
+use crate::events::network::{ConnectedPeerAddr, NetworkRequest};
 use crate::helpers::Height;
 use crate::messages::{Connect, Message, PeersRequest, Responses, Service, Signed, Status};
 
 impl NodeHandler {
     /// Redirects message to the corresponding `handle_...` function.
     pub fn handle_message(&mut self, msg: Message) {
+        let msg = match self.defer_paused_message(msg) {
+            Some(msg) => msg,
+            None => return,
+        };
+        if self.state.config().canonical_encoding && !msg.is_canonical() {
+            warn!(
+                "Received message which is not encoded canonically: {:?}",
+                msg
+            );
+            return;
+        }
         match msg {
             Message::Consensus(msg) => self.handle_consensus(msg),
             Message::Requests(ref msg) => self.handle_request(msg),
@@ -47,6 +59,8 @@ impl NodeHandler {
         info!("Received Connect message from peer: {:?}", address);
         // TODO: use `ConnectInfo` instead of connect-messages. (ECR-1452)
         self.state.add_connection(connect.author(), address.clone());
+        self.observe_peer_event(connect.author(), PeerEventKind::Connected);
+        self.observe_peer_time(connect.author(), connect.time());
         self.handle_connect(connect);
     }
 
@@ -54,6 +68,7 @@ impl NodeHandler {
     /// in the validators list.
     pub fn handle_disconnected(&mut self, key: PublicKey) {
         info!("Disconnected from: {}", key);
+        self.observe_peer_event(key, PeerEventKind::Disconnected);
         self.remove_peer_with_addr(key);
     }
 
@@ -61,6 +76,7 @@ impl NodeHandler {
     /// if it was in the validators list.
     pub fn handle_unable_to_connect(&mut self, key: PublicKey) {
         info!("Could not connect to: {}", key);
+        self.observe_peer_event(key, PeerEventKind::ConnectionFailed);
         self.remove_peer_with_addr(key);
     }
 
@@ -69,6 +85,7 @@ impl NodeHandler {
     fn remove_peer_with_addr(&mut self, key: PublicKey) {
         self.state.remove_peer_with_pubkey(&key);
         self.blockchain.remove_peer_with_pubkey(&key);
+        self.forget_peer_time(&key);
         let is_validator = self.state.peer_is_validator(&key);
         let in_connect_list = self.state.peer_in_connect_list(&key);
         if is_validator && in_connect_list {
@@ -76,6 +93,53 @@ impl NodeHandler {
         }
     }
 
+    /// Handles the removal of a peer from the connect list via the API. The connection
+    /// with the peer is dropped, and the updated connect list is stored in the config file.
+    pub(crate) fn handle_peer_remove(&mut self, key: PublicKey) {
+        if !self.state.remove_peer_from_connect_list(&key) {
+            warn!("Peer {} is not in the connect list", key);
+            return;
+        }
+        info!("Removed peer {} from the connect list", key);
+        self.disconnect(key);
+        self.store_connect_list();
+    }
+
+    /// Handles the connect list read from the config file. The node disconnects from
+    /// the removed peers and connects to the added ones.
+    pub(crate) fn handle_connect_list_update(&mut self, config: ConnectListConfig) {
+        let (added, removed) = self
+            .state
+            .update_connect_list(ConnectList::from_config(config));
+        for key in removed {
+            info!("Peer {} has been removed from the connect list", key);
+            self.disconnect(key);
+        }
+        for key in added {
+            info!("Peer {} has been added to the connect list", key);
+            self.connect(key);
+        }
+    }
+
+    /// Stores the connect list in the config file if the node has been started with one.
+    pub(crate) fn store_connect_list(&self) {
+        if let Some(ref config_manager) = self.config_manager {
+            let connect_list_config =
+                ConnectListConfig::from_connect_list(&self.state.connect_list());
+            config_manager.store_connect_list(connect_list_config);
+        }
+    }
+
+    /// Drops the connection with the peer and forgets its `Connect` message. The node does
+    /// not reconnect to the peer, since it is not in the connect list any more.
+    fn disconnect(&mut self, key: PublicKey) {
+        self.channel
+            .network_requests
+            .send(NetworkRequest::DisconnectWithPeer(key))
+            .log_error();
+        self.remove_peer_with_addr(key);
+    }
+
     /// Handles the `Connect` message and connects to a peer as result.
     pub fn handle_connect(&mut self, message: Signed<Connect>) {
         // TODO Add spam protection (ECR-170)
@@ -92,6 +156,15 @@ impl NodeHandler {
             return;
         }
 
+        if !message.is_compatible() {
+            warn!(
+                "Received Connect message from {} with incompatible protocol version {}",
+                public_key,
+                message.protocol_version()
+            );
+            return;
+        }
+
         if !self.state.connect_list().is_peer_allowed(&public_key) {
             error!(
                 "Received connect message from {:?} peer which not in ConnectList.",
@@ -168,7 +241,9 @@ impl NodeHandler {
             }
 
             // Request block
-            self.request(RequestData::Block(height), peer);
+            if !self.request_blocks_ahead() {
+                self.request(RequestData::Block(height), peer);
+            }
         }
 
         if self.uncommitted_txs_count() == 0 && msg.pool_size() > 0 {
@@ -217,8 +292,9 @@ impl NodeHandler {
                 .unwrap();
             let msg = PeersRequest::new(&peer.author());
             trace!("Request peers from peer with addr {:?}", peer.pub_addr());
-            let message = self.sign_message(msg);
-            self.send_to_peer(peer.author(), message);
+            if let Some(message) = self.sign_message(msg) {
+                self.send_to_peer(peer.author(), message);
+            }
         }
         self.add_peer_exchange_timeout();
     }
@@ -226,10 +302,46 @@ impl NodeHandler {
     /// Node update internal `ApiState` and `NodeRole`.
     pub fn handle_update_api_state_timeout(&mut self) {
         self.api_state.update_node_state(&self.state);
-        self.node_role = NodeRole::new(self.state.validator_id());
+        self.api_state
+            .set_consensus_state(self.consensus_state_info());
+        self.update_node_role();
+        self.save_peer_stats();
         self.add_update_api_state_timeout();
     }
 
+    /// Updates the role of the node after the actual configuration has changed. A node
+    /// whose keys are added to the validators starts to participate in the consensus
+    /// at the height the configuration becomes actual, without a restart.
+    pub(crate) fn update_node_role(&mut self) {
+        let role = NodeRole::new(self.state.validator_id());
+        let height = self.state.height();
+        match (self.node_role, role) {
+            (NodeRole::Auditor, NodeRole::Validator(id)) => {
+                info!(
+                    "The node has become validator {} at height {}, joining the consensus",
+                    id, height
+                );
+            }
+            (NodeRole::Validator(id), NodeRole::Auditor) => {
+                info!(
+                    "The node is no longer a validator at height {}, previous id {}",
+                    height, id
+                );
+            }
+            (NodeRole::Validator(old), NodeRole::Validator(new)) if old != new => {
+                info!(
+                    "The validator id of the node has changed from {} to {} at height {}",
+                    old, new, height
+                );
+            }
+            _ => return,
+        }
+        self.node_role = role;
+        self.api_state.set_node_role(role);
+        self.api_state
+            .set_consensus_state(self.consensus_state_info());
+    }
+
     /// Broadcasts the `Status` message to all peers.
     pub fn broadcast_status(&mut self) {
         let hash = self.blockchain.last_hash();
@@ -237,7 +349,8 @@ impl NodeHandler {
         let status = Status::new(self.state.height(), &hash, pool_size);
         trace!("Broadcast status: {:?}", status);
 
-        let message = self.sign_message(status);
-        self.broadcast(message);
+        if let Some(message) = self.sign_message(status) {
+            self.broadcast(message);
+        }
     }
 }
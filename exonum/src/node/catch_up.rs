@@ -0,0 +1,269 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fast synchronization of the nodes lagging far behind their peers.
+
+use std::{cmp, collections::BTreeMap, fmt, thread};
+
+use super::{CatchUpConfig, NodeHandler, RequestData};
+use crate::crypto::{Hash, PublicKey};
+use crate::events::error::LogError;
+use crate::helpers::Height;
+use crate::messages::{BlockResponse, Precommit, Signed};
+
+/// State of the catch-up mode.
+#[derive(Debug)]
+pub(crate) struct CatchUp {
+    config: CatchUpConfig,
+    /// Whether the node is catching up at the moment.
+    active: bool,
+    /// Whether the received blocks are being applied.
+    applying: bool,
+    /// Received blocks following the current height.
+    blocks: BTreeMap<Height, Signed<BlockResponse>>,
+    /// Precommits of the received block verified while the previous block was merged.
+    verified: Option<VerifiedPrecommits>,
+}
+
+impl CatchUp {
+    pub fn new(config: CatchUpConfig) -> Self {
+        Self {
+            config,
+            active: false,
+            applying: false,
+            blocks: BTreeMap::new(),
+            verified: None,
+        }
+    }
+}
+
+/// Precommits of a received block with valid signatures.
+#[derive(Debug)]
+struct VerifiedPrecommits {
+    raw: Vec<Vec<u8>>,
+    network_id: Option<Hash>,
+    precommits: Vec<Signed<Precommit>>,
+}
+
+/// Verification of the precommits of the received block following the current height,
+/// which proceeds while the block at the current height is merged into the storage.
+pub(crate) struct PendingVerification {
+    raw: Vec<Vec<u8>>,
+    network_id: Option<Hash>,
+    verification: thread::JoinHandle<Result<Vec<Signed<Precommit>>, failure::Error>>,
+}
+
+impl fmt::Debug for PendingVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PendingVerification")
+            .field("network_id", &self.network_id)
+            .finish()
+    }
+}
+
+impl NodeHandler {
+    /// Returns `true` if the node is in the catch-up mode. Maintenance which is not needed
+    /// to commit blocks, such as pruning, checkpoints and garbage collection, is deferred
+    /// until the node catches up.
+    pub(crate) fn is_catching_up(&self) -> bool {
+        self.catch_up
+            .as_ref()
+            .map_or(false, |catch_up| catch_up.active)
+    }
+
+    /// Requests the blocks following the current height if the node lags behind its peers
+    /// by at least `min_lag` blocks. The requests are distributed among the peers having
+    /// the blocks, so the blocks are downloaded in parallel.
+    ///
+    /// Returns `false` if the catch-up mode is disabled or the node does not lag enough,
+    /// in which case the next block should be requested as usual.
+    pub(crate) fn request_blocks_ahead(&mut self) -> bool {
+        let config = match self.catch_up {
+            Some(ref catch_up) => catch_up.config,
+            None => return false,
+        };
+
+        let height = self.state.height();
+        let mut peers: Vec<(PublicKey, Height)> = self
+            .state
+            .nodes_with_bigger_height()
+            .into_iter()
+            .filter(|key| self.state.peers().contains_key(key))
+            .map(|key| (*key, self.state.node_height(key)))
+            .collect();
+        peers.sort();
+        let max_height = peers.iter().map(|&(_, h)| h).max().unwrap_or(height);
+        let lag = max_height.0 - height.0;
+        self.set_catching_up(lag >= config.min_lag, lag);
+        if !self.is_catching_up() {
+            return false;
+        }
+
+        let last_height = cmp::min(max_height.0, height.0 + config.max_blocks_ahead + 1);
+        for block_height in (height.0..last_height).map(Height) {
+            if self.has_block_ahead(block_height) {
+                continue;
+            }
+            let candidates: Vec<PublicKey> = peers
+                .iter()
+                .filter(|&&(_, peer_height)| peer_height > block_height)
+                .map(|&(key, _)| key)
+                .collect();
+            let peer = candidates[block_height.0 as usize % candidates.len()];
+            self.request(RequestData::Block(block_height), peer);
+        }
+        true
+    }
+
+    /// Starts verifying the signatures of the precommits of the received block following
+    /// the current height in a separate thread, so that the verification proceeds while
+    /// the block at the current height is merged into the storage.
+    pub(crate) fn start_block_ahead_verification(&self) -> Option<PendingVerification> {
+        let catch_up = self.catch_up.as_ref()?;
+        let msg = catch_up.blocks.get(&self.state.height().next())?;
+        let raw = msg.precommits();
+        let network_id = self.state.network_id();
+        let buffers = raw.clone();
+        let verification = thread::spawn(move || Precommit::verify_precommits(buffers, network_id));
+        Some(PendingVerification {
+            raw,
+            network_id,
+            verification,
+        })
+    }
+
+    /// Waits for the verification of the precommits started before the merge. Valid
+    /// precommits are not verified again once the block is applied.
+    pub(crate) fn finish_block_ahead_verification(&mut self, pending: PendingVerification) {
+        // Invalid precommits are reported once the block is applied.
+        let precommits = match pending.verification.join() {
+            Ok(Ok(precommits)) => precommits,
+            _ => return,
+        };
+        self.api_state.verification_metrics().record_pipelined();
+        if let Some(ref mut catch_up) = self.catch_up {
+            catch_up.verified = Some(VerifiedPrecommits {
+                raw: pending.raw,
+                network_id: pending.network_id,
+                precommits,
+            });
+        }
+    }
+
+    /// Verifies the signatures of the precommits of the received block, unless they have
+    /// been verified while the previous block was merged.
+    pub(crate) fn verify_block_precommits(
+        &self,
+        msg: &Signed<BlockResponse>,
+    ) -> Result<Vec<Signed<Precommit>>, failure::Error> {
+        let raw = msg.precommits();
+        let network_id = self.state.network_id();
+        let verified = self
+            .catch_up
+            .as_ref()
+            .and_then(|catch_up| catch_up.verified.as_ref())
+            .filter(|verified| verified.raw == raw && verified.network_id == network_id);
+        match verified {
+            Some(verified) => Ok(verified.precommits.clone()),
+            None => Precommit::verify_precommits(raw, network_id),
+        }
+    }
+
+    /// Saves the block following the current height received in the catch-up mode,
+    /// so that it is applied after the preceding blocks.
+    ///
+    /// Returns `false` if the block should be handled as usual.
+    pub(crate) fn save_block_ahead(&mut self, msg: &Signed<BlockResponse>) -> bool {
+        let max_blocks_ahead = match self.catch_up {
+            Some(ref catch_up) if catch_up.active => catch_up.config.max_blocks_ahead,
+            _ => return false,
+        };
+
+        let height = self.state.height();
+        let block_height = msg.block().height();
+        if block_height <= height || block_height.0 > height.0 + max_blocks_ahead {
+            return false;
+        }
+        if msg.to() != self.state.consensus_public_key()
+            || !self.state.connect_list().is_peer_allowed(&msg.author())
+        {
+            return false;
+        }
+
+        trace!("Save block at height {} to apply it later", block_height);
+        self.remove_request(&RequestData::Block(block_height));
+        if let Some(ref mut catch_up) = self.catch_up {
+            catch_up.blocks.insert(block_height, msg.clone());
+        }
+        true
+    }
+
+    /// Applies the saved blocks following the committed ones.
+    pub(crate) fn apply_blocks_ahead(&mut self) {
+        {
+            let catch_up = match self.catch_up {
+                Some(ref mut catch_up) => catch_up,
+                None => return,
+            };
+            // Blocks are applied by the outer call.
+            if catch_up.applying || catch_up.blocks.is_empty() {
+                return;
+            }
+            catch_up.applying = true;
+        }
+
+        loop {
+            let height = self.state.height();
+            let block = match self.catch_up {
+                Some(ref mut catch_up) => {
+                    catch_up.blocks = catch_up.blocks.split_off(&height);
+                    catch_up.blocks.remove(&height)
+                }
+                None => None,
+            };
+            match block {
+                // If the block is not committed, e.g., because its transactions are
+                // unknown, the loop stops since the height does not change.
+                Some(block) => self.handle_block(&block).log_error(),
+                None => break,
+            }
+        }
+
+        if let Some(ref mut catch_up) = self.catch_up {
+            catch_up.applying = false;
+        }
+    }
+
+    fn has_block_ahead(&self, height: Height) -> bool {
+        self.catch_up
+            .as_ref()
+            .map_or(false, |catch_up| catch_up.blocks.contains_key(&height))
+    }
+
+    fn set_catching_up(&mut self, active: bool, lag: u64) {
+        let height = self.state.height();
+        if let Some(ref mut catch_up) = self.catch_up {
+            if catch_up.active == active {
+                return;
+            }
+            catch_up.active = active;
+            if active {
+                info!("Entering the catch-up mode, height={}, lag={}", height, lag);
+            } else {
+                info!("Leaving the catch-up mode, height={}", height);
+                catch_up.blocks.clear();
+            }
+        }
+    }
+}
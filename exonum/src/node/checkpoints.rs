@@ -0,0 +1,198 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background creation of database checkpoints.
+
+use failure::Error;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use super::CheckpointConfig;
+use crate::blockchain::Schema;
+use crate::helpers::Height;
+use exonum_merkledb::Database;
+
+/// Prefix of the checkpoint directory names.
+const CHECKPOINT_PREFIX: &str = "checkpoint-";
+/// Name of the directory used for the checkpoint being created.
+const PENDING_CHECKPOINT: &str = "pending-checkpoint";
+
+/// Handle to the thread creating database checkpoints.
+///
+/// The thread is stopped once the handle is dropped.
+pub(crate) struct Checkpointer {
+    interval: u64,
+    sender: mpsc::Sender<Height>,
+}
+
+impl Checkpointer {
+    /// Spawns a thread creating checkpoints of the given database.
+    pub fn new(db: Arc<dyn Database>, config: CheckpointConfig) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let interval = config.interval;
+        let worker = CheckpointWorker { db, config };
+        thread::spawn(move || worker.run(&receiver));
+        Self { interval, sender }
+    }
+
+    /// Notifies the checkpointer that the block at the specified height was committed.
+    pub fn block_committed(&self, height: Height) {
+        if height.0 % self.interval == 0 && self.sender.send(height).is_err() {
+            error!(
+                "Checkpoint thread is stopped, checkpoint for height {} is skipped",
+                height
+            );
+        }
+    }
+}
+
+struct CheckpointWorker {
+    db: Arc<dyn Database>,
+    config: CheckpointConfig,
+}
+
+impl CheckpointWorker {
+    fn run(&self, receiver: &mpsc::Receiver<Height>) {
+        while let Ok(mut height) = receiver.recv() {
+            // If checkpoints are created slower than blocks are committed,
+            // only the most recent request is served.
+            while let Ok(next) = receiver.try_recv() {
+                height = next;
+            }
+
+            match self.create_checkpoint() {
+                Ok(path) => info!(
+                    "Created database checkpoint {} requested at height {}",
+                    path.display(),
+                    height
+                ),
+                Err(e) => error!("Failed to create database checkpoint: {}", e),
+            }
+        }
+    }
+
+    fn create_checkpoint(&self) -> Result<PathBuf, Error> {
+        let directory = &self.config.directory;
+        fs::create_dir_all(directory)?;
+        let pending_path = directory.join(PENDING_CHECKPOINT);
+        if pending_path.exists() {
+            // Leftover of the interrupted checkpoint.
+            fs::remove_dir_all(&pending_path)?;
+        }
+        self.db.create_checkpoint(&pending_path)?;
+
+        // New blocks may be committed while the checkpoint is created,
+        // so the actual height is read from the checkpoint itself.
+        let height = Schema::new(&self.db.checkpoint_snapshot(&pending_path)?).height();
+        let path = directory.join(format!("{}{:020}", CHECKPOINT_PREFIX, height.0));
+        if path.exists() {
+            fs::remove_dir_all(&pending_path)?;
+        } else {
+            fs::rename(&pending_path, &path)?;
+        }
+
+        self.remove_outdated_checkpoints()?;
+        Ok(path)
+    }
+
+    fn remove_outdated_checkpoints(&self) -> Result<(), Error> {
+        let checkpoints = list_checkpoints(&self.config.directory)?;
+        let outdated = checkpoints
+            .len()
+            .saturating_sub(self.config.keep_checkpoints);
+        for path in &checkpoints[..outdated] {
+            fs::remove_dir_all(path)?;
+            info!("Removed outdated database checkpoint {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Returns the paths of the checkpoints in the given directory from the oldest to the newest.
+pub(crate) fn list_checkpoints(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut checkpoints = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .map_or(false, |name| name.starts_with(CHECKPOINT_PREFIX))
+        })
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    // Heights in the names are zero-padded, so the lexicographic order is chronological.
+    checkpoints.sort();
+    Ok(checkpoints)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::crypto::Hash;
+    use exonum_merkledb::{DbOptions, RocksDB};
+
+    fn commit_block(db: &dyn Database) {
+        let fork = db.fork();
+        Schema::new(&fork)
+            .block_hashes_by_height()
+            .push(Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoints_retention() {
+        let dir = TempDir::new().unwrap();
+        let db = Arc::new(RocksDB::open(dir.path().join("db"), &DbOptions::default()).unwrap());
+        let worker = CheckpointWorker {
+            db: Arc::clone(&db),
+            config: CheckpointConfig {
+                directory: dir.path().join("checkpoints"),
+                interval: 1,
+                keep_checkpoints: 2,
+            },
+        };
+
+        for _ in 0..3 {
+            commit_block(&*db);
+            worker.create_checkpoint().unwrap();
+        }
+
+        let mut checkpoints = fs::read_dir(dir.path().join("checkpoints"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        checkpoints.sort();
+        assert_eq!(
+            checkpoints,
+            vec![
+                format!("{}{:020}", CHECKPOINT_PREFIX, 1),
+                format!("{}{:020}", CHECKPOINT_PREFIX, 2),
+            ]
+        );
+
+        let checkpoint = RocksDB::open(
+            dir.path().join("checkpoints").join(&checkpoints[1]),
+            &DbOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(Schema::new(&checkpoint.snapshot()).height(), Height(2));
+    }
+}
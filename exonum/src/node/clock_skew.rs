@@ -0,0 +1,110 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Estimation of the clock skew between the node and its peers.
+
+use chrono::{DateTime, Utc};
+
+use std::collections::BTreeMap;
+
+use super::NodeHandler;
+use crate::api::node::public::system::{ClockSkewInfo, PeerClockSkew};
+use crate::crypto::PublicKey;
+use crate::helpers::Milliseconds;
+
+/// Latest offsets of the clocks of the peers relative to the local clock.
+#[derive(Debug, Default)]
+pub(crate) struct ClockSkewEstimator {
+    /// Offsets in milliseconds, positive if the clock of the peer is ahead.
+    offsets: BTreeMap<PublicKey, i64>,
+    /// Whether the estimated skew of the local clock exceeds the maximal one.
+    exceeded: bool,
+}
+
+impl ClockSkewEstimator {
+    /// Returns the median of the offsets of the peers taken with the opposite sign,
+    /// that is, the offset of the local clock relative to the peers.
+    fn skew(&self) -> Option<i64> {
+        if self.offsets.is_empty() {
+            return None;
+        }
+        let mut offsets: Vec<i64> = self.offsets.values().cloned().collect();
+        offsets.sort();
+        Some(-offsets[offsets.len() / 2])
+    }
+}
+
+impl NodeHandler {
+    /// Returns the maximal clock skew which is not reported, equal to the half
+    /// of the first round timeout.
+    pub(crate) fn max_clock_skew(&self) -> Milliseconds {
+        self.first_round_timeout() / 2
+    }
+
+    /// Observes the time of a message created by the peer just before sending, such as
+    /// `Connect` or `Precommit`. The observed offset of the peer clock includes
+    /// the network delay of the message.
+    pub(crate) fn observe_peer_time(&mut self, peer: PublicKey, time: DateTime<Utc>) {
+        if peer == *self.state.consensus_public_key() {
+            return;
+        }
+        let now: DateTime<Utc> = self.system_state.current_time().into();
+        let offset = time.signed_duration_since(now).num_milliseconds();
+        let max_skew = self.max_clock_skew();
+
+        let previous = self.clock_skew.offsets.insert(peer, offset);
+        if offset.abs() as u64 > max_skew
+            && previous.map_or(true, |previous| previous.abs() as u64 <= max_skew)
+        {
+            warn!(
+                "The clock of peer {} differs from the local one by {} ms, which exceeds {} ms",
+                peer, offset, max_skew
+            );
+        }
+
+        let skew = self.clock_skew.skew().unwrap_or(0);
+        let exceeded = skew.abs() as u64 > max_skew;
+        if exceeded && !self.clock_skew.exceeded {
+            warn!(
+                "The local clock differs from the clocks of the peers by {} ms, which exceeds \
+                 {} ms and may cause round timeouts",
+                skew, max_skew
+            );
+        }
+        self.clock_skew.exceeded = exceeded;
+        metric!("node.clock_skew", skew);
+
+        self.api_state.set_clock_skew(self.clock_skew_info());
+    }
+
+    /// Forgets the clock offset of the disconnected peer.
+    pub(crate) fn forget_peer_time(&mut self, peer: &PublicKey) {
+        if self.clock_skew.offsets.remove(peer).is_some() {
+            self.api_state.set_clock_skew(self.clock_skew_info());
+        }
+    }
+
+    fn clock_skew_info(&self) -> ClockSkewInfo {
+        ClockSkewInfo {
+            skew: self.clock_skew.skew(),
+            max_skew: self.max_clock_skew(),
+            peers: self
+                .clock_skew
+                .offsets
+                .iter()
+                .map(|(&public_key, &offset)| PeerClockSkew { public_key, offset })
+                .collect(),
+        }
+    }
+}
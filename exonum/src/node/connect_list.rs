@@ -14,7 +14,11 @@
 
 //! Mapping between peers public keys and IP-addresses.
 
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    iter,
+    net::{IpAddr, SocketAddr},
+};
 
 use crate::crypto::PublicKey;
 use crate::node::{ConnectInfo, ConnectListConfig};
@@ -24,12 +28,29 @@ use crate::node::{ConnectInfo, ConnectListConfig};
 pub struct PeerAddress {
     /// External address of the peer hostname:port.
     pub address: String,
+    /// Alternative addresses of the peer, such as its IPv6 address, which are tried
+    /// if the peer cannot be reached at the main address.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_addresses: Vec<String>,
 }
 
 impl PeerAddress {
     /// New unresolved address.
     pub fn new(address: String) -> Self {
-        PeerAddress { address }
+        Self::with_fallbacks(address, Vec::new())
+    }
+
+    /// New unresolved address with the given alternative addresses.
+    pub fn with_fallbacks(address: String, fallback_addresses: Vec<String>) -> Self {
+        PeerAddress {
+            address,
+            fallback_addresses,
+        }
+    }
+
+    /// Returns all the candidate addresses of the peer, starting with the main one.
+    pub fn addresses(&self) -> impl Iterator<Item = &str> {
+        iter::once(self.address.as_str()).chain(self.fallback_addresses.iter().map(String::as_str))
     }
 }
 
@@ -47,7 +68,12 @@ impl ConnectList {
         let peers: BTreeMap<PublicKey, PeerAddress> = config
             .peers
             .into_iter()
-            .map(|peer| (peer.public_key, PeerAddress::new(peer.address)))
+            .map(|peer| {
+                (
+                    peer.public_key,
+                    PeerAddress::with_fallbacks(peer.address, peer.fallback_addresses),
+                )
+            })
             .collect();
 
         ConnectList { peers }
@@ -60,7 +86,19 @@ impl ConnectList {
 
     /// Check if we allow to connect to `address`.
     pub fn is_address_allowed(&self, address: &str) -> bool {
-        self.peers.values().any(|a| a.address == address)
+        self.peers
+            .values()
+            .any(|a| a.addresses().any(|candidate| candidate == address))
+    }
+
+    /// Returns `true` if one of the candidate addresses of a peer has the given IP address.
+    /// The addresses specified with a hostname are not resolved, so they do not match.
+    pub fn is_ip_listed(&self, ip: &IpAddr) -> bool {
+        self.peers.values().any(|a| {
+            a.addresses()
+                .filter_map(|candidate| candidate.parse::<SocketAddr>().ok())
+                .any(|candidate| candidate.ip() == *ip)
+        })
     }
 
     /// Get peer address with public key.
@@ -70,13 +108,31 @@ impl ConnectList {
 
     /// Adds peer to the ConnectList.
     pub fn add(&mut self, peer: ConnectInfo) {
-        self.peers
-            .insert(peer.public_key, PeerAddress::new(peer.address));
+        self.peers.insert(
+            peer.public_key,
+            PeerAddress::with_fallbacks(peer.address, peer.fallback_addresses),
+        );
     }
 
-    /// Update peer address.
+    /// Update peer address. The address replaces the main address of the peer
+    /// unless it is already one of its candidate addresses.
     pub fn update_peer(&mut self, public_key: &PublicKey, address: String) {
-        self.peers.insert(*public_key, PeerAddress::new(address));
+        match self.peers.get_mut(public_key) {
+            Some(peer) => {
+                if !peer.addresses().any(|candidate| candidate == address) {
+                    peer.address = address;
+                }
+            }
+            None => {
+                self.peers.insert(*public_key, PeerAddress::new(address));
+            }
+        }
+    }
+
+    /// Removes peer from the ConnectList. Returns the address of the peer
+    /// if it was in the list.
+    pub fn remove(&mut self, public_key: &PublicKey) -> Option<PeerAddress> {
+        self.peers.remove(public_key)
     }
 }
 
@@ -127,11 +183,13 @@ mod test {
         connect_list.add(ConnectInfo {
             public_key: regular[0],
             address: address.clone(),
+            fallback_addresses: Vec::new(),
         });
         check_in_connect_list(&connect_list, &regular, &[0], &[1, 2, 3]);
         connect_list.add(ConnectInfo {
             public_key: regular[2],
             address: address.clone(),
+            fallback_addresses: Vec::new(),
         });
         check_in_connect_list(&connect_list, &regular, &[0, 2], &[1, 3]);
 
@@ -159,6 +217,7 @@ mod test {
             connect_list.add(ConnectInfo {
                 public_key: *peer,
                 address: address.clone(),
+                fallback_addresses: Vec::new(),
             })
         }
     }
@@ -190,8 +249,70 @@ mod test {
         connect_list.add(ConnectInfo {
             public_key,
             address: address.clone(),
+            fallback_addresses: Vec::new(),
         });
         assert!(connect_list.is_address_allowed(&address));
     }
 
+    #[test]
+    fn test_ip_listed() {
+        let (public_key, _) = gen_keypair();
+        let mut connect_list = ConnectList::default();
+        connect_list.add(ConnectInfo {
+            public_key,
+            address: "127.0.0.1:80".to_owned(),
+            fallback_addresses: vec!["[::1]:80".to_owned(), "example.com:80".to_owned()],
+        });
+
+        assert!(connect_list.is_ip_listed(&"127.0.0.1".parse().unwrap()));
+        assert!(connect_list.is_ip_listed(&"::1".parse().unwrap()));
+        assert!(!connect_list.is_ip_listed(&"127.0.0.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_remove() {
+        let regular = make_keys(REGULAR_PEERS, 4);
+        let mut connect_list = ConnectList::default();
+        add_to_connect_list(&mut connect_list, &regular);
+        check_in_connect_list(&connect_list, &regular, &[0, 1, 2, 3], &[]);
+
+        assert!(connect_list.remove(&regular[1]).is_some());
+        check_in_connect_list(&connect_list, &regular, &[0, 2, 3], &[1]);
+        assert!(connect_list.remove(&regular[1]).is_none());
+        assert_eq!(connect_list.peers.len(), 3);
+    }
+
+    #[test]
+    fn test_fallback_addresses() {
+        let (public_key, _) = gen_keypair();
+        let address = "127.0.0.1:80".to_owned();
+        let fallback = "[::1]:80".to_owned();
+
+        let mut connect_list = ConnectList::default();
+        connect_list.add(ConnectInfo {
+            public_key,
+            address: address.clone(),
+            fallback_addresses: vec![fallback.clone()],
+        });
+        assert!(connect_list.is_address_allowed(&address));
+        assert!(connect_list.is_address_allowed(&fallback));
+
+        // The known candidate addresses are kept.
+        connect_list.update_peer(&public_key, fallback.clone());
+        let peer = connect_list.find_address_by_pubkey(&public_key).unwrap();
+        assert_eq!(
+            peer.addresses().collect::<Vec<_>>(),
+            [address.as_str(), fallback.as_str()]
+        );
+
+        // A new address replaces the main one.
+        connect_list.update_peer(&public_key, "example.com:80".to_owned());
+        let peer = connect_list.find_address_by_pubkey(&public_key).unwrap();
+        assert_eq!(
+            peer.addresses().collect::<Vec<_>>(),
+            ["example.com:80", fallback.as_str()]
+        );
+        assert!(!connect_list.is_address_allowed(&address));
+    }
+
 }
@@ -14,8 +14,14 @@
 
 use std::collections::HashSet;
 
-use crate::blockchain::{check_tx, Schema};
-use crate::crypto::{CryptoHash, Hash, PublicKey};
+use crate::api::websocket::MempoolEvent;
+#[cfg(feature = "threshold-precommits")]
+use crate::blockchain::certificate;
+use crate::blockchain::{
+    check_tx, BlockCommitted, DoubleSignEvidence, RejectionReason, Schema, TxCandidate,
+    TxOrderingPolicy,
+};
+use crate::crypto::{CryptoHash, Hash, PublicKey, SignatureScheme};
 use crate::events::InternalRequest;
 use crate::helpers::{Height, Round, ValidatorId};
 use crate::messages::{
@@ -23,8 +29,13 @@ use crate::messages::{
     Prevote, PrevotesRequest, Propose, ProposeRequest, RawTransaction, Signed, SignedMessage,
     TransactionsRequest, TransactionsResponse,
 };
-use crate::node::{NodeHandler, RequestData};
-use exonum_merkledb::Patch;
+use crate::node::metrics::{
+    PRECOMMITS_RECEIVED, PRECOMMITS_SENT, PREVOTES_RECEIVED, PREVOTES_SENT, PROPOSES_RECEIVED,
+    PROPOSES_SENT,
+};
+use crate::node::{NodeHandler, NodeTimeout, RequestData};
+use crate::proto;
+use exonum_merkledb::{BinaryValue, Patch};
 
 // TODO Reduce view invocations. (ECR-171)
 impl NodeHandler {
@@ -68,6 +79,7 @@ impl NodeHandler {
             let validator = msg.validator();
             let round = msg.round();
             self.state.add_queued(msg);
+            self.update_queue_metrics();
             trace!("Trying to reach actual round.");
             if let Some(r) = self.state.update_validator_round(validator, round) {
                 trace!("Scheduling jump to round.");
@@ -93,6 +105,7 @@ impl NodeHandler {
             Some(from),
             self.state.consensus_public_key_of(msg.validator())
         );
+        self.api_state.metrics().increment(&PROPOSES_RECEIVED);
 
         // Check prev_hash
         if msg.prev_hash() != self.state.last_hash() {
@@ -186,12 +199,8 @@ impl NodeHandler {
         if !msg.verify_tx_hash() {
             bail!("Received block has invalid tx_hash, msg={:?}", msg);
         }
-        let precommits: Result<Vec<_>, _> = msg
-            .precommits()
-            .into_iter()
-            .map(Precommit::verify_precommit)
-            .collect();
-        self.verify_precommits(&precommits?, &block_hash, block.height())?;
+        let precommits = self.verify_block_precommits(msg)?;
+        self.verify_precommits(&precommits, &block_hash, block.height())?;
 
         Ok(())
     }
@@ -199,6 +208,9 @@ impl NodeHandler {
     /// Handles the `Block` message. For details see the message documentation.
     // TODO: Write helper function which returns Result. (ECR-123)
     pub fn handle_block(&mut self, msg: &Signed<BlockResponse>) -> Result<(), failure::Error> {
+        if self.save_block_ahead(msg) {
+            return Ok(());
+        }
         self.validate_block_response(&msg)?;
 
         let block = msg.block();
@@ -208,7 +220,7 @@ impl NodeHandler {
             let schema = Schema::new(&snapshot);
             let has_unknown_txs = self
                 .state
-                .create_incomplete_block(&msg, &schema.transactions(), &schema.transactions_pool())
+                .create_incomplete_block(&msg, &schema)
                 .has_unknown_txs();
 
             let known_nodes = self.remove_request(&RequestData::Block(block.height()));
@@ -224,13 +236,9 @@ impl NodeHandler {
                 self.handle_full_block(&msg)?;
             }
         } else {
-            let precommits: Result<Vec<_>, _> = msg
-                .precommits()
-                .into_iter()
-                .map(Precommit::verify_precommit)
-                .collect();
+            let precommits = self.verify_block_precommits(msg)?;
 
-            self.commit(block_hash, precommits?.into_iter(), None);
+            self.commit(block_hash, precommits.into_iter(), None);
             self.request_next_block();
         }
         Ok(())
@@ -300,13 +308,9 @@ impl NodeHandler {
                 block.proposer_id(),
             );
         }
-        let precommits: Result<Vec<_>, _> = msg
-            .precommits()
-            .into_iter()
-            .map(Precommit::verify_precommit)
-            .collect();
+        let precommits = self.verify_block_precommits(msg)?;
 
-        self.commit(block_hash, precommits?.into_iter(), None);
+        self.commit(block_hash, precommits.into_iter(), None);
         self.request_next_block();
         Ok(())
     }
@@ -319,6 +323,18 @@ impl NodeHandler {
             Some(from),
             self.state.consensus_public_key_of(msg.validator())
         );
+        self.api_state.metrics().increment(&PREVOTES_RECEIVED);
+
+        if let Some(other) = self.state.conflicting_prevote(msg) {
+            let evidence = DoubleSignEvidence::new(
+                msg.validator(),
+                msg.height(),
+                msg.round(),
+                other,
+                msg.clone(),
+            );
+            self.record_double_sign(evidence);
+        }
 
         // Add prevote
         let has_consensus = self.state.add_prevote(msg.clone());
@@ -441,6 +457,28 @@ impl NodeHandler {
             Some(from),
             self.state.consensus_public_key_of(msg.validator())
         );
+        self.api_state.metrics().increment(&PRECOMMITS_RECEIVED);
+
+        if !self.verify_threshold_share(msg) {
+            error!(
+                "Received precommit with an invalid threshold signature share, msg={:?}",
+                msg
+            );
+            return;
+        }
+
+        if let Some(other) = self.state.conflicting_precommit(msg) {
+            let evidence = DoubleSignEvidence::new(
+                msg.validator(),
+                msg.height(),
+                msg.round(),
+                other,
+                msg.clone(),
+            );
+            self.record_double_sign(evidence);
+        }
+
+        self.observe_peer_time(from, msg.time());
 
         // Add precommit
         let has_consensus = self.state.add_precommit(msg.clone());
@@ -466,6 +504,17 @@ impl NodeHandler {
         }
     }
 
+    /// Records the evidence of the validator signing conflicting prevotes or precommits.
+    fn record_double_sign(&mut self, evidence: DoubleSignEvidence) {
+        error!(
+            "Validator {} has signed conflicting messages at height {}, round {}",
+            evidence.validator(),
+            evidence.height(),
+            evidence.round()
+        );
+        self.blockchain.save_double_sign_evidence(evidence);
+    }
+
     /// Commits block, so new height is achieved.
     pub fn commit<I: Iterator<Item = Signed<Precommit>>>(
         &mut self,
@@ -474,12 +523,20 @@ impl NodeHandler {
         round: Option<Round>,
     ) {
         trace!("COMMIT {:?}", block_hash);
+        self.observe_commit(round);
+        self.observe_commit_metrics(round);
+        self.observe_round_changes(round);
+        self.record_height_diagnostics(&block_hash);
+        // Maintenance is deferred until the node catches up.
+        let catching_up = self.is_catching_up();
+        // The precommits of the next received block are verified during the merge.
+        let pending_verification = self.start_block_ahead_verification();
 
         // Merge changes into storage
         let (committed_txs, proposer) = {
             let (committed_txs, proposer) = {
                 let block_state = self.state.block_mut(&block_hash).unwrap();
-                let committed_txs = block_state.txs().len();
+                let committed_txs = block_state.txs().clone();
                 let proposer = block_state.proposer_id();
 
                 self.blockchain
@@ -487,12 +544,32 @@ impl NodeHandler {
                         block_state.patch(),
                         block_hash,
                         precommits,
-                        self.state.tx_cache_mut(),
+                        &mut self.state.tx_cache_mut(),
                     )
                     .unwrap();
+                if let Some(pending) = pending_verification {
+                    self.finish_block_ahead_verification(pending);
+                }
+                if !catching_up {
+                    if let Some(ref pruning) = self.pruning {
+                        // Pruning resumes from the first unpruned height, so the blocks
+                        // left after a failure are pruned on the next commit.
+                        if let Err(e) = self.blockchain.prune(pruning) {
+                            error!("Unable to prune blocks, retrying on the next commit: {}", e);
+                        }
+                    }
+                    if let Some(ref checkpointer) = self.checkpointer {
+                        checkpointer.block_committed(self.state.height());
+                    }
+                }
 
                 (committed_txs, proposer)
             };
+            self.state.remove_tx_arrival_times(&committed_txs);
+            for tx_hash in &committed_txs {
+                self.api_state
+                    .broadcast_mempool_event(MempoolEvent::Committed, tx_hash);
+            }
             // Update node state.
             self.state
                 .update_config(Schema::new(&self.blockchain.snapshot()).actual_configuration());
@@ -500,16 +577,54 @@ impl NodeHandler {
             let block_hash = self.blockchain.last_hash();
             self.state
                 .new_height(&block_hash, self.system_state.current_time());
-            (committed_txs, proposer)
+            self.update_node_role();
+            (committed_txs.len(), proposer)
         };
 
         self.api_state.broadcast(&block_hash);
+        self.check_liveness();
+        self.reset_watchdog();
 
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
+        let committed_height = schema.height();
+        for depth in self.api_state.block_subscription_depths() {
+            // The genesis block is not reported.
+            if depth >= committed_height.0 {
+                continue;
+            }
+            let height = Height(committed_height.0 - depth);
+            let block_hash = schema.block_hash_by_height(height).unwrap();
+            let block = schema.blocks().get(&block_hash).unwrap();
+            self.api_state.notify_block_committed(
+                depth,
+                &BlockCommitted {
+                    height,
+                    block_hash,
+                    tx_hashes: schema.block_transactions(height).iter().collect(),
+                    state_hash: *block.state_hash(),
+                },
+            );
+        }
         let pool_len = schema.transactions_pool_len();
+        self.update_height_metrics(pool_len);
 
         metric!("node.mempool", pool_len);
+        let storage = self.blockchain.storage_metrics().info();
+        metric!("storage.reads", storage.reads);
+        metric!("storage.read_bytes", storage.read_bytes);
+        metric!("storage.iterated_entries", storage.iterated_entries);
+        metric!("storage.merges", storage.merges);
+        metric!("storage.failed_merges", storage.failed_merges);
+        metric!("storage.written_entries", storage.written_entries);
+        metric!("storage.written_bytes", storage.written_bytes);
+        metric!("storage.max_merge_bytes", storage.max_merge_bytes);
+        let verification = self.api_state.verification_metrics().info();
+        metric!("node.verification.pending", verification.pending_messages);
+        metric!("node.verification.verified", verification.verified_messages);
+        metric!("node.verification.rejected", verification.rejected_messages);
+        metric!("node.verification.saturations", verification.saturations);
+        metric!("node.verification.pipelined", verification.pipelined_blocks);
 
         let height = self.state.height();
         info!(
@@ -524,6 +639,9 @@ impl NodeHandler {
 
         self.broadcast_status();
         self.add_status_timeout();
+        if !catching_up {
+            self.collect_garbage();
+        }
 
         // Add timeout for first round
         self.add_round_timeout();
@@ -536,6 +654,9 @@ impl NodeHandler {
         for msg in self.state.queued() {
             self.handle_consensus(msg);
         }
+        self.update_queue_metrics();
+
+        self.apply_blocks_ahead();
     }
 
     /// Checks if the transaction is new and adds it to the pool. This may trigger an expedited
@@ -543,22 +664,78 @@ impl NodeHandler {
     pub fn handle_tx(&mut self, msg: Signed<RawTransaction>) -> Result<(), failure::Error> {
         let hash = msg.hash();
 
+        // The network may accept messages larger than the consensus limit,
+        // which still applies to transactions.
+        let max_message_len = self.state.config().consensus.max_message_len as usize;
+        if msg.signed_message().raw().len() > max_message_len {
+            bail!(
+                "Received transaction larger than {} bytes, hash {:?}",
+                max_message_len,
+                hash
+            )
+        }
+
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
 
-        if check_tx(&hash, &schema.transactions(), self.state.tx_cache()) {
+        if check_tx(&hash, &schema, &self.state.tx_cache()) {
             bail!("Received already processed transaction, hash {:?}", hash)
         }
 
-        if let Err(e) = self.blockchain.tx_from_raw(msg.payload().clone()) {
+        if msg.signed_message().signature_scheme() == SignatureScheme::Secp256k1
+            && !self.state.config().secp256k1_signatures
+        {
+            bail!(
+                "Received transaction signed with secp256k1 key, which is not allowed, hash {:?}",
+                hash
+            )
+        }
+
+        if msg.signed_message().is_compressed() {
+            match self.state.config().consensus.max_decompressed_len {
+                Some(max_len) if msg.payload().to_bytes().len() <= max_len as usize => {}
+                Some(max_len) => bail!(
+                    "Received compressed transaction larger than {} bytes, hash {:?}",
+                    max_len,
+                    hash
+                ),
+                None => bail!(
+                    "Received compressed transaction, which is not allowed, hash {:?}",
+                    hash
+                ),
+            }
+        }
+
+        // The payload of the transaction is checked by its service.
+        let converted = if self.state.config().canonical_encoding {
+            ensure!(
+                msg.is_canonical(),
+                "Received transaction which is not encoded canonically, hash {:?}",
+                hash
+            );
+            proto::with_canonical_encoding(|| self.blockchain.tx_from_signed(&msg))
+        } else {
+            self.blockchain.tx_from_signed(&msg)
+        };
+        if let Err(e) = converted {
             error!("Received invalid transaction {:?}, result: {}", msg, e);
             bail!("Received malicious transaction.")
         }
 
         self.state.tx_cache_mut().insert(hash, msg);
+        self.state
+            .add_tx_arrival_time(hash, self.system_state.current_time());
+        self.api_state
+            .broadcast_mempool_event(MempoolEvent::Added, &hash);
+        self.enforce_pool_limits();
 
         if self.state.is_leader() && self.state.round() != Round::zero() {
-            self.maybe_add_propose_timeout();
+            let (height, round) = (self.state.height(), self.state.round());
+            if self.skipped_propose == Some((height, round)) {
+                self.handle_propose_timeout(height, round);
+            } else {
+                self.maybe_add_propose_timeout();
+            }
         }
 
         let full_proposes = self.state.check_incomplete_proposes(hash);
@@ -596,9 +773,13 @@ impl NodeHandler {
                 msg.author().to_hex()
             )
         }
-        for tx in msg.transactions() {
-            self.execute_later(InternalRequest::VerifyMessage(tx));
-        }
+        let network_id = self.state.network_id();
+        let max_len = self.state.config().consensus.max_decompressed_len;
+        self.execute_later(InternalRequest::VerifyMessages(
+            msg.transactions(),
+            network_id,
+            max_len,
+        ));
         Ok(())
     }
 
@@ -632,6 +813,8 @@ impl NodeHandler {
 
     // Try to process consensus messages from the future round.
     fn process_new_round(&mut self) {
+        self.collect_garbage();
+
         if self.state.is_validator() {
             // Send prevote if we are locked or propose if we are leader
             if let Some(hash) = self.state.locked_propose() {
@@ -661,6 +844,8 @@ impl NodeHandler {
             return;
         }
         warn!("ROUND TIMEOUT height={}, round={}", height, round);
+        self.observe_round_timeout_metrics();
+        self.observe_round_timeout_diagnostics(round);
 
         // Update state to new round
         self.state.new_round();
@@ -681,6 +866,7 @@ impl NodeHandler {
         if round != self.state.round() {
             return;
         }
+        self.observe_propose_timeout_diagnostics(round);
         if self.state.locked_propose().is_some() {
             return;
         }
@@ -690,21 +876,40 @@ impl NodeHandler {
             }
             let round = self.state.round();
 
+            if let Some(paced_until) = self.paced_until() {
+                trace!("Delay propose until {:?}", paced_until);
+                // Unlike the idle interval, the pacing is not cut short by new transactions.
+                self.add_timeout(NodeTimeout::Propose(height, round), paced_until);
+                return;
+            }
+            if let Some(idle_until) = self.idle_until() {
+                trace!("Skip empty propose until {:?}", idle_until);
+                // Propose is retried once a transaction arrives or the idle interval passes.
+                self.skipped_propose = Some((height, round));
+                self.add_timeout(NodeTimeout::Propose(height, round), idle_until);
+                return;
+            }
+            self.skipped_propose = None;
+
             let txs = self.get_txs_for_propose();
 
-            let propose = self.sign_message(Propose::new(
+            let propose = match self.sign_message(Propose::new(
                 validator_id,
                 self.state.height(),
                 round,
                 self.state.last_hash(),
                 &txs,
-            ));
+            )) {
+                Some(propose) => propose,
+                None => return,
+            };
 
             // Put our propose to the consensus messages cache
             self.blockchain.save_message(round, propose.clone());
 
             trace!("Broadcast propose: {:?}", propose);
             self.broadcast(propose.clone());
+            self.api_state.metrics().increment(&PROPOSES_SENT);
 
             self.allow_expedited_propose = true;
 
@@ -719,7 +924,26 @@ impl NodeHandler {
         }
     }
 
-    fn get_txs_for_propose(&self) -> Vec<Hash> {
+    fn get_txs_for_propose(&mut self) -> Vec<Hash> {
+        let tx_ordering = &self.state.consensus_config().tx_ordering;
+        let txs = if let Some(scheduler) = self.blockchain.tx_scheduler() {
+            self.get_ordered_txs_for_propose(scheduler)
+        } else if tx_ordering.is_unordered() {
+            self.get_unordered_txs_for_propose()
+        } else {
+            self.get_ordered_txs_for_propose(tx_ordering)
+        };
+        let txs = if self.state.consensus_config().exclude_failing_txs {
+            self.exclude_failing_txs(txs)
+        } else {
+            txs
+        };
+        self.limit_block_size(txs)
+    }
+
+    /// Chooses the transactions for the propose in an unspecified order, taking
+    /// the cached transactions first.
+    fn get_unordered_txs_for_propose(&self) -> Vec<Hash> {
         let txs_cache_len = self.state.tx_cache_len() as u64;
         let tx_block_limit = self.txs_block_limit();
 
@@ -746,6 +970,153 @@ impl NodeHandler {
         cache_txs
     }
 
+    /// Chooses the transactions for the propose among all the uncommitted transactions
+    /// with the given ordering policy.
+    fn get_ordered_txs_for_propose(&self, tx_ordering: &dyn TxOrderingPolicy) -> Vec<Hash> {
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let transactions = schema.transactions();
+        let arrival_times = self.state.tx_arrival_times();
+        let to_candidate = |hash: Hash, tx: &Signed<RawTransaction>| {
+            let candidate = TxCandidate {
+                hash,
+                service_id: tx.payload().service_id(),
+                author: tx.author(),
+            };
+            (arrival_times.get(&hash).cloned(), candidate)
+        };
+
+        let mut candidates = self
+            .state
+            .tx_cache()
+            .iter()
+            .map(|(hash, tx)| to_candidate(*hash, tx))
+            .collect::<Vec<_>>();
+        candidates.extend(
+            schema
+                .transactions_pool()
+                .iter()
+                .filter_map(|hash| transactions.get(&hash).map(|tx| to_candidate(hash, &tx))),
+        );
+        info!("LEADER: candidates = {}", candidates.len());
+
+        // Transactions without arrival time were received before the node restart,
+        // so they are the earliest ones.
+        candidates.sort_by_key(|&(arrival_time, _)| arrival_time);
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect();
+        let mut candidate_hashes: HashSet<_> =
+            candidates.iter().map(|candidate| candidate.hash).collect();
+
+        // The transactions which are not among the candidates or are repeated would make
+        // the propose invalid, so they are skipped.
+        let limit = self.txs_block_limit() as usize;
+        let mut ordered = tx_ordering.order(candidates, limit);
+        ordered.retain(|hash| candidate_hashes.remove(hash));
+        ordered.truncate(limit);
+        ordered
+    }
+
+    /// Leaves out the transactions which fail if executed in the given order. Evicts
+    /// the transactions which have failed `max_failed_dry_runs` times in a row.
+    fn exclude_failing_txs(&mut self, txs: Vec<Hash>) -> Vec<Hash> {
+        let count = txs.len();
+        let limit = self.txs_block_limit() as usize;
+        let (txs, failed) = self
+            .blockchain
+            .dry_run_transactions(txs, limit, &self.state.tx_cache());
+        if txs.len() < count {
+            info!(
+                "LEADER: excluded {} failing transactions",
+                count - txs.len()
+            );
+        }
+        if let Some(max_failures) = self.state.consensus_config().max_failed_dry_runs {
+            let exhausted = self.state.record_failed_dry_runs(&failed, max_failures);
+            self.evict_failing_txs(exhausted);
+        }
+        txs
+    }
+
+    /// Evicts the transactions which keep failing the execution in advance from the pool,
+    /// and records them in the rejected transactions log. The transactions which are
+    /// a part of the current height proposes are kept.
+    fn evict_failing_txs(&mut self, mut tx_hashes: Vec<Hash>) {
+        tx_hashes.retain(|tx_hash| !self.state.is_tx_in_proposes(tx_hash));
+        if tx_hashes.is_empty() {
+            return;
+        }
+
+        let fork = self.blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            for tx_hash in &tx_hashes {
+                // Transactions from the cache are not flushed into the persistent pool yet.
+                if self.state.tx_cache_mut().remove(tx_hash).is_none() {
+                    schema.reject_transaction(tx_hash);
+                }
+                schema.log_rejected_transaction(tx_hash, RejectionReason::Failed);
+            }
+        }
+        self.blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to evict transactions from the persistent pool");
+        self.state.remove_tx_arrival_times(&tx_hashes);
+
+        for tx_hash in &tx_hashes {
+            self.api_state
+                .broadcast_mempool_event(MempoolEvent::Removed, tx_hash);
+        }
+        warn!(
+            "LEADER: evicted {} repeatedly failing transactions from the pool",
+            tx_hashes.len()
+        );
+    }
+
+    /// Leaves the transactions fitting into `max_block_size`. The transactions exceeding
+    /// the remaining size of the block are skipped, so the smaller ones following them
+    /// may still be included.
+    fn limit_block_size(&self, txs: Vec<Hash>) -> Vec<Hash> {
+        let max_block_size = match self.state.consensus_config().max_block_size {
+            Some(max_block_size) => max_block_size,
+            None => return txs,
+        };
+
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let transactions = schema.transactions();
+        let tx_size = |hash: &Hash| match self.state.tx_cache().get(hash) {
+            Some(tx) => tx.signed_message().raw().len() as u64,
+            None => transactions
+                .get(hash)
+                .map_or(0, |tx| tx.signed_message().raw().len() as u64),
+        };
+
+        let mut block_size = 0;
+        let total_count = txs.len();
+        let txs: Vec<Hash> = txs
+            .into_iter()
+            .filter(|hash| {
+                let size = tx_size(hash);
+                if block_size + size > max_block_size {
+                    return false;
+                }
+                block_size += size;
+                true
+            })
+            .collect();
+        if txs.len() < total_count {
+            info!(
+                "LEADER: skipped {} transactions exceeding max_block_size, block size = {}",
+                total_count - txs.len(),
+                block_size
+            );
+        }
+        txs
+    }
+
     /// Handles request timeout by sending the corresponding request message to a peer.
     pub fn handle_request_timeout(&mut self, data: &RequestData, peer: Option<PublicKey>) {
         trace!("HANDLE REQUEST TIMEOUT");
@@ -753,14 +1124,14 @@ impl NodeHandler {
         if let Some(peer) = self.state.retry(data, peer) {
             self.add_request_timeout(data.clone(), Some(peer));
 
-            let message: SignedMessage = match *data {
+            let message: Option<SignedMessage> = match *data {
                 RequestData::Propose(ref propose_hash) => self
                     .sign_message(ProposeRequest::new(
                         &peer,
                         self.state.height(),
                         propose_hash,
                     ))
-                    .into(),
+                    .map(Into::into),
                 RequestData::ProposeTransactions(ref propose_hash) => {
                     let txs: Vec<_> = self
                         .state
@@ -771,11 +1142,11 @@ impl NodeHandler {
                         .cloned()
                         .collect();
                     self.sign_message(TransactionsRequest::new(&peer, &txs))
-                        .into()
-                }
-                RequestData::PoolTransactions => {
-                    self.sign_message(PoolTransactionsRequest::new(peer)).into()
+                        .map(Into::into)
                 }
+                RequestData::PoolTransactions => self
+                    .sign_message(PoolTransactionsRequest::new(peer))
+                    .map(Into::into),
                 RequestData::BlockTransactions => {
                     let txs: Vec<_> = match self.state.incomplete_block() {
                         Some(incomplete_block) => {
@@ -784,7 +1155,7 @@ impl NodeHandler {
                         None => return,
                     };
                     self.sign_message(TransactionsRequest::new(&peer, &txs))
-                        .into()
+                        .map(Into::into)
                 }
                 RequestData::Prevotes(round, ref propose_hash) => self
                     .sign_message(PrevotesRequest::new(
@@ -794,13 +1165,15 @@ impl NodeHandler {
                         propose_hash,
                         self.state.known_prevotes(round, propose_hash),
                     ))
-                    .into(),
-                RequestData::Block(height) => {
-                    self.sign_message(BlockRequest::new(&peer, height)).into()
-                }
+                    .map(Into::into),
+                RequestData::Block(height) => self
+                    .sign_message(BlockRequest::new(&peer, height))
+                    .map(Into::into),
             };
-            trace!("Send request {:?} to peer {:?}", data, peer);
-            self.send_to_peer(peer, message);
+            if let Some(message) = message {
+                trace!("Send request {:?} to peer {:?}", data, peer);
+                self.send_to_peer(peer, message);
+            }
         }
     }
 
@@ -871,6 +1244,9 @@ impl NodeHandler {
     /// Requests a block for the next height from all peers with a bigger height. Called when the
     /// node tries to catch up with other nodes' height.
     pub fn request_next_block(&mut self) {
+        if self.request_blocks_ahead() {
+            return;
+        }
         // TODO: Randomize next peer. (ECR-171)
         let heights: Vec<_> = self
             .state
@@ -892,6 +1268,7 @@ impl NodeHandler {
     /// Removes the specified request from the pending request list.
     pub fn remove_request(&mut self, data: &RequestData) -> HashSet<PublicKey> {
         // TODO: Clear timeout. (ECR-171)
+        self.request_completed(data);
         self.state.remove_request(data)
     }
 
@@ -901,14 +1278,20 @@ impl NodeHandler {
             .state
             .validator_id()
             .expect("called broadcast_prevote in Auditor node.");
+        if !self.record_signed_prevote(round, propose_hash) {
+            return false;
+        }
         let locked_round = self.state.locked_round();
-        let prevote = self.sign_message(Prevote::new(
+        let prevote = match self.sign_message(Prevote::new(
             validator_id,
             self.state.height(),
             round,
             propose_hash,
             locked_round,
-        ));
+        )) {
+            Some(prevote) => prevote,
+            None => return false,
+        };
         let has_majority_prevotes = self.state.add_prevote(prevote.clone());
 
         // save outgoing Prevote to the consensus messages cache before broadcast
@@ -917,6 +1300,7 @@ impl NodeHandler {
 
         trace!("Broadcast prevote: {:?}", prevote);
         self.broadcast(prevote);
+        self.api_state.metrics().increment(&PREVOTES_SENT);
 
         has_majority_prevotes
     }
@@ -927,14 +1311,24 @@ impl NodeHandler {
             .state
             .validator_id()
             .expect("called broadcast_precommit in Auditor node.");
-        let precommit = self.sign_message(Precommit::new(
-            validator_id,
-            self.state.height(),
-            round,
-            propose_hash,
-            block_hash,
-            self.system_state.current_time().into(),
-        ));
+        if !self.record_signed_precommit(round, block_hash) {
+            return;
+        }
+        let threshold_share = self.threshold_share(block_hash);
+        let precommit = match self.sign_message(
+            Precommit::new(
+                validator_id,
+                self.state.height(),
+                round,
+                propose_hash,
+                block_hash,
+                self.system_state.current_time().into(),
+            )
+            .with_threshold_share(threshold_share),
+        ) {
+            Some(precommit) => precommit,
+            None => return,
+        };
         self.state.add_precommit(precommit.clone());
 
         // Put our Precommit to the consensus cache before broadcast
@@ -942,6 +1336,50 @@ impl NodeHandler {
 
         trace!("Broadcast precommit: {:?}", precommit);
         self.broadcast(precommit);
+        self.api_state.metrics().increment(&PRECOMMITS_SENT);
+    }
+
+    /// Signs the block hash with the share of the threshold key if the validators use
+    /// threshold signatures. Otherwise, returns an empty share.
+    #[cfg(feature = "threshold-precommits")]
+    fn threshold_share(&self, block_hash: &Hash) -> Vec<u8> {
+        match self.threshold_key {
+            Some(ref key) if self.state.consensus_config().threshold_keys.is_some() => {
+                key.sign(block_hash).to_bytes().to_vec()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    #[cfg(not(feature = "threshold-precommits"))]
+    fn threshold_share(&self, _block_hash: &Hash) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Checks that the threshold signature share of the block hash contained
+    /// in the precommit is valid if the validators use threshold signatures.
+    ///
+    /// Precommits without a share are accepted, since they are still authenticated
+    /// by the consensus key; the block is then certified by the precommits only
+    /// if the other shares are not enough to combine the certificate.
+    #[cfg(feature = "threshold-precommits")]
+    fn verify_threshold_share(&self, precommit: &Precommit) -> bool {
+        if precommit.threshold_share().is_empty() {
+            return true;
+        }
+        match self.state.consensus_config().threshold_keys {
+            Some(ref keys) => keys
+                .key_set()
+                .ok()
+                .and_then(|key_set| certificate::verify_share(&key_set, precommit))
+                .is_some(),
+            None => true,
+        }
+    }
+
+    #[cfg(not(feature = "threshold-precommits"))]
+    fn verify_threshold_share(&self, _precommit: &Precommit) -> bool {
+        true
     }
 
     /// Checks that pre-commits count is correct and calls `verify_precommit` for each of them.
@@ -0,0 +1,105 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording of the consensus diagnostics of the committed heights.
+
+use super::{DiagnosticsConfig, NodeHandler};
+use crate::blockchain::{HeightDiagnostics, ProposeDiagnostics};
+use crate::crypto::Hash;
+use crate::helpers::Round;
+
+/// Timeouts fired at the current height.
+#[derive(Debug)]
+pub(crate) struct Diagnostics {
+    config: DiagnosticsConfig,
+    round_timeouts: Vec<Round>,
+    propose_timeouts: Vec<Round>,
+}
+
+impl Diagnostics {
+    pub fn new(config: DiagnosticsConfig) -> Self {
+        Self {
+            config,
+            round_timeouts: Vec::new(),
+            propose_timeouts: Vec::new(),
+        }
+    }
+}
+
+impl NodeHandler {
+    /// Notes the round finished by the timeout at the current height.
+    pub(crate) fn observe_round_timeout_diagnostics(&mut self, round: Round) {
+        if let Some(ref mut diagnostics) = self.diagnostics {
+            diagnostics.round_timeouts.push(round);
+        }
+    }
+
+    /// Notes the propose timeout fired at the current height.
+    pub(crate) fn observe_propose_timeout_diagnostics(&mut self, round: Round) {
+        if let Some(ref mut diagnostics) = self.diagnostics {
+            diagnostics.propose_timeouts.push(round);
+        }
+    }
+
+    /// Saves the diagnostics of the current height at which the block with the given hash
+    /// is committed. Should be called before the node moves to the next height.
+    /// Does nothing if the diagnostics are disabled.
+    pub(crate) fn record_height_diagnostics(&mut self, block_hash: &Hash) {
+        let (keep_heights, round_timeouts, propose_timeouts) = match self.diagnostics {
+            Some(ref mut diagnostics) => (
+                diagnostics.config.keep_heights,
+                diagnostics.round_timeouts.split_off(0),
+                diagnostics.propose_timeouts.split_off(0),
+            ),
+            None => return,
+        };
+
+        let round = self.state.round();
+        let mut proposes: Vec<_> = self
+            .state
+            .proposes()
+            .map(|propose_state| {
+                let propose = propose_state.message();
+                ProposeDiagnostics {
+                    validator: propose.validator(),
+                    round: propose.round(),
+                    hash: propose.hash(),
+                    tx_count: propose.transactions().len() as u32,
+                }
+            })
+            .collect();
+        proposes.sort_by_key(|propose| (propose.round, propose.validator));
+
+        let diagnostics = HeightDiagnostics {
+            height: self.state.height(),
+            round,
+            proposer: self
+                .state
+                .block(block_hash)
+                .expect("Committed block is not found")
+                .proposer_id(),
+            leaders: Round::first()
+                .iter_to(round.next())
+                .map(|round| self.state.leader(round))
+                .collect(),
+            proposes,
+            round_timeouts,
+            propose_timeouts,
+            started_at: self.state.height_start_time().into(),
+            committed_at: self.system_state.current_time().into(),
+        };
+        self.blockchain
+            .save_height_diagnostics(diagnostics, keep_heights);
+    }
+}
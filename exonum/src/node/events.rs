@@ -12,8 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{ConnectListConfig, ExternalMessage, NodeHandler, NodeTimeout};
-use crate::blockchain::{get_tx, Schema};
+use std::iter;
+
+use super::{ExternalMessage, NodeHandler, NodeTimeout, RemoveTransactionError};
+use crate::api::websocket::MempoolEvent;
+use crate::blockchain::{get_tx, RejectionReason, Schema};
+use crate::crypto::Hash;
 use crate::events::{
     error::LogError, Event, EventHandler, InternalEvent, InternalRequest, NetworkEvent,
 };
@@ -35,9 +39,15 @@ impl NodeHandler {
     fn handle_internal_event(&mut self, event: InternalEvent) {
         match event {
             InternalEvent::Timeout(timeout) => self.handle_timeout(timeout),
-            InternalEvent::JumpToRound(height, round) => self.handle_new_round(height, round),
+            InternalEvent::JumpToRound(height, round) => {
+                // The round is reached again once the queued messages are processed.
+                if !self.is_paused() {
+                    self.handle_new_round(height, round)
+                }
+            }
             InternalEvent::Shutdown => panic!("Shutdown should be processed in the event loop"),
             InternalEvent::MessageVerified(msg) => self.handle_message(*msg),
+            InternalEvent::MessageRejected(address) => self.observe_peer_error(address),
         }
     }
 
@@ -46,8 +56,13 @@ impl NodeHandler {
             NetworkEvent::PeerConnected(peer, connect) => self.handle_connected(&peer, connect),
             NetworkEvent::PeerDisconnected(peer) => self.handle_disconnected(peer),
             NetworkEvent::UnableConnectToPeer(peer) => self.handle_unable_to_connect(peer),
-            NetworkEvent::MessageReceived(raw) => {
-                self.execute_later(InternalRequest::VerifyMessage(raw))
+            NetworkEvent::MessageReceived(address, raw) => {
+                self.observe_peer_message(address);
+                let network_id = self.state.network_id();
+                let max_len = self.state.config().consensus.max_decompressed_len;
+                self.execute_later(InternalRequest::VerifyMessage(
+                    address, raw, network_id, max_len,
+                ))
             }
         }
     }
@@ -55,23 +70,18 @@ impl NodeHandler {
     fn handle_api_event(&mut self, event: ExternalMessage) {
         match event {
             ExternalMessage::Transaction(tx) => {
-                self.handle_incoming_tx(tx);
+                if let Some(tx) = self.defer_paused_transaction(tx) {
+                    self.handle_incoming_tx(tx);
+                }
             }
             ExternalMessage::PeerAdd(info) => {
                 info!("Send Connect message to {}", info);
                 self.state.add_peer_to_connect_list(info.clone());
                 self.connect(info.public_key);
-
-                if self.config_manager.is_some() {
-                    let connect_list_config =
-                        ConnectListConfig::from_connect_list(&self.state.connect_list());
-
-                    self.config_manager
-                        .as_ref()
-                        .unwrap()
-                        .store_connect_list(connect_list_config);
-                }
+                self.store_connect_list();
             }
+            ExternalMessage::PeerRemove(key) => self.handle_peer_remove(key),
+            ExternalMessage::UpdateConnectList(config) => self.handle_connect_list_update(config),
             ExternalMessage::Enable(value) => {
                 let s = if value { "enabled" } else { "disabled" };
                 if self.is_enabled == value {
@@ -86,8 +96,15 @@ impl NodeHandler {
                     }
                 }
             }
+            ExternalMessage::Pause => self.handle_pause(),
+            ExternalMessage::Resume => self.handle_resume(),
             ExternalMessage::Shutdown => self.handle_shutdown(),
             ExternalMessage::Rebroadcast => self.handle_rebroadcast(),
+            ExternalMessage::RemoveTransaction(tx_hash, result) => {
+                let outcome = self.handle_remove_transaction(&tx_hash);
+                // The requester may have stopped waiting for the outcome.
+                let _ = result.send(outcome);
+            }
         }
     }
 
@@ -99,6 +116,13 @@ impl NodeHandler {
             );
             return;
         }
+        if self.is_paused_timeout(&timeout) {
+            trace!(
+                "Ignoring a timeout {:?} because the node is paused",
+                timeout
+            );
+            return;
+        }
         match timeout {
             NodeTimeout::Round(height, round) => self.handle_round_timeout(height, round),
             NodeTimeout::Request(data, peer) => self.handle_request_timeout(&data, peer),
@@ -106,6 +130,7 @@ impl NodeHandler {
             NodeTimeout::PeerExchange => self.handle_peer_exchange_timeout(),
             NodeTimeout::UpdateApiState => self.handle_update_api_state_timeout(),
             NodeTimeout::Propose(height, round) => self.handle_propose_timeout(height, round),
+            NodeTimeout::Watchdog => self.handle_watchdog_timeout(),
         }
     }
 
@@ -116,7 +141,6 @@ impl NodeHandler {
 
     /// Broadcasts all transactions from the pool to other validators.
     pub(crate) fn handle_rebroadcast(&mut self) {
-        use exonum_crypto::Hash;
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
 
@@ -124,10 +148,56 @@ impl NodeHandler {
         txs.extend(schema.transactions_pool().iter());
 
         for tx_hash in txs {
-            self.broadcast(
-                get_tx(&tx_hash, &schema.transactions(), &self.state.tx_cache())
-                    .expect("Rebroadcast: invalid transaction hash"),
-            )
+            let tx = get_tx(&tx_hash, &schema.transactions(), &self.state.tx_cache())
+                .expect("Rebroadcast: invalid transaction hash");
+            self.broadcast(tx)
+        }
+    }
+
+    /// Removes the uncommitted transaction from the transactions cache and the persistent pool.
+    ///
+    /// Transactions included into the known proposes or blocks of the current height
+    /// are not removed, because the node may need them to execute the next block.
+    pub(crate) fn handle_remove_transaction(
+        &mut self,
+        tx_hash: &Hash,
+    ) -> Result<(), RemoveTransactionError> {
+        if self.state.is_tx_in_proposes(tx_hash) {
+            warn!(
+                "Unable to remove transaction {:?}, it is a part of the current height proposes",
+                tx_hash
+            );
+            return Err(RemoveTransactionError::Proposed);
+        }
+
+        let in_cache = self.state.tx_cache_mut().remove(tx_hash).is_some();
+        let in_pool = {
+            let fork = self.blockchain.fork();
+            let in_pool = {
+                let mut schema = Schema::new(&fork);
+                let in_pool = schema.reject_transaction(tx_hash);
+                if in_cache || in_pool {
+                    schema.log_rejected_transaction(tx_hash, RejectionReason::Removed);
+                }
+                in_pool
+            };
+            if in_cache || in_pool {
+                self.blockchain
+                    .merge(fork.into_patch())
+                    .expect("Unable to remove transaction from the persistent pool");
+            }
+            in_pool
+        };
+        self.state.remove_tx_arrival_times(iter::once(tx_hash));
+
+        if in_cache || in_pool {
+            self.api_state
+                .broadcast_mempool_event(MempoolEvent::Removed, tx_hash);
+            info!("Removed transaction {:?} from the pool", tx_hash);
+            Ok(())
+        } else {
+            warn!("Transaction {:?} is not found in the pool", tx_hash);
+            Err(RemoveTransactionError::NotFound)
         }
     }
 
@@ -137,6 +207,7 @@ impl NodeHandler {
 
         // Flush transactions stored in tx_cache to persistent pool.
         self.flush_txs_into_pool();
+        self.save_peer_stats();
     }
 
     fn flush_txs_into_pool(&mut self) {
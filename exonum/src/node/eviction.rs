@@ -0,0 +1,182 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Eviction of uncommitted transactions from the full pool.
+
+use std::cmp::Reverse;
+
+use super::{EvictionStrategy, MemoryPoolConfig, NodeHandler};
+use crate::api::{node::public::system::PoolEvictionStats, websocket::MempoolEvent};
+use crate::blockchain::{RejectionReason, Schema, TxOrdering};
+use crate::crypto::Hash;
+use crate::messages::{RawTransaction, Signed};
+
+/// Share of the limits the pool is reduced to once they are exceeded, in percent.
+const EVICTION_TARGET_PERCENT: u64 = 90;
+
+/// Limits of the pool size along with the statistics of the evicted transactions.
+#[derive(Debug)]
+pub(crate) struct PoolLimits {
+    max_transactions: Option<u64>,
+    max_bytes: Option<u64>,
+    strategy: EvictionStrategy,
+    stats: PoolEvictionStats,
+}
+
+impl PoolLimits {
+    /// Returns `None` if the pool size is not limited by the configuration.
+    pub fn new(config: &MemoryPoolConfig) -> Option<Self> {
+        if config.max_transactions.is_none() && config.max_bytes.is_none() {
+            return None;
+        }
+        Some(Self {
+            max_transactions: config.max_transactions,
+            max_bytes: config.max_bytes,
+            strategy: config.eviction,
+            stats: PoolEvictionStats::default(),
+        })
+    }
+
+    fn exceeded(&self, transactions: u64, bytes: u64) -> bool {
+        self.max_transactions
+            .map_or(false, |max| transactions > max)
+            || self.max_bytes.map_or(false, |max| bytes > max)
+    }
+
+    fn fits_target(&self, transactions: u64, bytes: u64) -> bool {
+        let target = |max: u64| max * EVICTION_TARGET_PERCENT / 100;
+        self.max_transactions
+            .map_or(true, |max| transactions <= target(max))
+            && self.max_bytes.map_or(true, |max| bytes <= target(max))
+    }
+}
+
+impl NodeHandler {
+    /// Evicts uncommitted transactions if the pool exceeds the limits of the memory pool
+    /// configuration. Does nothing if the pool size is not limited.
+    pub(crate) fn enforce_pool_limits(&mut self) {
+        let (evicted, evicted_bytes) = {
+            let limits = match self.pool_limits {
+                Some(ref limits) => limits,
+                None => return,
+            };
+            let snapshot = self.blockchain.snapshot();
+            let schema = Schema::new(&snapshot);
+            let cache = self.state.tx_cache();
+            let mut transactions = schema.transactions_pool_len() + cache.len() as u64;
+            let mut bytes =
+                schema.transactions_pool_size() + cache.values().map(tx_size).sum::<u64>();
+            if !limits.exceeded(transactions, bytes) {
+                return;
+            }
+
+            let pool_transactions = schema.transactions();
+            let load_tx = |tx_hash: &Hash| {
+                cache
+                    .get(tx_hash)
+                    .cloned()
+                    .or_else(|| pool_transactions.get(tx_hash))
+            };
+            // Lower priority classes have larger indexes. Transactions are read only
+            // if their classes are needed.
+            let classes = match self.state.consensus_config().tx_ordering {
+                TxOrdering::Priority { ref classes }
+                    if limits.strategy == EvictionStrategy::LowestPriorityFirst =>
+                {
+                    Some(classes)
+                }
+                _ => None,
+            };
+            let class = |tx_hash: &Hash| {
+                classes.map_or(0, |classes| {
+                    let service_id = load_tx(tx_hash).map(|tx| tx.payload().service_id());
+                    classes
+                        .iter()
+                        .position(|class| service_id.map_or(false, |id| class.contains(&id)))
+                        .unwrap_or_else(|| classes.len())
+                })
+            };
+
+            let arrival_times = self.state.tx_arrival_times();
+            let mut candidates = cache
+                .keys()
+                .cloned()
+                .chain(schema.transactions_pool().iter())
+                .filter(|tx_hash| !self.state.is_tx_in_proposes(tx_hash))
+                .map(|tx_hash| {
+                    // Transactions without arrival time were received before the node
+                    // restart, so they are the oldest ones.
+                    let arrival_time = arrival_times.get(&tx_hash).cloned();
+                    (Reverse(class(&tx_hash)), arrival_time, tx_hash)
+                })
+                .collect::<Vec<_>>();
+            candidates.sort();
+
+            let mut evicted = Vec::new();
+            let mut evicted_bytes = 0;
+            for (_, _, tx_hash) in candidates {
+                if limits.fits_target(transactions, bytes) {
+                    break;
+                }
+                let size = load_tx(&tx_hash).map_or(0, |tx| tx_size(&tx));
+                transactions -= 1;
+                bytes = bytes.saturating_sub(size);
+                evicted_bytes += size;
+                evicted.push(tx_hash);
+            }
+            (evicted, evicted_bytes)
+        };
+
+        if evicted.is_empty() {
+            warn!("The pool has exceeded the limits, but no transactions can be evicted");
+            return;
+        }
+
+        let fork = self.blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            for tx_hash in &evicted {
+                // Transactions from the cache are not flushed into the persistent pool yet.
+                if self.state.tx_cache_mut().remove(tx_hash).is_none() {
+                    schema.reject_transaction(tx_hash);
+                }
+                schema.log_rejected_transaction(tx_hash, RejectionReason::Evicted);
+            }
+        }
+        self.blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to evict transactions from the persistent pool");
+        self.state.remove_tx_arrival_times(&evicted);
+
+        for tx_hash in &evicted {
+            self.api_state
+                .broadcast_mempool_event(MempoolEvent::Removed, tx_hash);
+        }
+        if let Some(ref mut limits) = self.pool_limits {
+            limits.stats.runs += 1;
+            limits.stats.evicted_transactions += evicted.len() as u64;
+            limits.stats.evicted_bytes += evicted_bytes;
+            self.api_state.set_eviction_stats(limits.stats);
+        }
+        warn!(
+            "The pool has exceeded the limits, evicted {} transactions ({} bytes)",
+            evicted.len(),
+            evicted_bytes
+        );
+    }
+}
+
+fn tx_size(tx: &Signed<RawTransaction>) -> u64 {
+    tx.signed_message().raw().len() as u64
+}
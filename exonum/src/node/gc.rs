@@ -0,0 +1,216 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Removal of the data which is no longer needed by the node.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use super::{GarbageCollectionConfig, NodeHandler};
+use crate::api::{node::public::system::GarbageCollectionStats, websocket::MempoolEvent};
+use crate::blockchain::{RejectionReason, Schema};
+use crate::crypto::Hash;
+use crate::helpers::{Height, Round};
+use crate::messages::{Consensus as ConsensusMessage, Message};
+
+/// State of the garbage collector.
+#[derive(Debug)]
+pub(crate) struct GarbageCollector {
+    config: GarbageCollectionConfig,
+    stats: GarbageCollectionStats,
+    /// Whether the arrival times are known for the transactions from the persistent pool.
+    pool_scanned: bool,
+    /// Heights at which the uncommitted transactions were first seen by the collector.
+    arrival_heights: HashMap<Hash, Height>,
+}
+
+impl GarbageCollector {
+    pub fn new(config: GarbageCollectionConfig) -> Self {
+        Self {
+            config,
+            stats: GarbageCollectionStats::default(),
+            pool_scanned: false,
+            arrival_heights: HashMap::new(),
+        }
+    }
+}
+
+impl NodeHandler {
+    /// Removes outdated transactions from the pool and superseded messages from
+    /// the consensus messages cache according to the garbage collection configuration.
+    /// Does nothing if garbage collection is disabled.
+    pub(crate) fn collect_garbage(&mut self) {
+        let config = match self.gc {
+            Some(ref gc) => gc.config,
+            None => return,
+        };
+
+        let evicted_transactions =
+            if config.max_pool_tx_age.is_some() || config.max_pool_tx_heights.is_some() {
+                self.evict_outdated_transactions(
+                    config.max_pool_tx_age.map(Duration::from_secs),
+                    config.max_pool_tx_heights,
+                )
+            } else {
+                0
+            };
+        let removed_consensus_messages = config
+            .keep_rounds
+            .map_or(0, |rounds| self.remove_superseded_messages(rounds));
+
+        if let Some(ref mut gc) = self.gc {
+            gc.stats.runs += 1;
+            gc.stats.evicted_transactions += evicted_transactions;
+            gc.stats.removed_consensus_messages += removed_consensus_messages;
+            self.api_state.set_gc_stats(gc.stats);
+        }
+    }
+
+    /// Evicts the uncommitted transactions received earlier than `max_age` ago or
+    /// at least `max_heights` heights ago, and records them in the rejected transactions log.
+    /// Returns the number of the evicted transactions.
+    fn evict_outdated_transactions(
+        &mut self,
+        max_age: Option<Duration>,
+        max_heights: Option<u64>,
+    ) -> u64 {
+        let now = self.system_state.current_time();
+        let height = self.state.height();
+        if let Some(ref mut gc) = self.gc {
+            if !gc.pool_scanned {
+                // Arrival times of the transactions received before the node restart
+                // are unknown, so their age is counted from the first collection.
+                let snapshot = self.blockchain.snapshot();
+                for tx_hash in Schema::new(&snapshot).transactions_pool().iter() {
+                    if !self.state.tx_arrival_times().contains_key(&tx_hash) {
+                        self.state.add_tx_arrival_time(tx_hash, now);
+                    }
+                }
+                gc.pool_scanned = true;
+            }
+
+            if max_heights.is_some() {
+                // Garbage is collected at each round, so the transactions are seen
+                // at the height they are received at.
+                let arrival_times = self.state.tx_arrival_times();
+                gc.arrival_heights
+                    .retain(|tx_hash, _| arrival_times.contains_key(tx_hash));
+                for tx_hash in arrival_times.keys() {
+                    gc.arrival_heights.entry(*tx_hash).or_insert(height);
+                }
+            }
+        }
+
+        let outdated = {
+            let arrival_heights = self.gc.as_ref().map(|gc| &gc.arrival_heights);
+            let is_outdated = |tx_hash: &Hash, time: SystemTime| {
+                let too_old = max_age.map_or(false, |max_age| {
+                    now.duration_since(time).map_or(false, |age| age > max_age)
+                });
+                let too_many_heights = max_heights.map_or(false, |max_heights| {
+                    arrival_heights
+                        .and_then(|heights| heights.get(tx_hash))
+                        .map_or(false, |arrival| height.0 - arrival.0 >= max_heights)
+                });
+                too_old || too_many_heights
+            };
+            self.state
+                .tx_arrival_times()
+                .iter()
+                .filter(|&(tx_hash, time)| is_outdated(tx_hash, *time))
+                .map(|(tx_hash, _)| *tx_hash)
+                .filter(|tx_hash| !self.state.is_tx_in_proposes(tx_hash))
+                .collect::<Vec<_>>()
+        };
+        if outdated.is_empty() {
+            return 0;
+        }
+
+        let fork = self.blockchain.fork();
+        {
+            let mut schema = Schema::new(&fork);
+            for tx_hash in &outdated {
+                // Transactions from the cache are not flushed into the persistent pool yet.
+                self.state.tx_cache_mut().remove(tx_hash);
+                schema.reject_transaction(tx_hash);
+                schema.log_rejected_transaction(tx_hash, RejectionReason::Expired);
+            }
+        }
+        self.blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to evict transactions from the persistent pool");
+        self.state.remove_tx_arrival_times(&outdated);
+        if let Some(ref mut gc) = self.gc {
+            for tx_hash in &outdated {
+                gc.arrival_heights.remove(tx_hash);
+            }
+        }
+
+        for tx_hash in &outdated {
+            self.api_state
+                .broadcast_mempool_event(MempoolEvent::Removed, tx_hash);
+        }
+        info!(
+            "Evicted {} outdated transactions from the pool",
+            outdated.len()
+        );
+        outdated.len() as u64
+    }
+
+    /// Removes messages of the rounds preceding the latest `keep_rounds` rounds from
+    /// the consensus messages cache. Returns the number of the removed messages.
+    fn remove_superseded_messages(&mut self, keep_rounds: u32) -> u64 {
+        let round = self.state.round();
+        if round.0 <= keep_rounds {
+            return 0;
+        }
+        let first_kept_round = Round(round.0 - keep_rounds + 1);
+        let height = self.state.height();
+        let locked_round = self.state.locked_round();
+        let locked_propose = self.state.locked_propose();
+
+        let snapshot = self.blockchain.snapshot();
+        let (kept, removed): (Vec<_>, Vec<_>) = Schema::new(&snapshot)
+            .consensus_messages_cache()
+            .iter()
+            .partition(|message| match *message {
+                Message::Consensus(ConsensusMessage::Propose(ref propose))
+                    if Some(propose.hash()) == locked_propose =>
+                {
+                    true
+                }
+                Message::Consensus(ref message) => {
+                    message.height() == height
+                        && (message.round() >= first_kept_round || message.round() == locked_round)
+                }
+                _ => true,
+            });
+        if removed.is_empty() {
+            return 0;
+        }
+
+        let fork = self.blockchain.fork();
+        {
+            let mut cache = Schema::new(&fork).consensus_messages_cache();
+            cache.clear();
+            cache.extend(kept);
+        }
+        self.blockchain
+            .merge(fork.into_patch())
+            .expect("Unable to remove messages from the consensus messages cache");
+        removed.len() as u64
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Light node keeping the light chain synchronized with a full node.
+
+use failure::Error;
+use futures::sync::mpsc;
+use reqwest::{Client, StatusCode};
+
+use std::{fmt, net::SocketAddr, sync::Arc, thread, time::Duration};
+
+use super::ApiSender;
+use crate::api::{
+    backends::actix::{ApiRuntimeConfig, SystemRuntimeConfig},
+    node::public::explorer::{BlockInfo, BlockQuery, ConfigProof, ConfigProofQuery},
+    ApiAccess, ApiAggregator,
+};
+use crate::blockchain::{
+    light::{LightChain, LightSource},
+    Block, BlockProof, Blockchain, SharedNodeState, StoredConfiguration,
+};
+use crate::crypto;
+use crate::helpers::Height;
+use exonum_merkledb::Database;
+
+/// Interval between the synchronizations of the light chain.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Timeout of the requests to the full node.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Interval between the updates of the API state, which is not used by the light node.
+const STATE_UPDATE_TIMEOUT: u64 = 10_000;
+
+/// Light node, which keeps only the block headers and precommits synchronized with
+/// the source, such as a full node, and serves the light client API.
+///
+/// The light node does not store transactions and the service state, so it serves
+/// only the light client API with the `light` prefix. See the [`light`] module
+/// for details.
+///
+/// [`light`]: ../blockchain/light/index.html
+pub struct LightNode {
+    chain: LightChain,
+    blockchain: Blockchain,
+    source: Box<dyn LightSource + Send>,
+    api_address: SocketAddr,
+}
+
+impl LightNode {
+    /// Creates a light node keeping the light chain in the database. The chain starts from
+    /// the given genesis block and configuration, which must be obtained from a trusted
+    /// source, see `LightChain::new`. The public API is served at `api_address`.
+    pub fn new(
+        db: Arc<dyn Database>,
+        genesis_block: Block,
+        genesis_config: StoredConfiguration,
+        source: Box<dyn LightSource + Send>,
+        api_address: SocketAddr,
+    ) -> Result<Self, Error> {
+        crypto::init();
+        let chain = LightChain::new(Arc::clone(&db), genesis_block, genesis_config)?;
+        // Service keys are not used, since the light node does not broadcast transactions.
+        let (service_public_key, service_secret_key) = crypto::gen_keypair();
+        let blockchain = Blockchain::new(
+            db,
+            Vec::new(),
+            service_public_key,
+            service_secret_key,
+            ApiSender::new(mpsc::channel(0).0),
+        );
+        Ok(Self {
+            chain,
+            blockchain,
+            source,
+            api_address,
+        })
+    }
+
+    /// Returns the light chain of the node.
+    pub fn chain(&self) -> &LightChain {
+        &self.chain
+    }
+
+    /// Synchronizes the light chain with the source, see `LightChain::sync`.
+    pub fn sync(&self) -> Result<u64, Error> {
+        self.chain.sync(&*self.source)
+    }
+
+    /// Starts the public API server and keeps the light chain synchronized with the source.
+    pub fn run(self) -> Result<(), Error> {
+        let api_state = SharedNodeState::new(STATE_UPDATE_TIMEOUT);
+        let _api_runtime = SystemRuntimeConfig {
+            api_runtimes: vec![ApiRuntimeConfig::new(self.api_address, ApiAccess::Public)],
+            api_aggregator: ApiAggregator::light(self.blockchain.clone(), api_state),
+        }
+        .start()?;
+
+        loop {
+            match self.sync() {
+                Ok(0) => {}
+                Ok(added) => info!(
+                    "Synchronized {} blocks, the light chain height is {}",
+                    added,
+                    self.chain.height()
+                ),
+                Err(e) => warn!("Failed to synchronize the light chain: {}", e),
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+impl fmt::Debug for LightNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LightNode")
+            .field("chain", &self.chain)
+            .field("api_address", &self.api_address)
+            .finish()
+    }
+}
+
+/// Source of the light chain requesting the blocks and configurations from the explorer
+/// API of a full node.
+pub struct HttpLightSource {
+    client: Client,
+    explorer_url: String,
+}
+
+impl HttpLightSource {
+    /// Creates a source for the full node with the public API at the given URL,
+    /// such as `http://127.0.0.1:8200`.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let client = Client::builder().timeout(REQUEST_TIMEOUT).build()?;
+        let explorer_url = format!("{}/api/explorer", url.trim_end_matches('/'));
+        Ok(Self {
+            client,
+            explorer_url,
+        })
+    }
+}
+
+impl LightSource for HttpLightSource {
+    fn block_proof(&self, height: Height) -> Result<Option<BlockProof>, Error> {
+        let response = self
+            .client
+            .get(&format!("{}/v1/block", self.explorer_url))
+            .query(&BlockQuery::new(height))
+            .send()?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let info: BlockInfo = response.error_for_status()?.json()?;
+        let precommits = info
+            .precommits
+            .ok_or_else(|| format_err!("The block at height {} has no precommits", height))?;
+        Ok(Some(BlockProof {
+            block: info.block,
+            precommits,
+            certificate: None,
+        }))
+    }
+
+    fn config_proof(&self, height: Option<Height>) -> Result<ConfigProof, Error> {
+        let proof = self
+            .client
+            .get(&format!("{}/v1/configs/proof", self.explorer_url))
+            .query(&ConfigProofQuery { height })
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(proof)
+    }
+}
+
+impl fmt::Debug for HttpLightSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpLightSource")
+            .field("explorer_url", &self.explorer_url)
+            .finish()
+    }
+}
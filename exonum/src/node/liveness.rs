@@ -0,0 +1,65 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Alerts about the validators missing consensus messages.
+
+use std::collections::HashSet;
+
+use super::NodeHandler;
+use crate::api::node::public::explorer::ValidatorLivenessInfo;
+use crate::blockchain::Schema;
+use crate::helpers::ValidatorId;
+
+impl NodeHandler {
+    /// Notifies the websocket subscribers about the validators whose uptime has fallen
+    /// below the configured threshold. A validator is reported again only after its uptime
+    /// recovers. Does nothing if liveness tracking is disabled.
+    pub(crate) fn check_liveness(&mut self) {
+        let config = match self.blockchain.liveness() {
+            Some(config) => config,
+            None => return,
+        };
+
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let liveness = schema.validator_liveness();
+        let mut lagging = HashSet::new();
+        for (id, keys) in self.state.validators().iter().enumerate() {
+            let consensus_key = keys.consensus_key;
+            let info = match liveness.get(&consensus_key) {
+                Some(info) => info,
+                None => continue,
+            };
+            if info.uptime_percent() >= config.min_uptime_percent {
+                continue;
+            }
+            lagging.insert(consensus_key);
+            if !self.lagging_validators.contains(&consensus_key) {
+                warn!(
+                    "Validator {} has precommitted {}% of the latest {} blocks",
+                    id,
+                    info.uptime_percent(),
+                    info.blocks
+                );
+                self.api_state
+                    .broadcast_liveness_alert(ValidatorLivenessInfo {
+                        validator_id: ValidatorId(id as u16),
+                        consensus_key,
+                        liveness: info,
+                    });
+            }
+        }
+        self.lagging_validators = lagging;
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consensus and node metrics reported to the `MetricsRegistry` of the node.
+
+use std::time::{Duration, SystemTime};
+
+use super::NodeHandler;
+use crate::helpers::{metrics::MetricDesc, Milliseconds, Round};
+
+pub(crate) const PROPOSES_SENT: MetricDesc = MetricDesc {
+    name: "exonum_consensus_proposes_sent_total",
+    help: "Number of the proposes sent by the node.",
+};
+pub(crate) const PROPOSES_RECEIVED: MetricDesc = MetricDesc {
+    name: "exonum_consensus_proposes_received_total",
+    help: "Number of the proposes received from the peers.",
+};
+pub(crate) const PREVOTES_SENT: MetricDesc = MetricDesc {
+    name: "exonum_consensus_prevotes_sent_total",
+    help: "Number of the prevotes sent by the node.",
+};
+pub(crate) const PREVOTES_RECEIVED: MetricDesc = MetricDesc {
+    name: "exonum_consensus_prevotes_received_total",
+    help: "Number of the prevotes received from the peers.",
+};
+pub(crate) const PRECOMMITS_SENT: MetricDesc = MetricDesc {
+    name: "exonum_consensus_precommits_sent_total",
+    help: "Number of the precommits sent by the node.",
+};
+pub(crate) const PRECOMMITS_RECEIVED: MetricDesc = MetricDesc {
+    name: "exonum_consensus_precommits_received_total",
+    help: "Number of the precommits received from the peers.",
+};
+pub(crate) const ROUND_TIMEOUTS: MetricDesc = MetricDesc {
+    name: "exonum_consensus_round_timeouts_total",
+    help: "Number of the rounds finished by the timeout.",
+};
+pub(crate) const ROUND_DURATION: MetricDesc = MetricDesc {
+    name: "exonum_consensus_round_duration_milliseconds",
+    help: "Duration of the finished rounds.",
+};
+pub(crate) const BLOCK_INTERVAL: MetricDesc = MetricDesc {
+    name: "exonum_consensus_block_interval_milliseconds",
+    help: "Time between the commits of the consecutive blocks.",
+};
+pub(crate) const HEIGHT: MetricDesc = MetricDesc {
+    name: "exonum_consensus_height",
+    help: "Current height of the node.",
+};
+pub(crate) const QUEUED_MESSAGES: MetricDesc = MetricDesc {
+    name: "exonum_consensus_queued_messages",
+    help: "Number of the consensus messages queued for the next rounds and height.",
+};
+pub(crate) const TX_CACHE: MetricDesc = MetricDesc {
+    name: "exonum_node_tx_cache_transactions",
+    help: "Number of the transactions in the cache of the current height.",
+};
+pub(crate) const TX_POOL: MetricDesc = MetricDesc {
+    name: "exonum_node_tx_pool_transactions",
+    help: "Number of the transactions in the pool of unconfirmed transactions.",
+};
+pub(crate) const SIGNING_DURATION: MetricDesc = MetricDesc {
+    name: "exonum_signer_signing_duration_microseconds",
+    help: "Duration of the successful signing requests to the remote signer.",
+};
+pub(crate) const SIGNING_FAILURES: MetricDesc = MetricDesc {
+    name: "exonum_signer_signing_failures_total",
+    help: "Number of the failed signing requests to the remote signer.",
+};
+pub(crate) const SIGNER_CONNECTIONS: MetricDesc = MetricDesc {
+    name: "exonum_signer_connections_total",
+    help: "Number of the connections established with the remote signer.",
+};
+pub(crate) const SIGNER_CONNECTED: MetricDesc = MetricDesc {
+    name: "exonum_signer_connected",
+    help: "Whether the node is connected to the remote signer.",
+};
+
+impl NodeHandler {
+    /// Observes the duration of the current round and the interval since the previous
+    /// block. Should be called before the node moves to the next height.
+    pub(crate) fn observe_commit_metrics(&self, round: Option<Round>) {
+        let now = self.system_state.current_time();
+        let metrics = self.api_state.metrics();
+        // Blocks committed during the catch up are not observed as finished rounds.
+        if round.is_some() {
+            let round_start = self.round_start_time(self.state.round());
+            metrics.observe(&ROUND_DURATION, millis_since(round_start, now));
+        }
+        metrics.observe(
+            &BLOCK_INTERVAL,
+            millis_since(self.state.height_start_time(), now),
+        );
+    }
+
+    /// Observes the duration of the round finished by the timeout. Should be called
+    /// before the node moves to the next round.
+    pub(crate) fn observe_round_timeout_metrics(&self) {
+        let round = self.state.round();
+        let round_start = self.round_start_time(round);
+        let metrics = self.api_state.metrics();
+        metrics.increment(&ROUND_TIMEOUTS);
+        metrics.observe(
+            &ROUND_DURATION,
+            millis_since(round_start, self.round_start_time(round.next())),
+        );
+    }
+
+    /// Updates the gauges of the height and the pool of unconfirmed transactions.
+    pub(crate) fn update_height_metrics(&self, pool_len: u64) {
+        let metrics = self.api_state.metrics();
+        metrics.set(&HEIGHT, self.state.height().0 as i64);
+        metrics.set(&TX_POOL, pool_len as i64);
+    }
+
+    /// Updates the gauges of the queued consensus messages and the transaction cache.
+    pub(crate) fn update_queue_metrics(&self) {
+        let metrics = self.api_state.metrics();
+        metrics.set(&QUEUED_MESSAGES, self.state.queued_len() as i64);
+        metrics.set(&TX_CACHE, self.state.tx_cache_len() as i64);
+    }
+}
+
+fn millis_since(earlier: SystemTime, later: SystemTime) -> Milliseconds {
+    let duration = later
+        .duration_since(earlier)
+        .unwrap_or_else(|_| Duration::from_secs(0));
+    duration.as_secs() * 1000 + Milliseconds::from(duration.subsec_millis())
+}
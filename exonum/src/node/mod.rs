@@ -19,70 +19,135 @@
 
 pub use self::{
     connect_list::{ConnectList, PeerAddress},
-    state::{RequestData, State, ValidatorState},
+    light::{HttpLightSource, LightNode},
+    replica::{DatabaseOpener, ReadReplica},
+    signer::{LocalSigner, RemoteSigner, Signer},
+    state::{
+        ConsensusStateInfo, ConsensusTimeoutsInfo, RequestData, State, ValidatorState, VotesInfo,
+    },
 };
 
+pub(crate) use self::api_manager::ApiManagerRequest;
+
 // TODO: Temporary solution to get access to WAIT constants. (ECR-167)
 pub mod state;
 
 use failure::Error;
-use futures::{sync::mpsc, Future, Sink};
+use futures::{
+    sync::{mpsc, oneshot},
+    Future, Sink,
+};
 use tokio_core::reactor::Core;
 use tokio_threadpool::Builder as ThreadPoolBuilder;
 use toml::Value;
 
 use std::{
+    cmp,
     collections::{BTreeMap, HashSet},
     fmt,
-    net::SocketAddr,
+    io::{self, Read},
+    iter,
+    net::{IpAddr, SocketAddr},
     path::{Path, PathBuf},
     sync::Arc,
     thread,
     time::{Duration, SystemTime},
 };
 
+use self::{
+    api_manager::ApiManager, catch_up::CatchUp, checkpoints::Checkpointer,
+    clock_skew::ClockSkewEstimator, diagnostics::Diagnostics, eviction::PoolLimits,
+    gc::GarbageCollector, pause::Pause, peer_stats::PeerStatsRecorder, round_changes::RoundChanges,
+    signing_record::SigningRecord, timeouts::LatencyEstimator, watchdog::Watchdog,
+};
 use crate::api::{
-    backends::actix::{AllowOrigin, ApiRuntimeConfig, App, AppConfig, Cors, SystemRuntimeConfig},
-    ApiAccess, ApiAggregator,
+    backends::actix::{AllowOrigin, AppConfig, CorsRule},
+    ApiAccess,
 };
 use crate::blockchain::{
-    Blockchain, ConsensusConfig, GenesisConfig, Schema, Service, SharedNodeState, ValidatorKeys,
+    archive, snapshot, Blockchain, ConsensusConfig, GenesisConfig, Schema, Service,
+    SharedNodeState, ValidatorKeys,
+};
+use crate::crypto::{
+    self, hd::DerivationPath, read_derived_keys_from_file, read_keys_from_file, CryptoHash, Hash,
+    PublicKey, SecretKey,
 };
-use crate::crypto::{self, read_keys_from_file, CryptoHash, Hash, PublicKey, SecretKey};
 use crate::events::{
     error::{into_failure, LogError},
     noise::HandshakeParams,
     HandlerPart, InternalEvent, InternalPart, InternalRequest, NetworkConfiguration, NetworkEvent,
-    NetworkPart, NetworkRequest, SyncSender, TimeoutRequest,
+    NetworkPart, NetworkRequest, ProxyConfig, SyncSender, TimeoutRequest,
 };
 use crate::helpers::{
     config::ConfigManager,
-    fabric::{NodePrivateConfig, NodePublicConfig},
+    fabric::{NodePrivateConfig, NodePublicConfig, ROCKSDB_DATABASE_TYPE},
     user_agent, Height, Milliseconds, Round, ValidatorId,
 };
 use crate::messages::{Connect, Message, ProtocolMessage, RawTransaction, Signed, SignedMessage};
 use crate::node::state::SharedConnectList;
-use exonum_merkledb::{Database, DbOptions};
+use exonum_merkledb::{CacheOptions, CompressionOptions, Database, DbOptions};
 
+mod api_manager;
 mod basic;
+mod catch_up;
+mod checkpoints;
+mod clock_skew;
 mod connect_list;
 mod consensus;
+mod diagnostics;
 mod events;
+mod eviction;
+mod gc;
+mod light;
+mod liveness;
+mod metrics;
+mod pause;
+mod peer_stats;
+mod replica;
 mod requests;
+mod round_changes;
+mod signer;
+mod signing_record;
+mod timeouts;
+mod watchdog;
 
 /// External messages.
 #[derive(Debug)]
 pub enum ExternalMessage {
     /// Add a new connection.
     PeerAdd(ConnectInfo),
+    /// Remove the peer with the given public key from the connect list
+    /// and drop the connection with it.
+    PeerRemove(PublicKey),
+    /// Replace the connect list with the one read from the configuration file.
+    UpdateConnectList(ConnectListConfig),
     /// Transaction that implements the `Transaction` trait.
     Transaction(Signed<RawTransaction>),
     /// Enable or disable the node.
     Enable(bool),
+    /// Pause the message processing and the block production.
+    Pause,
+    /// Resume the paused node.
+    Resume,
     /// Shutdown the node.
     Shutdown,
     /// Rebroadcast transactions from the pool.
     Rebroadcast,
+    /// Remove an uncommitted transaction with the given hash from the pool. The outcome
+    /// of the removal is sent to the channel.
+    RemoveTransaction(Hash, oneshot::Sender<Result<(), RemoveTransactionError>>),
+}
+
+/// Reasons why an uncommitted transaction is not removed from the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Fail)]
+pub enum RemoveTransactionError {
+    /// The transaction is neither in the transactions cache nor in the persistent pool.
+    #[fail(display = "Transaction is not found in the pool")]
+    NotFound,
+    /// The transaction is included into a known propose or block of the current height,
+    /// so the node may need it to execute the next block.
+    #[fail(display = "Transaction is a part of the current height proposes")]
+    Proposed,
 }
 
 /// Node timeout types.
@@ -100,6 +165,8 @@ pub enum NodeTimeout {
     UpdateApiState,
     /// Exchange peers timeout.
     PeerExchange,
+    /// Consensus stall check.
+    Watchdog,
 }
 
 /// A helper trait that provides the node with information about the state of the system such
@@ -137,6 +204,41 @@ pub struct NodeHandler {
     config_manager: Option<ConfigManager>,
     /// Can we speed up Propose with transaction pressure?
     allow_expedited_propose: bool,
+    /// Height and round in which the node skipped the empty propose.
+    skipped_propose: Option<(Height, Round)>,
+    /// Pruning configuration.
+    pruning: Option<PruningConfig>,
+    /// Creates database checkpoints after block commits.
+    checkpointer: Option<Checkpointer>,
+    /// Removes the data which is no longer needed by the node.
+    gc: Option<GarbageCollector>,
+    /// Limits of the pool size, if any.
+    pool_limits: Option<PoolLimits>,
+    /// Network latency observed by the node.
+    latency: LatencyEstimator,
+    /// Clock skew between the node and its peers.
+    clock_skew: ClockSkewEstimator,
+    /// Round changes observed by the node.
+    round_changes: RoundChanges,
+    /// Consensus keys of the validators reported as lagging.
+    lagging_validators: HashSet<PublicKey>,
+    /// Detects the consensus stalls.
+    watchdog: Option<Watchdog>,
+    /// State of the catch-up mode.
+    catch_up: Option<CatchUp>,
+    /// Events received during the pause, if the node is paused.
+    pause: Option<Pause>,
+    /// Consensus diagnostics of the current height.
+    diagnostics: Option<Diagnostics>,
+    /// Peer statistics collected since they were last saved.
+    peer_stats: Option<PeerStatsRecorder>,
+    /// Votes signed by the node at the highest height.
+    signing_record: Option<SigningRecord>,
+    /// Signs the messages with the consensus key.
+    signer: Box<dyn Signer>,
+    /// Share of the threshold key of the validators.
+    #[cfg(feature = "threshold-precommits")]
+    threshold_key: Option<crypto::threshold::SecretKeyShare>,
 }
 
 /// Service configuration.
@@ -170,6 +272,13 @@ pub struct NodeApiConfig {
     pub public_api_address: Option<SocketAddr>,
     /// Listen address for private api endpoints.
     pub private_api_address: Option<SocketAddr>,
+    /// Path to the Unix domain socket for private api endpoints. If set, the private api
+    /// is bound to this socket instead of `private_api_address`.
+    pub private_api_socket: Option<PathBuf>,
+    /// IP addresses allowed to access private api endpoints. Requests from other addresses
+    /// are rejected with the `403 Forbidden` status. If not set, access is not restricted.
+    /// The allowlist does not apply if the private api is bound to a Unix domain socket.
+    pub private_api_allowlist: Option<Vec<IpAddr>>,
     /// Cross-origin resource sharing ([CORS][cors]) options for responses returned
     /// by public API handlers.
     ///
@@ -180,6 +289,16 @@ pub struct NodeApiConfig {
     ///
     /// [cors]: https://developer.mozilla.org/en-US/docs/Web/HTTP/CORS
     pub private_allow_origin: Option<AllowOrigin>,
+    /// Per-origin CORS rules for responses returned by public API handlers. The first rule
+    /// matching the origin of a request is applied. If set, `public_allow_origin` is ignored.
+    pub public_cors_rules: Option<Vec<CorsRule>>,
+    /// Per-origin CORS rules for responses returned by private API handlers. The first rule
+    /// matching the origin of a request is applied. If set, `private_allow_origin` is ignored.
+    pub private_cors_rules: Option<Vec<CorsRule>>,
+    /// Whether to expose the websocket feed of changes in the pool of unconfirmed
+    /// transactions via the public API. The feed is always available via the private API.
+    #[serde(default)]
+    pub public_mempool_events: bool,
 }
 
 impl Default for NodeApiConfig {
@@ -188,8 +307,13 @@ impl Default for NodeApiConfig {
             state_update_timeout: 10_000,
             public_api_address: None,
             private_api_address: None,
+            private_api_socket: None,
+            private_api_allowlist: None,
             public_allow_origin: None,
             private_allow_origin: None,
+            public_cors_rules: None,
+            private_cors_rules: None,
+            public_mempool_events: false,
         }
     }
 }
@@ -224,12 +348,409 @@ pub struct MemoryPoolConfig {
     /// Sets the maximum number of messages that can be buffered on the event loop's
     /// notification channel before a send will fail.
     pub events_pool_capacity: EventsPoolCapacity,
+    /// Maximum number of uncommitted transactions. The number is not limited
+    /// if not specified. Must be strictly larger than 0.
+    #[serde(default)]
+    pub max_transactions: Option<u64>,
+    /// Maximum total size of uncommitted transactions in bytes. The size is not limited
+    /// if not specified. Must be strictly larger than 0.
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Strategy of choosing the transactions evicted from the pool once one of the limits
+    /// is exceeded.
+    #[serde(default)]
+    pub eviction: EvictionStrategy,
 }
 
 impl Default for MemoryPoolConfig {
     fn default() -> Self {
         Self {
             events_pool_capacity: EventsPoolCapacity::default(),
+            max_transactions: None,
+            max_bytes: None,
+            eviction: EvictionStrategy::default(),
+        }
+    }
+}
+
+/// Strategy of choosing the uncommitted transactions evicted from the full pool.
+///
+/// Once a received transaction makes the pool exceed one of the limits, transactions
+/// are evicted until the pool fits into 90% of the limits, so that the pool is not scanned
+/// on each transaction. Transactions included into the known proposes of the current
+/// height are never evicted.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EvictionStrategy {
+    /// Transactions received earliest are evicted first.
+    OldestFirst,
+    /// Transactions of the services from the latest classes of the `priority` transactions
+    /// ordering policy are evicted first, the transactions of the services not listed in any
+    /// class are evicted before them. Transactions of the same class are evicted starting
+    /// from the oldest ones. Same as `OldestFirst` for the other ordering policies.
+    LowestPriorityFirst,
+}
+
+impl Default for EvictionStrategy {
+    fn default() -> Self {
+        EvictionStrategy::OldestFirst
+    }
+}
+
+/// Pruning configuration parameters.
+///
+/// If pruning is enabled, bodies of transactions and precommits of the blocks older than
+/// the specified number of the latest blocks are removed from the storage. Block headers
+/// and the current state are kept.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PruningConfig {
+    /// Number of the latest blocks whose transactions and precommits are kept.
+    /// Must be strictly larger than 0.
+    pub keep_blocks: u64,
+}
+
+/// State history configuration parameters.
+///
+/// If state history is enabled, each committed block keeps the previous values of
+/// the storage keys it changes, so that the state at the specified number of the latest
+/// heights can be read with [`Blockchain::snapshot_at`], e.g., to build proofs against
+/// older block headers.
+///
+/// [`Blockchain::snapshot_at`]: ../blockchain/struct.Blockchain.html#method.snapshot_at
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct StateHistoryConfig {
+    /// Number of the latest heights preceding the current one whose state is kept.
+    /// Must be strictly larger than 0.
+    pub keep_heights: u64,
+}
+
+/// Validator liveness tracking configuration parameters.
+///
+/// If liveness tracking is enabled, the node counts the precommits and proposals missed
+/// by each validator over a sliding window of the latest blocks, see
+/// [`Schema::validator_liveness`], and notifies the websocket subscribers when
+/// the share of blocks precommitted by a validator falls below the threshold.
+///
+/// [`Schema::validator_liveness`]: ../blockchain/struct.Schema.html#method.validator_liveness
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LivenessConfig {
+    /// Number of the latest blocks over which the missed messages are counted.
+    /// Must be strictly larger than 0.
+    pub window: u64,
+    /// Minimal share of the blocks in the window precommitted by a validator, in percent.
+    /// Must not be larger than 100.
+    pub min_uptime_percent: u64,
+}
+
+/// Parallel transaction execution configuration parameters.
+///
+/// If parallel execution is enabled with the `parallel_execution` field of the consensus
+/// configuration, the transactions of a block are split into groups accessing disjoint
+/// storage namespaces, which are determined by the service names,
+/// [`Service::foreign_namespaces`] and [`Service::read_namespaces`]. The groups are executed
+/// in parallel worker threads, and their changes are merged in the order of the block.
+/// The number of the threads does not affect the results of the transactions, so it can
+/// differ among the validators.
+///
+/// [`Service::foreign_namespaces`]: ../blockchain/trait.Service.html#method.foreign_namespaces
+/// [`Service::read_namespaces`]: ../blockchain/trait.Service.html#method.read_namespaces
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ParallelExecutionConfig {
+    /// Maximal number of the threads executing the transactions of a block.
+    /// Must be strictly larger than 0.
+    pub threads: usize,
+}
+
+/// Consensus stall watchdog configuration parameters.
+///
+/// If the watchdog is enabled and no block has been committed for `stall_timeout`,
+/// the node logs its consensus state, reports the `Degraded` consensus status via
+/// the healthcheck endpoint, tries to reconnect to the disconnected peers from
+/// the connect list and resends the pending requests. The actions are repeated
+/// until a new block is committed.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct WatchdogConfig {
+    /// Time in seconds without new blocks after which the consensus is considered stalled.
+    /// Must be strictly larger than 0.
+    pub stall_timeout: u64,
+}
+
+/// Catch-up mode configuration parameters.
+///
+/// If the catch-up mode is enabled and the node lags behind its peers by at least `min_lag`
+/// blocks, the node requests up to `max_blocks_ahead` following blocks at once, distributing
+/// the requests among the peers. The received blocks are applied in order. Pruning,
+/// checkpoints and garbage collection are deferred until the node catches up.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CatchUpConfig {
+    /// Minimal lag in blocks at which the node enters the catch-up mode.
+    /// Must be strictly larger than 0.
+    pub min_lag: u64,
+    /// Maximal number of the blocks following the current one which are requested at once.
+    /// Must be strictly larger than 0.
+    pub max_blocks_ahead: u64,
+}
+
+/// Consensus diagnostics configuration parameters.
+///
+/// If the diagnostics are enabled, the node stores the consensus artifacts of each committed
+/// height, such as the observed proposals, the leaders of the rounds and the fired timeouts,
+/// see [`Schema::height_diagnostics`]. The diagnostics of the latest `keep_heights` heights
+/// are kept and can be queried via the private API.
+///
+/// [`Schema::height_diagnostics`]: ../blockchain/struct.Schema.html#method.height_diagnostics
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DiagnosticsConfig {
+    /// Number of the latest committed heights whose diagnostics are kept.
+    /// Must be strictly larger than 0.
+    pub keep_heights: u64,
+}
+
+/// Peer statistics configuration parameters.
+///
+/// If the statistics are enabled, the node stores the connection history and the numbers
+/// of the messages and the invalid messages received from each peer, see
+/// [`Schema::peer_stats`]. The latest `keep_events` connection events of each peer
+/// are kept. The statistics can be queried via the private API.
+///
+/// [`Schema::peer_stats`]: ../blockchain/struct.Schema.html#method.peer_stats
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PeerStatsConfig {
+    /// Number of the latest connection events kept for each peer.
+    /// Must be strictly larger than 0.
+    pub keep_events: u64,
+}
+
+/// Signing record configuration parameters.
+///
+/// If the signing record is enabled, the node persists the prevotes and precommits
+/// it signs at the highest height in the file at `path` before broadcasting them.
+/// The node refuses to sign votes at lower heights or votes conflicting with the recorded
+/// ones, which protects the validator from signing conflicting messages if it is restored
+/// from a stale backup of the database. The file should not be backed up with the database.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SigningRecordConfig {
+    /// Path to the file with the signed votes. The file is created if it does not exist.
+    pub path: PathBuf,
+}
+
+/// Remote signer configuration parameters.
+///
+/// If the remote signer is enabled, the consensus messages and the transactions broadcast
+/// by the services are signed by an external process listening on the Unix socket
+/// at `socket_path`, such as a front-end of a hardware security module, see the `Signer`
+/// trait for the protocol. The signer must be available when the node starts. If
+/// the connection fails, the node reconnects with an exponential backoff of up to
+/// `max_reconnect_delay` and does not sign messages until then.
+///
+/// Note that the encryption of the connections with the peers is still derived from
+/// the consensus secret key, so the key is read by the node anyway.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RemoteSignerConfig {
+    /// Path to the Unix socket of the signer.
+    pub socket_path: PathBuf,
+    /// Timeout in milliseconds of a signing request. Must be strictly larger than 0.
+    pub timeout: Milliseconds,
+    /// Maximal delay in milliseconds between the attempts to reconnect to the signer.
+    /// Must be strictly larger than 0.
+    pub max_reconnect_delay: Milliseconds,
+}
+
+/// Threshold signature key configuration parameters.
+///
+/// The validator signs the hashes of the blocks with the share of the threshold key
+/// read from the file at `path` if the consensus configuration specifies the threshold
+/// key set, see `ConsensusConfig::threshold_keys`. The file is written by
+/// `crypto::threshold::write_key_share_file` and should be protected like the other
+/// secret key files. Requires the `threshold-precommits` feature.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ThresholdKeyConfig {
+    /// Path to the file with the secret key share.
+    pub path: PathBuf,
+}
+
+/// Key derivation configuration parameters.
+///
+/// If the derivation path of a key is specified, the corresponding key file of the node
+/// configuration contains the encrypted master seed instead of the key, and the key
+/// is derived from the seed with the path, see `crypto::hd`. The seed file is written
+/// by `crypto::create_seed_file`. Both keys may be derived from the same seed file, in which
+/// case the consensus and service key passphrases must be equal.
+///
+/// A key is rotated by changing the rotation index in its path along with the
+/// public key in the configuration.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct KeyDerivationConfig {
+    /// Derivation path of the consensus key, for example `m/0'/0'`.
+    #[serde(default)]
+    pub consensus_key_path: Option<DerivationPath>,
+    /// Derivation path of the service key, for example `m/1'/0'`.
+    #[serde(default)]
+    pub service_key_path: Option<DerivationPath>,
+}
+
+/// Garbage collection configuration parameters.
+///
+/// If garbage collection is enabled, the node removes the data which is no longer
+/// needed: uncommitted transactions which stay in the pool for too long and consensus
+/// messages of the superseded rounds. Since this data is used by the consensus algorithm,
+/// garbage is collected on the node thread whenever a new round starts. Evicted transactions
+/// are recorded in the rejected transactions log, see [`Schema::rejected_transactions`].
+///
+/// [`Schema::rejected_transactions`]: ../blockchain/struct.Schema.html#method.rejected_transactions
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct GarbageCollectionConfig {
+    /// Maximum time in seconds an uncommitted transaction may stay in the pool.
+    /// Older transactions are evicted unless they are included into the known proposes.
+    /// Transactions are not evicted if not specified.
+    #[serde(default)]
+    pub max_pool_tx_age: Option<u64>,
+    /// Maximum number of heights an uncommitted transaction may stay in the pool.
+    /// Older transactions are evicted unless they are included into the known proposes.
+    /// Transactions are not evicted if not specified. Must be strictly larger than 0.
+    #[serde(default)]
+    pub max_pool_tx_heights: Option<u64>,
+    /// Number of the latest rounds of the current height, whose messages are kept
+    /// in the consensus messages cache. Messages the node is locked on are kept as well.
+    /// Messages are not removed if not specified. Must be strictly larger than 0.
+    #[serde(default)]
+    pub keep_rounds: Option<u32>,
+}
+
+/// Startup integrity check configuration parameters.
+///
+/// If the check is enabled, the node verifies the latest blocks and the state hash
+/// of the stored data before joining the consensus. Blocks must be linked to the previous
+/// ones and match the transaction roots in their headers, and the root hashes of the core
+/// and service tables must match the state hash of the latest block.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct IntegrityCheckConfig {
+    /// Number of the latest blocks which are verified.
+    pub blocks: u64,
+    /// Action taken if the stored data is corrupted.
+    #[serde(default)]
+    pub on_failure: IntegrityFailureAction,
+}
+
+/// Action taken by the node if the startup integrity check fails.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntegrityFailureAction {
+    /// The node refuses to start.
+    Refuse,
+    /// The node starts in the read-only mode: it serves the API, but does not join
+    /// the consensus and does not connect to the other peers.
+    ReadOnly,
+}
+
+impl Default for IntegrityFailureAction {
+    fn default() -> Self {
+        IntegrityFailureAction::Refuse
+    }
+}
+
+/// Database checkpoints configuration parameters.
+///
+/// If checkpoints are enabled, consistent hard-link copies of the database are created
+/// in the background after every `interval` committed blocks. Checkpoints are supported
+/// only by the RocksDB backend. Checkpoints are also served by read replicas,
+/// see [`ReadReplica`].
+///
+/// [`ReadReplica`]: struct.ReadReplica.html
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct CheckpointConfig {
+    /// Directory where checkpoints are stored. Should be located on the same filesystem
+    /// as the database, otherwise database files are copied instead of hard-linked.
+    pub directory: PathBuf,
+    /// Number of blocks between checkpoints. Must be strictly larger than 0.
+    pub interval: u64,
+    /// Number of the latest checkpoints that are kept, older ones are removed.
+    /// Must be strictly larger than 0.
+    pub keep_checkpoints: usize,
+}
+
+/// Automatic peer bans configuration parameters.
+///
+/// If automatic bans are enabled, each misbehaviour of a peer, such as an invalid message,
+/// a failed handshake or an undecodable frame, adds a penalty to the score of the peer
+/// IP address. Once the score reaches `max_score`, the address is banned: its connections
+/// are closed and new ones are rejected. Each following ban of the address lasts twice
+/// as long as the previous one, up to `max_ban_duration`. Bans can also be set and lifted
+/// manually via the private API, see [`PeerBans`].
+///
+/// [`PeerBans`]: ../events/bans/struct.PeerBans.html
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct PeerBansConfig {
+    /// Score at which the address is banned. Must be strictly larger than 0.
+    pub max_score: u32,
+    /// Number of points the score of an address decreases by every second.
+    pub score_decay: u32,
+    /// Duration of the first ban of an address in seconds. Must be strictly larger than 0.
+    pub ban_duration: u64,
+    /// Maximal duration of a ban in seconds. The escalation of the bans of an address
+    /// is reset if it has not been banned for this time. Must not be smaller than
+    /// `ban_duration`.
+    pub max_ban_duration: u64,
+}
+
+/// Database configuration parameters.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct DatabaseConfig {
+    /// Type of the database backend, e.g., `rocksdb` or `temporary`.
+    ///
+    /// Backends are looked up among the database factories registered
+    /// with `NodeBuilder::with_database_factory`. Defaults to `rocksdb`.
+    #[serde(rename = "type", default = "DatabaseConfig::default_db_type")]
+    pub db_type: String,
+    /// Whether values stored in the database are encrypted.
+    ///
+    /// The encryption key is derived from the passphrase requested on the node start.
+    /// Encryption can only be enabled for a new database and cannot be combined
+    /// with `options.column_families`. Keys and index names are not encrypted,
+    /// so the structure of the database stays visible. Defaults to `false`.
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Prefixes of the names of the secondary indexes, e.g., the lookup indexes
+    /// of a service.
+    ///
+    /// Changes of the secondary indexes are written in a background thread in parallel
+    /// with the rest of the merged changes, see `PipelinedDB`. The secondary indexes
+    /// may lose the changes of the latest block if the node crashes. Defaults
+    /// to an empty list, meaning that the changes are written at once.
+    #[serde(default)]
+    pub secondary_indexes: Vec<String>,
+    /// Options passed to the database backend.
+    // Should follow the fields serialized as values, since TOML requires the column
+    // families, which are serialized as tables, to follow other values.
+    #[serde(flatten)]
+    pub options: DbOptions,
+    /// Compression of the values stored in the database. Compression can only be enabled
+    /// for a new database, but the algorithm and the threshold may be changed afterwards.
+    /// Defaults to `None`, meaning that values are not compressed.
+    #[serde(default)]
+    pub compression: Option<CompressionOptions>,
+    /// In-memory caches of the recently read entries of the frequently read indexes,
+    /// e.g., `core.configs` or the wallets of a service. Caches are invalidated
+    /// when the changed entries are merged. Defaults to an empty list.
+    #[serde(default)]
+    pub caches: Vec<CacheOptions>,
+}
+
+impl DatabaseConfig {
+    fn default_db_type() -> String {
+        ROCKSDB_DATABASE_TYPE.to_owned()
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            db_type: Self::default_db_type(),
+            options: DbOptions::default(),
+            encrypted: false,
+            secondary_indexes: Vec::new(),
+            compression: None,
+            caches: Vec::new(),
         }
     }
 }
@@ -262,11 +783,89 @@ pub struct NodeConfig<T = SecretKey> {
     pub services_configs: BTreeMap<String, Value>,
     /// Optional database configuration.
     #[serde(default)]
-    pub database: DbOptions,
+    pub database: DatabaseConfig,
     /// Node's ConnectList.
     pub connect_list: ConnectListConfig,
     /// Transaction Verification Thread Pool size.
     pub thread_pool_size: Option<u8>,
+    /// Maximum number of the incoming messages verified in the thread pool at the same
+    /// time. Further messages wait in the internal requests queue until some of the pending
+    /// ones are verified. The transactions submitted via the API are counted as well and
+    /// rejected with the `503 Service Unavailable` status while the limit is reached.
+    /// The number is not limited if not specified. Must be strictly larger than 0.
+    #[serde(default)]
+    pub max_pending_verifications: Option<usize>,
+    /// Optional pruning configuration. Pruning is disabled if not specified.
+    #[serde(default)]
+    pub pruning: Option<PruningConfig>,
+    /// Optional database checkpoints configuration. Checkpoints are disabled if not specified.
+    #[serde(default)]
+    pub checkpoints: Option<CheckpointConfig>,
+    /// Optional garbage collection configuration. Garbage collection is disabled
+    /// if not specified.
+    #[serde(default)]
+    pub garbage_collection: Option<GarbageCollectionConfig>,
+    /// Optional startup integrity check configuration. The stored data is not verified
+    /// if not specified.
+    #[serde(default)]
+    pub integrity_check: Option<IntegrityCheckConfig>,
+    /// Optional state history configuration. Only the latest state is kept
+    /// if not specified.
+    #[serde(default)]
+    pub state_history: Option<StateHistoryConfig>,
+    /// Optional validator liveness tracking configuration. Liveness is not tracked
+    /// if not specified.
+    #[serde(default)]
+    pub liveness: Option<LivenessConfig>,
+    /// Optional parallel transaction execution configuration. If parallel execution
+    /// is enabled by the consensus configuration, transactions are executed on a single
+    /// thread if not specified.
+    #[serde(default)]
+    pub parallel_execution: Option<ParallelExecutionConfig>,
+    /// Optional consensus stall watchdog configuration. Stalls are not detected
+    /// if not specified.
+    #[serde(default)]
+    pub watchdog: Option<WatchdogConfig>,
+    /// Optional catch-up mode configuration. Blocks are requested one by one
+    /// if not specified.
+    #[serde(default)]
+    pub catch_up: Option<CatchUpConfig>,
+    /// Optional consensus diagnostics configuration. Diagnostics are not recorded
+    /// if not specified.
+    #[serde(default)]
+    pub diagnostics: Option<DiagnosticsConfig>,
+    /// Optional peer statistics configuration. Peer statistics are not recorded
+    /// if not specified.
+    #[serde(default)]
+    pub peer_stats: Option<PeerStatsConfig>,
+    /// Optional signing record configuration. Signed votes are not recorded
+    /// if not specified.
+    #[serde(default)]
+    pub signing_record: Option<SigningRecordConfig>,
+    /// Optional remote signer configuration. Messages are signed with the secret keys
+    /// read by the node if not specified.
+    #[serde(default)]
+    pub remote_signer: Option<RemoteSignerConfig>,
+    /// Optional threshold signature key configuration. The node does not sign
+    /// threshold signature shares if not specified.
+    #[serde(default)]
+    pub threshold_key: Option<ThresholdKeyConfig>,
+    /// Optional key derivation configuration. The secret keys are read from the key files
+    /// if not specified.
+    #[serde(default)]
+    pub key_derivation: Option<KeyDerivationConfig>,
+    /// Optional automatic peer bans configuration. Misbehaving peers are not banned
+    /// automatically if not specified.
+    #[serde(default)]
+    pub peer_bans: Option<PeerBansConfig>,
+    /// Optional proxy configuration of the outgoing connections to peers. The node
+    /// connects to peers directly if not specified.
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Optional connect list watch configuration. Changes of the connect list in
+    /// the configuration file are not applied until the node restart if not specified.
+    #[serde(default)]
+    pub connect_list_watch: Option<ConnectListWatchConfig>,
 }
 
 impl NodeConfig<PathBuf> {
@@ -289,12 +888,21 @@ impl NodeConfig<PathBuf> {
             config_folder.join(&self.service_secret_key)
         };
 
-        let consensus_secret_key = read_keys_from_file(&consensus_key_path, consensus_passphrase)
-            .expect("Could not read consensus_secret_key from file")
-            .1;
-        let service_secret_key = read_keys_from_file(&service_key_path, service_passphrase)
-            .expect("Could not read service_secret_key from file")
-            .1;
+        let key_derivation = self.key_derivation.clone().unwrap_or_default();
+        let consensus_secret_key = read_secret_key(
+            &consensus_key_path,
+            consensus_passphrase,
+            key_derivation.consensus_key_path.as_ref(),
+            self.consensus_public_key,
+        )
+        .expect("Could not read consensus_secret_key from file");
+        let service_secret_key = read_secret_key(
+            &service_key_path,
+            service_passphrase,
+            key_derivation.service_key_path.as_ref(),
+            self.service_public_key,
+        )
+        .expect("Could not read service_secret_key from file");
         NodeConfig {
             consensus_secret_key,
             service_secret_key,
@@ -310,10 +918,54 @@ impl NodeConfig<PathBuf> {
             database: self.database,
             connect_list: self.connect_list,
             thread_pool_size: self.thread_pool_size,
+            max_pending_verifications: self.max_pending_verifications,
+            pruning: self.pruning,
+            checkpoints: self.checkpoints,
+            garbage_collection: self.garbage_collection,
+            integrity_check: self.integrity_check,
+            state_history: self.state_history,
+            liveness: self.liveness,
+            parallel_execution: self.parallel_execution,
+            watchdog: self.watchdog,
+            catch_up: self.catch_up,
+            diagnostics: self.diagnostics,
+            peer_stats: self.peer_stats,
+            signing_record: self.signing_record,
+            remote_signer: self.remote_signer,
+            threshold_key: self.threshold_key,
+            key_derivation: self.key_derivation,
+            peer_bans: self.peer_bans,
+            proxy: self.proxy,
+            connect_list_watch: self.connect_list_watch,
         }
     }
 }
 
+/// Reads the secret key from the key file or derives it from the seed file if
+/// the derivation path is specified. The derived key is checked against the public key
+/// of the configuration, while the key file contains the public key itself.
+fn read_secret_key(
+    path: &Path,
+    passphrase: &[u8],
+    derivation_path: Option<&DerivationPath>,
+    public_key: PublicKey,
+) -> io::Result<SecretKey> {
+    let derivation_path = match derivation_path {
+        Some(derivation_path) => derivation_path,
+        None => return read_keys_from_file(path, passphrase).map(|keys| keys.1),
+    };
+    let (derived_public_key, secret_key) =
+        read_derived_keys_from_file(path, passphrase, derivation_path)?;
+    if derived_public_key == public_key {
+        Ok(secret_key)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "Derived key does not match the public key",
+        ))
+    }
+}
+
 impl<T> NodeConfig<T> {
     fn validate_or_panic(&self) {
         let capacity = &self.mempool.events_pool_capacity;
@@ -343,6 +995,119 @@ impl<T> NodeConfig<T> {
                 capacity.network_requests_capacity, sanity_max,
             );
         }
+        if self.mempool.max_transactions == Some(0) {
+            panic!("mempool.max_transactions must be strictly larger than 0");
+        }
+        if self.mempool.max_bytes == Some(0) {
+            panic!("mempool.max_bytes must be strictly larger than 0");
+        }
+        if let Some(ref pruning) = self.pruning {
+            if pruning.keep_blocks == 0 {
+                panic!("pruning.keep_blocks must be strictly larger than 0");
+            }
+        }
+        if let Some(ref state_history) = self.state_history {
+            if state_history.keep_heights == 0 {
+                panic!("state_history.keep_heights must be strictly larger than 0");
+            }
+        }
+        if let Some(ref liveness) = self.liveness {
+            if liveness.window == 0 {
+                panic!("liveness.window must be strictly larger than 0");
+            }
+            if liveness.min_uptime_percent > 100 {
+                panic!("liveness.min_uptime_percent must not be larger than 100");
+            }
+        }
+        if self.max_pending_verifications == Some(0) {
+            panic!("max_pending_verifications must be strictly larger than 0");
+        }
+        if let Some(ref parallel_execution) = self.parallel_execution {
+            if parallel_execution.threads == 0 {
+                panic!("parallel_execution.threads must be strictly larger than 0");
+            }
+        }
+        if let Some(ref watchdog) = self.watchdog {
+            if watchdog.stall_timeout == 0 {
+                panic!("watchdog.stall_timeout must be strictly larger than 0");
+            }
+        }
+        if let Some(ref remote_signer) = self.remote_signer {
+            if remote_signer.timeout == 0 {
+                panic!("remote_signer.timeout must be strictly larger than 0");
+            }
+            if remote_signer.max_reconnect_delay == 0 {
+                panic!("remote_signer.max_reconnect_delay must be strictly larger than 0");
+            }
+        }
+        #[cfg(not(feature = "threshold-precommits"))]
+        {
+            if self.threshold_key.is_some() {
+                panic!("threshold_key requires the threshold-precommits feature");
+            }
+        }
+        if self.network.max_message_len == Some(0) {
+            panic!("network.max_message_len must be strictly larger than 0");
+        }
+        if self.network.peer_send_rate == Some(0) {
+            panic!("network.peer_send_rate must be strictly larger than 0");
+        }
+        if self.network.peer_receive_rate == Some(0) {
+            panic!("network.peer_receive_rate must be strictly larger than 0");
+        }
+        if self.network.peer_queue_len == Some(0) {
+            panic!("network.peer_queue_len must be strictly larger than 0");
+        }
+        if let Some(ref catch_up) = self.catch_up {
+            if catch_up.min_lag == 0 {
+                panic!("catch_up.min_lag must be strictly larger than 0");
+            }
+            if catch_up.max_blocks_ahead == 0 {
+                panic!("catch_up.max_blocks_ahead must be strictly larger than 0");
+            }
+        }
+        if let Some(ref diagnostics) = self.diagnostics {
+            if diagnostics.keep_heights == 0 {
+                panic!("diagnostics.keep_heights must be strictly larger than 0");
+            }
+        }
+        if let Some(ref peer_stats) = self.peer_stats {
+            if peer_stats.keep_events == 0 {
+                panic!("peer_stats.keep_events must be strictly larger than 0");
+            }
+        }
+        if let Some(ref checkpoints) = self.checkpoints {
+            if checkpoints.interval == 0 {
+                panic!("checkpoints.interval must be strictly larger than 0");
+            }
+            if checkpoints.keep_checkpoints == 0 {
+                panic!("checkpoints.keep_checkpoints must be strictly larger than 0");
+            }
+        }
+        if let Some(ref peer_bans) = self.peer_bans {
+            if peer_bans.max_score == 0 {
+                panic!("peer_bans.max_score must be strictly larger than 0");
+            }
+            if peer_bans.ban_duration == 0 {
+                panic!("peer_bans.ban_duration must be strictly larger than 0");
+            }
+            if peer_bans.max_ban_duration < peer_bans.ban_duration {
+                panic!("peer_bans.max_ban_duration must not be smaller than ban_duration");
+            }
+        }
+        if let Some(ref connect_list_watch) = self.connect_list_watch {
+            if connect_list_watch.interval == 0 {
+                panic!("connect_list_watch.interval must be strictly larger than 0");
+            }
+        }
+        if let Some(ref gc) = self.garbage_collection {
+            if gc.keep_rounds == Some(0) {
+                panic!("garbage_collection.keep_rounds must be strictly larger than 0");
+            }
+            if gc.max_pool_tx_heights == Some(0) {
+                panic!("garbage_collection.max_pool_tx_heights must be strictly larger than 0");
+            }
+        }
     }
 }
 
@@ -359,6 +1124,28 @@ pub struct Configuration {
     pub peer_discovery: Vec<String>,
     /// Memory pool configuration.
     pub mempool: MemoryPoolConfig,
+    /// Pruning configuration.
+    pub pruning: Option<PruningConfig>,
+    /// Database checkpoints configuration.
+    pub checkpoints: Option<CheckpointConfig>,
+    /// Garbage collection configuration.
+    pub garbage_collection: Option<GarbageCollectionConfig>,
+    /// Consensus stall watchdog configuration.
+    pub watchdog: Option<WatchdogConfig>,
+    /// Connect list watch configuration.
+    pub connect_list_watch: Option<ConnectListWatchConfig>,
+    /// Catch-up mode configuration.
+    pub catch_up: Option<CatchUpConfig>,
+    /// Consensus diagnostics configuration.
+    pub diagnostics: Option<DiagnosticsConfig>,
+    /// Peer statistics configuration.
+    pub peer_stats: Option<PeerStatsConfig>,
+    /// Signing record configuration.
+    pub signing_record: Option<SigningRecordConfig>,
+    /// Remote signer configuration.
+    pub remote_signer: Option<RemoteSignerConfig>,
+    /// Threshold signature key configuration.
+    pub threshold_key: Option<ThresholdKeyConfig>,
 }
 
 /// Channel for messages, timeouts and api requests.
@@ -429,6 +1216,7 @@ impl ConnectListConfig {
             .map(|config| ConnectInfo {
                 public_key: config.validator_keys.consensus_key,
                 address: config.address.clone(),
+                fallback_addresses: Vec::new(),
             })
             .collect();
 
@@ -442,6 +1230,7 @@ impl ConnectListConfig {
             .zip(validators_keys.iter())
             .map(|(a, v)| ConnectInfo {
                 address: a.clone(),
+                fallback_addresses: Vec::new(),
                 public_key: v.consensus_key,
             })
             .collect();
@@ -462,10 +1251,23 @@ impl ConnectListConfig {
     }
 }
 
+/// Connect list watch configuration parameters.
+///
+/// If the watch is enabled, the node periodically checks the connect list in its
+/// configuration file and applies the changes at runtime: the node disconnects from
+/// the removed peers and connects to the added ones. The watch requires the node
+/// to be started with the path to its configuration file.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ConnectListWatchConfig {
+    /// Interval in milliseconds between the checks of the configuration file.
+    /// Must be strictly larger than 0.
+    pub interval: Milliseconds,
+}
+
 impl NodeHandler {
     /// Creates `NodeHandler` using specified `Configuration`.
     pub fn new(
-        blockchain: Blockchain,
+        mut blockchain: Blockchain,
         external_address: &str,
         sender: NodeSender,
         system_state: Box<dyn SystemStateProvider>,
@@ -480,7 +1282,9 @@ impl NodeHandler {
 
         let snapshot = blockchain.snapshot();
 
-        let stored = Schema::new(&snapshot).actual_configuration();
+        let schema = Schema::new(&snapshot);
+        let stored = schema.actual_configuration();
+        let network_id = schema.network_id();
         info!("Creating a node with config: {:#?}", stored);
 
         let validator_id = stored
@@ -489,15 +1293,35 @@ impl NodeHandler {
             .position(|pk| pk.consensus_key == config.listener.consensus_public_key)
             .map(|id| ValidatorId(id as u16));
         info!("Validator id = '{:?}'", validator_id);
-        let connect = Message::concrete(
+
+        let signer: Box<dyn Signer> = match config.remote_signer {
+            Some(remote_signer) => {
+                let signer = RemoteSigner::new(
+                    config.listener.consensus_public_key,
+                    remote_signer,
+                    api_state.metrics().clone(),
+                );
+                let service_signer = signer.with_key(config.service.service_public_key);
+                blockchain.set_service_signer(Some(Arc::new(service_signer)));
+                Box::new(signer)
+            }
+            None => Box::new(LocalSigner::new(
+                config.listener.consensus_public_key,
+                config.listener.consensus_secret_key.clone(),
+            )),
+        };
+        let connect = Message::concrete_with_signer(
             Connect::new(
                 external_address,
                 system_state.current_time().into(),
                 &user_agent::get(),
-            ),
-            config.listener.consensus_public_key,
-            &config.listener.consensus_secret_key,
-        );
+            )
+            .with_compression(config.network.compression),
+            // `Connect` messages never cover the network identifier.
+            None,
+            &*signer,
+        )
+        .expect("Unable to sign the Connect message");
 
         let connect_list = config.listener.connect_list;
         let state = State::new(
@@ -508,6 +1332,7 @@ impl NodeHandler {
             config.service.service_secret_key,
             connect_list,
             stored,
+            network_id,
             connect,
             blockchain.get_saved_peers(),
             last_hash,
@@ -518,12 +1343,37 @@ impl NodeHandler {
         let node_role = NodeRole::new(validator_id);
         let is_enabled = api_state.is_enabled();
         api_state.set_node_role(node_role);
-
-        let config_manager = match config_file_path {
-            Some(path) => Some(ConfigManager::new(path)),
-            None => None,
+        api_state.set_tx_cache(state.shared_tx_cache());
+
+        let config_manager = match (config_file_path, config.connect_list_watch) {
+            (Some(path), Some(watch)) => Some(ConfigManager::with_watch(
+                path,
+                watch,
+                ApiSender::new(sender.api_requests.get_ref().clone()),
+            )),
+            (Some(path), None) => Some(ConfigManager::new(path)),
+            (None, _) => None,
         };
 
+        let checkpointer = config
+            .checkpoints
+            .map(|checkpoints| Checkpointer::new(Arc::clone(blockchain.database()), checkpoints));
+
+        let signing_record = config.signing_record.map(|config| {
+            let record = SigningRecord::load(config).expect("Unable to load the signing record");
+            if let Some(height) = record.height() {
+                if height > last_height {
+                    warn!(
+                        "The node has signed votes at height {}, while the blockchain is \
+                         at height {}. The database may be restored from a stale backup, \
+                         the node does not vote until it reaches height {}",
+                        height, last_height, height
+                    );
+                }
+            }
+            record
+        });
+
         Self {
             blockchain,
             api_state,
@@ -535,15 +1385,40 @@ impl NodeHandler {
             node_role,
             config_manager,
             allow_expedited_propose: true,
+            skipped_propose: None,
+            pruning: config.pruning,
+            checkpointer,
+            gc: config.garbage_collection.map(GarbageCollector::new),
+            pool_limits: PoolLimits::new(&config.mempool),
+            latency: LatencyEstimator::default(),
+            clock_skew: ClockSkewEstimator::default(),
+            round_changes: RoundChanges::default(),
+            lagging_validators: HashSet::new(),
+            watchdog: config.watchdog.map(Watchdog::new),
+            catch_up: config.catch_up.map(CatchUp::new),
+            pause: None,
+            diagnostics: config.diagnostics.map(Diagnostics::new),
+            peer_stats: config.peer_stats.map(PeerStatsRecorder::new),
+            signing_record,
+            signer,
+            #[cfg(feature = "threshold-precommits")]
+            threshold_key: config.threshold_key.map(|config| {
+                crypto::threshold::read_key_share_file(&config.path)
+                    .expect("Unable to read the threshold key share")
+            }),
         }
     }
 
-    fn sign_message<T: ProtocolMessage>(&self, message: T) -> Signed<T> {
-        Message::concrete(
-            message,
-            *self.state.consensus_public_key(),
-            self.state.consensus_secret_key(),
-        )
+    /// Signs the message with the consensus key. Returns `None` if the signer has failed,
+    /// in which case the message should not be sent.
+    fn sign_message<T: ProtocolMessage>(&self, message: T) -> Option<Signed<T>> {
+        match Message::concrete_with_signer(message, self.state.network_id(), &*self.signer) {
+            Ok(message) => Some(message),
+            Err(e) => {
+                error!("Unable to sign the message: {}", e);
+                None
+            }
+        }
     }
 
     /// Return internal `SharedNodeState`
@@ -551,16 +1426,16 @@ impl NodeHandler {
         &self.api_state
     }
 
-    /// Returns value of the `first_round_timeout` field from the current `ConsensusConfig`.
+    /// Returns the timeout of the first round, which is chosen by the adaptive timeout policy
+    /// or equal to the `first_round_timeout` field from the current `ConsensusConfig`.
     pub fn first_round_timeout(&self) -> Milliseconds {
-        self.state().consensus_config().first_round_timeout
+        self.adaptive_round_timeout()
+            .unwrap_or_else(|| self.state().consensus_config().first_round_timeout)
     }
 
-    /// Returns value of the `round_timeout_increase` field from the current `ConsensusConfig`.
+    /// Returns the increase of the timeout of each next round.
     pub fn round_timeout_increase(&self) -> Milliseconds {
-        (self.state().consensus_config().first_round_timeout
-            * ConsensusConfig::TIMEOUT_LINEAR_INCREASE_PERCENT)
-            / 100
+        (self.first_round_timeout() * ConsensusConfig::TIMEOUT_LINEAR_INCREASE_PERCENT) / 100
     }
 
     /// Returns value of the `status_timeout` field from the current `ConsensusConfig`.
@@ -645,6 +1520,7 @@ impl NodeHandler {
         self.add_status_timeout();
         self.add_peer_exchange_timeout();
         self.add_update_api_state_timeout();
+        self.add_watchdog_timeout();
     }
 
     /// Sends the given message to a peer by its public key.
@@ -693,6 +1569,7 @@ impl NodeHandler {
     pub fn request(&mut self, data: RequestData, peer: PublicKey) {
         let is_new = self.state.request(data.clone(), peer);
         if is_new {
+            self.request_sent(data.clone());
             self.add_request_timeout(data, None);
         }
     }
@@ -745,6 +1622,37 @@ impl NodeHandler {
         pending_tx_count >= u64::from(self.propose_timeout_threshold())
     }
 
+    /// Returns the time until which the leader does not propose blocks because of
+    /// the minimal block interval, or `None` if the interval has passed.
+    fn paced_until(&self) -> Option<SystemTime> {
+        let min_block_interval = self.state.consensus_config().min_block_interval?;
+        let paced_until =
+            self.state.height_start_time() + Duration::from_millis(min_block_interval);
+        if self.system_state.current_time() < paced_until {
+            Some(paced_until)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the time until which the leader does not propose empty blocks,
+    /// or `None` if the node should propose a block now.
+    fn idle_until(&self) -> Option<SystemTime> {
+        let max_idle_interval = self.state.consensus_config().max_idle_interval?;
+        let snapshot = self.blockchain.snapshot();
+        let pending_tx_count =
+            Schema::new(&snapshot).transactions_pool_len() + self.state.tx_cache_len() as u64;
+        if pending_tx_count > 0 {
+            return None;
+        }
+        let idle_until = self.state.height_start_time() + Duration::from_millis(max_idle_interval);
+        if self.system_state.current_time() < idle_until {
+            Some(idle_until)
+        } else {
+            None
+        }
+    }
+
     /// Adds `NodeTimeout::Status` timeout to the channel.
     pub fn add_status_timeout(&mut self) {
         let time = self.system_state.current_time() + Duration::from_millis(self.status_timeout());
@@ -783,6 +1691,43 @@ impl NodeHandler {
         self.blockchain.pool_size() + self.state.tx_cache_len() as u64
     }
 
+    /// Returns the consensus state of the node for debugging purposes.
+    pub fn consensus_state_info(&self) -> ConsensusStateInfo {
+        let votes_info = |counts: BTreeMap<(Round, Hash), usize>| {
+            counts
+                .into_iter()
+                .map(|((round, hash), count)| VotesInfo { round, hash, count })
+                .collect()
+        };
+        let propose_timeout = if self.need_faster_propose() {
+            self.min_propose_timeout()
+        } else {
+            self.max_propose_timeout()
+        };
+        let round = self.state.round();
+        // Round is zero only before the node initialization.
+        let timeouts_round = cmp::max(round, Round::first());
+
+        ConsensusStateInfo {
+            height: self.state.height(),
+            round,
+            leader: self.state.leader(round),
+            validator_id: self.state.validator_id(),
+            locked_round: self.state.locked_round(),
+            locked_propose: self.state.locked_propose(),
+            majority_count: self.state.majority_count(),
+            prevotes: votes_info(self.state.prevotes_counts()),
+            precommits: votes_info(self.state.precommits_counts()),
+            timeouts: ConsensusTimeoutsInfo {
+                height_start_time: self.state.height_start_time().into(),
+                round_start_time: self.round_start_time(timeouts_round).into(),
+                next_round_start_time: self.round_start_time(timeouts_round.next()).into(),
+                propose_timeout,
+                status_timeout: self.status_timeout(),
+            },
+        }
+    }
+
     /// Returns start time of the requested round.
     pub fn round_start_time(&self, round: Round) -> SystemTime {
         // Round start time = H + (r - 1) * t0 + (r-1)(r-2)/2 * dt
@@ -820,6 +1765,12 @@ impl ApiSender {
         self.send_external_message(msg)
     }
 
+    /// Removes peer from the connect list and drops the connection with it.
+    pub fn peer_remove(&self, public_key: PublicKey) -> Result<(), Error> {
+        let msg = ExternalMessage::PeerRemove(public_key);
+        self.send_external_message(msg)
+    }
+
     /// Sends an external message.
     pub fn send_external_message(&self, message: ExternalMessage) -> Result<(), Error> {
         self.0
@@ -835,6 +1786,30 @@ impl ApiSender {
         let msg = ExternalMessage::Transaction(tx);
         self.send_external_message(msg)
     }
+
+    /// Removes an uncommitted transaction from the pool of the node. The returned
+    /// receiver resolves with the outcome of the removal once the node processes
+    /// the request.
+    pub fn remove_transaction(
+        &self,
+        tx_hash: Hash,
+    ) -> Result<oneshot::Receiver<Result<(), RemoveTransactionError>>, Error> {
+        let (result_tx, result_rx) = oneshot::channel();
+        let msg = ExternalMessage::RemoveTransaction(tx_hash, result_tx);
+        self.send_external_message(msg)?;
+        Ok(result_rx)
+    }
+
+    /// Pauses the message processing and the block production of the node. The node
+    /// keeps its connections and the transactions received so far.
+    pub fn pause(&self) -> Result<(), Error> {
+        self.send_external_message(ExternalMessage::Pause)
+    }
+
+    /// Resumes the paused node.
+    pub fn resume(&self) -> Result<(), Error> {
+        self.send_external_message(ExternalMessage::Resume)
+    }
 }
 
 impl fmt::Debug for ApiSender {
@@ -848,6 +1823,10 @@ impl fmt::Debug for ApiSender {
 pub struct ConnectInfo {
     /// Peer address.
     pub address: String,
+    /// Alternative addresses of the peer, such as its IPv6 address or DNS name, which are
+    /// tried if the peer cannot be reached at `address`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_addresses: Vec<String>,
     /// Peer public key.
     pub public_key: PublicKey,
 }
@@ -894,14 +1873,33 @@ pub struct NodeChannel {
 }
 
 /// Node that contains handler (`NodeHandler`) and `NodeApiConfig`.
-#[derive(Debug)]
 pub struct Node {
     api_options: NodeApiConfig,
+    app_configs: Vec<(ApiAccess, AppConfig)>,
     network_config: NetworkConfiguration,
+    proxy: ProxyConfig,
     handler: NodeHandler,
     channel: NodeChannel,
     max_message_len: u32,
     thread_pool_size: Option<u8>,
+    max_pending_verifications: Option<usize>,
+    read_only: bool,
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("api_options", &self.api_options)
+            .field("network_config", &self.network_config)
+            .field("proxy", &self.proxy)
+            .field("handler", &self.handler)
+            .field("channel", &self.channel)
+            .field("max_message_len", &self.max_message_len)
+            .field("thread_pool_size", &self.thread_pool_size)
+            .field("max_pending_verifications", &self.max_pending_verifications)
+            .field("read_only", &self.read_only)
+            .finish()
+    }
 }
 
 impl NodeChannel {
@@ -934,6 +1932,100 @@ impl Node {
         node_cfg: NodeConfig,
         config_file_path: Option<String>,
     ) -> Self {
+        Self::with_initializer(db, services, node_cfg, config_file_path, |_| Ok(()))
+            .expect("Unable to create node")
+    }
+
+    /// Creates node for the given services and node configuration, importing the state
+    /// from the given snapshot. The node then synchronizes only the blocks committed
+    /// after the snapshot height.
+    ///
+    /// The database must not contain any blocks except for the genesis one.
+    pub fn from_snapshot<D, R>(
+        db: D,
+        services: Vec<Box<dyn Service>>,
+        node_cfg: NodeConfig,
+        config_file_path: Option<String>,
+        snapshot: &mut R,
+    ) -> Result<Self, Error>
+    where
+        D: Into<Arc<dyn Database>>,
+        R: Read,
+    {
+        Self::with_initializer(db, services, node_cfg, config_file_path, |blockchain| {
+            let header = snapshot::import_snapshot(blockchain, snapshot)?;
+            info!(
+                "Imported state snapshot at height {}, block hash {:?}",
+                header.height, header.block_hash
+            );
+            Ok(())
+        })
+    }
+
+    /// Creates node for the given services and node configuration, importing the blocks
+    /// from the given block archive. The node then synchronizes only the blocks committed
+    /// after the last archived block.
+    ///
+    /// The blocks already present in the database are skipped if they match the archived ones.
+    pub fn from_block_archive<D, R>(
+        db: D,
+        services: Vec<Box<dyn Service>>,
+        node_cfg: NodeConfig,
+        config_file_path: Option<String>,
+        archive: &mut R,
+    ) -> Result<Self, Error>
+    where
+        D: Into<Arc<dyn Database>>,
+        R: Read,
+    {
+        Self::with_initializer(db, services, node_cfg, config_file_path, |blockchain| {
+            let committed = archive::import_blocks(blockchain, archive)?;
+            info!(
+                "Imported {} blocks from the archive, the latest height is {}",
+                committed,
+                Schema::new(&blockchain.snapshot()).height()
+            );
+            Ok(())
+        })
+    }
+
+    /// Checks that the validator can sign the threshold signature shares if the actual
+    /// or the scheduled consensus configuration specifies the threshold key set.
+    /// Otherwise, the blocks could not be certified by the validators.
+    fn check_threshold_key(blockchain: &Blockchain, node_cfg: &NodeConfig) -> Result<(), Error> {
+        let snapshot = blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        let configs =
+            iter::once(schema.actual_configuration()).chain(schema.following_configuration());
+        for config in configs {
+            let is_validator = config
+                .validator_keys
+                .iter()
+                .any(|keys| keys.consensus_key == node_cfg.consensus_public_key);
+            if is_validator && config.consensus.threshold_keys.is_some() {
+                ensure!(
+                    cfg!(feature = "threshold-precommits") && node_cfg.threshold_key.is_some(),
+                    "The consensus configuration actual from height {} specifies threshold_keys, \
+                     but the validator has no share of the threshold key; set threshold_key \
+                     in the node configuration and enable the threshold-precommits feature",
+                    config.actual_from
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn with_initializer<D, F>(
+        db: D,
+        services: Vec<Box<dyn Service>>,
+        node_cfg: NodeConfig,
+        config_file_path: Option<String>,
+        initializer: F,
+    ) -> Result<Self, Error>
+    where
+        D: Into<Arc<dyn Database>>,
+        F: FnOnce(&mut Blockchain) -> Result<(), Error>,
+    {
         crypto::init();
 
         node_cfg.validate_or_panic();
@@ -946,7 +2038,31 @@ impl Node {
             node_cfg.service_secret_key.clone(),
             ApiSender::new(channel.api_requests.0.clone()),
         );
-        blockchain.initialize(node_cfg.genesis.clone()).unwrap();
+        blockchain.set_state_history(node_cfg.state_history);
+        blockchain.set_liveness(node_cfg.liveness);
+        blockchain.set_parallel_execution(node_cfg.parallel_execution);
+        blockchain.initialize(node_cfg.genesis.clone())?;
+        initializer(&mut blockchain)?;
+        Self::check_threshold_key(&blockchain, &node_cfg)?;
+
+        let mut read_only = false;
+        if let Some(integrity_check) = node_cfg.integrity_check {
+            if let Err(e) = blockchain.check_integrity(integrity_check.blocks) {
+                match integrity_check.on_failure {
+                    IntegrityFailureAction::Refuse => {
+                        return Err(format_err!("Integrity check of the database failed: {}", e));
+                    }
+                    IntegrityFailureAction::ReadOnly => {
+                        error!(
+                            "Integrity check of the database failed, the node is started \
+                             in the read-only mode: {}",
+                            e
+                        );
+                        read_only = true;
+                    }
+                }
+            }
+        }
 
         let peers = node_cfg.connect_list.addresses();
 
@@ -964,9 +2080,24 @@ impl Node {
             mempool: node_cfg.mempool,
             network: node_cfg.network,
             peer_discovery: peers,
+            pruning: node_cfg.pruning,
+            checkpoints: node_cfg.checkpoints,
+            garbage_collection: node_cfg.garbage_collection,
+            watchdog: node_cfg.watchdog,
+            connect_list_watch: node_cfg.connect_list_watch,
+            catch_up: node_cfg.catch_up,
+            diagnostics: node_cfg.diagnostics,
+            peer_stats: node_cfg.peer_stats,
+            signing_record: node_cfg.signing_record,
+            remote_signer: node_cfg.remote_signer,
+            threshold_key: node_cfg.threshold_key,
         };
 
         let api_state = SharedNodeState::new(node_cfg.api.state_update_timeout as u64);
+        api_state
+            .verification_metrics()
+            .set_max_pending(node_cfg.max_pending_verifications);
+        api_state.peer_bans().set_config(node_cfg.peer_bans);
         let system_state = Box::new(DefaultSystemState(node_cfg.listen_address));
         let network_config = config.network;
         let handler = NodeHandler::new(
@@ -978,14 +2109,35 @@ impl Node {
             api_state,
             config_file_path,
         );
-        Self {
+        Ok(Self {
             api_options: node_cfg.api,
+            app_configs: Vec::new(),
             handler,
             channel,
             network_config,
-            max_message_len: node_cfg.genesis.consensus.max_message_len,
+            proxy: node_cfg.proxy.unwrap_or_default(),
+            max_message_len: network_config
+                .max_message_len
+                .unwrap_or(node_cfg.genesis.consensus.max_message_len),
             thread_pool_size: node_cfg.thread_pool_size,
-        }
+            max_pending_verifications: node_cfg.max_pending_verifications,
+            read_only,
+        })
+    }
+
+    /// Returns `true` if the node has been started in the read-only mode because
+    /// the startup integrity check failed. Such a node serves the API, but does not
+    /// join the consensus.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Adds the configuration hook for the `actix-web` application serving the API
+    /// with the given access level. Hooks may be used to install middleware for the whole
+    /// public or private API server, and are applied in the order they were added.
+    pub fn with_app_config(mut self, access: ApiAccess, app_config: AppConfig) -> Self {
+        self.app_configs.push((access, app_config));
+        self
     }
 
     /// Launches only consensus messages handler.
@@ -1031,53 +2183,23 @@ impl Node {
         trace!("Running node.");
         let api_state = self.handler.api_state.clone();
         // Runs actix-web api.
-        let actix_api_runtime = SystemRuntimeConfig {
-            api_runtimes: {
-                fn into_app_config(allow_origin: AllowOrigin) -> AppConfig {
-                    let app_config = move |app: App| -> App {
-                        let cors = Cors::from(allow_origin.clone());
-                        app.middleware(cors)
-                    };
-                    Arc::new(app_config)
-                };
-
-                let public_api_handler = self
-                    .api_options
-                    .public_api_address
-                    .map(|listen_address| ApiRuntimeConfig {
-                        listen_address,
-                        access: ApiAccess::Public,
-                        app_config: self
-                            .api_options
-                            .public_allow_origin
-                            .clone()
-                            .map(into_app_config),
-                    })
-                    .into_iter();
-                let private_api_handler = self
-                    .api_options
-                    .private_api_address
-                    .map(|listen_address| ApiRuntimeConfig {
-                        listen_address,
-                        access: ApiAccess::Private,
-                        app_config: self
-                            .api_options
-                            .private_allow_origin
-                            .clone()
-                            .map(into_app_config),
-                    })
-                    .into_iter();
-                // Collects API handlers.
-                public_api_handler
-                    .chain(private_api_handler)
-                    .collect::<Vec<_>>()
-            },
-            api_aggregator: ApiAggregator::new(
-                self.handler.blockchain.clone(),
-                self.handler.api_state.clone(),
-            ),
+        let mut api_manager = ApiManager::new(
+            self.handler.blockchain.clone(),
+            api_state.clone(),
+            self.app_configs.clone(),
+            self.api_options.clone(),
+        );
+        api_manager.start()?;
+        let (api_manager_tx, api_manager_rx) = mpsc::unbounded();
+        api_state.set_api_manager(api_manager_tx.clone());
+        let api_manager_thread = thread::spawn(move || api_manager.run(api_manager_rx));
+
+        if self.read_only {
+            warn!("Node is running in the read-only mode, consensus is disabled");
+            return api_manager_thread
+                .join()
+                .map_err(|e| format_err!("Unable to join api manager thread: {:?}", e))?;
         }
-        .start()?;
 
         // Runs NodeHandler.
         let handshake_params = HandshakeParams::new(
@@ -1089,11 +2211,11 @@ impl Node {
         );
         self.run_handler(&handshake_params)?;
 
-        // Stop ws server.
-        api_state.shutdown_broadcast_server();
-
-        // Stops actix web runtime.
-        actix_api_runtime.stop()?;
+        // Stops ws servers and actix web runtime.
+        api_manager_tx.unbounded_send(ApiManagerRequest::Stop).ok();
+        api_manager_thread
+            .join()
+            .map_err(|e| format_err!("Unable to join api manager thread: {:?}", e))??;
 
         info!("Exonum node stopped");
         Ok(())
@@ -1102,6 +2224,8 @@ impl Node {
     fn into_reactor(self) -> (HandlerPart<NodeHandler>, NetworkPart, InternalPart) {
         let connect_message = self.state().our_connect_message().clone();
         let connect_list = self.state().connect_list().clone();
+        let verification_metrics = self.handler.api_state.verification_metrics().clone();
+        let peer_bans = self.handler.api_state.peer_bans().clone();
         let (network_tx, network_rx) = self.channel.network_events;
         let internal_requests_rx = self.channel.internal_requests.1;
         let network_part = NetworkPart {
@@ -1112,6 +2236,8 @@ impl Node {
             network_config: self.network_config,
             max_message_len: self.max_message_len,
             connect_list,
+            peer_bans: peer_bans.clone(),
+            proxy: self.proxy,
         };
 
         let (internal_tx, internal_rx) = self.channel.internal_events;
@@ -1125,6 +2251,9 @@ impl Node {
         let internal_part = InternalPart {
             internal_tx,
             internal_requests_rx,
+            max_pending_verifications: self.max_pending_verifications,
+            verification_metrics,
+            peer_bans,
         };
         (handler_part, network_part, internal_part)
     }
@@ -1288,6 +2417,16 @@ mod tests {
         let _ = Node::new(db, services, node_cfg, None);
     }
 
+    #[test]
+    #[should_panic(expected = "mempool.max_transactions must be strictly larger than 0")]
+    fn test_bad_mempool_max_transactions() {
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let services = vec![];
+        let mut node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        node_cfg.mempool.max_transactions = Some(0);
+        let _ = Node::new(db, services, node_cfg, None);
+    }
+
     #[test]
     #[should_panic(expected = "must be smaller than 65536")]
     fn test_bad_internal_events_capacity_too_large() {
@@ -1315,4 +2454,18 @@ mod tests {
             .network_requests_capacity = accidental_large_value;
         let _ = Node::new(db, services, node_cfg, None);
     }
+
+    #[test]
+    #[should_panic(expected = "checkpoints.interval must be strictly larger than 0")]
+    fn test_bad_checkpoints_interval() {
+        let db = Arc::from(Box::new(TemporaryDB::new()) as Box<dyn Database>) as Arc<dyn Database>;
+        let services = vec![];
+        let mut node_cfg = helpers::generate_testnet_config(1, 16_500)[0].clone();
+        node_cfg.checkpoints = Some(CheckpointConfig {
+            directory: PathBuf::from("checkpoints"),
+            interval: 0,
+            keep_checkpoints: 1,
+        });
+        let _ = Node::new(db, services, node_cfg, None);
+    }
 }
@@ -0,0 +1,147 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pausing of the message processing and the block production.
+//!
+//! A paused node keeps its connections, the transaction cache and the messages
+//! of the current height, but neither processes the messages of the peers nor
+//! writes to the database. The messages and the transactions received during
+//! the pause are processed after the node is resumed. Thus, the node can be paused
+//! for a brief host maintenance, e.g., a disk snapshot, without a restart.
+
+use super::{NodeHandler, NodeTimeout};
+use crate::messages::{Message, RawTransaction, Service, Signed};
+
+/// Maximal number of the messages and transactions buffered during the pause.
+/// The subsequent ones are dropped.
+const MAX_PAUSED_EVENTS: usize = 100_000;
+
+/// Event received during the pause.
+#[derive(Debug)]
+enum PausedEvent {
+    /// Message received from a peer.
+    Message(Message),
+    /// Transaction submitted via the API.
+    Transaction(Signed<RawTransaction>),
+}
+
+/// Events buffered during the pause.
+#[derive(Debug, Default)]
+pub(crate) struct Pause {
+    events: Vec<PausedEvent>,
+    dropped: usize,
+}
+
+impl Pause {
+    fn push(&mut self, event: PausedEvent) {
+        if self.events.len() < MAX_PAUSED_EVENTS {
+            self.events.push(event);
+        } else {
+            self.dropped += 1;
+        }
+    }
+}
+
+impl NodeHandler {
+    /// Returns `true` if the node is paused.
+    pub fn is_paused(&self) -> bool {
+        self.pause.is_some()
+    }
+
+    /// Pauses the message processing and the block production.
+    pub(crate) fn handle_pause(&mut self) {
+        if self.is_paused() {
+            info!("The node is already paused");
+            return;
+        }
+        self.pause = Some(Pause::default());
+        self.api_state.set_paused(true);
+        info!("The node is paused at height {}", self.state.height());
+    }
+
+    /// Resumes the node and processes the messages and transactions received during the pause.
+    pub(crate) fn handle_resume(&mut self) {
+        let pause = match self.pause.take() {
+            Some(pause) => pause,
+            None => {
+                info!("The node is not paused");
+                return;
+            }
+        };
+        self.api_state.set_paused(false);
+        info!(
+            "The node is resumed, processing {} events received during the pause",
+            pause.events.len()
+        );
+        if pause.dropped > 0 {
+            warn!(
+                "{} events received during the pause have been dropped",
+                pause.dropped
+            );
+        }
+
+        // Timeouts of the consensus are dropped during the pause.
+        if self.is_enabled {
+            self.add_round_timeout();
+            self.add_status_timeout();
+            self.add_watchdog_timeout();
+            self.request_next_block();
+        }
+        for event in pause.events {
+            match event {
+                PausedEvent::Message(msg) => self.handle_message(msg),
+                PausedEvent::Transaction(tx) => self.handle_incoming_tx(tx),
+            }
+        }
+    }
+
+    /// Buffers the message from a peer if the node is paused. `Connect` messages are
+    /// processed during the pause to keep the connections alive. Returns the message back
+    /// if it should be processed.
+    pub(crate) fn defer_paused_message(&mut self, msg: Message) -> Option<Message> {
+        let pause = match self.pause {
+            Some(ref mut pause) => pause,
+            None => return Some(msg),
+        };
+        if let Message::Service(Service::Connect(_)) = msg {
+            return Some(msg);
+        }
+        pause.push(PausedEvent::Message(msg));
+        None
+    }
+
+    /// Buffers the transaction submitted via the API if the node is paused. Returns
+    /// the transaction back if it should be processed.
+    pub(crate) fn defer_paused_transaction(
+        &mut self,
+        tx: Signed<RawTransaction>,
+    ) -> Option<Signed<RawTransaction>> {
+        match self.pause {
+            Some(ref mut pause) => {
+                pause.push(PausedEvent::Transaction(tx));
+                None
+            }
+            None => Some(tx),
+        }
+    }
+
+    /// Returns `true` if the timeout should be ignored because the node is paused.
+    /// The peer exchange and the updates of the API state continue during the pause.
+    pub(crate) fn is_paused_timeout(&self, timeout: &NodeTimeout) -> bool {
+        match *timeout {
+            NodeTimeout::PeerExchange | NodeTimeout::UpdateApiState => false,
+            _ => self.is_paused(),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording of the peer statistics.
+
+use std::{collections::HashMap, net::SocketAddr};
+
+use super::{NodeHandler, PeerStatsConfig};
+use crate::blockchain::{PeerEvent, PeerEventKind, PeerStats};
+use crate::crypto::PublicKey;
+
+/// Statistics of the peers collected since they were last saved. The message counters
+/// are saved along with the connection events and on the API state updates, so that
+/// the database is not written on each message.
+#[derive(Debug)]
+pub(crate) struct PeerStatsRecorder {
+    config: PeerStatsConfig,
+    pending: HashMap<PublicKey, PeerStats>,
+}
+
+impl PeerStatsRecorder {
+    pub fn new(config: PeerStatsConfig) -> Self {
+        Self {
+            config,
+            pending: HashMap::new(),
+        }
+    }
+}
+
+impl NodeHandler {
+    /// Notes the connection event of the peer and saves the collected statistics.
+    pub(crate) fn observe_peer_event(&mut self, key: PublicKey, kind: PeerEventKind) {
+        let time = self.system_state.current_time().into();
+        let stats = match self.peer_stats {
+            Some(ref mut recorder) => recorder.pending.entry(key).or_default(),
+            None => return,
+        };
+        match kind {
+            PeerEventKind::Connected => stats.connects += 1,
+            PeerEventKind::Disconnected => stats.disconnects += 1,
+            PeerEventKind::ConnectionFailed => stats.connection_failures += 1,
+        }
+        stats.events.push(PeerEvent { kind, time });
+        self.save_peer_stats();
+    }
+
+    /// Notes the message received from the peer with the given address.
+    pub(crate) fn observe_peer_message(&mut self, address: SocketAddr) {
+        if let Some(stats) = self.pending_peer_stats(address) {
+            stats.messages += 1;
+        }
+    }
+
+    /// Notes the invalid message received from the peer with the given address.
+    pub(crate) fn observe_peer_error(&mut self, address: SocketAddr) {
+        if let Some(stats) = self.pending_peer_stats(address) {
+            stats.errors += 1;
+        }
+    }
+
+    /// Saves the statistics collected since the previous call. The statistics are lost
+    /// if they cannot be saved, since they are not essential for the node.
+    /// Does nothing if the peer statistics are disabled.
+    pub(crate) fn save_peer_stats(&mut self) {
+        let (keep_events, updates) = match self.peer_stats {
+            Some(ref mut recorder) if !recorder.pending.is_empty() => (
+                recorder.config.keep_events,
+                recorder.pending.drain().collect(),
+            ),
+            _ => return,
+        };
+        if let Err(e) = self.blockchain.save_peer_stats(updates, keep_events) {
+            error!("Unable to save peer statistics: {}", e);
+        }
+    }
+
+    // Messages are attributed to the peers by the addresses of their connections.
+    fn pending_peer_stats(&mut self, address: SocketAddr) -> Option<&mut PeerStats> {
+        let recorder = self.peer_stats.as_mut()?;
+        let key = self
+            .state
+            .connections()
+            .iter()
+            .find(|(_, connection)| connection.socket_address() == address)
+            .map(|(key, _)| *key)?;
+        Some(recorder.pending.entry(key).or_default())
+    }
+}
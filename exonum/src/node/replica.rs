@@ -0,0 +1,319 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Read replicas serving the public API from the database checkpoints of a node.
+
+use failure::Error;
+use futures::sync::mpsc;
+
+use std::{
+    fmt, fs, mem,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+    time::Duration,
+};
+
+use super::{api_manager::ApiManager, checkpoints::list_checkpoints, ApiSender, NodeApiConfig};
+use crate::api::{backends::actix::AppConfig, ApiAccess};
+use crate::blockchain::{Blockchain, Service, SharedNodeState};
+use crate::crypto;
+use exonum_merkledb::{ColumnFamilySize, Database, Patch, Snapshot};
+
+/// Interval between the checks for new checkpoints.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Function opening the database at the given path.
+pub type DatabaseOpener = Box<dyn Fn(&Path) -> Result<Box<dyn Database>, Error> + Send>;
+
+/// Read replica of a node, which serves the public API of the node from the newest
+/// database checkpoint without running the consensus.
+///
+/// The `RocksDB` version used by Exonum does not allow opening the database of a running
+/// node, so the replica relies on the checkpoints created by the node, see
+/// [`CheckpointConfig`]. The newest checkpoint is copied into the directory of the replica,
+/// hard-linking the immutable table files, and the replica switches to the newer
+/// checkpoints as they are created. Thus, the replica lags behind the node by at most
+/// `CheckpointConfig::interval` blocks. The node should keep at least two checkpoints,
+/// so that the newest one is not removed while the replica copies it.
+///
+/// Transactions cannot be submitted via the replica, and the private API is not served.
+///
+/// [`CheckpointConfig`]: struct.CheckpointConfig.html
+pub struct ReadReplica {
+    blockchain: Blockchain,
+    db: Arc<ReplicaDB>,
+    checkpoints: PathBuf,
+    directory: PathBuf,
+    current: PathBuf,
+    open_db: DatabaseOpener,
+    api_options: NodeApiConfig,
+    app_configs: Vec<(ApiAccess, AppConfig)>,
+}
+
+impl ReadReplica {
+    /// Creates a replica serving the newest checkpoint from the `checkpoints` directory.
+    /// The checkpoints are copied into the `directory` of the replica and opened
+    /// with `open_db`.
+    pub fn new(
+        checkpoints: PathBuf,
+        directory: PathBuf,
+        open_db: DatabaseOpener,
+        services: Vec<Box<dyn Service>>,
+        api_options: NodeApiConfig,
+    ) -> Result<Self, Error> {
+        crypto::init();
+
+        // Remove the checkpoints left by the previous runs of the replica.
+        if directory.exists() {
+            for path in list_checkpoints(&directory)? {
+                fs::remove_dir_all(path)?;
+            }
+        }
+        let checkpoint = list_checkpoints(&checkpoints)?
+            .pop()
+            .ok_or_else(|| format_err!("No checkpoints found in {}", checkpoints.display()))?;
+        let (current, inner) = open_checkpoint(&checkpoint, &directory, &open_db)?;
+        let db = Arc::new(ReplicaDB {
+            inner: RwLock::new(Arc::from(inner)),
+        });
+
+        // Service keys are not used, since the replica does not broadcast transactions.
+        let (service_public_key, service_secret_key) = crypto::gen_keypair();
+        let blockchain = Blockchain::new(
+            Arc::clone(&db) as Arc<dyn Database>,
+            services,
+            service_public_key,
+            service_secret_key,
+            ApiSender::new(mpsc::channel(0).0),
+        );
+        let api_options = NodeApiConfig {
+            private_api_address: None,
+            private_api_socket: None,
+            ..api_options
+        };
+        Ok(Self {
+            blockchain,
+            db,
+            checkpoints,
+            directory,
+            current,
+            open_db,
+            api_options,
+            app_configs: Vec::new(),
+        })
+    }
+
+    /// Adds the configuration hook for the `actix-web` application serving the public API,
+    /// see `Node::with_app_config`.
+    pub fn with_app_config(mut self, access: ApiAccess, app_config: AppConfig) -> Self {
+        self.app_configs.push((access, app_config));
+        self
+    }
+
+    /// Returns the blockchain with the state of the current checkpoint.
+    pub fn blockchain(&self) -> &Blockchain {
+        &self.blockchain
+    }
+
+    /// Starts the public API server and serves the newest checkpoints until the server
+    /// is stopped.
+    pub fn run(mut self) -> Result<(), Error> {
+        let api_state = SharedNodeState::new(self.api_options.state_update_timeout as u64);
+        let mut api_manager = ApiManager::new(
+            self.blockchain.clone(),
+            api_state,
+            mem::replace(&mut self.app_configs, Vec::new()),
+            self.api_options.clone(),
+        );
+        api_manager.start()?;
+        // The sender is kept until the server is stopped, otherwise the manager stops at once.
+        let (_api_manager_tx, api_manager_rx) = mpsc::unbounded();
+        let api_manager_thread = thread::spawn(move || api_manager.run(api_manager_rx));
+
+        thread::spawn(move || loop {
+            thread::sleep(POLL_INTERVAL);
+            if let Err(e) = self.switch_to_newest_checkpoint() {
+                error!("Failed to switch to the newest database checkpoint: {}", e);
+            }
+        });
+
+        api_manager_thread
+            .join()
+            .map_err(|e| format_err!("Unable to join api manager thread: {:?}", e))?
+    }
+
+    /// Switches the replica to the newest checkpoint if it is newer than the current one.
+    fn switch_to_newest_checkpoint(&mut self) -> Result<(), Error> {
+        let checkpoint = match list_checkpoints(&self.checkpoints)?.pop() {
+            Some(checkpoint) => checkpoint,
+            None => return Ok(()),
+        };
+        if checkpoint.file_name() <= self.current.file_name() {
+            return Ok(());
+        }
+
+        let (path, inner) = open_checkpoint(&checkpoint, &self.directory, &self.open_db)?;
+        *self
+            .db
+            .inner
+            .write()
+            .expect("Replica database lock is poisoned") = Arc::from(inner);
+        let previous = mem::replace(&mut self.current, path);
+        info!("Switched to database checkpoint {}", checkpoint.display());
+
+        // The snapshots of the previous checkpoint, which are still in use, keep its files open.
+        if let Err(e) = fs::remove_dir_all(&previous) {
+            warn!(
+                "Failed to remove the previous checkpoint {}: {}",
+                previous.display(),
+                e
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ReadReplica {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadReplica")
+            .field("checkpoints", &self.checkpoints)
+            .field("directory", &self.directory)
+            .field("current", &self.current)
+            .field("api_options", &self.api_options)
+            .finish()
+    }
+}
+
+/// Copies the checkpoint into the directory of the replica and opens the copy.
+///
+/// The table files of the checkpoint are immutable, so they are hard-linked if possible.
+/// The other files are copied, since the database may change them when it is opened.
+fn open_checkpoint(
+    checkpoint: &Path,
+    directory: &Path,
+    open_db: &DatabaseOpener,
+) -> Result<(PathBuf, Box<dyn Database>), Error> {
+    let name = checkpoint
+        .file_name()
+        .ok_or_else(|| format_err!("Invalid checkpoint path {}", checkpoint.display()))?;
+    let path = directory.join(name);
+    if path.exists() {
+        fs::remove_dir_all(&path)?;
+    }
+    fs::create_dir_all(&path)?;
+    for entry in fs::read_dir(checkpoint)? {
+        let source = entry?.path();
+        let target = path.join(source.file_name().expect("Directory entry has a name"));
+        let is_table = source.extension().map_or(false, |ext| ext == "sst");
+        if !is_table || fs::hard_link(&source, &target).is_err() {
+            fs::copy(&source, &target)?;
+        }
+    }
+    let db = open_db(&path)?;
+    Ok((path, db))
+}
+
+/// Database of the replica, which can be switched to another checkpoint. Writes are
+/// not allowed.
+struct ReplicaDB {
+    inner: RwLock<Arc<dyn Database>>,
+}
+
+impl Database for ReplicaDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        self.inner
+            .read()
+            .expect("Replica database lock is poisoned")
+            .snapshot()
+    }
+
+    fn merge(&self, _patch: Patch) -> exonum_merkledb::Result<()> {
+        Err(exonum_merkledb::Error::new(
+            "The database of a read replica cannot be changed",
+        ))
+    }
+
+    fn merge_sync(&self, patch: Patch) -> exonum_merkledb::Result<()> {
+        self.merge(patch)
+    }
+
+    fn approximate_sizes(&self) -> Vec<ColumnFamilySize> {
+        self.inner
+            .read()
+            .expect("Replica database lock is poisoned")
+            .approximate_sizes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::blockchain::Schema;
+    use crate::crypto::Hash;
+    use crate::helpers::Height;
+    use exonum_merkledb::{DbOptions, RocksDB};
+
+    fn commit_block(db: &dyn Database) {
+        let fork = db.fork();
+        Schema::new(&fork)
+            .block_hashes_by_height()
+            .push(Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    fn open_rocksdb(path: &Path) -> Result<Box<dyn Database>, Error> {
+        Ok(Box::new(RocksDB::open(path, &DbOptions::default())?))
+    }
+
+    #[test]
+    fn test_replica_switches_checkpoints() {
+        let dir = TempDir::new().unwrap();
+        let checkpoints = dir.path().join("checkpoints");
+        let db = RocksDB::open(dir.path().join("db"), &DbOptions::default()).unwrap();
+        commit_block(&db);
+        db.create_checkpoint(&checkpoints.join("checkpoint-00000000000000000000"))
+            .unwrap();
+
+        let mut replica = ReadReplica::new(
+            checkpoints.clone(),
+            dir.path().join("replica"),
+            Box::new(open_rocksdb),
+            Vec::new(),
+            NodeApiConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            Schema::new(&replica.blockchain().snapshot()).height(),
+            Height(0)
+        );
+        let mut blockchain = replica.blockchain().clone();
+        let patch = blockchain.fork().into_patch();
+        assert!(blockchain.merge(patch).is_err());
+        assert!(blockchain.storage_metrics().last_merge_failed());
+
+        commit_block(&db);
+        db.create_checkpoint(&checkpoints.join("checkpoint-00000000000000000001"))
+            .unwrap();
+        replica.switch_to_newest_checkpoint().unwrap();
+        assert_eq!(
+            Schema::new(&replica.blockchain().snapshot()).height(),
+            Height(1)
+        );
+        let replica_checkpoints = list_checkpoints(&dir.path().join("replica")).unwrap();
+        assert_eq!(replica_checkpoints.len(), 1);
+    }
+}
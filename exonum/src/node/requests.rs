@@ -100,7 +100,8 @@ impl NodeHandler {
             - TRANSACTION_RESPONSE_EMPTY_SIZE;
 
         for hash in hashes {
-            if let Some(tx) = get_tx(&hash, &schema.transactions(), &self.state.tx_cache()) {
+            let tx = get_tx(&hash, &schema.transactions(), &self.state.tx_cache());
+            if let Some(tx) = tx {
                 let raw = tx.signed_message().raw().to_vec();
                 if txs_size + raw.len() + RAW_TRANSACTION_HEADER > unoccupied_message_size {
                     let txs_response = self.sign_message(TransactionsResponse::new(
@@ -108,7 +109,9 @@ impl NodeHandler {
                         mem::replace(&mut txs, vec![]),
                     ));
 
-                    self.send_to_peer(*author, txs_response);
+                    if let Some(txs_response) = txs_response {
+                        self.send_to_peer(*author, txs_response);
+                    }
                     txs_size = 0;
                 }
                 txs_size += raw.len() + RAW_TRANSACTION_HEADER;
@@ -117,8 +120,9 @@ impl NodeHandler {
         }
 
         if !txs.is_empty() {
-            let txs_response = self.sign_message(TransactionsResponse::new(author, txs));
-            self.send_to_peer(*author, txs_response);
+            if let Some(txs_response) = self.sign_message(TransactionsResponse::new(author, txs)) {
+                self.send_to_peer(*author, txs_response);
+            }
         }
     }
 
@@ -158,6 +162,12 @@ impl NodeHandler {
         let schema = Schema::new(&snapshot);
 
         let height = msg.height;
+        // Transactions and precommits of pruned blocks are unavailable, so the block
+        // should be requested from another peer.
+        if schema.is_pruned(height) {
+            trace!("Ignore request for the pruned block at height {}", height);
+            return;
+        }
         let block_hash = schema.block_hash_by_height(height).unwrap();
 
         let block = schema.blocks().get(&block_hash).unwrap();
@@ -173,6 +183,8 @@ impl NodeHandler {
                 .collect(),
             &transactions.iter().collect::<Vec<_>>(),
         ));
-        self.send_to_peer(msg.author(), block_msg);
+        if let Some(block_msg) = block_msg {
+            self.send_to_peer(msg.author(), block_msg);
+        }
     }
 }
@@ -0,0 +1,92 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics of the round changes and the leaders causing them.
+
+use std::collections::BTreeMap;
+
+use super::NodeHandler;
+use crate::api::node::public::system::{LeaderRoundChanges, RoundChangeStats};
+use crate::crypto::PublicKey;
+use crate::helpers::Round;
+
+/// Round changes observed by the node since the start.
+#[derive(Debug, Default)]
+pub(crate) struct RoundChanges {
+    heights: u64,
+    heights_with_round_changes: u64,
+    rounds: u64,
+    /// Numbers of the failed rounds keyed by the consensus keys of their leaders.
+    failed_rounds: BTreeMap<PublicKey, u64>,
+}
+
+impl RoundChanges {
+    fn stats(&self) -> RoundChangeStats {
+        let mut leaders: Vec<_> = self
+            .failed_rounds
+            .iter()
+            .map(|(&consensus_key, &failed_rounds)| LeaderRoundChanges {
+                consensus_key,
+                failed_rounds,
+            })
+            .collect();
+        leaders.sort_by(|a, b| b.failed_rounds.cmp(&a.failed_rounds));
+
+        RoundChangeStats {
+            heights: self.heights,
+            heights_with_round_changes: self.heights_with_round_changes,
+            average_rounds_per_height: if self.heights == 0 {
+                None
+            } else {
+                Some(self.rounds as f64 / self.heights as f64)
+            },
+            leaders,
+        }
+    }
+}
+
+impl NodeHandler {
+    /// Counts the rounds preceding the round of the committed block as failed by their
+    /// leaders. Should be called before the node moves to the next height. The blocks
+    /// committed during the synchronization are not observed.
+    pub(crate) fn observe_round_changes(&mut self, round: Option<Round>) {
+        let round = match round {
+            Some(round) => round,
+            None => return,
+        };
+
+        let failed_leaders: Vec<PublicKey> = Round::first()
+            .iter_to(round)
+            .map(|round| {
+                let leader = self.state.leader(round);
+                self.state.validators()[leader.0 as usize].consensus_key
+            })
+            .collect();
+
+        let round_changes = &mut self.round_changes;
+        round_changes.heights += 1;
+        round_changes.rounds += u64::from(round.0);
+        if !failed_leaders.is_empty() {
+            round_changes.heights_with_round_changes += 1;
+        }
+        for consensus_key in failed_leaders {
+            *round_changes
+                .failed_rounds
+                .entry(consensus_key)
+                .or_insert(0) += 1;
+        }
+
+        self.api_state.set_round_changes(self.round_changes.stats());
+    }
+}
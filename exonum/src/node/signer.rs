@@ -0,0 +1,455 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing of the messages with the keys which may be kept outside of the node.
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use failure::Error;
+
+use std::{
+    cmp, fmt,
+    io::{self, Read, Write},
+    os::unix::net::UnixStream,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{
+    metrics::{SIGNER_CONNECTED, SIGNER_CONNECTIONS, SIGNING_DURATION, SIGNING_FAILURES},
+    RemoteSignerConfig,
+};
+use crate::crypto::{self, PublicKey, SecretKey, Signature, PUBLIC_KEY_LENGTH};
+use crate::helpers::{metrics::MetricsRegistry, Milliseconds};
+
+/// Delay before the first attempt to reconnect to the remote signer.
+const MIN_RECONNECT_DELAY: Milliseconds = 100;
+/// Status of the response of the remote signer containing the signature.
+const STATUS_OK: u8 = 0;
+
+/// Signer of the messages authored by a public key.
+///
+/// The node signs the consensus messages and the transactions broadcast by the services
+/// with signers. The `LocalSigner` uses the secret key kept in the memory of the node,
+/// while the `RemoteSigner` delegates signing to an external process.
+pub trait Signer: Send + Sync + fmt::Debug {
+    /// Returns the public key corresponding to the secret key of the signer.
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the data with the secret key.
+    fn sign(&self, data: &[u8]) -> Result<Signature, Error>;
+}
+
+/// Signer using the secret key kept in the memory of the node.
+#[derive(Debug, Clone)]
+pub struct LocalSigner {
+    public_key: PublicKey,
+    secret_key: SecretKey,
+}
+
+impl LocalSigner {
+    /// Creates a signer with the given keypair.
+    pub fn new(public_key: PublicKey, secret_key: SecretKey) -> Self {
+        Self {
+            public_key,
+            secret_key,
+        }
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, Error> {
+        Ok(crypto::sign(data, &self.secret_key))
+    }
+}
+
+/// Signer delegating signing to an external process listening on a Unix socket,
+/// such as a front-end of a hardware security module.
+///
+/// The signer process receives requests consisting of the big-endian `u32` length
+/// of the rest of the request, the public key to sign with and the data to sign.
+/// It replies with the status byte, the big-endian `u32` length of the payload and
+/// the payload. The payload is the ed25519 signature of the data if the status is 0,
+/// and the UTF-8 description of the error otherwise. Requests are processed one at
+/// a time over a single connection, which may be shared by the signers of several keys.
+///
+/// The signatures returned by the signer process are verified before they are used.
+/// If the connection fails, the signer reconnects on the next request; if the connection
+/// cannot be established, the reconnection attempts are delayed with an exponential backoff.
+/// The latency of signing, failures and connections are reported to the metrics registry.
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    public_key: PublicKey,
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl RemoteSigner {
+    /// Creates a signer of the given key. The connection to the signer process
+    /// is established on the first request.
+    pub fn new(
+        public_key: PublicKey,
+        config: RemoteSignerConfig,
+        metrics: MetricsRegistry,
+    ) -> Self {
+        Self {
+            public_key,
+            connection: Arc::new(Mutex::new(Connection::new(config, metrics))),
+        }
+    }
+
+    /// Returns a signer of another key sharing the connection with this signer.
+    pub fn with_key(&self, public_key: PublicKey) -> Self {
+        Self {
+            public_key,
+            connection: Arc::clone(&self.connection),
+        }
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Signature, Error> {
+        let mut connection = self.connection.lock().expect("Expected lock");
+        connection.sign(&self.public_key, data)
+    }
+}
+
+/// Supervised connection to the signer process.
+struct Connection {
+    config: RemoteSignerConfig,
+    metrics: MetricsRegistry,
+    stream: Option<UnixStream>,
+    /// Number of the consecutive failed attempts to connect.
+    failed_attempts: u32,
+    /// Time before which no attempts to connect are made.
+    retry_after: Option<Instant>,
+}
+
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Connection")
+            .field("config", &self.config)
+            .field("connected", &self.stream.is_some())
+            .field("failed_attempts", &self.failed_attempts)
+            .finish()
+    }
+}
+
+impl Connection {
+    fn new(config: RemoteSignerConfig, metrics: MetricsRegistry) -> Self {
+        Self {
+            config,
+            metrics,
+            stream: None,
+            failed_attempts: 0,
+            retry_after: None,
+        }
+    }
+
+    fn sign(&mut self, public_key: &PublicKey, data: &[u8]) -> Result<Signature, Error> {
+        let start = Instant::now();
+        let reused = self.stream.is_some();
+        let mut result = self.request(public_key, data);
+        if reused && self.stream.is_none() {
+            // The connection may have been closed by the signer, e.g., on its restart.
+            result = self.request(public_key, data);
+        }
+        let result = result.and_then(|signature| {
+            ensure!(
+                crypto::verify(&signature, data, public_key),
+                "Remote signer returned an invalid signature for key {:?}",
+                public_key
+            );
+            Ok(signature)
+        });
+
+        match result {
+            Ok(_) => {
+                let elapsed = start.elapsed();
+                let micros = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_micros());
+                self.metrics.observe(&SIGNING_DURATION, micros);
+            }
+            Err(_) => self.metrics.increment(&SIGNING_FAILURES),
+        }
+        result
+    }
+
+    fn request(&mut self, public_key: &PublicKey, data: &[u8]) -> Result<Signature, Error> {
+        self.connect()?;
+        let response = {
+            let stream = self
+                .stream
+                .as_mut()
+                .expect("Connected to the remote signer");
+            write_request(stream, public_key, data).and_then(|_| read_response(stream))
+        };
+
+        match response {
+            Ok((STATUS_OK, payload)) => Signature::from_slice(&payload).ok_or_else(|| {
+                format_err!(
+                    "Remote signer returned a signature of invalid length {}",
+                    payload.len()
+                )
+            }),
+            Ok((_, payload)) => bail!(
+                "Remote signer refused to sign: {}",
+                String::from_utf8_lossy(&payload)
+            ),
+            Err(e) => {
+                warn!("Connection to the remote signer has failed: {}", e);
+                self.stream = None;
+                self.metrics.set(&SIGNER_CONNECTED, 0);
+                Err(e.into())
+            }
+        }
+    }
+
+    fn connect(&mut self) -> Result<(), Error> {
+        if self.stream.is_some() {
+            return Ok(());
+        }
+        if let Some(retry_after) = self.retry_after {
+            let now = Instant::now();
+            ensure!(
+                now >= retry_after,
+                "Remote signer is unavailable, next attempt to connect in {:?}",
+                retry_after - now
+            );
+        }
+
+        let timeout = Some(Duration::from_millis(self.config.timeout));
+        let stream = UnixStream::connect(&self.config.socket_path).and_then(|stream| {
+            stream.set_read_timeout(timeout)?;
+            stream.set_write_timeout(timeout)?;
+            Ok(stream)
+        });
+        match stream {
+            Ok(stream) => {
+                info!(
+                    "Connected to the remote signer at {}",
+                    self.config.socket_path.display()
+                );
+                self.stream = Some(stream);
+                self.failed_attempts = 0;
+                self.retry_after = None;
+                self.metrics.increment(&SIGNER_CONNECTIONS);
+                self.metrics.set(&SIGNER_CONNECTED, 1);
+                Ok(())
+            }
+            Err(e) => {
+                let delay = self.reconnect_delay();
+                self.failed_attempts += 1;
+                self.retry_after = Some(Instant::now() + delay);
+                bail!(
+                    "Unable to connect to the remote signer at {}: {}",
+                    self.config.socket_path.display(),
+                    e
+                )
+            }
+        }
+    }
+
+    fn reconnect_delay(&self) -> Duration {
+        let exponent = cmp::min(self.failed_attempts, 16);
+        let delay = MIN_RECONNECT_DELAY << exponent;
+        Duration::from_millis(cmp::min(delay, self.config.max_reconnect_delay))
+    }
+}
+
+fn write_request<W: Write>(writer: &mut W, public_key: &PublicKey, data: &[u8]) -> io::Result<()> {
+    let mut request = Vec::with_capacity(4 + PUBLIC_KEY_LENGTH + data.len());
+    request.write_u32::<BigEndian>((PUBLIC_KEY_LENGTH + data.len()) as u32)?;
+    request.extend_from_slice(public_key.as_ref());
+    request.extend_from_slice(data);
+    writer.write_all(&request)?;
+    writer.flush()
+}
+
+fn read_response<R: Read>(reader: &mut R) -> io::Result<(u8, Vec<u8>)> {
+    let status = reader.read_u8()?;
+    let len = reader.read_u32::<BigEndian>()?;
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok((status, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+    use tempdir::TempDir;
+
+    use std::{
+        io::{Read, Write},
+        os::unix::net::UnixListener,
+        path::Path,
+        thread,
+    };
+
+    use super::*;
+    use crate::crypto::gen_keypair;
+    use crate::helpers::metrics::MetricValue;
+
+    /// Serves the signing requests of a single connection with the given keypair.
+    fn serve_connection(listener: &UnixListener, public_key: PublicKey, secret_key: &SecretKey) {
+        let (mut stream, _) = listener.accept().unwrap();
+        while let Ok(len) = stream.read_u32::<BigEndian>() {
+            let mut request = vec![0; len as usize];
+            stream.read_exact(&mut request).unwrap();
+            let (key, data) = request.split_at(PUBLIC_KEY_LENGTH);
+            let (status, payload) = if key == public_key.as_ref() {
+                let signature = crypto::sign(data, secret_key);
+                (STATUS_OK, signature.as_ref().to_vec())
+            } else {
+                (1, b"unknown key".to_vec())
+            };
+            stream.write_u8(status).unwrap();
+            stream.write_u32::<BigEndian>(payload.len() as u32).unwrap();
+            stream.write_all(&payload).unwrap();
+        }
+    }
+
+    fn spawn_signer(
+        path: &Path,
+        connections: usize,
+        public_key: PublicKey,
+        secret_key: SecretKey,
+    ) -> thread::JoinHandle<()> {
+        let listener = UnixListener::bind(path).unwrap();
+        thread::spawn(move || {
+            for _ in 0..connections {
+                serve_connection(&listener, public_key, &secret_key);
+            }
+        })
+    }
+
+    fn config(path: &Path) -> RemoteSignerConfig {
+        RemoteSignerConfig {
+            socket_path: path.to_owned(),
+            timeout: 1_000,
+            max_reconnect_delay: 60_000,
+        }
+    }
+
+    #[test]
+    fn test_remote_signer() {
+        let dir = TempDir::new("exonum_remote_signer").unwrap();
+        let path = dir.path().join("signer.sock");
+        let (public_key, secret_key) = gen_keypair();
+        let signer_thread = spawn_signer(&path, 1, public_key, secret_key);
+
+        let metrics = MetricsRegistry::default();
+        let signer = RemoteSigner::new(public_key, config(&path), metrics.clone());
+        for data in &[&b"foo"[..], &b"bar"[..]] {
+            let signature = signer.sign(data).unwrap();
+            assert!(crypto::verify(&signature, data, &public_key));
+        }
+
+        let (other_key, _) = gen_keypair();
+        let error = signer.with_key(other_key).sign(b"foo").unwrap_err();
+        assert!(error.to_string().contains("unknown key"));
+
+        match metrics.value(SIGNING_DURATION.name) {
+            Some(MetricValue::Summary { count, .. }) => assert_eq!(count, 2),
+            value => panic!("Unexpected signing duration: {:?}", value),
+        }
+        assert_eq!(
+            metrics.value(SIGNING_FAILURES.name),
+            Some(MetricValue::Counter { value: 1 })
+        );
+        assert_eq!(
+            metrics.value(SIGNER_CONNECTIONS.name),
+            Some(MetricValue::Counter { value: 1 })
+        );
+
+        drop(signer);
+        signer_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_remote_signer_rejects_invalid_signature() {
+        let dir = TempDir::new("exonum_remote_signer").unwrap();
+        let path = dir.path().join("signer.sock");
+        let (public_key, _) = gen_keypair();
+        let (_, other_secret_key) = gen_keypair();
+        let signer_thread = spawn_signer(&path, 1, public_key, other_secret_key);
+
+        let signer = RemoteSigner::new(public_key, config(&path), MetricsRegistry::default());
+        let error = signer.sign(b"foo").unwrap_err();
+        assert!(error.to_string().contains("invalid signature"));
+
+        drop(signer);
+        signer_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_remote_signer_reconnects() {
+        let dir = TempDir::new("exonum_remote_signer").unwrap();
+        let path = dir.path().join("signer.sock");
+        let (public_key, secret_key) = gen_keypair();
+        let metrics = MetricsRegistry::default();
+        let signer = RemoteSigner::new(public_key, config(&path), metrics.clone());
+
+        // The signer is not started yet, so the reconnection is delayed.
+        assert!(signer.sign(b"foo").is_err());
+        let error = signer.sign(b"foo").unwrap_err();
+        assert!(error.to_string().contains("unavailable"));
+        {
+            let mut connection = signer.connection.lock().unwrap();
+            assert_eq!(connection.failed_attempts, 1);
+            connection.retry_after = None;
+        }
+
+        // The signer restarts after serving a single connection.
+        let signer_thread = spawn_signer(&path, 2, public_key, secret_key);
+        assert!(signer.sign(b"foo").is_ok());
+        {
+            let connection = signer.connection.lock().unwrap();
+            connection
+                .stream
+                .as_ref()
+                .unwrap()
+                .shutdown(std::net::Shutdown::Both)
+                .unwrap();
+        }
+        assert!(signer.sign(b"bar").is_ok());
+        assert_eq!(
+            metrics.value(SIGNER_CONNECTIONS.name),
+            Some(MetricValue::Counter { value: 2 })
+        );
+        assert_eq!(
+            metrics.value(SIGNER_CONNECTED.name),
+            Some(MetricValue::Gauge { value: 1 })
+        );
+
+        drop(signer);
+        signer_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_reconnect_delay() {
+        let path = Path::new("signer.sock");
+        let mut connection = Connection::new(config(path), MetricsRegistry::default());
+        assert_eq!(connection.reconnect_delay(), Duration::from_millis(100));
+        connection.failed_attempts = 3;
+        assert_eq!(connection.reconnect_delay(), Duration::from_millis(800));
+        connection.failed_attempts = 100;
+        assert_eq!(connection.reconnect_delay(), Duration::from_millis(60_000));
+    }
+}
@@ -0,0 +1,229 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Protection from signing conflicting votes after an unclean restart.
+//!
+//! The votes signed by the node at the highest height are persisted in a file outside
+//! of the database before they are broadcast. Thus, if the database is restored from
+//! a stale backup, the node does not sign votes at the heights it has already voted at,
+//! and does not vote for another proposal or block in the rounds it has voted in.
+
+use failure::Error;
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+};
+
+use super::{NodeHandler, SigningRecordConfig};
+use crate::crypto::Hash;
+use crate::helpers::{Height, Round};
+
+/// Vote signed by the node in a round.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct SignedVote {
+    round: Round,
+    hash: Hash,
+}
+
+/// Votes signed by the node at the highest height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SignedVotes {
+    height: Height,
+    /// Prevotes with the hashes of the proposals.
+    prevotes: Vec<SignedVote>,
+    /// Precommits with the hashes of the blocks.
+    precommits: Vec<SignedVote>,
+}
+
+/// Kind of the signed vote.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum VoteKind {
+    Prevote,
+    Precommit,
+}
+
+/// The signing high-water mark persisted in a file.
+#[derive(Debug)]
+pub(crate) struct SigningRecord {
+    path: PathBuf,
+    votes: Option<SignedVotes>,
+}
+
+impl SigningRecord {
+    /// Loads the record from the configured file. The record is empty if the file
+    /// does not exist.
+    pub fn load(config: SigningRecordConfig) -> Result<Self, Error> {
+        let path = config.path;
+        let votes = if path.exists() {
+            let contents = fs::read(&path)?;
+            Some(serde_json::from_slice(&contents)?)
+        } else {
+            None
+        };
+        Ok(Self { path, votes })
+    }
+
+    /// Returns the highest height at which the node has signed votes, if any.
+    pub fn height(&self) -> Option<Height> {
+        self.votes.as_ref().map(|votes| votes.height)
+    }
+
+    /// Checks that the prevote does not conflict with the signed votes and persists it.
+    pub fn record_prevote(
+        &mut self,
+        height: Height,
+        round: Round,
+        propose_hash: Hash,
+    ) -> Result<(), Error> {
+        self.record(VoteKind::Prevote, height, round, propose_hash)
+    }
+
+    /// Checks that the precommit does not conflict with the signed votes and persists it.
+    pub fn record_precommit(
+        &mut self,
+        height: Height,
+        round: Round,
+        block_hash: Hash,
+    ) -> Result<(), Error> {
+        self.record(VoteKind::Precommit, height, round, block_hash)
+    }
+
+    fn record(
+        &mut self,
+        kind: VoteKind,
+        height: Height,
+        round: Round,
+        hash: Hash,
+    ) -> Result<(), Error> {
+        let mut votes = match self.votes {
+            Some(ref votes) if votes.height == height => votes.clone(),
+            Some(ref votes) if votes.height > height => bail!(
+                "The node has already signed votes at height {}, which is higher than {}",
+                votes.height,
+                height
+            ),
+            _ => SignedVotes {
+                height,
+                prevotes: Vec::new(),
+                precommits: Vec::new(),
+            },
+        };
+
+        let signed = match kind {
+            VoteKind::Prevote => &mut votes.prevotes,
+            VoteKind::Precommit => &mut votes.precommits,
+        };
+        match signed.iter().find(|vote| vote.round == round) {
+            Some(vote) if vote.hash == hash => return Ok(()),
+            Some(vote) => bail!(
+                "The node has already signed a {:?} for {:?} at height {}, round {}",
+                kind,
+                vote.hash,
+                height,
+                round
+            ),
+            None => signed.push(SignedVote { round, hash }),
+        }
+
+        self.save(&votes)?;
+        self.votes = Some(votes);
+        Ok(())
+    }
+
+    /// Writes the votes to a temporary file, which then replaces the record,
+    /// so that the record is not corrupted by a crash.
+    fn save(&self, votes: &SignedVotes) -> Result<(), Error> {
+        let pending_path = self.path.with_extension("pending");
+        {
+            let mut file = File::create(&pending_path)?;
+            file.write_all(&serde_json::to_vec(votes)?)?;
+            file.sync_all()?;
+        }
+        fs::rename(&pending_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl NodeHandler {
+    /// Persists the prevote in the signing record, if it is enabled. Returns `false`
+    /// if the prevote must not be signed.
+    pub(crate) fn record_signed_prevote(&mut self, round: Round, propose_hash: &Hash) -> bool {
+        let height = self.state.height();
+        match self.signing_record {
+            Some(ref mut record) => record
+                .record_prevote(height, round, *propose_hash)
+                .map_err(|e| error!("Refusing to sign prevote: {}", e))
+                .is_ok(),
+            None => true,
+        }
+    }
+
+    /// Persists the precommit in the signing record, if it is enabled. Returns `false`
+    /// if the precommit must not be signed.
+    pub(crate) fn record_signed_precommit(&mut self, round: Round, block_hash: &Hash) -> bool {
+        let height = self.state.height();
+        match self.signing_record {
+            Some(ref mut record) => record
+                .record_precommit(height, round, *block_hash)
+                .map_err(|e| error!("Refusing to sign precommit: {}", e))
+                .is_ok(),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::crypto::hash;
+
+    fn load(dir: &TempDir) -> SigningRecord {
+        SigningRecord::load(SigningRecordConfig {
+            path: dir.path().join("signing_record.json"),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refuse_conflicting_votes() {
+        let dir = TempDir::new().unwrap();
+        let mut record = load(&dir);
+        assert_eq!(record.height(), None);
+
+        let (first, second) = (hash(&[1]), hash(&[2]));
+        record.record_prevote(Height(5), Round(1), first).unwrap();
+        record.record_prevote(Height(5), Round(2), second).unwrap();
+        record.record_precommit(Height(5), Round(2), first).unwrap();
+        // The same votes can be signed again.
+        record.record_prevote(Height(5), Round(1), first).unwrap();
+        record.record_precommit(Height(5), Round(2), first).unwrap();
+
+        // The record survives the restart.
+        let mut record = load(&dir);
+        assert_eq!(record.height(), Some(Height(5)));
+        assert!(record.record_prevote(Height(5), Round(1), second).is_err());
+        assert!(record
+            .record_precommit(Height(5), Round(2), second)
+            .is_err());
+        assert!(record.record_prevote(Height(4), Round(3), first).is_err());
+
+        // The votes at the next height are not affected.
+        record.record_prevote(Height(6), Round(1), second).unwrap();
+        assert_eq!(load(&dir).height(), Some(Height(6)));
+    }
+}
@@ -15,16 +15,21 @@
 //! State of the `NodeHandler`.
 
 use bit_vec::BitVec;
+use chrono::{DateTime, Utc};
 use serde_json::Value;
 
 use std::{
     collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    mem,
+    net::IpAddr,
     ops::Deref,
-    sync::{Arc, RwLock},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
     time::{Duration, SystemTime},
 };
 
-use crate::blockchain::{check_tx, ConsensusConfig, StoredConfiguration, ValidatorKeys};
+use crate::blockchain::{
+    check_tx, ConsensusConfig, LeaderSelector, Schema, StoredConfiguration, ValidatorKeys,
+};
 use crate::crypto::{Hash, PublicKey, SecretKey};
 use crate::events::network::ConnectedPeerAddr;
 use crate::helpers::{Height, Milliseconds, Round, ValidatorId};
@@ -61,6 +66,8 @@ pub struct State {
     service_secret_key: SecretKey,
 
     config: StoredConfiguration,
+    /// Identifier of the network, see `Schema::network_id`.
+    network_id: Hash,
     connect_list: SharedConnectList,
 
     peers: HashMap<PublicKey, Signed<Connect>>,
@@ -94,8 +101,12 @@ pub struct State {
 
     incomplete_block: Option<IncompleteBlock>,
 
-    // Cache that stores transactions before adding to persistent pool.
-    tx_cache: BTreeMap<Hash, Signed<RawTransaction>>,
+    // Cache that stores transactions before adding to persistent pool, along with
+    // the times at which the uncommitted transactions were received by the node.
+    tx_cache: SharedTxCache,
+    // Numbers of the consecutive proposals for which the transactions have failed
+    // the execution in advance.
+    failed_dry_runs: HashMap<Hash, u32>,
 }
 
 /// State of a validator-node.
@@ -418,6 +429,7 @@ impl SharedConnectList {
             .iter()
             .map(|(pk, a)| ConnectInfo {
                 address: a.address.clone(),
+                fallback_addresses: a.fallback_addresses.clone(),
                 public_key: *pk,
             })
             .collect()
@@ -429,6 +441,12 @@ impl SharedConnectList {
         conn_list.update_peer(public_key, address);
     }
 
+    /// Returns `true` if one of the candidate addresses of a peer has the given IP address.
+    pub fn is_ip_listed(&self, ip: &IpAddr) -> bool {
+        let connect_list = self.inner.read().expect("ConnectList read lock");
+        connect_list.is_ip_listed(ip)
+    }
+
     /// Get peer address using public key.
     pub fn find_address_by_key(&self, public_key: &PublicKey) -> Option<PeerAddress> {
         let connect_list = self.inner.read().expect("ConnectList read lock");
@@ -436,6 +454,90 @@ impl SharedConnectList {
     }
 }
 
+/// Uncommitted transactions kept in memory by the node, shared with the API so that
+/// they are not copied into the API state.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SharedTxCache {
+    txs: Arc<RwLock<BTreeMap<Hash, Signed<RawTransaction>>>>,
+    arrival_times: Arc<RwLock<HashMap<Hash, SystemTime>>>,
+}
+
+impl SharedTxCache {
+    /// Returns the transactions which are not flushed into the persistent pool yet.
+    pub fn txs(&self) -> RwLockReadGuard<BTreeMap<Hash, Signed<RawTransaction>>> {
+        self.txs.read().expect("Transactions cache read lock")
+    }
+
+    fn txs_mut(&self) -> RwLockWriteGuard<BTreeMap<Hash, Signed<RawTransaction>>> {
+        self.txs.write().expect("Transactions cache write lock")
+    }
+
+    /// Returns the times at which the uncommitted transactions were received.
+    pub fn arrival_times(&self) -> RwLockReadGuard<HashMap<Hash, SystemTime>> {
+        self.arrival_times
+            .read()
+            .expect("Transaction arrival times read lock")
+    }
+
+    fn arrival_times_mut(&self) -> RwLockWriteGuard<HashMap<Hash, SystemTime>> {
+        self.arrival_times
+            .write()
+            .expect("Transaction arrival times write lock")
+    }
+}
+
+/// Number of votes collected by the node for a certain round.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VotesInfo {
+    /// Round of the votes.
+    pub round: Round,
+    /// Hash the votes are given for, that is, the propose hash for pre-votes
+    /// and the block hash for pre-commits.
+    pub hash: Hash,
+    /// Number of collected votes.
+    pub count: usize,
+}
+
+/// Timeouts of the current consensus round.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConsensusTimeoutsInfo {
+    /// Time at which the current height has started.
+    pub height_start_time: DateTime<Utc>,
+    /// Time at which the current round has started.
+    pub round_start_time: DateTime<Utc>,
+    /// Time at which the next round starts, if the block is not committed.
+    pub next_round_start_time: DateTime<Utc>,
+    /// Propose timeout used by the leader of the round.
+    pub propose_timeout: Milliseconds,
+    /// Status timeout.
+    pub status_timeout: Milliseconds,
+}
+
+/// Consensus state of the node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConsensusStateInfo {
+    /// Current height.
+    pub height: Height,
+    /// Current round.
+    pub round: Round,
+    /// Leader of the current round.
+    pub leader: ValidatorId,
+    /// Identifier of the node, if it is a validator.
+    pub validator_id: Option<ValidatorId>,
+    /// Round in which the node has locked on a propose.
+    pub locked_round: Round,
+    /// Hash of the propose the node has locked on, if any.
+    pub locked_propose: Option<Hash>,
+    /// Number of votes required for the consensus.
+    pub majority_count: usize,
+    /// Pre-votes collected at the current height.
+    pub prevotes: Vec<VotesInfo>,
+    /// Pre-commits collected at the current height.
+    pub precommits: Vec<VotesInfo>,
+    /// Timeouts of the current round.
+    pub timeouts: ConsensusTimeoutsInfo,
+}
+
 impl State {
     /// Creates state with the given parameters.
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
@@ -447,6 +549,7 @@ impl State {
         service_secret_key: SecretKey,
         connect_list: ConnectList,
         stored: StoredConfiguration,
+        network_id: Hash,
         connect: Signed<Connect>,
         peers: HashMap<PublicKey, Signed<Connect>>,
         last_hash: Hash,
@@ -487,10 +590,12 @@ impl State {
             requests: HashMap::new(),
 
             config: stored,
+            network_id,
 
             incomplete_block: None,
 
-            tx_cache: BTreeMap::new(),
+            tx_cache: SharedTxCache::default(),
+            failed_dry_runs: HashMap::new(),
         }
     }
 
@@ -552,6 +657,16 @@ impl State {
             .map(|id| ValidatorId(id as u16))
     }
 
+    /// Returns the network identifier covered by the signatures of the messages
+    /// according to the actual configuration, see `Schema::actual_network_id`.
+    pub fn network_id(&self) -> Option<Hash> {
+        if self.config.domain_separated_signatures {
+            Some(self.network_id)
+        } else {
+            None
+        }
+    }
+
     /// Returns `ConsensusConfig`.
     pub fn consensus_config(&self) -> &ConsensusConfig {
         &self.config.consensus
@@ -656,9 +771,12 @@ impl State {
 
     /// Returns the leader id for the specified round and current height.
     pub fn leader(&self, round: Round) -> ValidatorId {
-        let height: u64 = self.height().into();
-        let round: u64 = round.into();
-        ValidatorId(((height + round) % (self.validators().len() as u64)) as u16)
+        self.consensus_config().leader_selection.leader(
+            self.height(),
+            round,
+            self.last_hash(),
+            self.validators().len(),
+        )
     }
 
     /// Updates known round for a validator and returns
@@ -785,6 +903,11 @@ impl State {
         self.proposes.get(hash)
     }
 
+    /// Returns the states of all the proposes known at the current height.
+    pub(crate) fn proposes(&self) -> impl Iterator<Item = &ProposeState> {
+        self.proposes.values()
+    }
+
     /// Returns a block with the specified hash.
     pub fn block(&self, hash: &Hash) -> Option<&BlockState> {
         self.blocks.get(hash)
@@ -839,11 +962,28 @@ impl State {
         queued
     }
 
+    /// Returns the number of queued consensus messages.
+    pub fn queued_len(&self) -> usize {
+        self.queued.len()
+    }
+
     /// Add consensus message to the queue.
     pub fn add_queued(&mut self, msg: ConsensusMessage) {
         self.queued.push(msg);
     }
 
+    /// Checks whether the transaction is included into any known propose or block
+    /// of the current height.
+    pub fn is_tx_in_proposes(&self, tx_hash: &Hash) -> bool {
+        self.proposes
+            .values()
+            .any(|propose_state| propose_state.message().transactions().contains(tx_hash))
+            || self
+                .blocks
+                .values()
+                .any(|block_state| block_state.txs().contains(tx_hash))
+    }
+
     /// Checks whether some proposes are waiting for this transaction.
     /// Returns a list of proposes that don't contain unknown transactions.
     ///
@@ -894,6 +1034,22 @@ impl State {
             .map_or_else(|| [].as_ref(), |votes| votes.messages().as_slice())
     }
 
+    /// Returns the number of collected pre-votes for each known round and propose hash.
+    pub fn prevotes_counts(&self) -> BTreeMap<(Round, Hash), usize> {
+        self.prevotes
+            .iter()
+            .map(|(key, votes)| (*key, votes.count()))
+            .collect()
+    }
+
+    /// Returns the number of collected pre-commits for each known round and block hash.
+    pub fn precommits_counts(&self) -> BTreeMap<(Round, Hash), usize> {
+        self.precommits
+            .iter()
+            .map(|(key, votes)| (*key, votes.count()))
+            .collect()
+    }
+
     /// Returns `true` if this node has pre-vote for the specified round.
     ///
     /// # Panics
@@ -941,7 +1097,7 @@ impl State {
             Entry::Vacant(e) => {
                 let mut unknown_txs = HashSet::new();
                 for hash in msg.transactions() {
-                    if self.tx_cache.contains_key(hash) {
+                    if self.tx_cache.txs().contains_key(hash) {
                         //Tx with `hash` is  not committed yet.
                         continue;
                     }
@@ -1005,15 +1161,15 @@ impl State {
     pub fn create_incomplete_block<S: IndexAccess>(
         &mut self,
         msg: &Signed<BlockResponse>,
-        txs: &MapIndex<S, Hash, Signed<RawTransaction>>,
-        txs_pool: &KeySetIndex<S, Hash>,
+        schema: &Schema<S>,
     ) -> &IncompleteBlock {
         assert!(self.incomplete_block().is_none());
 
         let mut unknown_txs = HashSet::new();
+        let tx_cache = self.tx_cache.txs();
         for hash in msg.transactions() {
-            if check_tx(hash, &txs, &self.tx_cache) {
-                if !self.tx_cache.contains_key(hash) && !txs_pool.contains(hash) {
+            if check_tx(hash, schema, &tx_cache) {
+                if !tx_cache.contains_key(hash) && !schema.transactions_pool().contains(hash) {
                     panic!(
                         "Received block with already \
                          committed transaction"
@@ -1066,6 +1222,16 @@ impl State {
         votes.count() >= majority_count
     }
 
+    /// Returns the prevote of the same validator for another propose in the same round, if any.
+    pub fn conflicting_prevote(&self, msg: &Signed<Prevote>) -> Option<Signed<Prevote>> {
+        self.prevotes
+            .iter()
+            .filter(|((round, hash), _)| *round == msg.round() && hash != msg.propose_hash())
+            .flat_map(|(_, votes)| votes.messages())
+            .find(|other| other.validator() == msg.validator())
+            .cloned()
+    }
+
     /// Returns `true` if there are +2/3 pre-votes for the specified round and hash.
     pub fn has_majority_prevotes(&self, round: Round, propose_hash: Hash) -> bool {
         match self.prevotes.get(&(round, propose_hash)) {
@@ -1124,6 +1290,16 @@ impl State {
         votes.count() >= majority_count
     }
 
+    /// Returns the precommit of the same validator for another block in the same round, if any.
+    pub fn conflicting_precommit(&self, msg: &Signed<Precommit>) -> Option<Signed<Precommit>> {
+        self.precommits
+            .iter()
+            .filter(|((round, hash), _)| *round == msg.round() && hash != msg.block_hash())
+            .flat_map(|(_, votes)| votes.messages())
+            .find(|other| other.validator() == msg.validator())
+            .cloned()
+    }
+
     /// Adds unknown (for this node) propose.
     pub fn add_unknown_propose_with_precommits(
         &mut self,
@@ -1208,6 +1384,20 @@ impl State {
         state.map(|s| s.known_nodes).unwrap_or_default()
     }
 
+    /// Returns the data of the pending requests.
+    pub(crate) fn pending_requests(&self) -> Vec<&RequestData> {
+        self.requests.keys().collect()
+    }
+
+    /// Removes all the pending requests and returns their data along with the peers
+    /// which have the required information.
+    pub(crate) fn take_requests(&mut self) -> Vec<(RequestData, HashSet<PublicKey>)> {
+        self.requests
+            .drain()
+            .map(|(data, state)| (data, state.known_nodes))
+            .collect()
+    }
+
     /// Returns the `Connect` message of the current node.
     pub fn our_connect_message(&self) -> &Signed<Connect> {
         &self.our_connect_message
@@ -1228,18 +1418,107 @@ impl State {
         list.add(peer);
     }
 
+    /// Removes peer from node's `ConnectList`. Returns `true` if the peer was in the list.
+    pub fn remove_peer_from_connect_list(&mut self, public_key: &PublicKey) -> bool {
+        let mut list = self
+            .connect_list
+            .inner
+            .write()
+            .expect("ConnectList write lock");
+        list.remove(public_key).is_some()
+    }
+
+    /// Replaces node's `ConnectList` with the given one. Returns the keys of the peers
+    /// which have been added to the list and the keys of the peers which have been removed
+    /// from it.
+    pub fn update_connect_list(
+        &mut self,
+        connect_list: ConnectList,
+    ) -> (Vec<PublicKey>, Vec<PublicKey>) {
+        let mut list = self
+            .connect_list
+            .inner
+            .write()
+            .expect("ConnectList write lock");
+        let added = connect_list
+            .peers
+            .keys()
+            .filter(|key| !list.is_peer_allowed(key))
+            .cloned()
+            .collect();
+        let removed = list
+            .peers
+            .keys()
+            .filter(|key| !connect_list.is_peer_allowed(key))
+            .cloned()
+            .collect();
+        *list = connect_list;
+        (added, removed)
+    }
+
     /// Returns the transactions cache length.
     pub fn tx_cache_len(&self) -> usize {
-        self.tx_cache.len()
+        self.tx_cache.txs().len()
+    }
+
+    /// Returns the transactions cache. The cache is shared with the API, so the guard
+    /// should not be held while the cache is changed.
+    pub fn tx_cache(&self) -> RwLockReadGuard<BTreeMap<Hash, Signed<RawTransaction>>> {
+        self.tx_cache.txs()
     }
 
-    /// Returns reference to the transactions cache.
-    pub fn tx_cache(&self) -> &BTreeMap<Hash, Signed<RawTransaction>> {
-        &self.tx_cache
+    /// Returns the transactions cache for changing.
+    pub fn tx_cache_mut(&mut self) -> RwLockWriteGuard<BTreeMap<Hash, Signed<RawTransaction>>> {
+        self.tx_cache.txs_mut()
+    }
+
+    /// Returns the arrival times of the uncommitted transactions.
+    ///
+    /// Transactions restored from the persistent pool after the node restart
+    /// have no arrival time.
+    pub fn tx_arrival_times(&self) -> RwLockReadGuard<HashMap<Hash, SystemTime>> {
+        self.tx_cache.arrival_times()
     }
 
-    /// Returns mutable reference to the transactions cache.
-    pub fn tx_cache_mut(&mut self) -> &mut BTreeMap<Hash, Signed<RawTransaction>> {
-        &mut self.tx_cache
+    /// Returns the transactions cache and the arrival times shared with the API.
+    pub(crate) fn shared_tx_cache(&self) -> SharedTxCache {
+        self.tx_cache.clone()
+    }
+
+    /// Records the time at which the transaction with the given hash was received.
+    pub(crate) fn add_tx_arrival_time(&mut self, tx_hash: Hash, time: SystemTime) {
+        self.tx_cache.arrival_times_mut().insert(tx_hash, time);
+    }
+
+    /// Forgets the arrival times of the given transactions.
+    pub(crate) fn remove_tx_arrival_times<'a, I>(&mut self, tx_hashes: I)
+    where
+        I: IntoIterator<Item = &'a Hash>,
+    {
+        let mut arrival_times = self.tx_cache.arrival_times_mut();
+        for tx_hash in tx_hashes {
+            arrival_times.remove(tx_hash);
+        }
+    }
+
+    /// Records the transactions which have failed the execution in advance for
+    /// the proposal, and returns the ones which have failed `max_failures` times in a row.
+    /// The returned transactions and the ones which have not failed this time are forgotten.
+    pub(crate) fn record_failed_dry_runs(
+        &mut self,
+        failed: &[Hash],
+        max_failures: u32,
+    ) -> Vec<Hash> {
+        let previous = mem::replace(&mut self.failed_dry_runs, HashMap::new());
+        let mut exhausted = Vec::new();
+        for tx_hash in failed {
+            let failures = previous.get(tx_hash).map_or(1, |failures| failures + 1);
+            if failures >= max_failures {
+                exhausted.push(*tx_hash);
+            } else {
+                self.failed_dry_runs.insert(*tx_hash, failures);
+            }
+        }
+        exhausted
     }
 }
@@ -0,0 +1,146 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Round timeouts adapted to the observed network latency.
+
+use std::{
+    cmp,
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use super::{NodeHandler, RequestData};
+use crate::blockchain::TimeoutPolicy;
+use crate::helpers::{Milliseconds, Round};
+
+/// Network latency observed by the node. Used by the adaptive timeout policy.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyEstimator {
+    /// Moving average of the times spent to commit a block since the height start.
+    commit_time: Option<Milliseconds>,
+    /// Moving average of the round-trip times of the requests to peers.
+    request_rtt: Option<Milliseconds>,
+    /// Times at which the pending requests were sent.
+    pending_requests: HashMap<RequestData, SystemTime>,
+    /// Timeout of the first round chosen after the latest commit.
+    round_timeout: Option<Milliseconds>,
+}
+
+impl NodeHandler {
+    /// Returns the timeout of the first round chosen by the adaptive timeout policy,
+    /// or `None` if the policy is static or the latency has not been observed yet.
+    pub(crate) fn adaptive_round_timeout(&self) -> Option<Milliseconds> {
+        if self.state.consensus_config().timeout_policy.is_static() {
+            return None;
+        }
+        self.latency.round_timeout
+    }
+
+    /// Remembers the time at which the request was sent.
+    pub(crate) fn request_sent(&mut self, data: RequestData) {
+        if self.state.consensus_config().timeout_policy.is_static() {
+            return;
+        }
+        let now = self.system_state.current_time();
+        self.latency.pending_requests.entry(data).or_insert(now);
+    }
+
+    /// Observes the round-trip time of the request whose data has been received.
+    pub(crate) fn request_completed(&mut self, data: &RequestData) {
+        let smoothing_percent = match self.state.consensus_config().timeout_policy {
+            TimeoutPolicy::Adaptive {
+                smoothing_percent, ..
+            } => smoothing_percent,
+            TimeoutPolicy::Static => return,
+        };
+        if let Some(sent) = self.latency.pending_requests.remove(data) {
+            if let Ok(rtt) = self.system_state.current_time().duration_since(sent) {
+                self.latency.request_rtt = Some(moving_average(
+                    self.latency.request_rtt,
+                    to_millis(rtt),
+                    smoothing_percent,
+                ));
+            }
+        }
+    }
+
+    /// Observes the time spent to commit the block and chooses the timeout of the first
+    /// round at the next height. The time is observed only if the block is committed
+    /// in one of the rounds of the node, i.e., not obtained from peers during synchronization.
+    ///
+    /// The timeout is the larger of the average commit time and the time needed for
+    /// the proposal and two rounds of votes estimated from the round-trip times,
+    /// multiplied by the margin and limited by the policy bounds. Since the commit time
+    /// grows if the first rounds fail, too short timeouts are increased automatically.
+    pub(crate) fn observe_commit(&mut self, round: Option<Round>) {
+        let (min_round_timeout, max_round_timeout, smoothing_percent, margin_percent) =
+            match self.state.consensus_config().timeout_policy {
+                TimeoutPolicy::Adaptive {
+                    min_round_timeout,
+                    max_round_timeout,
+                    smoothing_percent,
+                    margin_percent,
+                } => (
+                    min_round_timeout,
+                    max_round_timeout,
+                    smoothing_percent,
+                    margin_percent,
+                ),
+                TimeoutPolicy::Static => return,
+            };
+
+        let now = self.system_state.current_time();
+        if round.is_some() {
+            if let Ok(commit_time) = now.duration_since(self.state.height_start_time()) {
+                self.latency.commit_time = Some(moving_average(
+                    self.latency.commit_time,
+                    to_millis(commit_time),
+                    smoothing_percent,
+                ));
+            }
+        }
+        // Requests are cleared at the new height.
+        self.latency.pending_requests.clear();
+
+        let max_propose_timeout = self.max_propose_timeout();
+        let observed = cmp::max(
+            self.latency.commit_time,
+            self.latency
+                .request_rtt
+                .map(|rtt| 2 * rtt + max_propose_timeout),
+        );
+        self.latency.round_timeout = observed.map(|latency| {
+            cmp::min(
+                cmp::max(latency * margin_percent / 100, min_round_timeout),
+                max_round_timeout,
+            )
+        });
+    }
+}
+
+/// Updates the exponentially weighted moving average with the new sample.
+fn moving_average(
+    average: Option<Milliseconds>,
+    sample: Milliseconds,
+    smoothing_percent: u64,
+) -> Milliseconds {
+    match average {
+        Some(average) => (average * (100 - smoothing_percent) + sample * smoothing_percent) / 100,
+        None => sample,
+    }
+}
+
+fn to_millis(duration: Duration) -> Milliseconds {
+    duration.as_secs() * 1000 + Milliseconds::from(duration.subsec_millis())
+}
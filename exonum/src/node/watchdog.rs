@@ -0,0 +1,147 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of the consensus stalls.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{NodeHandler, NodeTimeout, WatchdogConfig};
+use crate::crypto::PublicKey;
+
+/// State of the consensus stall watchdog.
+#[derive(Debug)]
+pub(crate) struct Watchdog {
+    config: WatchdogConfig,
+    /// Time since which the node waits for a new block.
+    progress_time: SystemTime,
+    /// Whether a stall has been detected since the latest commit.
+    stalled: bool,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            progress_time: UNIX_EPOCH,
+            stalled: false,
+        }
+    }
+
+    fn stall_timeout(&self) -> Duration {
+        Duration::from_secs(self.config.stall_timeout)
+    }
+}
+
+impl NodeHandler {
+    /// Starts waiting for a new block and adds `NodeTimeout::Watchdog` timeout to the channel.
+    /// Does nothing if the watchdog is disabled.
+    pub(crate) fn add_watchdog_timeout(&mut self) {
+        let now = self.system_state.current_time();
+        let stall_timeout = match self.watchdog {
+            Some(ref mut watchdog) => {
+                watchdog.progress_time = now;
+                watchdog.stall_timeout()
+            }
+            None => return,
+        };
+        self.add_timeout(NodeTimeout::Watchdog, now + stall_timeout);
+    }
+
+    /// Notifies the watchdog that a block has been committed.
+    pub(crate) fn reset_watchdog(&mut self) {
+        let now = self.system_state.current_time();
+        let height = self.state.height();
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.progress_time = now;
+            if watchdog.stalled {
+                watchdog.stalled = false;
+                self.api_state.set_consensus_stalled(false);
+                info!("Consensus has recovered from the stall, height={}", height);
+            }
+        }
+    }
+
+    /// Handles `NodeTimeout::Watchdog`. If no block has been committed for `stall_timeout`,
+    /// the node dumps its consensus state to the log, reports the stall via the healthcheck,
+    /// reconnects to the disconnected peers and resends the pending requests. The recovery
+    /// actions are repeated every `stall_timeout` until a new block is committed.
+    pub(crate) fn handle_watchdog_timeout(&mut self) {
+        let (progress_time, stall_timeout) = match self.watchdog {
+            Some(ref watchdog) => (watchdog.progress_time, watchdog.stall_timeout()),
+            None => return,
+        };
+        let now = self.system_state.current_time();
+        let stalled_for = now.duration_since(progress_time).unwrap_or_default();
+        if stalled_for < stall_timeout {
+            // A block has been committed since the timeout was added.
+            self.add_timeout(NodeTimeout::Watchdog, progress_time + stall_timeout);
+            return;
+        }
+
+        if let Some(ref mut watchdog) = self.watchdog {
+            watchdog.stalled = true;
+        }
+        self.api_state.set_consensus_stalled(true);
+        self.dump_consensus_state(stalled_for);
+        self.reconnect_peers();
+        self.resync_requests();
+        self.add_timeout(NodeTimeout::Watchdog, now + stall_timeout);
+    }
+
+    fn dump_consensus_state(&self, stalled_for: Duration) {
+        let info = self.consensus_state_info();
+        let state = serde_json::to_string(&info).unwrap_or_else(|e| e.to_string());
+        error!(
+            "Consensus stalled: no block has been committed for {} s, height={}, round={}, \
+             connected_peers={}, pending_requests={:?}, state={}",
+            stalled_for.as_secs(),
+            self.state.height(),
+            self.state.round(),
+            self.state.connections().len(),
+            self.state.pending_requests(),
+            state,
+        );
+    }
+
+    /// Sends the `Connect` message to the peers from the connect list the node is not
+    /// connected to.
+    fn reconnect_peers(&mut self) {
+        let our_key = *self.state.consensus_public_key();
+        let peers: Vec<PublicKey> = self
+            .state
+            .connect_list()
+            .peers()
+            .into_iter()
+            .map(|info| info.public_key)
+            .filter(|key| *key != our_key && !self.state.connections().contains_key(key))
+            .collect();
+
+        for key in peers {
+            info!("Trying to reconnect with peer {}", key);
+            self.connect(key);
+        }
+    }
+
+    /// Restarts the pending requests and requests the next block from the peers
+    /// with the bigger height.
+    fn resync_requests(&mut self) {
+        for (data, known_nodes) in self.state.take_requests() {
+            for peer in known_nodes {
+                self.request(data.clone(), peer);
+            }
+        }
+        self.request_next_block();
+        self.broadcast_status();
+    }
+}
@@ -59,12 +59,17 @@
 //! }
 //! ```
 
-pub use self::schema::blockchain::{Block, ConfigReference, TransactionResult, TxLocation};
+pub use self::schema::blockchain::{
+    ArchivedBlock, Block, ConfigReference, DoubleSignEvidence, HeightDiagnostics, LivenessRecord,
+    PeerEvent, PeerStats, ProposeDiagnostics, RejectedTransaction, ServiceDescriptor, StateChange,
+    TransactionResult, TxLocation, ValidatorLiveness,
+};
 pub use self::schema::helpers::{BitVec, Hash, PublicKey, Signature};
+pub use self::schema::proofs::{BlockProof, IndexProof, MapProof, MapProofEntry, MapProofNode};
 pub use self::schema::protocol::{
-    BlockRequest, BlockResponse, Connect, PeersRequest, PoolTransactionsRequest, Precommit,
-    Prevote, PrevotesRequest, Propose, ProposeRequest, Status, TransactionsRequest,
-    TransactionsResponse,
+    BlockRequest, BlockResponse, Connect, CosignerSignature, MultisigTransaction, PeersRequest,
+    PoolTransactionsRequest, Precommit, Prevote, PrevotesRequest, Propose, ProposeRequest, Status,
+    TransactionsRequest, TransactionsResponse,
 };
 
 pub mod schema;
@@ -75,10 +80,11 @@ mod macros;
 mod tests;
 
 use chrono::{DateTime, TimeZone, Utc};
+use exonum_merkledb::BinaryValue;
 use failure::Error;
 use protobuf::well_known_types;
 
-use std::collections::HashMap;
+use std::{borrow::Cow, cell::Cell, collections::HashMap};
 
 use crate::crypto;
 use crate::helpers::{Height, Round, ValidatorId};
@@ -96,6 +102,71 @@ pub trait ProtobufConvert: Sized {
     fn from_pb(pb: Self::ProtoStruct) -> Result<Self, Error>;
 }
 
+/// Deserializes the value and checks that the bytes are its canonical encoding.
+///
+/// The canonical encoding is the one produced by `BinaryValue::to_bytes`: the fields
+/// are in the order of their numbers, the fields with default values and unknown fields
+/// are omitted, and the integers are encoded with the minimal number of bytes.
+/// The networks may require the messages and transactions to be encoded canonically with
+/// `StoredConfiguration::canonical_encoding`, so that each message has a single valid
+/// encoding and, therefore, a single hash.
+///
+/// Values with `map` fields have no canonical encoding, since the order of the map
+/// entries is not defined.
+pub fn from_canonical_bytes<T: BinaryValue>(bytes: &[u8]) -> Result<T, Error> {
+    let value = T::from_bytes(Cow::from(bytes))?;
+    ensure!(
+        value.to_bytes() == bytes,
+        "Message is not encoded canonically"
+    );
+    Ok(value)
+}
+
+thread_local! {
+    static CANONICAL_ENCODING: Cell<bool> = Cell::new(false);
+}
+
+/// Deserializes the payload of a transaction. The payload must be encoded canonically,
+/// see [`from_canonical_bytes`](fn.from_canonical_bytes.html), if the transaction is
+/// received by the node while `StoredConfiguration::canonical_encoding` is set; otherwise,
+/// any valid encoding is accepted, so that the blocks committed before the requirement
+/// can be executed again.
+///
+/// `#[derive(TransactionSet)]` deserializes the transactions with this function.
+pub fn from_payload_bytes<T: BinaryValue>(bytes: &[u8]) -> Result<T, Error> {
+    if CANONICAL_ENCODING.with(Cell::get) {
+        from_canonical_bytes(bytes)
+    } else {
+        T::from_bytes(Cow::from(bytes))
+    }
+}
+
+/// Calls the closure requiring the payloads deserialized with `from_payload_bytes`
+/// on the current thread to be encoded canonically.
+pub(crate) fn with_canonical_encoding<R, F: FnOnce() -> R>(f: F) -> R {
+    // Restores the previous requirement even if the closure panics.
+    struct Reset(bool);
+
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            let previous = self.0;
+            CANONICAL_ENCODING.with(|required| required.set(previous));
+        }
+    }
+
+    let _reset = Reset(CANONICAL_ENCODING.with(|required| required.replace(true)));
+    f()
+}
+
+/// Converts the serialized value into its canonical encoding, see
+/// [`from_canonical_bytes`](fn.from_canonical_bytes.html).
+///
+/// Clients can use this function to check the encoding produced by other
+/// serializers before signing a message.
+pub fn to_canonical_bytes<T: BinaryValue>(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    T::from_bytes(Cow::from(bytes)).map(|value| value.to_bytes())
+}
+
 impl ProtobufConvert for crypto::Hash {
     type ProtoStruct = Hash;
 
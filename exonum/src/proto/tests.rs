@@ -18,7 +18,10 @@ use chrono::{DateTime, TimeZone, Utc};
 use std::{borrow::Cow, collections::HashMap};
 
 use super::schema;
-use super::ProtobufConvert;
+use super::{
+    from_canonical_bytes, from_payload_bytes, to_canonical_bytes, with_canonical_encoding,
+    ProtobufConvert,
+};
 use crate::crypto::{self, Hash, PublicKey, Signature};
 use exonum_merkledb::BinaryValue;
 
@@ -137,6 +140,54 @@ fn test_simple_struct_round_trip() {
     assert_eq!(point_encode_round_trip, point);
 }
 
+#[test]
+fn test_canonical_encoding() {
+    let point = Point { x: 1, y: 2 };
+    let bytes = point.to_bytes();
+    assert_eq!(bytes, vec![0x08, 0x01, 0x10, 0x02]);
+    assert_eq!(from_canonical_bytes::<Point>(&bytes).unwrap(), point);
+
+    let non_canonical_encodings = [
+        // Fields out of order.
+        vec![0x10, 0x02, 0x08, 0x01],
+        // Non-minimal varint.
+        vec![0x08, 0x81, 0x00, 0x10, 0x02],
+        // Repeated field.
+        vec![0x08, 0x05, 0x08, 0x01, 0x10, 0x02],
+        // Unknown field.
+        vec![0x08, 0x01, 0x10, 0x02, 0x18, 0x03],
+    ];
+    for encoding in &non_canonical_encodings {
+        assert_eq!(Point::from_bytes(Cow::from(encoding)).unwrap(), point);
+        assert!(from_canonical_bytes::<Point>(encoding).is_err());
+        assert_eq!(to_canonical_bytes::<Point>(encoding).unwrap(), bytes);
+    }
+
+    // Default value encoded explicitly.
+    let encoding = vec![0x08, 0x00, 0x10, 0x02];
+    assert!(from_canonical_bytes::<Point>(&encoding).is_err());
+    assert_eq!(
+        to_canonical_bytes::<Point>(&encoding).unwrap(),
+        vec![0x10, 0x02]
+    );
+}
+
+#[test]
+fn test_payload_encoding_requirement() {
+    let point = Point { x: 1, y: 2 };
+    let encoding = vec![0x10, 0x02, 0x08, 0x01];
+    // Any encoding is accepted unless the canonical one is required.
+    assert_eq!(from_payload_bytes::<Point>(&encoding).unwrap(), point);
+    with_canonical_encoding(|| {
+        assert!(from_payload_bytes::<Point>(&encoding).is_err());
+        assert_eq!(
+            from_payload_bytes::<Point>(&point.to_bytes()).unwrap(),
+            point
+        );
+    });
+    assert_eq!(from_payload_bytes::<Point>(&encoding).unwrap(), point);
+}
+
 #[derive(Debug, PartialEq, ProtobufConvert)]
 #[exonum(pb = "schema::tests::TestProtobufConvert", crate = "crate")]
 struct StructWithScalarTypes {
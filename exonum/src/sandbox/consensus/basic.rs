@@ -16,14 +16,19 @@
 //! related to consensus protocol handling, such as ability of the node
 //! to add block after receiving correct consensus messages.
 
+use futures::Stream;
 use rand::{thread_rng, Rng};
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
-use crate::blockchain::{Blockchain, Schema, CORE_SERVICE};
+use crate::api::node::public::system::PeerClockSkew;
+use crate::blockchain::{Blockchain, PeerEventKind, Schema, CORE_SERVICE};
 use crate::crypto::{gen_keypair_from_seed, CryptoHash, Hash, Seed, HASH_SIZE, SEED_LENGTH};
-use crate::helpers::{Height, Round, ValidatorId};
+use crate::helpers::{metrics::MetricValue, Height, Round, ValidatorId};
 use crate::messages::{Precommit, Signed};
+use crate::node::{
+    ConnectInfo, ConnectListConfig, DiagnosticsConfig, LivenessConfig, PeerStatsConfig,
+};
 use crate::sandbox::{
     self,
     sandbox_tests_helper::*,
@@ -281,6 +286,237 @@ fn test_store_txs_positions() {
     }
 }
 
+#[test]
+fn notify_block_subscribers() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+    let mut events = sandbox
+        .node_handler_mut()
+        .api_state()
+        .subscribe_blocks()
+        .wait();
+
+    add_one_height(&sandbox, &sandbox_state);
+    let txs = TimestampingTxGenerator::new(DATA_SIZE)
+        .take(2)
+        .map(|tx| (tx.hash(), tx))
+        .collect::<BTreeMap<_, _>>();
+    let hashes = add_one_height_with_transactions(&sandbox, &sandbox_state, txs.values());
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let schema = Schema::new(&snapshot);
+    for (height, tx_hashes) in vec![(Height(1), vec![]), (Height(2), hashes)] {
+        let event = events.next().unwrap().unwrap();
+        let block = schema.block_and_precommits(height).unwrap().block;
+        assert_eq!(event.height, height);
+        assert_eq!(event.block_hash, block.hash());
+        assert_eq!(event.tx_hashes, tx_hashes);
+        assert_eq!(event.state_hash, *block.state_hash());
+    }
+}
+
+#[test]
+fn notify_block_subscribers_at_depth() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+    let mut events = sandbox
+        .node_handler_mut()
+        .api_state()
+        .subscribe_blocks_at_depth(2)
+        .wait();
+
+    for _ in 0..3 {
+        add_one_height(&sandbox, &sandbox_state);
+    }
+    // Only the first block is followed by two blocks.
+    let event = events.next().unwrap().unwrap();
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let block = Schema::new(&snapshot)
+        .block_and_precommits(Height(1))
+        .unwrap()
+        .block;
+    assert_eq!(event.height, Height(1));
+    assert_eq!(event.block_hash, block.hash());
+    assert_eq!(event.state_hash, *block.state_hash());
+
+    add_one_height(&sandbox, &sandbox_state);
+    assert_eq!(events.next().unwrap().unwrap().height, Height(2));
+}
+
+#[test]
+fn report_consensus_metrics() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+    add_one_height(&sandbox, &sandbox_state);
+
+    let metrics = sandbox.node_handler_mut().api_state().metrics().clone();
+    let counter = |name| match metrics.value(name) {
+        Some(MetricValue::Counter { value }) => value,
+        other => panic!("Unexpected value of {}: {:?}", name, other),
+    };
+    assert_eq!(counter("exonum_consensus_proposes_sent_total"), 1);
+    assert_eq!(counter("exonum_consensus_prevotes_sent_total"), 1);
+    assert_eq!(counter("exonum_consensus_precommits_sent_total"), 1);
+    assert_eq!(counter("exonum_consensus_precommits_received_total"), 2);
+    assert_eq!(
+        metrics.value("exonum_consensus_height"),
+        Some(MetricValue::Gauge { value: 2 })
+    );
+    match metrics.value("exonum_consensus_block_interval_milliseconds") {
+        Some(MetricValue::Summary { count, .. }) => assert_eq!(count, 1),
+        other => panic!("Unexpected block interval: {:?}", other),
+    }
+    assert!(metrics
+        .render()
+        .contains("# TYPE exonum_consensus_proposes_sent_total counter\n"));
+}
+
+#[test]
+fn record_height_diagnostics() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_diagnostics(DiagnosticsConfig { keep_heights: 2 })
+        .build();
+    let sandbox_state = SandboxState::new();
+    for _ in 0..3 {
+        add_one_height(&sandbox, &sandbox_state);
+    }
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let index = Schema::new(&snapshot).height_diagnostics();
+    assert_eq!(index.keys().collect::<Vec<_>>(), vec![2, 3]);
+    for (height, diagnostics) in index.iter() {
+        let round = diagnostics.round;
+        assert_eq!(diagnostics.height, Height(height));
+        // The node commits each block proposed by itself.
+        assert_eq!(diagnostics.proposer, ValidatorId(0));
+        assert_eq!(diagnostics.leaders.len(), round.0 as usize);
+        assert_eq!(diagnostics.leaders.last(), Some(&ValidatorId(0)));
+        assert_eq!(
+            diagnostics.round_timeouts,
+            Round::first().iter_to(round).collect::<Vec<_>>()
+        );
+        assert_eq!(diagnostics.propose_timeouts.last(), Some(&round));
+        let propose = diagnostics.proposes.last().unwrap();
+        assert_eq!(propose.validator, ValidatorId(0));
+        assert_eq!(propose.round, round);
+        assert_eq!(propose.tx_count, 1);
+        assert!(diagnostics.started_at <= diagnostics.committed_at);
+    }
+}
+
+#[test]
+fn record_peer_stats() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_peer_stats(PeerStatsConfig { keep_events: 2 })
+        .build();
+    let peer = sandbox.public_key(ValidatorId(1));
+    let connect = sandbox.connect().unwrap().clone();
+
+    sandbox.recv_peer_connected(ValidatorId(1));
+    sandbox.recv(&sandbox.create_status(
+        &peer,
+        sandbox.current_height(),
+        &empty_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(1)),
+    ));
+    // The node reconnects to the validator.
+    sandbox.recv_peer_disconnected(peer);
+    sandbox.send(peer, &connect);
+    sandbox.recv_unable_to_connect(peer);
+    sandbox.send(peer, &connect);
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let stats = Schema::new(&snapshot).peer_stats().get(&peer).unwrap();
+    assert_eq!(stats.connects, 1);
+    assert_eq!(stats.disconnects, 1);
+    assert_eq!(stats.connection_failures, 1);
+    assert_eq!(stats.messages, 1);
+    assert_eq!(stats.errors, 0);
+    // Only the latest events are kept.
+    assert_eq!(
+        stats
+            .events
+            .iter()
+            .map(|event| event.kind)
+            .collect::<Vec<_>>(),
+        [PeerEventKind::Disconnected, PeerEventKind::ConnectionFailed]
+    );
+}
+
+#[test]
+fn report_round_changes() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+    assert_eq!(
+        sandbox.node_handler_mut().api_state().round_changes(),
+        Default::default()
+    );
+    add_one_height(&sandbox, &sandbox_state);
+    add_one_height(&sandbox, &sandbox_state);
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let schema = Schema::new(&snapshot);
+    let rounds: Vec<u64> = vec![Height(1), Height(2)]
+        .into_iter()
+        .map(|height| {
+            let precommits = schema.block_and_precommits(height).unwrap().precommits;
+            u64::from(precommits[0].round().0)
+        })
+        .collect();
+
+    let stats = sandbox.node_handler_mut().api_state().round_changes();
+    assert_eq!(stats.heights, 2);
+    assert_eq!(
+        stats.heights_with_round_changes,
+        rounds.iter().filter(|&&round| round > 1).count() as u64
+    );
+    let average_rounds = stats.average_rounds_per_height.unwrap();
+    assert_eq!(
+        (average_rounds * 2.0).round() as u64,
+        rounds.iter().sum::<u64>()
+    );
+    assert_eq!(
+        stats
+            .leaders
+            .iter()
+            .map(|leader| leader.failed_rounds)
+            .sum::<u64>(),
+        rounds.iter().map(|round| round - 1).sum::<u64>()
+    );
+}
+
+#[test]
+fn estimate_clock_skew_from_precommits() {
+    let sandbox = timestamping_sandbox();
+    let propose = ProposeBuilder::new(&sandbox).build();
+    let block = BlockBuilder::new(&sandbox).build();
+
+    sandbox.recv(&propose);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+    // The clock of the validator is 10 seconds ahead.
+    let peer_time = sandbox.time() + Duration::from_secs(10);
+    sandbox.recv(&sandbox.create_precommit(
+        ValidatorId(1),
+        Height(1),
+        Round(1),
+        &propose.hash(),
+        &block.hash(),
+        peer_time.into(),
+        sandbox.secret_key(ValidatorId(1)),
+    ));
+
+    let info = sandbox.node_handler_mut().api_state().clock_skew();
+    assert_eq!(info.skew, Some(-10_000));
+    assert_eq!(
+        info.peers,
+        vec![PeerClockSkew {
+            public_key: sandbox.public_key(ValidatorId(1)),
+            offset: 10_000,
+        }]
+    );
+}
+
 #[test]
 fn tx_cache_with_tx_block_limit() {
     let sandbox = timestamping_sandbox_builder()
@@ -308,3 +544,94 @@ fn tx_cache_with_tx_block_limit() {
 
     //TODO: check pool after commit.
 }
+
+/// idea of the test is to verify that the precommits missed by the validators
+/// are counted over the sliding window of the latest blocks
+#[test]
+fn test_validator_liveness() {
+    let sandbox = timestamping_sandbox();
+    sandbox.blockchain_mut().set_liveness(Some(LivenessConfig {
+        window: 2,
+        min_uptime_percent: 50,
+    }));
+    let sandbox_state = SandboxState::new();
+
+    for _ in 0..3 {
+        add_one_height(&sandbox, &sandbox_state);
+    }
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let schema = Schema::new(&snapshot);
+    let liveness = schema.validator_liveness();
+    // The precommits of `validator_3` are not needed for the majority.
+    let lagging = liveness.get(&sandbox.public_key(ValidatorId(3))).unwrap();
+    assert_eq!(lagging.blocks, 2);
+    assert_eq!(lagging.missed_precommits, 2);
+    assert_eq!(lagging.uptime_percent(), 0);
+    let active = liveness.get(&sandbox.public_key(ValidatorId(0))).unwrap();
+    assert_eq!(active.blocks, 2);
+    assert_eq!(active.missed_precommits, 0);
+    assert_eq!(active.missed_proposals, 0);
+    assert_eq!(active.uptime_percent(), 100);
+
+    // The node proposes and commits the blocks in the same round, so the leaders
+    // of all the preceding rounds have missed their proposals.
+    let expected_missed_proposals = (2..=3)
+        .map(|height| {
+            let block_hash = schema.block_hashes_by_height().get(height).unwrap();
+            let precommit = schema.precommits(&block_hash).get(0).unwrap();
+            u64::from(precommit.round().0) - 1
+        })
+        .sum::<u64>();
+    let missed_proposals = liveness
+        .values()
+        .map(|liveness| liveness.missed_proposals)
+        .sum::<u64>();
+    assert_eq!(missed_proposals, expected_missed_proposals);
+}
+
+/// idea of the test is to verify that the peer removed from the connect list is forgotten
+/// and its messages are ignored
+#[test]
+fn remove_peer_from_connect_list() {
+    let sandbox = timestamping_sandbox();
+    let removed = sandbox.public_key(ValidatorId(1));
+    assert!(sandbox.node_state().peers().contains_key(&removed));
+
+    sandbox.recv_peer_remove(removed);
+    assert!(!sandbox.node_state().peer_in_connect_list(&removed));
+    assert!(!sandbox.node_state().peers().contains_key(&removed));
+
+    // The node does not request the block from the removed peer.
+    sandbox.recv(&sandbox.create_status(
+        &removed,
+        Height(2),
+        &empty_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(1)),
+    ));
+}
+
+/// idea of the test is to verify that the node connects to the peers added
+/// to the connect list and forgets the removed ones
+#[test]
+fn update_connect_list() {
+    let sandbox = timestamping_sandbox();
+    let removed = sandbox.public_key(ValidatorId(1));
+    let (added, _) = gen_keypair_from_seed(&Seed::new([42; SEED_LENGTH]));
+
+    let mut peers = sandbox.node_state().connect_list().peers();
+    peers.retain(|peer| peer.public_key != removed);
+    peers.push(ConnectInfo {
+        address: "127.0.0.1:9000".to_owned(),
+        fallback_addresses: Vec::new(),
+        public_key: added,
+    });
+    sandbox.recv_connect_list_update(ConnectListConfig { peers });
+
+    let connect = sandbox.node_state().our_connect_message().clone();
+    sandbox.send(added, &connect);
+    assert!(sandbox.node_state().peer_in_connect_list(&added));
+    assert!(!sandbox.node_state().peer_in_connect_list(&removed));
+    assert!(!sandbox.node_state().peers().contains_key(&removed));
+}
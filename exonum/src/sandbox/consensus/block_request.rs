@@ -16,10 +16,15 @@
 
 use std::time::Duration;
 
-use crate::crypto::CryptoHash;
+use crate::crypto::{CryptoHash, Hash};
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::node::state::{BLOCK_REQUEST_TIMEOUT, TRANSACTIONS_REQUEST_TIMEOUT};
-use crate::sandbox::{compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox};
+use crate::node::{
+    state::{BLOCK_REQUEST_TIMEOUT, TRANSACTIONS_REQUEST_TIMEOUT},
+    CatchUpConfig,
+};
+use crate::sandbox::{
+    compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox, timestamping_sandbox_builder,
+};
 
 /// HANDLE block response
 
@@ -960,3 +965,115 @@ fn transactions_request_to_multiple_nodes() {
         sandbox.secret_key(ValidatorId(0)),
     ));
 }
+
+/// HANDLE block response in the catch-up mode
+
+/// - blocks following the current one should be requested at once and applied in order
+/// idea of test is:
+/// - getting Status from other node with the height bigger by `min_lag`
+/// - send BlockRequest for both missing blocks to this node
+/// - receive BlockResponse for the second block, it should be saved
+/// - receive BlockResponse for the first block
+/// - both blocks should be committed
+#[test]
+fn request_blocks_ahead_in_catch_up_mode() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_catch_up(CatchUpConfig {
+            min_lag: 2,
+            max_blocks_ahead: 2,
+        })
+        .build();
+
+    let first_propose = ProposeBuilder::new(&sandbox).build();
+    let first_block = BlockBuilder::new(&sandbox).build();
+    let second_propose = ProposeBuilder::new(&sandbox)
+        .with_height(Height(2))
+        .with_prev_hash(&first_block.hash())
+        .build();
+    let second_block = BlockBuilder::new(&sandbox)
+        .with_height(Height(2))
+        .with_prev_hash(&first_block.hash())
+        .build();
+
+    let precommits = |height: Height, propose_hash: &Hash, block_hash: &Hash| {
+        (1..4)
+            .map(|i| {
+                sandbox.create_precommit(
+                    ValidatorId(i),
+                    height,
+                    Round(1),
+                    propose_hash,
+                    block_hash,
+                    sandbox.time().into(),
+                    sandbox.secret_key(ValidatorId(i)),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let first_precommits = precommits(Height(1), &first_propose.hash(), &first_block.hash());
+    let second_precommits = precommits(Height(2), &second_propose.hash(), &second_block.hash());
+
+    sandbox.recv(&sandbox.create_status(
+        &sandbox.public_key(ValidatorId(3)),
+        Height(3),
+        &second_block.hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+
+    sandbox.add_time(Duration::from_millis(BLOCK_REQUEST_TIMEOUT));
+    for height in &[Height(1), Height(2)] {
+        sandbox.send(
+            sandbox.public_key(ValidatorId(3)),
+            &sandbox.create_block_request(
+                &sandbox.public_key(ValidatorId(0)),
+                &sandbox.public_key(ValidatorId(3)),
+                *height,
+                sandbox.secret_key(ValidatorId(0)),
+            ),
+        );
+    }
+
+    sandbox.recv(&sandbox.create_block_response(
+        &sandbox.public_key(ValidatorId(3)),
+        &sandbox.public_key(ValidatorId(0)),
+        second_block.clone(),
+        second_precommits,
+        &[],
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    sandbox.assert_state(Height(1), Round(1));
+
+    sandbox.recv(&sandbox.create_block_response(
+        &sandbox.public_key(ValidatorId(3)),
+        &sandbox.public_key(ValidatorId(0)),
+        first_block.clone(),
+        first_precommits,
+        &[],
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+
+    sandbox.broadcast(&sandbox.create_status(
+        &sandbox.public_key(ValidatorId(0)),
+        Height(2),
+        &first_block.hash(),
+        0,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+    sandbox.broadcast(&sandbox.create_status(
+        &sandbox.public_key(ValidatorId(0)),
+        Height(3),
+        &second_block.hash(),
+        0,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+    sandbox.assert_state(Height(3), Round(1));
+
+    // The precommits of the second block are verified while the first one is merged.
+    let verification = sandbox
+        .node_handler_mut()
+        .api_state
+        .verification_metrics()
+        .info();
+    assert_eq!(verification.pipelined_blocks, 1);
+}
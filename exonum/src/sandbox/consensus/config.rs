@@ -17,6 +17,7 @@
 use crate::blockchain::Schema;
 use crate::crypto::CryptoHash;
 use crate::helpers::{Height, ValidatorId};
+use crate::node::NodeRole;
 use crate::sandbox::{config_updater::TxConfig, sandbox_tests_helper::*, timestamping_sandbox};
 use exonum_merkledb::BinaryValue;
 
@@ -50,6 +51,61 @@ fn test_exclude_validator_from_consensus() {
     add_one_height_with_transactions_from_other_validator(&sandbox, &sandbox_state, &[]);
 }
 
+/// - exclude validator from consensus and add it back
+/// - idea of test is to check that the auditor joins the consensus once the configuration
+///   adding its keys becomes actual
+#[test]
+fn test_promote_auditor_to_validator() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+    let node_role = || sandbox.node_handler_mut().api_state().node_role();
+
+    add_one_height(&sandbox, &sandbox_state);
+    let initial_cfg = sandbox.cfg();
+
+    let tx_cfg = {
+        let mut consensus_cfg = sandbox.cfg();
+        consensus_cfg.validator_keys.swap_remove(0);
+        consensus_cfg.actual_from = sandbox.current_height().next().next();
+        consensus_cfg.previous_cfg_hash = sandbox.cfg().hash();
+
+        TxConfig::create_signed(
+            &sandbox.public_key(ValidatorId(0)),
+            &consensus_cfg.clone().into_bytes(),
+            consensus_cfg.actual_from,
+            sandbox.secret_key(ValidatorId(0)),
+        )
+    };
+
+    add_one_height_with_transactions(&sandbox, &sandbox_state, &[tx_cfg.clone()]);
+    add_one_height(&sandbox, &sandbox_state);
+    assert!(node_role().is_auditor());
+
+    let tx_cfg = {
+        let mut consensus_cfg = initial_cfg.clone();
+        consensus_cfg.actual_from = sandbox.current_height().next().next();
+        consensus_cfg.previous_cfg_hash = sandbox.cfg().hash();
+
+        TxConfig::create_signed(
+            &sandbox.public_key(ValidatorId(1)),
+            &consensus_cfg.clone().into_bytes(),
+            consensus_cfg.actual_from,
+            sandbox.secret_key(ValidatorId(1)),
+        )
+    };
+
+    add_one_height_with_transactions_from_other_validator(&sandbox, &sandbox_state, &[tx_cfg]);
+    assert!(node_role().is_auditor());
+    add_one_height_with_transactions_from_other_validator(&sandbox, &sandbox_state, &[]);
+
+    // The node is a validator again without a restart.
+    match node_role() {
+        NodeRole::Validator(id) => assert_eq!(id, ValidatorId(0)),
+        NodeRole::Auditor => panic!("The node is expected to be a validator"),
+    }
+    add_one_height(&sandbox, &sandbox_state);
+}
+
 /// - Check `following_configuration` method in schema
 /// - idea of the test is check configurations method from schema
 #[test]
@@ -15,6 +15,8 @@
 //! Tests in this module are designed to test ability of the node to handle
 //! incorrect messages.
 
+use crate::blockchain::Schema;
+use crate::crypto::hash;
 use crate::helpers::{Height, Round, ValidatorId};
 use crate::messages::{Message, Propose};
 use crate::sandbox::{sandbox_tests_helper::*, timestamping_sandbox};
@@ -139,3 +141,47 @@ fn handle_propose_that_sends_before_than_propose_timeout_exceeded() {
         sandbox.secret_key(ValidatorId(0)),
     ));
 }
+
+/// HANDLE conflicting prevotes
+/// - record the evidence of the validator signing prevotes for different proposes
+///   in the same round
+#[test]
+fn test_record_double_sign_evidence() {
+    let sandbox = timestamping_sandbox();
+
+    let first = sandbox.create_prevote(
+        ValidatorId(1),
+        Height(1),
+        Round(1),
+        &hash(&[1]),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(1)),
+    );
+    let second = sandbox.create_prevote(
+        ValidatorId(1),
+        Height(1),
+        Round(1),
+        &hash(&[2]),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(1)),
+    );
+    sandbox.recv(&first);
+    sandbox.recv(&second);
+    // The repeated message is not a new evidence.
+    sandbox.recv(&first);
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let evidence = Schema::new(&snapshot)
+        .double_sign_evidence()
+        .values()
+        .collect::<Vec<_>>();
+    assert_eq!(evidence.len(), 1);
+    assert_eq!(evidence[0].validator(), ValidatorId(1));
+    assert_eq!(evidence[0].height(), Height(1));
+    assert_eq!(evidence[0].round(), Round(1));
+    let mut messages = vec![evidence[0].first(), evidence[0].second()];
+    messages.sort();
+    let mut expected = vec![first.signed_message().raw(), second.signed_message().raw()];
+    expected.sort();
+    assert_eq!(messages, expected);
+}
@@ -15,11 +15,15 @@
 //! Tests in this module are designed to test ability of the node to recover
 //! state after restart/stop.
 
+use exonum_merkledb::BinaryValue;
+
 use std::time::Duration;
 
-use crate::crypto::CryptoHash;
+use crate::blockchain::Schema;
+use crate::crypto::{CryptoHash, Hash};
 use crate::helpers::{user_agent, Height, Round, ValidatorId};
-use crate::node;
+use crate::messages::Message;
+use crate::node::{self, GarbageCollectionConfig};
 use crate::sandbox::{sandbox_tests_helper::*, timestamping_sandbox, SandboxBuilder};
 
 #[test]
@@ -71,6 +75,46 @@ fn test_disable_and_enable() {
     sandbox.assert_state(Height(3), Round(1));
 }
 
+#[test]
+fn test_pause_and_resume() {
+    let mut sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+    try_add_one_height(&sandbox, &sandbox_state).unwrap();
+
+    // Pause the node.
+    let message = node::ExternalMessage::Pause;
+    sandbox
+        .node_handler_mut()
+        .channel
+        .api_requests
+        .send(message)
+        .unwrap();
+    sandbox.process_events();
+    assert!(sandbox.node_handler_mut().api_state().is_paused());
+
+    // Messages received during the pause are not processed.
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    assert_eq!(sandbox.node_state().tx_cache_len(), 0);
+    sandbox.assert_state(Height(2), Round(1));
+
+    // Resume the node.
+    let message = node::ExternalMessage::Resume;
+    sandbox
+        .node_handler_mut()
+        .channel
+        .api_requests
+        .send(message)
+        .unwrap();
+    sandbox.process_events();
+    assert!(!sandbox.node_handler_mut().api_state().is_paused());
+
+    // The messages received during the pause are processed after the resume.
+    assert_eq!(sandbox.node_state().tx_cache_len(), 1);
+    assert!(sandbox.node_state().tx_cache().contains_key(&tx.hash()));
+    sandbox.assert_state(Height(2), Round(1));
+}
+
 /// Scenario:
 /// - Node sends `Propose` and `Prevote`.
 /// - Node restarts.
@@ -456,3 +500,49 @@ fn should_restore_peers_after_restart() {
     sandbox_restarted.recv(&peers_request);
     sandbox_restarted.send(public_key1, &connect_from_1);
 }
+
+#[test]
+fn remove_superseded_consensus_messages() {
+    let sandbox = SandboxBuilder::new()
+        .with_garbage_collection(GarbageCollectionConfig {
+            max_pool_tx_age: None,
+            max_pool_tx_heights: None,
+            keep_rounds: Some(1),
+        })
+        .build();
+
+    let prevotes = (1..3)
+        .map(|round| {
+            Message::from(sandbox.create_prevote(
+                ValidatorId(1),
+                Height(1),
+                Round(round),
+                &Hash::zero(),
+                NOT_LOCKED,
+                sandbox.secret_key(ValidatorId(1)),
+            ))
+        })
+        .collect::<Vec<_>>();
+    sandbox
+        .blockchain_mut()
+        .save_messages(Round(1), prevotes.clone());
+
+    // Messages of the current round are kept.
+    sandbox.node_handler_mut().collect_garbage();
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    assert_eq!(Schema::new(&snapshot).consensus_messages_cache().len(), 2);
+
+    sandbox.node_handler_mut().state.jump_round(Round(2));
+    sandbox.node_handler_mut().collect_garbage();
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let cached = Schema::new(&snapshot)
+        .consensus_messages_cache()
+        .iter()
+        .map(|message| message.to_bytes())
+        .collect::<Vec<_>>();
+    assert_eq!(cached, vec![prevotes[1].to_bytes()]);
+
+    let stats = sandbox.node_handler_mut().api_state().gc_stats().unwrap();
+    assert_eq!(stats.runs, 2);
+    assert_eq!(stats.removed_consensus_messages, 1);
+}
@@ -1814,3 +1814,30 @@ fn handle_precommit_remove_propose_request_ask_precommitters() {
         "Should send TransactionsRequest to all validators"
     );
 }
+
+/// - consensus state reported to the API reflects the collected votes
+#[test]
+fn consensus_state_info_reflects_prevotes() {
+    let sandbox = timestamping_sandbox();
+
+    let propose = ProposeBuilder::new(&sandbox).build();
+    sandbox.recv(&propose);
+    sandbox.broadcast(&sandbox.create_prevote(
+        ValidatorId(0),
+        Height(1),
+        Round(1),
+        &propose.hash(),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+
+    let info = sandbox.node_handler_mut().consensus_state_info();
+    assert_eq!(info.height, Height(1));
+    assert_eq!(info.round, Round(1));
+    assert_eq!(info.locked_propose, None);
+    assert_eq!(info.prevotes.len(), 1);
+    assert_eq!(info.prevotes[0].hash, propose.hash());
+    assert_eq!(info.prevotes[0].count, 1);
+    assert!(info.precommits.is_empty());
+    assert!(info.timeouts.round_start_time < info.timeouts.next_round_start_time);
+}
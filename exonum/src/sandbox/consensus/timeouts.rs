@@ -16,10 +16,13 @@
 
 use std::time::Duration;
 
+use crate::blockchain::{ConsensusConfig, TimeoutPolicy};
 use crate::crypto::CryptoHash;
 use crate::helpers::{Height, Round, ValidatorId};
-use crate::node::state::PROPOSE_REQUEST_TIMEOUT;
-use crate::sandbox::{compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox};
+use crate::node::{state::PROPOSE_REQUEST_TIMEOUT, WatchdogConfig};
+use crate::sandbox::{
+    compute_tx_hash, sandbox_tests_helper::*, timestamping_sandbox, timestamping_sandbox_builder,
+};
 
 /// HANDLE ROUND TIMEOUT:
 /// - Ignore if height and round are not the same
@@ -284,3 +287,84 @@ fn test_round_timeout_increase() {
     sandbox.add_time(Duration::from_millis(1));
     sandbox.assert_state(Height(1), Round(5));
 }
+
+/// Adaptive timeout policy:
+/// - the configured timeout is used until the first block is committed
+/// - then the timeout is chosen from the observed commit time within the policy bounds
+#[test]
+fn adaptive_round_timeout_within_bounds() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_consensus(|config| {
+            config.timeout_policy = TimeoutPolicy::Adaptive {
+                min_round_timeout: 500,
+                max_round_timeout: 20_000,
+                smoothing_percent: 50,
+                margin_percent: 150,
+            };
+        })
+        .build();
+    let sandbox_state = SandboxState::new();
+
+    assert_eq!(sandbox.first_round_timeout(), 1000);
+    add_one_height(&sandbox, &sandbox_state);
+    let timeout = sandbox.first_round_timeout();
+    assert!(timeout >= 500 && timeout <= 20_000);
+
+    // Consensus proceeds with the adapted timeouts.
+    add_one_height(&sandbox, &sandbox_state);
+    add_one_height(&sandbox, &sandbox_state);
+    sandbox.assert_state(Height(4), Round(1));
+}
+
+/// Adaptive timeout policy:
+/// - the chosen timeout never exceeds the upper bound of the policy
+#[test]
+fn adaptive_round_timeout_clamped() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_consensus(|config| {
+            config.timeout_policy = TimeoutPolicy::Adaptive {
+                min_round_timeout: 300,
+                max_round_timeout: 300,
+                smoothing_percent: 100,
+                margin_percent: 200,
+            };
+        })
+        .build();
+    let sandbox_state = SandboxState::new();
+
+    add_one_height(&sandbox, &sandbox_state);
+    assert_eq!(sandbox.first_round_timeout(), 300);
+    assert_eq!(
+        sandbox.round_timeout_increase(),
+        300 * ConsensusConfig::TIMEOUT_LINEAR_INCREASE_PERCENT / 100
+    );
+    add_one_height(&sandbox, &sandbox_state);
+    sandbox.assert_state(Height(3), Round(1));
+}
+
+/// Consensus stall watchdog:
+/// - the stall is detected if no block has been committed for the stall timeout
+/// - the node reconnects to the peers and broadcasts its status
+/// - the stall is cleared after the next commit
+#[test]
+fn watchdog_detects_consensus_stall() {
+    let mut sandbox = timestamping_sandbox_builder()
+        .with_watchdog(WatchdogConfig { stall_timeout: 60 })
+        .build();
+    let sandbox_state = SandboxState::new();
+    let api_state = sandbox.node_handler_mut().api_state().clone();
+
+    sandbox.node_handler_mut().handle_watchdog_timeout();
+    assert!(!api_state.is_consensus_stalled());
+
+    let start = sandbox.time();
+    sandbox.set_time(start + Duration::from_secs(61));
+    sandbox.node_handler_mut().handle_watchdog_timeout();
+    assert!(api_state.is_consensus_stalled());
+    sandbox.broadcast(sandbox.connect().unwrap());
+    sandbox.check_broadcast_status(Height(1), &sandbox.last_hash());
+
+    sandbox.set_time(start);
+    add_one_height(&sandbox, &sandbox_state);
+    assert!(!api_state.is_consensus_stalled());
+}
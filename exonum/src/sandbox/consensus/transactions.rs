@@ -16,12 +16,16 @@
 
 use bit_vec::BitVec;
 
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
+use crate::blockchain::{RejectionReason, Schema, TxCandidate, TxOrderingPolicy};
 use crate::crypto::{gen_keypair, CryptoHash, Hash};
 use crate::helpers::{Height, Milliseconds, Round, ValidatorId};
 use crate::messages::{RawTransaction, Signed};
 use crate::node::state::TRANSACTIONS_REQUEST_TIMEOUT;
+use crate::node::{
+    EvictionStrategy, GarbageCollectionConfig, MemoryPoolConfig, RemoveTransactionError,
+};
 use crate::sandbox::{
     compute_tx_hash,
     config_updater::TxConfig,
@@ -263,6 +267,208 @@ fn rebroadcast_transactions() {
     }
 }
 
+#[test]
+fn remove_transaction_from_pool() {
+    let sandbox = timestamping_sandbox();
+
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    sandbox.assert_tx_cache_len(1);
+
+    assert_eq!(sandbox.recv_remove_transaction(tx.hash()), Ok(()));
+    sandbox.assert_tx_cache_len(0);
+    sandbox.assert_pool_len(0);
+    assert_eq!(
+        sandbox.recv_remove_transaction(tx.hash()),
+        Err(RemoveTransactionError::NotFound)
+    );
+
+    // Removed transaction should not be rebroadcast.
+    sandbox.recv_rebroadcast();
+}
+
+#[test]
+fn remove_transaction_from_persistent_pool() {
+    let sandbox = timestamping_sandbox();
+
+    let tx = gen_timestamping_tx();
+    {
+        let mut blockchain = sandbox.blockchain_mut();
+        let fork = blockchain.fork();
+        Schema::new(&fork).add_transaction_into_pool(tx.clone());
+        blockchain.merge(fork.into_patch()).unwrap();
+    }
+    sandbox.assert_pool_len(1);
+
+    assert_eq!(sandbox.recv_remove_transaction(tx.hash()), Ok(()));
+    sandbox.assert_pool_len(0);
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let schema = Schema::new(&snapshot);
+    assert!(!schema.transactions().contains(&tx.hash()));
+    let rejected = schema.rejected_transactions().get(&tx.hash()).unwrap();
+    assert_eq!(rejected.reason, RejectionReason::Removed);
+
+    // Removed transaction should not be rebroadcast.
+    sandbox.recv_rebroadcast();
+}
+
+#[test]
+fn remove_proposed_transaction_is_refused() {
+    let sandbox = timestamping_sandbox();
+
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_tx_hashes(&[tx.hash()])
+        .build();
+    sandbox.recv(&propose);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+
+    assert_eq!(
+        sandbox.recv_remove_transaction(tx.hash()),
+        Err(RemoveTransactionError::Proposed)
+    );
+    sandbox.assert_tx_cache_len(1);
+}
+
+#[test]
+fn evict_outdated_transactions() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_garbage_collection(GarbageCollectionConfig {
+            max_pool_tx_age: Some(60),
+            max_pool_tx_heights: None,
+            keep_rounds: None,
+        })
+        .build();
+
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    sandbox.assert_tx_cache_len(1);
+
+    // The transaction is not outdated yet.
+    sandbox.node_handler_mut().collect_garbage();
+    sandbox.assert_tx_cache_len(1);
+
+    let arrival_time = sandbox.time() - Duration::from_secs(61);
+    sandbox
+        .node_handler_mut()
+        .state
+        .add_tx_arrival_time(tx.hash(), arrival_time);
+    sandbox.node_handler_mut().collect_garbage();
+    sandbox.assert_tx_cache_len(0);
+    sandbox.assert_pool_len(0);
+
+    let stats = sandbox.node_handler_mut().api_state().gc_stats().unwrap();
+    assert_eq!(stats.runs, 2);
+    assert_eq!(stats.evicted_transactions, 1);
+
+    // Evicted transaction should not be rebroadcast.
+    sandbox.recv_rebroadcast();
+}
+
+#[test]
+fn evict_transactions_after_max_heights() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_garbage_collection(GarbageCollectionConfig {
+            max_pool_tx_age: None,
+            max_pool_tx_heights: Some(1),
+            keep_rounds: None,
+        })
+        .build();
+    let sandbox_state = SandboxState::new();
+
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    // The transaction is seen by the garbage collector at the current height.
+    sandbox.node_handler_mut().collect_garbage();
+    sandbox.assert_tx_cache_len(1);
+
+    add_one_height(&sandbox, &sandbox_state);
+    sandbox.node_handler_mut().collect_garbage();
+    sandbox.assert_tx_cache_len(0);
+    sandbox.assert_pool_len(0);
+
+    let snapshot = sandbox.blockchain_ref().snapshot();
+    let rejected = Schema::new(&snapshot)
+        .rejected_transactions()
+        .get(&tx.hash())
+        .unwrap();
+    assert_eq!(rejected.reason, RejectionReason::Expired);
+    assert_eq!(rejected.height, Height(2));
+}
+
+#[test]
+fn evict_oldest_transactions_from_full_pool() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_mempool(MemoryPoolConfig {
+            max_transactions: Some(5),
+            eviction: EvictionStrategy::OldestFirst,
+            ..MemoryPoolConfig::default()
+        })
+        .build();
+
+    let txs = (0..6).map(|_| gen_timestamping_tx()).collect::<Vec<_>>();
+    for tx in &txs[..5] {
+        sandbox.recv(tx);
+    }
+    sandbox.assert_tx_cache_len(5);
+
+    // The first two transactions are the oldest ones.
+    for (i, tx) in txs[..2].iter().enumerate() {
+        let arrival_time = sandbox.time() - Duration::from_secs(10 - i as u64);
+        sandbox
+            .node_handler_mut()
+            .state
+            .add_tx_arrival_time(tx.hash(), arrival_time);
+    }
+
+    // The pool is reduced to 90% of the limit.
+    sandbox.recv(&txs[5]);
+    sandbox.assert_tx_cache_len(4);
+    {
+        let handler = sandbox.node_handler_mut();
+        let tx_cache = handler.state.tx_cache();
+        assert!(txs[..2].iter().all(|tx| !tx_cache.contains_key(&tx.hash())));
+        assert!(txs[2..].iter().all(|tx| tx_cache.contains_key(&tx.hash())));
+    }
+
+    let stats = sandbox
+        .node_handler_mut()
+        .api_state()
+        .eviction_stats()
+        .unwrap();
+    assert_eq!(stats.runs, 1);
+    assert_eq!(stats.evicted_transactions, 2);
+}
+
+#[test]
+fn tx_cache_shared_with_api() {
+    let sandbox = timestamping_sandbox();
+    let sandbox_state = SandboxState::new();
+
+    // The API sees the received transaction without waiting for the API state update.
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    let tx_cache = sandbox.node_handler_mut().api_state().tx_cache();
+    assert!(tx_cache.txs().contains_key(&tx.hash()));
+    assert_eq!(
+        tx_cache.arrival_times().get(&tx.hash()),
+        Some(&sandbox.time())
+    );
+
+    sandbox
+        .node_handler_mut()
+        .handle_remove_transaction(&tx.hash())
+        .unwrap();
+    assert!(tx_cache.txs().is_empty());
+    assert!(tx_cache.arrival_times().is_empty());
+
+    // Arrival times of the committed transactions are forgotten as well.
+    add_one_height_with_transactions(&sandbox, &sandbox_state, &[gen_timestamping_tx()]);
+    assert!(tx_cache.txs().is_empty());
+    assert!(tx_cache.arrival_times().is_empty());
+}
+
 // TODO: transaction verification logic is duplicated,
 // in sandbox so this test is testing sandbox
 #[test]
@@ -320,6 +526,36 @@ fn incorrect_tx_in_request() {
     sandbox.add_time(Duration::from_millis(TRANSACTIONS_REQUEST_TIMEOUT));
 }
 
+#[test]
+fn tx_larger_than_max_message_len() {
+    let sandbox = timestamping_sandbox();
+    let max_message_len = sandbox.cfg().consensus.max_message_len as usize;
+    let tx = TimestampingTxGenerator::new(max_message_len)
+        .next()
+        .unwrap();
+    assert!(tx.signed_message().raw().len() > max_message_len);
+
+    sandbox.recv(&tx);
+    sandbox.assert_tx_cache_len(0);
+}
+
+#[test]
+fn secp256k1_transactions_are_not_allowed_by_default() {
+    use crate::crypto::secp256k1;
+    use crate::messages::Message;
+    use crate::sandbox::timestamping::{TimestampTx, TIMESTAMPING_SERVICE};
+
+    let sandbox = timestamping_sandbox();
+    assert!(!sandbox.cfg().secp256k1_signatures);
+    let mut payload = TimestampTx::new();
+    payload.set_data(vec![1; DATA_SIZE]);
+    let (_, secret_key) = secp256k1::gen_keypair();
+    let tx = Message::sign_transaction_secp256k1(payload, TIMESTAMPING_SERVICE, None, &secret_key);
+
+    sandbox.recv(&tx);
+    sandbox.assert_tx_cache_len(0);
+}
+
 #[test]
 fn response_size_larger_than_max_message_len() {
     use crate::messages::{RAW_TRANSACTION_HEADER, TRANSACTION_RESPONSE_EMPTY_SIZE};
@@ -731,3 +967,134 @@ fn expedited_propose_on_transaction_pressure() {
     sandbox.broadcast(&propose);
     sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
 }
+
+/// Idea of the scenario is to:
+/// - skip the empty propose while the transaction pool is empty
+/// - propose the block as soon as the transaction is received
+#[test]
+fn skip_empty_propose_until_transaction_arrives() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_consensus(|config| {
+            config.max_idle_interval = Some(600_000);
+        })
+        .build();
+
+    while !sandbox.is_leader() {
+        sandbox.add_time(Duration::from_millis(sandbox.current_round_timeout()));
+    }
+    // The pool is empty, so no propose is broadcast.
+    sandbox.add_time(Duration::from_millis(MAX_PROPOSE_TIMEOUT));
+
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_tx_hashes(&[tx.hash()])
+        .build();
+    sandbox.broadcast(&propose);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+}
+
+/// Idea of the scenario is to:
+/// - become the leader before `min_block_interval` has passed since the start of the height
+/// - delay the propose until the interval has passed, even if a transaction arrives
+#[test]
+fn delay_propose_until_min_block_interval() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_consensus(|config| {
+            config.min_block_interval = Some(1_500);
+        })
+        .build();
+
+    sandbox.add_time(Duration::from_millis(sandbox.current_round_timeout()));
+    assert!(sandbox.is_leader());
+    // 1200 ms have passed since the start of the height.
+    sandbox.add_time(Duration::from_millis(MAX_PROPOSE_TIMEOUT));
+
+    let tx = gen_timestamping_tx();
+    sandbox.recv(&tx);
+    sandbox.add_time(Duration::from_millis(299));
+
+    sandbox.add_time(Duration::from_millis(1));
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_tx_hashes(&[tx.hash()])
+        .build();
+    sandbox.broadcast(&propose);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+}
+
+/// Scheduler taking the transactions in the reverse order of their arrival.
+#[derive(Debug)]
+struct LatestFirst;
+
+impl TxOrderingPolicy for LatestFirst {
+    fn order(&self, candidates: Vec<TxCandidate>, limit: usize) -> Vec<Hash> {
+        candidates
+            .into_iter()
+            .rev()
+            .take(limit)
+            .map(|candidate| candidate.hash)
+            .collect()
+    }
+}
+
+/// Idea of the scenario is to:
+/// - plug a custom transaction scheduler into the blockchain
+/// - receive several transactions and propose them in the order chosen by the scheduler
+#[test]
+fn propose_with_custom_tx_scheduler() {
+    let sandbox = timestamping_sandbox();
+    sandbox
+        .blockchain_mut()
+        .set_tx_scheduler(Some(Arc::new(LatestFirst)));
+    let transactions = TimestampingTxGenerator::new(64).take(3).collect::<Vec<_>>();
+
+    while !sandbox.is_leader() {
+        sandbox.add_time(Duration::from_millis(sandbox.current_round_timeout()));
+    }
+    for tx in &transactions {
+        sandbox.recv(tx);
+        sandbox.add_time(Duration::from_millis(1));
+    }
+    sandbox.add_time(Duration::from_millis(PROPOSE_TIMEOUT));
+
+    let hashes = transactions
+        .iter()
+        .rev()
+        .map(Signed::hash)
+        .collect::<Vec<_>>();
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_tx_hashes(&hashes)
+        .build();
+    sandbox.broadcast(&propose);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+}
+
+/// Idea of the scenario is to:
+/// - receive several transactions which do not fit into a single block
+/// - propose the block containing only the transactions within `max_block_size`
+#[test]
+fn propose_within_max_block_size() {
+    let transactions = TimestampingTxGenerator::new(64).take(3).collect::<Vec<_>>();
+    let tx_size = transactions[0].signed_message().raw().len() as u64;
+    let sandbox = timestamping_sandbox_builder()
+        .with_consensus(|config| {
+            config.max_block_size = Some(tx_size * 2 + tx_size / 2);
+        })
+        .build();
+
+    while !sandbox.is_leader() {
+        sandbox.add_time(Duration::from_millis(sandbox.current_round_timeout()));
+    }
+    for tx in &transactions {
+        sandbox.recv(tx);
+    }
+    sandbox.add_time(Duration::from_millis(MAX_PROPOSE_TIMEOUT));
+
+    // Cached transactions are taken in the order of their hashes.
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_tx_hashes(&tx_hashes(&transactions)[..2])
+        .build();
+    sandbox.broadcast(&propose);
+    sandbox.broadcast(&make_prevote_from_propose(&sandbox, &propose));
+}
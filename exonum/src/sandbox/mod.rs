@@ -13,7 +13,10 @@
 // limitations under the License.
 
 use bit_vec::BitVec;
-use futures::{sync::mpsc, Async, Future, Sink, Stream};
+use futures::{
+    sync::{mpsc, oneshot},
+    Async, Future, Sink, Stream,
+};
 
 use std::{
     cell::{Ref, RefCell, RefMut},
@@ -31,13 +34,15 @@ use crate::blockchain::check_tx;
 use crate::messages::PoolTransactionsRequest;
 use crate::{
     blockchain::{
-        Block, BlockProof, Blockchain, ConsensusConfig, GenesisConfig, Schema, Service,
-        SharedNodeState, StoredConfiguration, Transaction, ValidatorKeys,
+        Block, BlockProof, Blockchain, ConsensusConfig, GenesisConfig, LeaderSelection, Schema,
+        Service, SharedNodeState, StoredConfiguration, TimeoutPolicy, Transaction, TxOrdering,
+        ValidatorKeys,
     },
     crypto::{gen_keypair, gen_keypair_from_seed, Hash, PublicKey, SecretKey, Seed, SEED_LENGTH},
     events::{
-        network::NetworkConfiguration, Event, EventHandler, InternalEvent, InternalRequest,
-        NetworkEvent, NetworkRequest, TimeoutRequest,
+        network::{ConnectedPeerAddr, NetworkConfiguration},
+        Event, EventHandler, InternalEvent, InternalRequest, NetworkEvent, NetworkRequest,
+        TimeoutRequest,
     },
     helpers::{user_agent, Height, Milliseconds, Round, ValidatorId},
     messages::{
@@ -46,9 +51,10 @@ use crate::{
         SignedMessage, Status, TransactionsRequest, TransactionsResponse,
     },
     node::{
-        ApiSender, Configuration, ConnectInfo, ConnectList, ConnectListConfig, ExternalMessage,
-        ListenerConfig, NodeHandler, NodeSender, PeerAddress, ServiceConfig, State,
-        SystemStateProvider,
+        ApiSender, CatchUpConfig, Configuration, ConnectInfo, ConnectList, ConnectListConfig,
+        DiagnosticsConfig, ExternalMessage, GarbageCollectionConfig, ListenerConfig,
+        MemoryPoolConfig, NodeHandler, NodeSender, PeerAddress, PeerStatsConfig,
+        RemoveTransactionError, ServiceConfig, State, SystemStateProvider, WatchdogConfig,
     },
     sandbox::{
         config_updater::ConfigUpdateService, sandbox_tests_helper::PROPOSE_TIMEOUT,
@@ -134,14 +140,25 @@ impl SandboxInner {
                         .handler
                         .handle_event(InternalEvent::JumpToRound(height, round).into()),
                     InternalRequest::Shutdown => unimplemented!(),
-                    InternalRequest::VerifyMessage(message) => {
+                    InternalRequest::VerifyMessage(_, message, network_id, max_len) => {
                         let protocol =
-                            Message::deserialize(SignedMessage::from_raw_buffer(message).unwrap())
+                            Message::from_raw_buffer_with_limit(message, network_id, max_len)
                                 .unwrap();
                         self.handler.handle_event(
                             InternalEvent::MessageVerified(Box::new(protocol)).into(),
                         );
                     }
+                    InternalRequest::VerifyMessages(messages, network_id, max_len) => {
+                        for message in
+                            SignedMessage::from_raw_buffers(messages, network_id).unwrap()
+                        {
+                            let protocol =
+                                Message::deserialize_with_limit(message, max_len).unwrap();
+                            self.handler.handle_event(
+                                InternalEvent::MessageVerified(Box::new(protocol)).into(),
+                            );
+                        }
+                    }
                 }
             }
             Ok(())
@@ -479,7 +496,15 @@ impl Sandbox {
 
     pub fn recv<T: ProtocolMessage>(&self, msg: &Signed<T>) {
         self.check_unexpected_message();
-        let event = NetworkEvent::MessageReceived(msg.clone().serialize());
+        let address = self
+            .addresses
+            .iter()
+            .find(|info| info.public_key == msg.author())
+            .map_or_else(
+                || gen_primitive_socket_addr(0),
+                |info| info.address.parse().expect("Invalid sandbox address"),
+            );
+        let event = NetworkEvent::MessageReceived(address, msg.clone().serialize());
         self.inner.borrow_mut().handle_event(event);
     }
 
@@ -490,6 +515,62 @@ impl Sandbox {
             .handle_event(ExternalMessage::Rebroadcast);
     }
 
+    pub fn recv_remove_transaction(&self, tx_hash: Hash) -> Result<(), RemoveTransactionError> {
+        self.check_unexpected_message();
+        let (result_tx, result_rx) = oneshot::channel();
+        self.inner
+            .borrow_mut()
+            .handle_event(ExternalMessage::RemoveTransaction(tx_hash, result_tx));
+        result_rx
+            .wait()
+            .expect("Outcome of the removal is not sent")
+    }
+
+    pub fn recv_peer_remove(&self, public_key: PublicKey) {
+        self.check_unexpected_message();
+        self.inner
+            .borrow_mut()
+            .handle_event(ExternalMessage::PeerRemove(public_key));
+    }
+
+    pub fn recv_connect_list_update(&self, connect_list: ConnectListConfig) {
+        self.check_unexpected_message();
+        self.inner
+            .borrow_mut()
+            .handle_event(ExternalMessage::UpdateConnectList(connect_list));
+    }
+
+    /// Notifies the node that the connection with the validator has been established.
+    pub fn recv_peer_connected(&self, id: ValidatorId) {
+        self.check_unexpected_message();
+        let address = self.address(id);
+        let connect = self.create_connect(
+            &self.public_key(id),
+            address.clone(),
+            self.time().into(),
+            &user_agent::get(),
+            self.secret_key(id),
+        );
+        let socket_address = address.parse().expect("Invalid sandbox address");
+        let event =
+            NetworkEvent::PeerConnected(ConnectedPeerAddr::Out(address, socket_address), connect);
+        self.inner.borrow_mut().handle_event(event);
+    }
+
+    pub fn recv_peer_disconnected(&self, public_key: PublicKey) {
+        self.check_unexpected_message();
+        self.inner
+            .borrow_mut()
+            .handle_event(NetworkEvent::PeerDisconnected(public_key));
+    }
+
+    pub fn recv_unable_to_connect(&self, public_key: PublicKey) {
+        self.check_unexpected_message();
+        self.inner
+            .borrow_mut()
+            .handle_event(NetworkEvent::UnableConnectToPeer(public_key));
+    }
+
     pub fn process_events(&self) {
         self.inner.borrow_mut().process_events();
     }
@@ -652,7 +733,6 @@ impl Sandbox {
         let mut unique_set: HashSet<Hash> = HashSet::new();
         let snapshot = self.blockchain_ref().snapshot();
         let schema = Schema::new(&snapshot);
-        let schema_transactions = schema.transactions();
         txs.into_iter()
             .filter(|elem| {
                 let hash_elem = elem.hash();
@@ -660,11 +740,7 @@ impl Sandbox {
                     return false;
                 }
                 unique_set.insert(hash_elem);
-                if check_tx(
-                    &hash_elem,
-                    &schema_transactions,
-                    self.node_state().tx_cache(),
-                ) {
+                if check_tx(&hash_elem, &schema, &self.node_state().tx_cache()) {
                     return false;
                 }
                 true
@@ -712,7 +788,7 @@ impl Sandbox {
             {
                 let mut schema = Schema::new(&fork);
                 for hash in recover {
-                    schema.reject_transaction(&hash).unwrap();
+                    assert!(schema.reject_transaction(&hash));
                 }
             }
             fork.into_patch()
@@ -749,13 +825,11 @@ impl Sandbox {
     }
 
     pub fn first_round_timeout(&self) -> Milliseconds {
-        self.cfg().consensus.first_round_timeout
+        self.inner.borrow().handler.first_round_timeout()
     }
 
     pub fn round_timeout_increase(&self) -> Milliseconds {
-        (self.cfg().consensus.first_round_timeout
-            * ConsensusConfig::TIMEOUT_LINEAR_INCREASE_PERCENT)
-            / 100
+        self.inner.borrow().handler.round_timeout_increase()
     }
 
     pub fn current_round_timeout(&self) -> Milliseconds {
@@ -890,6 +964,17 @@ impl Sandbox {
             network: NetworkConfiguration::default(),
             peer_discovery: Vec::new(),
             mempool: Default::default(),
+            pruning: None,
+            checkpoints: None,
+            garbage_collection: None,
+            watchdog: None,
+            connect_list_watch: None,
+            catch_up: None,
+            diagnostics: None,
+            peer_stats: None,
+            signing_record: None,
+            remote_signer: None,
+            threshold_key: None,
         };
 
         let system_state = SandboxSystemStateProvider {
@@ -954,6 +1039,7 @@ impl Sandbox {
             .state
             .add_peer_to_connect_list(ConnectInfo {
                 address: addr.to_string(),
+                fallback_addresses: Vec::new(),
                 public_key,
             });
     }
@@ -989,6 +1075,12 @@ pub struct SandboxBuilder {
     services: Vec<Box<dyn Service>>,
     validators_count: u8,
     consensus_config: ConsensusConfig,
+    garbage_collection: Option<GarbageCollectionConfig>,
+    mempool: MemoryPoolConfig,
+    watchdog: Option<WatchdogConfig>,
+    catch_up: Option<CatchUpConfig>,
+    diagnostics: Option<DiagnosticsConfig>,
+    peer_stats: Option<PeerStatsConfig>,
 }
 
 impl SandboxBuilder {
@@ -1006,7 +1098,27 @@ impl SandboxBuilder {
                 min_propose_timeout: PROPOSE_TIMEOUT,
                 max_propose_timeout: PROPOSE_TIMEOUT,
                 propose_timeout_threshold: std::u32::MAX,
+                timeout_policy: TimeoutPolicy::Static,
+                max_idle_interval: None,
+                leader_selection: LeaderSelection::RoundRobin,
+                tx_ordering: TxOrdering::Unordered,
+                max_block_size: None,
+                min_block_interval: None,
+                exclude_failing_txs: false,
+                max_failed_dry_runs: None,
+                max_decompressed_len: None,
+                enforce_storage_namespaces: false,
+                parallel_execution: false,
+                threshold_keys: None,
+                storage_quotas: BTreeMap::new(),
+                service_data_versions: BTreeMap::new(),
             },
+            garbage_collection: None,
+            mempool: MemoryPoolConfig::default(),
+            watchdog: None,
+            catch_up: None,
+            diagnostics: None,
+            peer_stats: None,
         }
     }
 
@@ -1030,6 +1142,36 @@ impl SandboxBuilder {
         self
     }
 
+    pub fn with_garbage_collection(mut self, config: GarbageCollectionConfig) -> Self {
+        self.garbage_collection = Some(config);
+        self
+    }
+
+    pub fn with_mempool(mut self, config: MemoryPoolConfig) -> Self {
+        self.mempool = config;
+        self
+    }
+
+    pub fn with_watchdog(mut self, config: WatchdogConfig) -> Self {
+        self.watchdog = Some(config);
+        self
+    }
+
+    pub fn with_catch_up(mut self, config: CatchUpConfig) -> Self {
+        self.catch_up = Some(config);
+        self
+    }
+
+    pub fn with_diagnostics(mut self, config: DiagnosticsConfig) -> Self {
+        self.diagnostics = Some(config);
+        self
+    }
+
+    pub fn with_peer_stats(mut self, config: PeerStatsConfig) -> Self {
+        self.peer_stats = Some(config);
+        self
+    }
+
     pub fn build(self) -> Sandbox {
         let _ = env_logger::Builder::from_default_env()
             .target(env_logger::Target::Stdout)
@@ -1039,6 +1181,12 @@ impl SandboxBuilder {
             self.services,
             self.consensus_config,
             self.validators_count,
+            self.garbage_collection,
+            self.mempool,
+            self.watchdog,
+            self.catch_up,
+            self.diagnostics,
+            self.peer_stats,
         );
 
         sandbox.inner.borrow_mut().sent.clear(); // To clear initial connect messages.
@@ -1057,10 +1205,17 @@ fn gen_primitive_socket_addr(idx: u8) -> SocketAddr {
 }
 
 /// Constructs an uninitialized instance of a `Sandbox`.
+#[allow(clippy::too_many_arguments)]
 fn sandbox_with_services_uninitialized(
     services: Vec<Box<dyn Service>>,
     consensus: ConsensusConfig,
     validators_count: u8,
+    garbage_collection: Option<GarbageCollectionConfig>,
+    mempool: MemoryPoolConfig,
+    watchdog: Option<WatchdogConfig>,
+    catch_up: Option<CatchUpConfig>,
+    diagnostics: Option<DiagnosticsConfig>,
+    peer_stats: Option<PeerStatsConfig>,
 ) -> Sandbox {
     let validators = (0..validators_count)
         .map(|i| gen_keypair_from_seed(&Seed::new([i; SEED_LENGTH])))
@@ -1082,6 +1237,7 @@ fn sandbox_with_services_uninitialized(
         .zip(str_addresses.iter())
         .map(|(p, a)| ConnectInfo {
             address: a.clone(),
+            fallback_addresses: Vec::new(),
             public_key: *p,
         })
         .collect();
@@ -1125,7 +1281,18 @@ fn sandbox_with_services_uninitialized(
         },
         network: NetworkConfiguration::default(),
         peer_discovery: Vec::new(),
-        mempool: Default::default(),
+        mempool,
+        pruning: None,
+        checkpoints: None,
+        garbage_collection,
+        watchdog,
+        connect_list_watch: None,
+        catch_up,
+        diagnostics,
+        peer_stats,
+        signing_record: None,
+        remote_signer: None,
+        threshold_key: None,
     };
 
     let system_state = SandboxSystemStateProvider {
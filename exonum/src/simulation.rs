@@ -0,0 +1,691 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of a network of validators running the consensus under adverse conditions.
+//!
+//! Each validator of the simulation is a complete node handler with its own blockchain
+//! in a temporary database. The nodes share a virtual clock and exchange the messages
+//! through a simulated network, in which the messages can be dropped or delayed by
+//! a scripted filter and the validators can be split into partitions. The simulation
+//! is single-threaded and does not depend on the real time, so the scenarios are
+//! reproducible.
+//!
+//! The module is available with the `simulation` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use exonum::helpers::{Height, ValidatorId};
+//! use exonum::simulation::SimulationBuilder;
+//!
+//! use std::time::Duration;
+//!
+//! let mut simulation = SimulationBuilder::new(4).build();
+//! // A validator is isolated, but the rest of them still have a quorum.
+//! simulation.partition(&[&[ValidatorId(0), ValidatorId(1), ValidatorId(2)]]);
+//! assert!(simulation.run_until(Duration::from_secs(60), |simulation| {
+//!     simulation.height(ValidatorId(0)) >= Height(3)
+//! }));
+//! assert_eq!(simulation.height(ValidatorId(3)), Height(0));
+//! simulation.check_safety().unwrap();
+//! ```
+
+use exonum_merkledb::TemporaryDB;
+use failure::Error;
+use futures::{sync::mpsc, Async, Future, Sink, Stream};
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::blockchain::{
+    Blockchain, ConsensusConfig, GenesisConfig, Schema, Service, SharedNodeState, ValidatorKeys,
+};
+use crate::crypto::{gen_keypair_from_seed, Hash, PublicKey, Seed, SEED_LENGTH};
+use crate::events::{
+    network::NetworkConfiguration, Event, EventHandler, InternalEvent, InternalRequest,
+    NetworkEvent, NetworkRequest, TimeoutRequest,
+};
+use crate::helpers::{Height, ValidatorId};
+use crate::messages::{Message, RawTransaction, Signed, SignedMessage};
+use crate::node::{
+    ApiSender, Configuration, ConnectList, ConnectListConfig, ExternalMessage, ListenerConfig,
+    MemoryPoolConfig, NodeHandler, NodeSender, ServiceConfig, SystemStateProvider,
+};
+
+/// Time at which the simulations start.
+const INITIAL_TIME_IN_SECS: u64 = 1_486_720_340;
+/// Capacity of the channels between the node handlers and the simulation.
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Fate of a message sent through the simulated network.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Delivery {
+    /// The message is delivered with the latency of the network.
+    Deliver,
+    /// The message is delivered with the given delay in addition to the latency.
+    Delay(Duration),
+    /// The message is lost.
+    Drop,
+}
+
+type MessageFilter = Box<dyn FnMut(ValidatorId, ValidatorId, &Message) -> Delivery>;
+
+/// Virtual clock shared by the nodes of the simulation.
+#[derive(Debug)]
+struct SimulatedSystemState {
+    listen_address: SocketAddr,
+    time: Arc<Mutex<SystemTime>>,
+}
+
+impl SystemStateProvider for SimulatedSystemState {
+    fn listen_address(&self) -> SocketAddr {
+        self.listen_address
+    }
+
+    fn current_time(&self) -> SystemTime {
+        *self.time.lock().unwrap()
+    }
+}
+
+/// Request sent by a node handler to the simulation.
+enum Request {
+    Internal(InternalRequest),
+    Api(ExternalMessage),
+    Network(NetworkRequest),
+}
+
+/// Node handler with the receiving ends of its channels.
+#[derive(Debug)]
+struct SimulatedNode {
+    handler: NodeHandler,
+    timers: BinaryHeap<TimeoutRequest>,
+    internal_requests: mpsc::Receiver<InternalRequest>,
+    api_requests: mpsc::Receiver<ExternalMessage>,
+    network_requests: mpsc::Receiver<NetworkRequest>,
+}
+
+impl SimulatedNode {
+    fn poll_requests(&mut self) -> Vec<Request> {
+        let mut requests = Vec::new();
+        futures::lazy(|| -> Result<(), ()> {
+            while let Async::Ready(Some(request)) = self.internal_requests.poll()? {
+                requests.push(Request::Internal(request));
+            }
+            while let Async::Ready(Some(request)) = self.api_requests.poll()? {
+                requests.push(Request::Api(request));
+            }
+            while let Async::Ready(Some(request)) = self.network_requests.poll()? {
+                requests.push(Request::Network(request));
+            }
+            Ok(())
+        })
+        .wait()
+        .unwrap();
+        requests
+    }
+}
+
+/// Message in flight in the simulated network.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct InFlight {
+    delivery_time: SystemTime,
+    /// Sequence number of the message, which orders the messages delivered at the same time.
+    sequence: u64,
+    /// Listen address of the sender.
+    from: SocketAddr,
+    to: usize,
+    message: Vec<u8>,
+}
+
+/// Builder of a simulation.
+pub struct SimulationBuilder {
+    validators_count: u16,
+    consensus: ConsensusConfig,
+    services: Box<dyn Fn() -> Vec<Box<dyn Service>>>,
+    latency: Duration,
+}
+
+impl SimulationBuilder {
+    /// Creates a builder of the simulation with the given number of validators, the default
+    /// consensus configuration, no services and the network latency of 10 milliseconds.
+    pub fn new(validators_count: u16) -> Self {
+        assert!(
+            validators_count > 0,
+            "There should be at least one validator"
+        );
+        Self {
+            validators_count,
+            consensus: ConsensusConfig::default(),
+            services: Box::new(Vec::new),
+            latency: Duration::from_millis(10),
+        }
+    }
+
+    /// Updates the consensus configuration of the genesis block.
+    pub fn with_consensus<F: FnOnce(&mut ConsensusConfig)>(mut self, update: F) -> Self {
+        update(&mut self.consensus);
+        self
+    }
+
+    /// Sets the function creating the services of each validator.
+    pub fn with_services<F>(mut self, services: F) -> Self
+    where
+        F: Fn() -> Vec<Box<dyn Service>> + 'static,
+    {
+        self.services = Box::new(services);
+        self
+    }
+
+    /// Sets the time it takes to deliver a message.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Creates the simulation. The nodes are started and connect to each other.
+    pub fn build(self) -> Simulation {
+        let count = usize::from(self.validators_count);
+        let keys = |offset: usize| {
+            (0..count)
+                .map(|i| gen_keypair_from_seed(&Seed::new([(i + offset) as u8; SEED_LENGTH])))
+                .collect::<Vec<_>>()
+        };
+        let (consensus_keys, service_keys) = (keys(0), keys(count));
+        let addresses = (0..count)
+            .map(|i| SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 6330 + i as u16))
+            .collect::<Vec<_>>();
+        let str_addresses = addresses
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        let genesis = GenesisConfig::new_with_consensus(
+            self.consensus,
+            consensus_keys
+                .iter()
+                .zip(&service_keys)
+                .map(|(consensus, service)| ValidatorKeys {
+                    consensus_key: consensus.0,
+                    service_key: service.0,
+                }),
+        );
+        let connect_list_config =
+            ConnectListConfig::from_validator_keys(&genesis.validator_keys, &str_addresses);
+        let time = Arc::new(Mutex::new(
+            UNIX_EPOCH + Duration::from_secs(INITIAL_TIME_IN_SECS),
+        ));
+
+        let nodes = (0..count)
+            .map(|i| {
+                let internal_channel = mpsc::channel(CHANNEL_CAPACITY);
+                let api_channel = mpsc::channel(CHANNEL_CAPACITY);
+                let network_channel = mpsc::channel(CHANNEL_CAPACITY);
+
+                let mut blockchain = Blockchain::new(
+                    TemporaryDB::new(),
+                    (self.services)(),
+                    service_keys[i].0,
+                    service_keys[i].1.clone(),
+                    ApiSender::new(api_channel.0.clone()),
+                );
+                blockchain
+                    .initialize(genesis.clone())
+                    .expect("Unable to initialize the blockchain");
+
+                let config = Configuration {
+                    listener: ListenerConfig {
+                        address: addresses[i],
+                        consensus_public_key: consensus_keys[i].0,
+                        consensus_secret_key: consensus_keys[i].1.clone(),
+                        connect_list: ConnectList::from_config(connect_list_config.clone()),
+                    },
+                    service: ServiceConfig {
+                        service_public_key: service_keys[i].0,
+                        service_secret_key: service_keys[i].1.clone(),
+                    },
+                    network: NetworkConfiguration::default(),
+                    peer_discovery: Vec::new(),
+                    mempool: MemoryPoolConfig::default(),
+                    pruning: None,
+                    checkpoints: None,
+                    garbage_collection: None,
+                    watchdog: None,
+                    connect_list_watch: None,
+                    catch_up: None,
+                    diagnostics: None,
+                    peer_stats: None,
+                    signing_record: None,
+                    remote_signer: None,
+                    threshold_key: None,
+                };
+                let system_state = SimulatedSystemState {
+                    listen_address: addresses[i],
+                    time: Arc::clone(&time),
+                };
+                let sender = NodeSender {
+                    internal_requests: internal_channel.0.wait(),
+                    network_requests: network_channel.0.wait(),
+                    api_requests: api_channel.0.wait(),
+                };
+                let handler = NodeHandler::new(
+                    blockchain,
+                    &str_addresses[i],
+                    sender,
+                    Box::new(system_state),
+                    config,
+                    SharedNodeState::new(5000),
+                    None,
+                );
+
+                SimulatedNode {
+                    handler,
+                    timers: BinaryHeap::new(),
+                    internal_requests: internal_channel.1,
+                    api_requests: api_channel.1,
+                    network_requests: network_channel.1,
+                }
+            })
+            .collect();
+
+        let mut simulation = Simulation {
+            nodes,
+            validators: consensus_keys
+                .iter()
+                .enumerate()
+                .map(|(i, keys)| (keys.0, i))
+                .collect(),
+            time,
+            latency: self.latency,
+            in_flight: BinaryHeap::new(),
+            sequence: 0,
+            partitions: None,
+            filter: None,
+        };
+        for index in 0..count {
+            simulation.nodes[index].handler.initialize();
+            simulation.process_requests(index);
+        }
+        simulation
+    }
+}
+
+impl fmt::Debug for SimulationBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SimulationBuilder")
+            .field("validators_count", &self.validators_count)
+            .field("consensus", &self.consensus)
+            .field("latency", &self.latency)
+            .finish()
+    }
+}
+
+/// Network of validators running the consensus in a virtual time.
+///
+/// The validators are identified by their identifiers in the genesis configuration.
+pub struct Simulation {
+    nodes: Vec<SimulatedNode>,
+    /// Indexes of the nodes keyed by their consensus keys.
+    validators: HashMap<PublicKey, usize>,
+    time: Arc<Mutex<SystemTime>>,
+    latency: Duration,
+    in_flight: BinaryHeap<Reverse<InFlight>>,
+    sequence: u64,
+    /// Partition of each node, if the network is partitioned.
+    partitions: Option<Vec<Option<usize>>>,
+    filter: Option<MessageFilter>,
+}
+
+impl Simulation {
+    /// Returns the number of the validators.
+    pub fn validators_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the current virtual time.
+    pub fn time(&self) -> SystemTime {
+        *self.time.lock().unwrap()
+    }
+
+    /// Returns the handler of the validator node.
+    pub fn node(&self, validator: ValidatorId) -> &NodeHandler {
+        &self.nodes[usize::from(validator)].handler
+    }
+
+    /// Returns the blockchain of the validator.
+    pub fn blockchain(&self, validator: ValidatorId) -> &Blockchain {
+        &self.node(validator).blockchain
+    }
+
+    /// Returns the height of the latest block committed by the validator.
+    pub fn height(&self, validator: ValidatorId) -> Height {
+        self.blockchain(validator).last_block().height()
+    }
+
+    /// Sets the filter deciding the fate of each message sent from one validator to another.
+    /// The filter is not applied to the messages dropped because of the partitions.
+    pub fn set_filter<F>(&mut self, filter: F)
+    where
+        F: FnMut(ValidatorId, ValidatorId, &Message) -> Delivery + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+    }
+
+    /// Removes the message filter, so that all the messages are delivered.
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+    }
+
+    /// Splits the network into the given groups of validators. The messages between
+    /// the validators of different groups are dropped, and the validators not listed
+    /// in any group are isolated. The messages already in flight are not affected.
+    pub fn partition(&mut self, groups: &[&[ValidatorId]]) {
+        let mut partitions = vec![None; self.nodes.len()];
+        for (group_index, group) in groups.iter().enumerate() {
+            for &validator in group.iter() {
+                partitions[usize::from(validator)] = Some(group_index);
+            }
+        }
+        self.partitions = Some(partitions);
+    }
+
+    /// Removes the partitions of the network.
+    pub fn heal(&mut self) {
+        self.partitions = None;
+    }
+
+    /// Submits the transaction to the validator, as if it is sent through the API.
+    pub fn send_transaction(&mut self, validator: ValidatorId, tx: Signed<RawTransaction>) {
+        self.handle_event(
+            usize::from(validator),
+            ExternalMessage::Transaction(tx).into(),
+        );
+    }
+
+    /// Advances the virtual time by the given duration, delivering the messages
+    /// and firing the timeouts on the way.
+    pub fn run_for(&mut self, duration: Duration) {
+        let deadline = self.time() + duration;
+        while self.step(deadline) {}
+        self.set_time(deadline);
+    }
+
+    /// Advances the virtual time until the condition holds or the given duration passes.
+    /// Returns whether the condition holds. The condition is checked after each delivered
+    /// message or fired timeout.
+    pub fn run_until<F>(&mut self, duration: Duration, mut condition: F) -> bool
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        let deadline = self.time() + duration;
+        while !condition(self) {
+            if !self.step(deadline) {
+                self.set_time(deadline);
+                return condition(self);
+            }
+        }
+        true
+    }
+
+    /// Checks that the validators have not committed different blocks at the same height.
+    pub fn check_safety(&self) -> Result<(), Error> {
+        let mut committed: HashMap<Height, (ValidatorId, Hash)> = HashMap::new();
+        for index in 0..self.nodes.len() {
+            let validator = ValidatorId(index as u16);
+            let snapshot = self.blockchain(validator).snapshot();
+            let schema = Schema::new(&snapshot);
+            for (height, block_hash) in schema.block_hashes_by_height().iter().enumerate() {
+                let height = Height(height as u64);
+                let (other, other_hash) =
+                    *committed.entry(height).or_insert((validator, block_hash));
+                ensure!(
+                    other_hash == block_hash,
+                    "Validators {} and {} have committed different blocks at height {}",
+                    other,
+                    validator,
+                    height
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn set_time(&self, time: SystemTime) {
+        *self.time.lock().unwrap() = time;
+    }
+
+    /// Delivers the earliest message or fires the earliest timeout not later than
+    /// the deadline. Returns `false` if there are no such events.
+    fn step(&mut self, deadline: SystemTime) -> bool {
+        let message_time = self.in_flight.peek().map(|m| (m.0).delivery_time);
+        let timer = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, node)| node.timers.peek().map(|timer| (timer.0, index)))
+            .min();
+
+        match (message_time, timer) {
+            (Some(time), _) if time <= deadline && timer.map_or(true, |t| time <= t.0) => {
+                let message = self.in_flight.pop().unwrap().0;
+                self.set_time(time);
+                let event = NetworkEvent::MessageReceived(message.from, message.message);
+                self.handle_event(message.to, event.into());
+                true
+            }
+            (_, Some((time, index))) if time <= deadline => {
+                let TimeoutRequest(_, timeout) = self.nodes[index].timers.pop().unwrap();
+                self.set_time(time);
+                self.handle_event(index, timeout.into());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_event(&mut self, index: usize, event: Event) {
+        self.nodes[index].handler.handle_event(event);
+        self.process_requests(index);
+    }
+
+    /// Processes the requests of the node until there are none left.
+    fn process_requests(&mut self, index: usize) {
+        loop {
+            let requests = self.nodes[index].poll_requests();
+            if requests.is_empty() {
+                break;
+            }
+
+            for request in requests {
+                let node = &mut self.nodes[index];
+                match request {
+                    Request::Internal(InternalRequest::Timeout(timeout)) => {
+                        node.timers.push(timeout)
+                    }
+                    Request::Internal(InternalRequest::JumpToRound(height, round)) => node
+                        .handler
+                        .handle_event(InternalEvent::JumpToRound(height, round).into()),
+                    Request::Internal(InternalRequest::VerifyMessage(
+                        _,
+                        raw,
+                        network_id,
+                        max_len,
+                    )) => {
+                        let message = Message::from_raw_buffer_with_limit(raw, network_id, max_len)
+                            .expect("Node has requested to verify an invalid message");
+                        node.handler
+                            .handle_event(InternalEvent::MessageVerified(Box::new(message)).into());
+                    }
+                    Request::Internal(InternalRequest::VerifyMessages(
+                        messages,
+                        network_id,
+                        max_len,
+                    )) => {
+                        for raw in messages {
+                            let message =
+                                Message::from_raw_buffer_with_limit(raw, network_id, max_len)
+                                    .expect("Node has requested to verify an invalid message");
+                            node.handler.handle_event(
+                                InternalEvent::MessageVerified(Box::new(message)).into(),
+                            );
+                        }
+                    }
+                    Request::Api(message) => node.handler.handle_event(message.into()),
+                    Request::Network(NetworkRequest::SendMessage(to, message)) => {
+                        self.send(index, &to, message)
+                    }
+                    Request::Internal(InternalRequest::Shutdown)
+                    | Request::Network(NetworkRequest::DisconnectWithPeer(_))
+                    | Request::Network(NetworkRequest::Shutdown) => {}
+                }
+            }
+        }
+    }
+
+    /// Puts the message into the network, unless it is dropped.
+    fn send(&mut self, from: usize, to: &PublicKey, message: SignedMessage) {
+        let to = match self.validators.get(to) {
+            Some(&to) => to,
+            None => return,
+        };
+        if let Some(ref partitions) = self.partitions {
+            if partitions[from].is_none() || partitions[from] != partitions[to] {
+                return;
+            }
+        }
+
+        let raw = message.raw().to_vec();
+        let delivery = match self.filter {
+            Some(ref mut filter) => {
+                let message =
+                    Message::deserialize(message).expect("Node has sent an invalid message");
+                filter(ValidatorId(from as u16), ValidatorId(to as u16), &message)
+            }
+            None => Delivery::Deliver,
+        };
+        let delay = match delivery {
+            Delivery::Deliver => self.latency,
+            Delivery::Delay(delay) => self.latency + delay,
+            Delivery::Drop => return,
+        };
+
+        self.sequence += 1;
+        self.in_flight.push(Reverse(InFlight {
+            delivery_time: self.time() + delay,
+            sequence: self.sequence,
+            from: self.nodes[from].handler.system_state.listen_address(),
+            to,
+            message: raw,
+        }));
+    }
+}
+
+impl fmt::Debug for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let heights = (0..self.nodes.len())
+            .map(|index| self.height(ValidatorId(index as u16)))
+            .collect::<Vec<_>>();
+        f.debug_struct("Simulation")
+            .field("time", &self.time())
+            .field("heights", &heights)
+            .field("in_flight", &self.in_flight.len())
+            .field("partitions", &self.partitions)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::Consensus;
+
+    const TIMEOUT: Duration = Duration::from_secs(120);
+
+    fn all_validators(simulation: &Simulation) -> Vec<ValidatorId> {
+        (0..simulation.validators_count())
+            .map(|i| ValidatorId(i as u16))
+            .collect()
+    }
+
+    fn all_reach(height: Height) -> impl Fn(&Simulation) -> bool {
+        move |simulation| {
+            all_validators(simulation)
+                .into_iter()
+                .all(|validator| simulation.height(validator) >= height)
+        }
+    }
+
+    #[test]
+    fn test_commit_blocks() {
+        let mut simulation = SimulationBuilder::new(4).build();
+        assert!(simulation.run_until(TIMEOUT, all_reach(Height(5))));
+        simulation.check_safety().unwrap();
+    }
+
+    #[test]
+    fn test_no_progress_without_quorum() {
+        let mut simulation = SimulationBuilder::new(4).build();
+        let (first, second) = (
+            [ValidatorId(0), ValidatorId(1)],
+            [ValidatorId(2), ValidatorId(3)],
+        );
+        simulation.partition(&[&first, &second]);
+        simulation.run_for(TIMEOUT);
+        for validator in all_validators(&simulation) {
+            assert_eq!(simulation.height(validator), Height(0));
+        }
+
+        simulation.heal();
+        assert!(simulation.run_until(TIMEOUT, all_reach(Height(2))));
+        simulation.check_safety().unwrap();
+    }
+
+    #[test]
+    fn test_isolated_validator_catches_up() {
+        let mut simulation = SimulationBuilder::new(4).build();
+        simulation.partition(&[&[ValidatorId(0), ValidatorId(1), ValidatorId(2)]]);
+        assert!(simulation.run_until(TIMEOUT, |simulation| {
+            simulation.height(ValidatorId(0)) >= Height(3)
+        }));
+        assert_eq!(simulation.height(ValidatorId(3)), Height(0));
+
+        simulation.heal();
+        let height = simulation.height(ValidatorId(0));
+        assert!(simulation.run_until(TIMEOUT, all_reach(height)));
+        simulation.check_safety().unwrap();
+    }
+
+    #[test]
+    fn test_lossy_network() {
+        let mut simulation = SimulationBuilder::new(4).build();
+        let mut sent = 0_u64;
+        simulation.set_filter(move |_, _, message| {
+            if let Message::Consensus(Consensus::Precommit(_)) = message {
+                sent += 1;
+                if sent % 3 == 0 {
+                    return Delivery::Drop;
+                }
+                return Delivery::Delay(Duration::from_millis(sent % 200));
+            }
+            Delivery::Deliver
+        });
+        assert!(simulation.run_until(TIMEOUT, all_reach(Height(3))));
+        simulation.check_safety().unwrap();
+    }
+}
@@ -20,14 +20,18 @@ extern crate pretty_assertions;
 extern crate serde_derive;
 
 use exonum::{
-    api::backends::actix::AllowOrigin,
+    api::backends::actix::{AllowOrigin, CorsRule},
     crypto::{PublicKey, PUBLIC_KEY_LENGTH},
     helpers::{
         config::{ConfigFile, ConfigManager},
         fabric::NodeBuilder,
     },
-    node::{ConnectInfo, ConnectListConfig, NodeConfig},
+    node::{
+        ConnectInfo, ConnectListConfig, DatabaseConfig, IntegrityCheckConfig,
+        IntegrityFailureAction, NodeConfig,
+    },
 };
+use exonum_merkledb::{CompactionStyle, CompressionAlgorithm};
 
 #[cfg(unix)]
 use std::os::unix::fs::OpenOptionsExt;
@@ -269,6 +273,49 @@ fn test_allow_origin_toml() {
     );
 }
 
+#[test]
+fn test_cors_rules_toml() {
+    #[derive(Serialize, Deserialize)]
+    struct Config {
+        cors_rules: Vec<CorsRule>,
+    }
+
+    let config_toml = r#"
+        [[cors_rules]]
+        origins = "https://wallet.example.com"
+        allowed_methods = ["GET", "POST"]
+        allowed_headers = ["Authorization"]
+        allow_credentials = true
+        max_age = 3600
+
+        [[cors_rules]]
+        origins = "*"
+        allowed_methods = ["GET"]
+    "#;
+    let config: Config = ::toml::from_str(config_toml).unwrap();
+    assert_eq!(
+        config.cors_rules,
+        vec![
+            CorsRule {
+                origins: AllowOrigin::Whitelist(vec!["https://wallet.example.com".to_owned()]),
+                allowed_methods: Some(vec!["GET".to_owned(), "POST".to_owned()]),
+                allowed_headers: Some(vec!["Authorization".to_owned()]),
+                exposed_headers: Vec::new(),
+                allow_credentials: true,
+                max_age: Some(3600),
+            },
+            CorsRule {
+                allowed_methods: Some(vec!["GET".to_owned()]),
+                ..CorsRule::from(AllowOrigin::Any)
+            },
+        ]
+    );
+
+    let serialized = ::toml::to_string(&config).unwrap();
+    let deserialized: Config = ::toml::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.cors_rules, config.cors_rules);
+}
+
 #[test]
 fn test_generate_template() {
     let env = ConfigSpec::new_without_pass();
@@ -486,6 +533,7 @@ fn test_update_config() {
     // Test config update.
     let peer = ConnectInfo {
         address: "0.0.0.1:8080".to_owned(),
+        fallback_addresses: Vec::new(),
         public_key: PublicKey::new([1; PUBLIC_KEY_LENGTH]),
     };
 
@@ -498,3 +546,100 @@ fn test_update_config() {
     let new_connect_list = config.connect_list;
     assert_eq!(new_connect_list.peers, connect_list.peers);
 }
+
+#[test]
+fn test_database_config_toml() {
+    #[derive(Serialize, Deserialize)]
+    struct Config {
+        database: DatabaseConfig,
+    }
+
+    let config: Config = ::toml::from_str("[database]\ncreate_if_missing = false").unwrap();
+    assert_eq!(config.database.db_type, "rocksdb");
+    assert!(!config.database.options.create_if_missing);
+    assert!(!config.database.encrypted);
+
+    let config_toml = r#"
+        [database]
+        type = "temporary"
+        max_open_files = 256
+        create_if_missing = true
+        encrypted = true
+    "#;
+    let config: Config = ::toml::from_str(config_toml).unwrap();
+    assert_eq!(config.database.db_type, "temporary");
+    assert_eq!(config.database.options.max_open_files, Some(256));
+    assert!(config.database.encrypted);
+    assert!(config.database.options.column_families.is_empty());
+
+    let serialized = ::toml::to_string(&config).unwrap();
+    let deserialized: Config = ::toml::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.database, config.database);
+
+    let config_toml = r#"
+        [database]
+        create_if_missing = true
+        secondary_indexes = ["wallets.history"]
+
+        [database.compression]
+        algorithm = "Zstd"
+
+        [[database.column_families]]
+        name = "blocks"
+        index_prefixes = ["core.blocks", "core.transactions"]
+        compaction_style = "Universal"
+
+        [[database.column_families]]
+        name = "caches"
+        index_prefixes = ["core.consensus_messages_cache"]
+        block_cache_size = 8388608
+
+        [[database.caches]]
+        index_prefixes = ["core.configs", "core.peers_cache"]
+        capacity = 128
+    "#;
+    let config: Config = ::toml::from_str(config_toml).unwrap();
+    let column_families = &config.database.options.column_families;
+    assert_eq!(column_families.len(), 2);
+    assert_eq!(
+        column_families[0].compaction_style,
+        CompactionStyle::Universal
+    );
+    assert_eq!(column_families[1].block_cache_size, Some(8_388_608));
+    let compression = config.database.compression.unwrap();
+    assert_eq!(compression.algorithm, CompressionAlgorithm::Zstd);
+    assert_eq!(compression.threshold, 256);
+    assert_eq!(config.database.secondary_indexes, vec!["wallets.history"]);
+    assert_eq!(config.database.caches.len(), 1);
+    assert_eq!(config.database.caches[0].capacity, 128);
+
+    let serialized = ::toml::to_string(&config).unwrap();
+    let deserialized: Config = ::toml::from_str(&serialized).unwrap();
+    assert_eq!(deserialized.database, config.database);
+}
+
+#[test]
+fn test_integrity_check_config_toml() {
+    #[derive(Serialize, Deserialize)]
+    struct Config {
+        integrity_check: IntegrityCheckConfig,
+    }
+
+    let config: Config = ::toml::from_str("[integrity_check]\nblocks = 100").unwrap();
+    assert_eq!(config.integrity_check.blocks, 100);
+    assert_eq!(
+        config.integrity_check.on_failure,
+        IntegrityFailureAction::Refuse
+    );
+
+    let config_toml = r#"
+        [integrity_check]
+        blocks = 10
+        on_failure = "read-only"
+    "#;
+    let config: Config = ::toml::from_str(config_toml).unwrap();
+    assert_eq!(
+        config.integrity_check.on_failure,
+        IntegrityFailureAction::ReadOnly
+    );
+}
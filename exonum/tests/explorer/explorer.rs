@@ -15,19 +15,26 @@
 //! Tests for the blockchain explorer functionality.
 
 use exonum::{
-    blockchain::{Schema, TransactionErrorType, TransactionSet, TxLocation},
+    api::backends::actix::{OCTET_STREAM_CONTENT_TYPE, PROTOBUF_CONTENT_TYPE},
+    blockchain::{Block, Schema, TransactionErrorType, TransactionSet, TxLocation},
     crypto::{self, Hash},
     explorer::*,
     helpers::Height,
-    messages::{self, Message, RawTransaction, Signed},
-    node::ExternalMessage,
+    messages::{self, Message, RawTransaction, Signed, Status},
+    node::{ExternalMessage, PruningConfig},
+    proto::{self, ProtobufConvert},
 };
-use reqwest::Client;
+use reqwest::{
+    header::{ACCEPT, CONTENT_TYPE},
+    Client, Response, StatusCode,
+};
+
+use std::{io::Read, thread, time::Duration};
 
 use crate::blockchain::{
     create_block, create_blockchain, CreateWallet, ExplorerTransactions, Transfer, SERVICE_ID,
 };
-use crate::node::run_node_with_message_len;
+use crate::node::{run_node, run_node_with_message_len};
 
 #[test]
 #[allow(clippy::cognitive_complexity)]
@@ -442,6 +449,37 @@ fn test_block_with_transactions_index_overflow() {
     assert!(block[6].status().is_ok());
 }
 
+#[test]
+fn test_explorer_pruned_blocks() {
+    let mut blockchain = create_blockchain();
+    let mut tx_gen = tx_generator();
+    let pruned_tx = tx_gen.next().unwrap();
+    create_block(&mut blockchain, vec![pruned_tx.clone()]);
+    let kept_tx = tx_gen.next().unwrap();
+    create_block(&mut blockchain, vec![kept_tx.clone()]);
+    create_block(&mut blockchain, vec![]);
+
+    blockchain.prune(&PruningConfig { keep_blocks: 2 }).unwrap();
+
+    let explorer = BlockchainExplorer::new(&blockchain);
+    assert_eq!(explorer.first_unpruned_height(), Height(2));
+    let block = explorer.block(Height(1)).unwrap();
+    assert!(block.is_pruned());
+    assert_eq!(block.header().tx_count(), 1);
+    assert!(block.precommits().is_empty());
+    assert!(block.transaction(0).is_none());
+    assert!(explorer.block_with_txs(Height(1)).is_none());
+    assert!(explorer.transaction(&pruned_tx.hash()).is_none());
+    assert!(explorer.is_transaction_pruned(&pruned_tx.hash()));
+
+    let block = explorer.block(Height(2)).unwrap();
+    assert!(!block.is_pruned());
+    assert_eq!(block.precommits().len(), 1);
+    assert!(explorer.transaction(&kept_tx.hash()).is_some());
+    assert!(!explorer.is_transaction_pruned(&kept_tx.hash()));
+    assert_eq!(explorer.block_with_txs(Height(2)).unwrap().len(), 1);
+}
+
 #[test]
 fn test_committed_transaction_roundtrip() {
     let mut blockchain = create_blockchain();
@@ -553,3 +591,143 @@ fn test_sending_message_size() {
         .unwrap();
     node_handler.node_thread.join().unwrap();
 }
+
+fn get_with_retries(client: &Client, url: &str, accept: Option<&str>) -> Response {
+    for _ in 0..50 {
+        let mut request = client.get(url);
+        if let Some(accept) = accept {
+            request = request.header(ACCEPT, accept);
+        }
+        if let Ok(response) = request.send() {
+            return response;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("Node API is not available");
+}
+
+#[test]
+fn test_protobuf_content_negotiation() {
+    let node_handler = run_node(6343, 8092);
+    let client = Client::new();
+
+    // Blocks are returned in JSON unless the protobuf encoding is requested.
+    let block_url = "http://localhost:8092/api/explorer/v1/block?height=0";
+    let json: serde_json::Value = get_with_retries(&client, block_url, None).json().unwrap();
+    let mut response = get_with_retries(
+        &client,
+        block_url,
+        Some("application/json;q=0.5, application/x-protobuf"),
+    );
+    assert_eq!(response.headers()[CONTENT_TYPE], PROTOBUF_CONTENT_TYPE);
+    let mut body = Vec::new();
+    response.read_to_end(&mut body).unwrap();
+    let mut block_response: proto::BlockResponse = protobuf::parse_from_bytes(&body).unwrap();
+    assert_eq!(
+        Block::from_pb(block_response.take_block()).unwrap(),
+        serde_json::from_value::<Block>(json.clone()).unwrap()
+    );
+    assert_eq!(
+        block_response.get_precommits().len(),
+        json["precommits"].as_array().unwrap().len()
+    );
+
+    // Transactions are encoded as signed messages.
+    let (pk, sk) = crypto::gen_keypair();
+    let tx = Message::sign_transaction(CreateWallet::new(&pk, "Alice"), SERVICE_ID, pk, &sk);
+    client
+        .post("http://localhost:8092/api/explorer/v1/transactions")
+        .json(&json!({ "tx_body": tx }))
+        .send()
+        .unwrap();
+    let tx_url = format!(
+        "http://localhost:8092/api/explorer/v1/transactions?hash={}",
+        tx.hash().to_hex()
+    );
+    let mut response = get_with_retries(&client, &tx_url, Some(PROTOBUF_CONTENT_TYPE));
+    assert_eq!(response.headers()[CONTENT_TYPE], PROTOBUF_CONTENT_TYPE);
+    let mut body = Vec::new();
+    response.read_to_end(&mut body).unwrap();
+    let txs_response: proto::TransactionsResponse = protobuf::parse_from_bytes(&body).unwrap();
+    assert_eq!(
+        txs_response.get_transactions(),
+        &[tx.signed_message().raw().to_vec()]
+    );
+
+    node_handler
+        .api_tx
+        .send_external_message(ExternalMessage::Shutdown)
+        .unwrap();
+    node_handler.node_thread.join().unwrap();
+}
+
+#[test]
+fn test_signed_transaction_submission() {
+    let node_handler = run_node(6349, 8098);
+    let client = Client::new();
+    let url = "http://localhost:8098/api/explorer/v1/transactions/signed";
+    get_with_retries(
+        &client,
+        "http://localhost:8098/api/explorer/v1/block?height=0",
+        None,
+    );
+
+    let (pk, sk) = crypto::gen_keypair();
+    let tx = Message::sign_transaction(CreateWallet::new(&pk, "Alice"), SERVICE_ID, pk, &sk);
+    let expected = json!({ "tx_hash": tx.hash(), "author": pk });
+
+    // The transaction may be submitted both as a hex string and as raw bytes.
+    let mut response = client
+        .post(url)
+        .body(messages::to_hex_string(&tx))
+        .send()
+        .unwrap();
+    assert_eq!(response.json::<serde_json::Value>().unwrap(), expected);
+    let mut response = client
+        .post(url)
+        .header(CONTENT_TYPE, OCTET_STREAM_CONTENT_TYPE)
+        .body(tx.signed_message().raw().to_vec())
+        .send()
+        .unwrap();
+    assert_eq!(response.json::<serde_json::Value>().unwrap(), expected);
+
+    let error_kind = |body: Vec<u8>, content_type: &str| {
+        let mut response = client
+            .post(url)
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        response.json::<serde_json::Value>().unwrap()["kind"].clone()
+    };
+
+    assert_eq!(
+        error_kind(b"not a hex".to_vec(), "text/plain"),
+        json!("invalid_encoding")
+    );
+    assert_eq!(
+        error_kind(vec![0; 10], OCTET_STREAM_CONTENT_TYPE),
+        json!("malformed_envelope")
+    );
+    let mut corrupted = tx.signed_message().raw().to_vec();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        error_kind(corrupted, OCTET_STREAM_CONTENT_TYPE),
+        json!("invalid_signature")
+    );
+    let status = Message::concrete(Status::new(Height(1), &Hash::zero(), 0), pk, &sk);
+    assert_eq!(
+        error_kind(
+            status.signed_message().raw().to_vec(),
+            OCTET_STREAM_CONTENT_TYPE
+        ),
+        json!("not_transaction")
+    );
+
+    node_handler
+        .api_tx
+        .send_external_message(ExternalMessage::Shutdown)
+        .unwrap();
+    node_handler.node_thread.join().unwrap();
+}
@@ -27,4 +27,5 @@ extern crate pretty_assertions;
 mod blockchain;
 mod explorer;
 mod node;
+mod private_api;
 mod websocket;
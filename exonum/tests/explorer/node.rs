@@ -14,7 +14,7 @@
 
 use exonum::{
     helpers::generate_testnet_config,
-    node::{ApiSender, Node, NodeConfig},
+    node::{ApiSender, Node, NodeApiConfig, NodeConfig},
 };
 use exonum_merkledb::TemporaryDB;
 use std::{
@@ -54,6 +54,26 @@ pub(crate) fn run_node_with_message_len(
     run_node_with_cfg(node_cfg)
 }
 
+pub(crate) fn run_node_with_public_mempool_events(
+    listen_port: u16,
+    pub_api_port: u16,
+) -> RunHandle {
+    let mut node_cfg = generate_testnet_config(1, listen_port).remove(0);
+    node_cfg.api.public_mempool_events = true;
+    node_cfg.api.public_api_address = Some(
+        format!("127.0.0.1:{}", pub_api_port)
+            .parse::<SocketAddr>()
+            .unwrap(),
+    );
+    run_node_with_cfg(node_cfg)
+}
+
+pub(crate) fn run_node_with_api_config(listen_port: u16, api_config: NodeApiConfig) -> RunHandle {
+    let mut node_cfg = generate_testnet_config(1, listen_port).remove(0);
+    node_cfg.api = api_config;
+    run_node_with_cfg(node_cfg)
+}
+
 fn run_node_with_cfg(node_cfg: NodeConfig) -> RunHandle {
     let service = Box::new(MyService);
     let node = Node::new(TemporaryDB::new(), vec![service], node_cfg, None);
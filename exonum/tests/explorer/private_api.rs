@@ -0,0 +1,196 @@
+// Copyright 2019 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the access controls and management of the node API.
+
+use exonum::{
+    api::{
+        backends::actix::{AllowOrigin, CorsRule},
+        node::private::ApiRestartQuery,
+    },
+    node::{ExternalMessage, NodeApiConfig},
+};
+use reqwest::{
+    header::{ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_ORIGIN, ORIGIN},
+    Client, StatusCode,
+};
+
+use std::{thread, time::Duration};
+
+use crate::node::{run_node_with_api_config, RunHandle};
+
+fn shutdown(node_handler: RunHandle) {
+    node_handler
+        .api_tx
+        .send_external_message(ExternalMessage::Shutdown)
+        .unwrap();
+    node_handler.node_thread.join().unwrap();
+}
+
+fn private_api_status(url: &str) -> StatusCode {
+    let client = Client::new();
+    for _ in 0..50 {
+        if let Ok(response) = client.get(url).send() {
+            return response.status();
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("Private API is not available");
+}
+
+#[test]
+fn test_private_api_allowlist_rejected() {
+    let node_handler = run_node_with_api_config(
+        6344,
+        NodeApiConfig {
+            private_api_address: Some("127.0.0.1:8093".parse().unwrap()),
+            private_api_allowlist: Some(vec!["10.0.0.1".parse().unwrap()]),
+            ..Default::default()
+        },
+    );
+    let status = private_api_status("http://127.0.0.1:8093/api/system/v1/network");
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    shutdown(node_handler);
+}
+
+#[test]
+fn test_private_api_allowlist_allowed() {
+    let node_handler = run_node_with_api_config(
+        6345,
+        NodeApiConfig {
+            private_api_address: Some("127.0.0.1:8094".parse().unwrap()),
+            private_api_allowlist: Some(vec!["127.0.0.1".parse().unwrap()]),
+            ..Default::default()
+        },
+    );
+    let status = private_api_status("http://127.0.0.1:8094/api/system/v1/network");
+    assert_eq!(status, StatusCode::OK);
+    shutdown(node_handler);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_private_api_unix_socket() {
+    use std::{
+        env,
+        io::{Read, Write},
+        os::unix::net::UnixStream,
+    };
+
+    let socket_path = env::temp_dir().join("exonum-test-private-api.sock");
+    let node_handler = run_node_with_api_config(
+        6346,
+        NodeApiConfig {
+            private_api_socket: Some(socket_path.clone()),
+            ..Default::default()
+        },
+    );
+
+    let mut stream = (0..50)
+        .find_map(|_| {
+            UnixStream::connect(&socket_path)
+                .map_err(|_| thread::sleep(Duration::from_millis(100)))
+                .ok()
+        })
+        .expect("Private API is not available");
+    let request = "GET /api/system/v1/network HTTP/1.1\r\n\
+                   Host: localhost\r\n\
+                   Connection: close\r\n\r\n";
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    shutdown(node_handler);
+}
+
+#[test]
+fn test_private_api_restart() {
+    let api_config = NodeApiConfig {
+        private_api_address: Some("127.0.0.1:8095".parse().unwrap()),
+        ..Default::default()
+    };
+    let node_handler = run_node_with_api_config(6347, api_config.clone());
+    let status = private_api_status("http://127.0.0.1:8095/api/system/v1/network");
+    assert_eq!(status, StatusCode::OK);
+
+    // Restart the API servers with the public API enabled.
+    let query = ApiRestartQuery {
+        config: Some(NodeApiConfig {
+            public_api_address: Some("127.0.0.1:8096".parse().unwrap()),
+            ..api_config
+        }),
+    };
+    let response = Client::new()
+        .post("http://127.0.0.1:8095/api/system/v1/api/restart")
+        .json(&query)
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let status = private_api_status("http://127.0.0.1:8096/api/system/v1/health/live");
+    assert_eq!(status, StatusCode::OK);
+    let status = private_api_status("http://127.0.0.1:8095/api/system/v1/network");
+    assert_eq!(status, StatusCode::OK);
+
+    shutdown(node_handler);
+}
+
+#[test]
+fn test_public_api_cors_rules() {
+    let wallet_rule = CorsRule {
+        allow_credentials: true,
+        ..CorsRule::from(AllowOrigin::Whitelist(vec![
+            "https://wallet.example.com".to_owned()
+        ]))
+    };
+    let node_handler = run_node_with_api_config(
+        6348,
+        NodeApiConfig {
+            public_api_address: Some("127.0.0.1:8097".parse().unwrap()),
+            public_cors_rules: Some(vec![wallet_rule]),
+            ..Default::default()
+        },
+    );
+    let url = "http://127.0.0.1:8097/api/system/v1/health/live";
+    assert_eq!(private_api_status(url), StatusCode::OK);
+
+    let client = Client::new();
+    let response = client
+        .get(url)
+        .header(ORIGIN, "https://wallet.example.com")
+        .send()
+        .unwrap();
+    let headers = response.headers();
+    assert_eq!(
+        headers.get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://wallet.example.com"
+    );
+    assert_eq!(
+        headers.get(ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(),
+        "true"
+    );
+
+    let response = client
+        .get(url)
+        .header(ORIGIN, "https://evil.example.com")
+        .send()
+        .unwrap();
+    assert!(response
+        .headers()
+        .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+        .is_none());
+
+    shutdown(node_handler);
+}
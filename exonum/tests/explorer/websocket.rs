@@ -26,7 +26,7 @@ use std::{
 };
 
 use crate::blockchain::{CreateWallet, Transfer, SERVICE_ID};
-use crate::node::{run_node, run_node_with_message_len};
+use crate::node::{run_node, run_node_with_message_len, run_node_with_public_mempool_events};
 
 fn create_ws_client(addr: &str) -> WebSocketResult<Client<TcpStream>> {
     let mut last_err = None;
@@ -330,6 +330,57 @@ fn test_transactions_subscribe_with_bad_filter() {
     node_handler.node_thread.join().unwrap();
 }
 
+#[test]
+fn test_mempool_subscribe() {
+    let node_handler = run_node_with_public_mempool_events(6341, 8090);
+
+    let mut client = create_ws_client("ws://localhost:8090/api/explorer/v1/mempool/subscribe")
+        .expect("Cannot connect to node");
+    client
+        .stream_ref()
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+
+    // Send transaction.
+    let (pk, sk) = gen_keypair();
+    let tx = Message::sign_transaction(CreateWallet::new(&pk, "Alice"), SERVICE_ID, pk, &sk);
+    let tx_hash = tx.hash();
+    let tx_json = json!({ "tx_body": tx });
+    let http_client = reqwest::Client::new();
+    let _res = http_client
+        .post("http://localhost:8090/api/explorer/v1/transactions")
+        .json(&tx_json)
+        .send()
+        .unwrap();
+
+    // Transaction should be reported as added to the pool and then as committed.
+    for expected_event in &[MempoolEvent::Added, MempoolEvent::Committed] {
+        let resp_text = recv_text_msg(&mut client).unwrap();
+        let notification = serde_json::from_str::<Notification>(&resp_text).unwrap();
+        match notification {
+            Notification::Mempool(MempoolNotification {
+                event,
+                tx_hash: hash,
+            }) => {
+                assert_eq!(event, *expected_event);
+                assert_eq!(hash, tx_hash);
+            }
+            other => panic!(
+                "Incorrect notification type (expected Mempool): {:?}",
+                other
+            ),
+        }
+    }
+
+    // Shutdown node.
+    client.shutdown().unwrap();
+    node_handler
+        .api_tx
+        .send_external_message(ExternalMessage::Shutdown)
+        .unwrap();
+    node_handler.node_thread.join().unwrap();
+}
+
 #[test]
 fn test_subscribe() {
     let node_handler = run_node(6336, 8085);
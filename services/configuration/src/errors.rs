@@ -20,7 +20,7 @@ use serde_json::error::Error as JsonError;
 
 use exonum::{
     blockchain::{ExecutionError, StoredConfiguration},
-    crypto::Hash,
+    crypto::{Hash, HashAlgorithm},
     helpers::Height,
 };
 
@@ -61,6 +61,16 @@ pub enum ErrorCode {
     /// Specific for `Propose`.
     InvalidMajorityCount = 34,
 
+    /// The configuration changes the hash function of the blockchain.
+    ///
+    /// Specific for `Propose`.
+    HashAlgorithmChanged = 35,
+
+    /// The configuration disables domain separation of the signatures.
+    ///
+    /// Specific for `Propose`.
+    DomainSeparationDisabled = 36,
+
     /// The transaction references an unknown configuration.
     ///
     /// Specific for `Vote`.
@@ -105,6 +115,12 @@ pub(crate) enum Error {
         proposed: usize,
     },
 
+    #[fail(display = "Cannot change hash algorithm {} of the blockchain", _0)]
+    HashAlgorithmChanged(HashAlgorithm),
+
+    #[fail(display = "Cannot disable domain separation of the signatures")]
+    DomainSeparationDisabled,
+
     #[fail(display = "Does not reference known config with hash {:?}", _0)]
     UnknownConfigRef(Hash),
 
@@ -124,6 +140,8 @@ impl Error {
             AlreadyProposed(..) => ErrorCode::AlreadyProposed,
             InvalidConfig(..) => ErrorCode::InvalidConfig,
             InvalidMajorityCount { .. } => ErrorCode::InvalidMajorityCount,
+            HashAlgorithmChanged(..) => ErrorCode::HashAlgorithmChanged,
+            DomainSeparationDisabled => ErrorCode::DomainSeparationDisabled,
             UnknownConfigRef(..) => ErrorCode::UnknownConfigRef,
             AlreadyVoted => ErrorCode::AlreadyVoted,
         }
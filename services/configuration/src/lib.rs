@@ -147,6 +147,11 @@ impl blockchain::Service for Service {
         to_value(self.config.clone()).unwrap()
     }
 
+    fn foreign_namespaces(&self) -> Vec<&str> {
+        // Accepted configurations are committed into the core schema.
+        vec!["core"]
+    }
+
     fn wire_api(&self, builder: &mut ServiceApiBuilder) {
         api::PublicApi::wire(builder);
         api::PrivateApi::wire(builder);
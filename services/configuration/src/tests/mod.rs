@@ -15,7 +15,7 @@ use exonum_merkledb::BinaryValue;
 
 use exonum::{
     blockchain::{Schema, StoredConfiguration},
-    crypto::{hash, CryptoHash, Hash, HASH_SIZE},
+    crypto::{hash, CryptoHash, Hash, HashAlgorithm, HASH_SIZE},
     helpers::{Height, ValidatorId},
     messages::{RawTransaction, Signed},
 };
@@ -332,6 +332,29 @@ fn test_discard_proposes_with_too_small_majority_count() {
     assert!(testkit.find_propose(new_cfg.hash()).is_none());
 }
 
+#[test]
+fn test_discard_proposes_changing_hash_algorithm() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_service(ConfigurationService {
+            config: ConfigurationServiceConfig::default(),
+        })
+        .create();
+
+    let new_cfg = {
+        let mut cfg = testkit.configuration_change_proposal();
+        cfg.set_service_config("dummy", "First cfg");
+        cfg.set_actual_from(Height(5));
+        let mut stored = cfg.stored_configuration().clone();
+        stored.hash_algorithm = HashAlgorithm::Blake2b;
+        stored
+    };
+
+    let propose_tx = new_tx_config_propose(&testkit.network().validators()[1], new_cfg.clone());
+    testkit.create_block_with_transactions(txvec![propose_tx]);
+    assert!(testkit.find_propose(new_cfg.hash()).is_none());
+}
+
 #[test]
 fn test_discard_propose_for_same_cfg() {
     let mut testkit: TestKit = TestKit::configuration_default();
@@ -232,6 +232,14 @@ impl Propose {
             return Err(ActivationInPast(current_height));
         }
 
+        if candidate.hash_algorithm != actual_config.hash_algorithm {
+            return Err(HashAlgorithmChanged(actual_config.hash_algorithm));
+        }
+
+        if actual_config.domain_separated_signatures && !candidate.domain_separated_signatures {
+            return Err(DomainSeparationDisabled);
+        }
+
         let config: ConfigurationServiceConfig = get_service_config(candidate);
 
         if let Some(proposed_majority_count) = config.majority_count.map(|count| count as usize) {
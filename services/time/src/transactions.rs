@@ -110,7 +110,9 @@ impl TxTime {
     }
 
     fn update_consolidated_time(fork: &Fork) {
-        let keys = Schema::new(fork).actual_configuration().validator_keys;
+        let keys = Schema::new(fork.as_ref())
+            .actual_configuration()
+            .validator_keys;
         let schema = TimeSchema::new(fork);
 
         // Find all known times for the validators.
@@ -293,6 +293,8 @@ where
             StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED => Err(api::Error::Unauthorized),
             StatusCode::BAD_REQUEST => Err(api::Error::BadRequest(error(response))),
             StatusCode::NOT_FOUND => Err(api::Error::NotFound(error(response))),
+            StatusCode::GONE => Err(api::Error::Pruned(error(response))),
+            StatusCode::CONFLICT => Err(api::Error::Conflict(error(response))),
             s if s.is_server_error() => Err(api::Error::InternalError(format_err!(
                 "{}",
                 error(response)
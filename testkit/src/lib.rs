@@ -181,12 +181,18 @@ use exonum::{
         backends::actix::{ApiRuntimeConfig, SystemRuntimeConfig},
         ApiAccess,
     },
-    blockchain::{Blockchain, GenesisConfig, Schema as CoreSchema, Service, StoredConfiguration},
+    blockchain::{
+        Blockchain, GenesisConfig, Schema as CoreSchema, Service, StorageQuotaConfig,
+        StoredConfiguration,
+    },
     crypto::{self, Hash},
     explorer::{BlockWithTransactions, BlockchainExplorer},
     helpers::{Height, ValidatorId},
     messages::{RawTransaction, Signed},
-    node::{ApiSender, ExternalMessage, State as NodeState},
+    node::{
+        ApiSender, ExternalMessage, ParallelExecutionConfig, RemoveTransactionError,
+        State as NodeState, StateHistoryConfig,
+    },
 };
 
 use crate::checkpoint_db::{CheckpointDb, CheckpointDbHandler};
@@ -288,6 +294,12 @@ pub struct TestKitBuilder {
     validator_count: Option<u16>,
     services: Vec<Box<dyn Service>>,
     logger: bool,
+    track_write_conflicts: bool,
+    enforce_storage_namespaces: bool,
+    storage_quotas: BTreeMap<String, StorageQuotaConfig>,
+    state_history: Option<StateHistoryConfig>,
+    parallel_execution: Option<ParallelExecutionConfig>,
+    domain_separated_signatures: bool,
 }
 
 impl fmt::Debug for TestKitBuilder {
@@ -310,6 +322,18 @@ impl fmt::Debug for TestKitBuilder {
                     .collect::<Vec<_>>(),
             )
             .field("logger", &self.logger)
+            .field("track_write_conflicts", &self.track_write_conflicts)
+            .field(
+                "enforce_storage_namespaces",
+                &self.enforce_storage_namespaces,
+            )
+            .field("storage_quotas", &self.storage_quotas)
+            .field("state_history", &self.state_history)
+            .field("parallel_execution", &self.parallel_execution)
+            .field(
+                "domain_separated_signatures",
+                &self.domain_separated_signatures,
+            )
             .finish()
     }
 }
@@ -322,6 +346,12 @@ impl TestKitBuilder {
             our_validator_id: Some(ValidatorId(0)),
             services: Vec::new(),
             logger: false,
+            track_write_conflicts: false,
+            enforce_storage_namespaces: false,
+            storage_quotas: BTreeMap::new(),
+            state_history: None,
+            parallel_execution: None,
+            domain_separated_signatures: false,
         }
     }
 
@@ -332,6 +362,12 @@ impl TestKitBuilder {
             our_validator_id: None,
             services: Vec::new(),
             logger: false,
+            track_write_conflicts: false,
+            enforce_storage_namespaces: false,
+            storage_quotas: BTreeMap::new(),
+            state_history: None,
+            parallel_execution: None,
+            domain_separated_signatures: false,
         }
     }
 
@@ -360,6 +396,56 @@ impl TestKitBuilder {
         self
     }
 
+    /// Enables tracking of write conflicts. With tracking enabled, the keys changed
+    /// by more than one transaction or `before_commit` handler within a block are logged
+    /// as warnings; use it together with [`with_logger`](#method.with_logger).
+    pub fn with_write_conflicts_tracking(mut self) -> Self {
+        self.track_write_conflicts = true;
+        self
+    }
+
+    /// Enables the enforcement of storage namespaces. With enforcement enabled, transactions
+    /// and `before_commit` handlers of a service fail on accessing the indexes outside
+    /// of the service namespace and the namespaces declared by `Service::foreign_namespaces`.
+    pub fn with_storage_namespaces_enforcement(mut self) -> Self {
+        self.enforce_storage_namespaces = true;
+        self
+    }
+
+    /// Sets the storage quota of the namespace in the genesis consensus configuration.
+    /// Transactions growing the namespace beyond the hard quota fail with
+    /// the `QuotaExceeded` error.
+    pub fn with_storage_quota<S: Into<String>>(
+        mut self,
+        namespace: S,
+        quota: StorageQuotaConfig,
+    ) -> Self {
+        self.storage_quotas.insert(namespace.into(), quota);
+        self
+    }
+
+    /// Keeps the state at the given number of the latest heights preceding the current one,
+    /// so that it can be read with `Blockchain::snapshot_at`.
+    pub fn with_state_history(mut self, keep_heights: u64) -> Self {
+        self.state_history = Some(StateHistoryConfig { keep_heights });
+        self
+    }
+
+    /// Executes the transactions of the services with disjoint storage namespaces
+    /// in parallel, using at most the given number of threads. Parallel execution is
+    /// enabled with the `parallel_execution` field of the genesis consensus configuration.
+    pub fn with_parallel_execution(mut self, threads: usize) -> Self {
+        self.parallel_execution = Some(ParallelExecutionConfig { threads });
+        self
+    }
+
+    /// Enables domain separation of the signatures in the genesis configuration.
+    /// The transactions must then be signed for the network, see `Blockchain::network_id`.
+    pub fn with_domain_separated_signatures(mut self) -> Self {
+        self.domain_separated_signatures = true;
+        self
+    }
+
     /// Creates the testkit.
     pub fn create(self) -> TestKit {
         if self.logger {
@@ -369,8 +455,20 @@ impl TestKitBuilder {
 
         let network =
             TestNetwork::with_our_role(self.our_validator_id, self.validator_count.unwrap_or(1));
-        let genesis = network.genesis_config();
-        TestKit::assemble(TemporaryDB::new(), self.services, network, genesis)
+        let mut genesis = network.genesis_config();
+        genesis.consensus.enforce_storage_namespaces = self.enforce_storage_namespaces;
+        genesis.consensus.parallel_execution = self.parallel_execution.is_some();
+        genesis.consensus.storage_quotas = self.storage_quotas;
+        genesis.domain_separated_signatures = self.domain_separated_signatures;
+        let mut testkit = TestKit::assemble(TemporaryDB::new(), self.services, network, genesis);
+        testkit
+            .blockchain
+            .set_write_conflicts_tracking(self.track_write_conflicts);
+        testkit.blockchain.set_state_history(self.state_history);
+        testkit
+            .blockchain
+            .set_parallel_execution(self.parallel_execution);
+        testkit
     }
 
     /// Starts a testkit web server, which listens to public and private APIs exposed by
@@ -454,8 +552,20 @@ impl TestKit {
                             schema.add_transaction_into_pool(tx.clone());
                         }
                     }
+                    ExternalMessage::RemoveTransaction(hash, result) => {
+                        let outcome = if schema.reject_transaction(&hash) {
+                            Ok(())
+                        } else {
+                            Err(RemoveTransactionError::NotFound)
+                        };
+                        let _ = result.send(outcome);
+                    }
                     ExternalMessage::PeerAdd(_)
+                    | ExternalMessage::PeerRemove(_)
+                    | ExternalMessage::UpdateConnectList(_)
                     | ExternalMessage::Enable(_)
+                    | ExternalMessage::Pause
+                    | ExternalMessage::Resume
                     | ExternalMessage::Rebroadcast
                     | ExternalMessage::Shutdown => { /* Ignored */ }
                 }
@@ -652,14 +762,15 @@ impl TestKit {
             patch
         };
 
-        let propose = self
-            .leader()
-            .create_propose(new_block_height, &last_hash, tx_hashes);
+        let network_id = self.blockchain.network_id();
+        let propose =
+            self.leader()
+                .create_propose(new_block_height, &last_hash, tx_hashes, network_id);
         let precommits: Vec<_> = self
             .network()
             .validators()
             .iter()
-            .map(|v| v.create_precommit(&propose, &block_hash))
+            .map(|v| v.create_precommit(&propose, &block_hash, network_id))
             .collect();
 
         let guard = self.processing_lock.lock().unwrap();
@@ -163,14 +163,16 @@ impl TestNode {
         }
     }
 
-    /// Creates a `Propose` message signed by this validator.
+    /// Creates a `Propose` message signed by this validator for the network with the given
+    /// identifier, see `Blockchain::network_id`.
     pub fn create_propose(
         &self,
         height: Height,
         last_hash: &crypto::Hash,
         tx_hashes: &[crypto::Hash],
+        network_id: Option<crypto::Hash>,
     ) -> Signed<Propose> {
-        Message::concrete(
+        Message::concrete_in_network(
             Propose::new(
                 self.validator_id
                     .expect("An attempt to create propose from a non-validator node."),
@@ -179,20 +181,23 @@ impl TestNode {
                 last_hash,
                 tx_hashes,
             ),
+            network_id,
             self.consensus_public_key,
             &self.consensus_secret_key,
         )
     }
 
-    /// Creates a `Precommit` message signed by this validator.
+    /// Creates a `Precommit` message signed by this validator for the network with the given
+    /// identifier, see `Blockchain::network_id`.
     pub fn create_precommit(
         &self,
         propose: &Propose,
         block_hash: &crypto::Hash,
+        network_id: Option<crypto::Hash>,
     ) -> Signed<Precommit> {
         use std::time::SystemTime;
 
-        Message::concrete(
+        Message::concrete_in_network(
             Precommit::new(
                 self.validator_id
                     .expect("An attempt to create propose from a non-validator node."),
@@ -202,6 +207,7 @@ impl TestNode {
                 block_hash,
                 SystemTime::now().into(),
             ),
+            network_id,
             self.consensus_public_key,
             &self.consensus_secret_key,
         )
@@ -18,11 +18,16 @@ extern crate pretty_assertions;
 extern crate serde_derive;
 
 use exonum::{
-    blockchain::Schema,
+    api::node::public::explorer::{ConfigProof, ConfigProofQuery},
+    blockchain::{
+        light::{LightChain, LightSchema, LightSource},
+        BlockProof, Schema,
+    },
     crypto::CryptoHash,
     helpers::{Height, ValidatorId},
 };
-use exonum_testkit::TestKitBuilder;
+use exonum_merkledb::TemporaryDB;
+use exonum_testkit::{ApiKind, TestKit, TestKitBuilder};
 
 #[test]
 fn test_following_config() {
@@ -203,3 +208,144 @@ fn test_another_configuration_change_proposal() {
     };
     testkit.commit_configuration_change(second_proposal);
 }
+
+fn sync_light_chain(light_chain: &LightChain, testkit: &TestKit) -> Result<(), failure::Error> {
+    let snapshot = testkit.snapshot();
+    let schema = Schema::new(&snapshot);
+    for height in light_chain.height().next().0..=testkit.height().0 {
+        light_chain.add_block(schema.block_and_precommits(Height(height)).unwrap())?;
+    }
+    Ok(())
+}
+
+fn config_proof(testkit: &TestKit) -> ConfigProof {
+    testkit
+        .api()
+        .public(ApiKind::Explorer)
+        .query(&ConfigProofQuery { height: None })
+        .get("v1/configs/proof")
+        .unwrap()
+}
+
+struct TestKitSource<'a>(&'a TestKit);
+
+impl LightSource for TestKitSource<'_> {
+    fn block_proof(&self, height: Height) -> Result<Option<BlockProof>, failure::Error> {
+        let snapshot = self.0.snapshot();
+        Ok(Schema::new(&snapshot).block_and_precommits(height))
+    }
+
+    fn config_proof(&self, height: Option<Height>) -> Result<ConfigProof, failure::Error> {
+        Ok(self
+            .0
+            .api()
+            .public(ApiKind::Explorer)
+            .query(&ConfigProofQuery { height })
+            .get("v1/configs/proof")?)
+    }
+}
+
+#[test]
+fn test_light_chain_follows_validators_change() {
+    let mut testkit = TestKitBuilder::validator().with_validators(2).create();
+    let (genesis_block, genesis_config) = {
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        (
+            schema.block_and_precommits(Height(0)).unwrap().block,
+            schema.configuration_by_height(Height(0)),
+        )
+    };
+    let light_chain = LightChain::new(
+        TemporaryDB::new(),
+        genesis_block.clone(),
+        genesis_config.clone(),
+    )
+    .unwrap();
+    let outdated_light_chain =
+        LightChain::new(TemporaryDB::new(), genesis_block, genesis_config).unwrap();
+
+    let cfg_change_height = Height(5);
+    let proposal = {
+        let mut cfg = testkit.configuration_change_proposal();
+        let validator = cfg.validators()[1].clone();
+        cfg.set_actual_from(cfg_change_height);
+        cfg.set_validators(vec![validator]);
+        cfg
+    };
+    testkit.commit_configuration_change(proposal);
+    testkit.create_blocks_until(Height(2));
+    sync_light_chain(&light_chain, &testkit).unwrap();
+    assert_eq!(light_chain.height(), Height(2));
+
+    assert!(light_chain
+        .add_configuration(config_proof(&testkit))
+        .unwrap());
+    assert!(!light_chain
+        .add_configuration(config_proof(&testkit))
+        .unwrap());
+
+    testkit.create_blocks_until(Height(7));
+    sync_light_chain(&light_chain, &testkit).unwrap();
+    assert_eq!(light_chain.height(), Height(7));
+
+    // The blocks signed by the new validators are rejected without the configuration.
+    assert!(sync_light_chain(&outdated_light_chain, &testkit).is_err());
+    assert_eq!(outdated_light_chain.height(), cfg_change_height.previous());
+    // The proof anchored to an unknown block is rejected.
+    assert!(outdated_light_chain
+        .add_configuration(config_proof(&testkit))
+        .is_err());
+}
+
+#[test]
+fn test_light_chain_sync() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(2)
+        .with_state_history(10)
+        .create();
+    let (genesis_block, genesis_config) = {
+        let snapshot = testkit.snapshot();
+        let schema = Schema::new(&snapshot);
+        (
+            schema.block_and_precommits(Height(0)).unwrap().block,
+            schema.configuration_by_height(Height(0)),
+        )
+    };
+    let light_chain = LightChain::new(
+        TemporaryDB::new(),
+        genesis_block.clone(),
+        genesis_config.clone(),
+    )
+    .unwrap();
+    let outdated_light_chain =
+        LightChain::new(TemporaryDB::new(), genesis_block, genesis_config).unwrap();
+
+    let cfg_change_height = Height(5);
+    let proposal = {
+        let mut cfg = testkit.configuration_change_proposal();
+        let validator = cfg.validators()[1].clone();
+        cfg.set_actual_from(cfg_change_height);
+        cfg.set_validators(vec![validator]);
+        cfg
+    };
+    testkit.commit_configuration_change(proposal);
+    testkit.create_blocks_until(Height(2));
+
+    // The configuration is accepted once the chain is synchronized with the latest block.
+    assert_eq!(light_chain.sync(&TestKitSource(&testkit)).unwrap(), 2);
+    assert_eq!(light_chain.sync(&TestKitSource(&testkit)).unwrap(), 0);
+    testkit.create_blocks_until(Height(7));
+    assert_eq!(light_chain.sync(&TestKitSource(&testkit)).unwrap(), 5);
+    assert_eq!(light_chain.height(), Height(7));
+
+    // The configuration is obtained from the state history once the block signed by
+    // the new validators is not accepted.
+    assert_eq!(
+        outdated_light_chain.sync(&TestKitSource(&testkit)).unwrap(),
+        7
+    );
+    assert_eq!(outdated_light_chain.height(), Height(7));
+    let snapshot = outdated_light_chain.snapshot();
+    assert_eq!(LightSchema::new(&snapshot).configs_actual_from().len(), 2);
+}
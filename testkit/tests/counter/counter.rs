@@ -14,10 +14,14 @@
 
 //! Sample counter service.
 
-use actix_web::{http::Method, HttpResponse};
+use actix_web::{
+    http::Method,
+    middleware::{Middleware, Started},
+    HttpResponse,
+};
 use exonum::{
     api::backends::actix::{HttpRequest, RawHandler, RequestHandler},
-    api::{self, ServiceApiBackend},
+    api::{self, ServiceApiBackend, ServiceApiState},
     blockchain::{
         ExecutionError, ExecutionResult, Service, Transaction, TransactionContext, TransactionSet,
     },
@@ -39,6 +43,10 @@ pub const SERVICE_ID: u16 = 1;
 // "correct horse battery staple" brainwallet pubkey in Ed25519 with SHA-256 digest
 pub const ADMIN_KEY: &str = "506f27b1b4c2403f2602d663a059b0262afd6a5bcda95a08dd96a4614a89f1b0";
 
+// Key checked by the private API middleware.
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+pub const API_KEY: &str = "SUPER_SECRET_222";
+
 // // // // Schema // // // //
 
 pub struct CounterSchema<T> {
@@ -215,6 +223,54 @@ impl CounterApi {
                 method: Method::GET,
                 inner: handler,
             });
+
+        // Check endpoints registered under several API versions.
+        let versions = [
+            api::ApiVersion::new("v1").deprecated(api::Deprecation {
+                sunset: None,
+                description: Some("Use `v2/counter` instead".to_owned()),
+            }),
+            api::ApiVersion::new("v2"),
+        ];
+        builder
+            .public_scope()
+            .versioned_endpoint(&versions, "counter", Self::count);
+
+        // Check middleware installed for the whole scope.
+        builder
+            .private_scope()
+            .web_backend()
+            .middleware(ApiKeyGuard);
+
+        // Check mounting of the service frontend.
+        builder
+            .public_scope()
+            .static_files(
+                "static",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/counter/static"),
+            )
+            .embedded_files(
+                "ui",
+                vec![
+                    ("index.html", &b"<!DOCTYPE html><title>Counter</title>"[..]),
+                    ("config.json", &b"{ \"service\": \"counter\" }"[..]),
+                ],
+            );
+    }
+}
+
+/// Middleware rejecting requests with an invalid API key. Requests without a key are passed.
+#[derive(Debug, Clone)]
+struct ApiKeyGuard;
+
+impl Middleware<ServiceApiState> for ApiKeyGuard {
+    fn start(&self, request: &HttpRequest) -> actix_web::Result<Started> {
+        match request.headers().get(API_KEY_HEADER) {
+            Some(key) if key.as_bytes() != API_KEY.as_bytes() => {
+                Ok(Started::Response(HttpResponse::Unauthorized().finish()))
+            }
+            _ => Ok(Started::Done),
+        }
     }
 }
 
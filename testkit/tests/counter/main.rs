@@ -14,19 +14,39 @@
 
 use assert_matches::assert_matches;
 use exonum::{
-    api::{node::public::explorer::TransactionQuery, Error as ApiError},
-    blockchain::TransactionErrorType as ErrorType,
+    api::{
+        node::{
+            private::{MempoolInfo, MempoolQuery},
+            public::explorer::{
+                TransactionQuery, TransactionValidateQuery, TransactionValidateResponse,
+            },
+        },
+        Error as ApiError,
+    },
+    blockchain::{
+        archive::{export_blocks, import_blocks},
+        snapshot::{
+            export_json, export_snapshot, import_snapshot, JsonManifest, JSON_MANIFEST_FILE,
+        },
+        Blockchain, GenesisConfig, PeerStats, Schema, Service, StorageQuotaConfig,
+        TransactionErrorType as ErrorType,
+    },
     crypto::{self, CryptoHash, PublicKey},
     helpers::Height,
     messages::{self, RawTransaction, Signed},
+    node::ApiSender,
 };
-use exonum_merkledb::HashTag;
+use exonum_merkledb::{BinaryValue, HashTag, ObjectHash, Snapshot, TemporaryDB};
 use exonum_testkit::{txvec, ApiKind, ComparableSnapshot, TestKit, TestKitApi, TestKitBuilder};
+use futures::sync::mpsc;
 use hex::FromHex;
 use serde_json::{json, Value};
 
+use std::{fs, sync::Arc};
+
 use crate::counter::{
-    CounterSchema, CounterService, TransactionResponse, TxIncrement, TxReset, ADMIN_KEY, SERVICE_ID,
+    CounterSchema, CounterService, TransactionResponse, TxIncrement, TxReset, ADMIN_KEY, API_KEY,
+    API_KEY_HEADER, SERVICE_ID,
 };
 
 mod counter;
@@ -188,6 +208,179 @@ fn test_private_api() {
     assert_eq!(counter, 0);
 }
 
+#[test]
+fn test_private_api_middleware() {
+    let (mut testkit, api) = init_testkit();
+    inc_count(&api, 5);
+    testkit.create_block();
+
+    let error = api
+        .private(ApiKind::Service("counter"))
+        .with(|req| req.header(API_KEY_HEADER, "foobar"))
+        .get::<u64>("count")
+        .unwrap_err();
+    assert_matches!(error, ApiError::Unauthorized);
+
+    let counter: u64 = api
+        .private(ApiKind::Service("counter"))
+        .with(|req| req.header(API_KEY_HEADER, API_KEY))
+        .get("count")
+        .unwrap();
+    assert_eq!(counter, 5);
+
+    // Public API is not affected by the middleware.
+    let counter: u64 = api
+        .public(ApiKind::Service("counter"))
+        .with(|req| req.header(API_KEY_HEADER, "foobar"))
+        .get("count")
+        .unwrap();
+    assert_eq!(counter, 5);
+}
+
+#[test]
+fn test_frontend_files() {
+    let (_testkit, api) = init_testkit();
+
+    let info: Value = api
+        .public(ApiKind::Service("counter"))
+        .get("static/info.json")
+        .unwrap();
+    assert_eq!(info, json!({ "name": "counter" }));
+
+    let config: Value = api
+        .public(ApiKind::Service("counter"))
+        .get("ui/config.json")
+        .unwrap();
+    assert_eq!(config, json!({ "service": "counter" }));
+
+    let error = api
+        .public(ApiKind::Service("counter"))
+        .get::<Value>("ui/missing.json")
+        .unwrap_err();
+    assert_matches!(error, ApiError::NotFound(_));
+}
+
+#[test]
+fn test_validate_transaction() {
+    let (mut testkit, api) = init_testkit();
+    let (pubkey, key) = crypto::gen_keypair();
+    let validate = |tx: &Signed<RawTransaction>, execute: bool| {
+        let query = TransactionValidateQuery {
+            tx_body: hex::encode(tx.clone().serialize()),
+            execute,
+        };
+        api.public(ApiKind::Explorer)
+            .query(&query)
+            .post::<TransactionValidateResponse>("v1/transactions/validate")
+            .unwrap()
+    };
+
+    let tx = TxIncrement::sign(&pubkey, 5, &key);
+    let response = validate(&tx, false);
+    assert_eq!(response.tx_hash, tx.hash());
+    assert_eq!(response.service_id, SERVICE_ID);
+    assert_eq!(response.author, pubkey);
+    assert!(!response.is_known);
+    assert!(response.execution.is_none());
+
+    let response = validate(&tx, true);
+    assert_eq!(response.execution.unwrap().status.0, Ok(()));
+
+    // Validated transactions are not added to the pool.
+    let block = testkit.create_block();
+    assert!(block.is_empty());
+    let schema = CounterSchema::new(testkit.snapshot());
+    assert_eq!(schema.count(), None);
+
+    let tx = TxIncrement::sign(&pubkey, 0, &key);
+    let status = validate(&tx, true).execution.unwrap().status;
+    let err = status.0.unwrap_err();
+    assert_eq!(err.error_type(), ErrorType::Code(0));
+    assert_eq!(err.description(), Some("Adding zero does nothing!"));
+
+    testkit.create_block_with_transaction(tx.clone());
+    assert!(validate(&tx, false).is_known);
+}
+
+#[test]
+fn test_domain_separated_signatures() {
+    use exonum::api::node::public::explorer::TransactionHex;
+    use exonum::messages::{Message, SignedMessage};
+
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(CounterService)
+        .with_domain_separated_signatures()
+        .create();
+    let api = testkit.api();
+    let network_id = testkit.blockchain().network_id();
+    assert_eq!(
+        network_id,
+        Some(Schema::new(&testkit.snapshot()).network_id())
+    );
+
+    let (pubkey, key) = crypto::gen_keypair();
+    let send = |tx: &Signed<RawTransaction>| {
+        let query = TransactionHex {
+            tx_body: hex::encode(tx.clone().serialize()),
+        };
+        api.public(ApiKind::Explorer)
+            .query(&query)
+            .post::<TransactionResponse>("v1/transactions")
+    };
+
+    // Transactions signed without the network identifier are rejected.
+    let tx = TxIncrement::sign(&pubkey, 5, &key);
+    assert!(send(&tx).is_err());
+
+    let tx = Message::sign_transaction_in_network(
+        TxIncrement::new(5),
+        SERVICE_ID,
+        network_id,
+        pubkey,
+        &key,
+    );
+    assert_eq!(send(&tx).unwrap().tx_hash, tx.hash());
+    let block = testkit.create_block();
+    assert_eq!(block.len(), 1);
+    let snapshot = testkit.snapshot();
+    assert_eq!(CounterSchema::new(&snapshot).count(), Some(5));
+
+    // The precommits of the block are signed for the network as well.
+    let precommits = Schema::new(&snapshot)
+        .block_and_precommits(testkit.height())
+        .unwrap()
+        .precommits;
+    assert!(!precommits.is_empty());
+    for precommit in precommits {
+        let raw = precommit.signed_message().raw().to_vec();
+        assert!(SignedMessage::from_raw_buffer(raw.clone()).is_err());
+        assert!(SignedMessage::from_raw_buffer_in_network(raw, network_id).is_ok());
+    }
+}
+
+#[test]
+fn test_versioned_endpoints() {
+    let (mut testkit, api) = init_testkit();
+    testkit.create_block_with_transaction({
+        let (pubkey, key) = crypto::gen_keypair();
+        TxIncrement::sign(&pubkey, 5, &key)
+    });
+
+    for endpoint in &["v1/counter", "v2/counter"] {
+        let counter: u64 = api
+            .public(ApiKind::Service("counter"))
+            .get(endpoint)
+            .unwrap();
+        assert_eq!(counter, 5);
+    }
+
+    let error = api
+        .public(ApiKind::Service("counter"))
+        .get::<u64>("v3/counter")
+        .unwrap_err();
+    assert_matches!(error, ApiError::NotFound(_));
+}
+
 #[test]
 fn test_probe() {
     let (mut testkit, api) = init_testkit();
@@ -922,3 +1115,369 @@ fn test_custom_headers_handling() {
         .unwrap();
     assert_eq!(counter, 5);
 }
+
+#[test]
+fn test_snapshot_export_import() {
+    let (mut testkit, api) = init_testkit();
+    inc_count(&api, 5);
+    testkit.create_block();
+    inc_count(&api, 3);
+    testkit.create_block();
+    testkit.create_blocks_until(Height(5));
+    // Peer statistics are local to the node, so they are not exported.
+    let fork = testkit.blockchain_mut().fork();
+    Schema::new(&fork)
+        .peer_stats()
+        .put(&crypto::gen_keypair().0, PeerStats::default());
+    testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+
+    let mut snapshot = Vec::new();
+    let header = export_snapshot(&*testkit.snapshot(), Height(5), &mut snapshot).unwrap();
+    assert_eq!(header.height, Height(5));
+    assert_eq!(header.block_hash, testkit.last_block_hash());
+    // Only the latest state can be exported.
+    assert!(export_snapshot(&*testkit.snapshot(), Height(3), &mut Vec::new()).is_err());
+
+    // The snapshot is rejected by the blockchain with another genesis block.
+    let mut other_testkit = TestKit::for_service(CounterService);
+    assert!(import_snapshot(other_testkit.blockchain_mut(), &mut &snapshot[..]).is_err());
+
+    let config = testkit.actual_configuration();
+    let genesis =
+        GenesisConfig::new_with_consensus(config.consensus, config.validator_keys.into_iter());
+    let (public_key, secret_key) = crypto::gen_keypair();
+    let mut blockchain = Blockchain::new(
+        TemporaryDB::new(),
+        vec![Box::new(CounterService) as Box<dyn Service>],
+        public_key,
+        secret_key,
+        ApiSender::new(mpsc::channel(1).0),
+    );
+    blockchain.initialize(genesis).unwrap();
+
+    let imported = import_snapshot(&mut blockchain, &mut &snapshot[..]).unwrap();
+    assert_eq!(imported, header);
+    assert_eq!(blockchain.last_hash(), testkit.last_block_hash());
+    let snapshot = blockchain.snapshot();
+    assert_eq!(CounterSchema::new(&snapshot).count(), Some(8));
+    assert_eq!(Schema::new(&snapshot).peer_stats().iter().count(), 0);
+
+    // The state can be imported only once.
+    let mut snapshot = Vec::new();
+    export_snapshot(&*testkit.snapshot(), Height(5), &mut snapshot).unwrap();
+    assert!(import_snapshot(&mut blockchain, &mut &snapshot[..]).is_err());
+}
+
+#[test]
+fn test_block_archive_export_import() {
+    let (mut testkit, api) = init_testkit();
+    inc_count(&api, 5);
+    testkit.create_block();
+    inc_count(&api, 3);
+    testkit.create_block();
+    testkit.create_blocks_until(Height(5));
+
+    let mut archive = Vec::new();
+    let info = export_blocks(&*testkit.snapshot(), Height(0), Height(5), &mut archive).unwrap();
+    assert_eq!((info.from, info.to), (Height(0), Height(5)));
+    // Blocks above the latest height can't be exported.
+    assert!(export_blocks(&*testkit.snapshot(), Height(3), Height(6), &mut Vec::new()).is_err());
+
+    // The archive is rejected by the blockchain with another genesis block.
+    let mut other_testkit = TestKit::for_service(CounterService);
+    assert!(import_blocks(other_testkit.blockchain_mut(), &mut &archive[..]).is_err());
+
+    let config = testkit.actual_configuration();
+    let genesis =
+        GenesisConfig::new_with_consensus(config.consensus, config.validator_keys.into_iter());
+    let (public_key, secret_key) = crypto::gen_keypair();
+    let mut blockchain = Blockchain::new(
+        TemporaryDB::new(),
+        vec![Box::new(CounterService) as Box<dyn Service>],
+        public_key,
+        secret_key,
+        ApiSender::new(mpsc::channel(1).0),
+    );
+    blockchain.initialize(genesis).unwrap();
+
+    // The tail of the chain can't be imported without the earlier blocks.
+    let mut tail = Vec::new();
+    export_blocks(&*testkit.snapshot(), Height(3), Height(5), &mut tail).unwrap();
+    assert!(import_blocks(&mut blockchain, &mut &tail[..]).is_err());
+
+    assert_eq!(
+        import_blocks(&mut blockchain, &mut &archive[..]).unwrap(),
+        5
+    );
+    assert_eq!(blockchain.last_hash(), testkit.last_block_hash());
+    let snapshot = blockchain.snapshot();
+    assert_eq!(CounterSchema::new(&snapshot).count(), Some(8));
+    assert_eq!(Schema::new(&snapshot).transactions_len(), 2);
+
+    // The already committed blocks are skipped.
+    assert_eq!(
+        import_blocks(&mut blockchain, &mut &archive[..]).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn test_state_export_to_json() {
+    let (mut testkit, api) = init_testkit();
+    inc_count(&api, 5);
+    testkit.create_block();
+    let fork = testkit.blockchain_mut().fork();
+    Schema::new(&fork)
+        .peer_stats()
+        .put(&crypto::gen_keypair().0, PeerStats::default());
+    testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let snapshot = testkit.snapshot();
+    let manifest = export_json(&*snapshot, Height(1), &["counter"], dir.path()).unwrap();
+    assert_eq!(manifest.height, Height(1));
+    assert_eq!(manifest.block_hash, testkit.last_block_hash());
+    assert_eq!(manifest.indexes.len(), 1);
+    let index = &manifest.indexes[0];
+    assert_eq!(index.name, "counter.count");
+    assert_eq!(index.file, "counter.count.json");
+    assert_eq!(index.entries, 1);
+
+    let contents = fs::read(dir.path().join(&index.file)).unwrap();
+    assert_eq!(crypto::hash(&contents), index.file_hash);
+    let entries: Vec<(String, String)> = serde_json::from_slice(&contents).unwrap();
+    assert_eq!(
+        entries,
+        vec![(String::new(), hex::encode(5_u64.to_bytes()))]
+    );
+    let stored: JsonManifest =
+        serde_json::from_slice(&fs::read(dir.path().join(JSON_MANIFEST_FILE)).unwrap()).unwrap();
+    assert_eq!(stored, manifest);
+
+    // Exports of the same state are identical.
+    let other_dir = tempfile::TempDir::new().unwrap();
+    let other_manifest =
+        export_json(&*snapshot, Height(1), &["counter"], other_dir.path()).unwrap();
+    assert_eq!(other_manifest, manifest);
+
+    let manifest = export_json(&*snapshot, Height(1), &["core"], dir.path()).unwrap();
+    assert!(manifest
+        .indexes
+        .iter()
+        .any(|index| index.name == "core.blocks"));
+    assert!(manifest
+        .indexes
+        .iter()
+        .all(|index| index.name != "core.peer_stats"));
+    assert!(manifest
+        .indexes
+        .iter()
+        .all(|index| index.name.starts_with("core.")));
+    // Only the latest state can be exported.
+    assert!(export_json(&*snapshot, Height(0), &[], dir.path()).is_err());
+}
+
+#[test]
+fn test_integrity_check() {
+    let (mut testkit, api) = init_testkit();
+    inc_count(&api, 5);
+    testkit.create_block();
+    testkit.create_blocks_until(Height(5));
+    testkit.blockchain().check_integrity(10).unwrap();
+
+    // Break the link between the first two blocks.
+    let fork = testkit.blockchain().fork();
+    Schema::new(&fork)
+        .block_hashes_by_height()
+        .set(1, crypto::Hash::zero());
+    testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+    // Only the latest blocks are verified.
+    testkit.blockchain().check_integrity(3).unwrap();
+    let err = testkit.blockchain().check_integrity(10).unwrap_err();
+    assert!(err.to_string().contains("Block at height 1 is missing"));
+
+    // Change the state without committing a block.
+    let (mut testkit, _) = init_testkit();
+    testkit.create_block();
+    let fork = testkit.blockchain().fork();
+    Schema::new(&fork)
+        .state_hash_aggregator()
+        .put(&crypto::hash(b"table"), crypto::hash(b"root"));
+    testkit.blockchain_mut().merge(fork.into_patch()).unwrap();
+    let err = testkit.blockchain().check_integrity(1).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "The state hash does not match the latest block"
+    );
+}
+
+#[test]
+fn test_storage_quota() {
+    fn create_testkit(hard_limit: u64) -> TestKit {
+        let quota = StorageQuotaConfig {
+            soft_limit: Some(8),
+            hard_limit: Some(hard_limit),
+        };
+        TestKitBuilder::validator()
+            .with_service(CounterService)
+            .with_storage_quota("counter", quota)
+            .create()
+    }
+
+    // The counter entry takes 8 bytes of the index identifier and 8 bytes of the value.
+    let mut testkit = create_testkit(12);
+    let (pubkey, key) = crypto::gen_keypair();
+    let block = testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 5, &key));
+    let err = block[0].status().unwrap_err();
+    assert_eq!(err.error_type(), ErrorType::QuotaExceeded);
+    assert_eq!(CounterSchema::new(testkit.snapshot()).count(), None);
+
+    // Exceeding the soft quota does not affect transactions.
+    let mut testkit = create_testkit(16);
+    let block = testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 5, &key));
+    assert!(block[0].status().is_ok());
+    // Transactions which do not grow the namespace are not limited.
+    let block = testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 3, &key));
+    assert!(block[0].status().is_ok());
+    assert_eq!(CounterSchema::new(testkit.snapshot()).count(), Some(8));
+}
+
+#[test]
+fn test_speculative_execution() {
+    let (mut testkit, _) = init_testkit();
+    testkit.create_block();
+    let blockchain = testkit.blockchain();
+    let (pubkey, key) = crypto::gen_keypair();
+
+    let snapshot: Arc<dyn Snapshot> = Arc::from(blockchain.snapshot());
+    let (result, fork) = blockchain
+        .speculate(&snapshot, &TxIncrement::sign(&pubkey, 5, &key))
+        .unwrap();
+    assert!(result.0.is_ok());
+    assert_eq!(CounterSchema::new(&fork).count(), Some(5));
+
+    // The following transaction is executed on top of the previous one.
+    let (result, fork) = blockchain
+        .speculate(&fork.into_snapshot(), &TxIncrement::sign(&pubkey, 3, &key))
+        .unwrap();
+    assert!(result.0.is_ok());
+    assert_eq!(CounterSchema::new(&fork).count(), Some(8));
+
+    // Failed transactions do not change the state.
+    let (result, fork) = blockchain
+        .speculate(&snapshot, &TxIncrement::sign(&pubkey, 0, &key))
+        .unwrap();
+    assert!(result.0.is_err());
+    assert_eq!(CounterSchema::new(&fork).count(), None);
+    // Speculations do not affect the blockchain.
+    assert_eq!(CounterSchema::new(testkit.snapshot()).count(), None);
+}
+
+#[test]
+fn test_state_history() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(CounterService)
+        .with_state_history(2)
+        .create();
+    let (pubkey, key) = crypto::gen_keypair();
+    testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 5, &key));
+    testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 3, &key));
+    testkit.create_block();
+
+    let blockchain = testkit.blockchain();
+    let snapshot = blockchain.snapshot_at(Height(1)).unwrap();
+    assert_eq!(CounterSchema::new(&snapshot).count(), Some(5));
+    let schema = Schema::new(&snapshot);
+    assert_eq!(schema.height(), Height(1));
+    let block = schema.last_block();
+    assert_eq!(
+        Some(block.hash()),
+        Schema::new(testkit.snapshot()).block_hash_by_height(Height(1))
+    );
+    assert_eq!(
+        schema.state_hash_aggregator().object_hash(),
+        *block.state_hash()
+    );
+
+    let snapshot = blockchain.snapshot_at(Height(2)).unwrap();
+    assert_eq!(CounterSchema::new(&snapshot).count(), Some(8));
+    let snapshot = blockchain.snapshot_at(Height(3)).unwrap();
+    assert_eq!(CounterSchema::new(&snapshot).count(), Some(8));
+    // The state is kept only for the 2 heights preceding the latest one.
+    assert!(blockchain.snapshot_at(Height(0)).is_err());
+    assert!(blockchain.snapshot_at(Height(4)).is_err());
+
+    // Only the latest state is available without the state history.
+    let mut testkit = TestKitBuilder::validator()
+        .with_service(CounterService)
+        .create();
+    testkit.create_block_with_transaction(TxIncrement::sign(&pubkey, 5, &key));
+    assert!(testkit.blockchain().snapshot_at(Height(1)).is_ok());
+    assert!(testkit.blockchain().snapshot_at(Height(0)).is_err());
+}
+
+#[test]
+fn test_mempool() {
+    let mut testkit = TestKit::for_service(CounterService);
+    let (pubkey, key) = crypto::gen_keypair();
+    let (other_pubkey, other_key) = crypto::gen_keypair();
+    let mut txs = vec![
+        TxIncrement::sign(&pubkey, 1, &key),
+        TxReset::sign(&pubkey, &key),
+        TxIncrement::sign(&other_pubkey, 2, &other_key),
+    ];
+    for tx in &txs {
+        testkit.add_tx(tx.clone());
+    }
+    txs.sort_by_key(Signed::hash);
+    let api = testkit.api();
+
+    let info: MempoolInfo = api.private(ApiKind::System).get("v1/mempool").unwrap();
+    assert_eq!(info.total, 3);
+    let hashes: Vec<_> = info.transactions.iter().map(|tx| tx.tx_hash).collect();
+    assert_eq!(hashes, txs.iter().map(Signed::hash).collect::<Vec<_>>());
+    for (tx_info, tx) in info.transactions.iter().zip(&txs) {
+        assert_eq!(tx_info.service_id, SERVICE_ID);
+        assert_eq!(tx_info.author, tx.author());
+        assert_eq!(tx_info.size, tx.signed_message().raw().len());
+        // Transactions are added directly into the persistent pool by the testkit.
+        assert_eq!(tx_info.arrival_time, None);
+    }
+
+    let query = MempoolQuery {
+        author: Some(other_pubkey),
+        ..MempoolQuery::default()
+    };
+    let filtered: MempoolInfo = api
+        .private(ApiKind::System)
+        .query(&query)
+        .get("v1/mempool")
+        .unwrap();
+    assert_eq!(filtered.total, 1);
+    assert_eq!(filtered.transactions[0].author, other_pubkey);
+
+    let query = MempoolQuery {
+        service_id: Some(SERVICE_ID + 1),
+        ..MempoolQuery::default()
+    };
+    let filtered: MempoolInfo = api
+        .private(ApiKind::System)
+        .query(&query)
+        .get("v1/mempool")
+        .unwrap();
+    assert_eq!(filtered.total, 0);
+    assert!(filtered.transactions.is_empty());
+
+    let query = MempoolQuery {
+        offset: 1,
+        count: Some(1),
+        ..MempoolQuery::default()
+    };
+    let page: MempoolInfo = api
+        .private(ApiKind::System)
+        .query(&query)
+        .get("v1/mempool")
+        .unwrap();
+    assert_eq!(page.total, 3);
+    assert_eq!(page.transactions, vec![info.transactions[1].clone()]);
+}
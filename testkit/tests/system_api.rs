@@ -15,14 +15,26 @@
 #[macro_use]
 extern crate pretty_assertions;
 
+use assert_matches::assert_matches;
 use exonum::{
-    api::node::{
-        private::NodeInfo,
-        public::system::{ConsensusStatus, HealthCheckInfo, StatsInfo},
+    api::{
+        self,
+        node::{
+            private::{
+                BanQuery, DbInfo, MempoolInfo, MempoolQuery, NodeInfo, PeerStatsInfo,
+                MAX_MEMPOOL_TXS_PER_REQUEST,
+            },
+            public::system::{
+                ApiStatsInfo, ConsensusStatus, HealthCheckInfo, ReadinessInfo, ReadinessQuery,
+                StatsInfo,
+            },
+        },
     },
+    events::BanInfo,
     helpers::user_agent,
     messages::PROTOCOL_MAJOR_VERSION,
 };
+use exonum_merkledb::StorageMetricsInfo;
 use exonum_testkit::{ApiKind, TestKitBuilder};
 
 #[test]
@@ -43,6 +55,54 @@ fn healthcheck() {
     assert_eq!(info, expected);
 }
 
+#[test]
+fn liveness() {
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    assert_eq!(
+        api.public(ApiKind::System)
+            .get::<()>("v1/health/live")
+            .unwrap(),
+        ()
+    );
+}
+
+#[test]
+fn readiness() {
+    // The node is not ready, since the consensus is not active in the testkit.
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    let err = api
+        .public(ApiKind::System)
+        .get::<ReadinessInfo>("v1/health/ready")
+        .unwrap_err();
+    let info: ReadinessInfo = match err {
+        api::Error::InternalError(e) => serde_json::from_str(&e.to_string()).unwrap(),
+        e => panic!("Unexpected error: {:?}", e),
+    };
+    let expected = ReadinessInfo {
+        ready: false,
+        height_lag: 0,
+        consensus_status: ConsensusStatus::Enabled,
+        connected_peers: 0,
+        db_writable: true,
+    };
+    assert_eq!(info, expected);
+
+    let query = ReadinessQuery {
+        max_height_lag: Some(0),
+        min_peers: 1,
+    };
+    let err = api
+        .public(ApiKind::System)
+        .query(&query)
+        .get::<ReadinessInfo>("v1/health/ready")
+        .unwrap_err();
+    assert_matches!(err, api::Error::InternalError(_));
+}
+
 #[test]
 fn stats() {
     let testkit = TestKitBuilder::validator().with_validators(2).create();
@@ -51,12 +111,55 @@ fn stats() {
     let info: StatsInfo = api.public(ApiKind::System).get("v1/stats").unwrap();
     let expected = StatsInfo {
         tx_pool_size: 0,
+        tx_pool_bytes: 0,
         tx_count: 0,
         tx_cache_size: 0,
+        gc: None,
+        evictions: None,
+        verification: Default::default(),
     };
     assert_eq!(info, expected);
 }
 
+#[test]
+fn api_stats() {
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    for _ in 0..2 {
+        api.public(ApiKind::System)
+            .get::<StatsInfo>("v1/stats")
+            .unwrap();
+    }
+    let query = MempoolQuery {
+        count: Some(MAX_MEMPOOL_TXS_PER_REQUEST + 1),
+        ..MempoolQuery::default()
+    };
+    api.private(ApiKind::System)
+        .query(&query)
+        .get::<MempoolInfo>("v1/mempool")
+        .unwrap_err();
+
+    let info: ApiStatsInfo = api.public(ApiKind::System).get("v1/stats/api").unwrap();
+    let stats = info
+        .endpoints
+        .iter()
+        .find(|stats| stats.access == "public" && stats.endpoint == "system/v1/stats")
+        .expect("No stats for `v1/stats` endpoint");
+    assert_eq!(stats.method, "GET");
+    assert_eq!(stats.requests, 2);
+    assert_eq!(stats.errors, 0);
+    assert!(stats.latency.p50 <= stats.latency.max);
+
+    let stats = info
+        .endpoints
+        .iter()
+        .find(|stats| stats.access == "private" && stats.endpoint == "system/v1/mempool")
+        .expect("No stats for `v1/mempool` endpoint");
+    assert_eq!(stats.requests, 1);
+    assert_eq!(stats.errors, 1);
+}
+
 #[test]
 fn user_agent_info() {
     let testkit = TestKitBuilder::validator().with_validators(2).create();
@@ -103,3 +206,104 @@ fn rebroadcast() {
         ()
     )
 }
+
+#[test]
+fn mempool() {
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    let info: MempoolInfo = api.private(ApiKind::System).get("v1/mempool").unwrap();
+    let expected = MempoolInfo {
+        total: 0,
+        transactions: vec![],
+    };
+    assert_eq!(info, expected);
+}
+
+#[test]
+fn mempool_count_limit() {
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    let query = MempoolQuery {
+        count: Some(MAX_MEMPOOL_TXS_PER_REQUEST + 1),
+        ..MempoolQuery::default()
+    };
+    let err = api
+        .private(ApiKind::System)
+        .query(&query)
+        .get::<MempoolInfo>("v1/mempool")
+        .unwrap_err();
+    assert_matches!(err, api::Error::BadRequest(_));
+}
+
+#[test]
+fn peer_bans() {
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    let bans: Vec<BanInfo> = api.private(ApiKind::System).get("v1/peers/bans").unwrap();
+    assert!(bans.is_empty());
+
+    let query = BanQuery {
+        address: "10.0.0.1".parse().unwrap(),
+        duration: None,
+    };
+    api.private(ApiKind::System)
+        .query(&query)
+        .post::<()>("v1/peers/bans")
+        .unwrap();
+
+    let bans: Vec<BanInfo> = api.private(ApiKind::System).get("v1/peers/bans").unwrap();
+    assert_eq!(bans.len(), 1);
+    assert_eq!(bans[0].address, query.address);
+    assert_eq!(bans[0].until, None);
+    assert!(bans[0].manual);
+}
+
+#[test]
+fn peer_stats() {
+    let testkit = TestKitBuilder::validator().with_validators(2).create();
+    let api = testkit.api();
+
+    // Peer statistics are not recorded by the testkit.
+    let stats: Vec<PeerStatsInfo> = api.private(ApiKind::System).get("v1/peers/stats").unwrap();
+    assert!(stats.is_empty());
+}
+
+#[test]
+fn storage_stats() {
+    let mut testkit = TestKitBuilder::validator().with_validators(2).create();
+    testkit.create_block();
+    let api = testkit.api();
+
+    let info: StorageMetricsInfo = api.public(ApiKind::System).get("v1/stats/storage").unwrap();
+    assert!(info.merges > 0);
+    assert!(info.written_entries > 0);
+    assert!(info.max_merge_bytes > 0);
+}
+
+#[test]
+fn db_info() {
+    let mut testkit = TestKitBuilder::validator().with_validators(2).create();
+    testkit.create_block();
+    let api = testkit.api();
+
+    let info: DbInfo = api.private(ApiKind::System).get("v1/db-info").unwrap();
+    assert!(info.metrics.merges > 0);
+    assert!(info.indexes.is_empty());
+
+    let info: DbInfo = api
+        .private(ApiKind::System)
+        .get("v1/db-info?indexes=true")
+        .unwrap();
+    let blocks = info
+        .indexes
+        .iter()
+        .find(|index| index.name == "core.blocks")
+        .expect("No statistics for the blocks index");
+    assert_eq!(blocks.indexes, 1);
+    assert_eq!(blocks.entries, 2);
+    assert!(blocks.size > 0);
+    assert!(info.peer_stats.is_empty());
+}